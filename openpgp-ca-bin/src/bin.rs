@@ -4,12 +4,34 @@
 // This file is part of OpenPGP CA
 // https://gitlab.com/openpgp-ca/openpgp-ca
 
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use clap::{CommandFactory, FromArgMatches};
+use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyModifiers};
 use lazy_static::lazy_static;
+use openpgp_ca_lib::cert_info::{self, CertInfo};
+use openpgp_ca_lib::db::models;
+use openpgp_ca_lib::events::{EventsConfig, DEFAULT_TOPIC_PREFIX};
+use openpgp_ca_lib::i18n::{tr, Language, Msg};
+use openpgp_ca_lib::pgp::{KeyVersion, PasswordPolicy};
+use openpgp_ca_lib::split_format::QueueEntry;
+use openpgp_ca_lib::types::{
+    BlocklistKind, BridgeScope, CaConfigKey, CaInfo, CaRekeyParams, CaRekeyReport, CertBlobOutcome,
+    CertFormat, CertificationValidity, CryptoPolicy, ExportChannel, ExportCompat,
+    ExportCompression, FingerprintFormat, KeyPolicy, KeyProfile, KeylistConfig, KeylistFilter,
+    KeyserverConfig, NewUserKey, NotationPolicy, OffboardRevocation, Progress, Retention,
+    RetentionPolicy, RevocationPublication, SmoketestStatus, SyncOptions, SyncReport, SyncSource,
+    UriPolicy, UserDossier, WotGraphFormat,
+};
 use openpgp_ca_lib::{pgp, Oca, Uninit};
 
 mod cli;
+mod wizard;
 
 lazy_static! {
     static ref VER: String = format!(
@@ -59,6 +81,11 @@ fn main() -> Result<()> {
     let c = cli::Cli::from_arg_matches(&cli.get_matches())?;
     let db = c.database.as_deref();
 
+    init_logging(c.quiet, c.verbose);
+
+    // Before a CA is opened, only the environment can choose the language
+    let lang = Language::from_env()?.unwrap_or_default();
+
     // Handle init calls separately, here.
     // Setting up an OpenpgpCa instance differs from most other workflows.
     if let cli::Commands::Ca {
@@ -68,15 +95,39 @@ fn main() -> Result<()> {
                 name,
                 backend,
                 cipher_suite,
+                key_version,
+                expire_in,
+                interactive,
             },
     } = &c.cmd
     {
+        if *interactive {
+            if backend.is_some() {
+                return Err(anyhow::anyhow!(
+                    "The backend is chosen during interactive setup, don't pass a backend subcommand"
+                ));
+            }
+
+            return wizard::init(lang, db);
+        }
+
+        // NOTE: unwrap is ok because clap requires "domain" without "interactive"
+        let domain = domain.as_deref().unwrap();
+
+        let backend = backend.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("Please choose a backend (softkey or card), or use --interactive")
+        })?;
+
         let cau = Uninit::new(db)?;
 
         let ca = match backend {
-            cli::Backend::Softkey => {
-                cau.init_softkey(domain, name.as_deref(), cipher_suite.clone())
-            }
+            cli::Backend::Softkey => cau.init_softkey_with_validity(
+                domain,
+                name.as_deref(),
+                cipher_suite.clone(),
+                *key_version,
+                *expire_in,
+            ),
             cli::Backend::Card {
                 ident,
                 pinpad,
@@ -94,22 +145,37 @@ fn main() -> Result<()> {
                     unimplemented!("pinpad mode is not implemented yet");
                 }
 
+                if expire_in.is_some() && (*from_card || import.is_some() || *generate_on_card) {
+                    return Err(anyhow::anyhow!(
+                        "--expire-in is only supported when the CA key is generated on the host"
+                    ));
+                }
+                if *key_version != KeyVersion::default()
+                    && (*from_card || import.is_some() || *generate_on_card)
+                {
+                    return Err(anyhow::anyhow!(
+                        "--key-version is only supported when the CA key is generated on the host"
+                    ));
+                }
+
                 match (from_card, import, generate_on_card) {
                     (false, None, false) => {
                         // Generate key in CA, import to card, print private key
                         let ident = find_one_empty_card(ident)?;
 
-                        println!("Initializing OpenPGP CA on card {ident}.");
+                        println!("{}", tr(lang, Msg::CardInit, &[("ident", &ident)]));
                         println!();
 
-                        let (ca, key) = cau.init_card_generate_on_host(
+                        let (ca, key) = cau.init_card_generate_on_host_with_validity(
                             &ident,
                             domain,
                             name.as_deref(),
                             cipher_suite.clone(),
+                            *key_version,
+                            *expire_in,
                         )?;
 
-                        println!("Generated new CA key:\n\n{key}");
+                        println!("{}", tr(lang, Msg::CardGeneratedKey, &[("key", &key)]));
 
                         Ok(ca)
                     }
@@ -121,14 +187,14 @@ fn main() -> Result<()> {
                         let ca_cert = std::fs::read(public_key.as_ref().unwrap())?;
                         let ident = find_one_matching_card(ident, &ca_cert)?;
 
-                        println!(
-                            "Initializing OpenPGP CA from pre-configured OpenPGP card {ident}."
-                        );
+                        println!("{}", tr(lang, Msg::CardInitFromCard, &[("ident", &ident)]));
                         println!();
 
                         // This card is already initialized, ask for User PIN
-                        let pin = rpassword::prompt_password(format!(
-                            "Enter User PIN for OpenPGP card {ident}: "
+                        let pin = rpassword::prompt_password(tr(
+                            lang,
+                            Msg::CardUserPinPrompt,
+                            &[("ident", &ident)],
                         ))?;
                         println!();
 
@@ -138,7 +204,7 @@ fn main() -> Result<()> {
                         // Initialize CA onto a blank card, from private CA key file
                         let ident = find_one_empty_card(ident)?;
 
-                        println!("Initializing OpenPGP CA from existing key, on card {ident}.");
+                        println!("{}", tr(lang, Msg::CardInitImport, &[("ident", &ident)]));
                         println!();
 
                         let ca_cert = std::fs::read(import)?;
@@ -148,15 +214,16 @@ fn main() -> Result<()> {
                         // Generate key on card, make public key (and store it in DB)
                         let ident = find_one_empty_card(ident)?;
 
-                        println!("Generate new OpenPGP CA key on card {ident}.");
+                        println!(
+                            "{}",
+                            tr(lang, Msg::CardGenerateOnCard, &[("ident", &ident)])
+                        );
                         println!();
-                        println!("Note:");
-                        println!("1) The private CA key will only exist on the card (you can't make a backup)");
-                        println!("2) The randomness your OpenPGP card generates could be worse than your host computer's");
+                        println!("{}", tr(lang, Msg::CardOnCardNote, &[]));
                         println!();
 
                         let mut line = String::new();
-                        println!("Are you sure? (type 'yes' to continue)");
+                        println!("{}", tr(lang, Msg::Confirm, &[]));
                         std::io::stdin().read_line(&mut line)?;
                         println!();
 
@@ -174,8 +241,8 @@ fn main() -> Result<()> {
             }
         }?;
 
-        println!("Initialized OpenPGP CA instance:\n");
-        ca.ca_show()?;
+        println!("{}\n", tr(lang, Msg::Initialized, &[]));
+        print_ca_info(lang, &ca.ca_info()?);
 
         return Ok(());
     }
@@ -190,18 +257,11 @@ fn main() -> Result<()> {
             cli::MigrateCommand::Card { ident, pinpad: _ } => {
                 let ident = find_one_empty_card(ident)?;
 
-                println!("Migrating OpenPGP CA instance to card {ident}.");
-                println!();
-                println!(
-                    "Caution: After migration is performed, the CA private key material will not"
-                );
-                println!("be available in the CA database anymore!");
-                println!();
-                println!("Make sure you have a backup of your CA key before continuing!");
+                println!("{}", tr(lang, Msg::CardMigrate, &[("ident", &ident)]));
                 println!();
 
                 let mut line = String::new();
-                println!("Are you sure? (type 'yes' to continue)");
+                println!("{}", tr(lang, Msg::Confirm, &[]));
                 std::io::stdin().read_line(&mut line)?;
                 println!();
 
@@ -209,8 +269,8 @@ fn main() -> Result<()> {
                     let cau = Uninit::new(db)?;
                     let ca = cau.migrate_card_import_key(&ident)?;
 
-                    println!("Migrated OpenPGP CA instance:\n");
-                    ca.ca_show()?;
+                    println!("{}\n", tr(lang, Msg::CardMigrated, &[]));
+                    print_ca_info(lang, &ca.ca_info()?);
                 } else {
                     return Err(anyhow::anyhow!("Aborted CA migration."));
                 }
@@ -220,9 +280,24 @@ fn main() -> Result<()> {
         }
     }
 
-    // The CLI command was not `ca init` or `ca migrate`, so we should be able to directly open
-    // the database as an Oca object
+    // Utility commands don't need a CA instance
+    if let cli::Commands::Util { cmd } = c.cmd {
+        return util(lang, cmd);
+    }
+
+    // The CLI command was not `ca init`, `ca migrate` or `util`, so we should be able to
+    // directly open the database as an Oca object
     let ca = Oca::open(db)?;
+    let lang = ca.language()?;
+
+    if c.progress {
+        ca.set_progress_handler(Some(Box::new(|p: &Progress| {
+            eprint!("\r{}: {}/{}", p.operation, p.done, p.total);
+            if p.done == p.total {
+                eprintln!();
+            }
+        })));
+    }
 
     match c.cmd {
         cli::Commands::User { cmd } => match cmd {
@@ -230,28 +305,95 @@ fn main() -> Result<()> {
                 email,
                 name,
                 minimal,
+                key_file,
                 password_file,
                 cipher_suite,
+                key_version,
+                expire_in,
+                profile,
                 enable_encryption_subkey,
                 enable_signing_subkey,
                 enable_authentication_subkey,
             } => {
-                // TODO: key-profile?
+                let emails: Vec<_> = email.iter().map(String::as_str).collect();
 
+                let key = if let Some(profile) = profile {
+                    ca.user_new_with_profile(
+                        name.as_deref(),
+                        &emails[..],
+                        None,
+                        &profile,
+                        password_file.map(PasswordPolicy::File),
+                    )?
+                } else {
+                    ca.user_new_with_validity(
+                        name.as_deref(),
+                        &emails[..],
+                        None,
+                        expire_in,
+                        true,
+                        password_file.map(PasswordPolicy::File),
+                        cipher_suite,
+                        key_version,
+                        enable_encryption_subkey,
+                        enable_signing_subkey,
+                        enable_authentication_subkey,
+                    )?
+                };
+
+                print_new_user_key(lang, &key, name.as_deref(), minimal, key_file.as_deref())?;
+            }
+            cli::UserCommand::AddOnCard {
+                ident,
+                email,
+                name,
+                cipher_suite,
+                expire_in,
+            } => {
                 let emails: Vec<_> = email.iter().map(String::as_str).collect();
 
-                ca.user_new(
+                let key = ca.user_new_on_card(
+                    &ident,
                     name.as_deref(),
-                    &emails[..],
+                    &emails,
                     None,
-                    true,
-                    password_file,
-                    minimal,
                     cipher_suite,
-                    enable_encryption_subkey,
-                    enable_signing_subkey,
-                    enable_authentication_subkey,
+                    expire_in,
+                )?;
+
+                println!(
+                    "{}",
+                    tr(
+                        lang,
+                        Msg::UserCardCreated,
+                        &[
+                            ("fingerprint", &key.fingerprint),
+                            ("ident", &key.ident),
+                            ("user_pin", &key.user_pin),
+                            ("admin_pin", &key.admin_pin),
+                        ]
+                    )
+                );
+            }
+            cli::UserCommand::AddRole {
+                role,
+                name,
+                minimal,
+                key_file,
+                password_file,
+                profile,
+            } => {
+                let roles: Vec<_> = role.iter().map(String::as_str).collect();
+
+                let key = ca.role_new(
+                    name.as_deref(),
+                    &roles,
+                    None,
+                    profile.as_deref(),
+                    password_file.map(PasswordPolicy::File),
                 )?;
+
+                print_new_user_key(lang, &key, name.as_deref(), minimal, key_file.as_deref())?;
             }
             cli::UserCommand::AddRevocation { revocation_file } => {
                 ca.revocation_add_from_file(&revocation_file)?
@@ -261,15 +403,153 @@ fn main() -> Result<()> {
                 cli::UserCheckSubcommand::Expiry { days } => {
                     Oca::print_expiry_status(&ca, days)?;
                 }
-                cli::UserCheckSubcommand::Certifications => {
+                cli::UserCheckSubcommand::SubkeyRotation { days, reminders } => {
+                    for (db_cert, rotation) in ca.certs_subkey_rotation_due(days)? {
+                        let name = ca.cert_get_name(&db_cert)?;
+                        match (&rotation.subkey, rotation.rotation_due) {
+                            (Some(subkey), Some(due)) => println!(
+                                "{}",
+                                tr(
+                                    lang,
+                                    Msg::SubkeyRotationDue,
+                                    &[
+                                        ("name", &name),
+                                        ("fingerprint", &db_cert.fingerprint),
+                                        ("subkey", &subkey),
+                                        ("date", &due.format("%F")),
+                                    ]
+                                )
+                            ),
+                            _ => println!(
+                                "{}",
+                                tr(
+                                    lang,
+                                    Msg::SubkeyRotationNoSubkey,
+                                    &[("name", &name), ("fingerprint", &db_cert.fingerprint)]
+                                )
+                            ),
+                        }
+
+                        if let Some(dir) = &reminders {
+                            let mail = ca.subkey_rotation_reminder(&db_cert, &rotation)?;
+                            let path = dir.join(format!("{}.eml", db_cert.fingerprint));
+                            std::fs::write(path, mail.to_string())?;
+                        }
+                    }
+                }
+                cli::UserCheckSubcommand::Certifications { no_cache } => {
+                    ca.set_verification_cache(!no_cache);
                     Oca::print_certifications_status(&ca)?;
                 }
             },
+            cli::UserCommand::Certify {
+                fingerprint,
+                email,
+                days,
+                valid_until,
+                profile,
+            } => {
+                let emails: Vec<_> = email.iter().map(String::as_str).collect();
+
+                match valid_until {
+                    Some(date) => ca.cert_certify_until(
+                        &fingerprint,
+                        &emails,
+                        end_of_day(&date)?,
+                        profile.parse()?,
+                    )?,
+                    None => ca.cert_certify(&fingerprint, &emails, days, profile.parse()?)?,
+                }
+            }
+            cli::UserCommand::RecertifyExpired { grace_days } => {
+                for cert in ca.certs_recertify_expired(grace_days)? {
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::Recertified,
+                            &[("fingerprint", &cert.fingerprint)]
+                        )
+                    );
+                }
+            }
+            cli::UserCommand::RefreshCertifications {
+                threshold_days,
+                days,
+                valid_until,
+            } => match valid_until {
+                Some(date) => {
+                    ca.certs_refresh_ca_certifications_until(threshold_days, end_of_day(&date)?)?
+                }
+                None => ca.certs_refresh_ca_certifications(threshold_days, days)?,
+            },
+            cli::UserCommand::ExtendCertifications {
+                from,
+                until,
+                days,
+                dry_run,
+            } => {
+                let start = NaiveDate::parse_from_str(&from, "%Y-%m-%d")?;
+                let end = NaiveDate::parse_from_str(&until, "%Y-%m-%d")?
+                    .succ_opt()
+                    .ok_or_else(|| anyhow::anyhow!("Invalid date '{until}'"))?;
+
+                let report = ca.certifications_extend_expiring(
+                    start.and_time(NaiveTime::MIN).and_utc(),
+                    end.and_time(NaiveTime::MIN).and_utc(),
+                    days,
+                    dry_run,
+                )?;
+
+                for exp in &report.expiring {
+                    let user = exp
+                        .user_name
+                        .as_ref()
+                        .map(|n| format!(" '{n}'"))
+                        .unwrap_or_default();
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::CertificationExpires,
+                            &[
+                                ("fingerprint", &exp.fingerprint),
+                                ("user", &user),
+                                ("userid", &exp.user_id),
+                                ("date", &exp.expiration.format("%F")),
+                            ]
+                        )
+                    );
+                }
+                for (fp, err) in &report.failed {
+                    eprintln!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::RenewFailed,
+                            &[("fingerprint", fp), ("error", err)]
+                        )
+                    );
+                }
+
+                let (msg, count) = if dry_run {
+                    (Msg::RenewPreview, report.expiring.len())
+                } else if report.queued {
+                    (Msg::RenewQueued, report.renewed)
+                } else {
+                    (Msg::Renewed, report.renewed)
+                };
+                println!("{}", tr(lang, msg, &[("count", &count)]));
+            }
             cli::UserCommand::Import {
                 cert_file,
                 name,
                 email,
+                uri,
+                role,
                 revocation_file,
+                valid_until,
+                strict,
             } => {
                 let cert = std::fs::read(cert_file)?;
 
@@ -280,36 +560,599 @@ fn main() -> Result<()> {
                 }
 
                 let emails: Vec<_> = email.iter().map(String::as_str).collect();
+                let uris: Vec<_> = uri.iter().map(String::as_str).collect();
+                let roles: Vec<_> = role.iter().map(String::as_str).collect();
+                let revoc_certs: Vec<_> = revoc_certs.iter().map(|v| v.as_slice()).collect();
 
-                ca.cert_import_new(
-                    &cert,
-                    revoc_certs
-                        .iter()
-                        .map(|v| v.as_slice())
-                        .collect::<Vec<_>>()
-                        .as_ref(),
-                    name.as_deref(),
-                    &emails,
-                    None,
-                )?;
+                let warnings = ca.cert_import_warnings(&pgp::to_cert(&cert)?, &emails)?;
+                for w in &warnings {
+                    println!("{}", tr(lang, Msg::Warning, &[("message", &w.message)]));
+                }
+                if strict && !warnings.is_empty() {
+                    return Err(anyhow::anyhow!(tr(
+                        lang,
+                        Msg::ImportStrict,
+                        &[("count", &warnings.len())]
+                    )));
+                }
+
+                match valid_until {
+                    Some(date) => ca.cert_import_new_until(
+                        &cert,
+                        &revoc_certs,
+                        name.as_deref(),
+                        &emails,
+                        &uris,
+                        &roles,
+                        end_of_day(&date)?,
+                    )?,
+                    None => ca.cert_import_new_with_roles(
+                        &cert,
+                        &revoc_certs,
+                        name.as_deref(),
+                        &emails,
+                        &uris,
+                        &roles,
+                        None,
+                    )?,
+                }
             }
-            cli::UserCommand::Update { cert_file } => {
+            cli::UserCommand::Update {
+                cert_file,
+                allow_downgrade,
+                rotation,
+            } => {
+                let cert = std::fs::read(cert_file)?;
+                if rotation {
+                    ca.cert_import_rotation(&cert)?;
+                } else if allow_downgrade {
+                    ca.cert_import_update_allow_downgrade(&cert)?;
+                } else {
+                    ca.cert_import_update(&cert)?;
+                }
+            }
+            cli::UserCommand::ImportCertifications { cert_file } => {
                 let cert = std::fs::read(cert_file)?;
-                ca.cert_import_update(&cert)?;
+                let count = ca.import_certifications(&cert)?;
+                println!(
+                    "{}",
+                    tr(lang, Msg::ImportedCertifications, &[("count", &count)])
+                );
             }
-            cli::UserCommand::Export { email, path } => {
-                if let Some(path) = path {
-                    ca.export_certs_as_files(email, &path)?;
+            cli::UserCommand::Export {
+                email,
+                path,
+                encryption_capable,
+                minimize,
+                format,
+                compat,
+            } => {
+                let format = CertFormat::from(format);
+                let compat = export_compat(&compat);
+
+                if encryption_capable {
+                    let (certs, rejected) = ca.certs_for_encryption(email)?;
+
+                    match format {
+                        CertFormat::Armored => println!("{}", pgp::certs_to_armored(&certs)?),
+                        CertFormat::Binary => {
+                            std::io::stdout().write_all(&pgp::certs_to_binary(&certs)?)?
+                        }
+                    }
+
+                    for r in rejected {
+                        eprintln!(
+                            "{}",
+                            tr(
+                                lang,
+                                Msg::ExportSkipped,
+                                &[("fingerprint", &r.fingerprint), ("reason", &r.reason)]
+                            )
+                        );
+                    }
+                } else if let Some(path) = path {
+                    ca.export_certs_as_files(email, &path, minimize, format, compat)?;
                 } else {
-                    ca.print_certring(email)?;
+                    let certring = ca.certring(email.as_deref(), minimize, format, compat)?;
+                    std::io::stdout().write_all(&certring)?;
+                }
+            }
+            cli::UserCommand::ExportKeyring {
+                output,
+                chunk_size,
+                compression,
+                minimize,
+                format,
+                compat,
+            } => {
+                let format = CertFormat::from(format);
+                let compat = export_compat(&compat);
+                let compression = export_compression(&compression);
+
+                match chunk_size {
+                    Some(chunk_size) => {
+                        let index = ca.export_certring_chunked(
+                            &output,
+                            chunk_size,
+                            minimize,
+                            format,
+                            compat,
+                            compression,
+                        )?;
+
+                        let certs: usize = index.chunks.iter().map(|c| c.fingerprints.len()).sum();
+                        println!(
+                            "{}",
+                            tr(
+                                lang,
+                                Msg::ExportedChunks,
+                                &[
+                                    ("certs", &certs),
+                                    ("chunks", &index.chunks.len()),
+                                    ("path", &output.display()),
+                                ]
+                            )
+                        );
+                    }
+                    None => {
+                        ca.export_certring(&output, None, minimize, format, compat, compression)?
+                    }
+                }
+            }
+            cli::UserCommand::ExportChain { email, output } => {
+                let chain = ca.export_chain(&email)?;
+
+                match output {
+                    Some(output) => std::fs::write(output, chain)?,
+                    None => print!("{chain}"),
+                }
+            }
+            cli::UserCommand::ExportAutocrypt { email } => {
+                println!("{}", ca.export_autocrypt(&email)?);
+            }
+            cli::UserCommand::ExportSsh { email } => {
+                print!("{}", ca.export_ssh_keys(&email)?);
+            }
+            cli::UserCommand::ExportGnupg { homedir } => {
+                ca.export_to_gnupg_home(&homedir)?;
+            }
+            cli::UserCommand::Bundle {
+                fingerprint,
+                private_key_file,
+                output,
+            } => {
+                let private_key = private_key_file.map(std::fs::read_to_string).transpose()?;
+
+                let bundle = ca.user_provisioning_bundle(&fingerprint, private_key.as_deref())?;
+
+                match output {
+                    Some(output) => std::fs::write(output, bundle.armored())?,
+                    None => print!("{}", bundle.armored()),
                 }
             }
             cli::UserCommand::List => Oca::print_users(&ca)?,
+            cli::UserCommand::Notes { cmd } => match cmd {
+                cli::NotesCommand::Set {
+                    fingerprint,
+                    text,
+                    user,
+                } => {
+                    if user {
+                        ca.user_notes_set(&fingerprint, Some(&text))?
+                    } else {
+                        ca.cert_notes_set(&fingerprint, Some(&text))?
+                    }
+                }
+                cli::NotesCommand::Append {
+                    fingerprint,
+                    text,
+                    user,
+                } => {
+                    if user {
+                        ca.user_notes_append(&fingerprint, &text)?
+                    } else {
+                        ca.cert_notes_append(&fingerprint, &text)?
+                    }
+                }
+                cli::NotesCommand::Clear { fingerprint, user } => {
+                    if user {
+                        ca.user_notes_set(&fingerprint, None)?
+                    } else {
+                        ca.cert_notes_set(&fingerprint, None)?
+                    }
+                }
+            },
+            cli::UserCommand::X509 { cmd } => match cmd {
+                cli::X509Command::Export { output } => {
+                    let mapping = serde_json::to_string_pretty(&ca.export_x509_mapping()?)?;
+
+                    match output {
+                        Some(output) => std::fs::write(output, mapping)?,
+                        None => println!("{mapping}"),
+                    }
+                }
+                cli::X509Command::SetSerial {
+                    fingerprint,
+                    serial,
+                } => ca.x509_serial_set(&fingerprint, Some(&serial))?,
+                cli::X509Command::RemoveSerial { fingerprint } => {
+                    ca.x509_serial_set(&fingerprint, None)?
+                }
+            },
+            cli::UserCommand::Tag { cmd } => match cmd {
+                cli::TagCommand::Set {
+                    fingerprint,
+                    name,
+                    value,
+                    user,
+                } => {
+                    if user {
+                        ca.user_tag_set(&fingerprint, &name, &value)?
+                    } else {
+                        ca.cert_tag_set(&fingerprint, &name, &value)?
+                    }
+                }
+                cli::TagCommand::Remove {
+                    fingerprint,
+                    name,
+                    user,
+                } => {
+                    if user {
+                        ca.user_tag_remove(&fingerprint, &name)?
+                    } else {
+                        ca.cert_tag_remove(&fingerprint, &name)?
+                    }
+                }
+                cli::TagCommand::List { fingerprint } => {
+                    let cert = ca
+                        .cert_get_by_fingerprint(&fingerprint)?
+                        .ok_or_else(|| anyhow::anyhow!("No key with fingerprint {fingerprint}"))?;
+
+                    if let Some(user) = ca.cert_get_users(&cert)? {
+                        for (name, value) in ca.user_tags(&user)? {
+                            println!(
+                                "{}",
+                                tr(lang, Msg::TagUser, &[("name", &name), ("value", &value)])
+                            );
+                        }
+                    }
+                    for (name, value) in ca.cert_tags(&cert)? {
+                        println!("{name}={value}");
+                    }
+                }
+                cli::TagCommand::Find { name, value } => {
+                    for cert in ca.certs_by_tag(&name, value.as_deref())? {
+                        print!("{}", cert.fingerprint);
+                        if let Some(name) = ca.cert_get_users(&cert)?.and_then(|u| u.name) {
+                            print!(" '{name}'");
+                        }
+                        println!();
+                    }
+                }
+            },
+            cli::UserCommand::Search { text } => {
+                for m in ca.search(&text)? {
+                    let fields: Vec<_> = m.fields.iter().map(ToString::to_string).collect();
+
+                    print!("{}", m.cert.fingerprint);
+                    if let Some(name) = m.user.and_then(|u| u.name) {
+                        print!(" '{name}'");
+                    }
+                    println!(
+                        " {}",
+                        tr(lang, Msg::SearchMatched, &[("fields", &fields.join(", "))])
+                    );
+                }
+            }
+            cli::UserCommand::WkdPublish {
+                fingerprint,
+                disable,
+            } => {
+                ca.user_set_wkd_publish(&fingerprint, !disable)?;
+                let msg = if disable {
+                    Msg::WkdPublishDisabled
+                } else {
+                    Msg::WkdPublishEnabled
+                };
+                println!("{}", tr(lang, msg, &[("fingerprint", &fingerprint)]));
+            }
+            cli::UserCommand::Delist {
+                fingerprint,
+                channel,
+                undo,
+            } => {
+                let channels = if channel.is_empty() {
+                    ExportChannel::ALL.to_vec()
+                } else {
+                    channel
+                        .iter()
+                        .map(|c| c.parse())
+                        .collect::<Result<Vec<ExportChannel>>>()?
+                };
+
+                for channel in &channels {
+                    ca.cert_set_delisted(&fingerprint, *channel, !undo)?;
+                }
+
+                let channels: Vec<_> = channels.iter().map(ExportChannel::name).collect();
+                let msg = if undo { Msg::Listed } else { Msg::Delisted };
+                println!(
+                    "{}",
+                    tr(
+                        lang,
+                        msg,
+                        &[
+                            ("fingerprint", &fingerprint),
+                            ("channels", &channels.join(", ")),
+                        ]
+                    )
+                );
+            }
+            cli::UserCommand::ReassignCert {
+                fingerprint,
+                to,
+                new_user,
+                reason,
+            } => {
+                let user = ca.cert_reassign(&fingerprint, &to, new_user, reason.as_deref())?;
+                println!(
+                    "{}",
+                    tr(
+                        lang,
+                        Msg::Reassigned,
+                        &[
+                            ("fingerprint", &fingerprint),
+                            ("user", &to),
+                            ("id", &user.id),
+                        ]
+                    )
+                );
+            }
+            cli::UserCommand::ShowReassignments { fingerprint } => {
+                let name = |id: i32| -> Result<String> {
+                    Ok(match ca.user_by_id(id)?.and_then(|u| u.name) {
+                        Some(name) => format!("'{name}' (id {id})"),
+                        None => format!("(id {id})"),
+                    })
+                };
+
+                for r in ca.cert_reassignments(&fingerprint)? {
+                    let from = match r.from_user_id {
+                        Some(id) => name(id)?,
+                        None => tr(lang, Msg::NoUser, &[]),
+                    };
+
+                    print!(
+                        "{}  {from} -> {}",
+                        r.created.format("%F %T"),
+                        name(r.to_user_id)?
+                    );
+                    if let Some(reason) = r.reason {
+                        print!(": {reason}");
+                    }
+                    println!();
+                }
+            }
+            cli::UserCommand::Purge { fingerprint, force } => {
+                if force {
+                    ca.cert_purge(&fingerprint)?;
+                    println!(
+                        "{}",
+                        tr(lang, Msg::Purged, &[("fingerprint", &fingerprint)])
+                    );
+                } else {
+                    let cert = ca
+                        .cert_get_by_fingerprint(&fingerprint)?
+                        .ok_or_else(|| anyhow::anyhow!("Cert not found"))?;
+
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::PurgePreview,
+                            &[("fingerprint", &cert.fingerprint)]
+                        )
+                    );
+                    if let Some(name) = cert
+                        .user_id
+                        .map(|id| ca.user_by_id(id))
+                        .transpose()?
+                        .flatten()
+                        .and_then(|u| u.name)
+                    {
+                        println!("{}", tr(lang, Msg::PurgeUser, &[("name", &name)]));
+                    }
+                    for email in ca.emails_get(&cert)? {
+                        println!("{}", tr(lang, Msg::PurgeEmail, &[("email", &email.addr)]));
+                    }
+                    let count = ca.revocations_get(&cert)?.len();
+                    println!("{}", tr(lang, Msg::PurgeRevocations, &[("count", &count)]));
+                    println!();
+                    println!("{}", tr(lang, Msg::PurgeForce, &[]));
+                }
+            }
+            cli::UserCommand::Replacements { cmd } => match cmd {
+                cli::ReplacementsCommand::List { all } => {
+                    let replacements = if all {
+                        ca.key_replacements()?
+                    } else {
+                        ca.key_replacements_pending()?
+                    };
+
+                    let fps: HashMap<i32, String> = ca
+                        .user_certs_get_all()?
+                        .into_iter()
+                        .map(|c| (c.id, c.fingerprint))
+                        .collect();
+
+                    for r in replacements {
+                        let old = fps.get(&r.cert_id).map(String::as_str).unwrap_or_default();
+
+                        print!(
+                            "{:>5}  {}  {old} -> {}  {}",
+                            r.id,
+                            r.created.format("%F %T"),
+                            r.new_fingerprint,
+                            r.status
+                        );
+                        if let Some(reason) = r.reason {
+                            print!(": {reason}");
+                        }
+                        println!();
+                    }
+                }
+                cli::ReplacementsCommand::Approve { id, days, keep_old } => {
+                    let cert = ca.key_replacement_approve(id, days, !keep_old)?;
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::ReplacementStored,
+                            &[("fingerprint", &cert.fingerprint)]
+                        )
+                    );
+                }
+                cli::ReplacementsCommand::Reject { id, reason } => {
+                    ca.key_replacement_reject(id, reason.as_deref())?
+                }
+            },
+            cli::UserCommand::Show { email } => {
+                let dossiers = ca.user_dossiers(&email)?;
+                if dossiers.is_empty() {
+                    return Err(anyhow::anyhow!("No user with email address {email}"));
+                }
+                for (i, dossier) in dossiers.iter().enumerate() {
+                    if i > 0 {
+                        println!();
+                    }
+                    print_user_dossier(lang, dossier)?;
+                }
+            }
+            cli::UserCommand::Inspect { fingerprint } => {
+                let cert = ca
+                    .cert_get_by_fingerprint(&fingerprint)?
+                    .ok_or_else(|| anyhow::anyhow!("No key with fingerprint {fingerprint}"))?;
+                let cert = pgp::to_cert(cert.pub_cert.as_bytes())?;
+
+                print_cert_info(lang, &ca.cert_info(&cert)?);
+                for w in ca.cert_warnings(&cert)? {
+                    println!("{}", tr(lang, Msg::Warning, &[("message", &w.message)]));
+                }
+            }
             cli::UserCommand::ShowRevocations { email } => Oca::print_revocations(&ca, &email)?,
             cli::UserCommand::ApplyRevocation { hash } => {
                 let rev = ca.revocation_get_by_hash(&hash)?;
                 ca.revocation_apply(rev)?;
+
+                print_revocation_publication(lang, &ca.revocation_get_by_hash(&hash)?);
+            }
+            cli::UserCommand::Offboard { email, reason } => {
+                let report = ca.user_offboard(&email, &reason)?;
+                for cert in report.certs {
+                    let fp = &cert.fingerprint;
+                    match cert.revocation {
+                        OffboardRevocation::AlreadyRevoked => {
+                            println!(
+                                "{}",
+                                tr(lang, Msg::OffboardAlreadyRevoked, &[("fingerprint", fp)])
+                            )
+                        }
+                        OffboardRevocation::Applied(hash) => println!(
+                            "{}",
+                            tr(
+                                lang,
+                                Msg::OffboardApplied,
+                                &[("fingerprint", fp), ("hash", &hash)]
+                            )
+                        ),
+                        OffboardRevocation::Retracted(uids) => {
+                            println!(
+                                "{}",
+                                tr(lang, Msg::OffboardNoRevocation, &[("fingerprint", fp)])
+                            );
+                            for uid in uids {
+                                println!(
+                                    "{}",
+                                    tr(lang, Msg::OffboardRetracted, &[("userid", &uid)])
+                                );
+                            }
+                        }
+                    }
+                }
+                println!("{}", tr(lang, Msg::Offboarded, &[("email", &report.email)]));
             }
+            cli::UserCommand::PublishRevocation { hash } => {
+                let rev = ca.revocation_publish(&hash)?;
+                print_revocation_publication(lang, &rev);
+            }
+            cli::UserCommand::RetractCertification {
+                fingerprint,
+                userid,
+                reason,
+            } => ca.cert_retract_certification(&fingerprint, &userid, &reason)?,
+            cli::UserCommand::Versions { cmd } => match cmd {
+                cli::VersionsCommand::List { fingerprint } => {
+                    for v in ca.cert_versions(&fingerprint)? {
+                        println!(
+                            "{:>5}  {}  {}",
+                            v.id,
+                            v.created.format("%F %T"),
+                            tr(lang, Msg::VersionReplacedBy, &[("origin", &v.origin)])
+                        );
+                    }
+                }
+                cli::VersionsCommand::Diff { from, to } => {
+                    let diff = ca.cert_versions_diff(from, to)?;
+                    for p in diff.removed {
+                        println!("- {p}");
+                    }
+                    for p in diff.added {
+                        println!("+ {p}");
+                    }
+                }
+                cli::VersionsCommand::AsOf { fingerprint, time } => {
+                    let time = match DateTime::parse_from_rfc3339(&time) {
+                        Ok(time) => time.with_timezone(&Utc),
+                        Err(_) => NaiveDate::parse_from_str(&time, "%Y-%m-%d")?
+                            .and_time(NaiveTime::MIN)
+                            .and_utc(),
+                    };
+
+                    let state = ca.cert_as_of(&fingerprint, time)?;
+                    match state.cert {
+                        None => println!("{}", tr(lang, Msg::AsOfMissing, &[("time", &time)])),
+                        Some(_) => {
+                            match state.version {
+                                Some(id) => {
+                                    println!("{}", tr(lang, Msg::AsOfVersion, &[("id", &id)]))
+                                }
+                                None => println!("{}", tr(lang, Msg::AsOfCurrent, &[])),
+                            }
+                            if state.revoked {
+                                println!("{}", tr(lang, Msg::AsOfRevoked, &[]));
+                            }
+                            if state.certified.is_empty() {
+                                println!("{}", tr(lang, Msg::AsOfNotCertified, &[]));
+                            }
+                            for uid in &state.certified {
+                                let uid = String::from_utf8_lossy(uid.value());
+                                println!("{}", tr(lang, Msg::AsOfCertified, &[("userid", &uid)]));
+                            }
+                        }
+                    }
+                    if state.incomplete {
+                        println!("{}", tr(lang, Msg::AsOfIncomplete, &[("time", &time)]));
+                    }
+                }
+                cli::VersionsCommand::Restore { id } => ca.cert_version_restore(id)?,
+                cli::VersionsCommand::Retention { keep } => {
+                    if let Some(keep) = keep {
+                        ca.cert_versions_set_retention(keep)?;
+                    } else {
+                        println!("{}", ca.cert_versions_retention()?);
+                    }
+                }
+            },
         },
         cli::Commands::Ca { cmd } => match cmd {
             cli::CaCommand::Init { .. } | cli::CaCommand::Migrate { .. } => {
@@ -323,113 +1166,2164 @@ fn main() -> Result<()> {
                     let ident = find_one_matching_card(&ident, ca_cert.as_bytes())?;
 
                     // This card is already initialized, ask for User PIN
-                    let user_pin = rpassword::prompt_password(format!(
-                        "Enter User PIN for OpenPGP card {ident}: "
+                    let user_pin = rpassword::prompt_password(tr(
+                        lang,
+                        Msg::CardUserPinPrompt,
+                        &[("ident", &ident)],
                     ))?;
                     println!();
 
+                    // (This consumes the Oca instance)
                     ca.set_card_backend(&ident, &user_pin)?;
-                    println!("CA backend configuration is changed.");
+                    println!("{}", tr(lang, Msg::BackendChanged, &[]));
+
+                    return Ok(());
                 }
             },
-            cli::CaCommand::Export => {
-                println!("{}", ca.ca_get_pubkey_armored()?);
+            cli::CaCommand::Export { strip_stale_tsigs } => {
+                if strip_stale_tsigs {
+                    let cert = ca.ca_get_cert_pub_without_stale_tsigs()?;
+                    println!("{}", pgp::cert_to_armored(&cert)?);
+                } else {
+                    println!("{}", ca.ca_get_pubkey_armored()?);
+                }
+            }
+            cli::CaCommand::Revocations { output, compat } => {
+                ca.ca_generate_revocations(output, export_compat(&compat))?;
+                println!("{}", tr(lang, Msg::RevocationsWritten, &[]));
             }
-            cli::CaCommand::Revocations { output } => {
-                ca.ca_generate_revocations(output)?;
-                println!("Wrote a set of revocations to the output file");
+            cli::CaCommand::RevocationList { output } => {
+                ca.export_revocation_list(&output)?;
             }
             cli::CaCommand::ImportTsig { cert_file } => {
                 let cert = std::fs::read(cert_file)?;
                 ca.ca_import_tsig(&cert)?;
             }
-            cli::CaCommand::Show => ca.ca_show()?,
-            cli::CaCommand::Private => ca.ca_print_private()?,
+            cli::CaCommand::CheckCertifications { input } => {
+                let keyring = std::fs::read(input)?;
+                let res = ca.extract_issued_certifications(&keyring)?;
 
-            cli::CaCommand::ReCertify {
-                pubkey_file_old: cert_file_old,
-                validity_days,
-            } => {
-                let cert_old = std::fs::read(cert_file_old)?;
-                ca.ca_re_certify(&cert_old, validity_days)?;
-            }
+                for (status, issued) in [
+                    ("unrecorded", &res.unrecorded),
+                    ("invalid", &res.invalid),
+                    ("unpublished", &res.not_published),
+                    ("ok", &res.recorded),
+                ] {
+                    for i in issued {
+                        println!("{:<11} {} {}", status, i.fingerprint, i.userid);
+                    }
+                }
 
-            cli::CaCommand::Split { cmd } => match cmd {
-                cli::SplitCommand::Into { front, back } => ca.ca_split_into(&front, &back)?,
-                cli::SplitCommand::Merge { back } => ca.ca_merge_split(&back)?,
+                if !res.unrecorded.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "Found {} certification(s) by the CA key that are not recorded in the CA database",
+                        res.unrecorded.len()
+                    ));
+                }
+            }
+            cli::CaCommand::Tsigs { cmd } => match cmd {
+                cli::TsigsCommand::List => {
+                    for tsig in ca.ca_tsigs()? {
+                        let name = match &tsig.cert {
+                            Some(cert) => ca.cert_get_users(cert)?.and_then(|u| u.name),
+                            None => None,
+                        };
 
-                cli::SplitCommand::Export { file } => ca.ca_split_export(file)?,
+                        println!(
+                            "{} {:<10} {}",
+                            tsig.issuer,
+                            tsig.status,
+                            name.unwrap_or_default()
+                        );
+                    }
+                }
+                cli::TsigsCommand::Missing => {
+                    for cert in ca.ca_tsigs_missing()? {
+                        let name = ca.cert_get_users(&cert)?.and_then(|u| u.name);
+                        let emails: Vec<_> =
+                            ca.emails_get(&cert)?.into_iter().map(|e| e.addr).collect();
 
-                cli::SplitCommand::Certify {
-                    import,
-                    export,
-                    batch,
-                } => ca.ca_split_certify(import, export, batch)?,
+                        println!(
+                            "{} {} <{}>",
+                            cert.fingerprint,
+                            name.unwrap_or_default(),
+                            emails.join(", ")
+                        );
+                    }
+                }
+            },
+            cli::CaCommand::KeyPolicy { cmd } => match cmd {
+                cli::KeyPolicyCommand::Show => {
+                    let policy = ca.key_policy()?;
+
+                    let show = |o: Option<String>| o.unwrap_or_else(|| "-".to_string());
 
-                cli::SplitCommand::Import { import: file } => ca.ca_split_import(file)?,
+                    print_fields(
+                        lang,
+                        &[
+                            (
+                                Msg::KeyPolicyMinRsaBits,
+                                show(policy.min_rsa_bits.map(|b| b.to_string())),
+                            ),
+                            (
+                                Msg::KeyPolicyAllowedAlgorithms,
+                                show(policy.allowed_algorithms.map(|a| a.join(", "))),
+                            ),
+                            (
+                                Msg::KeyPolicyRequireEncryptionSubkey,
+                                policy.require_encryption_subkey.to_string(),
+                            ),
+                            (
+                                Msg::KeyPolicyRequireSigningSubkey,
+                                policy.require_signing_subkey.to_string(),
+                            ),
+                            (
+                                Msg::KeyPolicyMaxExpiry,
+                                show(policy.max_expiry_days.map(|d| d.to_string())),
+                            ),
+                            (
+                                Msg::KeyPolicyMaxSubkeyAge,
+                                show(policy.max_encryption_subkey_age_days.map(|d| d.to_string())),
+                            ),
+                        ],
+                    );
+                }
+                cli::KeyPolicyCommand::Set {
+                    min_rsa_bits,
+                    allowed_algorithms,
+                    require_encryption_subkey,
+                    require_signing_subkey,
+                    max_expiry_days,
+                    max_encryption_subkey_age_days,
+                } => {
+                    let allowed_algorithms = if allowed_algorithms.is_empty() {
+                        None
+                    } else {
+                        Some(allowed_algorithms)
+                    };
 
-                cli::SplitCommand::ShowQueue {} => ca.ca_split_show_queue()?,
+                    ca.set_key_policy(&KeyPolicy {
+                        min_rsa_bits,
+                        allowed_algorithms,
+                        require_encryption_subkey,
+                        require_signing_subkey,
+                        max_expiry_days,
+                        max_encryption_subkey_age_days,
+                    })?;
+                }
             },
-        },
-        cli::Commands::Bridge { cmd } => match cmd {
-            cli::BridgeCommand::New {
-                email,
-                scope,
-                remote_key_file,
-                commit,
-            } => {
-                if commit {
-                    let (email, fp) =
-                        ca.add_bridge(email.as_deref(), &remote_key_file, scope.as_deref(), false)?;
+            cli::CaCommand::UriPolicy { cmd } => match cmd {
+                cli::UriPolicyCommand::Show => {
+                    for prefix in ca.uri_policy()?.allowed_prefixes {
+                        println!("{prefix}");
+                    }
+                }
+                cli::UriPolicyCommand::Set { allowed_prefixes } => {
+                    ca.set_uri_policy(&UriPolicy { allowed_prefixes })?;
+                }
+            },
+            cli::CaCommand::Blocklist { cmd } => match cmd {
+                cli::BlocklistCommand::List => {
+                    for e in ca.blocklist()? {
+                        print!("{:>5}  {:<11}  {}", e.id, e.kind, e.pattern);
+                        if let Some(reason) = e.reason {
+                            print!(": {reason}");
+                        }
+                        println!();
+                    }
+                }
+                cli::BlocklistCommand::Add {
+                    fingerprint,
+                    domain,
+                    reason,
+                } => {
+                    let (kind, pattern) = match (fingerprint, domain) {
+                        (Some(fp), _) => (BlocklistKind::Fingerprint, fp),
+                        (None, Some(domain)) => (BlocklistKind::Domain, domain),
+                        (None, None) => unreachable!("enforced by clap"),
+                    };
+
+                    let entry = ca.blocklist_add(kind, &pattern, reason.as_deref())?;
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::BlocklistAdded,
+                            &[("kind", &entry.kind), ("pattern", &entry.pattern)]
+                        )
+                    );
+                }
+                cli::BlocklistCommand::Remove { id } => {
+                    let entry = ca.blocklist_remove(id)?;
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::BlocklistRemoved,
+                            &[("kind", &entry.kind), ("pattern", &entry.pattern)]
+                        )
+                    );
+                }
+                cli::BlocklistCommand::Log => {
+                    let entries = ca.blocklist_log()?;
+
+                    // additions and removals, in chronological order
+                    let mut log: Vec<_> =
+                        entries
+                            .iter()
+                            .map(|e| (e.created, Msg::BlocklistLogAdded, e))
+                            .chain(entries.iter().filter_map(|e| {
+                                e.removed.map(|r| (r, Msg::BlocklistLogRemoved, e))
+                            }))
+                            .collect();
+                    log.sort_by_key(|(time, _, e)| (*time, e.id));
+
+                    for (time, action, e) in log {
+                        print!(
+                            "{}  {:<11}  {:>5}  {} '{}'",
+                            time.format("%F %T"),
+                            tr(lang, action, &[]),
+                            e.id,
+                            e.kind,
+                            e.pattern
+                        );
+                        if let (Some(reason), Msg::BlocklistLogAdded) = (&e.reason, action) {
+                            print!(": {reason}");
+                        }
+                        println!();
+                    }
+                }
+            },
+            cli::CaCommand::CtLog { cmd } => match cmd {
+                cli::CtLogCommand::List => {
+                    for e in ca.ct_log()? {
+                        println!(
+                            "{:>6}  {}  {:<24}  {}",
+                            e.seq,
+                            e.created.format("%F %T"),
+                            e.kind,
+                            e.fingerprint
+                        );
+                    }
+                }
+                cli::CtLogCommand::Export { output } => {
+                    let log = ca.ct_log_export()?;
+                    match output {
+                        Some(output) => std::fs::write(output, log)?,
+                        None => print!("{log}"),
+                    }
+                }
+                cli::CtLogCommand::Verify { file } => {
+                    let report = match file {
+                        Some(file) => Oca::ct_log_verify_export(&std::fs::read(file)?)?,
+                        None => ca.ct_log_verify()?,
+                    };
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::CtLogIntact,
+                            &[("entries", &report.entries), ("head", &report.head)]
+                        )
+                    );
+                }
+            },
+            cli::CaCommand::NotationPolicy { cmd } => match cmd {
+                cli::NotationPolicyCommand::Show => {
+                    let policy = ca.notation_policy()?;
+                    match ca.certification_notation()? {
+                        Some(n) => println!(
+                            "{}",
+                            tr(
+                                lang,
+                                Msg::NotationShow,
+                                &[
+                                    ("name", &n.name),
+                                    ("value", &n.value),
+                                    ("required", &policy.required),
+                                ]
+                            )
+                        ),
+                        None => println!("{}", tr(lang, Msg::NotationNone, &[])),
+                    }
+                }
+                cli::NotationPolicyCommand::Set {
+                    name,
+                    value,
+                    required,
+                } => {
+                    ca.set_notation_policy(&NotationPolicy {
+                        enabled: true,
+                        name,
+                        value,
+                        required,
+                    })?;
+                }
+                cli::NotationPolicyCommand::Disable => {
+                    ca.set_notation_policy(&NotationPolicy::default())?;
+                }
+            },
+            cli::CaCommand::Config { cmd } => match cmd {
+                cli::ConfigCommand::Show => {
+                    let config = ca.ca_config()?;
 
-                    println!("Added OpenPGP key for {} as bridge.\n", email);
-                    println!("The fingerprint of the remote CA key is");
-                    println!("{fp}\n");
+                    for key in CaConfigKey::ALL {
+                        println!("{:>16}: {}", key, config.get(key).unwrap_or("-"));
+                    }
+                }
+                cli::ConfigCommand::Set { key, value } => {
+                    ca.ca_config_set(key.parse::<CaConfigKey>()?, Some(&value))?;
+                }
+                cli::ConfigCommand::Unset { key } => {
+                    ca.ca_config_set(key.parse::<CaConfigKey>()?, None)?;
+                }
+                cli::ConfigCommand::History => {
+                    for change in ca.ca_config_history()? {
+                        println!(
+                            "{} {}: {} -> {}",
+                            change.changed.format("%F %T"),
+                            change.key,
+                            change.old.as_deref().unwrap_or("-"),
+                            change.new.as_deref().unwrap_or("-"),
+                        );
+                    }
+                }
+            },
+            cli::CaCommand::Language { language } => {
+                if let Some(language) = language {
+                    ca.set_language(language.parse()?)?;
+                } else {
+                    println!("{}", ca.configured_language()?);
+                }
+            }
+            cli::CaCommand::MailSigning { enabled } => {
+                if let Some(enabled) = enabled {
+                    ca.set_mail_signing(enabled)?;
                 } else {
-                    println!("Bridge creation DRY RUN.");
+                    println!("{}", ca.mail_signing()?);
+                }
+            }
+            cli::CaCommand::CryptoPolicy { cmd } => match cmd {
+                cli::CryptoPolicyCommand::Show => {
+                    let policy = ca.crypto_policy()?;
+
+                    let show = |v: Vec<String>| {
+                        if v.is_empty() {
+                            "-".to_string()
+                        } else {
+                            v.join(", ")
+                        }
+                    };
+
+                    print_fields(
+                        lang,
+                        &[
+                            (
+                                Msg::CryptoPolicySha1Cutoff,
+                                policy
+                                    .sha1_cutoff
+                                    .map(|t| t.format("%F").to_string())
+                                    .unwrap_or_else(|| "-".to_string()),
+                            ),
+                            (
+                                Msg::CryptoPolicyRejectedHashes,
+                                show(policy.rejected_hashes),
+                            ),
+                            (
+                                Msg::CryptoPolicyRejectedAlgorithms,
+                                show(policy.rejected_algorithms),
+                            ),
+                        ],
+                    );
+                }
+                cli::CryptoPolicyCommand::Set {
+                    sha1_cutoff,
+                    rejected_hashes,
+                    rejected_algorithms,
+                } => {
+                    let sha1_cutoff = match sha1_cutoff {
+                        Some(date) => Some(
+                            NaiveDate::parse_from_str(&date, "%Y-%m-%d")?
+                                .and_time(NaiveTime::MIN)
+                                .and_utc(),
+                        ),
+                        None => None,
+                    };
+
+                    ca.set_crypto_policy(&CryptoPolicy {
+                        sha1_cutoff,
+                        rejected_hashes,
+                        rejected_algorithms,
+                    })?;
+                }
+            },
+            cli::CaCommand::Stats { cmd } => match cmd {
+                cli::StatsCommand::Show => {
+                    println!("{}", serde_json::to_string_pretty(&ca.usage_stats()?)?);
+                }
+                cli::StatsCommand::Share { endpoint } => {
+                    if ca.tor_proxy()?.is_some() {
+                        return Err(anyhow::anyhow!(
+                            "This CA routes network operations through Tor, sharing statistics is not supported"
+                        ));
+                    }
+
+                    let body = serde_json::to_string_pretty(&ca.usage_stats_submission()?)?;
+
+                    println!(
+                        "{}",
+                        tr(lang, Msg::StatsPreview, &[("endpoint", &endpoint)])
+                    );
+                    println!();
+                    println!("{body}");
+                    println!();
+                    println!("{}", tr(lang, Msg::StatsSigned, &[]));
+                    println!();
+
+                    let mut line = String::new();
+                    println!("{}", tr(lang, Msg::StatsConfirm, &[]));
+                    std::io::stdin().read_line(&mut line)?;
                     println!();
 
+                    if line.trim().to_ascii_lowercase() != "yes" {
+                        return Err(anyhow::anyhow!("Aborted, no statistics were sent."));
+                    }
+
+                    reqwest::blocking::Client::new()
+                        .post(&endpoint)
+                        .header("Content-Type", "application/json")
+                        .body(body)
+                        .send()?
+                        .error_for_status()?;
+
+                    println!("{}", tr(lang, Msg::StatsSent, &[("endpoint", &endpoint)]));
+                }
+            },
+            cli::CaCommand::Tor { cmd } => match cmd {
+                cli::TorCommand::Show => match ca.tor_proxy()? {
+                    Some(proxy) => println!("{}", tr(lang, Msg::TorProxy, &[("proxy", &proxy)])),
+                    None => println!("{}", tr(lang, Msg::TorDirect, &[])),
+                },
+                cli::TorCommand::Set { proxy } => ca.set_tor_proxy(Some(proxy))?,
+                cli::TorCommand::Unset => ca.set_tor_proxy(None)?,
+            },
+            cli::CaCommand::Keyservers { cmd } => match cmd {
+                cli::KeyserversCommand::Show => {
+                    let config = ca.keyserver_config()?;
+                    for keyserver in &config.keyservers {
+                        println!("{}", tr(lang, Msg::Keyserver, &[("keyserver", keyserver)]));
+                    }
+                    println!(
+                        "{}",
+                        tr(lang, Msg::KeyserverQuorum, &[("quorum", &config.quorum)])
+                    );
+                }
+                cli::KeyserversCommand::Set { keyservers, quorum } => {
+                    ca.set_keyserver_config(&KeyserverConfig { keyservers, quorum })?
+                }
+                cli::KeyserversCommand::Unset => {
+                    ca.set_keyserver_config(&KeyserverConfig::default())?
+                }
+            },
+            cli::CaCommand::RevocationPublication { cmd } => match cmd {
+                cli::RevocationPublicationCommand::Show => {
+                    let config = ca.revocation_publication()?;
+                    if !config.enabled() {
+                        println!("{}", tr(lang, Msg::RevocationPublicationDisabled, &[]));
+                    }
+                    for keyserver in &config.keyservers {
+                        println!("{}", tr(lang, Msg::Keyserver, &[("keyserver", keyserver)]));
+                    }
+                    if let Some(path) = &config.wkd_path {
+                        println!(
+                            "{}",
+                            tr(
+                                lang,
+                                Msg::RevocationPublicationWkd,
+                                &[("path", &path.display())]
+                            )
+                        );
+                    }
+                }
+                cli::RevocationPublicationCommand::Set {
+                    keyservers,
+                    wkd_path,
+                } => ca.set_revocation_publication(&RevocationPublication {
+                    keyservers,
+                    wkd_path,
+                })?,
+                cli::RevocationPublicationCommand::Unset => {
+                    ca.set_revocation_publication(&RevocationPublication::default())?
+                }
+            },
+            cli::CaCommand::CertificationKey { cmd } => match cmd {
+                cli::CertificationKeyCommand::Show => {
+                    let selected = ca.certification_key()?;
+                    for fp in ca.certification_keys()? {
+                        if selected.as_ref() == Some(&fp) {
+                            println!(
+                                "{}",
+                                tr(lang, Msg::CertificationKeySelected, &[("fingerprint", &fp)])
+                            );
+                        } else {
+                            println!("{fp}");
+                        }
+                    }
+                    if selected.is_none() {
+                        println!("{}", tr(lang, Msg::CertificationKeyAll, &[]));
+                    }
+                }
+                cli::CertificationKeyCommand::Set { fingerprint } => {
+                    ca.set_certification_key(Some(&fingerprint))?
+                }
+                cli::CertificationKeyCommand::Unset => ca.set_certification_key(None)?,
+            },
+            cli::CaCommand::Doctor {
+                repair_blobs,
+                release,
+            } => {
+                if let Some(cert_id) = release {
+                    ca.cert_quarantine_release(cert_id)?;
+                    println!(
+                        "{}",
+                        tr(lang, Msg::DoctorReleased, &[("cert_id", &cert_id)])
+                    );
+                } else {
+                    let report = ca.check_consistency()?;
                     println!(
-                        "Please verify that this is the correct fingerprint for the \
-            remote CA admin before continuing:"
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::DoctorChecked,
+                            &[
+                                ("certs", &report.certs),
+                                ("revocations", &report.revocations),
+                                ("queue_entries", &report.queue_entries),
+                            ]
+                        )
                     );
+                    if report.is_consistent() {
+                        println!("{}", tr(lang, Msg::DoctorConsistent, &[]));
+                    }
+                    for issue in &report.issues {
+                        println!("{} [{}]: {}", issue.row, issue.kind, issue.message);
+                    }
                     println!();
 
-                    let key = std::fs::read(remote_key_file)?;
-                    pgp::print_cert_info(&key)?;
+                    let reports = ca.db_check_blobs(repair_blobs)?;
+                    if reports.is_empty() {
+                        println!("{}", tr(lang, Msg::DoctorBlobsOk, &[]));
+                    }
+                    for r in reports {
+                        let outcome = match r.outcome {
+                            CertBlobOutcome::Recoverable => Msg::BlobRecoverable,
+                            CertBlobOutcome::Repaired => Msg::BlobRepaired,
+                            CertBlobOutcome::Irrecoverable => Msg::BlobIrrecoverable,
+                            CertBlobOutcome::Quarantined => Msg::BlobQuarantined,
+                        };
+                        println!(
+                            "{}",
+                            tr(
+                                lang,
+                                Msg::DoctorBlob,
+                                &[
+                                    ("cert_id", &r.cert_id),
+                                    ("fingerprint", &r.fingerprint),
+                                    ("error", &r.error),
+                                    ("outcome", &tr(lang, outcome, &[])),
+                                ]
+                            )
+                        );
+                    }
+                }
 
+                let quarantined = ca.certs_quarantined()?;
+                if !quarantined.is_empty() {
                     println!();
+                    println!("{}", tr(lang, Msg::DoctorQuarantined, &[]));
+                    for q in quarantined {
+                        println!(
+                            "{}",
+                            tr(
+                                lang,
+                                Msg::DoctorQuarantinedRow,
+                                &[
+                                    ("cert_id", &q.cert_id),
+                                    ("since", &q.created),
+                                    ("error", &q.error),
+                                ]
+                            )
+                        );
+                    }
+                }
+            }
+            cli::CaCommand::Db { cmd } => match cmd {
+                cli::DbCommand::Retention { cmd } => match cmd {
+                    cli::RetentionCommand::Show => {
+                        let policy = ca.retention_policy()?;
+
+                        let show = |o: Option<u64>| {
+                            o.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string())
+                        };
+
+                        let retention = |r: &Retention| {
+                            tr(
+                                lang,
+                                Msg::Retention,
+                                &[
+                                    ("max_age_days", &show(r.max_age_days)),
+                                    ("max_count", &show(r.max_count)),
+                                ],
+                            )
+                        };
+
+                        print_fields(
+                            lang,
+                            &[
+                                (Msg::RetentionQueueDone, retention(&policy.queue_done)),
+                                (Msg::RetentionCertVersions, retention(&policy.cert_versions)),
+                            ],
+                        );
+                    }
+                    cli::RetentionCommand::Set {
+                        queue_max_age_days,
+                        queue_max_count,
+                        cert_versions_max_age_days,
+                        cert_versions_max_count,
+                    } => {
+                        ca.set_retention_policy(&RetentionPolicy {
+                            queue_done: Retention {
+                                max_age_days: queue_max_age_days,
+                                max_count: queue_max_count,
+                            },
+                            cert_versions: Retention {
+                                max_age_days: cert_versions_max_age_days,
+                                max_count: cert_versions_max_count,
+                            },
+                        })?;
+                    }
+                },
+                cli::DbCommand::Cleanup { dry_run } => {
+                    let report = ca.db_cleanup(dry_run)?;
+
+                    let (queue_done, cert_versions) = if dry_run {
+                        (Msg::CleanupQueuePreview, Msg::CleanupVersionsPreview)
+                    } else {
+                        (Msg::CleanupQueue, Msg::CleanupVersions)
+                    };
+                    println!("{}", tr(lang, queue_done, &[("count", &report.queue_done)]));
+                    println!(
+                        "{}",
+                        tr(lang, cert_versions, &[("count", &report.cert_versions)])
+                    );
+                }
+            },
+            cli::CaCommand::Federation { cmd } => match cmd {
+                cli::FederationCommand::Publish {
+                    domains,
+                    keylist_url,
+                    wkd_method,
+                    contact,
+                    path,
+                } => {
+                    let domains: Vec<&str> = domains.iter().map(|d| d.as_str()).collect();
+
+                    if let Some(path) = path {
+                        let file = ca.export_federation_metadata(
+                            &path,
+                            &domains,
+                            keylist_url.as_deref(),
+                            wkd_method.as_deref(),
+                            contact.as_deref(),
+                        )?;
+                        println!(
+                            "{}",
+                            tr(lang, Msg::FederationWritten, &[("path", &file.display())])
+                        );
+                    } else {
+                        println!(
+                            "{}",
+                            ca.federation_metadata(
+                                &domains,
+                                keylist_url.as_deref(),
+                                wkd_method.as_deref(),
+                                contact.as_deref(),
+                            )?
+                        );
+                    }
+                }
+            },
+            cli::CaCommand::TrustPackage { cmd } => match cmd {
+                cli::TrustPackageCommand::Export { path } => {
+                    let manifest = ca.trust_package_export(&path)?;
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::TrustPackageWritten,
+                            &[("version", &manifest.version), ("path", &path.display())]
+                        )
+                    );
+                }
+                cli::TrustPackageCommand::Version => {
+                    println!("{}", ca.trust_package_version()?);
+                }
+                cli::TrustPackageCommand::Verify {
+                    file,
+                    fingerprint,
+                    newer_than,
+                    unpack,
+                } => {
+                    let package = std::fs::read(&file)?;
+                    let fingerprint = match fingerprint {
+                        Some(fp) => fp,
+                        None => ca.ca_get_cert_pub()?.fingerprint().to_hex(),
+                    };
+
+                    let manifest = match unpack {
+                        Some(dir) => {
+                            Oca::trust_package_unpack(&package, &fingerprint, newer_than, &dir)?
+                        }
+                        None => Oca::trust_package_verify(&package, &fingerprint, newer_than)?,
+                    };
+
                     println!(
-                        "When you've confirmed that the remote key is correct, repeat \
-            this command with the additional parameter '--commit' \
-            to commit the OpenPGP CA bridge to the database."
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::TrustPackage,
+                            &[
+                                ("version", &manifest.version),
+                                ("domain", &manifest.domain),
+                                ("fingerprint", &manifest.ca_fingerprint),
+                                ("created", &manifest.created),
+                            ]
+                        )
+                    );
+                    for f in manifest.files {
+                        println!(" {}", f.path);
+                    }
+                }
+            },
+            cli::CaCommand::Events { cmd } => match cmd {
+                cli::EventsCommand::Show => {
+                    let config = ca.events_config()?;
+
+                    let show = |o: Option<String>| o.unwrap_or_else(|| "-".to_string());
+
+                    print_fields(
+                        lang,
+                        &[
+                            (
+                                Msg::EventsTopicPrefix,
+                                config
+                                    .topic_prefix
+                                    .unwrap_or_else(|| DEFAULT_TOPIC_PREFIX.to_string()),
+                            ),
+                            (Msg::EventsNatsServer, show(config.nats_url)),
+                            (Msg::EventsAmqpBroker, show(config.amqp_url)),
+                            (Msg::EventsAmqpExchange, show(config.amqp_exchange)),
+                        ],
                     );
                 }
+                cli::EventsCommand::Set {
+                    topic_prefix,
+                    nats_url,
+                    amqp_url,
+                    amqp_exchange,
+                } => {
+                    ca.set_events_config(&EventsConfig {
+                        topic_prefix,
+                        nats_url,
+                        amqp_url,
+                        amqp_exchange,
+                    })?;
+                }
+            },
+            cli::CaCommand::Show => print_ca_info(lang, &ca.ca_info()?),
+            cli::CaCommand::WotGraph {
+                format,
+                output,
+                compression,
+            } => {
+                let format = match format.as_str() {
+                    "json" => WotGraphFormat::Json,
+                    _ => WotGraphFormat::Dot,
+                };
+
+                let graph = ca.export_wot_graph(format)?;
+                match output {
+                    Some(output) => {
+                        let data = export_compression(&compression).compress(graph.as_bytes())?;
+                        std::fs::write(output, data)?;
+                    }
+                    None => println!("{}", graph.trim_end()),
+                }
             }
-            cli::BridgeCommand::Revoke { email } => ca.bridge_revoke(&email)?,
-            cli::BridgeCommand::List => ca.list_bridges()?,
-            cli::BridgeCommand::Export { email } => ca.print_bridges(email)?,
-        },
-        cli::Commands::Wkd { cmd } => match cmd {
-            cli::WkdCommand::Export { path } => {
-                ca.export_wkd(ca.domainname(), &path)?;
+            cli::CaCommand::Smoketest => {
+                let steps = ca.smoketest()?;
+
+                for step in &steps {
+                    println!("{:>20}: {}", step.name, step.status);
+                }
+
+                if steps
+                    .iter()
+                    .any(|s| matches!(s.status, SmoketestStatus::Failed(_)))
+                {
+                    return Err(anyhow::anyhow!("Smoke test failed"));
+                }
             }
-        },
+            cli::CaCommand::Timeline { days } => {
+                let events = ca.expiry_timeline(days)?;
+                if events.is_empty() {
+                    println!("{}", tr(lang, Msg::TimelineEmpty, &[("days", &days)]));
+                }
 
-        cli::Commands::Keylist { cmd } => match cmd {
-            cli::KeyListCommand::Export {
-                path,
-                signature_uri,
-                force,
+                for e in events {
+                    print!("{}  {}: {}", e.time.format("%F"), e.kind, e.fingerprint);
+                    if let Some(label) = e.label {
+                        print!(" ({label})");
+                    }
+                    match e.user_id {
+                        Some(uid) => println!(", {uid}"),
+                        None => println!(),
+                    }
+                }
+            }
+            cli::CaCommand::Fingerprint { format } => {
+                let format = match format.as_str() {
+                    "plain" => FingerprintFormat::Plain,
+                    "qr" => FingerprintFormat::Qr,
+                    _ => FingerprintFormat::Spaced,
+                };
+
+                println!("{}", ca.ca_fingerprint(format)?);
+            }
+            cli::CaCommand::VerifyRemote { fingerprint } => {
+                if ca.ca_verify_fingerprint(&fingerprint)? {
+                    println!("{}", tr(lang, Msg::FingerprintMatches, &[]));
+                } else {
+                    return Err(anyhow::anyhow!(
+                        "The fingerprint does NOT match the key of this CA ({})",
+                        ca.ca_fingerprint(FingerprintFormat::Spaced)?
+                    ));
+                }
+            }
+            cli::CaCommand::Private => println!("{}", ca.ca_private_key()?),
+
+            cli::CaCommand::ReCertify {
+                pubkey_file_old: cert_file_old,
+                validity_days,
             } => {
-                ca.export_keylist(path, signature_uri, force)?;
+                let cert_old = std::fs::read(cert_file_old)?;
+                ca.ca_re_certify(&cert_old, validity_days)?;
             }
-        },
-        cli::Commands::Update { cmd } => match cmd {
-            cli::UpdateCommand::Keyserver {} => ca.update_from_keyserver()?,
-            cli::UpdateCommand::Wkd {} => ca.update_from_wkd()?,
-        },
-    }
 
-    Ok(())
+            cli::CaCommand::Rekey {
+                name,
+                cipher_suite,
+                expire_in,
+                validity_days,
+            } => {
+                let params = CaRekeyParams {
+                    name,
+                    cipher_suite,
+                    validity_days: expire_in,
+                    certification_validity_days: validity_days,
+                };
+
+                // (This consumes the Oca instance)
+                let (ca, report) = ca.ca_rekey(&params)?;
+
+                return print_rekey_report(lang, &ca, &report);
+            }
+
+            cli::CaCommand::Split { cmd } => match cmd {
+                // (These consume the Oca instance)
+                cli::SplitCommand::Into { front, back } => return ca.ca_split_into(&front, &back),
+                cli::SplitCommand::Merge { back } => return ca.ca_merge_split(&back),
+
+                cli::SplitCommand::Export { file } => match ca.ca_split_export(file)? {
+                    0 => println!("{}", tr(lang, Msg::SplitExportEmpty, &[])),
+                    n => println!("{}", tr(lang, Msg::SplitExported, &[("count", &n)])),
+                },
+
+                cli::SplitCommand::Certify {
+                    import,
+                    export,
+                    batch,
+                } => ca.ca_split_certify(import, export, &mut |id, created, qe| {
+                    if batch {
+                        return Ok(true);
+                    }
+
+                    // interactive mode
+                    print_split_request(lang, id, created, qe)?;
+
+                    // FIXME: show if a previous certification by this CA exists
+                    // and inform the CA operator, if so.
+                    // [see sequoia-sq:src/commands/mod.rs:active_certification]
+
+                    println!();
+                    println!("{}", tr(lang, Msg::SplitCertifyPrompt, &[]));
+
+                    let key_event = get_raw_key()?;
+                    let approved = key_event.code == KeyCode::Char('y')
+                        && key_event.modifiers == KeyModifiers::NONE;
+                    if !approved {
+                        println!();
+                        println!("{}", tr(lang, Msg::SplitCertifySkipped, &[]));
+                    }
+
+                    println!();
+                    println!();
+
+                    Ok(approved)
+                })?,
+
+                cli::SplitCommand::Import { import: file } => {
+                    let imported = ca.ca_split_import(file)?;
+                    println!("{}", tr(lang, Msg::SplitImported, &[("count", &imported)]));
+                }
+
+                cli::SplitCommand::Pull {
+                    url,
+                    token_file,
+                    file,
+                } => {
+                    let requests = split_http_client(&ca)?
+                        .get(format!("{}/split/requests", url.trim_end_matches('/')))
+                        .bearer_auth(read_token(&token_file)?)
+                        .send()?
+                        .error_for_status()?
+                        .text()?;
+
+                    std::fs::write(file, requests)?;
+                }
+
+                cli::SplitCommand::Push {
+                    url,
+                    token_file,
+                    file,
+                } => {
+                    let resp = split_http_client(&ca)?
+                        .post(format!("{}/split/responses", url.trim_end_matches('/')))
+                        .bearer_auth(read_token(&token_file)?)
+                        .body(std::fs::read(file)?)
+                        .send()?
+                        .error_for_status()?
+                        .text()?;
+                    let resp: serde_json::Value = serde_json::from_str(&resp)?;
+
+                    println!(
+                        "{}",
+                        tr(lang, Msg::SplitPushed, &[("count", &resp["imported"])])
+                    );
+                }
+
+                cli::SplitCommand::ShowQueue {} => {
+                    for (id, created, qe) in ca.ca_split_queue()? {
+                        print_split_request(lang, id, created, &qe)?;
+                        println!();
+                    }
+                }
+
+                cli::SplitCommand::RekeyImport {
+                    file,
+                    validity_days,
+                } => {
+                    let new_ca = std::fs::read(file)?;
+
+                    // (This consumes the Oca instance)
+                    let (ca, report) = ca.ca_rekey_import(&new_ca, validity_days)?;
+
+                    return print_rekey_report(lang, &ca, &report);
+                }
+            },
+        },
+        cli::Commands::Bridge { cmd } => match cmd {
+            cli::BridgeCommand::New {
+                email,
+                commit,
+                from_metadata: Some(source),
+                fingerprint,
+                profile,
+                ..
+            } => {
+                let doc = read_source(&source, ca.tor_proxy()?.is_some())?;
+
+                if commit {
+                    let (email, fp) = ca.add_bridge_from_metadata(
+                        email.as_deref(),
+                        &doc,
+                        fingerprint.as_deref(),
+                        profile.parse()?,
+                    )?;
+
+                    println!("{}\n", tr(lang, Msg::BridgeAdded, &[("email", &email)]));
+                    println!(
+                        "{}\n",
+                        tr(lang, Msg::BridgeFingerprint, &[("fingerprint", &fp)])
+                    );
+                } else {
+                    let metadata = Oca::federation_metadata_verify(&doc, fingerprint.as_deref())?;
+
+                    println!("{}", tr(lang, Msg::BridgeDryRun, &[]));
+                    println!();
+
+                    println!("{}", tr(lang, Msg::BridgeMetadata, &[]));
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::BridgeMetadataDomains,
+                            &[("domains", &metadata.domains.join(", "))]
+                        )
+                    );
+                    if let Some(keylist_url) = &metadata.keylist_url {
+                        println!(
+                            "{}",
+                            tr(lang, Msg::BridgeMetadataKeylist, &[("url", keylist_url)])
+                        );
+                    }
+                    if let Some(wkd_method) = &metadata.wkd_method {
+                        println!(
+                            "{}",
+                            tr(lang, Msg::BridgeMetadataWkd, &[("method", wkd_method)])
+                        );
+                    }
+                    if let Some(contact) = &metadata.contact {
+                        println!(
+                            "{}",
+                            tr(lang, Msg::BridgeMetadataContact, &[("contact", contact)])
+                        );
+                    }
+                    println!();
+
+                    println!("{}", tr(lang, Msg::BridgeVerify, &[]));
+                    println!();
+
+                    let cert = pgp::to_cert(metadata.ca_cert.as_bytes())?;
+                    print_cert_info(lang, &ca.cert_info(&cert)?);
+
+                    println!();
+                    println!("{}", tr(lang, Msg::BridgeCommit, &[]));
+                }
+            }
+            cli::BridgeCommand::New {
+                email,
+                scope,
+                scope_regex,
+                unscoped,
+                force,
+                remote_key_file,
+                commit,
+                from_metadata: None,
+                fingerprint: _,
+                profile,
+            } => {
+                // clap requires remote_key_file if from_metadata is unset
+                let remote_key_file = remote_key_file.unwrap();
+
+                let scope = if unscoped {
+                    BridgeScope::Unscoped
+                } else if !scope_regex.is_empty() {
+                    BridgeScope::Regexes(scope_regex)
+                } else if let Some(domain) = scope {
+                    BridgeScope::Domain { domain, force }
+                } else {
+                    BridgeScope::RemoteDomain
+                };
+
+                if commit {
+                    let (email, fp) = ca.add_bridge_with_scope(
+                        email.as_deref(),
+                        &remote_key_file,
+                        &scope,
+                        profile.parse()?,
+                    )?;
+
+                    println!("{}\n", tr(lang, Msg::BridgeAdded, &[("email", &email)]));
+                    println!(
+                        "{}\n",
+                        tr(lang, Msg::BridgeFingerprint, &[("fingerprint", &fp)])
+                    );
+                } else {
+                    println!("{}", tr(lang, Msg::BridgeDryRun, &[]));
+                    println!();
+
+                    println!("{}", tr(lang, Msg::BridgeVerify, &[]));
+                    println!();
+
+                    let key = pgp::to_cert(&std::fs::read(remote_key_file)?)?;
+                    print_cert_info(lang, &ca.cert_info(&key)?);
+
+                    println!();
+                    println!("{}", tr(lang, Msg::BridgeCommit, &[]));
+                }
+            }
+            cli::BridgeCommand::Revoke { email } => {
+                let revocation = ca.bridge_revoke(&email)?;
+                println!(
+                    "{}\n{}",
+                    tr(lang, Msg::BridgeRevocation, &[("email", &email)]),
+                    Oca::revoc_to_armored(&revocation)?
+                );
+            }
+            cli::BridgeCommand::ExportBundle { email } => {
+                print!("{}", ca.export_bridge_bundle(&email)?)
+            }
+            cli::BridgeCommand::ImportBundle {
+                file,
+                fingerprint,
+                profile,
+            } => {
+                let bundle = std::fs::read(file)?;
+                let import =
+                    ca.import_bridge_bundle(&bundle, fingerprint.as_deref(), profile.parse()?)?;
+
+                println!(
+                    "{}",
+                    tr(lang, Msg::BridgeTsigImported, &[("email", &import.email)])
+                );
+                if import.bridge_created {
+                    println!(
+                        "{}\n",
+                        tr(lang, Msg::BridgeAdded, &[("email", &import.email)])
+                    );
+                }
+                println!(
+                    "{}\n",
+                    tr(
+                        lang,
+                        Msg::BridgeFingerprint,
+                        &[("fingerprint", &import.fingerprint)]
+                    )
+                );
+            }
+            cli::BridgeCommand::List => ca.list_bridges()?,
+            cli::BridgeCommand::Export { email, compat } => {
+                ca.print_bridges(email, export_compat(&compat))?
+            }
+        },
+        cli::Commands::Wkd { cmd } => match cmd {
+            cli::WkdCommand::Export {
+                path,
+                minimize,
+                compat,
+                only_changed,
+                email,
+            } => {
+                let emails: Vec<&str> = email.iter().map(String::as_str).collect();
+                let compat = export_compat(&compat);
+
+                if only_changed {
+                    let written = if emails.is_empty() {
+                        ca.export_wkd_changed(ca.domainname(), &path, minimize, compat)?
+                    } else {
+                        ca.export_wkd_changed_for_emails(
+                            ca.domainname(),
+                            &path,
+                            &emails,
+                            minimize,
+                            compat,
+                        )?
+                    };
+                    for file in written {
+                        println!(
+                            "{}",
+                            tr(lang, Msg::WkdUpdated, &[("path", &file.display())])
+                        );
+                    }
+                } else if emails.is_empty() {
+                    ca.export_wkd(ca.domainname(), &path, minimize, compat)?;
+                } else {
+                    ca.export_wkd_for_emails(ca.domainname(), &path, &emails, minimize, compat)?;
+                }
+            }
+        },
+
+        cli::Commands::Keylist { cmd } => match cmd {
+            cli::KeyListCommand::Export {
+                path,
+                signature_uri,
+                force,
+            } => {
+                ca.export_keylist(path, signature_uri, force)?;
+            }
+            cli::KeyListCommand::ExportAll { path, force } => {
+                for name in ca.export_keylists(&path, force)? {
+                    println!("{}", tr(lang, Msg::KeylistExported, &[("name", &name)]));
+                }
+            }
+            cli::KeyListCommand::List => {
+                for list in ca.keylists()? {
+                    println!("{}", list.name);
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::KeylistSignatureUri,
+                            &[("uri", &list.signature_uri)]
+                        )
+                    );
+                    if !list.filter.domains.is_empty() {
+                        println!(
+                            "{}",
+                            tr(
+                                lang,
+                                Msg::KeylistDomains,
+                                &[("domains", &list.filter.domains.join(", "))]
+                            )
+                        );
+                    }
+                    if !list.filter.emails.is_empty() {
+                        println!(
+                            "{}",
+                            tr(
+                                lang,
+                                Msg::KeylistEmails,
+                                &[("emails", &list.filter.emails.join(", "))]
+                            )
+                        );
+                    }
+                    if list.filter.exclude_inactive {
+                        println!("{}", tr(lang, Msg::KeylistExcludesInactive, &[]));
+                    }
+                    if let Some(group) = &list.filter.group {
+                        println!("{}", tr(lang, Msg::KeylistGroup, &[("group", group)]));
+                    }
+                    if list.filter.include_roles {
+                        println!("{}", tr(lang, Msg::KeylistIncludesRoles, &[]));
+                    }
+                }
+            }
+            cli::KeyListCommand::Set {
+                name,
+                signature_uri,
+                domain,
+                email,
+                exclude_inactive,
+                group,
+                include_roles,
+            } => ca.keylist_set(KeylistConfig {
+                name,
+                signature_uri,
+                filter: KeylistFilter {
+                    domains: domain,
+                    emails: email,
+                    exclude_inactive,
+                    group,
+                    include_roles,
+                },
+            })?,
+            cli::KeyListCommand::Remove { name } => ca.keylist_remove(&name)?,
+        },
+        cli::Commands::Group { cmd } => match cmd {
+            cli::GroupCommand::List => {
+                for group in ca.groups()? {
+                    let members = ca.group_users(&group.name)?.len();
+                    let summary = tr(
+                        lang,
+                        Msg::GroupSummary,
+                        &[("name", &group.name), ("count", &members)],
+                    );
+                    match &group.description {
+                        Some(description) => println!("{summary}: {description}"),
+                        None => println!("{summary}"),
+                    }
+                }
+            }
+            cli::GroupCommand::New { name, description } => {
+                ca.group_new(&name, description.as_deref())?;
+            }
+            cli::GroupCommand::Delete { name } => ca.group_delete(&name)?,
+            cli::GroupCommand::Show { name } => {
+                for user in ca.group_users(&name)? {
+                    match &user.name {
+                        Some(name) => println!("{name}"),
+                        None => println!("{}", tr(lang, Msg::NoName, &[])),
+                    }
+                    for cert in ca.get_certs_by_user(&user)? {
+                        println!("  {}", cert.fingerprint);
+                    }
+                }
+            }
+            cli::GroupCommand::AddMember { name, fingerprint } => {
+                if !ca.group_add_member(&name, &fingerprint)? {
+                    println!("{}", tr(lang, Msg::GroupAlreadyMember, &[("name", &name)]));
+                }
+            }
+            cli::GroupCommand::RemoveMember { name, fingerprint } => {
+                if !ca.group_remove_member(&name, &fingerprint)? {
+                    println!("{}", tr(lang, Msg::GroupNotMember, &[("name", &name)]));
+                }
+            }
+            cli::GroupCommand::Certify { name, days } => {
+                for cert in ca.group_certify(&name, days)? {
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::GroupCertified,
+                            &[("fingerprint", &cert.fingerprint)]
+                        )
+                    );
+                }
+            }
+            cli::GroupCommand::RefreshCertifications {
+                name,
+                threshold_days,
+                days,
+            } => ca.group_refresh_ca_certifications(&name, threshold_days, days)?,
+            cli::GroupCommand::Export {
+                name,
+                output,
+                compression,
+                minimize,
+                format,
+                compat,
+            } => {
+                let format = CertFormat::from(format);
+
+                ca.export_group_certring(
+                    &name,
+                    &output,
+                    minimize,
+                    format,
+                    export_compat(&compat),
+                    export_compression(&compression),
+                )?;
+            }
+            cli::GroupCommand::ExportWkd {
+                name,
+                path,
+                minimize,
+                compat,
+            } => ca.export_group_wkd(
+                &name,
+                ca.domainname(),
+                &path,
+                minimize,
+                export_compat(&compat),
+            )?,
+            cli::GroupCommand::ExportKeylist {
+                name,
+                path,
+                signature_uri,
+                force,
+            } => ca.export_group_keylist(&name, &path, &signature_uri, force)?,
+            cli::GroupCommand::Notify { name, text, output } => {
+                let notification = ca.group_notification(&name, &std::fs::read_to_string(text)?)?;
+                std::fs::write(output, notification.mail.to_string())?;
+
+                for recipient in notification.recipients {
+                    println!("{recipient}");
+                }
+            }
+        },
+        cli::Commands::KeyProfile { cmd } => match cmd {
+            cli::KeyProfileCommand::List => {
+                for profile in ca.key_profiles()? {
+                    let mut subkeys = vec![];
+                    if profile.encryption_subkey {
+                        subkeys.push(tr(lang, Msg::SubkeyEncryption, &[]));
+                    }
+                    if profile.signing_subkey {
+                        subkeys.push(tr(lang, Msg::SubkeySigning, &[]));
+                    }
+                    if profile.authentication_subkey {
+                        subkeys.push(tr(lang, Msg::SubkeyAuthentication, &[]));
+                    }
+
+                    println!("{}", profile.name);
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::ProfileCipherSuite,
+                            &[("cipher_suite", &profile.cipher_suite)]
+                        )
+                    );
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::ProfileSubkeys,
+                            &[("subkeys", &subkeys.join(", "))]
+                        )
+                    );
+                    if let Some(days) = profile.expiration_days {
+                        println!("{}", tr(lang, Msg::ProfileExpiry, &[("days", &days)]));
+                    }
+                    if profile.password {
+                        let msg = if profile.password_short_words {
+                            Msg::ProfilePasswordShortWords
+                        } else {
+                            Msg::ProfilePassword
+                        };
+                        println!("{}", tr(lang, msg, &[("words", &profile.password_words)]));
+                    } else {
+                        println!("{}", tr(lang, Msg::ProfileNoPassword, &[]));
+                    }
+                }
+            }
+            cli::KeyProfileCommand::Set {
+                name,
+                cipher_suite,
+                encryption_subkey,
+                signing_subkey,
+                authentication_subkey,
+                expiration_days,
+                no_password,
+                password_words,
+                password_short_words,
+            } => ca.key_profile_set(KeyProfile {
+                name,
+                cipher_suite,
+                encryption_subkey,
+                signing_subkey,
+                authentication_subkey,
+                expiration_days,
+                password: !no_password,
+                password_words,
+                password_short_words,
+            })?,
+            cli::KeyProfileCommand::Remove { name } => ca.key_profile_remove(&name)?,
+        },
+        cli::Commands::Queue { cmd } => match cmd {
+            cli::QueueCommand::List { all } => {
+                let proposals = if all {
+                    ca.proposals()?
+                } else {
+                    ca.proposals_pending()?
+                };
+
+                for p in proposals {
+                    print!(
+                        "{:>5}  {}  {}  {}",
+                        p.id,
+                        p.created.format("%F %T"),
+                        p.change,
+                        p.status
+                    );
+                    if let Some(reason) = p.reason {
+                        print!(": {reason}");
+                    }
+                    println!();
+                }
+            }
+            cli::QueueCommand::Approve { id } => {
+                let p = ca.proposal_approve(id)?;
+                println!(
+                    "{}",
+                    tr(lang, Msg::ProposalApplied, &[("change", &p.change)])
+                );
+            }
+            cli::QueueCommand::Reject { id, reason } => {
+                ca.proposal_reject(id, reason.as_deref())?
+            }
+        },
+        cli::Commands::Update { cmd } => match cmd {
+            cli::UpdateCommand::Keyserver { sync } => {
+                let report = ca.sync_certs(SyncSource::Keyserver, &sync_options(&sync))?;
+                print_sync_report(lang, &report);
+            }
+            cli::UpdateCommand::Wkd { sync } => {
+                let report = ca.sync_certs(SyncSource::Wkd, &sync_options(&sync))?;
+                print_sync_report(lang, &report);
+            }
+            cli::UpdateCommand::Failing { source, days } => {
+                let source = source.map(|s| s.parse()).transpose()?;
+                let failing = ca.sync_failing(source, Duration::from_secs(days * 24 * 60 * 60))?;
+
+                for f in failing {
+                    println!("{} ({})", f.fingerprint, f.source);
+                    if let Some(since) = f.failing_since {
+                        println!(
+                            "{}",
+                            tr(
+                                lang,
+                                Msg::SyncFailingSince,
+                                &[("time", &since.format("%F %T"))]
+                            )
+                        );
+                    }
+                    match f.last_success {
+                        Some(t) => println!(
+                            "{}",
+                            tr(lang, Msg::SyncLastSuccess, &[("time", &t.format("%F %T"))])
+                        ),
+                        None => println!("{}", tr(lang, Msg::SyncNeverSucceeded, &[])),
+                    }
+                    if let Some(error) = f.last_error {
+                        println!("{}", tr(lang, Msg::SyncLastError, &[("error", &error)]));
+                    }
+                }
+            }
+        },
+        cli::Commands::Util { .. } => {
+            // handled separately, above
+            unreachable!()
+        }
+    }
+
+    ca.close()
+}
+
+/// Print the labeled `fields`, with the labels right-aligned
+fn print_fields(lang: Language, fields: &[(Msg, String)]) {
+    let labels: Vec<_> = fields.iter().map(|(msg, _)| tr(lang, *msg, &[])).collect();
+    let width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+    for (label, (_, value)) in labels.iter().zip(fields) {
+        println!("{label:>width$}: {value}");
+    }
+}
+
+fn print_key(lang: Language, label: Msg, key: &cert_info::Key) {
+    println!("{}", tr(lang, label, &[("fingerprint", &key.fingerprint)]));
+    println!(
+        "{}",
+        tr(
+            lang,
+            Msg::KeyAlgorithm,
+            &[("algorithm", &key.algo), ("bits", &key.bits)]
+        )
+    );
+    if let Some(flags) = &key.flags {
+        println!("{}", tr(lang, Msg::KeyFlags, &[("flags", flags)]));
+    }
+    println!(
+        "{}",
+        tr(
+            lang,
+            Msg::KeyCreated,
+            &[("time", &key.creation_time.format("%F %T"))]
+        )
+    );
+    if let Some(exp) = key.expiration_time {
+        println!(
+            "{}",
+            tr(lang, Msg::KeyExpires, &[("time", &exp.format("%F %T"))])
+        );
+    }
+    for r in key.revocations.iter().flatten() {
+        print_revoked(lang, r.reason.as_deref());
+    }
+    if let Some(e) = &key.policy_error {
+        println!("{}", tr(lang, Msg::PolicyRejected, &[("error", e)]));
+    }
+}
+
+/// Print a revocation of a key or User ID, with its reason
+fn print_revoked(lang: Language, reason: Option<&str>) {
+    let reason = match reason {
+        Some(reason) => reason.to_string(),
+        None => tr(lang, Msg::NoReason, &[]),
+    };
+    println!("{}", tr(lang, Msg::Revoked, &[("reason", &reason)]));
+}
+
+/// The User ID `raw`, for display
+fn user_id(lang: Language, raw: Option<&str>) -> String {
+    match raw {
+        Some(raw) => raw.to_string(),
+        None => tr(lang, Msg::NotUtf8, &[]),
+    }
+}
+
+fn print_ca_info(lang: Language, info: &CaInfo) {
+    let created = info.created.format("%F %T %Z").to_string();
+
+    println!(
+        "{}",
+        tr(lang, Msg::CaInfoDomain, &[("domain", &info.domainname)])
+    );
+    println!(
+        "{}",
+        tr(
+            lang,
+            Msg::CaInfoFingerprint,
+            &[("fingerprint", &info.fingerprint)]
+        )
+    );
+    println!("{}", tr(lang, Msg::CaInfoCreated, &[("created", &created)]));
+    println!(
+        "{}",
+        tr(lang, Msg::CaInfoBackend, &[("backend", &info.backend)])
+    );
+}
+
+/// Print a request in the queue of a split mode instance
+fn print_split_request(
+    lang: Language,
+    id: i32,
+    created: DateTime<Utc>,
+    qe: &QueueEntry,
+) -> Result<()> {
+    let created = created.format("%F %T %Z").to_string();
+    let fingerprint = qe.cert()?.fingerprint().to_hex();
+
+    match qe {
+        QueueEntry::CertificationReq(cr) => {
+            println!(
+                "{}",
+                tr(
+                    lang,
+                    Msg::SplitRequestCertification,
+                    &[("id", &id), ("created", &created)]
+                )
+            );
+            println!(
+                "{}",
+                tr(lang, Msg::SplitRequestKey, &[("fingerprint", &fingerprint)])
+            );
+            for uid in cr.user_ids() {
+                println!("{}", tr(lang, Msg::SplitRequestUserId, &[("userid", uid)]));
+            }
+            match cr.validity() {
+                Some(CertificationValidity::Days(days)) => {
+                    println!(
+                        "{}",
+                        tr(lang, Msg::SplitRequestValidDays, &[("days", &days)])
+                    )
+                }
+                Some(CertificationValidity::Until(until)) => {
+                    let until = until.format("%F %T %Z").to_string();
+                    println!(
+                        "{}",
+                        tr(lang, Msg::SplitRequestValidUntil, &[("until", &until)])
+                    )
+                }
+                None => println!("{}", tr(lang, Msg::SplitRequestNoExpiration, &[])),
+            }
+            if !cr.profile().is_default() {
+                println!(
+                    "{}",
+                    tr(
+                        lang,
+                        Msg::SplitRequestProfile,
+                        &[("profile", &cr.profile())]
+                    )
+                );
+            }
+            if let Some(n) = cr.notation() {
+                println!(
+                    "{}",
+                    tr(
+                        lang,
+                        Msg::SplitRequestNotation,
+                        &[("name", &n.name), ("value", &n.value)]
+                    )
+                );
+            }
+        }
+        QueueEntry::BridgeReq(br) => {
+            println!(
+                "{}",
+                tr(
+                    lang,
+                    Msg::SplitRequestBridge,
+                    &[("id", &id), ("created", &created)]
+                )
+            );
+            println!(
+                "{}",
+                tr(
+                    lang,
+                    Msg::SplitRequestRemoteKey,
+                    &[("fingerprint", &fingerprint)]
+                )
+            );
+            if br.scope_regexes().is_empty() {
+                println!("{}", tr(lang, Msg::SplitRequestUnscoped, &[]));
+            }
+            for scope in br.scope_regexes() {
+                println!("{}", tr(lang, Msg::SplitRequestScope, &[("scope", scope)]));
+            }
+            if !br.profile().is_default() {
+                println!(
+                    "{}",
+                    tr(
+                        lang,
+                        Msg::SplitRequestProfile,
+                        &[("profile", &br.profile())]
+                    )
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Wait for a key press on the terminal
+fn get_raw_key() -> Result<KeyEvent> {
+    crossterm::terminal::enable_raw_mode()?;
+
+    // Loop until we get a KeyEvent
+    loop {
+        let event = read()?;
+
+        if let Event::Key(key_event) = event {
+            crossterm::terminal::disable_raw_mode()?;
+
+            return Ok(key_event);
+        }
+    }
+}
+
+fn print_cert_info(lang: Language, info: &CertInfo) {
+    print_key(lang, Msg::PrimaryKey, &info.primary);
+    for sk in &info.subkeys {
+        print_key(lang, Msg::Subkey, sk);
+    }
+
+    for uid in &info.user_ids {
+        let raw = user_id(lang, uid.raw.as_deref());
+        println!("{}", tr(lang, Msg::UserId, &[("userid", &raw)]));
+        for r in uid.revocations.iter().flatten() {
+            print_revoked(lang, r.reason.as_deref());
+        }
+        if let Some(e) = &uid.policy_error {
+            println!("{}", tr(lang, Msg::PolicyRejected, &[("error", e)]));
+        }
+    }
+}
+
+fn print_user_dossier(lang: Language, dossier: &UserDossier) -> Result<()> {
+    let name = match &dossier.user.name {
+        Some(name) => name.clone(),
+        None => tr(lang, Msg::NoName, &[]),
+    };
+    println!("{}", tr(lang, Msg::DossierUser, &[("name", &name)]));
+    if !dossier.user.wkd_publish {
+        println!("{}", tr(lang, Msg::DossierWkdOptOut, &[]));
+    }
+    if let Some(notes) = &dossier.user.notes {
+        println!("{}", tr(lang, Msg::DossierNotes, &[("notes", notes)]));
+    }
+    for (name, value) in &dossier.tags {
+        println!(
+            "{}",
+            tr(lang, Msg::DossierTag, &[("name", name), ("value", value)])
+        );
+    }
+
+    for cd in &dossier.certs {
+        println!();
+        println!(
+            "{}",
+            tr(
+                lang,
+                Msg::DossierKey,
+                &[("fingerprint", &cd.cert.fingerprint)]
+            )
+        );
+        if cd.cert.delisted {
+            println!("{}", tr(lang, Msg::DossierDelisted, &[]));
+        } else {
+            let channels: Vec<_> = ExportChannel::ALL
+                .iter()
+                .filter(|c| cd.cert.is_delisted(**c))
+                .map(ExportChannel::name)
+                .collect();
+            if !channels.is_empty() {
+                println!(
+                    "{}",
+                    tr(
+                        lang,
+                        Msg::DossierDelistedOn,
+                        &[("channels", &channels.join(", "))]
+                    )
+                );
+            }
+        }
+        if cd.cert.inactive {
+            println!("{}", tr(lang, Msg::DossierDeactivated, &[]));
+        }
+        if let Some(notes) = &cd.cert.notes {
+            println!("{}", tr(lang, Msg::DossierNotes, &[("notes", notes)]));
+        }
+        for (name, value) in &cd.tags {
+            println!(
+                "{}",
+                tr(lang, Msg::DossierTag, &[("name", name), ("value", value)])
+            );
+        }
+        if cd.tsig_on_ca {
+            println!("{}", tr(lang, Msg::DossierTsig, &[]));
+        } else {
+            println!("{}", tr(lang, Msg::DossierNoTsig, &[]));
+        }
+
+        print_key(lang, Msg::PrimaryKey, &cd.info.primary);
+        for sk in &cd.info.subkeys {
+            print_key(lang, Msg::Subkey, sk);
+        }
+
+        for uid in &cd.info.user_ids {
+            let raw = user_id(lang, uid.raw.as_deref());
+            println!("{}", tr(lang, Msg::UserId, &[("userid", &raw)]));
+
+            let status = if cd
+                .certification
+                .expired
+                .iter()
+                .any(|u| String::from_utf8_lossy(u.value()) == raw)
+            {
+                Msg::DossierCertificationExpired
+            } else if cd
+                .certification
+                .certified
+                .iter()
+                .any(|u| String::from_utf8_lossy(u.value()) == raw)
+            {
+                Msg::DossierCertified
+            } else {
+                Msg::DossierNotCertified
+            };
+            println!("{}", tr(lang, status, &[]));
+
+            for r in uid.revocations.iter().flatten() {
+                print_revoked(lang, r.reason.as_deref());
+            }
+            if let Some(e) = &uid.policy_error {
+                println!("{}", tr(lang, Msg::PolicyRejected, &[("error", e)]));
+            }
+        }
+
+        for rev in &cd.revocations {
+            let (reason, time) = Oca::revocation_details(rev)?;
+            let time = time
+                .map(|t| {
+                    chrono::DateTime::<chrono::Utc>::from(t)
+                        .format("%F %T")
+                        .to_string()
+                })
+                .unwrap_or_else(|| tr(lang, Msg::UnknownTime, &[]));
+            let state = if rev.published {
+                Msg::DossierRevocationApplied
+            } else {
+                Msg::DossierRevocationPending
+            };
+            println!(
+                "{}",
+                tr(
+                    lang,
+                    state,
+                    &[("hash", &rev.hash), ("reason", &reason), ("time", &time)]
+                )
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the publication status of an applied revocation, per channel
+fn print_revocation_publication(lang: Language, rev: &models::Revocation) {
+    let channels = [
+        ("Keyserver", rev.keyserver_published, &rev.keyserver_error),
+        ("WKD", rev.wkd_published, &rev.wkd_error),
+    ];
+    for (channel, published, error) in channels {
+        match (published, error) {
+            (_, Some(error)) => println!(
+                "{}",
+                tr(
+                    lang,
+                    Msg::PublicationFailed,
+                    &[("channel", &channel), ("error", error)]
+                )
+            ),
+            (Some(time), None) => println!(
+                "{}",
+                tr(
+                    lang,
+                    Msg::Published,
+                    &[("channel", &channel), ("time", &time.format("%F %T"))]
+                )
+            ),
+            (None, None) => {}
+        }
+    }
+}
+
+fn print_rekey_report(lang: Language, ca: &Oca, report: &CaRekeyReport) -> Result<()> {
+    println!(
+        "{}",
+        tr(
+            lang,
+            Msg::RekeyReplaced,
+            &[
+                ("old", &report.old_fingerprint),
+                ("new", &report.new_fingerprint),
+            ]
+        )
+    );
+
+    for fp in &report.recertified {
+        println!("{}", tr(lang, Msg::Recertified, &[("fingerprint", fp)]));
+    }
+
+    if !report.tsig_needed.is_empty() {
+        println!();
+        println!("{}", tr(lang, Msg::RekeyTsigNeeded, &[]));
+        for cert in &report.tsig_needed {
+            let name = ca.cert_get_users(cert)?.and_then(|u| u.name);
+            let emails: Vec<_> = ca.emails_get(cert)?.into_iter().map(|e| e.addr).collect();
+
+            println!(
+                "{} {} <{}>",
+                cert.fingerprint,
+                name.unwrap_or_default(),
+                emails.join(", ")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// An http client for the split mode HTTP transport.
+///
+/// If the CA routes network operations through Tor, the transport is not
+/// available (it would connect directly).
+fn split_http_client(ca: &Oca) -> Result<reqwest::blocking::Client> {
+    if ca.tor_proxy()?.is_some() {
+        return Err(anyhow::anyhow!(
+            "This CA routes network operations through Tor, please transfer the \
+             certification requests and responses as files ('ca split export' and \
+             'ca split import' on the front instance)"
+        ));
+    }
+
+    Ok(reqwest::blocking::Client::new())
+}
+
+/// Read a bearer token from a file (surrounding whitespace is ignored)
+fn read_token(file: &Path) -> Result<String> {
+    let token = std::fs::read_to_string(file)?.trim().to_string();
+    if token.is_empty() {
+        return Err(anyhow::anyhow!("The token file {:?} is empty", file));
+    }
+
+    Ok(token)
+}
+
+/// Read data from an http(s) URL, or from a file.
+///
+/// If the CA routes network operations through Tor, only files are read.
+fn read_source(source: &str, tor: bool) -> Result<Vec<u8>> {
+    if source.starts_with("https://") || source.starts_with("http://") {
+        if tor {
+            return Err(anyhow::anyhow!(
+                "This CA routes network operations through Tor, please download {} \
+                 and pass the file name",
+                source
+            ));
+        }
+
+        let resp = reqwest::blocking::get(source)?.error_for_status()?;
+        Ok(resp.bytes()?.to_vec())
+    } else {
+        Ok(std::fs::read(source)?)
+    }
+}
+
+/// Map the value of a `--compat` argument to an ExportCompat
+fn export_compat(compat: &str) -> ExportCompat {
+    match compat {
+        "thunderbird" => ExportCompat::Thunderbird,
+        "sequoia" => ExportCompat::Sequoia,
+        _ => ExportCompat::GnuPG22,
+    }
+}
+
+fn export_compression(compression: &str) -> ExportCompression {
+    match compression {
+        "gzip" => ExportCompression::Gzip,
+        "zstd" => ExportCompression::Zstd,
+        _ => ExportCompression::None,
+    }
+}
+
+/// Write `data` to the file `output`, or to stdout
+/// The end of the day `date` (YYYY-MM-DD), in UTC
+fn end_of_day(date: &str) -> Result<DateTime<Utc>> {
+    Ok(NaiveDate::parse_from_str(date, "%Y-%m-%d")?
+        .succ_opt()
+        .ok_or_else(|| anyhow::anyhow!("Invalid date '{date}'"))?
+        .and_time(NaiveTime::MIN)
+        .and_utc())
+}
+
+fn write_output(output: Option<PathBuf>, data: &[u8]) -> Result<()> {
+    match output {
+        Some(path) => std::fs::write(path, data)?,
+        None => std::io::stdout().write_all(data)?,
+    }
+
+    Ok(())
+}
+
+fn util(lang: Language, cmd: cli::UtilCommand) -> Result<()> {
+    match cmd {
+        cli::UtilCommand::Armor { input, output } => {
+            let armored = pgp::armor(&std::fs::read(input)?)?;
+            write_output(output, armored.as_bytes())?;
+        }
+        cli::UtilCommand::Dearmor { input, output } => {
+            let binary = pgp::dearmor(&std::fs::read(input)?)?;
+            write_output(output, &binary)?;
+        }
+        cli::UtilCommand::ExtractSignatures {
+            input,
+            issuer,
+            output,
+        } => {
+            let sigs = pgp::signatures_by_issuer(&std::fs::read(input)?, &issuer)?;
+            if sigs.is_empty() {
+                return Err(anyhow::anyhow!("No signatures by {} found", issuer));
+            }
+
+            write_output(output, pgp::signatures_to_armored(&sigs)?.as_bytes())?;
+        }
+        cli::UtilCommand::VerifyChain {
+            input,
+            email,
+            ca_fingerprint,
+        } => {
+            let (user, ca) = pgp::verify_chain(&std::fs::read(input)?, &email, &ca_fingerprint)?;
+
+            println!(
+                "{}",
+                tr(
+                    lang,
+                    Msg::ChainVerified,
+                    &[
+                        ("fingerprint", &user.fingerprint()),
+                        ("email", &email),
+                        ("ca_fingerprint", &ca.fingerprint()),
+                    ]
+                )
+            );
+        }
+        cli::UtilCommand::SplitKeyring { input, path } => {
+            let certs = pgp::armored_keyring_to_certs(&std::fs::read(input)?)?;
+
+            for cert in certs {
+                let armored = if cert.is_tsk() {
+                    pgp::cert_to_armored_private_key(&cert)?
+                } else {
+                    pgp::cert_to_armored(&cert)?
+                };
+
+                let filename = path.join(format!("{}.asc", cert.fingerprint().to_hex()));
+                std::fs::write(&filename, armored)?;
+
+                println!("{}", filename.display());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn sync_options(args: &cli::SyncArgs) -> SyncOptions {
+    SyncOptions {
+        min_interval: Duration::from_secs(args.interval_hours * 60 * 60),
+        limit: args.limit,
+        delay: Duration::from_millis(args.delay_ms),
+    }
+}
+
+/// Hand the key material of a new user over to the operator: the private key
+/// is printed to stdout (or written to `key_file`), the password is printed
+/// to stderr (or, in `minimal` mode, to stdout before the key).
+fn print_new_user_key(
+    lang: Language,
+    key: &NewUserKey,
+    name: Option<&str>,
+    minimal: bool,
+    key_file: Option<&Path>,
+) -> Result<()> {
+    if let Some(key_file) = key_file {
+        key.write_private_key(key_file)?;
+    }
+
+    if minimal {
+        // short format (convenient for use with the 'pass' tool)
+        if let Some(pass) = &key.password {
+            println!("{pass}");
+        }
+        if key_file.is_none() {
+            println!("{}", key.private_key);
+        }
+    } else {
+        if let Some(name) = name {
+            eprintln!("{}\n", tr(lang, Msg::UserKeyCreatedFor, &[("name", &name)]));
+        } else {
+            eprintln!("{}\n", tr(lang, Msg::UserKeyCreated, &[]));
+        }
+
+        match key_file {
+            Some(key_file) => eprintln!(
+                "{}\n",
+                tr(lang, Msg::UserKeyWritten, &[("path", &key_file.display())])
+            ),
+            None => println!("{}", key.private_key),
+        }
+
+        if let Some(pass) = &key.password {
+            eprintln!(
+                "{}\n",
+                tr(lang, Msg::UserKeyPassword, &[("password", pass)])
+            );
+        } else {
+            eprintln!("{}\n", tr(lang, Msg::UserKeyNoPassword, &[]));
+        }
+    }
+
+    Ok(())
+}
+
+/// Log events of the library to stderr.
+///
+/// By default, only warnings and errors are logged. `quiet` limits logging
+/// to errors, each `verbose` step adds a more detailed level.
+fn init_logging(quiet: bool, verbose: u8) {
+    let level = match (quiet, verbose) {
+        (true, _) => tracing::Level::ERROR,
+        (false, 0) => tracing::Level::WARN,
+        (false, 1) => tracing::Level::INFO,
+        (false, 2) => tracing::Level::DEBUG,
+        (false, _) => tracing::Level::TRACE,
+    };
+
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_target(false)
+        .without_time()
+        .init();
+}
+
+fn print_sync_report(lang: Language, report: &SyncReport) {
+    println!(
+        "\n{}",
+        tr(
+            lang,
+            Msg::SyncReport,
+            &[
+                ("updated", &report.updated),
+                ("unchanged", &report.unchanged),
+                ("failed", &report.failed),
+                ("skipped", &report.skipped),
+            ]
+        )
+    );
 }
@@ -4,10 +4,46 @@
 // This file is part of OpenPGP CA
 // https://gitlab.com/openpgp-ca/openpgp-ca
 
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
-use openpgp_ca_lib::pgp::CipherSuite;
+use clap::{Parser, Subcommand, ValueEnum};
+use openpgp_ca_lib::pgp::{CipherSuite, KeyVersion};
+use openpgp_ca_lib::types::CertFormat;
+
+/// Parse a validity period ("365", "30d", "6w", "18m", "2y") into days
+fn parse_expire_in(s: &str) -> Result<u64, String> {
+    let (num, factor) = match s.char_indices().last() {
+        Some((i, 'd')) => (&s[..i], 1),
+        Some((i, 'w')) => (&s[..i], 7),
+        Some((i, 'm')) => (&s[..i], 30),
+        Some((i, 'y')) => (&s[..i], 365),
+        _ => (s, 1),
+    };
+
+    match num.parse::<u64>() {
+        Ok(n) if n > 0 => Ok(n * factor),
+        _ => Err(format!(
+            "'{s}' is not a validity period (e.g. \"365\", \"30d\", \"6w\", \"18m\", \"2y\")"
+        )),
+    }
+}
+
+/// Encoding of exported keys
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Armored,
+    Binary,
+}
+
+impl From<ExportFormat> for CertFormat {
+    fn from(format: ExportFormat) -> Self {
+        match format {
+            ExportFormat::Armored => CertFormat::Armored,
+            ExportFormat::Binary => CertFormat::Binary,
+        }
+    }
+}
 
 #[derive(Parser)]
 #[clap(
@@ -20,6 +56,29 @@ pub struct Cli {
     #[clap(name = "filename", short = 'd', long = "database")]
     pub database: Option<String>,
 
+    #[clap(
+        long = "progress",
+        global = true,
+        help = "Show the progress of long operations (on stderr)"
+    )]
+    pub progress: bool,
+
+    #[clap(
+        long = "quiet",
+        global = true,
+        conflicts_with = "verbose",
+        help = "Only log errors (on stderr)"
+    )]
+    pub quiet: bool,
+
+    #[clap(
+        long = "verbose",
+        global = true,
+        action = clap::ArgAction::Count,
+        help = "Log more details of operations (on stderr), repeat for more"
+    )]
+    pub verbose: u8,
+
     #[clap(subcommand)]
     pub cmd: Commands,
 }
@@ -51,18 +110,36 @@ pub enum Commands {
         #[clap(subcommand)]
         cmd: KeyListCommand,
     },
+    /// Manage groups of users, and perform operations per group
+    Group {
+        #[clap(subcommand)]
+        cmd: GroupCommand,
+    },
+    /// Manage key profiles (parameters for generating user keys)
+    KeyProfile {
+        #[clap(subcommand)]
+        cmd: KeyProfileCommand,
+    },
+    /// Manage changes that were proposed via restd in approval mode
+    Queue {
+        #[clap(subcommand)]
+        cmd: QueueCommand,
+    },
     /// Update
     Update {
         #[clap(subcommand)]
         cmd: UpdateCommand,
     },
+    /// OpenPGP data conversion helpers (these don't use the CA database)
+    Util {
+        #[clap(subcommand)]
+        cmd: UtilCommand,
+    },
     //    /// Manage Directories
     //    Directory {
     //        #[clap(subcommand)]
     //        cmd: DirCommand,
     //    },
-    //    /// Manage key-profiles
-    //    KeyProfile {}
 }
 
 #[derive(Subcommand)]
@@ -165,8 +242,12 @@ pub enum MigrateCommand {
 pub enum CaCommand {
     /// Create CA
     Init {
-        #[clap(long = "domain", help = "CA domain name")]
-        domain: String,
+        #[clap(
+            long = "domain",
+            help = "CA domain name",
+            required_unless_present = "interactive"
+        )]
+        domain: Option<String>,
 
         #[clap(short = 'n', long = "name", help = "Descriptive User Name")]
         name: Option<String>,
@@ -174,8 +255,27 @@ pub enum CaCommand {
         #[clap(long = "cipher-suite", help = "Set cipher suite")]
         cipher_suite: Option<CipherSuite>,
 
+        /// OpenPGP version of the generated CA key: 4 (default), or 6
+        /// (RFC 9580, not supported yet).
+        #[clap(long = "key-version", default_value = "4")]
+        key_version: KeyVersion,
+
+        /// The generated CA key expires after this period: a number of days,
+        /// or a number with the suffix d, w, m or y (e.g. "5y").
+        #[clap(long = "expire-in", value_parser = parse_expire_in)]
+        expire_in: Option<u64>,
+
+        /// Guided setup: asks for all settings, shows a summary and
+        /// initializes the CA after confirmation.
+        #[clap(
+            long = "interactive",
+            conflicts_with_all = ["domain", "name", "cipher_suite", "key_version", "expire_in"],
+            help = "Guided setup of a new CA instance"
+        )]
+        interactive: bool,
+
         #[clap(subcommand)]
-        backend: Backend,
+        backend: Option<Backend>,
     },
     /// Migrate a softkey CA instance onto an OpenPGP card.
     ///
@@ -193,11 +293,35 @@ pub enum CaCommand {
         backend: SetBackendCommand,
     },
     /// Export CA public key
-    Export,
+    Export {
+        #[clap(
+            long = "strip-stale-tsigs",
+            help = "Omit trust signatures by superseded or revoked user keys"
+        )]
+        strip_stale_tsigs: bool,
+    },
     /// Generate a set of revocations for the CA key
     Revocations {
         #[clap(short = 'o', long = "output", help = "File to export to")]
         output: PathBuf,
+
+        #[clap(
+            long = "compat",
+            value_parser = ["gnupg-2.2", "thunderbird", "sequoia"],
+            default_value = "gnupg-2.2",
+            help = "Armor the revocations as this client expects"
+        )]
+        compat: String,
+    },
+
+    /// Export a signed list of all revoked user keys
+    RevocationList {
+        #[clap(
+            short = 'o',
+            long = "output",
+            help = "File to export to (the signature is written to <output>.sig)"
+        )]
+        output: PathBuf,
     },
 
     /// Import trust signature for CA Key
@@ -205,8 +329,73 @@ pub enum CaCommand {
         #[clap(help = "File that contains the tsigned CA Key")]
         cert_file: PathBuf,
     },
+    /// Trust signatures by user keys on the CA Key
+    Tsigs {
+        #[clap(subcommand)]
+        cmd: TsigsCommand,
+    },
+
+    /// Reconcile the certifications by the CA key in a keyring (e.g. a
+    /// keyserver dump) with the certifications in the CA database
+    CheckCertifications {
+        #[clap(
+            short = 'i',
+            long = "input",
+            help = "File that contains the keyring to check"
+        )]
+        input: PathBuf,
+    },
     /// Show CA information
     Show,
+    /// Print the CA fingerprint (e.g. for onboarding documentation)
+    Fingerprint {
+        #[clap(
+            long = "format",
+            value_parser = ["plain", "spaced", "qr"],
+            default_value = "spaced",
+            help = "Representation of the fingerprint ('qr' prints the OPENPGP4FPR: URI to encode in a QR code)"
+        )]
+        format: String,
+    },
+    /// Check a fingerprint (e.g. as read out during a bridge setup ceremony) against the CA key
+    VerifyRemote {
+        #[clap(help = "Fingerprint to check (with or without spaces)")]
+        fingerprint: String,
+    },
+    /// Print the trust topology (certifications, user tsigs, bridges) as a graph
+    WotGraph {
+        #[clap(
+            long = "format",
+            value_parser = ["dot", "json"],
+            default_value = "dot",
+            help = "Output format (Graphviz DOT, or JSON nodes and edges)"
+        )]
+        format: String,
+
+        #[clap(short = 'o', long = "output", help = "File to export to")]
+        output: Option<PathBuf>,
+
+        #[clap(
+            long = "compression",
+            value_parser = ["none", "gzip", "zstd"],
+            default_value = "none",
+            requires = "output",
+            help = "Compress the output file"
+        )]
+        compression: String,
+    },
+    /// Check the deployment end-to-end, on a temporary clone of the CA (e.g. after an upgrade)
+    Smoketest,
+    /// Show upcoming expirations of the CA cert, user certs, CA certifications and bridge tsigs
+    Timeline {
+        #[clap(
+            short = 'd',
+            long = "days",
+            help = "Show expirations within 'days' days",
+            default_value = "90"
+        )]
+        days: u64,
+    },
     /// Print CA private key
     Private,
 
@@ -228,204 +417,1813 @@ pub enum CaCommand {
         validity_days: u64,
     },
 
+    /// Replace the CA key with a new key (e.g. after a compromise of the old key).
+    ///
+    /// User certs that were certified by the old CA key are re-certified with the
+    /// new key. Users who had tsigned the old CA cert need to tsign the new one.
+    ///
+    /// On a split-mode back instance, only the CA key is replaced: import the new
+    /// CA cert into the front instance with "ca split rekey-import".
+    Rekey {
+        #[clap(short = 'n', long = "name", help = "Descriptive User Name")]
+        name: Option<String>,
+
+        #[clap(long = "cipher-suite", help = "Set cipher suite")]
+        cipher_suite: Option<CipherSuite>,
+
+        /// The new CA key expires after this period: a number of days,
+        /// or a number with the suffix d, w, m or y (e.g. "5y").
+        #[clap(long = "expire-in", value_parser = parse_expire_in)]
+        expire_in: Option<u64>,
+
+        #[clap(
+            short = 'v',
+            long = "validity",
+            help = "Validity of the new certifications in days",
+            default_value = "365"
+        )]
+        validity_days: u64,
+    },
+
     /// Split mode commands
     Split {
         #[clap(subcommand)]
         cmd: SplitCommand,
     },
+
+    /// Minimum requirements for imported user keys
+    KeyPolicy {
+        #[clap(subcommand)]
+        cmd: KeyPolicyCommand,
+    },
+
+    /// URI identifiers in User IDs (e.g. machine identities) that the CA certifies
+    UriPolicy {
+        #[clap(subcommand)]
+        cmd: UriPolicyCommand,
+    },
+
+    /// Keys and domains that are never imported or certified
+    Blocklist {
+        #[clap(subcommand)]
+        cmd: BlocklistCommand,
+    },
+
+    /// Tamper-evident log of the certifications and revocations issued by the CA
+    CtLog {
+        #[clap(subcommand)]
+        cmd: CtLogCommand,
+    },
+
+    /// Notation that marks the certifications issued by the CA
+    NotationPolicy {
+        #[clap(subcommand)]
+        cmd: NotationPolicyCommand,
+    },
+
+    /// Adjustments of the standard policy for cryptographic algorithms
+    CryptoPolicy {
+        #[clap(subcommand)]
+        cmd: CryptoPolicyCommand,
+    },
+
+    /// Show or set if notification emails are signed with the CA key (PGP/MIME)
+    ///
+    /// Split-mode front instances can't sign emails.
+    MailSigning {
+        #[clap(help = "Enable or disable signing (true or false)")]
+        enabled: Option<bool>,
+    },
+
+    /// Show or set the language of user-facing output (can be overridden with the
+    /// environment variable OPENPGP_CA_LANG)
+    Language {
+        #[clap(help = "Language code (en, de)")]
+        language: Option<String>,
+    },
+
+    /// Contact and policy information published in the CA cert
+    Config {
+        #[clap(subcommand)]
+        cmd: ConfigCommand,
+    },
+
+    /// Publishing of CA events to message queues
+    Events {
+        #[clap(subcommand)]
+        cmd: EventsCommand,
+    },
+
+    /// Federation metadata for partner CAs
+    Federation {
+        #[clap(subcommand)]
+        cmd: FederationCommand,
+    },
+
+    /// Signed, versioned trust packages (CA cert, bridges, keylists, policy)
+    TrustPackage {
+        #[clap(subcommand)]
+        cmd: TrustPackageCommand,
+    },
+
+    /// Database maintenance
+    Db {
+        #[clap(subcommand)]
+        cmd: DbCommand,
+    },
+
+    /// Anonymous usage statistics (only shared on explicit request)
+    Stats {
+        #[clap(subcommand)]
+        cmd: StatsCommand,
+    },
+
+    /// Route WKD and keyserver lookups through Tor (requires the "tor" feature)
+    Tor {
+        #[clap(subcommand)]
+        cmd: TorCommand,
+    },
+
+    /// Keyservers that certs are updated from
+    Keyservers {
+        #[clap(subcommand)]
+        cmd: KeyserversCommand,
+    },
+
+    /// Publish revoked keys to keyservers and WKD as soon as revocations are applied
+    RevocationPublication {
+        #[clap(subcommand)]
+        cmd: RevocationPublicationCommand,
+    },
+
+    /// Select the CA (sub)key that issues certifications (if the CA has several)
+    CertificationKey {
+        #[clap(subcommand)]
+        cmd: CertificationKeyCommand,
+    },
+
+    /// Check the database for consistency, and for cert rows that can't be parsed
+    Doctor {
+        #[clap(
+            long = "repair-blobs",
+            help = "Recover damaged certs by lenient parsing, quarantine the others"
+        )]
+        repair_blobs: bool,
+
+        #[clap(
+            long = "release",
+            value_name = "CERT_ID",
+            conflicts_with = "repair_blobs",
+            help = "Release a manually fixed cert row from quarantine"
+        )]
+        release: Option<i32>,
+    },
 }
 
 #[derive(Subcommand)]
-pub enum UserCommand {
-    /// Add User (create new Key-Pair)
-    Add {
+pub enum DbCommand {
+    /// Retention policy for done queue entries and cert versions
+    Retention {
+        #[clap(subcommand)]
+        cmd: RetentionCommand,
+    },
+    /// Remove the rows that the retention policy doesn't retain
+    Cleanup {
+        #[clap(long = "dry-run", help = "Only report how many rows would be removed")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum StatsCommand {
+    /// Show the anonymous usage statistics of this CA
+    Show,
+    /// Send the usage statistics, signed with the CA key, to the OpenPGP CA project
+    Share {
+        #[clap(long = "endpoint", help = "URL to send the statistics to")]
+        endpoint: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TorCommand {
+    /// Show the Tor proxy that this CA uses
+    Show,
+    /// Route network lookups through the SOCKS5 proxy of a Tor client
+    /// (e.g. a system Tor daemon, or "arti proxy")
+    Set {
+        #[clap(help = "Address of the SOCKS5 proxy (e.g. 127.0.0.1:9050)")]
+        proxy: SocketAddr,
+    },
+    /// Use direct connections for network lookups
+    Unset,
+}
+
+#[derive(Subcommand)]
+pub enum KeyserversCommand {
+    /// Show the keyservers that certs are updated from
+    Show,
+    /// Replace the keyservers that certs are updated from
+    Set {
         #[clap(
-            short = 'e',
-            long = "email",
+            long = "keyserver",
             required = true,
-            number_of_values = 1,
-            help = "Email address"
+            help = "Keyserver to look up certs on (e.g. hkps://keys.openpgp.org)"
         )]
-        email: Vec<String>,
+        keyservers: Vec<String>,
 
-        #[clap(short = 'n', long = "name", help = "Descriptive User Name")]
-        name: Option<String>,
+        #[clap(
+            long = "quorum",
+            default_value = "1",
+            help = "Number of keyservers that must return a packet before it is merged in"
+        )]
+        quorum: usize,
+    },
+    /// Update certs from keys.openpgp.org only (the default)
+    Unset,
+}
 
+#[derive(Subcommand)]
+pub enum RevocationPublicationCommand {
+    /// Show the channels that revoked keys are published through
+    Show,
+    /// Replace the channels that revoked keys are published through
+    Set {
         #[clap(
-            short = 'm',
-            long = "minimal",
-            help = "Minimal output (for consumption by tools such as 'pass')"
+            long = "keyserver",
+            help = "Keyserver to push revoked keys to (e.g. hkps://keys.openpgp.org)"
         )]
-        minimal: bool,
+        keyservers: Vec<String>,
 
-        /// Set an explicit password for the generated user key
-        /// (a filename, or - for stdin).
-        #[clap(long = "password-file")]
-        password_file: Option<String>,
+        #[clap(
+            long = "wkd-path",
+            help = "WKD directory structure to update the entries of revoked keys in"
+        )]
+        wkd_path: Option<PathBuf>,
+    },
+    /// Don't publish revoked keys when revocations are applied
+    Unset,
+}
 
-        #[clap(long = "cipher-suite", help = "Set cipher suite")]
-        cipher_suite: Option<CipherSuite>,
+#[derive(Subcommand)]
+pub enum CertificationKeyCommand {
+    /// Show the certification capable keys of the CA, and which of them is selected
+    Show,
+    /// Certify only with this key
+    Set {
+        #[clap(help = "Fingerprint of a certification capable key of the CA")]
+        fingerprint: String,
+    },
+    /// Certify with each certification capable key
+    Unset,
+}
+
+#[derive(Subcommand)]
+pub enum RetentionCommand {
+    /// Show the retention policy
+    Show,
+    /// Replace the retention policy (unset limits are not enforced)
+    Set {
+        #[clap(
+            long = "queue-max-age-days",
+            help = "Maximum age of done queue entries in days"
+        )]
+        queue_max_age_days: Option<u64>,
 
         #[clap(
-            long = "encryption",
-            help = "Enable creating an encryption subkey",
-            default_value_t = true,
-            action = clap::ArgAction::Set,
+            long = "queue-max-count",
+            help = "Maximum number of done queue entries"
         )]
-        enable_encryption_subkey: bool,
+        queue_max_count: Option<u64>,
 
         #[clap(
-            long = "signing",
-            help = "Enable creating an signing subkey",
-            default_value_t = true,
-            action = clap::ArgAction::Set,
+            long = "cert-versions-max-age-days",
+            help = "Maximum age of previous cert versions in days"
         )]
-        enable_signing_subkey: bool,
+        cert_versions_max_age_days: Option<u64>,
 
         #[clap(
-            long = "authentication",
-            help = "Enable creating an authentication subkey",
-            default_value_t = false,
-            action = clap::ArgAction::Set,
+            long = "cert-versions-max-count",
+            help = "Maximum number of previous versions per cert"
         )]
-        enable_authentication_subkey: bool,
+        cert_versions_max_count: Option<u64>,
     },
+}
+
+#[derive(Subcommand)]
+pub enum TsigsCommand {
+    /// List trust signatures on the CA Key, and the status of the user keys
+    /// that made them
+    List,
+    /// List current user keys that still need to tsign the CA Key (of users
+    /// who tsigned it with a superseded or revoked key)
+    Missing,
+}
+
+#[derive(Subcommand)]
+pub enum KeyPolicyCommand {
+    /// Show the key policy
+    Show,
+    /// Replace the key policy (unset options are not enforced)
+    Set {
+        #[clap(long = "min-rsa-bits", help = "Minimum size of RSA keys")]
+        min_rsa_bits: Option<usize>,
 
-    /// Add Revocation Certificate
-    AddRevocation {
-        #[clap(help = "File that contains a revocation cert")]
-        revocation_file: PathBuf,
-    },
-    /// Bulk checks on Users
-    Check {
-        #[clap(subcommand)]
-        cmd: UserCheckSubcommand,
-    },
-    /// Import User (use existing Public Key)
-    Import {
         #[clap(
-            short = 'e',
-            long = "email",
-            required = true,
+            long = "allowed-algorithm",
             number_of_values = 1,
-            help = "Email address"
+            help = "Allowed public key algorithm (rsa, dsa, elgamal, ecdsa, eddsa, ecdh)"
         )]
-        email: Vec<String>,
+        allowed_algorithms: Vec<String>,
 
         #[clap(
-            short = 'f',
-            long = "key-file",
-            help = "File that contains the User's Public Key"
+            long = "require-encryption-subkey",
+            help = "Require an encryption subkey"
         )]
-        cert_file: PathBuf,
+        require_encryption_subkey: bool,
 
-        #[clap(short = 'n', long = "name", help = "Descriptive User Name")]
-        name: Option<String>,
+        #[clap(long = "require-signing-subkey", help = "Require a signing subkey")]
+        require_signing_subkey: bool,
 
         #[clap(
-            short = 'r',
-            long = "revocation-file",
-            number_of_values = 1,
-            help = "File that contains a revocation cert for this user"
+            long = "max-expiry-days",
+            help = "Maximum remaining validity of keys in days"
         )]
-        revocation_file: Vec<PathBuf>,
-    },
-    /// Update User (use existing Public Key)
-    Update {
+        max_expiry_days: Option<u64>,
+
         #[clap(
-            short = 'f',
-            long = "key-file",
-            help = "File that contains the User's Public Key"
+            long = "max-encryption-subkey-age-days",
+            help = "Maximum age of the newest encryption subkey in days"
         )]
-        cert_file: PathBuf,
+        max_encryption_subkey_age_days: Option<u64>,
     },
-    /// Export User Public Key (bulk, if no email address is given)
+}
+
+#[derive(Subcommand)]
+pub enum UriPolicyCommand {
+    /// Show the URI policy
+    Show,
+    /// Replace the URI policy
+    Set {
+        #[clap(
+            long = "allow-prefix",
+            number_of_values = 1,
+            help = "URI prefix that may be certified (e.g. spiffe://cluster)"
+        )]
+        allowed_prefixes: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BlocklistCommand {
+    /// Show the active blocklist entries
+    List,
+    /// Block a key, or the email addresses of a domain
+    Add {
+        #[clap(
+            long = "fingerprint",
+            conflicts_with = "domain",
+            required_unless_present = "domain",
+            help = "Fingerprint of a primary key or subkey"
+        )]
+        fingerprint: Option<String>,
+
+        #[clap(
+            long = "domain",
+            help = "Domain of email addresses (use '*.example.org' for all subdomains)"
+        )]
+        domain: Option<String>,
+
+        #[clap(long = "reason", help = "Reason for blocking")]
+        reason: Option<String>,
+    },
+    /// Remove an entry from the blocklist
+    Remove {
+        #[clap(help = "Id of the blocklist entry")]
+        id: i32,
+    },
+    /// Show all additions to and removals from the blocklist
+    Log,
+}
+
+#[derive(Subcommand)]
+pub enum CtLogCommand {
+    /// Show the entries of the log
+    List,
+    /// Export the log as JSON lines
+    Export {
+        #[clap(
+            short = 'o',
+            long = "output",
+            help = "File to export to (default: stdout)"
+        )]
+        output: Option<PathBuf>,
+    },
+    /// Check that the log is an unbroken hash chain
+    Verify {
+        #[clap(
+            long = "file",
+            help = "Check this exported log, instead of the log in the CA database"
+        )]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum NotationPolicyCommand {
+    /// Show the notation policy
+    Show,
+    /// Add a notation to all new certifications by the CA
+    Set {
+        #[clap(
+            long = "name",
+            help = "Notation name (default: openpgp-ca@notations.sequoia-pgp.org)"
+        )]
+        name: Option<String>,
+
+        #[clap(long = "value", help = "Notation value (default: certified=<domain>)")]
+        value: Option<String>,
+
+        #[clap(
+            long = "required",
+            help = "Only count certifications with the notation when checking certs"
+        )]
+        required: bool,
+    },
+    /// Don't add a notation to new certifications
+    Disable,
+}
+
+#[derive(Subcommand)]
+pub enum CryptoPolicyCommand {
+    /// Show the crypto policy
+    Show,
+    /// Replace the crypto policy (without options, the standard policy is used unchanged)
+    Set {
+        #[clap(
+            long = "sha1-cutoff",
+            help = "Accept SHA-1 in signatures created before this date (YYYY-MM-DD)"
+        )]
+        sha1_cutoff: Option<String>,
+
+        #[clap(
+            long = "reject-hash",
+            number_of_values = 1,
+            help = "Reject a hash algorithm (e.g. sha224)"
+        )]
+        rejected_hashes: Vec<String>,
+
+        #[clap(
+            long = "reject-algorithm",
+            number_of_values = 1,
+            help = "Reject a public key algorithm (e.g. rsa2048, brainpoolp256)"
+        )]
+        rejected_algorithms: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Show the CA config
+    Show,
+    /// Set a value (this re-signs the CA cert)
+    Set {
+        #[clap(
+            value_parser = ["policy-uri", "security-contact", "cps-uri"],
+            help = "Setting to change"
+        )]
+        key: String,
+
+        #[clap(help = "New value (e.g. https://example.org/ca-policy)")]
+        value: String,
+    },
+    /// Remove a value (this re-signs the CA cert)
+    Unset {
+        #[clap(
+            value_parser = ["policy-uri", "security-contact", "cps-uri"],
+            help = "Setting to remove"
+        )]
+        key: String,
+    },
+    /// Show all previous changes
+    History,
+}
+
+#[derive(Subcommand)]
+pub enum EventsCommand {
+    /// Show the event publishing configuration
+    Show,
+    /// Replace the event publishing configuration (unset servers are not used)
+    Set {
+        #[clap(
+            long = "topic-prefix",
+            help = "Prefix for topic names (default: 'openpgp-ca')"
+        )]
+        topic_prefix: Option<String>,
+
+        #[clap(long = "nats-url", help = "URL of a NATS server")]
+        nats_url: Option<String>,
+
+        #[clap(long = "amqp-url", help = "URL of an AMQP broker")]
+        amqp_url: Option<String>,
+
+        #[clap(
+            long = "amqp-exchange",
+            help = "AMQP exchange to publish to (default: the default exchange)"
+        )]
+        amqp_exchange: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum FederationCommand {
+    /// Generate the signed federation metadata document of this CA
+    Publish {
+        #[clap(
+            long = "domain",
+            number_of_values = 1,
+            help = "Additional domain of this CA"
+        )]
+        domains: Vec<String>,
+
+        #[clap(long = "keylist-url", help = "URL of the keylist of this CA")]
+        keylist_url: Option<String>,
+
+        #[clap(
+            long = "wkd-method",
+            value_parser = ["advanced", "direct"],
+            help = "WKD method that keys of this CA are published with"
+        )]
+        wkd_method: Option<String>,
+
+        #[clap(long = "contact", help = "Contact address for bridge requests")]
+        contact: Option<String>,
+
+        #[clap(
+            short = 'p',
+            long = "path",
+            help = "Web root to write the document to (in .well-known), default: stdout"
+        )]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TrustPackageCommand {
+    /// Generate a new trust package version
+    Export {
+        #[clap(
+            short = 'p',
+            long = "path",
+            help = "Directory to write the package and 'latest.json' to"
+        )]
+        path: PathBuf,
+    },
+
+    /// Show the version of the most recent trust package
+    Version,
+
+    /// Check a trust package
+    Verify {
+        #[clap(short = 'f', long = "file", help = "Trust package file")]
+        file: PathBuf,
+
+        #[clap(
+            long = "fingerprint",
+            help = "Fingerprint of the CA key (default: this CA)"
+        )]
+        fingerprint: Option<String>,
+
+        #[clap(
+            long = "newer-than",
+            help = "Only accept packages with a version higher than this"
+        )]
+        newer_than: Option<u64>,
+
+        #[clap(long = "unpack", help = "Directory to unpack the package into")]
+        unpack: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum UserCommand {
+    /// Add User (create new Key-Pair)
+    Add {
+        #[clap(
+            short = 'e',
+            long = "email",
+            required = true,
+            number_of_values = 1,
+            help = "Email address"
+        )]
+        email: Vec<String>,
+
+        #[clap(short = 'n', long = "name", help = "Descriptive User Name")]
+        name: Option<String>,
+
+        #[clap(
+            short = 'm',
+            long = "minimal",
+            help = "Minimal output (for consumption by tools such as 'pass')"
+        )]
+        minimal: bool,
+
+        #[clap(
+            long = "key-file",
+            help = "Write the private key to this new file (readable only by the current user), \
+                    instead of stdout"
+        )]
+        key_file: Option<PathBuf>,
+
+        /// Set an explicit password for the generated user key
+        /// (a filename, or - for stdin).
+        #[clap(long = "password-file")]
+        password_file: Option<String>,
+
+        #[clap(long = "cipher-suite", help = "Set cipher suite")]
+        cipher_suite: Option<CipherSuite>,
+
+        /// OpenPGP version of the generated user key: 4, or 6 (RFC 9580,
+        /// not supported yet). By default, the version of the CA key.
+        #[clap(long = "key-version")]
+        key_version: Option<KeyVersion>,
+
+        /// The generated user key expires after this period: a number of
+        /// days, or a number with the suffix d, w, m or y (e.g. "2y").
+        #[clap(long = "expire-in", value_parser = parse_expire_in)]
+        expire_in: Option<u64>,
+
+        /// Generate the key according to this key profile (instead of the
+        /// cipher suite, expiration and subkey settings).
+        #[clap(
+            long = "profile",
+            conflicts_with_all = [
+                "cipher_suite",
+                "key_version",
+                "expire_in",
+                "enable_encryption_subkey",
+                "enable_signing_subkey",
+                "enable_authentication_subkey",
+            ]
+        )]
+        profile: Option<String>,
+
+        #[clap(
+            long = "encryption",
+            help = "Enable creating an encryption subkey",
+            default_value_t = true,
+            action = clap::ArgAction::Set,
+        )]
+        enable_encryption_subkey: bool,
+
+        #[clap(
+            long = "signing",
+            help = "Enable creating an signing subkey",
+            default_value_t = true,
+            action = clap::ArgAction::Set,
+        )]
+        enable_signing_subkey: bool,
+
+        #[clap(
+            long = "authentication",
+            help = "Enable creating an authentication subkey",
+            default_value_t = false,
+            action = clap::ArgAction::Set,
+        )]
+        enable_authentication_subkey: bool,
+    },
+
+    /// Add User, and move the new key to an OpenPGP card (the private key
+    /// is never written to disk)
+    AddOnCard {
+        #[clap(
+            long = "card",
+            help = "Identifier of the (empty) OpenPGP card, e.g. \"FFFE:01234567\""
+        )]
+        ident: String,
+
+        #[clap(
+            short = 'e',
+            long = "email",
+            required = true,
+            number_of_values = 1,
+            help = "Email address"
+        )]
+        email: Vec<String>,
+
+        #[clap(short = 'n', long = "name", help = "Descriptive User Name")]
+        name: Option<String>,
+
+        #[clap(long = "cipher-suite", help = "Set cipher suite")]
+        cipher_suite: Option<CipherSuite>,
+
+        /// The generated user key expires after this period: a number of
+        /// days, or a number with the suffix d, w, m or y (e.g. "2y").
+        #[clap(long = "expire-in", value_parser = parse_expire_in)]
+        expire_in: Option<u64>,
+    },
+
+    /// Add a service account or role key (create new Key-Pair with role User IDs, without email)
+    AddRole {
+        #[clap(
+            short = 'r',
+            long = "role",
+            required = true,
+            number_of_values = 1,
+            help = "Role User ID (e.g. \"Backup signing key 2024\")"
+        )]
+        role: Vec<String>,
+
+        #[clap(short = 'n', long = "name", help = "Descriptive User Name")]
+        name: Option<String>,
+
+        #[clap(
+            short = 'm',
+            long = "minimal",
+            help = "Minimal output (for consumption by tools such as 'pass')"
+        )]
+        minimal: bool,
+
+        #[clap(
+            long = "key-file",
+            help = "Write the private key to this new file (readable only by the current user), \
+                    instead of stdout"
+        )]
+        key_file: Option<PathBuf>,
+
+        /// Set an explicit password for the generated key
+        /// (a filename, or - for stdin).
+        #[clap(long = "password-file")]
+        password_file: Option<String>,
+
+        #[clap(
+            long = "profile",
+            help = "Generate the key according to this key profile"
+        )]
+        profile: Option<String>,
+    },
+
+    /// Add Revocation Certificate
+    AddRevocation {
+        #[clap(help = "File that contains a revocation cert")]
+        revocation_file: PathBuf,
+    },
+    /// Bulk checks on Users
+    Check {
+        #[clap(subcommand)]
+        cmd: UserCheckSubcommand,
+    },
+    /// Show everything the CA knows about a user: keys, User IDs, certification status,
+    /// revocations on file
+    Show {
+        #[clap(help = "Email address of the user")]
+        email: String,
+    },
+    /// Show the User IDs and (sub)keys of a key, as evaluated by the policy of the CA
+    Inspect {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+    },
+    /// Certify User IDs of an existing User key
+    Certify {
+        #[clap(help = "Fingerprint of the User key")]
+        fingerprint: String,
+
+        #[clap(
+            short = 'e',
+            long = "email",
+            required = true,
+            number_of_values = 1,
+            help = "Email address of a User ID to certify"
+        )]
+        email: Vec<String>,
+
+        #[clap(long = "days", help = "Validity of the certifications in days")]
+        days: Option<u64>,
+
+        #[clap(
+            long = "valid-until",
+            value_name = "YYYY-MM-DD",
+            conflicts_with = "days",
+            help = "The certifications are valid until the end of this day (UTC)"
+        )]
+        valid_until: Option<String>,
+
+        #[clap(
+            long = "profile",
+            value_parser = ["default", "rfc4880-compat"],
+            default_value = "default",
+            help = "Make the certifications according to this profile"
+        )]
+        profile: String,
+    },
+    /// Re-certify User IDs whose CA certifications have expired recently
+    RecertifyExpired {
+        #[clap(
+            short = 'g',
+            long = "grace-days",
+            help = "Only re-certify if the certifications expired within 'grace-days' days",
+            default_value = "30"
+        )]
+        grace_days: u64,
+    },
+    /// Renew CA certifications that expire soon (inactive keys are skipped)
+    RefreshCertifications {
+        #[clap(
+            long = "threshold-days",
+            help = "Renew certifications that expire within 'threshold-days' days",
+            default_value = "30"
+        )]
+        threshold_days: u64,
+
+        #[clap(
+            short = 'd',
+            long = "days",
+            help = "Validity of the new certifications, in days",
+            default_value = "365"
+        )]
+        days: u64,
+
+        #[clap(
+            long = "valid-until",
+            value_name = "YYYY-MM-DD",
+            conflicts_with = "days",
+            help = "The new certifications are valid until the end of this day (UTC)"
+        )]
+        valid_until: Option<String>,
+    },
+    /// Renew CA certifications that would expire during a window (e.g. a change freeze)
+    ExtendCertifications {
+        #[clap(long = "from", help = "First day of the window (YYYY-MM-DD)")]
+        from: String,
+
+        #[clap(long = "until", help = "Last day of the window (YYYY-MM-DD)")]
+        until: String,
+
+        #[clap(
+            short = 'd',
+            long = "days",
+            help = "Validity of the new certifications, in days",
+            default_value = "365"
+        )]
+        days: u64,
+
+        #[clap(
+            long = "dry-run",
+            help = "Only list the certifications that would be renewed"
+        )]
+        dry_run: bool,
+    },
+    /// Import User (use existing Public Key)
+    Import {
+        #[clap(
+            short = 'e',
+            long = "email",
+            required_unless_present_any = ["uri", "role"],
+            number_of_values = 1,
+            help = "Email address"
+        )]
+        email: Vec<String>,
+
+        #[clap(
+            long = "uri",
+            number_of_values = 1,
+            help = "URI identifier (e.g. spiffe://cluster/ns/job), must be allowed by the URI policy"
+        )]
+        uri: Vec<String>,
+
+        #[clap(
+            long = "role",
+            number_of_values = 1,
+            help = "Role User ID without email (e.g. \"Backup signing key 2024\")"
+        )]
+        role: Vec<String>,
+
+        #[clap(
+            short = 'f',
+            long = "key-file",
+            help = "File that contains the User's Public Key"
+        )]
+        cert_file: PathBuf,
+
+        #[clap(short = 'n', long = "name", help = "Descriptive User Name")]
+        name: Option<String>,
+
+        #[clap(
+            short = 'r',
+            long = "revocation-file",
+            number_of_values = 1,
+            help = "File that contains a revocation cert for this user"
+        )]
+        revocation_file: Vec<PathBuf>,
+
+        #[clap(
+            long = "valid-until",
+            value_name = "YYYY-MM-DD",
+            help = "The certifications are valid until the end of this day (UTC), e.g. the end of an employment contract"
+        )]
+        valid_until: Option<String>,
+
+        #[clap(
+            long = "strict",
+            help = "Don't import the key if there are warnings (e.g. weak algorithms, imminent expiry)"
+        )]
+        strict: bool,
+    },
+    /// Update User (use existing Public Key)
+    Update {
+        #[clap(
+            short = 'f',
+            long = "key-file",
+            help = "File that contains the User's Public Key"
+        )]
+        cert_file: PathBuf,
+
+        #[clap(
+            long = "allow-downgrade",
+            help = "Accept updates that roll back the stored cert (e.g. an earlier expiration time)"
+        )]
+        allow_downgrade: bool,
+
+        #[clap(
+            long = "rotation",
+            conflicts_with = "allow_downgrade",
+            help = "Only accept the update if it adds a new encryption subkey"
+        )]
+        rotation: bool,
+    },
+    /// Import certifications that were made with the CA key outside of OpenPGP CA
+    ImportCertifications {
+        #[clap(
+            short = 'f',
+            long = "key-file",
+            help = "File that contains a copy of the User's Public Key, with the certifications"
+        )]
+        cert_file: PathBuf,
+    },
+    /// Export User Public Key (bulk, if no email address is given)
     Export {
         #[clap(short = 'e', long = "email", help = "Email address")]
         email: Option<String>,
 
-        #[clap(short = 'p', long = "path", help = "Output path")]
-        path: Option<String>,
+        #[clap(short = 'p', long = "path", help = "Output path")]
+        path: Option<String>,
+
+        #[clap(
+            long = "encryption-capable",
+            conflicts_with = "path",
+            help = "Only export keys that can currently be used for encryption"
+        )]
+        encryption_capable: bool,
+
+        #[clap(
+            long = "minimize",
+            conflicts_with = "encryption_capable",
+            help = "Only export the relevant User IDs, the CA certifications and current subkeys"
+        )]
+        minimize: bool,
+
+        #[clap(
+            long = "format",
+            value_enum,
+            default_value_t = ExportFormat::Armored,
+            help = "Encoding of the exported keys"
+        )]
+        format: ExportFormat,
+
+        #[clap(
+            long = "compat",
+            value_parser = ["gnupg-2.2", "thunderbird", "sequoia"],
+            default_value = "gnupg-2.2",
+            help = "Adjust the exported keys to the quirks of this client"
+        )]
+        compat: String,
+    },
+    /// Export the CA Public Key and all User Public Keys as a keyring file, or
+    /// as a directory of keyring chunks with an index (for very large keyrings)
+    ExportKeyring {
+        #[clap(
+            short = 'o',
+            long = "output",
+            help = "File to export to (with --chunk-size: directory)"
+        )]
+        output: PathBuf,
+
+        #[clap(
+            long = "chunk-size",
+            help = "Split the User Public Keys into chunks of about this many keys"
+        )]
+        chunk_size: Option<usize>,
+
+        #[clap(
+            long = "compression",
+            value_parser = ["none", "gzip", "zstd"],
+            default_value = "none",
+            help = "Compress the exported file(s)"
+        )]
+        compression: String,
+
+        #[clap(
+            long = "minimize",
+            help = "Only export the certified User IDs, the CA certifications and current subkeys"
+        )]
+        minimize: bool,
+
+        #[clap(
+            long = "format",
+            value_enum,
+            default_value_t = ExportFormat::Armored,
+            help = "Encoding of the exported keys"
+        )]
+        format: ExportFormat,
+
+        #[clap(
+            long = "compat",
+            value_parser = ["gnupg-2.2", "thunderbird", "sequoia"],
+            default_value = "gnupg-2.2",
+            help = "Adjust the exported keys to the quirks of this client"
+        )]
+        compat: String,
+    },
+    /// Export the certificate chain (User Public Key and CA Public Key) for an
+    /// email address, for external verification of the User's signatures
+    ExportChain {
+        #[clap(help = "Email address")]
+        email: String,
+
+        #[clap(short = 'o', long = "output", help = "File to export to")]
+        output: Option<PathBuf>,
+    },
+    /// Export a User's Public Key as Autocrypt header attributes
+    ExportAutocrypt {
+        #[clap(short = 'e', long = "email", help = "Email address")]
+        email: String,
+    },
+    /// Export a User's authentication subkeys as OpenSSH "authorized_keys" lines
+    ExportSsh {
+        #[clap(short = 'e', long = "email", help = "Email address")]
+        email: String,
+    },
+    /// Import the CA Public Key and all User Public Keys into a GnuPG home directory,
+    /// with "ultimate" ownertrust for the CA (e.g. for a mail gateway that uses GnuPG)
+    ExportGnupg {
+        #[clap(
+            long = "homedir",
+            help = "GnuPG home directory (created if it doesn't exist)"
+        )]
+        homedir: PathBuf,
+    },
+    /// Export a provisioning bundle for a User: the User's key, the CA public key,
+    /// and the CA public key with the User's trust signature
+    Bundle {
+        #[clap(
+            short = 'f',
+            long = "fingerprint",
+            help = "Fingerprint of the User's key"
+        )]
+        fingerprint: String,
+
+        #[clap(
+            short = 'k',
+            long = "private-key-file",
+            help = "File that contains the User's private key (if it is available)"
+        )]
+        private_key_file: Option<PathBuf>,
+
+        #[clap(short = 'o', long = "output", help = "File to export to")]
+        output: Option<PathBuf>,
+    },
+    /// List Users
+    List,
+    /// Apply a Revocation Certificate
+    ApplyRevocation {
+        #[clap(help = "Id of a revocation cert")]
+        hash: String,
+    },
+    /// Publish a revoked key through the configured channels (e.g. after a failed attempt)
+    PublishRevocation {
+        #[clap(help = "Id of an applied revocation cert")]
+        hash: String,
+    },
+    /// Show Revocation Certificates (if available)
+    ShowRevocations {
+        #[clap(short = 'e', long = "email", help = "Email address")]
+        email: String,
+    },
+    /// Retract the CA certification of one User ID (e.g. if it was certified by mistake)
+    RetractCertification {
+        #[clap(short = 'f', long = "fingerprint", help = "Fingerprint of the key")]
+        fingerprint: String,
+
+        #[clap(
+            short = 'u',
+            long = "userid",
+            help = "User ID to retract the certification of"
+        )]
+        userid: String,
+
+        #[clap(
+            short = 'r',
+            long = "reason",
+            help = "Reason for the retraction",
+            default_value = "Certification retracted"
+        )]
+        reason: String,
+    },
+    /// Manage previous versions of User Public Keys
+    Versions {
+        #[clap(subcommand)]
+        cmd: VersionsCommand,
+    },
+    /// Manage free-form notes on Users and their keys
+    Notes {
+        #[clap(subcommand)]
+        cmd: NotesCommand,
+    },
+    /// Manage key-value tags on Users and their keys (e.g. department, employee ID)
+    Tag {
+        #[clap(subcommand)]
+        cmd: TagCommand,
+    },
+    /// Bridging to an X.509 CA that issues S/MIME certificates (experimental)
+    X509 {
+        #[clap(subcommand)]
+        cmd: X509Command,
+    },
+    /// Search keys by fingerprint, User name, email address, User ID, notes and tags
+    Search {
+        #[clap(help = "Text to search for (case-insensitive)")]
+        text: String,
+    },
+    /// Set if a User's keys are published via WKD (the keys stay certified)
+    WkdPublish {
+        #[clap(help = "Fingerprint of one of the User's keys")]
+        fingerprint: String,
+
+        #[clap(long = "disable", help = "Don't publish the User's keys via WKD")]
+        disable: bool,
+    },
+    /// Delist a key: don't export it (on all channels, or only on some)
+    Delist {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+
+        #[clap(
+            long = "channel",
+            value_parser = ["wkd", "keylist", "keyserver", "certring"],
+            help = "Only delist the key on this channel (may be given multiple times)"
+        )]
+        channel: Vec<String>,
+
+        #[clap(long = "undo", help = "List the key again (on the given channels)")]
+        undo: bool,
+    },
+    /// Move a key to a different User (the move is recorded)
+    ReassignCert {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+
+        #[clap(long = "to", help = "Name of the User to move the key to")]
+        to: String,
+
+        #[clap(long = "new-user", help = "Create a new User with this name")]
+        new_user: bool,
+
+        #[clap(long = "reason", help = "Reason for the move")]
+        reason: Option<String>,
+    },
+    /// Show the recorded moves of a key between Users
+    ShowReassignments {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+    },
+    /// Permanently delete a key (e.g. one that was imported by mistake)
+    Purge {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+
+        #[clap(
+            long = "force",
+            help = "Delete the key (otherwise only show what would be deleted)"
+        )]
+        force: bool,
+    },
+    /// Offboard a User: revoke, deactivate and delist all of their keys
+    Offboard {
+        #[clap(short = 'e', long = "email", help = "Email address of the User")]
+        email: String,
+
+        #[clap(
+            short = 'r',
+            long = "reason",
+            help = "Reason for retracting certifications (for keys without a stored revocation)",
+            default_value = "User offboarded"
+        )]
+        reason: String,
+    },
+    /// Manage replacement keys that Users submitted (e.g. via restd)
+    Replacements {
+        #[clap(subcommand)]
+        cmd: ReplacementsCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ReplacementsCommand {
+    /// List replacement requests that wait for approval
+    List {
+        #[clap(long = "all", help = "Include approved and rejected requests")]
+        all: bool,
+    },
+    /// Store and certify the replacement key for the User of the old key
+    Approve {
+        #[clap(help = "Id of the replacement request")]
+        id: i32,
+
+        #[clap(long = "days", help = "Validity of the certifications in days")]
+        days: Option<u64>,
+
+        #[clap(long = "keep-old", help = "Don't mark the old key as inactive")]
+        keep_old: bool,
+    },
+    /// Reject a replacement request
+    Reject {
+        #[clap(help = "Id of the replacement request")]
+        id: i32,
+
+        #[clap(long = "reason", help = "Reason for the rejection")]
+        reason: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum NotesCommand {
+    /// Replace the notes
+    Set {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+
+        #[clap(help = "Notes")]
+        text: String,
+
+        #[clap(
+            long = "user",
+            help = "Notes on the User that the key belongs to (default: notes on the key)"
+        )]
+        user: bool,
+    },
+    /// Add a line to the notes
+    Append {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+
+        #[clap(help = "Line to add")]
+        text: String,
+
+        #[clap(
+            long = "user",
+            help = "Notes on the User that the key belongs to (default: notes on the key)"
+        )]
+        user: bool,
+    },
+    /// Remove the notes
+    Clear {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+
+        #[clap(
+            long = "user",
+            help = "Notes on the User that the key belongs to (default: notes on the key)"
+        )]
+        user: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum X509Command {
+    /// Export a mapping of Users' identities and keys as JSON, for an X.509 CA
+    Export {
+        #[clap(short = 'o', long = "output", help = "File to export to")]
+        output: Option<PathBuf>,
+    },
+    /// Record the serial number of the X.509 certificate that was issued for a key
+    SetSerial {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+
+        #[clap(help = "Serial number of the X.509 certificate (hex)")]
+        serial: String,
+    },
+    /// Remove the recorded X.509 serial number of a key
+    RemoveSerial {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TagCommand {
+    /// Set a tag (replacing its previous value)
+    Set {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+
+        #[clap(help = "Name of the tag")]
+        name: String,
+
+        #[clap(help = "Value of the tag")]
+        value: String,
+
+        #[clap(
+            long = "user",
+            help = "Tag the User that the key belongs to (default: tag the key)"
+        )]
+        user: bool,
+    },
+    /// Remove a tag
+    Remove {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+
+        #[clap(help = "Name of the tag")]
+        name: String,
+
+        #[clap(
+            long = "user",
+            help = "Tag of the User that the key belongs to (default: tag of the key)"
+        )]
+        user: bool,
+    },
+    /// Show the tags of a key and of its User
+    List {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+    },
+    /// List keys that have a tag (or whose User has it)
+    Find {
+        #[clap(help = "Name of the tag")]
+        name: String,
+
+        #[clap(help = "Only list keys where the tag has this value")]
+        value: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum VersionsCommand {
+    /// List previous versions of a key
+    List {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+    },
+    /// Show changes between a previous version and a later (or the current) version
+    Diff {
+        #[clap(help = "Id of the previous version")]
+        from: i32,
+
+        #[clap(help = "Id of the later version (default: current version)")]
+        to: Option<i32>,
+    },
+    /// Show a key as it was stored at a past point in time, and which of its User IDs
+    /// were certified by the CA then
+    AsOf {
+        #[clap(help = "Fingerprint of the key")]
+        fingerprint: String,
+
+        #[clap(help = "Point in time (YYYY-MM-DD for the start of the day in UTC, or RFC 3339)")]
+        time: String,
+    },
+    /// Replace a key with one of its previous versions
+    Restore {
+        #[clap(help = "Id of the previous version")]
+        id: i32,
+    },
+    /// Show or set the number of previous versions retained per key
+    Retention {
+        #[clap(help = "Number of versions to retain (0 disables version history)")]
+        keep: Option<u32>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum UserCheckSubcommand {
+    /// Check user key expiry
+    Expiry {
+        #[clap(
+            short = 'd',
+            long = "days",
+            help = "Check for keys that expire within 'days' days",
+            default_value = "30"
+        )]
+        days: u64,
+    },
+    /// Check for keys whose encryption subkey is due for rotation
+    SubkeyRotation {
+        #[clap(
+            short = 'd',
+            long = "days",
+            help = "Include keys whose encryption subkey is due for rotation within 'days' days",
+            default_value = "30"
+        )]
+        days: u64,
+
+        #[clap(
+            long = "reminders",
+            value_name = "DIR",
+            help = "Write a reminder email for each key to DIR"
+        )]
+        reminders: Option<PathBuf>,
+    },
+    /// Check certifications on CA key
+    Certifications {
+        #[clap(
+            long = "no-cache",
+            help = "Verify all signatures, ignoring cached verification results"
+        )]
+        no_cache: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BridgeCommand {
+    /// List Bridges
+    List,
+    /// Export Bridge Public Key (bulk, if no domain name is given)
+    Export {
+        #[clap(help = "Remote CA Email address")]
+        email: Option<String>,
+
+        #[clap(
+            long = "compat",
+            value_parser = ["gnupg-2.2", "thunderbird", "sequoia"],
+            default_value = "gnupg-2.2",
+            help = "Adjust the exported keys to the quirks of this client"
+        )]
+        compat: String,
+    },
+
+    /// Add New Bridge (certify existing remote CA Public Key)
+    New {
+        #[clap(short = 'e', long = "email", help = "Bridge remote Email")]
+        email: Option<String>,
+
+        #[clap(short = 'c', long = "commit", help = "Commit Bridge certification")]
+        commit: bool,
+
+        #[clap(
+            help = "File that contains the remote CA's Public Key",
+            required_unless_present = "from_metadata"
+        )]
+        remote_key_file: Option<PathBuf>,
+
+        #[clap(
+            name = "domainname",
+            short = 's',
+            long = "scope",
+            help = "Scope for trust of this bridge"
+        )]
+        scope: Option<String>,
+
+        #[clap(
+            long = "scope-regex",
+            conflicts_with = "domainname",
+            help = "Scope the trust of this bridge with a custom regular expression \
+            (may be given multiple times)"
+        )]
+        scope_regex: Vec<String>,
+
+        #[clap(
+            long = "unscoped",
+            conflicts_with_all = ["domainname", "scope_regex"],
+            help = "Don't limit the scope of trust of this bridge"
+        )]
+        unscoped: bool,
+
+        #[clap(
+            long = "force",
+            requires = "domainname",
+            help = "Allow a scope that differs from the domain of the remote CA"
+        )]
+        force: bool,
+
+        #[clap(
+            long = "from-metadata",
+            conflicts_with_all = ["remote_key_file", "domainname", "scope_regex", "unscoped"],
+            help = "URL or file of the remote CA's federation metadata document"
+        )]
+        from_metadata: Option<String>,
+
+        #[clap(
+            long = "fingerprint",
+            requires = "from_metadata",
+            help = "Expected fingerprint of the remote CA key (with --from-metadata)"
+        )]
+        fingerprint: Option<String>,
+
+        #[clap(
+            long = "profile",
+            value_parser = ["default", "rfc4880-compat"],
+            default_value = "default",
+            help = "Make the trust signature according to this profile \
+            (with rfc4880-compat, the bridge is unscoped)"
+        )]
+        profile: String,
+    },
+    /// Revoke Bridge
+    Revoke {
+        #[clap(short = 'e', long = "email", help = "Bridge remote Email")]
+        email: String,
+    },
+    /// Export a bundle for the remote CA's operator (remote CA Public Key with our tsig, and our CA Public Key)
+    ExportBundle {
+        #[clap(short = 'e', long = "email", help = "Bridge remote Email")]
+        email: String,
+    },
+    /// Import a bundle that a remote CA exported for us (adds its tsig to our CA Public Key, and a bridge to it)
+    ImportBundle {
+        #[clap(help = "File that contains the bundle")]
+        file: PathBuf,
+
+        #[clap(
+            long = "fingerprint",
+            help = "Expected fingerprint of the remote CA key"
+        )]
+        fingerprint: Option<String>,
+
+        #[clap(
+            long = "profile",
+            value_parser = ["default", "rfc4880-compat"],
+            default_value = "default",
+            help = "Make the trust signature of a new bridge according to this profile \
+            (with rfc4880-compat, the bridge is unscoped)"
+        )]
+        profile: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum WkdCommand {
+    /// Export WKD structure
+    Export {
+        #[clap(help = "Filesystem directory for WKD export")]
+        path: PathBuf,
+
+        #[clap(
+            long = "minimize",
+            help = "Only publish the User IDs in the CA's domain, the CA certifications and current subkeys"
+        )]
+        minimize: bool,
+
+        #[clap(
+            long = "compat",
+            value_parser = ["gnupg-2.2", "thunderbird", "sequoia"],
+            default_value = "gnupg-2.2",
+            help = "Adjust the exported keys to the quirks of this client"
+        )]
+        compat: String,
+
+        #[clap(
+            long = "only-changed",
+            help = "Regenerate the WKD, but only write files whose content changed"
+        )]
+        only_changed: bool,
+
+        #[clap(
+            short = 'e',
+            long = "email",
+            help = "Only export the User IDs with this email address (may be given multiple times)"
+        )]
+        email: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum KeyListCommand {
+    /// Export KeyList
+    Export {
+        #[clap(
+            short = 'p',
+            long = "path",
+            help = "Filesystem directory for KeyList export"
+        )]
+        path: PathBuf,
+
+        #[clap(short = 's', long = "sig-uri", help = "Sinature URI")]
+        signature_uri: String,
+
+        #[clap(
+            short = 'f',
+            long = "force",
+            help = "Overwrite keylist/sig files if they exist"
+        )]
+        force: bool,
+    },
+    /// Export all named KeyLists (each into a subdirectory named after the list)
+    ExportAll {
+        #[clap(
+            short = 'p',
+            long = "path",
+            help = "Filesystem directory for KeyList export"
+        )]
+        path: PathBuf,
+
+        #[clap(
+            short = 'f',
+            long = "force",
+            help = "Overwrite keylist/sig files if they exist"
+        )]
+        force: bool,
     },
-    /// List Users
+    /// List the named KeyLists
     List,
-    /// Apply a Revocation Certificate
-    ApplyRevocation {
-        #[clap(help = "Id of a revocation cert")]
-        hash: String,
+    /// Add a named KeyList (or replace the one with the same name)
+    Set {
+        #[clap(help = "Name of the KeyList (ASCII letters, digits, '-' and '_')")]
+        name: String,
+
+        #[clap(short = 's', long = "sig-uri", help = "Signature URI")]
+        signature_uri: String,
+
+        #[clap(
+            long = "domain",
+            number_of_values = 1,
+            help = "Only include email addresses in this domain"
+        )]
+        domain: Vec<String>,
+
+        #[clap(
+            short = 'e',
+            long = "email",
+            number_of_values = 1,
+            help = "Only include this email address"
+        )]
+        email: Vec<String>,
+
+        #[clap(long = "exclude-inactive", help = "Leave out inactive keys")]
+        exclude_inactive: bool,
+
+        #[clap(
+            long = "group",
+            help = "Only include the keys of the users in this group"
+        )]
+        group: Option<String>,
+
+        #[clap(
+            long = "include-roles",
+            help = "Also list role User IDs without email (e.g. of service accounts)"
+        )]
+        include_roles: bool,
     },
-    /// Show Revocation Certificates (if available)
-    ShowRevocations {
-        #[clap(short = 'e', long = "email", help = "Email address")]
-        email: String,
+    /// Remove a named KeyList
+    Remove {
+        #[clap(help = "Name of the KeyList")]
+        name: String,
     },
 }
 
 #[derive(Subcommand)]
-pub enum UserCheckSubcommand {
-    /// Check user key expiry
-    Expiry {
+pub enum GroupCommand {
+    /// List the groups
+    List,
+    /// Add a new (empty) group
+    New {
+        #[clap(help = "Name of the group (ASCII letters, digits, '-' and '_')")]
+        name: String,
+
+        #[clap(long = "description", help = "Description of the group")]
+        description: Option<String>,
+    },
+    /// Delete a group (the users in the group are not affected)
+    Delete {
+        #[clap(help = "Name of the group")]
+        name: String,
+    },
+    /// Show the users in a group
+    Show {
+        #[clap(help = "Name of the group")]
+        name: String,
+    },
+    /// Add the user of a key to a group
+    AddMember {
+        #[clap(help = "Name of the group")]
+        name: String,
+
+        #[clap(
+            short = 'f',
+            long = "fingerprint",
+            help = "Fingerprint of a key of the user"
+        )]
+        fingerprint: String,
+    },
+    /// Remove the user of a key from a group
+    RemoveMember {
+        #[clap(help = "Name of the group")]
+        name: String,
+
+        #[clap(
+            short = 'f',
+            long = "fingerprint",
+            help = "Fingerprint of a key of the user"
+        )]
+        fingerprint: String,
+    },
+    /// Certify the not yet certified User IDs (with the keys' email addresses) in a group
+    Certify {
+        #[clap(help = "Name of the group")]
+        name: String,
+
         #[clap(
             short = 'd',
             long = "days",
-            help = "Check for keys that expire within 'days' days",
+            help = "Validity of the new certifications, in days"
+        )]
+        days: Option<u64>,
+    },
+    /// Renew the CA certifications in a group that expire soon
+    RefreshCertifications {
+        #[clap(help = "Name of the group")]
+        name: String,
+
+        #[clap(
+            long = "threshold-days",
+            help = "Renew certifications that expire within 'threshold-days' days",
             default_value = "30"
         )]
+        threshold_days: u64,
+
+        #[clap(
+            short = 'd',
+            long = "days",
+            help = "Validity of the new certifications, in days",
+            default_value = "365"
+        )]
         days: u64,
     },
-    /// Check certifications on CA key
-    Certifications,
-}
-
-#[derive(Subcommand)]
-pub enum BridgeCommand {
-    /// List Bridges
-    List,
-    /// Export Bridge Public Key (bulk, if no domain name is given)
+    /// Export the CA Public Key and the User Public Keys in a group as a keyring
     Export {
-        #[clap(help = "Remote CA Email address")]
-        email: Option<String>,
-    },
+        #[clap(help = "Name of the group")]
+        name: String,
 
-    /// Add New Bridge (certify existing remote CA Public Key)
-    New {
-        #[clap(short = 'e', long = "email", help = "Bridge remote Email")]
-        email: Option<String>,
+        #[clap(short = 'o', long = "output", help = "File to export to")]
+        output: PathBuf,
 
-        #[clap(short = 'c', long = "commit", help = "Commit Bridge certification")]
-        commit: bool,
+        #[clap(
+            long = "compression",
+            value_parser = ["none", "gzip", "zstd"],
+            default_value = "none",
+            help = "Compress the exported file"
+        )]
+        compression: String,
 
-        #[clap(help = "File that contains the remote CA's Public Key")]
-        remote_key_file: PathBuf,
+        #[clap(
+            long = "minimize",
+            help = "Only export the certified User IDs, the CA certifications and current subkeys"
+        )]
+        minimize: bool,
 
         #[clap(
-            name = "domainname",
-            short = 's',
-            long = "scope",
-            help = "Scope for trust of this bridge"
+            long = "format",
+            value_enum,
+            default_value_t = ExportFormat::Armored,
+            help = "Encoding of the exported keys"
         )]
-        scope: Option<String>,
-    },
-    /// Revoke Bridge
-    Revoke {
-        #[clap(short = 'e', long = "email", help = "Bridge remote Email")]
-        email: String,
+        format: ExportFormat,
+
+        #[clap(
+            long = "compat",
+            value_parser = ["gnupg-2.2", "thunderbird", "sequoia"],
+            default_value = "gnupg-2.2",
+            help = "Adjust the exported keys to the quirks of this client"
+        )]
+        compat: String,
     },
-}
+    /// Export the User IDs in a group into a WKD structure
+    ExportWkd {
+        #[clap(help = "Name of the group")]
+        name: String,
 
-#[derive(Subcommand)]
-pub enum WkdCommand {
-    /// Export WKD structure
-    Export {
         #[clap(help = "Filesystem directory for WKD export")]
         path: PathBuf,
+
+        #[clap(
+            long = "minimize",
+            help = "Only publish the User IDs in the CA's domain, the CA certifications and current subkeys"
+        )]
+        minimize: bool,
+
+        #[clap(
+            long = "compat",
+            value_parser = ["gnupg-2.2", "thunderbird", "sequoia"],
+            default_value = "gnupg-2.2",
+            help = "Adjust the exported keys to the quirks of this client"
+        )]
+        compat: String,
     },
-}
+    /// Export a KeyList with the keys in a group
+    ExportKeylist {
+        #[clap(help = "Name of the group")]
+        name: String,
 
-#[derive(Subcommand)]
-pub enum KeyListCommand {
-    /// Export KeyList
-    Export {
         #[clap(
             short = 'p',
             long = "path",
@@ -433,7 +2231,7 @@ pub enum KeyListCommand {
         )]
         path: PathBuf,
 
-        #[clap(short = 's', long = "sig-uri", help = "Sinature URI")]
+        #[clap(short = 's', long = "sig-uri", help = "Signature URI")]
         signature_uri: String,
 
         #[clap(
@@ -443,14 +2241,214 @@ pub enum KeyListCommand {
         )]
         force: bool,
     },
+    /// Write a notification email for the users in a group, and print its recipients
+    Notify {
+        #[clap(help = "Name of the group")]
+        name: String,
+
+        #[clap(
+            long = "text",
+            help = "File that contains the text of the notification"
+        )]
+        text: PathBuf,
+
+        #[clap(short = 'o', long = "output", help = "File to write the email to")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum QueueCommand {
+    /// List proposed changes that wait for approval
+    List {
+        #[clap(long = "all", help = "Include approved and rejected changes")]
+        all: bool,
+    },
+    /// Apply a proposed change
+    Approve {
+        #[clap(help = "Id of the proposed change")]
+        id: i32,
+    },
+    /// Reject a proposed change
+    Reject {
+        #[clap(help = "Id of the proposed change")]
+        id: i32,
+
+        #[clap(long = "reason", help = "Reason for the rejection")]
+        reason: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum KeyProfileCommand {
+    /// List the key profiles
+    List,
+    /// Add a key profile (or replace the one with the same name)
+    Set {
+        #[clap(help = "Name of the key profile (ASCII letters, digits, '-' and '_')")]
+        name: String,
+
+        #[clap(
+            long = "cipher-suite",
+            default_value = "cv25519",
+            help = "Cipher suite"
+        )]
+        cipher_suite: String,
+
+        #[clap(
+            long = "encryption",
+            help = "Create an encryption subkey",
+            default_value_t = true,
+            action = clap::ArgAction::Set,
+        )]
+        encryption_subkey: bool,
+
+        #[clap(
+            long = "signing",
+            help = "Create a signing subkey",
+            default_value_t = true,
+            action = clap::ArgAction::Set,
+        )]
+        signing_subkey: bool,
+
+        #[clap(
+            long = "authentication",
+            help = "Create an authentication subkey",
+            default_value_t = false,
+            action = clap::ArgAction::Set,
+        )]
+        authentication_subkey: bool,
+
+        #[clap(long = "days", help = "Keys expire after this many days")]
+        expiration_days: Option<u64>,
+
+        #[clap(long = "no-password", help = "Don't password protect generated keys")]
+        no_password: bool,
+
+        #[clap(
+            long = "password-words",
+            default_value_t = 5,
+            help = "Number of words in generated passwords"
+        )]
+        password_words: usize,
+
+        #[clap(
+            long = "short-words",
+            help = "Generate passwords from the short EFF word list"
+        )]
+        password_short_words: bool,
+    },
+    /// Remove a key profile
+    Remove {
+        #[clap(help = "Name of the key profile")]
+        name: String,
+    },
 }
 
 #[derive(Subcommand)]
 pub enum UpdateCommand {
     /// Update certificates from a keyserver
-    Keyserver {},
+    Keyserver {
+        #[clap(flatten)]
+        sync: SyncArgs,
+    },
     /// Update certificates from WKD
-    Wkd {},
+    Wkd {
+        #[clap(flatten)]
+        sync: SyncArgs,
+    },
+    /// Show certificates that have been failing to update for a long time
+    Failing {
+        #[clap(
+            long = "source",
+            value_parser = ["wkd", "keyserver"],
+            help = "Only show failures for this source"
+        )]
+        source: Option<String>,
+
+        #[clap(
+            long = "days",
+            default_value = "7",
+            help = "Minimum duration of the failures in days"
+        )]
+        days: u64,
+    },
+}
+
+#[derive(clap::Args)]
+pub struct SyncArgs {
+    #[clap(
+        long = "interval-hours",
+        default_value = "1",
+        help = "Skip certificates that were looked up less than this many hours ago"
+    )]
+    pub interval_hours: u64,
+
+    #[clap(long = "limit", help = "Look up at most this many certificates")]
+    pub limit: Option<usize>,
+
+    #[clap(
+        long = "delay-ms",
+        default_value = "0",
+        help = "Pause between lookups in milliseconds"
+    )]
+    pub delay_ms: u64,
+}
+
+#[derive(Subcommand)]
+pub enum UtilCommand {
+    /// Convert OpenPGP data to ASCII-armored format
+    Armor {
+        #[clap(short = 'i', long = "input", help = "Input file")]
+        input: PathBuf,
+
+        #[clap(short = 'o', long = "output", help = "Output file (default: stdout)")]
+        output: Option<PathBuf>,
+    },
+    /// Convert OpenPGP data to binary format
+    Dearmor {
+        #[clap(short = 'i', long = "input", help = "Input file")]
+        input: PathBuf,
+
+        #[clap(short = 'o', long = "output", help = "Output file (default: stdout)")]
+        output: Option<PathBuf>,
+    },
+    /// Extract the signatures by one issuer (e.g. a revocation) from OpenPGP data
+    ExtractSignatures {
+        #[clap(short = 'i', long = "input", help = "Input file")]
+        input: PathBuf,
+
+        #[clap(
+            long = "issuer",
+            help = "Fingerprint or Key ID of the issuer of the signatures"
+        )]
+        issuer: String,
+
+        #[clap(short = 'o', long = "output", help = "Output file (default: stdout)")]
+        output: Option<PathBuf>,
+    },
+    /// Verify a certificate chain (as exported with 'user export-chain')
+    VerifyChain {
+        #[clap(short = 'i', long = "input", help = "Input file")]
+        input: PathBuf,
+
+        #[clap(short = 'e', long = "email", help = "Email address of the User")]
+        email: String,
+
+        #[clap(
+            long = "ca-fingerprint",
+            help = "Fingerprint of the CA key (obtained from a trusted source)"
+        )]
+        ca_fingerprint: String,
+    },
+    /// Split a keyring into one armored file per cert (named by fingerprint)
+    SplitKeyring {
+        #[clap(short = 'i', long = "input", help = "Input file")]
+        input: PathBuf,
+
+        #[clap(long = "path", help = "Output directory")]
+        path: PathBuf,
+    },
 }
 
 #[derive(Subcommand)]
@@ -469,6 +2467,10 @@ pub enum SplitCommand {
     },
 
     /// Merge a back instance into a front CA, resulting in a regular "non-split" CA.
+    ///
+    /// All pending certification requests of the front instance must be
+    /// processed before merging.
+    #[clap(visible_alias = "merge-back")]
     Merge {
         #[clap(short = 'b', long = "back", help = "Filename for the back CA instance")]
         back: PathBuf,
@@ -517,6 +2519,72 @@ pub enum SplitCommand {
         import: PathBuf,
     },
 
+    /// Download certification requests from a front instance's restd
+    /// (on a back instance, via the split mode HTTP transport)
+    ///
+    /// Not available if the CA routes network operations through Tor.
+    Pull {
+        #[clap(long = "url", help = "Base URL of the front instance's restd")]
+        url: String,
+
+        #[clap(
+            long = "token-file",
+            help = "File that contains the bearer token for the split mode routes"
+        )]
+        token_file: PathBuf,
+
+        #[clap(
+            short = 'f',
+            long = "file",
+            help = "File to store the certification requests in"
+        )]
+        file: PathBuf,
+    },
+
+    /// Upload generated certifications to a front instance's restd
+    /// (on a back instance, via the split mode HTTP transport)
+    ///
+    /// Not available if the CA routes network operations through Tor.
+    Push {
+        #[clap(long = "url", help = "Base URL of the front instance's restd")]
+        url: String,
+
+        #[clap(
+            long = "token-file",
+            help = "File that contains the bearer token for the split mode routes"
+        )]
+        token_file: PathBuf,
+
+        #[clap(
+            short = 'f',
+            long = "file",
+            help = "File that contains the generated certifications"
+        )]
+        file: PathBuf,
+    },
+
+    /// Import the new CA cert into a front instance, after the CA key was
+    /// replaced on the back instance (with "ca rekey").
+    ///
+    /// Certification requests are queued for all user certs that were
+    /// certified by the old CA key.
+    RekeyImport {
+        #[clap(
+            short = 'f',
+            long = "file",
+            help = "File that contains the new CA public key"
+        )]
+        file: PathBuf,
+
+        #[clap(
+            short = 'v',
+            long = "validity",
+            help = "Validity of the new certifications in days",
+            default_value = "365"
+        )]
+        validity_days: u64,
+    },
+
     /// Show queue entries in a front CA instance
     ShowQueue,
 }
@@ -0,0 +1,549 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Guided setup of a new CA instance (`ca init --interactive`).
+//!
+//! All settings are collected and checked first, then shown as a summary.
+//! The CA is only initialized after the operator confirms the summary.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::Result;
+use openpgp_ca_lib::i18n::{tr, Language, Msg};
+use openpgp_ca_lib::pgp::CipherSuite;
+use openpgp_ca_lib::types::ExportCompat;
+use openpgp_ca_lib::util::create_private_file;
+use openpgp_ca_lib::{Oca, Uninit};
+
+// The first entry is the recommended cipher suite
+const CIPHER_SUITES: &[(&str, &str)] = &[
+    ("cv25519", "Curve25519"),
+    ("rsa4k", "RSA 4096 bit"),
+    ("rsa3k", "RSA 3072 bit"),
+    ("rsa2k", "RSA 2048 bit"),
+    ("p256", "NIST P-256"),
+    ("p384", "NIST P-384"),
+    ("p521", "NIST P-521"),
+];
+
+/// How the CA key gets onto an OpenPGP card
+enum CardMode {
+    /// Generate the key on the host, import it to the card, and write a
+    /// backup of the private key to a file
+    GenerateOnHost { backup: PathBuf },
+
+    /// Generate the key on the card (no backup is possible)
+    GenerateOnCard,
+
+    /// Import an existing CA private key from a file
+    Import { key: PathBuf },
+
+    /// Use a card that already contains the CA key, and its public key
+    FromCard { public_key: PathBuf },
+}
+
+/// Which OpenPGP cards are offered for the CA
+enum CardKind {
+    /// Blank cards, to store a new or imported CA key on
+    Blank,
+
+    /// Cards that already hold the CA key
+    Matching,
+}
+
+/// Where the CA private key is kept
+enum KeyStorage {
+    Softkey,
+    Card { ident: String, mode: CardMode },
+}
+
+struct Settings {
+    domain: String,
+    name: Option<String>,
+    storage: KeyStorage,
+    cipher_suite: Option<String>,
+    revocations: Option<PathBuf>,
+
+    /// Front and back database files, for a split mode setup
+    split: Option<(PathBuf, PathBuf)>,
+}
+
+/// Read one line from stdin (fails at end of input)
+fn read_line() -> Result<String> {
+    std::io::stdout().flush()?;
+
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line)? == 0 {
+        return Err(anyhow::anyhow!("Aborted CA initialization."));
+    }
+
+    Ok(line.trim().to_string())
+}
+
+/// Ask for a line of text, an empty answer selects `default`
+fn prompt(question: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) => print!("{question} [{default}]: "),
+        None => print!("{question}: "),
+    }
+
+    let answer = read_line()?;
+    if answer.is_empty() {
+        Ok(default.unwrap_or_default().to_string())
+    } else {
+        Ok(answer)
+    }
+}
+
+fn prompt_yes_no(lang: Language, question: &str, default: bool) -> Result<bool> {
+    let hint = if default { "Y/n" } else { "y/N" };
+
+    loop {
+        print!("{question} [{hint}]: ");
+
+        match read_line()?.to_ascii_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("{}", tr(lang, Msg::WizardYesNo, &[])),
+        }
+    }
+}
+
+/// Ask for one of `options` (by number or by name), returns the index
+fn prompt_choice(
+    lang: Language,
+    question: &str,
+    options: &[(&str, String)],
+    default: usize,
+) -> Result<usize> {
+    println!("{question}");
+    for (i, (name, description)) in options.iter().enumerate() {
+        println!("  {}) {name:<10} {description}", i + 1);
+    }
+
+    loop {
+        let answer = prompt(&tr(lang, Msg::WizardChoice, &[]), Some(options[default].0))?;
+
+        if let Ok(n) = answer.parse::<usize>() {
+            if (1..=options.len()).contains(&n) {
+                return Ok(n - 1);
+            }
+        }
+        if let Some(i) = options.iter().position(|(name, _)| *name == answer) {
+            return Ok(i);
+        }
+
+        println!("{}", tr(lang, Msg::WizardChooseListed, &[]));
+    }
+}
+
+/// Ask for the name of a file that doesn't exist yet
+fn prompt_new_file(lang: Language, question: Msg, default: &str) -> Result<PathBuf> {
+    loop {
+        let path = PathBuf::from(prompt(&tr(lang, question, &[]), Some(default))?);
+
+        if path.exists() {
+            let path = format!("{path:?}");
+            println!("{}", tr(lang, Msg::WizardFileExists, &[("path", &path)]));
+        } else {
+            return Ok(path);
+        }
+    }
+}
+
+/// Ask for the name of an existing file
+fn prompt_existing_file(lang: Language, question: Msg) -> Result<PathBuf> {
+    loop {
+        let path = PathBuf::from(prompt(&tr(lang, question, &[]), None)?);
+
+        if path.is_file() {
+            return Ok(path);
+        } else {
+            let path = format!("{path:?}");
+            println!("{}", tr(lang, Msg::WizardNotAFile, &[("path", &path)]));
+        }
+    }
+}
+
+/// Pick one card from `cards`
+fn choose_card(lang: Language, cards: Vec<String>, kind: CardKind) -> Result<String> {
+    let (name, using, multiple) = match kind {
+        CardKind::Blank => ("blank", Msg::WizardUsingBlankCard, Msg::WizardBlankCards),
+        CardKind::Matching => (
+            "matching",
+            Msg::WizardUsingMatchingCard,
+            Msg::WizardMatchingCards,
+        ),
+    };
+
+    match cards.len() {
+        0 => Err(anyhow::anyhow!(
+            "No {name} OpenPGP card found, please connect one and restart the setup"
+        )),
+        1 => {
+            println!("{}", tr(lang, using, &[("ident", &cards[0])]));
+            Ok(cards[0].clone())
+        }
+        _ => {
+            let options: Vec<_> = cards.iter().map(|c| (c.as_str(), String::new())).collect();
+            let i = prompt_choice(lang, &tr(lang, multiple, &[]), &options, 0)?;
+            Ok(cards[i].clone())
+        }
+    }
+}
+
+fn ask_cipher_suite(lang: Language) -> Result<String> {
+    let options: Vec<_> = CIPHER_SUITES
+        .iter()
+        .enumerate()
+        .map(|(i, (name, description))| match i {
+            0 => (
+                *name,
+                tr(lang, Msg::WizardRecommended, &[("name", description)]),
+            ),
+            _ => (*name, description.to_string()),
+        })
+        .collect();
+
+    println!();
+    let i = prompt_choice(lang, &tr(lang, Msg::WizardCipherSuite, &[]), &options, 0)?;
+
+    Ok(CIPHER_SUITES[i].0.to_string())
+}
+
+/// Ask how the CA key should be stored on an OpenPGP card
+fn ask_card(lang: Language, domain: &str) -> Result<(KeyStorage, Option<String>)> {
+    println!();
+    let mode = prompt_choice(
+        lang,
+        &tr(lang, Msg::WizardCardMode, &[]),
+        &[
+            ("host", tr(lang, Msg::WizardCardModeHost, &[])),
+            ("on-card", tr(lang, Msg::WizardCardModeOnCard, &[])),
+            ("import", tr(lang, Msg::WizardCardModeImport, &[])),
+            ("from-card", tr(lang, Msg::WizardCardModeFromCard, &[])),
+        ],
+        0,
+    )?;
+
+    let mut cipher_suite = None;
+
+    let (ident, mode) = match mode {
+        0 => {
+            cipher_suite = Some(ask_cipher_suite(lang)?);
+
+            println!();
+            println!("{}", tr(lang, Msg::WizardBackupNote, &[]));
+            let backup =
+                prompt_new_file(lang, Msg::WizardBackupFile, &format!("{domain}-ca-key.asc"))?;
+
+            let ident = choose_card(lang, openpgp_ca_lib::blank_cards()?, CardKind::Blank)?;
+
+            (ident, CardMode::GenerateOnHost { backup })
+        }
+        1 => {
+            println!();
+            println!("{}", tr(lang, Msg::CardOnCardNote, &[]));
+
+            let ident = choose_card(lang, openpgp_ca_lib::blank_cards()?, CardKind::Blank)?;
+
+            (ident, CardMode::GenerateOnCard)
+        }
+        2 => {
+            let key = prompt_existing_file(lang, Msg::WizardPrivateKeyFile)?;
+            let ident = choose_card(lang, openpgp_ca_lib::blank_cards()?, CardKind::Blank)?;
+
+            (ident, CardMode::Import { key })
+        }
+        _ => {
+            let public_key = prompt_existing_file(lang, Msg::WizardPublicKeyFile)?;
+
+            let cert = std::fs::read(&public_key)?;
+            let ident = choose_card(
+                lang,
+                openpgp_ca_lib::matching_cards(&cert)?,
+                CardKind::Matching,
+            )?;
+
+            (ident, CardMode::FromCard { public_key })
+        }
+    };
+
+    Ok((KeyStorage::Card { ident, mode }, cipher_suite))
+}
+
+fn ask(lang: Language, db: Option<&str>) -> Result<Settings> {
+    println!();
+    let domain = loop {
+        let domain = prompt(&tr(lang, Msg::WizardDomain, &[]), None)?;
+
+        match Uninit::check_domainname(&domain) {
+            Ok(()) => break domain,
+            Err(e) => println!("{e:#}"),
+        }
+    };
+
+    let name = prompt(&tr(lang, Msg::WizardName, &[]), None)?;
+    let name = (!name.is_empty()).then_some(name);
+
+    println!();
+    let backend = prompt_choice(
+        lang,
+        &tr(lang, Msg::WizardBackend, &[]),
+        &[
+            ("softkey", tr(lang, Msg::WizardBackendSoftkey, &[])),
+            ("card", tr(lang, Msg::WizardBackendCard, &[])),
+            ("split", tr(lang, Msg::WizardBackendSplit, &[])),
+        ],
+        0,
+    )?;
+
+    let (storage, cipher_suite, split) = match backend {
+        0 => (KeyStorage::Softkey, Some(ask_cipher_suite(lang)?), None),
+        1 => {
+            let (storage, cipher_suite) = ask_card(lang, &domain)?;
+            (storage, cipher_suite, None)
+        }
+        _ => {
+            println!();
+            let on_card = prompt_yes_no(lang, &tr(lang, Msg::WizardBackOnCard, &[]), false)?;
+            let (storage, cipher_suite) = if on_card {
+                ask_card(lang, &domain)?
+            } else {
+                (KeyStorage::Softkey, Some(ask_cipher_suite(lang)?))
+            };
+
+            println!();
+            println!("{}", tr(lang, Msg::WizardSplitNote, &[]));
+            let front =
+                prompt_new_file(lang, Msg::WizardFrontFile, &format!("{domain}-front.oca"))?;
+            let back = prompt_new_file(lang, Msg::WizardBackFile, &format!("{domain}-back.oca"))?;
+
+            if front == back
+                || db.map(Path::new) == Some(&front)
+                || db.map(Path::new) == Some(&back)
+            {
+                return Err(anyhow::anyhow!(
+                    "The front, back and original database files must all be different"
+                ));
+            }
+
+            (storage, cipher_suite, Some((front, back)))
+        }
+    };
+
+    println!();
+    println!("{}", tr(lang, Msg::WizardRevocationsNote, &[]));
+    let generate = prompt_yes_no(lang, &tr(lang, Msg::WizardRevocationsGenerate, &[]), true)?;
+    let revocations = if generate {
+        Some(prompt_new_file(
+            lang,
+            Msg::WizardRevocationsFile,
+            &format!("{domain}-revocations.asc"),
+        )?)
+    } else {
+        None
+    };
+
+    Ok(Settings {
+        domain,
+        name,
+        storage,
+        cipher_suite,
+        revocations,
+        split,
+    })
+}
+
+fn show(lang: Language, settings: &Settings) {
+    let mut rows = vec![(Msg::WizardSummaryDomain, settings.domain.clone())];
+    if let Some(name) = &settings.name {
+        rows.push((Msg::WizardSummaryName, name.clone()));
+    }
+
+    let key = match &settings.storage {
+        KeyStorage::Softkey => tr(lang, Msg::WizardKeySoftkey, &[]),
+        KeyStorage::Card { ident, mode } => {
+            let (msg, path) = match mode {
+                CardMode::GenerateOnHost { backup } => (Msg::WizardModeHost, Some(backup)),
+                CardMode::GenerateOnCard => (Msg::WizardModeOnCard, None),
+                CardMode::Import { key } => (Msg::WizardModeImport, Some(key)),
+                CardMode::FromCard { public_key } => (Msg::WizardModeFromCard, Some(public_key)),
+            };
+            let path = path.map(|p| format!("{p:?}")).unwrap_or_default();
+            let mode = tr(lang, msg, &[("path", &path)]);
+
+            tr(
+                lang,
+                Msg::WizardKeyCard,
+                &[("ident", ident), ("mode", &mode)],
+            )
+        }
+    };
+    rows.push((Msg::WizardSummaryKey, key));
+
+    if let Some(cipher_suite) = &settings.cipher_suite {
+        rows.push((Msg::WizardSummaryCipherSuite, cipher_suite.clone()));
+    }
+    let revocations = match &settings.revocations {
+        Some(path) => format!("{path:?}"),
+        None => tr(lang, Msg::WizardRevocationsNone, &[]),
+    };
+    rows.push((Msg::WizardSummaryRevocations, revocations));
+    if let Some((front, back)) = &settings.split {
+        let split = tr(
+            lang,
+            Msg::WizardSplitValue,
+            &[
+                ("front", &format!("{front:?}")),
+                ("back", &format!("{back:?}")),
+            ],
+        );
+        rows.push((Msg::WizardSummarySplit, split));
+    }
+
+    let labels: Vec<_> = rows
+        .iter()
+        .map(|(msg, _)| format!("{}:", tr(lang, *msg, &[])))
+        .collect();
+    let width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+    println!();
+    println!("{}", tr(lang, Msg::WizardSummary, &[]));
+    for (label, (_, value)) in labels.iter().zip(&rows) {
+        println!("  {label:<width$} {value}");
+    }
+}
+
+fn initialize(lang: Language, cau: Uninit, settings: &Settings) -> Result<Oca> {
+    let domain = &settings.domain;
+    let name = settings.name.as_deref();
+
+    let cipher_suite = match &settings.cipher_suite {
+        Some(cs) => Some(CipherSuite::from_str(cs).map_err(|e| anyhow::anyhow!(e))?),
+        None => None,
+    };
+
+    match &settings.storage {
+        KeyStorage::Softkey => cau.init_softkey(domain, name, cipher_suite),
+        KeyStorage::Card { ident, mode } => match mode {
+            CardMode::GenerateOnHost { backup } => {
+                // Create the backup file before generating the key, so that
+                // a problem with the file doesn't leave us with a card-only key
+                let mut file = create_private_file(backup)?;
+
+                let (ca, key) =
+                    match cau.init_card_generate_on_host(ident, domain, name, cipher_suite) {
+                        Ok(res) => res,
+                        Err(e) => {
+                            let _ = std::fs::remove_file(backup);
+                            return Err(e);
+                        }
+                    };
+
+                if let Err(e) = file.write_all(key.as_bytes()) {
+                    println!(
+                        "{}",
+                        tr(
+                            lang,
+                            Msg::WizardBackupFailed,
+                            &[("error", &e), ("key", &key)]
+                        )
+                    );
+                }
+
+                Ok(ca)
+            }
+            CardMode::GenerateOnCard => cau.init_card_generate_on_card(ident, domain, name, None),
+            CardMode::Import { key } => {
+                let key = std::fs::read(key)?;
+                cau.init_card_import_key(ident, domain, &key)
+            }
+            CardMode::FromCard { public_key } => {
+                let cert = std::fs::read(public_key)?;
+
+                let pin = rpassword::prompt_password(tr(
+                    lang,
+                    Msg::CardUserPinPrompt,
+                    &[("ident", ident)],
+                ))?;
+                println!();
+
+                cau.init_card_import_card(ident, &pin, domain, &cert)
+            }
+        },
+    }
+}
+
+/// Run the guided setup for a new CA instance in the database `db`
+pub(crate) fn init(lang: Language, db: Option<&str>) -> Result<()> {
+    let cau = Uninit::new(db)?;
+    if cau.is_initialized()? {
+        return Err(anyhow::anyhow!("The CA database is already initialized"));
+    }
+
+    println!("{}", tr(lang, Msg::WizardIntro, &[]));
+
+    let settings = ask(lang, db)?;
+    show(lang, &settings);
+
+    println!();
+    println!("{}", tr(lang, Msg::WizardConfirm, &[]));
+    if !read_line()?.eq_ignore_ascii_case("yes") {
+        return Err(anyhow::anyhow!("Aborted CA initialization."));
+    }
+    println!();
+
+    let ca = initialize(lang, cau, &settings)?;
+
+    println!("{}\n", tr(lang, Msg::Initialized, &[]));
+    crate::print_ca_info(lang, &ca.ca_info()?);
+
+    if let Some(path) = &settings.revocations {
+        ca.ca_generate_revocations(path.clone(), ExportCompat::default())?;
+        println!();
+        let path = format!("{path:?}");
+        println!(
+            "{}",
+            tr(lang, Msg::WizardRevocationsWritten, &[("path", &path)])
+        );
+    }
+
+    if let Some((front, back)) = &settings.split {
+        ca.ca_split_into(front, back)?;
+
+        println!();
+        println!(
+            "{}",
+            tr(
+                lang,
+                Msg::WizardSplitDone,
+                &[
+                    ("front", &format!("{front:?}")),
+                    ("back", &format!("{back:?}")),
+                ]
+            )
+        );
+    }
+
+    if let KeyStorage::Card {
+        mode: CardMode::GenerateOnHost { backup },
+        ..
+    } = &settings.storage
+    {
+        println!();
+        let path = format!("{backup:?}");
+        println!(
+            "{}",
+            tr(lang, Msg::WizardBackupReminder, &[("path", &path)])
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+/*
+ * SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+ * SPDX-License-Identifier: GPL-3.0-or-later
+ *
+ * This file is part of OpenPGP CA
+ * https://gitlab.com/openpgp-ca/openpgp-ca
+ *
+ * C interface to OpenPGP CA.
+ *
+ * Operations are invoked by name with oca_call(), parameters and results
+ * are JSON documents. oca_call() and oca_close() return an object with
+ * either a "result" or an "error" member.
+ *
+ * Methods: "ca.info", "users.new", "certs.import", "certs.check",
+ * "certs.status", "revocations.add", "revocations.apply",
+ * "revocations.list", "export.wkd", "export.certring", "methods".
+ *
+ * All strings returned by the library must be released with
+ * oca_string_free(). A handle must only be used from one thread at a time.
+ */
+
+#ifndef OPENPGP_CA_H
+#define OPENPGP_CA_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct OcaHandle oca_ca;
+
+/* Open the initialized CA in the database file "db".
+ * Returns NULL on failure, and sets *error (if error is not NULL). */
+oca_ca *oca_open(const char *db, char **error);
+
+/* Initialize a new CA in "db". "params" is a JSON object:
+ * {"domain": "example.org", "name": "Example CA"} ("name" is optional).
+ * Returns NULL on failure, and sets *error (if error is not NULL). */
+oca_ca *oca_init(const char *db, const char *params, char **error);
+
+/* Invoke "method" with the JSON parameters "params" (may be NULL). */
+char *oca_call(oca_ca *ca, const char *method, const char *params);
+
+/* Close a CA handle (NULL is ignored). Checkpoints the database, and
+ * returns {"result": null} or {"error": ...}. The handle is released in
+ * both cases. */
+char *oca_close(oca_ca *ca);
+
+/* Release a string returned by the library (NULL is ignored). */
+void oca_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* OPENPGP_CA_H */
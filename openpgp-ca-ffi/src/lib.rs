@@ -0,0 +1,229 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! C bindings for OpenPGP CA, for embedding the core CA operations in
+//! applications that are not written in Rust (e.g. via the FFI of Python,
+//! Go or Java).
+//!
+//! The interface consists of a handful of functions (see
+//! `include/openpgp-ca.h`): a CA is opened with [oca_open] (or created with
+//! [oca_init]), and operations on it are invoked by name with [oca_call].
+//! Parameters and results are exchanged as JSON documents, so that the C
+//! ABI stays stable while methods are added.
+//!
+//! The methods are those of [openpgp_ca_lib::api] (the JSON-RPC interface
+//! of restd offers the same methods), plus "methods", which lists them.
+//!
+//! The result of [oca_call] and [oca_close] is an object with either a
+//! "result" or an "error" member. All strings returned by this library are
+//! owned by the caller, and must be released with [oca_string_free].
+//!
+//! A CA handle must only be used from one thread at a time.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use anyhow::{Context, Result};
+use openpgp_ca_lib::{api, Oca, Uninit};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// An opened OpenPGP CA instance (opaque to C callers)
+pub struct OcaHandle {
+    ca: Oca,
+}
+
+/// Parameters of [oca_init]
+#[derive(Debug, Deserialize)]
+pub struct InitParams {
+    pub domain: String,
+
+    /// Name for the User ID of the CA
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// The methods that [oca_call] offers
+fn methods() -> Vec<&'static str> {
+    api::METHODS
+        .iter()
+        .copied()
+        .chain(std::iter::once("methods"))
+        .collect()
+}
+
+/// Convert the C string `s` to a `&str` (fails for NULL or invalid UTF-8)
+///
+/// # Safety
+///
+/// `s` must be NULL or point to a NUL-terminated string that outlives the
+/// returned reference.
+unsafe fn to_str<'a>(s: *const c_char) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(anyhow::anyhow!("Unexpected NULL string"));
+    }
+
+    CStr::from_ptr(s)
+        .to_str()
+        .context("String is not valid UTF-8")
+}
+
+/// Hand `s` over to the C caller (to be released with [oca_string_free])
+fn to_c_string(s: String) -> *mut c_char {
+    // JSON output never contains NUL bytes, but error messages might
+    CString::new(s.replace('\0', ""))
+        .expect("NUL bytes were removed")
+        .into_raw()
+}
+
+/// Run `f`, turning errors and panics into an error message
+fn guard<T>(f: impl FnOnce() -> Result<T>) -> Result<T, String> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(res)) => Ok(res),
+        Ok(Err(e)) => Err(format!("{e:#}")),
+        Err(_) => Err("Internal error (panic) in OpenPGP CA".to_string()),
+    }
+}
+
+/// The JSON object for `res`, with either a "result" or an "error" member
+fn response(res: Result<Value, String>) -> *mut c_char {
+    let response = match res {
+        Ok(result) => json!({ "result": result }),
+        Err(error) => json!({ "error": error }),
+    };
+
+    to_c_string(response.to_string())
+}
+
+/// Store the result of `res` as a handle, or set `*error` to the error
+/// message
+unsafe fn handle_or_error(res: Result<Oca, String>, error: *mut *mut c_char) -> *mut OcaHandle {
+    match res {
+        Ok(ca) => Box::into_raw(Box::new(OcaHandle { ca })),
+        Err(msg) => {
+            if !error.is_null() {
+                *error = to_c_string(msg);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Open the initialized CA in the database file `db`.
+///
+/// Returns NULL on failure, and sets `*error` to an error message (if
+/// `error` is not NULL).
+///
+/// # Safety
+///
+/// `db` must be a NUL-terminated string. `error` must be NULL or point to
+/// a writable `char*`.
+#[no_mangle]
+pub unsafe extern "C" fn oca_open(db: *const c_char, error: *mut *mut c_char) -> *mut OcaHandle {
+    let res = guard(|| Oca::open(Some(to_str(db)?)));
+
+    handle_or_error(res, error)
+}
+
+/// Initialize a new CA (with a softkey backend) in the database file `db`.
+///
+/// `params` is a JSON object with the members "domain" and (optionally)
+/// "name". Returns NULL on failure, and sets `*error` to an error message
+/// (if `error` is not NULL).
+///
+/// # Safety
+///
+/// `db` and `params` must be NUL-terminated strings. `error` must be NULL
+/// or point to a writable `char*`.
+#[no_mangle]
+pub unsafe extern "C" fn oca_init(
+    db: *const c_char,
+    params: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut OcaHandle {
+    let res = guard(|| {
+        let p: InitParams = serde_json::from_str(to_str(params)?).context("Invalid parameters")?;
+
+        Uninit::new(Some(to_str(db)?))?.init_softkey(&p.domain, p.name.as_deref(), None)
+    });
+
+    handle_or_error(res, error)
+}
+
+/// Invoke `method` on the CA `handle`, with the JSON parameters `params`
+/// (NULL for methods that take none).
+///
+/// Returns a JSON object with either a "result" or an "error" member.
+///
+/// # Safety
+///
+/// `handle` must have been returned by [oca_open] or [oca_init], and not
+/// been closed. `method` and `params` (if not NULL) must be NUL-terminated
+/// strings.
+#[no_mangle]
+pub unsafe extern "C" fn oca_call(
+    handle: *mut OcaHandle,
+    method: *const c_char,
+    params: *const c_char,
+) -> *mut c_char {
+    let res = guard(|| {
+        let handle = handle
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Unexpected NULL handle"))?;
+
+        let params = if params.is_null() {
+            Value::Null
+        } else {
+            serde_json::from_str(to_str(params)?).context("Invalid JSON parameters")?
+        };
+
+        match to_str(method)? {
+            "methods" => Ok(json!(methods())),
+            method => api::call(&handle.ca, method, params).map_err(Into::into),
+        }
+    });
+
+    response(res)
+}
+
+/// Close the CA `handle` (NULL is ignored).
+///
+/// Checkpoints the write-ahead log of the database, and releases the
+/// handle. Returns a JSON object with either a "result" (null) or an
+/// "error" member. The handle is released in both cases.
+///
+/// # Safety
+///
+/// `handle` must have been returned by [oca_open] or [oca_init], and must
+/// not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn oca_close(handle: *mut OcaHandle) -> *mut c_char {
+    let res = guard(|| {
+        if !handle.is_null() {
+            let OcaHandle { ca } = *Box::from_raw(handle);
+            ca.close()?;
+        }
+
+        Ok(Value::Null)
+    });
+
+    response(res)
+}
+
+/// Release a string that was returned by this library (NULL is ignored).
+///
+/// # Safety
+///
+/// `s` must have been returned by this library, and must not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn oca_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
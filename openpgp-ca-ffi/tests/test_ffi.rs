@@ -0,0 +1,151 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+
+use openpgp_ca::{oca_call, oca_close, oca_init, oca_open, oca_string_free, OcaHandle};
+use sequoia_openpgp::armor::{Kind, Writer};
+use sequoia_openpgp::cert::CertBuilder;
+use sequoia_openpgp::serialize::{Marshal, SerializeInto};
+use serde_json::{json, Value};
+
+fn take_string(s: *mut c_char) -> String {
+    assert!(!s.is_null());
+    let res = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+    unsafe { oca_string_free(s) };
+    res
+}
+
+fn call(ca: *mut OcaHandle, method: &str, params: Value) -> Value {
+    let method = CString::new(method).unwrap();
+    let params = CString::new(params.to_string()).unwrap();
+
+    let res = unsafe { oca_call(ca, method.as_ptr(), params.as_ptr()) };
+    serde_json::from_str(&take_string(res)).unwrap()
+}
+
+#[test]
+fn test_ffi() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = CString::new(dir.path().join("ca.sqlite").to_str().unwrap()).unwrap();
+
+    // -- opening an uninitialized CA fails --
+    let mut error: *mut c_char = ptr::null_mut();
+    let ca = unsafe { oca_open(db.as_ptr(), &mut error) };
+    assert!(ca.is_null());
+    assert!(!take_string(error).is_empty());
+
+    let params = CString::new(json!({"domain": "example.org"}).to_string()).unwrap();
+    let ca = unsafe { oca_init(db.as_ptr(), params.as_ptr(), ptr::null_mut()) };
+    assert!(!ca.is_null());
+
+    // -- ca.info --
+    let res = call(ca, "ca.info", Value::Null);
+    assert_eq!(res["result"]["domain"], "example.org");
+    assert_eq!(res["result"]["email"], "openpgp-ca@example.org");
+
+    // -- users.new --
+    let res = call(
+        ca,
+        "users.new",
+        json!({"name": "Bob", "emails": ["bob@example.org"], "password": true}),
+    );
+    assert!(res["result"]["private_key"]
+        .as_str()
+        .unwrap()
+        .contains("PRIVATE KEY"));
+    assert!(res["result"]["password"].is_string());
+    let bob_fp = res["result"]["fingerprint"].as_str().unwrap().to_string();
+
+    let res = call(ca, "certs.check", json!({"fingerprint": bob_fp}));
+    assert_eq!(res["result"]["certified"], json!(["Bob <bob@example.org>"]));
+    assert_eq!(res["result"]["tsigned_ca"], true);
+
+    // -- certs.import --
+    let (alice, rev) = CertBuilder::general_purpose(None, Some("Alice <alice@example.org>"))
+        .generate()
+        .unwrap();
+    let alice_fp = alice.fingerprint().to_hex();
+    let alice_armored = String::from_utf8(alice.armored().to_vec().unwrap()).unwrap();
+
+    let mut w = Writer::new(vec![], Kind::Signature).unwrap();
+    sequoia_openpgp::Packet::from(rev)
+        .serialize(&mut w)
+        .unwrap();
+    let rev_armored = String::from_utf8(w.finalize().unwrap()).unwrap();
+
+    let res = call(
+        ca,
+        "certs.import",
+        json!({"cert": alice_armored, "name": "Alice", "emails": ["alice@example.org"]}),
+    );
+    assert_eq!(res["result"], alice_fp);
+
+    let res = call(ca, "certs.check", json!({"fingerprint": alice_fp}));
+    assert_eq!(
+        res["result"]["certified"],
+        json!(["Alice <alice@example.org>"])
+    );
+    assert_eq!(res["result"]["tsigned_ca"], false);
+
+    // -- revocations --
+    let res = call(ca, "revocations.add", json!({"revocation": rev_armored}));
+    assert_eq!(res["result"]["published"], false);
+    assert_eq!(res["result"]["fingerprint"], alice_fp);
+    let hash = res["result"]["hash"].as_str().unwrap().to_string();
+
+    let res = call(ca, "revocations.apply", json!({ "hash": hash }));
+    assert_eq!(res["result"]["published"], true);
+
+    let res = call(ca, "revocations.list", json!({"fingerprint": alice_fp}));
+    assert_eq!(res["result"].as_array().unwrap().len(), 1);
+
+    // -- exports --
+    let certring = dir.path().join("certring.asc");
+    let res = call(ca, "export.certring", json!({ "path": certring }));
+    assert!(res.get("error").is_none());
+    assert!(certring.exists());
+
+    let wkd = dir.path().join("wkd");
+    let res = call(ca, "export.wkd", json!({ "path": wkd }));
+    assert!(res.get("error").is_none());
+    assert!(wkd.join(".well-known/openpgpkey/example.org").exists());
+
+    // -- errors --
+    let res = call(ca, "no.such.method", Value::Null);
+    assert!(res["error"].as_str().unwrap().contains("Unknown method"));
+
+    let res = call(ca, "certs.check", json!({}));
+    assert!(res["error"].is_string());
+
+    let res: Value = serde_json::from_str(&take_string(unsafe { oca_close(ca) })).unwrap();
+    assert_eq!(res, json!({ "result": null }));
+
+    // -- the CA can be opened again --
+    let ca = unsafe { oca_open(db.as_ptr(), ptr::null_mut()) };
+    assert!(!ca.is_null());
+
+    let res = call(ca, "methods", Value::Null);
+    assert!(res["result"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("certs.import")));
+
+    let res = call(ca, "certs.status", json!({ "fingerprint": alice_fp }));
+    assert!(res["result"]["status"]
+        .as_str()
+        .unwrap()
+        .contains("revoked"));
+
+    take_string(unsafe { oca_close(ca) });
+
+    // closing NULL is a no-op
+    let res: Value =
+        serde_json::from_str(&take_string(unsafe { oca_close(ptr::null_mut()) })).unwrap();
+    assert_eq!(res["result"], Value::Null);
+}
@@ -0,0 +1,37 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Add "cert_versions" table for historical versions of certs,
+-- and a "prefs" table for CA-wide settings
+
+-- Previous versions of Certificates
+--
+-- Whenever the armored representation of a cert in the "certs" table is
+-- replaced, the prior version is stored in this table.
+CREATE TABLE cert_versions (
+  id INTEGER NOT NULL PRIMARY KEY,
+  pub_cert VARCHAR NOT NULL, -- the armored cert, as it was before the update
+  created TIMESTAMP NOT NULL, -- time at which this version was superseded
+  origin VARCHAR NOT NULL, -- the operation that superseded this version (e.g. "import", "wkd")
+
+  cert_id INTEGER NOT NULL,
+  FOREIGN KEY(cert_id) REFERENCES certs(id)
+);
+
+-- cert_versions.cert_id is used for lookups, so we create an index
+CREATE INDEX idx_cert_versions_cert_id
+ON cert_versions (cert_id);
+
+
+-- CA-wide settings, as name/value pairs
+CREATE TABLE prefs (
+  id INTEGER NOT NULL PRIMARY KEY,
+  name VARCHAR NOT NULL,
+  value VARCHAR NOT NULL,
+
+  CONSTRAINT prefs_name_unique UNIQUE (name)
+);
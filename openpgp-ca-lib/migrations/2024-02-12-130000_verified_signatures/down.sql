@@ -0,0 +1,7 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+
+DROP TABLE if exists verified_signatures;
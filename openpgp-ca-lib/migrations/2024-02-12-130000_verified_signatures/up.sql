@@ -0,0 +1,28 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Cache for the results of cryptographic verification of signatures on
+-- user certs (e.g. CA certifications).
+--
+-- Entries are only valid for the current version of a cert: they get
+-- removed when the cert in the "certs" table is updated.
+--
+-- Each result is cached only once per cert (concurrent certification checks
+-- may try to cache the same result).
+CREATE TABLE verified_signatures (
+  id INTEGER NOT NULL PRIMARY KEY,
+  digest VARCHAR NOT NULL, -- SHA256 over the signed component and the signature packet
+  verified_by VARCHAR NOT NULL, -- fingerprint of the key that the signature was verified with
+
+  cert_id INTEGER NOT NULL,
+  FOREIGN KEY(cert_id) REFERENCES certs(id),
+  UNIQUE(cert_id, digest)
+);
+
+-- verified_signatures.cert_id is used for lookups, so we create an index
+CREATE INDEX idx_verified_signatures_cert_id
+ON verified_signatures (cert_id);
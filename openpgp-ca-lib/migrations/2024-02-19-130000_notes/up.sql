@@ -0,0 +1,14 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Free-form notes by the CA admins, on users and on certs
+
+ALTER TABLE users
+  ADD COLUMN notes VARCHAR;
+
+ALTER TABLE certs
+  ADD COLUMN notes VARCHAR;
@@ -0,0 +1,24 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Audit trail of certs that were moved from one user to another
+CREATE TABLE cert_reassignments (
+  id INTEGER NOT NULL PRIMARY KEY,
+  created TIMESTAMP NOT NULL,
+  from_user_id INTEGER, -- previous user of the cert (NULL if the cert had no user)
+  to_user_id INTEGER NOT NULL,
+  reason VARCHAR, -- free-form explanation by the CA admin
+
+  cert_id INTEGER NOT NULL,
+  FOREIGN KEY(cert_id) REFERENCES certs(id),
+  FOREIGN KEY(from_user_id) REFERENCES users(id),
+  FOREIGN KEY(to_user_id) REFERENCES users(id)
+);
+
+-- cert_reassignments.cert_id is used for lookups, so we create an index
+CREATE INDEX idx_cert_reassignments_cert_id
+ON cert_reassignments (cert_id);
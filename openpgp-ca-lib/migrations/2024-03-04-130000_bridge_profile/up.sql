@@ -0,0 +1,10 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Certification profile that the trust signature of a bridge was made with
+ALTER TABLE bridges
+  ADD COLUMN profile VARCHAR NOT NULL DEFAULT 'default';
@@ -0,0 +1,18 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Certs whose stored armored cert couldn't be parsed or recovered.
+-- Quarantined certs are left out of listings, until they are released
+-- after manual handling.
+CREATE TABLE cert_quarantine (
+  id INTEGER NOT NULL PRIMARY KEY,
+  created TIMESTAMP NOT NULL,
+  error VARCHAR NOT NULL, -- why the armored cert couldn't be parsed
+
+  cert_id INTEGER UNIQUE NOT NULL,
+  FOREIGN KEY(cert_id) REFERENCES certs(id)
+);
@@ -0,0 +1,23 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Replacement certs that users submitted for one of their certs, along
+-- with a handover statement signed by the old key. A replacement cert is
+-- only stored as a user cert after an admin approved the request.
+CREATE TABLE key_replacements (
+  id INTEGER NOT NULL PRIMARY KEY,
+  created TIMESTAMP NOT NULL,
+  new_fingerprint VARCHAR NOT NULL,
+  new_cert VARCHAR NOT NULL, -- armored replacement cert
+  handover VARCHAR NOT NULL, -- armored signature by the old key
+  status VARCHAR NOT NULL, -- "pending", "approved" or "rejected"
+  decided TIMESTAMP,
+  reason VARCHAR, -- why the request was rejected
+
+  cert_id INTEGER NOT NULL, -- the cert that is replaced
+  FOREIGN KEY(cert_id) REFERENCES certs(id)
+);
@@ -0,0 +1,18 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Keys and domains that must never be imported or certified.
+-- Entries are not deleted: a removed entry is marked with the time of its
+-- removal, so that the table is a log of all changes to the blocklist.
+CREATE TABLE blocklist (
+  id INTEGER NOT NULL PRIMARY KEY,
+  created TIMESTAMP NOT NULL,
+  kind VARCHAR NOT NULL, -- "fingerprint" or "domain"
+  pattern VARCHAR NOT NULL, -- fingerprint, or domain (optionally "*.domain")
+  reason VARCHAR,
+  removed TIMESTAMP
+);
@@ -0,0 +1,12 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Regular expressions that scope the trust signature of a bridge, as a JSON
+-- array (an empty array for unscoped bridges).
+-- NULL for bridges that were created before the scopes were stored.
+ALTER TABLE bridges
+  ADD COLUMN scope_regexes VARCHAR;
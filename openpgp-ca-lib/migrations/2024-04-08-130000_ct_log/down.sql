@@ -0,0 +1,9 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+
+DROP TRIGGER if exists ct_log_no_update;
+DROP TRIGGER if exists ct_log_no_delete;
+DROP TABLE if exists ct_log;
@@ -0,0 +1,28 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Append-only log of the certifications and revocations that the CA issues.
+-- Each entry contains the hash of the previous entry (see "ct_log.rs").
+CREATE TABLE ct_log (
+  id INTEGER NOT NULL PRIMARY KEY, -- sequence number, starting at 1
+  created TIMESTAMP NOT NULL,
+  kind VARCHAR NOT NULL,
+  fingerprint VARCHAR NOT NULL, -- of the cert that the signature is on
+  signature VARCHAR NOT NULL, -- base64 encoded signature packet
+  prev_hash VARCHAR NOT NULL,
+  hash VARCHAR NOT NULL
+);
+
+CREATE TRIGGER ct_log_no_update BEFORE UPDATE ON ct_log
+BEGIN
+  SELECT RAISE(ABORT, 'ct_log is append-only');
+END;
+
+CREATE TRIGGER ct_log_no_delete BEFORE DELETE ON ct_log
+BEGIN
+  SELECT RAISE(ABORT, 'ct_log is append-only');
+END;
@@ -0,0 +1,10 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Users can opt out of the publication of their certs via WKD
+ALTER TABLE users
+  ADD COLUMN wkd_publish BOOLEAN NOT NULL DEFAULT 1;
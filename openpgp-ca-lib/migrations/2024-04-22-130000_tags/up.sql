@@ -0,0 +1,19 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Key-value metadata on users and certs (e.g. department, employee ID).
+-- Each tag belongs to either a user or a cert.
+CREATE TABLE tags (
+  id INTEGER NOT NULL PRIMARY KEY,
+  user_id INTEGER REFERENCES users(id),
+  cert_id INTEGER REFERENCES certs(id),
+  name VARCHAR NOT NULL,
+  value VARCHAR NOT NULL,
+  CHECK ((user_id IS NULL) <> (cert_id IS NULL)),
+  UNIQUE (user_id, name),
+  UNIQUE (cert_id, name)
+);
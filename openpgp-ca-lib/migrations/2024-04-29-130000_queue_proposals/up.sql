@@ -0,0 +1,16 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Besides the requests of a split mode front instance for the back
+-- instance ("split"), the queue holds changes that were proposed via restd
+-- in approval mode, and wait for a decision by a CA admin ("proposal").
+ALTER TABLE queue ADD COLUMN kind VARCHAR NOT NULL DEFAULT 'split';
+
+-- Proposals only: "pending", "approved" or "rejected"
+ALTER TABLE queue ADD COLUMN status VARCHAR;
+ALTER TABLE queue ADD COLUMN decided TIMESTAMP;
+ALTER TABLE queue ADD COLUMN reason VARCHAR;
@@ -0,0 +1,22 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- State of the updates of each cert from a public source ("wkd" or
+-- "keyserver"), so that an interrupted update run can be resumed, and certs
+-- that can't be updated for a long time can be reported.
+CREATE TABLE sync_state (
+  id INTEGER NOT NULL PRIMARY KEY,
+  source VARCHAR NOT NULL,
+  last_attempt TIMESTAMP NOT NULL,
+  last_success TIMESTAMP,
+  last_error VARCHAR,
+  -- time of the first failed attempt after the last successful one
+  failing_since TIMESTAMP,
+  cert_id INTEGER NOT NULL,
+  FOREIGN KEY(cert_id) REFERENCES certs(id),
+  UNIQUE (cert_id, source)
+);
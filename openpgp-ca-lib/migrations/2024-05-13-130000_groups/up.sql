@@ -0,0 +1,21 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Named groups of users (e.g. departments), so that operations such as
+-- re-certification can be performed one group at a time.
+CREATE TABLE groups (
+  id INTEGER NOT NULL PRIMARY KEY,
+  name VARCHAR NOT NULL UNIQUE,
+  description VARCHAR
+);
+
+CREATE TABLE group_members (
+  id INTEGER NOT NULL PRIMARY KEY,
+  group_id INTEGER NOT NULL REFERENCES groups(id),
+  user_id INTEGER NOT NULL REFERENCES users(id),
+  UNIQUE (group_id, user_id)
+);
@@ -0,0 +1,17 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Publication status of applied revocations, per channel: time of the last
+-- successful publication, and the error of the last failed attempt
+ALTER TABLE revocations
+  ADD COLUMN keyserver_published TIMESTAMP;
+ALTER TABLE revocations
+  ADD COLUMN keyserver_error VARCHAR;
+ALTER TABLE revocations
+  ADD COLUMN wkd_published TIMESTAMP;
+ALTER TABLE revocations
+  ADD COLUMN wkd_error VARCHAR;
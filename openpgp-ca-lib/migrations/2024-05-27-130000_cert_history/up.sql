@@ -0,0 +1,17 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Time at which a cert was added to the CA (NULL for certs that were added
+-- before this column existed)
+ALTER TABLE certs
+  ADD COLUMN created TIMESTAMP;
+
+-- The version history of a cert (in "cert_versions") is complete from this
+-- time on: earlier versions have been pruned, or were not recorded
+-- (NULL if the history is complete)
+ALTER TABLE certs
+  ADD COLUMN history_since TIMESTAMP;
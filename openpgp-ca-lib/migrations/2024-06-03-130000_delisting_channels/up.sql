@@ -0,0 +1,24 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Delisting per export channel: 'true' when a cert should not be exported
+-- through the channel. "delisted" remains set for certs that are delisted
+-- on all channels.
+ALTER TABLE certs
+  ADD COLUMN delisted_wkd BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE certs
+  ADD COLUMN delisted_keylist BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE certs
+  ADD COLUMN delisted_keyserver BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE certs
+  ADD COLUMN delisted_certring BOOLEAN NOT NULL DEFAULT false;
+
+UPDATE certs
+  SET delisted_wkd = delisted,
+      delisted_keylist = delisted,
+      delisted_keyserver = delisted,
+      delisted_certring = delisted;
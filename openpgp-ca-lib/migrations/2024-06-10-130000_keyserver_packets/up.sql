@@ -0,0 +1,20 @@
+-- SPDX-FileCopyrightText: 2024 Heiko Schaefer <heiko@schaefer.name>
+-- SPDX-License-Identifier: GPL-3.0-or-later
+--
+-- This file is part of OpenPGP CA
+-- https://gitlab.com/openpgp-ca/openpgp-ca
+--
+
+-- Provenance of the packets that were merged into certs from keyservers:
+-- one row for each merged packet and each keyserver that supplied it.
+CREATE TABLE keyserver_packets (
+  id INTEGER NOT NULL PRIMARY KEY,
+  keyserver VARCHAR NOT NULL,
+  -- SHA256 digest (as hex) of the normalized packet
+  digest VARCHAR NOT NULL,
+  -- kind of the packet (e.g. "Signature Packet (CertificationRevocation)")
+  packet VARCHAR NOT NULL,
+  created TIMESTAMP NOT NULL,
+  cert_id INTEGER NOT NULL,
+  FOREIGN KEY(cert_id) REFERENCES certs(id)
+);
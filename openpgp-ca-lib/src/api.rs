@@ -0,0 +1,507 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! The core operations of a CA, as methods that are invoked by name with
+//! JSON parameters.
+//!
+//! This is the shared basis of the JSON-RPC interface of restd and of the
+//! C bindings (openpgp-ca-ffi), so that both offer the same methods, with
+//! the same parameters and results.
+//!
+//! With the "schemars" feature, the parameter and result types implement
+//! `JsonSchema`, and [method_doc] describes each method.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sequoia_openpgp::packet::UserID;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::db::models;
+use crate::pgp;
+use crate::types::{CertFormat, ExportCompat, ExportCompression, SignedCertStatus};
+use crate::Oca;
+
+/// All methods, in the order in which they are listed
+pub const METHODS: &[&str] = &[
+    "ca.info",
+    "users.new",
+    "certs.import",
+    "certs.check",
+    "certs.status",
+    "revocations.add",
+    "revocations.apply",
+    "revocations.list",
+    "export.wkd",
+    "export.certring",
+];
+
+// armored cert size limit (1 MiB)
+pub const CERT_SIZE_LIMIT: usize = 1024 * 1024;
+
+// signed revocation status statements may be cached for 15 minutes
+pub const CERT_STATUS_MAX_AGE_SECS: u64 = 15 * 60;
+
+// subkeys of the keys that "users.new" generates
+const ENCRYPTION_SUBKEY: bool = true;
+const SIGNING_SUBKEY: bool = true;
+const AUTHENTICATION_SUBKEY: bool = false;
+
+/// Why a [call] failed
+#[derive(Debug)]
+pub enum CallError {
+    /// There is no method with this name
+    UnknownMethod(String),
+
+    /// The parameters don't fit the method
+    InvalidParams(String),
+
+    /// The operation on the CA failed
+    Failed(anyhow::Error),
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::UnknownMethod(method) => write!(f, "Unknown method '{method}'"),
+            CallError::InvalidParams(msg) => write!(f, "Invalid parameters: {msg}"),
+            CallError::Failed(e) => write!(f, "{e:#}"),
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+impl From<anyhow::Error> for CallError {
+    fn from(e: anyhow::Error) -> Self {
+        CallError::Failed(e)
+    }
+}
+
+/// Parameters of "users.new"
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct UserNewParams {
+    #[serde(default)]
+    pub name: Option<String>,
+
+    pub emails: Vec<String>,
+
+    /// Validity of the CA certifications, in days (default: no expiry)
+    #[serde(default)]
+    pub validity_days: Option<u64>,
+
+    /// Protect the private key with a generated password
+    #[serde(default)]
+    pub password: bool,
+}
+
+/// Parameters of "certs.import"
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ImportParams {
+    /// The armored public key of the user
+    pub cert: String,
+
+    /// Name of the user (derived from the User IDs, if unset)
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// The CA certifies the User IDs with these email addresses
+    #[serde(default)]
+    pub emails: Vec<String>,
+
+    /// Armored revocation certificates, for storage in the CA
+    #[serde(default)]
+    pub revocations: Vec<String>,
+
+    /// Validity of the CA certifications, in days (default: no expiry)
+    #[serde(default)]
+    pub validity_days: Option<u64>,
+}
+
+/// Parameters of the methods that refer to one cert
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct FingerprintParams {
+    pub fingerprint: String,
+}
+
+/// Parameters of "revocations.add"
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RevocationParams {
+    /// The armored revocation certificate
+    pub revocation: String,
+}
+
+/// Parameters of "revocations.apply"
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct HashParams {
+    pub hash: String,
+}
+
+/// Parameters of "export.wkd"
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct WkdParams {
+    /// Output directory
+    pub path: PathBuf,
+
+    /// Domain to export (default: the domain of the CA)
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+/// Parameters of "export.certring"
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CertringParams {
+    /// Output file
+    pub path: PathBuf,
+
+    /// Only export the certs for this email address
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// Result of "ca.info"
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CaInfo {
+    pub domain: String,
+    pub email: String,
+    pub fingerprint: String,
+
+    /// The armored CA cert
+    pub cert: String,
+}
+
+/// Result of "users.new"
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct NewUser {
+    pub fingerprint: String,
+    pub cert: String,
+    pub private_key: String,
+    pub password: Option<String>,
+}
+
+/// Result of "certs.check"
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CertCheck {
+    /// User IDs that the CA has certified
+    pub certified: Vec<String>,
+
+    /// User IDs that the CA has not certified
+    pub uncertified: Vec<String>,
+
+    /// Certified User IDs whose CA certifications have all expired
+    pub expired: Vec<String>,
+
+    /// Has the cert tsigned the CA?
+    pub tsigned_ca: bool,
+}
+
+/// A revocation certificate that is stored in the CA database
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RevocationInfo {
+    /// Identifier of the revocation (for deleting it)
+    pub hash: String,
+
+    /// Fingerprint of the revoked cert
+    pub fingerprint: String,
+
+    /// Reason for revocation (code and message)
+    pub reason: String,
+
+    pub created: Option<DateTime<Utc>>,
+
+    /// Has the revocation been applied to the cert?
+    pub published: bool,
+
+    /// The armored revocation certificate
+    pub revocation: String,
+}
+
+impl RevocationInfo {
+    /// `revocation`, for the cert with `fingerprint`
+    pub fn new(revocation: models::Revocation, fingerprint: String) -> anyhow::Result<Self> {
+        let (reason, created) = Oca::revocation_details(&revocation)?;
+
+        Ok(RevocationInfo {
+            hash: revocation.hash,
+            fingerprint,
+            reason,
+            created: created.map(DateTime::<Utc>::from),
+            published: revocation.published,
+            revocation: revocation.revocation,
+        })
+    }
+}
+
+/// Description of one method
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MethodDoc {
+    pub name: String,
+    pub summary: String,
+
+    /// JSON schema of the parameters (null if the method takes none)
+    pub params: Value,
+
+    /// JSON schema of the result
+    pub result: Value,
+}
+
+#[cfg(feature = "schemars")]
+fn schema<T: schemars::JsonSchema>(gen: &mut schemars::gen::SchemaGenerator) -> Value {
+    serde_json::to_value(gen.subschema_for::<T>()).expect("schema serialization failed")
+}
+
+/// Describe `method` (None for methods that are not in [METHODS]).
+///
+/// The schemas of the parameters and results are registered in `gen`.
+#[cfg(feature = "schemars")]
+pub fn method_doc(method: &str, gen: &mut schemars::gen::SchemaGenerator) -> Option<MethodDoc> {
+    let (summary, params, result) = match method {
+        "ca.info" => (
+            "Get the domain, email address, fingerprint and cert of the CA",
+            Value::Null,
+            schema::<CaInfo>(gen),
+        ),
+        "users.new" => (
+            "Create a new user with a key that the CA generates (returns the private key)",
+            schema::<UserNewParams>(gen),
+            schema::<NewUser>(gen),
+        ),
+        "certs.import" => (
+            "Import a new user cert, and certify the User IDs for the given emails",
+            schema::<ImportParams>(gen),
+            schema::<String>(gen),
+        ),
+        "certs.check" => (
+            "Check which User IDs of a cert the CA has certified, and if it has tsigned the CA",
+            schema::<FingerprintParams>(gen),
+            schema::<CertCheck>(gen),
+        ),
+        "certs.status" => (
+            "Get the revocation status of a cert (good, revoked or unknown), signed by the CA",
+            schema::<FingerprintParams>(gen),
+            schema::<SignedCertStatus>(gen),
+        ),
+        "revocations.add" => (
+            "Deposit a revocation certificate for a user cert (it is stored, but not applied)",
+            schema::<RevocationParams>(gen),
+            schema::<RevocationInfo>(gen),
+        ),
+        "revocations.apply" => (
+            "Apply a stored revocation certificate to its cert",
+            schema::<HashParams>(gen),
+            schema::<RevocationInfo>(gen),
+        ),
+        "revocations.list" => (
+            "Get the stored revocation certificates for a cert",
+            schema::<FingerprintParams>(gen),
+            schema::<Vec<RevocationInfo>>(gen),
+        ),
+        "export.wkd" => (
+            "Export the certs for a domain as a WKD directory structure",
+            schema::<WkdParams>(gen),
+            Value::Null,
+        ),
+        "export.certring" => (
+            "Export the certs (or those for an email address) to a file",
+            schema::<CertringParams>(gen),
+            Value::Null,
+        ),
+        _ => return None,
+    };
+
+    Some(MethodDoc {
+        name: method.to_string(),
+        summary: summary.to_string(),
+        params,
+        result,
+    })
+}
+
+fn params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, CallError> {
+    serde_json::from_value(params).map_err(|e| CallError::InvalidParams(e.to_string()))
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, CallError> {
+    Ok(serde_json::to_value(value).map_err(anyhow::Error::from)?)
+}
+
+fn check_size(armored: &str) -> Result<(), CallError> {
+    if armored.len() > CERT_SIZE_LIMIT {
+        return Err(CallError::InvalidParams("Size exceeds limit".to_string()));
+    }
+
+    Ok(())
+}
+
+/// The stored cert with `fingerprint` (fails if it doesn't exist)
+fn db_cert(ca: &Oca, fingerprint: &str) -> Result<models::Cert, CallError> {
+    ca.cert_get_by_fingerprint(fingerprint)?
+        .ok_or_else(|| CallError::Failed(anyhow::anyhow!("No cert found for {}", fingerprint)))
+}
+
+/// `revocation`, with the fingerprint of the cert that it belongs to
+fn revocation_info(ca: &Oca, revocation: models::Revocation) -> Result<RevocationInfo, CallError> {
+    let fingerprint = ca
+        .user_certs_get_all()?
+        .into_iter()
+        .find(|c| c.id == revocation.cert_id)
+        .map(|c| c.fingerprint)
+        .unwrap_or_default();
+
+    Ok(RevocationInfo::new(revocation, fingerprint)?)
+}
+
+fn userids(uids: &[UserID]) -> Vec<String> {
+    uids.iter()
+        .map(|u| String::from_utf8_lossy(u.value()).to_string())
+        .collect()
+}
+
+/// Invoke `method` on `ca`, with the JSON parameters `p`
+pub fn call(ca: &Oca, method: &str, p: Value) -> Result<Value, CallError> {
+    match method {
+        "ca.info" => {
+            let cert = ca.ca_get_cert_pub()?;
+
+            to_value(CaInfo {
+                domain: ca.domainname().to_string(),
+                email: ca.get_ca_email()?,
+                fingerprint: cert.fingerprint().to_hex(),
+                cert: ca.ca_get_pubkey_armored()?,
+            })
+        }
+        "users.new" => {
+            let p: UserNewParams = params(p)?;
+            let emails: Vec<_> = p.emails.iter().map(String::as_str).collect();
+
+            let key = ca.user_new(
+                p.name.as_deref(),
+                &emails,
+                p.validity_days,
+                p.password,
+                None,
+                None,
+                ENCRYPTION_SUBKEY,
+                SIGNING_SUBKEY,
+                AUTHENTICATION_SUBKEY,
+            )?;
+
+            to_value(NewUser {
+                fingerprint: key.fingerprint,
+                cert: key.cert,
+                private_key: key.private_key,
+                password: key.password,
+            })
+        }
+        "certs.import" => {
+            let p: ImportParams = params(p)?;
+            check_size(&p.cert)?;
+
+            let fingerprint = pgp::to_cert(p.cert.as_bytes())?.fingerprint().to_hex();
+
+            let emails: Vec<_> = p.emails.iter().map(String::as_str).collect();
+            let revocations: Vec<_> = p.revocations.iter().map(String::as_bytes).collect();
+
+            ca.cert_import_new(
+                p.cert.as_bytes(),
+                &revocations,
+                p.name.as_deref(),
+                &emails,
+                p.validity_days,
+            )?;
+
+            to_value(fingerprint)
+        }
+        "certs.check" => {
+            let p: FingerprintParams = params(p)?;
+            let cert = db_cert(ca, &p.fingerprint)?;
+
+            let status = ca.cert_check_ca_sig(&cert)?;
+            to_value(CertCheck {
+                certified: userids(&status.certified),
+                uncertified: userids(&status.uncertified),
+                expired: userids(&status.expired),
+                tsigned_ca: ca.cert_check_tsig_on_ca(&cert)?,
+            })
+        }
+        "certs.status" => {
+            let p: FingerprintParams = params(p)?;
+            let fingerprint = pgp::fingerprint_from_str(&p.fingerprint)
+                .map_err(|e| CallError::InvalidParams(format!("{e:#}")))?;
+
+            let status = ca.cert_status(
+                &fingerprint.to_hex(),
+                Duration::from_secs(CERT_STATUS_MAX_AGE_SECS),
+            )?;
+            to_value(status)
+        }
+        "revocations.add" => {
+            let p: RevocationParams = params(p)?;
+            check_size(&p.revocation)?;
+
+            let revocation = ca.revocation_add(p.revocation.as_bytes())?;
+
+            to_value(revocation_info(ca, revocation)?)
+        }
+        "revocations.apply" => {
+            let p: HashParams = params(p)?;
+            let revocation = ca.revocation_get_by_hash(&p.hash)?;
+            ca.revocation_apply(revocation)?;
+
+            to_value(revocation_info(ca, ca.revocation_get_by_hash(&p.hash)?)?)
+        }
+        "revocations.list" => {
+            let p: FingerprintParams = params(p)?;
+            let cert = db_cert(ca, &p.fingerprint)?;
+
+            let revocations = ca
+                .revocations_get(&cert)?
+                .into_iter()
+                .map(|r| RevocationInfo::new(r, cert.fingerprint.clone()))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            to_value(revocations)
+        }
+        "export.wkd" => {
+            let p: WkdParams = params(p)?;
+            let domain = p.domain.as_deref().unwrap_or_else(|| ca.domainname());
+
+            ca.export_wkd(domain, &p.path, false, ExportCompat::default())?;
+
+            Ok(Value::Null)
+        }
+        "export.certring" => {
+            let p: CertringParams = params(p)?;
+
+            ca.export_certring(
+                &p.path,
+                p.email.as_deref(),
+                false,
+                CertFormat::Armored,
+                ExportCompat::default(),
+                ExportCompression::None,
+            )?;
+
+            Ok(Value::Null)
+        }
+        _ => Err(CallError::UnknownMethod(method.to_string())),
+    }
+}
@@ -12,6 +12,9 @@ use std::fmt::Formatter;
 
 use anyhow::anyhow;
 
+use crate::db::PREF_CERTIFICATION_KEY;
+use crate::{pgp, Oca};
+
 pub(crate) mod card;
 pub(crate) mod softkey;
 pub(crate) mod split;
@@ -71,6 +74,19 @@ impl Backend {
     }
 }
 
+impl Backend {
+    /// The type of this backend, without configuration details (such as
+    /// card idents)
+    pub(crate) fn kind(&self) -> String {
+        match self {
+            Backend::Softkey => "softkey".to_string(),
+            Backend::Card(_) => BACKEND_TYPE_CARD.to_string(),
+            Backend::SplitFront => BACKEND_TYPE_SPLIT_FRONT.to_string(),
+            Backend::SplitBack(b) => format!("{}({})", BACKEND_TYPE_SPLIT_BACK, b.kind()),
+        }
+    }
+}
+
 impl std::fmt::Display for Backend {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -119,6 +135,56 @@ impl std::fmt::Display for Card {
     }
 }
 
+/// Fingerprints of the valid, certification capable (sub)keys of the CA cert
+pub(crate) fn certification_keys(oca: &Oca) -> anyhow::Result<Vec<String>> {
+    let ca = oca.ca_get_cert_pub()?;
+    let policy = oca.policy()?;
+
+    let keys = ca
+        .keys()
+        .with_policy(&policy, None)
+        .alive()
+        .revoked(false)
+        .for_certification()
+        .map(|ka| ka.key().fingerprint().to_hex())
+        .collect();
+
+    Ok(keys)
+}
+
+/// The (sub)key that the CA certifies with (None, if the CA certifies with
+/// all of its certification capable keys)
+pub(crate) fn certification_key(oca: &Oca) -> anyhow::Result<Option<String>> {
+    Ok(oca
+        .storage
+        .pref(PREF_CERTIFICATION_KEY)?
+        .filter(|fp| !fp.is_empty()))
+}
+
+pub(crate) fn set_certification_key(oca: &Oca, fingerprint: Option<&str>) -> anyhow::Result<()> {
+    if *oca.backend() != Backend::Softkey {
+        return Err(anyhow!(
+            "Selecting a certification key is only supported for softkey CAs"
+        ));
+    }
+
+    let value = match fingerprint {
+        Some(fp) => {
+            let fp = pgp::normalize_fp(fp)?;
+            if !certification_keys(oca)?.contains(&fp) {
+                return Err(anyhow!(
+                    "{} is not a valid, certification capable key of the CA cert",
+                    fp
+                ));
+            }
+            fp
+        }
+        None => String::new(),
+    };
+
+    oca.storage.pref_set(PREF_CERTIFICATION_KEY, &value)
+}
+
 /// Backend-specific implementation of certification and signing operations
 pub trait CertificationBackend {
     /// Make a certification signature.
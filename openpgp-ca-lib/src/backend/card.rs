@@ -110,7 +110,7 @@ impl CertificationBackend for CardBackend {
             .user_card()
             .ok_or_else(|| anyhow!("Unexpected: can't get card in user mode"))?;
         let mut signer =
-            user.authenticator(&|| println!("Touch confirmation needed for certification"))?;
+            user.authenticator(&|| tracing::warn!("Touch confirmation needed for certification"))?;
 
         op(&mut signer as &mut dyn sequoia_openpgp::crypto::Signer)?;
 
@@ -133,7 +133,8 @@ impl CertificationBackend for CardBackend {
         let mut sign = open
             .signing_card()
             .ok_or_else(|| anyhow!("Unexpected: can't get card in signing mode"))?;
-        let mut signer = sign.signer(&|| println!("Touch confirmation needed for signing"))?;
+        let mut signer =
+            sign.signer(&|| tracing::warn!("Touch confirmation needed for signing"))?;
 
         op(&mut signer as &mut dyn sequoia_openpgp::crypto::Signer)?;
 
@@ -265,17 +266,11 @@ pub(crate) fn generate_on_card(
         None => Some(AlgoSimple::RSA4k),
     };
 
-    // Print information about algorithm and possible slowness.
-    println!(
-        "Generating {}key material on the card, this might take a while.",
-        // Printable algo name (with trailing space, if not 'None')
-        if let Some(algo) = algo {
-            format!("{algo:?} ")
-        } else {
-            "".to_string()
-        }
+    // Log information about algorithm and possible slowness.
+    tracing::info!(
+        algo = ?algo,
+        "Generating key material on the card, this might take a while"
     );
-    println!();
 
     // We assume that the default Admin PIN is currently valid
     if transaction.verify_admin(PW3_DEFAULT.as_bytes()).is_err() {
@@ -340,7 +335,7 @@ pub(crate) fn generate_on_card(
                 if let Some(mut user) = card.user_card() {
                     // Card-backed signer for bindings
                     let mut card_signer = user.authenticator_from_public(auth_pubkey, &|| {
-                        println!("Need touch confirmation for certification.")
+                        tracing::warn!("Touch confirmation needed for certification")
                     });
 
                     // Make signature, return it
@@ -370,7 +365,7 @@ pub(crate) fn generate_on_card(
                 if let Some(mut sign) = card.signing_card() {
                     // Card-backed signer for bindings
                     let mut card_signer = sign.signer_from_public(sig_pubkey, &|| {
-                        println!("Need touch confirmation for signing.")
+                        tracing::warn!("Touch confirmation needed for signing")
                     });
 
                     // Make signature, return it
@@ -520,6 +515,74 @@ pub(crate) fn import_to_card(ident: &str, key: &Cert) -> Result<String> {
     }
 }
 
+/// Upload the subkeys of the new user key `key` to the (empty) card `ident`:
+/// the encryption, signing and authentication subkeys go into the respective
+/// slots. If `name` is set, it is stored as the cardholder name.
+///
+/// Expects the Admin PIN to be set to the default value of `12345678`.
+/// Afterwards, the User and Admin PIN are set to new random 8 digit values.
+///
+/// Returns the new (User PIN, Admin PIN).
+pub(crate) fn provision_user_card(
+    ident: &str,
+    key: &Cert,
+    name: Option<&str>,
+) -> Result<(String, String)> {
+    let backend = PcscBackend::open_by_ident(ident, None)?;
+    let mut card: Card<Open> = backend.into();
+    let mut transaction = card.transaction()?;
+
+    // check that card has no keys on it
+    if !check_card_empty(&transaction)? {
+        return Err(anyhow!(
+            "The OpenPGP card contains key material, please reset it before provisioning a user key."
+        ));
+    }
+
+    transaction.verify_admin(PW3_DEFAULT.as_bytes())?;
+
+    let mut admin = transaction
+        .admin_card()
+        .ok_or_else(|| anyhow!("Failed to open card in admin mode."))?;
+
+    let policy = StandardPolicy::new();
+
+    let mut uploaded = 0;
+    for key_type in [
+        KeyType::Decryption,
+        KeyType::Signing,
+        KeyType::Authentication,
+    ] {
+        if let Some(subkey) = sq_util::subkey_by_type(key, &policy, key_type)? {
+            admin.upload_key(subkey, key_type, None)?;
+            uploaded += 1;
+        }
+    }
+
+    if uploaded == 0 {
+        return Err(anyhow!("The user key has no subkeys to upload to the card"));
+    }
+
+    if let Some(name) = name {
+        admin.set_name(name)?;
+    }
+
+    // Change User and Admin PIN
+    //
+    // NOTE: This is done after the key upload because Gnuk doesn't allow PIN changes
+    // when the card contains no keys.
+    let admin_pin = random_user_pin();
+    admin
+        .as_open()
+        .change_admin_pin(PW3_DEFAULT.as_bytes(), admin_pin.as_bytes())?;
+    admin.as_open().verify_admin(admin_pin.as_bytes())?;
+
+    let user_pin = random_user_pin();
+    admin.reset_user_pin(user_pin.as_bytes())?;
+
+    Ok((user_pin, admin_pin))
+}
+
 /// Given the current `admin_pin`, set both the User and Admin PIN to a new random 8-digit value
 /// (the new PIN gets returned)
 fn set_user_and_admin_pin(card: &mut Card<Admin>, admin_pin: &str) -> Result<String> {
@@ -4,27 +4,48 @@
 // This file is part of OpenPGP CA
 // https://gitlab.com/openpgp-ca/openpgp-ca
 
+use std::rc::Rc;
+
 use anyhow::Result;
 use sequoia_openpgp::cert::Cert;
 use sequoia_openpgp::crypto::Signer;
 
 use crate::backend::CertificationBackend;
+use crate::db::{OcaDb, PREF_CERTIFICATION_KEY};
 use crate::pgp;
 
 pub(crate) struct SoftkeyBackend {
     // CA private key material
     ca_cert: Cert,
+
+    // CA database (for the selection of the certification key)
+    db: Rc<OcaDb>,
 }
 
 impl SoftkeyBackend {
-    pub(crate) fn new(ca_cert: Cert) -> Self {
-        Self { ca_cert }
+    pub(crate) fn new(ca_cert: Cert, db: Rc<OcaDb>) -> Self {
+        Self { ca_cert, db }
     }
 }
 
 impl CertificationBackend for SoftkeyBackend {
     fn certify(&self, op: &mut dyn FnMut(&mut dyn Signer) -> Result<()>) -> Result<()> {
-        let ca_keys = pgp::get_cert_keys(&self.ca_cert, None);
+        let mut ca_keys = pgp::get_cert_keys(&self.ca_cert, None);
+
+        // If a certification key is selected, only certify with that key
+        // (otherwise, each certification capable key makes a certification)
+        if let Some(fp) = self.db.pref(PREF_CERTIFICATION_KEY)? {
+            if !fp.is_empty() {
+                ca_keys.retain(|k| k.public().fingerprint().to_hex() == fp);
+
+                if ca_keys.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "The selected certification key {} is not usable",
+                        fp
+                    ));
+                }
+            }
+        }
 
         for mut s in ca_keys {
             op(&mut s as &mut dyn Signer)?;
@@ -6,107 +6,295 @@
 
 use std::collections::LinkedList;
 use std::fs::File;
+use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::str::FromStr;
 
 use anyhow::Result;
 use base64::{engine::general_purpose, Engine};
-use chrono::{DateTime, TimeZone, Utc};
-use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyModifiers};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use sequoia_openpgp::packet::signature::SignatureBuilder;
 use sequoia_openpgp::packet::{Signature, UserID};
 use sequoia_openpgp::parse::Parse;
 use sequoia_openpgp::serialize::{Marshal, SerializeInto};
-use sequoia_openpgp::{Cert, Packet};
+use sequoia_openpgp::Cert;
 use serde::{Deserialize, Serialize};
 
 use crate::db::models::{Bridge, Cacert, NewQueue, Queue, Revocation, User};
-use crate::db::{models, OcaDb};
-use crate::pgp;
+use crate::db::{models, OcaDb, QUEUE_PROPOSAL, QUEUE_SPLIT};
+use crate::i18n::Language;
 use crate::secret::CaSec;
 use crate::storage::{ca_get_cert_pub, CaStorage, CaStorageRW, CaStorageWrite, QueueDb, UninitDb};
-
-// Internal version identifier, to be incremented when the JSON request format changes
-// in an incompatible way.
-//
-// NOTE: In most problematic cases, Serde will fail to deserialize before the version is read.
-const SPLIT_OCA_REQUEST_VERSION: u32 = 1;
-
-// Internal version identifier, to be incremented when the JSON request format changes
-// in an incompatible way.
-//
-// NOTE: In most problematic cases, Serde will fail to deserialize before the version is read.
-const SPLIT_OCA_RESPONSE_VERSION: u32 = 1;
+use crate::types::{
+    CertificationProfile, CertificationValidity, CtLogKind, ExportChannel, ExportCompat, Notation,
+    PublicationChannel,
+};
+use crate::{ct_log, pgp};
+
+/// Version identifier of the JSON request format, to be incremented when the
+/// format changes in an incompatible way.
+///
+/// NOTE: In most problematic cases, Serde will fail to deserialize before the version is read.
+pub const SPLIT_OCA_REQUEST_VERSION: u32 = 1;
+
+/// Version identifier of the JSON response format, to be incremented when the
+/// format changes in an incompatible way.
+///
+/// NOTE: In most problematic cases, Serde will fail to deserialize before the version is read.
+pub const SPLIT_OCA_RESPONSE_VERSION: u32 = 1;
 
 const CHRONO_FMT: &str = "%Y-%m-%d %H:%M:%S %Z";
-const CHRONO_FMT_NAIVE: &str = "%Y-%m-%d %H:%M:%S";
 
+/// Certification requests of a split-mode front instance, for processing by
+/// the back instance.
+///
+/// The JSON serialization of this type is the request format of split mode
+/// (version [SPLIT_OCA_REQUEST_VERSION]).
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct SplitOcaRequests {
+pub struct SplitOcaRequests {
     version: u32,
     ca_fingerprint: String,
     created: DateTime<Utc>, // informational timestamp
     queue: LinkedList<(i32, DateTime<Utc>, QueueEntry)>,
 }
 
+impl SplitOcaRequests {
+    /// Read a request document in JSON format.
+    ///
+    /// Fails if the document is not in the request format version
+    /// [SPLIT_OCA_REQUEST_VERSION].
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        let reqs: Self = serde_json::from_reader(reader)?;
+
+        if reqs.version != SPLIT_OCA_REQUEST_VERSION {
+            return Err(anyhow::anyhow!(
+                "Unexpected version {} in request file",
+                reqs.version
+            ));
+        }
+
+        Ok(reqs)
+    }
+
+    /// Write this request document in JSON format
+    pub fn to_writer(&self, writer: impl Write) -> Result<()> {
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+
+    /// Fingerprint of the CA that the requests are for
+    pub fn ca_fingerprint(&self) -> &str {
+        &self.ca_fingerprint
+    }
+
+    /// When the requests were exported
+    pub fn created(&self) -> DateTime<Utc> {
+        self.created
+    }
+
+    /// The requests: queue id, creation time and request
+    pub fn entries(&self) -> impl Iterator<Item = (i32, DateTime<Utc>, &QueueEntry)> {
+        self.queue
+            .iter()
+            .map(|(id, created, qe)| (*id, *created, qe))
+    }
+}
+
+/// A request in a [SplitOcaRequests] document
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) enum QueueEntry {
+pub enum QueueEntry {
     CertificationReq(CertificationReq),
     BridgeReq(BridgeReq),
 }
 
+/// A request to certify User IDs of a cert
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct CertificationReq {
+pub struct CertificationReq {
     cert: String,
     user_ids: Vec<String>,
     days: Option<u64>,
+
+    // Certify until a fixed point in time. `days` is set to the remaining
+    // number of days at the time of the request, as a fallback for back
+    // instances that don't know about this field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    until: Option<DateTime<Utc>>,
+
+    // Omitted for the default profile, so that requests stay readable for
+    // back instances that don't know about profiles
+    #[serde(default, skip_serializing_if = "CertificationProfile::is_default")]
+    profile: CertificationProfile,
+
+    // Notation that marks the certifications as issued by the CA (the
+    // front instance's notation policy applies)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notation: Option<Notation>,
 }
 
+/// A request to bridge to a remote CA (with a trust signature)
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct BridgeReq {
+pub struct BridgeReq {
     cert: String,
     scope_regexes: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "CertificationProfile::is_default")]
+    profile: CertificationProfile,
+}
+
+impl QueueEntry {
+    fn profile(&self) -> CertificationProfile {
+        match self {
+            QueueEntry::CertificationReq(cr) => cr.profile,
+            QueueEntry::BridgeReq(br) => br.profile,
+        }
+    }
+
+    /// The cert that the request is about
+    pub fn cert(&self) -> Result<Cert> {
+        match self {
+            QueueEntry::CertificationReq(cr) => cr.cert(),
+            QueueEntry::BridgeReq(br) => Cert::from_str(&br.cert),
+        }
+    }
 }
 
 impl CertificationReq {
-    pub(crate) fn cert(&self) -> Result<Cert> {
+    pub fn cert(&self) -> Result<Cert> {
         Cert::from_str(&self.cert)
     }
 
-    pub(crate) fn days(&self) -> Option<u64> {
-        self.days
+    pub fn validity(&self) -> Option<CertificationValidity> {
+        match self.until {
+            Some(until) => Some(CertificationValidity::Until(until)),
+            None => CertificationValidity::from_days(self.days),
+        }
     }
 
-    pub(crate) fn user_ids(&self) -> &[String] {
+    pub fn user_ids(&self) -> &[String] {
         &self.user_ids
     }
+
+    pub fn notation(&self) -> Option<&Notation> {
+        self.notation.as_ref()
+    }
+
+    pub fn profile(&self) -> CertificationProfile {
+        self.profile
+    }
+}
+
+impl BridgeReq {
+    pub fn cert(&self) -> Result<Cert> {
+        Cert::from_str(&self.cert)
+    }
+
+    pub fn scope_regexes(&self) -> &[String] {
+        &self.scope_regexes
+    }
+
+    pub fn profile(&self) -> CertificationProfile {
+        self.profile
+    }
 }
 
+/// The results of processing a [SplitOcaRequests] document on the back
+/// instance, for import by the front instance.
+///
+/// The JSON serialization of this type is the response format of split mode
+/// (version [SPLIT_OCA_RESPONSE_VERSION]).
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct SplitOcaResponse {
+pub struct SplitOcaResponse {
     version: u32,
     ca_fingerprint: String,
     created: DateTime<Utc>, // informational timestamp
     queue: LinkedList<(i32, QueueResponse)>,
 }
 
+impl SplitOcaResponse {
+    /// Read a response document in JSON format.
+    ///
+    /// Fails if the document is not in the response format version
+    /// [SPLIT_OCA_RESPONSE_VERSION].
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        let sor: Self = serde_json::from_reader(reader)?;
+
+        if sor.version != SPLIT_OCA_RESPONSE_VERSION {
+            return Err(anyhow::anyhow!(
+                "Unexpected response format version {}",
+                sor.version
+            ));
+        }
+
+        Ok(sor)
+    }
+
+    /// Write this response document in JSON format
+    pub fn to_writer(&self, writer: impl Write) -> Result<()> {
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+
+    /// Fingerprint of the CA that generated the response
+    pub fn ca_fingerprint(&self) -> &str {
+        &self.ca_fingerprint
+    }
+
+    /// When the response was generated
+    pub fn created(&self) -> DateTime<Utc> {
+        self.created
+    }
+
+    /// The results: queue id of the request, and result
+    pub fn entries(&self) -> impl Iterator<Item = (i32, &QueueResponse)> {
+        self.queue.iter().map(|(id, qr)| (*id, qr))
+    }
+}
+
+/// A result in a [SplitOcaResponse] document
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) enum QueueResponse {
+pub enum QueueResponse {
     CertificationResp(CertificationResp),
     BridgeResp(BridgeResp),
 }
 
+/// The certifications for a [CertificationReq]
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct CertificationResp {
+pub struct CertificationResp {
     fingerprint: String,
     sigs: Vec<String>,
 }
 
+impl CertificationResp {
+    /// Fingerprint of the certified cert
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// The certifications
+    pub fn signatures(&self) -> Result<Vec<Signature>> {
+        self.sigs
+            .iter()
+            .map(|s| {
+                let bytes = general_purpose::STANDARD
+                    .decode(s)
+                    .map_err(|e| anyhow::anyhow!("Error while decoding base64: {}", e))?;
+                Ok(Signature::from_bytes(&bytes)?)
+            })
+            .collect()
+    }
+}
+
+/// The trust-signed remote CA cert for a [BridgeReq]
 #[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct BridgeResp {
+pub struct BridgeResp {
     cert: String,
 }
 
+impl BridgeResp {
+    pub fn cert(&self) -> Result<Cert> {
+        Cert::from_str(&self.cert)
+    }
+}
+
 /// Backend for the secret-key-material relevant parts of a split CA instance
 pub(crate) struct SplitCa {
     #[allow(dead_code)]
@@ -120,38 +308,53 @@ impl SplitCa {
         })
     }
 
-    pub(crate) fn export_csr_queue(output: PathBuf, queue: Vec<Queue>, ca_fp: &str) -> Result<()> {
-        if !queue.is_empty() {
-            let mut qes: LinkedList<(i32, DateTime<Utc>, QueueEntry)> = LinkedList::new();
+    /// The pending requests in `queue`, as a request document for the back
+    /// instance
+    pub(crate) fn csr_queue(queue: &[Queue], ca_fp: &str) -> Result<SplitOcaRequests> {
+        let mut qes: LinkedList<(i32, DateTime<Utc>, QueueEntry)> = LinkedList::new();
 
-            for entry in &queue {
-                let task = &entry.task;
-                let qe: QueueEntry = serde_json::from_str(task)?;
+        for entry in queue {
+            let task = &entry.task;
+            let qe: QueueEntry = serde_json::from_str(task)?;
 
-                let created = Utc.from_utc_datetime(&entry.created);
+            let created = Utc.from_utc_datetime(&entry.created);
 
-                qes.push_back((entry.id, created, qe));
-            }
+            qes.push_back((entry.id, created, qe));
+        }
+
+        Ok(SplitOcaRequests {
+            version: SPLIT_OCA_REQUEST_VERSION,
+            ca_fingerprint: ca_fp.to_string(),
+            created: Utc::now(),
+            queue: qes,
+        })
+    }
+
+    /// The pending requests in `queue`, in the JSON request format for the
+    /// back instance
+    pub(crate) fn csr_queue_json(queue: &[Queue], ca_fp: &str) -> Result<String> {
+        let reqs = Self::csr_queue(queue, ca_fp)?;
 
-            let sor = SplitOcaRequests {
-                version: SPLIT_OCA_REQUEST_VERSION,
-                ca_fingerprint: ca_fp.to_string(),
-                created: Utc::now(),
-                queue: qes,
-            };
+        Ok(serde_json::to_string_pretty(&reqs)?)
+    }
 
-            let output = File::create(output)?;
-            serde_json::to_writer_pretty(output, &sor)?;
+    pub(crate) fn export_csr_queue(
+        output: PathBuf,
+        queue: Vec<Queue>,
+        ca_fp: &str,
+    ) -> Result<usize> {
+        if !queue.is_empty() {
+            std::fs::write(output, Self::csr_queue_json(&queue, ca_fp)?)?;
 
-            println!(
-                "Exported queue with {} entries for processing by the back instance",
-                queue.len()
+            tracing::info!(
+                entries = queue.len(),
+                "Exported queue for processing by the back instance"
             );
         } else {
-            println!("The queue contains no requests for the back instance, didn't export.");
+            tracing::info!("The queue contains no requests for the back instance, didn't export");
         }
 
-        Ok(())
+        Ok(queue.len())
     }
 }
 
@@ -165,7 +368,9 @@ impl CaSec for SplitCa {
         &self,
         cert: &Cert,
         uids_certify: &[&UserID],
-        duration_days: Option<u64>,
+        validity: Option<CertificationValidity>,
+        profile: CertificationProfile,
+        notation: Option<&Notation>,
     ) -> Result<Vec<Signature>> {
         // If no User IDs are requested to be signed, we can ignore the request
         if uids_certify.is_empty() {
@@ -174,10 +379,23 @@ impl CaSec for SplitCa {
 
         let c = pgp::cert_to_armored(cert)?;
 
+        let (days, until) = match validity {
+            None => (None, None),
+            Some(CertificationValidity::Days(days)) => (Some(days), None),
+            Some(v @ CertificationValidity::Until(until)) => {
+                let secs = v.period(std::time::SystemTime::now())?.as_secs();
+
+                (Some(secs.div_ceil(pgp::SECONDS_IN_DAY).max(1)), Some(until))
+            }
+        };
+
         let cr = CertificationReq {
             user_ids: uids_certify.iter().map(|u| u.to_string()).collect(),
             cert: c,
-            days: duration_days,
+            days,
+            until,
+            profile,
+            notation: notation.cloned(),
         };
 
         // Wrap the CertificationReq in a QueueEntry and store as a JSON string.
@@ -190,6 +408,8 @@ impl CaSec for SplitCa {
             created,
             task: &serialized,
             done: false,
+            kind: QUEUE_SPLIT,
+            status: None,
         };
 
         // Store the certification task in the queue
@@ -199,7 +419,12 @@ impl CaSec for SplitCa {
         Ok(vec![])
     }
 
-    fn ca_generate_revocations(&self, _output: PathBuf) -> Result<()> {
+    fn ca_generate_revocations(
+        &self,
+        _output: PathBuf,
+        _compat: ExportCompat,
+        _lang: Language,
+    ) -> Result<()> {
         Err(anyhow::anyhow!(
             "Operation is not supported on a split-mode CA front instance. Please perform it on your back CA instance."
         ))
@@ -214,12 +439,18 @@ impl CaSec for SplitCa {
         ))
     }
 
-    fn bridge_to_remote_ca(&self, remote_ca: Cert, scope_regexes: Vec<String>) -> Result<Cert> {
+    fn bridge_to_remote_ca(
+        &self,
+        remote_ca: Cert,
+        scope_regexes: Vec<String>,
+        profile: CertificationProfile,
+    ) -> Result<Cert> {
         let c = pgp::cert_to_armored(&remote_ca)?;
 
         let br = BridgeReq {
             scope_regexes,
             cert: c,
+            profile,
         };
 
         // Wrap the CertificationReq in a QueueEntry and store as a JSON string.
@@ -232,6 +463,8 @@ impl CaSec for SplitCa {
             created,
             task: &serialized,
             done: false,
+            kind: QUEUE_SPLIT,
+            status: None,
         };
 
         // Store the certification task in the queue
@@ -247,13 +480,32 @@ impl CaSec for SplitCa {
             "Operation is not currently supported on a split-mode CA instance. Please perform it on your back CA instance."
         ))
     }
+
+    fn revoke_certification(
+        &self,
+        _cert: &Cert,
+        _userid: &UserID,
+        _reason: &str,
+    ) -> Result<Signature> {
+        Err(anyhow::anyhow!(
+            "Operation is not currently supported on a split-mode CA instance. Please perform it on your back CA instance."
+        ))
+    }
+
+    fn sign_direct_key(&self, _sb: SignatureBuilder) -> Result<Signature> {
+        Err(anyhow::anyhow!(
+            "Operation is not currently supported on a split-mode CA instance. Please perform it on your back CA instance."
+        ))
+    }
 }
 
 fn gen_certification(
     ca_sec: &dyn CaSec,
     c: &Cert,
     uids: &[String],
-    days_valid: Option<u64>,
+    validity: Option<CertificationValidity>,
+    profile: CertificationProfile,
+    notation: Option<&Notation>,
 ) -> Result<QueueResponse> {
     let u: Vec<_> = c
         .userids()
@@ -262,7 +514,7 @@ fn gen_certification(
         .collect();
 
     // Generate certifications
-    let s = ca_sec.sign_user_ids(c, &u[..], days_valid)?;
+    let s = ca_sec.sign_user_ids(c, &u[..], validity, profile, notation)?;
 
     // Map Signatures to base64 encoded Strings
     let mut sigs: Vec<_> = vec![];
@@ -281,8 +533,13 @@ fn gen_certification(
     Ok(QueueResponse::CertificationResp(resp))
 }
 
-fn gen_bridge(ca_sec: &dyn CaSec, c: Cert, scope_regexes: Vec<String>) -> Result<QueueResponse> {
-    let tsigned = ca_sec.bridge_to_remote_ca(c, scope_regexes)?;
+fn gen_bridge(
+    ca_sec: &dyn CaSec,
+    c: Cert,
+    scope_regexes: Vec<String>,
+    profile: CertificationProfile,
+) -> Result<QueueResponse> {
+    let tsigned = ca_sec.bridge_to_remote_ca(c, scope_regexes, profile)?;
     let cert = pgp::cert_to_armored(&tsigned)?;
 
     let resp = BridgeResp { cert };
@@ -290,39 +547,27 @@ fn gen_bridge(ca_sec: &dyn CaSec, c: Cert, scope_regexes: Vec<String>) -> Result
     Ok(QueueResponse::BridgeResp(resp))
 }
 
-fn get_raw_key() -> Result<KeyEvent> {
-    crossterm::terminal::enable_raw_mode()?;
-
-    // Loop until we get a KeyEvent
-    loop {
-        let event = read()?;
-
-        if let Event::Key(key_event) = event {
-            crossterm::terminal::disable_raw_mode()?;
-
-            return Ok(key_event);
-        }
-    }
-}
-
-// FIXME:
-// The interactive handling of certifications should take place in the frontend,
-// not in this library.
+/// Process the requests in `import`, write the response to `export`.
+///
+/// Only requests for which `approve` returns `true` are processed.
 pub(crate) fn certify(
     ca_sec: &dyn CaSec,
     import: PathBuf,
     export: PathBuf,
-    batch: bool,
+    approve: &mut dyn FnMut(i32, DateTime<Utc>, &QueueEntry) -> Result<bool>,
 ) -> Result<()> {
-    let input = File::open(import)?;
-    let reqs: SplitOcaRequests = serde_json::from_reader(input)?;
+    certify_stream(ca_sec, File::open(import)?, File::create(export)?, approve)
+}
 
-    if reqs.version != SPLIT_OCA_REQUEST_VERSION {
-        return Err(anyhow::anyhow!(
-            "Unexpected version {} in request file",
-            reqs.version
-        ));
-    }
+/// Process the requests in `input`, write the response to `output` (see
+/// [certify])
+pub(crate) fn certify_stream(
+    ca_sec: &dyn CaSec,
+    input: impl Read,
+    output: impl Write,
+    approve: &mut dyn FnMut(i32, DateTime<Utc>, &QueueEntry) -> Result<bool>,
+) -> Result<()> {
+    let reqs = SplitOcaRequests::from_reader(input)?;
 
     if reqs.ca_fingerprint != ca_sec.cert()?.fingerprint().to_hex() {
         return Err(anyhow::anyhow!(
@@ -331,109 +576,35 @@ pub(crate) fn certify(
         ));
     }
 
-    println!(
-        "Processing {} certification requests, exported on {}.",
-        reqs.queue.len(),
-        reqs.created.format(CHRONO_FMT)
+    tracing::info!(
+        requests = reqs.queue.len(),
+        exported = %reqs.created.format(CHRONO_FMT),
+        "Processing certification requests"
     );
-    println!();
 
     // queue responses
     let mut qrs: LinkedList<(i32, QueueResponse)> = LinkedList::new();
 
     for (db_id, created, qe) in reqs.queue {
-        match qe {
-            QueueEntry::CertificationReq(cr) => {
-                // Cert/User ID that should be certified
-                let c = cr.cert()?;
-                let days_valid = cr.days();
-                let uids = cr.user_ids();
-
-                let mut doit = || -> Result<()> {
-                    let qr = gen_certification(ca_sec, &c, uids, days_valid)?;
-                    qrs.push_back((db_id, qr));
-                    Ok(())
-                };
-
-                if !batch {
-                    // interactive mode
-                    println!(
-                        "Request for User ID certification [created {}]:",
-                        created.format(CHRONO_FMT)
-                    );
-                    println!();
-                    println!("Bind key {} with", c.fingerprint().to_hex(),);
-                    for u in uids {
-                        println!("- '{}'", u);
-                    }
-
-                    // FIXME: show if a previous certification by this CA exists
-                    // and inform the CA operator, if so.
-                    // [see sequoia-sq:src/commands/mod.rs:active_certification]
-
-                    println!();
-                    println!("Certify? [y/n]");
-
-                    let key_event = get_raw_key()?;
-                    if key_event.code == KeyCode::Char('y')
-                        && key_event.modifiers == KeyModifiers::NONE
-                    {
-                        doit()?;
-                    } else {
-                        println!();
-                        println!("Skipping this queue entry");
-                    }
-
-                    println!();
-                    println!();
-                } else {
-                    // batch mode
-                    doit()?;
-                }
-            }
+        if !approve(db_id, created, &qe)? {
+            tracing::info!(id = db_id, "Skipped certification request");
+            continue;
+        }
+
+        let qr = match qe {
+            QueueEntry::CertificationReq(cr) => gen_certification(
+                ca_sec,
+                &cr.cert()?,
+                cr.user_ids(),
+                cr.validity(),
+                cr.profile(),
+                cr.notation(),
+            )?,
             QueueEntry::BridgeReq(br) => {
-                let c = Cert::from_str(&br.cert)?;
-
-                let mut doit = || -> Result<()> {
-                    let qr = gen_bridge(ca_sec, c.clone(), br.scope_regexes.clone())?;
-                    qrs.push_back((db_id, qr));
-                    Ok(())
-                };
-
-                if !batch {
-                    // interactive mode
-                    println!(
-                        "Request for bridge certification [created {}]:",
-                        created.format(CHRONO_FMT)
-                    );
-                    println!();
-                    println!("Remote key {}", c.fingerprint().to_hex());
-                    println!("Scoped for:");
-                    for scope in &br.scope_regexes {
-                        println!("- '{}'", scope);
-                    }
-
-                    println!();
-                    println!("Certify? [y/n]");
-
-                    let key_event = get_raw_key()?;
-                    if key_event.code == KeyCode::Char('y')
-                        && key_event.modifiers == KeyModifiers::NONE
-                    {
-                        doit()?;
-                    } else {
-                        println!();
-                        println!("Skipping this queue entry");
-                    }
-
-                    println!();
-                    println!();
-                } else {
-                    // batch mode
-                    doit()?;
-                }
+                gen_bridge(ca_sec, br.cert()?, br.scope_regexes, br.profile)?
             }
-        }
+        };
+        qrs.push_back((db_id, qr));
     }
 
     let sor = SplitOcaResponse {
@@ -443,26 +614,40 @@ pub(crate) fn certify(
         queue: qrs,
     };
 
-    // Write to output file
-    let output = File::create(export)?;
-    serde_json::to_writer_pretty(output, &sor)?;
+    sor.to_writer(output)?;
 
-    println!("Processed {} certification requests", sor.queue.len());
+    tracing::info!(
+        requests = sor.queue.len(),
+        "Processed certification requests"
+    );
 
     Ok(())
 }
 
-pub(crate) fn ca_split_import(storage: &dyn CaStorageRW, file: PathBuf) -> Result<()> {
-    let input = File::open(file)?;
-    let sor: SplitOcaResponse = serde_json::from_reader(input)?;
+pub(crate) fn ca_split_import(storage: &dyn CaStorageRW, file: PathBuf) -> Result<usize> {
+    let (imported, done) = import_response(storage, &std::fs::read(file)?)?;
 
-    if sor.version != SPLIT_OCA_RESPONSE_VERSION {
-        return Err(anyhow::anyhow!(
-            "Unexpected response format version {}",
-            sor.version
-        ));
+    tracing::info!(imported, "Imported certifications from the back instance");
+    if done > 0 {
+        tracing::warn!(
+            ignored = done,
+            "Some certifications were ignored (they were already imported)"
+        );
     }
 
+    Ok(imported)
+}
+
+/// Ingest a response of the back instance (in JSON format).
+///
+/// Returns the number of imported entries, and the number of entries that
+/// were ignored because they had already been imported.
+pub(crate) fn import_response(
+    storage: &dyn CaStorageRW,
+    response: &[u8],
+) -> Result<(usize, usize)> {
+    let sor = SplitOcaResponse::from_reader(response)?;
+
     if sor.ca_fingerprint != storage.ca_get_cert_pub()?.fingerprint().to_hex() {
         return Err(anyhow::anyhow!(
             "Unexpected remote CA {}",
@@ -476,7 +661,8 @@ pub(crate) fn ca_split_import(storage: &dyn CaStorageRW, file: PathBuf) -> Resul
     let mut done: usize = 0;
 
     for (db_id, qr) in sor.queue {
-        if let Some(q) = storage.queue(db_id)? {
+        let queued = storage.queue(db_id)?.filter(|q| q.kind == QUEUE_SPLIT);
+        let profile = if let Some(q) = queued {
             // has this queue entry already been marked as "done"?
 
             if q.done {
@@ -485,29 +671,28 @@ pub(crate) fn ca_split_import(storage: &dyn CaStorageRW, file: PathBuf) -> Resul
                 // already done: skip processing this entry
                 continue;
             }
+
+            let qe: QueueEntry = serde_json::from_str(&q.task)?;
+            qe.profile()
         } else {
             return Err(anyhow::anyhow!(
                 "Got a result for an unexpected queue id: {}",
                 db_id
             ));
-        }
+        };
+        let origin = profile.history_label("split import");
 
         match qr {
             QueueResponse::CertificationResp(cr) => {
-                let mut packets: Vec<Packet> = vec![];
-                for s in cr.sigs {
-                    let bytes = general_purpose::STANDARD
-                        .decode(s)
-                        .map_err(|e| anyhow::anyhow!("Error while decoding base64: {}", e))?;
-                    let s = Signature::from_bytes(&bytes)?.into();
-                    packets.push(s);
-                }
+                let sigs = cr.signatures()?;
 
                 if let Some(cert) = storage.cert_by_fp(&cr.fingerprint)? {
+                    ct_log::record(storage, CtLogKind::Certification, &cr.fingerprint, &sigs)?;
+
                     let c = Cert::from_str(&cert.pub_cert)?;
-                    let certified = c.insert_packets(packets)?;
+                    let certified = c.insert_packets(sigs)?;
 
-                    storage.cert_update(&certified.to_vec()?)?;
+                    storage.cert_update(&certified.to_vec()?, &origin, false)?;
                 } else {
                     // FIXME: mark queue entry as failed?
                     return Err(anyhow::anyhow!("failed to load fp {}", cr.fingerprint));
@@ -516,7 +701,14 @@ pub(crate) fn ca_split_import(storage: &dyn CaStorageRW, file: PathBuf) -> Resul
             QueueResponse::BridgeResp(br) => {
                 // Merge update to bridge cert into database
                 // (presumably the update consists of a new tsig from our CA)
-                storage.cert_update(br.cert.as_bytes())?;
+                let tsigned = Cert::from_str(&br.cert)?;
+                let known = storage
+                    .cert_by_fp(&tsigned.fingerprint().to_hex())?
+                    .map(|c| Cert::from_str(&c.pub_cert))
+                    .transpose()?;
+                ct_log::record_new(storage, CtLogKind::TrustSignature, known.as_ref(), &tsigned)?;
+
+                storage.cert_update(br.cert.as_bytes(), &origin, false)?;
             }
         }
 
@@ -525,64 +717,33 @@ pub(crate) fn ca_split_import(storage: &dyn CaStorageRW, file: PathBuf) -> Resul
         storage.queue_mark_done(db_id)?;
     }
 
-    println!("Imported {len} certifications from the back instance.");
-    if done > 0 {
-        println!("WARN: {done} certifications were ignored (they were already imported).");
-    }
-
-    Ok(())
+    Ok((len - done, done))
 }
 
-pub(crate) fn ca_split_show_queue(storage: &dyn CaStorageRW) -> Result<()> {
-    let queue = storage.queue_not_done()?;
-    for q in queue {
-        let qe: QueueEntry = serde_json::from_str(&q.task)?;
-        match qe {
-            QueueEntry::CertificationReq(cr) => {
-                let c = Cert::from_str(&cr.cert)?;
-
-                println!("Certification request [#{}]", q.id);
-                println!("  For User IDs {:?}", cr.user_ids);
-                println!("  On {}", c.fingerprint().to_hex());
-                if let Some(days) = cr.days {
-                    println!("  Limited to {} days", days);
-                } else {
-                    println!("  No expiration");
-                }
-                println!("  Queued: {} UTC", q.created.format(CHRONO_FMT_NAIVE));
-                println!();
-            }
-            QueueEntry::BridgeReq(br) => {
-                let c = Cert::from_str(&br.cert)?;
-
-                println!("Bridging request [#{}]", q.id,);
-                println!("  For {}", c.fingerprint().to_hex(),);
-                if br.scope_regexes.is_empty() {
-                    println!("  Unscoped.");
-                } else {
-                    print!("  Scoped to");
-                    for s in br.scope_regexes {
-                        print!(" '{}'", s)
-                    }
-                    println!();
-                }
-                println!("  Queued: {} UTC", q.created.format(CHRONO_FMT_NAIVE));
-                println!();
-            }
-        }
-    }
-
-    Ok(())
+pub(crate) fn ca_split_queue(
+    storage: &dyn CaStorageRW,
+) -> Result<Vec<(i32, DateTime<Utc>, QueueEntry)>> {
+    storage
+        .queue_not_done()?
+        .into_iter()
+        .map(|q| {
+            let qe: QueueEntry = serde_json::from_str(&q.task)?;
+            Ok((q.id, Utc.from_utc_datetime(&q.created), qe))
+        })
+        .collect()
 }
 
 pub(crate) struct SplitBackDb {
+    // the back instance's own database (only holds the CA configuration)
+    back: Rc<OcaDb>,
+
     // read-only from separate oca file
     readonly: Option<Rc<OcaDb>>,
 }
 
 impl SplitBackDb {
-    pub(crate) fn new(readonly: Option<Rc<OcaDb>>) -> Self {
-        Self { readonly }
+    pub(crate) fn new(back: Rc<OcaDb>, readonly: Option<Rc<OcaDb>>) -> Self {
+        Self { back, readonly }
     }
 }
 
@@ -633,6 +794,20 @@ impl CaStorage for SplitBackDb {
         }
     }
 
+    fn cert_rows(&self) -> Result<Vec<models::Cert>> {
+        self.certs()
+    }
+
+    fn certs_quarantined(&self) -> Result<Vec<models::CertQuarantine>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.cert_quarantine_all()
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
     fn cert_by_id(&self, id: i32) -> Result<Option<models::Cert>> {
         if let Some(readonly) = &self.readonly {
             readonly.cert_by_id(id)
@@ -673,6 +848,63 @@ impl CaStorage for SplitBackDb {
         }
     }
 
+    fn certs_search(&self, text: &str) -> Result<Vec<models::Cert>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.certs_search(text)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn cert_versions(&self, cert: &models::Cert) -> Result<Vec<models::CertVersion>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.cert_versions_by_cert(cert)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn cert_version_by_id(&self, id: i32) -> Result<Option<models::CertVersion>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.cert_version_by_id(id)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn cert_versions_keep(&self) -> Result<u32> {
+        if let Some(readonly) = &self.readonly {
+            readonly.cert_versions_keep()
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn pref(&self, name: &str) -> Result<Option<String>> {
+        // Without overlay, the settings of the back instance itself apply
+        // (e.g. for the language, or the Tor proxy)
+        match &self.readonly {
+            Some(readonly) => readonly.pref(name),
+            None => self.back.pref(name),
+        }
+    }
+
+    fn verified_signatures(&self, cert: &models::Cert) -> Result<Vec<models::VerifiedSignature>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.verified_signatures_by_cert(cert)
+        } else {
+            Ok(vec![])
+        }
+    }
+
     fn emails(&self) -> Result<Vec<models::CertEmail>> {
         if let Some(readonly) = &self.readonly {
             readonly.emails()
@@ -703,9 +935,9 @@ impl CaStorage for SplitBackDb {
         }
     }
 
-    fn users_sorted_by_name(&self) -> Result<Vec<models::User>> {
+    fn user_by_id(&self, id: i32) -> Result<Option<models::User>> {
         if let Some(readonly) = &self.readonly {
-            readonly.users_sorted_by_name()
+            readonly.user_by_id(id)
         } else {
             Err(anyhow::anyhow!(
                 "Operation unsupported: split-mode backend CA without overlay database"
@@ -713,9 +945,9 @@ impl CaStorage for SplitBackDb {
         }
     }
 
-    fn revocation_exists(&self, revocation: &[u8]) -> Result<bool> {
+    fn cert_reassignments(&self, cert: &models::Cert) -> Result<Vec<models::CertReassignment>> {
         if let Some(readonly) = &self.readonly {
-            readonly.revocation_exists(revocation)
+            readonly.cert_reassignments_by_cert(cert)
         } else {
             Err(anyhow::anyhow!(
                 "Operation unsupported: split-mode backend CA without overlay database"
@@ -723,9 +955,9 @@ impl CaStorage for SplitBackDb {
         }
     }
 
-    fn revocations_by_cert(&self, cert: &models::Cert) -> Result<Vec<models::Revocation>> {
+    fn key_replacements(&self) -> Result<Vec<models::KeyReplacement>> {
         if let Some(readonly) = &self.readonly {
-            readonly.revocations_by_cert(cert)
+            readonly.key_replacements_all()
         } else {
             Err(anyhow::anyhow!(
                 "Operation unsupported: split-mode backend CA without overlay database"
@@ -733,9 +965,9 @@ impl CaStorage for SplitBackDb {
         }
     }
 
-    fn revocation_by_hash(&self, hash: &str) -> Result<Option<models::Revocation>> {
+    fn key_replacement_by_id(&self, id: i32) -> Result<Option<models::KeyReplacement>> {
         if let Some(readonly) = &self.readonly {
-            readonly.revocation_by_hash(hash)
+            readonly.key_replacement_by_id(id)
         } else {
             Err(anyhow::anyhow!(
                 "Operation unsupported: split-mode backend CA without overlay database"
@@ -743,9 +975,9 @@ impl CaStorage for SplitBackDb {
         }
     }
 
-    fn list_bridges(&self) -> Result<Vec<models::Bridge>> {
+    fn blocklist(&self) -> Result<Vec<models::BlocklistEntry>> {
         if let Some(readonly) = &self.readonly {
-            readonly.list_bridges()
+            readonly.blocklist_all()
         } else {
             Err(anyhow::anyhow!(
                 "Operation unsupported: split-mode backend CA without overlay database"
@@ -753,11 +985,19 @@ impl CaStorage for SplitBackDb {
         }
     }
 
-    // ------
+    fn ct_log(&self) -> Result<Vec<models::CtLogEntry>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.ct_log_all()
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
 
-    fn bridge_by_email(&self, email: &str) -> Result<Option<models::Bridge>> {
+    fn tags(&self) -> Result<Vec<models::Tag>> {
         if let Some(readonly) = &self.readonly {
-            readonly.bridge_by_email(email)
+            readonly.tags()
         } else {
             Err(anyhow::anyhow!(
                 "Operation unsupported: split-mode backend CA without overlay database"
@@ -765,9 +1005,9 @@ impl CaStorage for SplitBackDb {
         }
     }
 
-    fn queue(&self, id: i32) -> Result<Option<Queue>> {
+    fn tags_by_user(&self, user: &models::User) -> Result<Vec<models::Tag>> {
         if let Some(readonly) = &self.readonly {
-            readonly.queue_by_id(id)
+            readonly.tags_by_user(user.id)
         } else {
             Err(anyhow::anyhow!(
                 "Operation unsupported: split-mode backend CA without overlay database"
@@ -775,37 +1015,215 @@ impl CaStorage for SplitBackDb {
         }
     }
 
-    fn queue_not_done(&self) -> Result<Vec<models::Queue>> {
+    fn tags_by_cert(&self, cert: &models::Cert) -> Result<Vec<models::Tag>> {
         if let Some(readonly) = &self.readonly {
-            readonly.queue_not_done()
+            readonly.tags_by_cert(cert.id)
         } else {
             Err(anyhow::anyhow!(
                 "Operation unsupported: split-mode backend CA without overlay database"
             ))
         }
     }
-}
 
-/// Returns Errors for all fn, because a SplitBackDb should never
-/// be written to
-/// (some fn throw unimplemented, because they should definitely
-/// not be called on this Database implementation and indicate a
-/// wrong use of this struct)
-impl CaStorageWrite for SplitBackDb {
-    fn into_uninit(self: Box<Self>) -> UninitDb {
-        unimplemented!("This should never be used with a SplitBackDb")
+    fn groups(&self) -> Result<Vec<models::Group>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.groups()
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
     }
 
-    fn cacert_update(self: Box<Self>, _cacert: &Cacert) -> Result<()> {
-        Err(anyhow::anyhow!(
-            "Unsupported operation on Split-mode backend CA"
-        ))
+    fn group_by_name(&self, name: &str) -> Result<Option<models::Group>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.group_by_name(name)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
     }
 
-    fn ca_import_tsig(&self, _cert: &[u8]) -> Result<()> {
-        Err(anyhow::anyhow!(
-            "Unsupported operation on Split-mode backend CA"
-        ))
+    fn group_users(&self, group: &models::Group) -> Result<Vec<models::User>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.group_users(group.id)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn groups_by_user(&self, user: &models::User) -> Result<Vec<models::Group>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.groups_by_user(user.id)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn sync_states(&self, source: &str) -> Result<Vec<models::SyncState>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.sync_states(source)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn keyserver_packets(&self, cert: &models::Cert) -> Result<Vec<models::KeyserverPacket>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.keyserver_packets_by_cert(cert)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn users_sorted_by_name(&self) -> Result<Vec<models::User>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.users_sorted_by_name()
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn revocations(&self) -> Result<Vec<models::Revocation>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.revocations()
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn revocations_by_cert(&self, cert: &models::Cert) -> Result<Vec<models::Revocation>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.revocations_by_cert(cert)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn revocation_by_hash(&self, hash: &str) -> Result<Option<models::Revocation>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.revocation_by_hash(hash)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn list_bridges(&self) -> Result<Vec<models::Bridge>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.list_bridges()
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    // ------
+
+    fn bridge_by_email(&self, email: &str) -> Result<Option<models::Bridge>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.bridge_by_email(email)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn queue(&self, id: i32) -> Result<Option<Queue>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.queue_by_id(id)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn queue_not_done(&self) -> Result<Vec<models::Queue>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.queue_not_done()
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn proposals(&self) -> Result<Vec<models::Queue>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.queue_by_kind(QUEUE_PROPOSAL)
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn queue_done_created(&self) -> Result<Vec<(i32, NaiveDateTime)>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.queue_done_created()
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+
+    fn cert_versions_created(&self) -> Result<Vec<(i32, i32, NaiveDateTime)>> {
+        if let Some(readonly) = &self.readonly {
+            readonly.cert_versions_created()
+        } else {
+            Err(anyhow::anyhow!(
+                "Operation unsupported: split-mode backend CA without overlay database"
+            ))
+        }
+    }
+}
+
+/// Returns Errors for all fn, because a SplitBackDb should never
+/// be written to
+/// (some fn throw unimplemented, because they should definitely
+/// not be called on this Database implementation and indicate a
+/// wrong use of this struct)
+impl CaStorageWrite for SplitBackDb {
+    fn into_uninit(self: Box<Self>) -> UninitDb {
+        UninitDb::new(self.back)
+    }
+
+    fn cacert_update(self: Box<Self>, _cacert: &Cacert) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn ca_import_tsig(&self, _cert: &[u8]) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn ca_cert_merge(&self, _cert: &Cert) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
     }
 
     fn cert_add(
@@ -819,24 +1237,235 @@ impl CaStorageWrite for SplitBackDb {
         ))
     }
 
-    fn cert_update(&self, _cert: &[u8]) -> Result<()> {
+    fn cert_update(&self, _cert: &[u8], _origin: &str, _allow_downgrade: bool) -> Result<()> {
         Err(anyhow::anyhow!(
             "Unsupported operation on Split-mode backend CA"
         ))
     }
 
+    fn cert_version_restore(&self, _version: &models::CertVersion) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn cert_blob_replace(
+        &self,
+        _cert: &models::Cert,
+        _pub_cert: &str,
+        _origin: &str,
+    ) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn cert_quarantine(&self, _cert: &models::Cert, _error: &str) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn cert_quarantine_release(&self, _cert_id: i32) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn sync_state_record(
+        &self,
+        _cert: &models::Cert,
+        _source: &str,
+        _error: Option<&str>,
+    ) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn keyserver_packets_add(
+        &self,
+        _cert: &models::Cert,
+        _packets: &[(String, String, String)],
+    ) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn cert_versions_set_keep(&self, _keep: u32) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn pref_set(&self, _name: &str, _value: &str) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn verified_signatures_add(
+        &self,
+        _cert: &models::Cert,
+        _verified: &[(String, String)],
+    ) -> Result<()> {
+        // The cache is not persisted on a split-mode backend CA
+        Ok(())
+    }
+
     fn cert_delist(&self, _fp: &str) -> Result<()> {
         Err(anyhow::anyhow!(
             "Unsupported operation on Split-mode backend CA"
         ))
     }
 
+    fn cert_set_delisted(&self, _fp: &str, _channel: ExportChannel, _delisted: bool) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
     fn cert_deactivate(&self, _fp: &str) -> Result<()> {
         Err(anyhow::anyhow!(
             "Unsupported operation on Split-mode backend CA"
         ))
     }
 
+    fn cert_purge(&self, _fp: &str) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn cert_notes_set(&self, _fp: &str, _notes: Option<&str>, _append: bool) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn user_notes_set(&self, _fp: &str, _notes: Option<&str>, _append: bool) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn user_wkd_publish_set(&self, _fp: &str, _publish: bool) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn cert_tag_set(&self, _fp: &str, _name: &str, _value: Option<&str>) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn cert_tags_set(&self, _fp: &str, _tags: &[(&str, Option<&str>)]) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn user_tag_set(&self, _fp: &str, _name: &str, _value: Option<&str>) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn group_new(&self, _name: &str, _description: Option<&str>) -> Result<models::Group> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn group_delete(&self, _name: &str) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn group_member_set(&self, _name: &str, _fp: &str, _member: bool) -> Result<bool> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn cert_reassign(
+        &self,
+        _fp: &str,
+        _to: &str,
+        _new_user: bool,
+        _reason: Option<&str>,
+    ) -> Result<models::User> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn key_replacement_add(
+        &self,
+        _cert: &models::Cert,
+        _new_cert: &str,
+        _new_fingerprint: &str,
+        _handover: &str,
+    ) -> Result<models::KeyReplacement> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn key_replacement_approve(
+        &self,
+        _replacement: &models::KeyReplacement,
+        _pub_cert: &str,
+        _emails: &[&str],
+        _deactivate: bool,
+    ) -> Result<models::Cert> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn key_replacement_reject(
+        &self,
+        _replacement: &models::KeyReplacement,
+        _reason: Option<&str>,
+    ) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn blocklist_add(
+        &self,
+        _kind: &str,
+        _pattern: &str,
+        _reason: Option<&str>,
+    ) -> Result<models::BlocklistEntry> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn blocklist_remove(&self, _id: i32) -> Result<models::BlocklistEntry> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn ct_log_append(
+        &self,
+        _kind: &str,
+        _fingerprint: &str,
+        _signature: &str,
+    ) -> Result<models::CtLogEntry> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
     fn user_add(
         &self,
         _name: Option<&str>,
@@ -850,7 +1479,24 @@ impl CaStorageWrite for SplitBackDb {
         ))
     }
 
-    fn revocation_add(&self, _revocation: &[u8]) -> Result<()> {
+    fn revocation_add(&self, _revocation: &[u8]) -> Result<Revocation> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn revocation_delete(&self, _hash: &str) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn revocation_publication_record(
+        &self,
+        _revocation: &models::Revocation,
+        _channel: PublicationChannel,
+        _error: Option<&str>,
+    ) -> Result<()> {
         Err(anyhow::anyhow!(
             "Unsupported operation on Split-mode backend CA"
         ))
@@ -868,6 +1514,8 @@ impl CaStorageWrite for SplitBackDb {
         _remote_fp: &str,
         _remote_email: &str,
         _scope: &str,
+        _scope_regexes: &[String],
+        _profile: CertificationProfile,
     ) -> Result<Bridge> {
         Err(anyhow::anyhow!(
             "Unsupported operation on Split-mode backend CA"
@@ -877,6 +1525,48 @@ impl CaStorageWrite for SplitBackDb {
     fn queue_mark_done(&self, _id: i32) -> Result<()> {
         unimplemented!("This should never be used with a SplitBackDb")
     }
+
+    fn transaction(&self, f: &mut dyn FnMut() -> Result<()>) -> Result<()> {
+        self.back.transaction(f)
+    }
+
+    fn checkpoint(&self) -> Result<()> {
+        match &self.readonly {
+            Some(db) => db.checkpoint(),
+            None => Ok(()),
+        }
+    }
+
+    fn set_wal_autocheckpoint(&self, pages: u32) -> Result<()> {
+        match &self.readonly {
+            Some(db) => db.set_wal_autocheckpoint(pages),
+            None => Ok(()),
+        }
+    }
+
+    fn proposal_add(&self, _task: &str) -> Result<models::Queue> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn proposal_decide(&self, _id: i32, _status: &str, _reason: Option<&str>) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn queue_delete(&self, _ids: &[i32]) -> Result<usize> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
+
+    fn cert_versions_delete(&self, _ids: &[i32]) -> Result<usize> {
+        Err(anyhow::anyhow!(
+            "Unsupported operation on Split-mode backend CA"
+        ))
+    }
 }
 
 impl CaStorageRW for SplitBackDb {}
@@ -0,0 +1,148 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Blocklist of keys and domains that the CA never imports or certifies
+//! (e.g. known-compromised keys).
+//!
+//! Fingerprint entries match the primary key and all subkeys of a cert.
+//! Domain entries match the domain of email addresses, either exactly
+//! ("example.org"), or all subdomains ("*.example.org").
+//!
+//! Removed entries stay in the database, marked with the time of their
+//! removal, as a log of all changes to the blocklist.
+
+use anyhow::Result;
+use sequoia_openpgp::Cert;
+
+use crate::db::models;
+use crate::pgp;
+use crate::types::{BlocklistError, BlocklistKind, BlocklistMatch};
+use crate::Oca;
+
+/// Normalize `pattern`, and check that it is a legal pattern for `kind`
+fn normalize(kind: BlocklistKind, pattern: &str) -> Result<String> {
+    match kind {
+        BlocklistKind::Fingerprint => {
+            let fp = pgp::fingerprint_from_str(pattern)?;
+            if let sequoia_openpgp::Fingerprint::Invalid(_) = fp {
+                return Err(anyhow::anyhow!(
+                    "'{}' is not a fingerprint (key IDs can't be blocked)",
+                    pattern
+                ));
+            }
+            Ok(fp.to_hex())
+        }
+        BlocklistKind::Domain => {
+            let domain = pattern.trim().to_lowercase();
+            let name = domain.strip_prefix("*.").unwrap_or(&domain);
+
+            if name.is_empty()
+                || name.split('.').any(|label| {
+                    label.is_empty()
+                        || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                })
+            {
+                return Err(anyhow::anyhow!(
+                    "'{}' is not a domain (or '*.' followed by a domain)",
+                    pattern
+                ));
+            }
+            Ok(domain)
+        }
+    }
+}
+
+pub(crate) fn add(
+    oca: &Oca,
+    kind: BlocklistKind,
+    pattern: &str,
+    reason: Option<&str>,
+) -> Result<models::BlocklistEntry> {
+    let pattern = normalize(kind, pattern)?;
+
+    oca.storage.blocklist_add(kind.name(), &pattern, reason)
+}
+
+pub(crate) fn remove(oca: &Oca, id: i32) -> Result<models::BlocklistEntry> {
+    oca.storage.blocklist_remove(id)
+}
+
+/// The active entries of the blocklist
+pub(crate) fn entries(oca: &Oca) -> Result<Vec<models::BlocklistEntry>> {
+    Ok(oca
+        .storage
+        .blocklist()?
+        .into_iter()
+        .filter(|e| e.removed.is_none())
+        .collect())
+}
+
+/// Does the email domain `domain` match the domain pattern `pattern`?
+fn domain_matches(pattern: &str, domain: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(parent) => domain
+            .strip_suffix(parent)
+            .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+        None => domain == pattern,
+    }
+}
+
+/// The blocklist entries that match a key of `cert` (if any), or one of
+/// `emails`
+pub(crate) fn matches(
+    oca: &Oca,
+    cert: Option<&Cert>,
+    emails: &[&str],
+) -> Result<Vec<BlocklistMatch>> {
+    let entries = entries(oca)?;
+    if entries.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let fps: Vec<String> = cert
+        .map(|c| c.keys().map(|ka| ka.fingerprint().to_hex()).collect())
+        .unwrap_or_default();
+
+    let mut res = vec![];
+    for e in entries {
+        let kind: BlocklistKind = e.kind.parse()?;
+
+        let subjects: Vec<String> = match kind {
+            BlocklistKind::Fingerprint => {
+                fps.iter().filter(|fp| **fp == e.pattern).cloned().collect()
+            }
+            BlocklistKind::Domain => emails
+                .iter()
+                .filter(|email| {
+                    email
+                        .rsplit_once('@')
+                        .is_some_and(|(_, d)| domain_matches(&e.pattern, &d.to_lowercase()))
+                })
+                .map(|email| email.to_string())
+                .collect(),
+        };
+
+        res.extend(subjects.into_iter().map(|subject| BlocklistMatch {
+            subject,
+            kind,
+            pattern: e.pattern.clone(),
+            reason: e.reason.clone(),
+        }));
+    }
+
+    Ok(res)
+}
+
+/// Fail with a [BlocklistError] if a key of `cert`, or one of `emails`, is
+/// blocked
+pub(crate) fn check(oca: &Oca, cert: Option<&Cert>, emails: &[&str]) -> Result<()> {
+    let matches = matches(oca, cert, emails)?;
+    if !matches.is_empty() {
+        return Err(BlocklistError { matches }.into());
+    }
+
+    Ok(())
+}
@@ -1,16 +1,15 @@
 // SPDX-FileCopyrightText: 2019-2023 Heiko Schaefer <heiko@schaefer.name>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::path::Path;
-
-use anyhow::{Context, Result};
-use sequoia_openpgp::parse::Parse;
+use anyhow::Result;
+use sequoia_openpgp::packet::Signature;
 use sequoia_openpgp::serialize::SerializeInto;
 use sequoia_openpgp::{Cert, Fingerprint};
 
 use crate::db::models;
-use crate::pgp;
+use crate::types::{BridgeBundleImport, BridgeScope, CertificationProfile, CtLogKind};
 use crate::Oca;
+use crate::{blocklist, cert, ct_log, pgp};
 
 /// Create a new Bridge (between this OpenPGP CA and a remote OpenPGP
 /// CA instance)
@@ -19,16 +18,24 @@ use crate::Oca;
 /// CA. Once this signature is published and available to OpenPGP
 /// CA users, the bridge is in effect.
 ///
-/// When `remote_email` or `remote_scope` are not set, they are derived
-/// from the User ID in the key_file
+/// When `remote_email` is not set, it is derived from the User ID in
+/// `remote_ca_cert`.
+///
+/// The trust signature is made according to `profile`. If the profile
+/// doesn't allow scoping trust signatures, bridges that are scoped to a
+/// domain are unscoped (custom regexes are rejected).
 pub fn bridge_new(
     oca: &Oca,
-    remote_cert_file: &Path,
+    remote_ca_cert: Cert,
     remote_email: Option<&str>,
-    remote_scope: Option<&str>,
-    unscoped: bool,
+    scope: &BridgeScope,
+    profile: CertificationProfile,
 ) -> Result<(models::Bridge, Fingerprint)> {
-    let remote_ca_cert = Cert::from_file(remote_cert_file).context("Failed to read key")?;
+    if remote_ca_cert.fingerprint() == oca.ca_get_cert_pub()?.fingerprint() {
+        return Err(anyhow::anyhow!(
+            "This is the certificate of this CA, can't create a bridge to it."
+        ));
+    }
 
     let remote_uids: Vec<_> = remote_ca_cert.userids().collect();
 
@@ -60,6 +67,15 @@ pub fn bridge_new(
         }
     };
 
+    if remote_cert_domain.eq_ignore_ascii_case(oca.domainname()) {
+        return Err(anyhow::anyhow!(
+            "The remote CA uses the domain of this CA ({}), can't create a bridge to it.",
+            oca.domainname()
+        ));
+    }
+
+    blocklist::check(oca, Some(&remote_ca_cert), &[&remote_cert_email])?;
+
     // Email to store in the oca-database for this bridge
     let email = match remote_email {
         None => remote_cert_email,
@@ -67,64 +83,91 @@ pub fn bridge_new(
     };
 
     // Scope for the bridge (limit which user ids the trust signature is
-    // valid for, by domainname)
-    let scope = match remote_scope {
-        Some(scope) => {
-            // if scope and domain don't match, warn/error?
-            // (FIXME: error, unless --force parameter has been given?!)
-            if scope != remote_cert_domain {
+    // valid for)
+    let (scope, scope_regexes) = match scope {
+        BridgeScope::RemoteDomain => {
+            let regexes = domain_scope(&email, &remote_cert_domain, profile)?;
+            (remote_cert_domain, regexes)
+        }
+        BridgeScope::Domain { domain, force } => {
+            if !domain.eq_ignore_ascii_case(&remote_cert_domain) && !force {
                 return Err(anyhow::anyhow!(
-                    "Scope and domain don't match, currently unsupported"
+                    "Scope '{}' doesn't match the domain of the remote CA ('{}'), \
+                    use 'force' to create the bridge anyway",
+                    domain,
+                    remote_cert_domain
                 ));
             }
 
-            scope
+            let regexes = domain_scope(&email, domain, profile)?;
+            (domain.to_string(), regexes)
         }
-        None => &remote_cert_domain,
-    };
+        BridgeScope::Regexes(regexes) => {
+            if !profile.regex_trust_signatures() {
+                return Err(anyhow::anyhow!(
+                    "The '{}' profile doesn't support scoped trust signatures",
+                    profile
+                ));
+            }
 
-    let regex = domain_to_regex(scope)?;
-    let scope_regexes = if unscoped { vec![] } else { vec![regex] };
+            (remote_cert_domain, check_regexes(regexes)?)
+        }
+        BridgeScope::Unscoped => (remote_cert_domain, vec![]),
+    };
 
     // -- CA secret operation --
 
     // Make trust signature on the remote CA cert, to set up the bridge
-    let remote_ca = oca
-        .secret()
-        .bridge_to_remote_ca(remote_ca_cert, scope_regexes)?;
+    let remote_ca =
+        oca.secret()
+            .bridge_to_remote_ca(remote_ca_cert.clone(), scope_regexes.clone(), profile)?;
+    ct_log::record_new(
+        oca.storage.as_ref(),
+        CtLogKind::TrustSignature,
+        Some(&remote_ca_cert),
+        &remote_ca,
+    )?;
 
     let remote_armored = pgp::cert_to_armored(&remote_ca)?;
     let remote_fp = remote_ca.fingerprint().to_hex();
 
     // -- CA storage operation --
 
-    let bridge_db = oca
-        .storage
-        .bridge_add(&remote_armored, &remote_fp, &email, scope)?;
+    let bridge_db = oca.storage.bridge_add(
+        &remote_armored,
+        &remote_fp,
+        &email,
+        &scope,
+        &scope_regexes,
+        profile,
+    )?;
 
     Ok((bridge_db, remote_ca.fingerprint()))
 }
 
-pub fn bridge_revoke(oca: &Oca, email: &str) -> Result<()> {
+pub fn bridge_revoke(oca: &Oca, email: &str) -> Result<Signature> {
     // FIXME: db operations should be bracketed in a transaction
 
     if let Some(bridge) = oca.storage.bridge_by_email(email)? {
         if let Some(db_cert) = oca.storage.cert_by_id(bridge.cert_id)? {
-            let bridge_cert = pgp::to_cert(db_cert.pub_cert.as_bytes())?;
+            let bridge_cert = oca.storage.cert_parsed(&db_cert)?;
 
             // Generate revocation for the bridge
             let (revocation, revoked) = oca.secret().bridge_revoke(&bridge_cert)?;
-
-            // Print the revocation in case the user wants to publish it
-            // using external mechanisms.
-            println!(
-                "Revocation for the bridge to {}:\n{}",
-                email,
-                pgp::revoc_to_armored(&revocation, None)?
-            );
+            ct_log::record(
+                oca.storage.as_ref(),
+                CtLogKind::BridgeRevocation,
+                &db_cert.fingerprint,
+                std::slice::from_ref(&revocation),
+            )?;
 
             // Merge the revoked bridge Cert into DB
-            oca.storage.cert_update(&revoked.to_vec()?)
+            oca.storage
+                .cert_update(&revoked.to_vec()?, "bridge revocation", false)?;
+
+            // Return the revocation in case the user wants to publish it
+            // using external mechanisms.
+            Ok(revocation)
         } else {
             Err(anyhow::anyhow!("No cert found for bridge"))
         }
@@ -133,6 +176,163 @@ pub fn bridge_revoke(oca: &Oca, email: &str) -> Result<()> {
     }
 }
 
+/// A bundle for the operator of the remote CA of the bridge to `email`:
+/// the remote CA cert with our trust signature, followed by our CA cert,
+/// as one armored keyring.
+pub(crate) fn export_bundle(oca: &Oca, email: &str) -> Result<String> {
+    let bridge = oca.bridges_search(email)?;
+    let remote = oca.storage.cert_parsed(&oca.bridge_get_cert(&bridge)?)?;
+
+    let ca = oca.ca_get_cert_pub()?;
+    if !cert::check_tsig_on_cert(&ca, &remote)? {
+        return Err(anyhow::anyhow!(
+            "The bridge to '{}' has no trust signature by this CA",
+            email
+        ));
+    }
+
+    pgp::certs_to_armored(&[remote, ca])
+}
+
+/// Ingest a bundle that the remote CA exported for us (see
+/// [export_bundle]): merge the remote CA's trust signature into our CA
+/// cert, and create a bridge to the remote CA, if there is none yet.
+///
+/// If `fingerprint` is set, the remote CA key must have that fingerprint.
+/// The new bridge is scoped to the domain of the remote CA, with a trust
+/// signature according to `profile`.
+pub(crate) fn import_bundle(
+    oca: &Oca,
+    bundle: &[u8],
+    fingerprint: Option<&str>,
+    profile: CertificationProfile,
+) -> Result<BridgeBundleImport> {
+    let ca = oca.ca_get_cert_pub()?;
+
+    let certs = pgp::armored_keyring_to_certs(&bundle)?;
+    if certs.len() != 2 {
+        return Err(anyhow::anyhow!(
+            "Expected two certs in the bridge bundle, found {}",
+            certs.len()
+        ));
+    }
+
+    let (ours, remote): (Vec<_>, Vec<_>) = certs
+        .into_iter()
+        .partition(|c| c.fingerprint() == ca.fingerprint());
+    let (ours, remote) = match (ours.into_iter().next(), remote.into_iter().next()) {
+        (Some(ours), Some(remote)) => (ours, remote),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "The bridge bundle doesn't contain the cert of this CA"
+            ))
+        }
+    };
+
+    if let Some(fp) = fingerprint {
+        if pgp::normalize_fp(fp)? != remote.fingerprint().to_hex() {
+            return Err(anyhow::anyhow!(
+                "The remote CA key in the bridge bundle has the fingerprint {}, expected {}",
+                remote.fingerprint(),
+                fp
+            ));
+        }
+    }
+
+    // The remote CA must have made a valid trust signature on our CA cert
+    let tsigned = ours.userids().any(|uid| {
+        pgp::valid_certifications_by(&uid, &ours, remote.clone(), pgp::SP)
+            .iter()
+            .any(|s| s.trust_signature().is_some())
+    });
+    if !tsigned {
+        return Err(anyhow::anyhow!(
+            "The bridge bundle contains no trust signature by the remote CA on this CA's cert"
+        ));
+    }
+
+    oca.ca_import_tsig(&ours.to_vec()?)?;
+
+    let remote_fp = remote.fingerprint();
+
+    let existing = match oca.storage.cert_by_fp(&remote_fp.to_hex())? {
+        Some(db_cert) => oca
+            .storage
+            .list_bridges()?
+            .into_iter()
+            .find(|b| b.cert_id == db_cert.id),
+        None => None,
+    };
+
+    let (email, bridge_created) = match existing {
+        Some(bridge) => (bridge.email, false),
+        None => {
+            let (bridge, _) = bridge_new(oca, remote, None, &BridgeScope::RemoteDomain, profile)?;
+            (bridge.email, true)
+        }
+    };
+
+    Ok(BridgeBundleImport {
+        email,
+        fingerprint: remote_fp.to_hex(),
+        bridge_created,
+    })
+}
+
+/// The regexes for a bridge to `email` that is scoped to `domain`.
+///
+/// If `profile` doesn't support scoped trust signatures, the bridge is
+/// unscoped (with a warning).
+fn domain_scope(email: &str, domain: &str, profile: CertificationProfile) -> Result<Vec<String>> {
+    let regex = domain_to_regex(domain)?;
+
+    if profile.regex_trust_signatures() {
+        Ok(vec![regex])
+    } else {
+        tracing::warn!(
+            %profile,
+            email,
+            domain,
+            "The profile doesn't support scoped trust signatures, \
+             the bridge will NOT be limited to User IDs in the domain"
+        );
+
+        Ok(vec![])
+    }
+}
+
+/// Check that `regexes` are valid regular expressions for a trust
+/// signature, and remove duplicates
+fn check_regexes(regexes: &[String]) -> Result<Vec<String>> {
+    if regexes.is_empty() {
+        return Err(anyhow::anyhow!("No scope regex given"));
+    }
+
+    let mut res: Vec<String> = vec![];
+    for regex in regexes {
+        sequoia_openpgp::regex::Regex::new(regex)
+            .map_err(|e| anyhow::anyhow!("Invalid scope regex '{}': {}", regex, e))?;
+
+        if !res.contains(regex) {
+            res.push(regex.clone());
+        }
+    }
+
+    Ok(res)
+}
+
+/// The regular expressions that scope the trust signature of `bridge`
+/// (empty, if the bridge is unscoped).
+///
+/// `None` for bridges that were created before the scope was stored.
+pub(crate) fn scope_regexes(bridge: &models::Bridge) -> Result<Option<Vec<String>>> {
+    bridge
+        .scope_regexes
+        .as_deref()
+        .map(|json| Ok(serde_json::from_str(json)?))
+        .transpose()
+}
+
 /// Make regex for trust signature from domain name.
 ///
 /// ("other.org" => "<[^>]+[@.]other\\.org>$")
@@ -4,42 +4,75 @@
 // This file is part of OpenPGP CA
 // https://gitlab.com/openpgp-ca/openpgp-ca
 
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::time::{Duration, SystemTime};
 
 use anyhow::{Context, Result};
-use sequoia_openpgp::cert::amalgamation::ValidateAmalgamation;
+use chrono::{DateTime, Utc};
+use sequoia_openpgp::cert::amalgamation::{ValidAmalgamation, ValidateAmalgamation};
 use sequoia_openpgp::packet::{Signature, UserID};
+use sequoia_openpgp::policy::{HashAlgoSecurity, Policy};
 use sequoia_openpgp::serialize::SerializeInto;
-use sequoia_openpgp::Cert;
+use sequoia_openpgp::types::{RevocationStatus, SignatureType};
+use sequoia_openpgp::{Cert, Packet};
 
+use crate::backend::{self, card, Backend};
 use crate::db::models;
-use crate::pgp::{self, CipherSuite};
-use crate::secret::CaSec;
-use crate::types::CertificationStatus;
+use crate::events::{self, EventKind};
+use crate::pgp::{self, CipherSuite, KeyVersion, PasswordPolicy};
+use crate::types::{
+    CardUserKey, CertAsOf, CertDiff, CertOwnershipError, CertificationExtensionReport,
+    CertificationProfile, CertificationStatus, CertificationValidity, CtLogKind,
+    ExpiringCertification, KeyPolicyError, KeyProfile, NewUserKey, ProgressOperation,
+    ProvisioningBundle,
+};
 use crate::Oca;
+use crate::{blocklist, ct_log, key_profile, policy, progress, tsig};
 
 #[allow(clippy::too_many_arguments)]
 pub fn user_new(
     oca: &Oca,
     name: Option<&str>,
     emails: &[&str],
-    duration_days: Option<u64>,
+    roles: &[&str],
+    validity: Option<CertificationValidity>,
     password: bool,
-    password_file: Option<String>,
-    output_format_minimal: bool,
+    password_policy: Option<PasswordPolicy>,
     cipher_suite: Option<CipherSuite>,
+    key_version: Option<KeyVersion>,
+    key_validity_days: Option<u64>,
     enable_encryption_subkey: bool,
     enable_signing_subkey: bool,
     enable_authentication_subkey: bool,
-) -> Result<()> {
+    provision: Option<&dyn Fn(&Cert) -> Result<()>>,
+) -> Result<NewUserKey> {
+    if emails.is_empty() && roles.is_empty() {
+        return Err(anyhow::anyhow!(
+            "A new user key needs at least one email address or role"
+        ));
+    }
+
+    check_not_ca_email(oca, emails)?;
+    check_roles(roles)?;
+    blocklist::check(oca, None, emails)?;
+
+    // By default, user keys have the version of the CA key
+    let key_version = match key_version {
+        Some(key_version) => key_version,
+        None => KeyVersion::of(&oca.ca_get_cert_pub()?),
+    };
+
     // Generate new user key
     let (user_key, user_revoc, pass) = pgp::make_user_cert(
         emails,
+        roles,
         name,
         password,
-        password_file,
+        password_policy,
         cipher_suite,
+        key_version,
+        key_validity_days.map(|days| Duration::from_secs(days * pgp::SECONDS_IN_DAY)),
         enable_encryption_subkey,
         enable_signing_subkey,
         enable_authentication_subkey,
@@ -48,8 +81,22 @@ pub fn user_new(
 
     // -- CA secret operation --
     // CA certifies user cert
-    let user_certified = certify_emails(oca.secret(), &user_key, Some(emails), duration_days)
-        .context("sign_user_emails failed")?;
+    let user_certified = certify_emails(
+        oca,
+        &user_key,
+        Some(emails),
+        validity,
+        CertificationProfile::Default,
+    )
+    .context("sign_user_emails failed")?;
+    let user_certified = certify_roles(
+        oca,
+        &user_certified,
+        roles,
+        validity,
+        CertificationProfile::Default,
+    )
+    .context("certify_roles failed")?;
 
     // -- User key secret operation --
     // User tsigns CA cert
@@ -59,6 +106,12 @@ pub fn user_new(
 
     let tsigned_ca = pgp::cert_to_armored_private_key(&tsigned_ca)?;
 
+    // Move the user's private key material to its destination (e.g. an
+    // OpenPGP card), before the user is stored
+    if let Some(provision) = provision {
+        provision(&user_key)?;
+    }
+
     // Store new user cert in DB
     let user_cert = pgp::cert_to_armored(&user_certified)?;
     let user_revoc = pgp::revoc_to_armored(&user_revoc, None)?;
@@ -74,63 +127,136 @@ pub fn user_new(
         )
         .context("Failed to insert new user into DB")?;
 
-    // -- Communicate result to user --
+    let fp = user_key.fingerprint().to_hex();
+    events::emit(oca, EventKind::CertImported, Some(&fp));
+    if user_certified != user_key {
+        events::emit(oca, EventKind::CertCertified, Some(&fp));
+    }
 
-    // the private key needs to be handed over to the user -> print it
-    let private = pgp::cert_to_armored_private_key(&user_certified)?;
+    // -- Communicate result to user --
 
-    if output_format_minimal {
-        // short format (convenient for use with the 'pass' tool)
-        if let Some(pass) = pass {
-            println!("{pass}");
-        }
-        println!("{private}");
-    } else {
-        if let Some(name) = name {
-            eprintln!("Created new user key for {name}.\n");
-        } else {
-            eprintln!("Created new user key.\n");
-        }
+    // the private key needs to be handed over to the user -> return it
+    Ok(NewUserKey {
+        fingerprint: fp,
+        cert: user_cert,
+        private_key: pgp::cert_to_armored_private_key(&user_certified)?,
+        password: pass,
+    })
+}
 
-        println!("{private}");
+/// Create a new user with a key that is generated according to `profile`
+/// (`password_policy` overrides the password settings of the profile).
+#[allow(clippy::too_many_arguments)]
+pub fn user_new_with_profile(
+    oca: &Oca,
+    name: Option<&str>,
+    emails: &[&str],
+    roles: &[&str],
+    validity: Option<CertificationValidity>,
+    profile: &KeyProfile,
+    password_policy: Option<PasswordPolicy>,
+) -> Result<NewUserKey> {
+    let password = profile.password || password_policy.is_some();
+    let password_policy = password_policy.or_else(|| Some(key_profile::password_policy(profile)));
+
+    user_new(
+        oca,
+        name,
+        emails,
+        roles,
+        validity,
+        password,
+        password_policy,
+        Some(key_profile::cipher_suite(profile)?),
+        None,
+        profile.expiration_days,
+        profile.encryption_subkey,
+        profile.signing_subkey,
+        profile.authentication_subkey,
+        None,
+    )
+}
 
-        if let Some(pass) = pass {
-            eprintln!("Password for this key: '{pass}'.\n");
-        } else {
-            eprintln!("No password set for this key.\n");
-        }
-    }
+/// Create a new user, and move the subkeys of the generated key to the
+/// empty OpenPGP card `ident`, before the user is stored.
+///
+/// The private key is not returned: the card holds the only copy.
+pub(crate) fn user_new_on_card(
+    oca: &Oca,
+    ident: &str,
+    name: Option<&str>,
+    emails: &[&str],
+    validity: Option<CertificationValidity>,
+    cipher_suite: Option<CipherSuite>,
+    key_validity_days: Option<u64>,
+) -> Result<CardUserKey> {
+    let pins = RefCell::new(None);
+    let provision = |key: &Cert| -> Result<()> {
+        pins.replace(Some(card::provision_user_card(ident, key, name)?));
+        Ok(())
+    };
 
-    Ok(())
+    let key = user_new(
+        oca,
+        name,
+        emails,
+        &[],
+        validity,
+        false,
+        None,
+        cipher_suite,
+        None,
+        key_validity_days,
+        true,
+        true,
+        true,
+        Some(&provision),
+    )?;
+
+    let (user_pin, admin_pin) = pins
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("The user key was not provisioned to the card"))?;
+
+    Ok(CardUserKey {
+        fingerprint: key.fingerprint,
+        cert: key.cert,
+        ident: ident.to_string(),
+        user_pin,
+        admin_pin,
+    })
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn cert_import_new(
     oca: &Oca,
     user_cert: &[u8],
     revoc_certs: &[&[u8]],
     name: Option<&str>,
     cert_emails: &[&str],
-    duration_days: Option<u64>,
+    cert_uris: &[&str],
+    cert_roles: &[&str],
+    validity: Option<CertificationValidity>,
 ) -> Result<()> {
     let user_cert =
         pgp::to_cert(user_cert).context("cert_import_new: Couldn't process user cert.")?;
 
     let fp = user_cert.fingerprint().to_hex();
 
-    if let Some(_exists) = oca
-        .storage
-        .cert_by_fp(&fp)
-        .context("cert_import_new(): get_cert() check by fingerprint failed")?
-    {
-        // import_new is not intended for certs we already have a version of
+    if user_cert.fingerprint() == oca.ca_get_cert_pub()?.fingerprint() {
         return Err(anyhow::anyhow!(
-            "A key with this fingerprint already exists in the DB.\nTo update it, use the 'user update' command."
+            "This is the certificate of this CA, it can't be imported as a user key."
         ));
     }
 
-    // Sign user cert with CA key (only the User IDs that have been specified)
-    let certified = certify_emails(oca.secret(), &user_cert, Some(cert_emails), duration_days)
-        .context("sign_cert_emails() failed")?;
+    check_not_ca_email(oca, cert_emails)?;
+    check_uris_allowed(oca, cert_uris)?;
+    check_roles(cert_roles)?;
+    blocklist::check(oca, Some(&user_cert), cert_emails)?;
+
+    let violations = oca.check_key_policy(&user_cert)?;
+    if !violations.is_empty() {
+        return Err(KeyPolicyError { violations }.into());
+    }
 
     // Determine "name" for this user in the CA database
     let name = if let Some(name) = name {
@@ -153,6 +279,55 @@ pub fn cert_import_new(
         }
     };
 
+    if let Some(exists) = oca
+        .storage
+        .cert_by_fp(&fp)
+        .context("cert_import_new(): get_cert() check by fingerprint failed")?
+    {
+        // The same cert must not silently end up with a different user
+        if let Some(user) = oca.storage.user_by_cert(&exists)? {
+            if user.name != name {
+                return Err(CertOwnershipError {
+                    fingerprint: fp,
+                    user_id: user.id,
+                    user_name: user.name,
+                }
+                .into());
+            }
+        }
+
+        // import_new is not intended for certs we already have a version of
+        return Err(anyhow::anyhow!(
+            "A key with this fingerprint already exists in the DB.\nTo update it, use the 'user update' command."
+        ));
+    }
+
+    // Sign user cert with CA key (only the User IDs that have been specified)
+    let certified = certify_emails(
+        oca,
+        &user_cert,
+        Some(cert_emails),
+        validity,
+        CertificationProfile::Default,
+    )
+    .context("sign_cert_emails() failed")?;
+    let certified = certify_uris(
+        oca,
+        &certified,
+        cert_uris,
+        validity,
+        CertificationProfile::Default,
+    )
+    .context("certify_uris() failed")?;
+    let certified = certify_roles(
+        oca,
+        &certified,
+        cert_roles,
+        validity,
+        CertificationProfile::Default,
+    )
+    .context("certify_roles() failed")?;
+
     // Insert new user cert into DB
     let pub_cert =
         pgp::cert_to_armored(&certified).context("cert_import_new: Couldn't re-armor key")?;
@@ -175,32 +350,437 @@ pub fn cert_import_new(
         )
         .context("Couldn't insert user")?;
 
+    events::emit(oca, EventKind::CertImported, Some(&fp));
+    if certified != user_cert {
+        events::emit(oca, EventKind::CertCertified, Some(&fp));
+    }
+
+    Ok(())
+}
+
+/// Fail if `emails` contains the email address of this CA.
+///
+/// Users should never be associated with the CA's own identity.
+fn check_not_ca_email(oca: &Oca, emails: &[&str]) -> Result<()> {
+    let ca_email = oca.get_ca_email()?;
+
+    if let Some(email) = emails.iter().find(|e| e.eq_ignore_ascii_case(&ca_email)) {
+        return Err(anyhow::anyhow!(
+            "The email address {} belongs to this CA, it can't be used for a user.",
+            email
+        ));
+    }
+
+    Ok(())
+}
+
+/// The normalized email address in `uid` (if any)
+fn uid_email(uid: &UserID) -> Option<String> {
+    uid.email_normalized().ok().flatten()
+}
+
+/// Fail if one of `roles` can't be used as a role User ID (roles must not
+/// be empty, or contain an email address or URI).
+fn check_roles(roles: &[&str]) -> Result<()> {
+    if let Some(bad) = roles
+        .iter()
+        .find(|&&role| pgp::uid_role(&UserID::from(role)) != Some(role))
+    {
+        return Err(anyhow::anyhow!(
+            "'{}' can't be used as a role (it must not be empty, or contain an email address or URI)",
+            bad
+        ));
+    }
+
+    Ok(())
+}
+
+/// Fail if the URI policy of the CA doesn't allow certifying each of `uris`.
+fn check_uris_allowed(oca: &Oca, uris: &[&str]) -> Result<()> {
+    if uris.is_empty() {
+        return Ok(());
+    }
+
+    let policy = policy::uri_policy(oca)?;
+
+    let denied: Vec<_> = uris
+        .iter()
+        .filter(|uri| !policy::uri_allowed(&policy, uri))
+        .copied()
+        .collect();
+
+    if !denied.is_empty() {
+        return Err(anyhow::anyhow!(
+            "The URI policy of this CA doesn't allow certifying {}",
+            denied.join(", ")
+        ));
+    }
+
     Ok(())
 }
 
-pub fn cert_import_update(oca: &Oca, cert: &[u8]) -> Result<()> {
-    oca.storage.cert_update(cert)
+/// The stored armored cert `fp` (if any)
+fn stored_pub_cert(oca: &Oca, fp: &str) -> Result<Option<String>> {
+    Ok(oca.storage.cert_by_fp(fp)?.map(|c| c.pub_cert))
 }
 
-/// Certify the User IDs in `certify` in the Cert `c` (with validity of `validity_days`).
+pub fn cert_import_update(oca: &Oca, cert: &[u8], allow_downgrade: bool) -> Result<()> {
+    let fp = pgp::to_cert(cert)?.fingerprint().to_hex();
+    let before = stored_pub_cert(oca, &fp)?;
+
+    oca.storage.cert_update(cert, "import", allow_downgrade)?;
+
+    // Only announce updates that added something to the stored cert
+    if stored_pub_cert(oca, &fp)? != before {
+        events::emit(oca, EventKind::CertUpdated, Some(&fp));
+    }
+
+    Ok(())
+}
+
+/// Certify the User IDs of the cert `fp` that contain one of `emails`,
+/// with signatures according to `profile`.
+///
+/// User IDs that are already certified by the CA are skipped.
+pub fn cert_certify(
+    oca: &Oca,
+    fp: &str,
+    emails: &[&str],
+    validity: Option<CertificationValidity>,
+    profile: CertificationProfile,
+) -> Result<()> {
+    if emails.is_empty() {
+        return Err(anyhow::anyhow!("No email addresses to certify given"));
+    }
+
+    let db_cert = oca
+        .storage
+        .cert_by_fp(fp)?
+        .ok_or_else(|| anyhow::anyhow!("No cert with fingerprint {} found", fp))?;
+    let cert = oca.storage.cert_parsed(&db_cert)?;
+
+    let certified = certify_emails(oca, &cert, Some(emails), validity, profile)?;
+
+    oca.storage.cert_update(
+        &certified.to_vec()?,
+        &profile.history_label("certification"),
+        false,
+    )?;
+
+    events::emit(oca, EventKind::CertCertified, Some(&db_cert.fingerprint));
+
+    Ok(())
+}
+
+/// Merge the certifications (and certification revocations) that the CA
+/// key has made on User IDs of `cert` outside of OpenPGP CA into the stored
+/// copy of the cert.
+///
+/// All other packets of `cert` are ignored. Signatures that claim to be
+/// issued by the CA are rejected if they don't verify for their User ID, or
+/// if the CA policy doesn't accept them.
+///
+/// Returns the number of newly merged signatures.
+pub fn import_certifications(oca: &Oca, cert: &[u8]) -> Result<usize> {
+    let upload = pgp::to_cert(cert)?;
+    let fp = upload.fingerprint().to_hex();
+
+    let db_cert = oca
+        .storage
+        .cert_by_fp(&fp)?
+        .ok_or_else(|| anyhow::anyhow!("No cert with fingerprint {} found", fp))?;
+    let stored = oca.storage.cert_parsed(&db_cert)?;
+
+    let ca = oca.ca_get_cert_pub()?;
+    let policy = oca.policy()?;
+
+    let certifier_keys: Vec<_> = ca
+        .keys()
+        .with_policy(&policy, None)
+        .for_certification()
+        .collect();
+
+    let pk = stored.primary_key();
+
+    let mut sigs: Vec<Signature> = vec![];
+
+    for uid in upload.userids() {
+        let by_ca: Vec<&Signature> = uid
+            .certifications()
+            .chain(uid.other_revocations())
+            .filter(|s| {
+                s.get_issuers()
+                    .iter()
+                    .any(|i| certifier_keys.iter().any(|k| k.key_handle().aliases(i)))
+            })
+            .collect();
+
+        if by_ca.is_empty() {
+            continue;
+        }
+
+        let stored_uid = stored
+            .userids()
+            .find(|u| u.userid() == uid.userid())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "User ID '{}' is not part of the stored cert {}",
+                    String::from_utf8_lossy(uid.userid().value()),
+                    fp
+                )
+            })?;
+
+        for s in by_ca {
+            let valid = certifier_keys.iter().any(|k| {
+                if s.typ() == SignatureType::CertificationRevocation {
+                    s.verify_userid_revocation(k.key(), &pk, uid.userid())
+                        .is_ok()
+                } else {
+                    s.clone()
+                        .verify_userid_binding(k.key(), &pk, uid.userid())
+                        .is_ok()
+                }
+            });
+            if !valid {
+                return Err(anyhow::anyhow!(
+                    "Invalid certification by the CA on User ID '{}'",
+                    String::from_utf8_lossy(uid.userid().value())
+                ));
+            }
+
+            policy
+                .signature(s, HashAlgoSecurity::CollisionResistance)
+                .context("Certification by the CA rejected by policy")?;
+
+            let known = stored_uid
+                .certifications()
+                .chain(stored_uid.other_revocations())
+                .any(|k| k.normalized_eq(s));
+            if !known {
+                sigs.push(s.clone());
+            }
+        }
+    }
+
+    let count = sigs.len();
+    if count > 0 {
+        let certified = stored.insert_packets(sigs)?;
+
+        oca.storage
+            .cert_update(&certified.to_vec()?, "certification import", false)?;
+
+        events::emit(oca, EventKind::CertCertified, Some(&fp));
+    }
+
+    Ok(count)
+}
+
+fn cert_version(oca: &Oca, id: i32) -> Result<models::CertVersion> {
+    oca.storage
+        .cert_version_by_id(id)?
+        .ok_or_else(|| anyhow::anyhow!("No cert version with id {} found", id))
+}
+
+/// Compare the previous cert version `from` with the version `to`
+/// (or with the current state of the cert, if `to` is None).
+pub fn cert_versions_diff(oca: &Oca, from: i32, to: Option<i32>) -> Result<CertDiff> {
+    let from = cert_version(oca, from)?;
+
+    let to = match to {
+        Some(id) => {
+            let to = cert_version(oca, id)?;
+            if to.cert_id != from.cert_id {
+                return Err(anyhow::anyhow!(
+                    "Cert versions {} and {} belong to different certs",
+                    from.id,
+                    to.id
+                ));
+            }
+            to.pub_cert
+        }
+        None => {
+            oca.storage
+                .cert_by_id(from.cert_id)?
+                .context("Couldn't find cert for version")?
+                .pub_cert
+        }
+    };
+
+    let old = pgp::to_cert(from.pub_cert.as_bytes())?;
+    let new = pgp::to_cert(to.as_bytes())?;
+
+    packets_diff(old, new)
+}
+
+/// Reconstruct the state of the cert `fingerprint` at `time` from its
+/// version history.
+///
+/// Certifications are checked against the current CA cert, with the CA's
+/// policy at `time`.
+pub(crate) fn cert_as_of(oca: &Oca, fingerprint: &str, time: DateTime<Utc>) -> Result<CertAsOf> {
+    let fp = pgp::normalize_fp(fingerprint)?;
+    let db_cert = oca
+        .storage
+        .cert_by_fp(&fp)?
+        .ok_or_else(|| anyhow::anyhow!("No cert with fingerprint {} found", fp))?;
+
+    let t = time.naive_utc();
+
+    if db_cert.created.is_some_and(|created| t < created) {
+        return Ok(CertAsOf {
+            time,
+            cert: None,
+            version: None,
+            certified: vec![],
+            revoked: false,
+            incomplete: false,
+        });
+    }
+
+    // Versions are stored in the order in which they were superseded
+    let versions = oca.storage.cert_versions(&db_cert)?;
+
+    // Without a recorded creation time, the cert is only known to have
+    // existed since its first recorded version was superseded
+    let incomplete = db_cert.history_since.is_some_and(|since| t < since)
+        || (db_cert.created.is_none() && !versions.first().is_some_and(|v| t >= v.created));
+
+    // The version that was current at `time` is the first one that was
+    // superseded after `time`
+    let version = versions.iter().find(|v| v.created > t);
+    let armored = version.map_or(&db_cert.pub_cert, |v| &v.pub_cert);
+    let cert = pgp::to_cert(armored.as_bytes())?;
+
+    let at = SystemTime::from(time);
+    let policy = oca.policy_at(at)?;
+    let ca = oca.ca_get_cert_pub()?;
+
+    let certified = cert
+        .userids()
+        .filter(|uid| {
+            pgp::valid_certifications_by(uid, &cert, ca.clone(), &policy)
+                .iter()
+                .any(|s| s.signature_alive(at, Duration::ZERO).is_ok())
+        })
+        .map(|uid| uid.userid().clone())
+        .collect();
+
+    let revoked = matches!(
+        cert.revocation_status(&policy, at),
+        RevocationStatus::Revoked(_)
+    );
+
+    Ok(CertAsOf {
+        time,
+        version: version.map(|v| v.id),
+        cert: Some(cert),
+        certified,
+        revoked,
+        incomplete,
+    })
+}
+
+fn packets_diff(old: Cert, new: Cert) -> Result<CertDiff> {
+    let serialized = |c: Cert| -> Result<Vec<(Vec<u8>, Packet)>> {
+        c.into_packets2().map(|p| Ok((p.to_vec()?, p))).collect()
+    };
+
+    let old = serialized(old)?;
+    let new = serialized(new)?;
+
+    let old_set: HashSet<_> = old.iter().map(|(b, _)| b.clone()).collect();
+    let new_set: HashSet<_> = new.iter().map(|(b, _)| b.clone()).collect();
+
+    let added = new
+        .iter()
+        .filter(|(b, _)| !old_set.contains(b))
+        .map(|(_, p)| describe_packet(p))
+        .collect();
+    let removed = old
+        .iter()
+        .filter(|(b, _)| !new_set.contains(b))
+        .map(|(_, p)| describe_packet(p))
+        .collect();
+
+    Ok(CertDiff { added, removed })
+}
+
+fn describe_packet(p: &Packet) -> String {
+    match p {
+        Packet::PublicKey(k) => format!("Primary key {}", k.fingerprint()),
+        Packet::PublicSubkey(k) => format!("Subkey {}", k.fingerprint()),
+        Packet::UserID(uid) => format!("User ID '{}'", String::from_utf8_lossy(uid.value())),
+        Packet::Signature(sig) => {
+            let issuer = sig
+                .get_issuers()
+                .first()
+                .map(|i| i.to_hex())
+                .unwrap_or_else(|| "unknown issuer".to_string());
+
+            let created = sig
+                .signature_creation_time()
+                .map(|t| DateTime::<Utc>::from(t).format("%F %T %Z").to_string())
+                .unwrap_or_else(|| "unknown time".to_string());
+
+            format!(
+                "Signature ({}) by {}, created {}",
+                sig.typ(),
+                issuer,
+                created
+            )
+        }
+        p => format!("{} packet", p.tag()),
+    }
+}
+
+/// Restore a previous version of a cert (the current version is retained
+/// as a previous version, so this operation can be undone).
+pub fn cert_version_restore(oca: &Oca, id: i32) -> Result<()> {
+    let version = cert_version(oca, id)?;
+
+    oca.storage.cert_version_restore(&version)
+}
+
+/// Certify the User IDs in `certify` in the Cert `c` (with `validity`).
 /// Then update `db_cert` in the database to contain the resulting armored cert.
 fn add_certifications(
     oca: &Oca,
     certify: Vec<&UserID>,
     c: &Cert,
-    validity_days: u64,
+    validity: CertificationValidity,
 ) -> Result<()> {
     if !certify.is_empty() {
+        let emails: Vec<_> = certify.iter().filter_map(|u| uid_email(u)).collect();
+        let emails: Vec<&str> = emails.iter().map(String::as_str).collect();
+        blocklist::check(oca, Some(c), &emails)?;
+
         // Make new certifications for the User IDs identified above
-        let sigs = oca
-            .secret()
-            .sign_user_ids(c, &certify[..], Some(validity_days))?;
+        let sigs = oca.secret().sign_user_ids(
+            c,
+            &certify[..],
+            Some(validity),
+            CertificationProfile::Default,
+            policy::certification_notation(oca)?.as_ref(),
+        )?;
+        ct_log::record(
+            oca.storage.as_ref(),
+            CtLogKind::Certification,
+            &c.fingerprint().to_hex(),
+            &sigs,
+        )?;
 
         let certified = c.clone().insert_packets(sigs)?;
 
         // Merge cert updates into db
         // (a Cert merge operation is performed in a DB transaction)
-        oca.storage.cert_update(&certified.to_vec()?)?;
+        oca.storage
+            .cert_update(&certified.to_vec()?, "certification", false)?;
+
+        events::emit(
+            oca,
+            EventKind::CertCertified,
+            Some(&c.fingerprint().to_hex()),
+        );
     }
 
     Ok(())
@@ -209,29 +789,58 @@ fn add_certifications(
 pub fn certs_refresh_ca_certifications(
     oca: &Oca,
     threshold_days: u64,
-    validity_days: u64,
+    validity: CertificationValidity,
+) -> Result<()> {
+    refresh_ca_certifications(oca, oca.storage.certs()?, threshold_days, validity)
+}
+
+/// Refresh the CA certifications on `db_certs` that expire in less than
+/// `threshold_days` (see [certs_refresh_ca_certifications])
+pub(crate) fn refresh_ca_certifications(
+    oca: &Oca,
+    db_certs: Vec<models::Cert>,
+    threshold_days: u64,
+    validity: CertificationValidity,
 ) -> Result<()> {
     // FIXME: fail/report individual certification problems?
 
     let threshold_time =
         SystemTime::now() + Duration::from_secs(threshold_days * pgp::SECONDS_IN_DAY);
 
+    // New certifications must outlast the threshold, otherwise they would
+    // get renewed again on every run
+    if let CertificationValidity::Until(until) = validity {
+        if SystemTime::from(until) <= threshold_time {
+            return Err(anyhow::anyhow!(
+                "New certifications would expire within {} days ({})",
+                threshold_days,
+                until.format("%F %T %Z")
+            ));
+        }
+    }
+
     let ca = oca.ca_get_cert_pub()?;
+    let policy = oca.policy()?;
+
+    // ignore "inactive" Certs
+    let db_certs: Vec<_> = db_certs.into_iter().filter(|c| !c.inactive).collect();
+
+    for (i, db_cert) in db_certs.iter().enumerate() {
+        progress::report(
+            oca,
+            ProgressOperation::Recertification,
+            i,
+            db_certs.len(),
+            Some(&db_cert.fingerprint),
+        );
 
-    for db_cert in oca
-        .storage
-        .certs()?
-        .into_iter()
-        // ignore "inactive" Certs
-        .filter(|c| !c.inactive)
-    {
-        let c = pgp::to_cert(db_cert.pub_cert.as_bytes())?;
+        let c = oca.storage.cert_parsed(db_cert)?;
 
         let mut re_certify = Vec::new();
 
         for uid in c.userids() {
             // find valid certifications by the CA on this uid
-            let ca_certifications = pgp::valid_certifications_by(&uid, &c, ca.clone());
+            let ca_certifications = pgp::valid_certifications_by(&uid, &c, ca.clone(), &policy);
 
             let sig_valid_past_threshold = |sig: &Signature| {
                 if let Some(expiration) = sig.signature_expiration_time() {
@@ -253,15 +862,206 @@ pub fn certs_refresh_ca_certifications(
             }
         }
 
-        add_certifications(oca, re_certify, &c, validity_days)?;
+        add_certifications(oca, re_certify, &c, validity)?;
     }
 
+    progress::report(
+        oca,
+        ProgressOperation::Recertification,
+        db_certs.len(),
+        db_certs.len(),
+        None,
+    );
+
     Ok(())
 }
 
-pub fn certs_re_certify(oca: &Oca, cert_old: Cert, validity_days: u64) -> Result<()> {
+pub fn certs_recertify_expired(oca: &Oca, grace_days: u64) -> Result<Vec<models::Cert>> {
+    let now = SystemTime::now();
+    let grace_start = now - Duration::from_secs(grace_days * pgp::SECONDS_IN_DAY);
+
+    let ca = oca.ca_get_cert_pub()?;
+    let policy = oca.policy()?;
+
+    let mut recertified = vec![];
+
+    for db_cert in oca
+        .storage
+        .certs()?
+        .into_iter()
+        // ignore "inactive" Certs
+        .filter(|c| !c.inactive)
+    {
+        let c = oca.storage.cert_parsed(&db_cert)?;
+
+        // Only certs that are valid themselves get new certifications
+        let valid = match c.with_policy(&policy, now) {
+            Ok(valid) => valid,
+            Err(_) => continue,
+        };
+        if valid.alive().is_err() {
+            continue;
+        }
+        if let RevocationStatus::Revoked(_) = valid.revocation_status() {
+            continue;
+        }
+
+        // User IDs to re-certify, grouped by the validity (in days) of
+        // their expired certification
+        let mut re_certify: BTreeMap<u64, Vec<&UserID>> = BTreeMap::new();
+
+        for uid in valid.userids().revoked(false) {
+            let ca_certifications = pgp::valid_certifications_by(&uid, &c, ca.clone(), &policy);
+
+            if let Some(expired) = pgp::certifications_expired(&ca_certifications, now) {
+                if expired < grace_start {
+                    continue;
+                }
+
+                // Use the validity period of the certification that expired last
+                let days = ca_certifications
+                    .iter()
+                    .filter(|s| s.signature_expiration_time() == Some(expired))
+                    .filter_map(|s| s.signature_validity_period())
+                    .map(|p| p.as_secs().div_ceil(pgp::SECONDS_IN_DAY).max(1))
+                    .next()
+                    .unwrap_or(1);
+
+                re_certify.entry(days).or_default().push(uid.userid());
+            }
+        }
+
+        if re_certify.is_empty() {
+            continue;
+        }
+
+        for (days, uids) in re_certify {
+            add_certifications(oca, uids, &c, CertificationValidity::Days(days))?;
+        }
+
+        recertified.push(db_cert);
+    }
+
+    Ok(recertified)
+}
+
+/// Renew the CA certifications of User IDs whose certifications all expire
+/// between `window_start` and `window_end` (e.g. a change freeze), on
+/// certs that are otherwise fine (active, and neither expired nor revoked).
+pub fn certifications_extend_expiring(
+    oca: &Oca,
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+    validity_days: u64,
+    dry_run: bool,
+) -> Result<CertificationExtensionReport> {
+    if window_start >= window_end {
+        return Err(anyhow::anyhow!("The window must start before it ends"));
+    }
+
+    let now = SystemTime::now();
+
+    let new_expiration: DateTime<Utc> =
+        (now + Duration::from_secs(validity_days * pgp::SECONDS_IN_DAY)).into();
+    if new_expiration <= window_end {
+        return Err(anyhow::anyhow!(
+            "New certifications that are valid for {} days would expire before the end of the window",
+            validity_days
+        ));
+    }
+
+    let ca = oca.ca_get_cert_pub()?;
+    let policy = oca.policy()?;
+
+    let mut report = CertificationExtensionReport {
+        queued: !dry_run && matches!(oca.backend(), Backend::SplitFront),
+        ..Default::default()
+    };
+
+    for db_cert in oca
+        .storage
+        .certs()?
+        .into_iter()
+        // ignore "inactive" Certs
+        .filter(|c| !c.inactive)
+    {
+        let c = oca.storage.cert_parsed(&db_cert)?;
+
+        // Only certs that are valid themselves get new certifications
+        let valid = match c.with_policy(&policy, now) {
+            Ok(valid) => valid,
+            Err(_) => continue,
+        };
+        if valid.alive().is_err() {
+            continue;
+        }
+        if let RevocationStatus::Revoked(_) = valid.revocation_status() {
+            continue;
+        }
+
+        let user_name = oca.storage.user_by_cert(&db_cert)?.and_then(|u| u.name);
+
+        let mut expiring = vec![];
+        let mut re_certify = vec![];
+
+        for uid in valid.userids().revoked(false) {
+            let ca_certifications = pgp::valid_certifications_by(&uid, &c, ca.clone(), &policy);
+
+            // The certification that is valid the longest (None, if one of
+            // them doesn't expire)
+            let expiration = ca_certifications
+                .iter()
+                .map(|s| s.signature_expiration_time())
+                .try_fold(None, |latest: Option<SystemTime>, exp| {
+                    exp.map(|exp| latest.max(Some(exp)))
+                })
+                .flatten();
+
+            if let Some(expiration) = expiration {
+                let expiration: DateTime<Utc> = expiration.into();
+
+                if expiration >= window_start && expiration <= window_end {
+                    expiring.push(ExpiringCertification {
+                        fingerprint: db_cert.fingerprint.clone(),
+                        user_name: user_name.clone(),
+                        user_id: String::from_utf8_lossy(uid.userid().value()).to_string(),
+                        expiration,
+                    });
+                    re_certify.push(uid.userid());
+                }
+            }
+        }
+
+        if !dry_run && !re_certify.is_empty() {
+            let count = re_certify.len();
+
+            match add_certifications(
+                oca,
+                re_certify,
+                &c,
+                CertificationValidity::Days(validity_days),
+            ) {
+                Ok(()) => report.renewed += count,
+                Err(e) => report
+                    .failed
+                    .push((db_cert.fingerprint.clone(), format!("{e:#}"))),
+            }
+        }
+
+        report.expiring.append(&mut expiring);
+    }
+
+    Ok(report)
+}
+
+/// Returns the fingerprints of the certs for which new certifications were made.
+pub fn certs_re_certify(oca: &Oca, cert_old: Cert, validity_days: u64) -> Result<Vec<String>> {
     // FIXME: fail/report individual certification problems?
 
+    let policy = oca.policy()?;
+
+    let mut recertified = vec![];
+
     for db_cert in oca
         .storage
         .certs()?
@@ -271,28 +1071,69 @@ pub fn certs_re_certify(oca: &Oca, cert_old: Cert, validity_days: u64) -> Result
     {
         let ca_new = oca.ca_get_cert_pub()?;
 
-        let c = pgp::to_cert(db_cert.pub_cert.as_bytes())?;
+        let c = oca.storage.cert_parsed(&db_cert)?;
 
         let mut re_certify = Vec::new();
 
         for uid in c.userids() {
             // find valid certifications by the old CA on this uid
-            let ca_certifications = pgp::valid_certifications_by(&uid, &c, cert_old.clone());
+            let ca_certifications =
+                pgp::valid_certifications_by(&uid, &c, cert_old.clone(), &policy);
 
             // A new certification is created if any certification by old_cert exists
             if !ca_certifications.is_empty() {
                 // Only certify if there is not yet any certification by the current CA key
-                if pgp::valid_certifications_by(&uid, &c, ca_new.clone()).is_empty() {
+                if pgp::valid_certifications_by(&uid, &c, ca_new.clone(), &policy).is_empty() {
                     // A new certification for this uid should be created
                     re_certify.push(uid.userid());
                 }
             }
         }
 
-        add_certifications(oca, re_certify, &c, validity_days)?;
+        if !re_certify.is_empty() {
+            recertified.push(c.fingerprint().to_hex());
+        }
+
+        add_certifications(
+            oca,
+            re_certify,
+            &c,
+            CertificationValidity::Days(validity_days),
+        )?;
+
+        dedup_ca_certifications(oca, &db_cert.fingerprint)?;
     }
 
-    Ok(())
+    Ok(recertified)
+}
+
+/// Remove redundant certifications by the CA (made with several of its
+/// certification capable keys at once) from the cert `fp`. If a
+/// certification key is selected, its certifications are kept.
+///
+/// Returns the number of removed certifications.
+fn dedup_ca_certifications(oca: &Oca, fp: &str) -> Result<usize> {
+    let db_cert = oca
+        .storage
+        .cert_by_fp(fp)?
+        .context(format!("Cert {fp} not found"))?;
+    let c = oca.storage.cert_parsed(&db_cert)?;
+
+    let ca = oca.ca_get_cert_pub()?;
+    let preferred = backend::certification_key(oca)?;
+
+    let (deduplicated, removed) =
+        pgp::dedup_certifications(c, &ca, preferred.as_deref(), &oca.policy()?)?;
+
+    if removed > 0 {
+        oca.storage.cert_blob_replace(
+            &db_cert,
+            &pgp::cert_to_armored(&deduplicated)?,
+            "deduplication",
+        )?;
+    }
+
+    Ok(removed)
 }
 
 /// Return a list of Certs that are alive now, but will not be alive
@@ -308,16 +1149,17 @@ pub fn certs_expired(oca: &Oca, days: u64) -> Result<HashMap<models::Cert, Optio
     let expiry_test = SystemTime::now().checked_add(days).unwrap();
 
     let certs = oca.user_certs_get_all().context("couldn't load certs")?;
+    let policy = oca.policy()?;
 
     for db_cert in certs {
-        let c = pgp::to_cert(db_cert.pub_cert.as_bytes())?;
+        let c = oca.storage.cert_parsed(&db_cert)?;
 
         // Notify only certs that are alive now, but not alive at
         // 'expiry_test'.
-        if c.with_policy(pgp::SP, None)?.alive().is_ok()
-            && c.with_policy(pgp::SP, expiry_test)?.alive().is_err()
+        if c.with_policy(&policy, None)?.alive().is_ok()
+            && c.with_policy(&policy, expiry_test)?.alive().is_err()
         {
-            res.insert(db_cert, pgp::get_expiry(&c)?);
+            res.insert(db_cert, pgp::get_expiry(&c, &policy)?);
         }
     }
 
@@ -325,23 +1167,240 @@ pub fn certs_expired(oca: &Oca, days: u64) -> Result<HashMap<models::Cert, Optio
 }
 
 pub fn cert_check_ca_sig(oca: &Oca, cert: &models::Cert) -> Result<CertificationStatus> {
-    let c = pgp::to_cert(cert.pub_cert.as_bytes())?;
+    let c = oca.storage.cert_parsed(cert)?;
     let ca = oca.ca_get_cert_pub()?;
+    let policy = oca.policy()?;
+
+    // If the notation policy requires it, only certifications that carry
+    // the CA's notation count
+    let notation_policy = policy::notation_policy(oca)?;
+    let required = match notation_policy.required {
+        true => notation_policy.notation(oca.domainname()),
+        false => None,
+    };
 
     let mut certified = vec![];
     let mut uncertified = vec![];
+    let mut expired = vec![];
 
-    for uid in c.userids() {
-        if pgp::valid_certifications_by(&uid, &c, ca.clone()).is_empty() {
-            uncertified.push(uid.userid().clone());
-        } else {
-            certified.push(uid.userid().clone());
+    let now = SystemTime::now();
+
+    with_verified_signatures(oca, cert, |verified| {
+        for uid in c.userids() {
+            let mut sigs =
+                pgp::valid_certifications_by_cached(&uid, &c, ca.clone(), &policy, verified);
+            if let Some(notation) = &required {
+                sigs.retain(|s| notation.is_on(s));
+            }
+
+            if sigs.is_empty() {
+                uncertified.push(uid.userid().clone());
+            } else {
+                if pgp::certifications_expired(&sigs, now).is_some() {
+                    expired.push(uid.userid().clone());
+                }
+                certified.push(uid.userid().clone());
+            }
         }
-    }
+    })?;
 
     Ok(CertificationStatus {
         certified,
         uncertified,
+        expired,
+    })
+}
+
+/// Run `f` with the cached signature verification results for `cert`.
+///
+/// Results that `f` adds are stored in the database afterwards. If the
+/// cache is disabled for `oca`, `f` starts out with no cached results, and
+/// nothing is stored.
+fn with_verified_signatures<T>(
+    oca: &Oca,
+    cert: &models::Cert,
+    f: impl FnOnce(&mut pgp::VerifiedSignatures) -> T,
+) -> Result<T> {
+    if !oca.verification_cache.get() {
+        return Ok(f(&mut HashMap::new()));
+    }
+
+    let mut verified: pgp::VerifiedSignatures = oca
+        .storage
+        .verified_signatures(cert)?
+        .into_iter()
+        .map(|v| (v.digest, v.verified_by))
+        .collect();
+    let cached: HashSet<String> = verified.keys().cloned().collect();
+
+    let res = f(&mut verified);
+
+    let new: Vec<_> = verified
+        .into_iter()
+        .filter(|(digest, _)| !cached.contains(digest))
+        .collect();
+    if !new.is_empty() {
+        oca.storage.verified_signatures_add(cert, &new)?;
+    }
+
+    Ok(res)
+}
+
+/// Retract the CA certification(s) on the User ID `userid` of the cert
+/// `fingerprint`.
+///
+/// The certification revocation is merged into the cert, and thus gets
+/// published along with it.
+pub fn cert_retract_certification(
+    oca: &Oca,
+    fingerprint: &str,
+    userid: &str,
+    reason: &str,
+) -> Result<()> {
+    let fp = pgp::normalize_fp(fingerprint)?;
+    let db_cert = oca
+        .storage
+        .cert_by_fp(&fp)?
+        .ok_or_else(|| anyhow::anyhow!("No cert with fingerprint {} found", fp))?;
+
+    let c = oca.storage.cert_parsed(&db_cert)?;
+    let ca = oca.ca_get_cert_pub()?;
+
+    let uid = c
+        .userids()
+        .find(|u| u.userid().value() == userid.as_bytes())
+        .ok_or_else(|| anyhow::anyhow!("Cert {} has no User ID '{}'", fp, userid))?;
+
+    if pgp::valid_certifications_by(&uid, &c, ca, &oca.policy()?).is_empty() {
+        return Err(anyhow::anyhow!(
+            "User ID '{}' is not certified by this CA",
+            userid
+        ));
+    }
+
+    let rev = oca
+        .secret()
+        .revoke_certification(&c, uid.userid(), reason)?;
+    ct_log::record(
+        oca.storage.as_ref(),
+        CtLogKind::CertificationRevocation,
+        &fp,
+        std::slice::from_ref(&rev),
+    )?;
+
+    let revoked = c.clone().insert_packets(rev)?;
+
+    oca.storage
+        .cert_update(&revoked.to_vec()?, "certification retraction", false)
+}
+
+/// How long a cached result of [lookup_valid_cert] may be reused
+/// (as long as the underlying certs in the database are unchanged)
+const LOOKUP_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A cached result of [lookup_valid_cert] for one email address
+pub(crate) struct CachedLookup {
+    // The database certs (id, armored cert) that the result is based on
+    certs: Vec<(i32, String)>,
+    checked: SystemTime,
+    cert: Option<Cert>,
+}
+
+/// Find the currently valid, CA-certified cert for `email`.
+///
+/// Results are cached in `oca`, for up to `LOOKUP_CACHE_TTL`.
+/// A cached result is discarded early if any cert for `email` changes in the
+/// database.
+pub fn lookup_valid_cert(oca: &Oca, email: &str) -> Result<Option<Cert>> {
+    let db_certs: Vec<_> = oca
+        .storage
+        .certs_by_email(email)?
+        .into_iter()
+        // certs that are delisted on all channels are not published
+        .filter(|c| !c.delisted)
+        .collect();
+
+    let certs: Vec<_> = db_certs
+        .iter()
+        .map(|c| (c.id, c.pub_cert.clone()))
+        .collect();
+
+    let now = SystemTime::now();
+
+    if let Some(cached) = oca.lookup_cache.borrow().get(email) {
+        let fresh = now
+            .duration_since(cached.checked)
+            .map(|age| age < LOOKUP_CACHE_TTL)
+            .unwrap_or(false);
+
+        if fresh && cached.certs == certs {
+            return Ok(cached.cert.clone());
+        }
+    }
+
+    let ca = oca.ca_get_cert_pub()?;
+    let policy = oca.policy()?;
+
+    let mut valid = vec![];
+    for db_cert in &db_certs {
+        let c = oca.storage.cert_parsed(db_cert)?;
+        if with_verified_signatures(oca, db_cert, |verified| {
+            valid_for_email(&c, &ca, email, now, &policy, verified)
+        })? {
+            valid.push(c);
+        }
+    }
+
+    // If there are multiple valid certs, prefer the most recent one
+    let cert = valid
+        .into_iter()
+        .max_by_key(|c| c.primary_key().creation_time());
+
+    oca.lookup_cache.borrow_mut().insert(
+        email.to_string(),
+        CachedLookup {
+            certs,
+            checked: now,
+            cert: cert.clone(),
+        },
+    );
+
+    Ok(cert)
+}
+
+/// Is `cert` valid at `time` (under `policy`), with a User ID for `email`
+/// that is certified by `ca`?
+fn valid_for_email(
+    cert: &Cert,
+    ca: &Cert,
+    email: &str,
+    time: SystemTime,
+    policy: &dyn Policy,
+    verified: &mut pgp::VerifiedSignatures,
+) -> bool {
+    let valid = match cert.with_policy(policy, time) {
+        Ok(valid) => valid,
+        Err(_) => return false,
+    };
+
+    if valid.alive().is_err() {
+        return false;
+    }
+    if let RevocationStatus::Revoked(_) = valid.revocation_status() {
+        return false;
+    }
+
+    let email = email.to_lowercase();
+
+    valid.userids().any(|uid| {
+        let matches = matches!(uid.email_normalized(), Ok(Some(e)) if e == email);
+        let revoked = matches!(uid.revocation_status(), RevocationStatus::Revoked(_));
+
+        matches
+            && !revoked
+            && pgp::valid_certifications_by_cached(&uid, cert, ca.clone(), policy, verified)
+                .iter()
+                .any(|s| s.signature_alive(time, Duration::ZERO).is_ok())
     })
 }
 
@@ -355,10 +1414,57 @@ pub(crate) fn check_tsig_on_cert(signer: &Cert, signee: &Cert) -> Result<bool> {
     }))
 }
 
+pub fn user_provisioning_bundle(
+    oca: &Oca,
+    fp: &str,
+    private_key: Option<&str>,
+) -> Result<ProvisioningBundle> {
+    let fp = pgp::normalize_fp(fp)?;
+
+    let cert = oca
+        .storage
+        .cert_by_fp(&fp)?
+        .ok_or_else(|| anyhow::anyhow!("No cert with fingerprint {} found", fp))?;
+
+    if cert.user_id.is_none() {
+        return Err(anyhow::anyhow!("Cert {} doesn't belong to a user", fp));
+    }
+
+    let user_key = match private_key {
+        Some(key) => {
+            let key = pgp::to_cert(key.as_bytes())?;
+
+            if key.fingerprint().to_hex() != cert.fingerprint {
+                return Err(anyhow::anyhow!(
+                    "The private key {} doesn't match the cert {}",
+                    key.fingerprint(),
+                    fp
+                ));
+            }
+            if !key.is_tsk() {
+                return Err(anyhow::anyhow!("The key {} is not a private key", fp));
+            }
+
+            pgp::cert_to_armored_private_key(&key)?
+        }
+        None => cert.pub_cert.clone(),
+    };
+
+    let ca_cert_tsigned = tsig::ca_cert_tsigned_by(oca, &cert.fingerprint)?
+        .map(|ca| pgp::cert_to_armored(&ca))
+        .transpose()?;
+
+    Ok(ProvisioningBundle {
+        user_key,
+        ca_cert: oca.ca_get_pubkey_armored()?,
+        ca_cert_tsigned,
+    })
+}
+
 /// Has "cert" tsigned this CAs certificate?
 pub fn cert_check_tsig_on_ca(oca: &Oca, cert: &models::Cert) -> Result<bool> {
     let ca = oca.ca_get_cert_pub()?;
-    let user_cert = pgp::to_cert(cert.pub_cert.as_bytes())?;
+    let user_cert = oca.storage.cert_parsed(cert)?;
 
     check_tsig_on_cert(&user_cert, &ca)
 }
@@ -367,12 +1473,14 @@ pub fn cert_check_tsig_on_ca(oca: &Oca, cert: &models::Cert) -> Result<bool> {
 ///
 /// 'emails_filter' (if not None) specifies the subset of User IDs to
 /// certify.
-fn certify_emails(
-    ca_sec: &dyn CaSec,
+pub(crate) fn certify_emails(
+    oca: &Oca,
     cert: &Cert,
     emails_filter: Option<&[&str]>,
-    duration_days: Option<u64>,
+    validity: Option<CertificationValidity>,
+    profile: CertificationProfile,
 ) -> Result<Cert> {
+    let ca_sec = oca.secret();
     let fp_ca = ca_sec.cert()?.fingerprint();
 
     let mut uids = Vec::new();
@@ -419,21 +1527,119 @@ fn certify_emails(
         }
     }
 
-    // Print a message when specified email addresses couldn't be found and certified on a User ID.
-    //
-    // FIXME: this information should not be printed here, but returned to the user of the library
-    // as a list
+    // Log a warning when specified email addresses couldn't be found and certified on a User ID.
     if !unused_email.is_empty() {
         let mut unused: Vec<_> = unused_email.into_iter().collect();
         unused.sort_unstable();
 
-        println!(
-            "Warning: Couldn't find a User ID to certify for '{}' in {}",
-            unused.join(", "),
-            cert.fingerprint()
+        tracing::warn!(
+            fingerprint = %cert.fingerprint(),
+            emails = %unused.join(", "),
+            "Couldn't find a User ID to certify"
         );
     }
 
-    let sigs = ca_sec.sign_user_ids(cert, &uids, duration_days)?;
+    let emails: Vec<_> = uids.iter().filter_map(|u| uid_email(u)).collect();
+    let emails: Vec<&str> = emails.iter().map(String::as_str).collect();
+    blocklist::check(oca, Some(cert), &emails)?;
+
+    let notation = policy::certification_notation(oca)?;
+    let sigs = ca_sec.sign_user_ids(cert, &uids, validity, profile, notation.as_ref())?;
+    ct_log::record(
+        oca.storage.as_ref(),
+        CtLogKind::Certification,
+        &cert.fingerprint().to_hex(),
+        &sigs,
+    )?;
+    cert.clone().insert_packets(sigs)
+}
+
+/// Certify the User IDs of `cert` that carry one of the URI identifiers
+/// in `uris` (User IDs that are already certified by the CA are skipped).
+///
+/// Fails if there is no User ID for one of `uris`.
+fn certify_uris(
+    oca: &Oca,
+    cert: &Cert,
+    uris: &[&str],
+    validity: Option<CertificationValidity>,
+    profile: CertificationProfile,
+) -> Result<Cert> {
+    certify_identifiers(oca, cert, uris, pgp::uid_uri, validity, profile)
+}
+
+/// Certify the role User IDs of `cert` (User IDs without an email address
+/// or URI, such as "Backup signing key 2024") that are listed in `roles`.
+///
+/// Fails if there is no User ID for one of `roles`.
+fn certify_roles(
+    oca: &Oca,
+    cert: &Cert,
+    roles: &[&str],
+    validity: Option<CertificationValidity>,
+    profile: CertificationProfile,
+) -> Result<Cert> {
+    certify_identifiers(oca, cert, roles, pgp::uid_role, validity, profile)
+}
+
+/// Certify the User IDs of `cert` whose identifier (as determined by `id`)
+/// is one of `ids` (User IDs that are already certified by the CA are
+/// skipped).
+///
+/// Fails if there is no User ID for one of `ids`.
+fn certify_identifiers(
+    oca: &Oca,
+    cert: &Cert,
+    ids: &[&str],
+    id: impl Fn(&UserID) -> Option<&str>,
+    validity: Option<CertificationValidity>,
+    profile: CertificationProfile,
+) -> Result<Cert> {
+    if ids.is_empty() {
+        return Ok(cert.clone());
+    }
+
+    let ca_sec = oca.secret();
+    let fp_ca = ca_sec.cert()?.fingerprint();
+
+    if let Some(missing) = ids
+        .iter()
+        .find(|&&i| !cert.userids().any(|uid| id(uid.userid()) == Some(i)))
+    {
+        return Err(anyhow::anyhow!(
+            "Couldn't find a User ID for '{}' in {}",
+            missing,
+            cert.fingerprint()
+        ));
+    }
+
+    let mut uids = Vec::new();
+
+    for uid in cert.userids() {
+        if !id(uid.userid()).is_some_and(|i| ids.contains(&i)) {
+            continue;
+        }
+
+        // Don't add another certification, if this User ID is already certified
+        if !uid
+            .clone()
+            .with_policy(pgp::SP, None)?
+            .certifications()
+            .any(|s| s.issuer_fingerprints().any(|fp| fp == &fp_ca))
+        {
+            uids.push(uid.userid());
+        }
+    }
+
+    blocklist::check(oca, Some(cert), &[])?;
+
+    let notation = policy::certification_notation(oca)?;
+    let sigs = ca_sec.sign_user_ids(cert, &uids, validity, profile, notation.as_ref())?;
+    ct_log::record(
+        oca.storage.as_ref(),
+        CtLogKind::Certification,
+        &cert.fingerprint().to_hex(),
+        &sigs,
+    )?;
     cert.clone().insert_packets(sigs)
 }
@@ -0,0 +1,443 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Human-readable, structured reports about OpenPGP certificates: User IDs,
+//! (sub)keys with their algorithms, expiration and revocations, and the
+//! verdict of a policy on each component.
+//!
+//! With the "schemars" feature, the report types implement `JsonSchema`.
+
+use std::convert::{TryFrom, TryInto};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sequoia_openpgp::cert::amalgamation::key::ErasedKeyAmalgamation;
+use sequoia_openpgp::cert::amalgamation::{ComponentAmalgamation, ValidateAmalgamation};
+use sequoia_openpgp::packet::key;
+use sequoia_openpgp::packet::Signature;
+use sequoia_openpgp::policy::{Policy, StandardPolicy};
+use sequoia_openpgp::types::{HashAlgorithm, PublicKeyAlgorithm};
+use sequoia_openpgp::Cert;
+use serde::{Deserialize, Serialize};
+
+use crate::Oca;
+
+/// Human-readable, factual information about an OpenPGP certificate
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CertInfo {
+    pub user_ids: Vec<UserId>,
+
+    pub primary: Key,
+    pub subkeys: Vec<Key>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct UserId {
+    pub email: Option<String>,
+    pub name: Option<String>,
+
+    /// If the UserID consists of valid utf8, this field contains the raw data
+    /// (in many cases this will be redundant with the data in email + name).
+    ///
+    /// NOTE: this field contains user-provided utf8. It may contain html or
+    /// quotes, which the frontend might need to protect itself against.
+    pub raw: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revocations: Option<Vec<Revocation>>,
+
+    /// If the policy rejects this User ID: the reason
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Key {
+    pub fingerprint: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<String>,
+
+    pub creation_time: DateTime<Utc>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration_time: Option<DateTime<Utc>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// if this (sub-)key has an expiration_time, `expires_in_sec` shows in
+    /// how many seconds it will expire (e.g. "+1000" means "will expire in
+    /// 1000 seconds"), or if negative, how long ago it has expired (e.g.
+    /// "-1000" means "has expired 1000s ago)
+    pub expires_in_sec: Option<i64>,
+
+    pub algo: String,
+    pub bits: usize,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revocations: Option<Vec<Revocation>>,
+
+    /// If the policy rejects this (sub-)key: the reason
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Revocation {
+    pub reason: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub information: Option<String>,
+
+    pub time: Option<DateTime<Utc>>,
+}
+
+/// Kinds of problems that a cert will run into in the future
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum CertWarningKind {
+    /// The cert relies on SHA1 hashes, which the policy will reject
+    #[allow(clippy::upper_case_acronyms)]
+    WeakCryptoSHA1,
+
+    /// The cert will expire in the next 90 days
+    ExpiresSoon,
+
+    /// A key uses RSA, DSA or ElGamal with insufficient key length
+    WeakKey,
+
+    /// A User ID has no valid self-signature (under the policy of the CA)
+    UnboundUserId,
+
+    /// An email address of the user is not contained in any User ID
+    MissingUserId,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CertWarning {
+    pub kind: CertWarningKind,
+    pub message: String,
+}
+
+impl CertInfo {
+    /// Information about `cert`, with its components evaluated by `policy`
+    pub fn with_policy(cert: &Cert, policy: &dyn Policy) -> Result<Self> {
+        let mut user_ids: Vec<UserId> = vec![];
+
+        for userid in cert.userids() {
+            let mut uid: UserId = (&userid).try_into()?;
+            uid.policy_error = userid
+                .with_policy(policy, None)
+                .err()
+                .map(|e| format!("{e:#}"));
+
+            user_ids.push(uid)
+        }
+
+        let ka: ErasedKeyAmalgamation<_> = cert.primary_key().into();
+        let primary = Key::with_policy(&ka, policy);
+
+        let subkeys = cert
+            .keys()
+            .subkeys()
+            .map(|ka| {
+                let ka: ErasedKeyAmalgamation<_> = ka.into();
+                Key::with_policy(&ka, policy)
+            })
+            .collect();
+
+        Ok(CertInfo {
+            user_ids,
+            primary,
+            subkeys,
+        })
+    }
+}
+
+impl TryFrom<&Cert> for CertInfo {
+    type Error = anyhow::Error;
+
+    /// Information about `cert`, evaluated by Sequoia's standard policy
+    fn try_from(cert: &Cert) -> Result<Self, Self::Error> {
+        CertInfo::with_policy(cert, &StandardPolicy::new())
+    }
+}
+
+impl TryFrom<&ComponentAmalgamation<'_, sequoia_openpgp::packet::UserID>> for UserId {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        uid: &ComponentAmalgamation<sequoia_openpgp::packet::UserID>,
+    ) -> Result<Self, Self::Error> {
+        let email = uid
+            .email2()
+            .context("ERROR while converting userid.email")?
+            .map(|s| s.to_string());
+
+        let name = uid
+            .name2()
+            .context("ERROR while converting userid.name")?
+            .map(|s| s.to_string());
+
+        let raw = String::from_utf8(uid.value().to_vec()).ok();
+
+        let revocations: Vec<_> = uid.self_revocations().map(|rev| rev.into()).collect();
+
+        let revocations = if revocations.is_empty() {
+            None
+        } else {
+            Some(revocations)
+        };
+
+        Ok(UserId {
+            email,
+            name,
+            raw,
+            revocations,
+            policy_error: None,
+        })
+    }
+}
+
+impl Key {
+    fn with_policy(ka: &ErasedKeyAmalgamation<key::PublicParts>, policy: &dyn Policy) -> Self {
+        let (expiration, flags, policy_error) = match ka.clone().with_policy(policy, None) {
+            Ok(valid_sk) => (valid_sk.key_expiration_time(), valid_sk.key_flags(), None),
+            Err(e) => (None, None, Some(format!("{e:#}"))),
+        };
+
+        let expires_in_sec = if let Some(exp) = expiration {
+            let now = SystemTime::now();
+
+            if exp > now {
+                // expiration is in the future
+                Some(exp.duration_since(now).unwrap().as_secs() as i64)
+            } else {
+                // expiration is in the past
+                Some(-(now.duration_since(exp).unwrap().as_secs() as i64))
+            }
+        } else {
+            None
+        };
+
+        let fingerprint = ka.fingerprint().to_spaced_hex();
+
+        let creation = ka.creation_time();
+
+        let algo = ka.pk_algo();
+        let algo = algo.to_string();
+        let bits = ka.key().mpis().bits().unwrap_or(0);
+
+        let flags = if let Some(f) = flags {
+            if !f.is_empty() {
+                Some(format!("{f:?}"))
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        let revocations: Vec<_> = ka.self_revocations().map(|rev| rev.into()).collect();
+
+        let revocations = if revocations.is_empty() {
+            None
+        } else {
+            Some(revocations)
+        };
+
+        Key {
+            fingerprint,
+            flags,
+            creation_time: creation.into(),
+            expiration_time: expiration.map(|time| time.into()),
+            expires_in_sec,
+            algo,
+            bits,
+            revocations,
+            policy_error,
+        }
+    }
+}
+
+impl From<&ErasedKeyAmalgamation<'_, key::PublicParts>> for Key {
+    fn from(ka: &ErasedKeyAmalgamation<key::PublicParts>) -> Self {
+        Key::with_policy(ka, &StandardPolicy::new())
+    }
+}
+
+impl From<&Signature> for Revocation {
+    fn from(rev: &Signature) -> Self {
+        let rfr = rev.reason_for_revocation();
+
+        if let Some(r) = rfr {
+            let reason = Some(r.0.to_string());
+
+            let information = if let Ok(msg) = String::from_utf8(r.1.to_vec()) {
+                if !msg.is_empty() {
+                    Some(msg)
+                } else {
+                    None
+                }
+            } else {
+                Some("ERROR: bad utf8".to_string())
+            };
+
+            let rev_time = rev.signature_creation_time();
+            let time = rev_time.map(|time| time.into());
+
+            Revocation {
+                reason,
+                information,
+                time,
+            }
+        } else {
+            Revocation {
+                reason: None,
+                information: None,
+                time: None,
+            }
+        }
+    }
+}
+
+/// Information about `cert`, with its components evaluated by the policy
+/// of the CA
+pub(crate) fn cert_info(oca: &Oca, cert: &Cert) -> Result<CertInfo> {
+    CertInfo::with_policy(cert, &oca.policy()?)
+}
+
+/// Warnings for `cert`.
+///
+/// Warnings are currently generated for:
+/// - The CA policy gives an error for 'now + 2 years', but not when
+///   allowing for SHA1
+/// - Cert is alive now, but not in 'now + 3 months'
+pub(crate) fn cert_warnings(oca: &Oca, cert: &Cert) -> Result<Vec<CertWarning>> {
+    let mut warns = Vec::new();
+    let now = SystemTime::now();
+
+    // Check if the policy is bad in 'now + 2 years', but good when
+    // allowing for SHA1.
+    let now2y = now
+        .checked_add(Duration::from_secs(60 * 60 * 24 * 365 * 2))
+        .context("cert_warnings: duration checked_add failed")?;
+
+    let policy_plus2y = oca.policy_at(now2y)?;
+    let valid2y = cert.with_policy(&policy_plus2y, Some(now2y));
+
+    let mut sp_plus_sha1 = oca.policy_at(now2y)?;
+    sp_plus_sha1.accept_hash(HashAlgorithm::SHA1);
+    let valid2y_sha1 = cert.with_policy(&sp_plus_sha1, now2y);
+
+    if valid2y.is_err() && valid2y_sha1.is_ok() {
+        warns.push(CertWarning {
+            kind: CertWarningKind::WeakCryptoSHA1,
+            message: "This certificate relies on SHA1 hashes, which are deprecated. \
+                      It should be updated!"
+                .to_string(),
+        });
+    }
+
+    // Check if cert is alive() now, but will not be in 'now + 3 months'.
+    // If so: warn about imminent expiry.
+    let now3m = now
+        .checked_add(Duration::from_secs(60 * 60 * 24 * 30 * 3))
+        .context("cert_warnings: duration checked_add failed")?;
+
+    let policy_plus3m = oca.policy_at(now3m)?;
+    let policy_now = oca.policy_at(now)?;
+
+    let alive_now = cert
+        .with_policy(&policy_now, None)
+        .map(|vc| vc.alive().is_ok())
+        .unwrap_or(false);
+    let alive_3m = cert
+        .with_policy(&policy_plus3m, Some(now3m))
+        .map(|vc| vc.alive().is_ok());
+
+    if alive_now && matches!(alive_3m, Ok(false)) {
+        warns.push(CertWarning {
+            kind: CertWarningKind::ExpiresSoon,
+            message: "Will expire in the next 90 days".to_string(),
+        });
+    }
+
+    Ok(warns)
+}
+
+/// Problems with `cert`, as an import for a user with the email addresses
+/// `emails`: the warnings of [cert_warnings], weak keys, User IDs without a
+/// valid self-signature, and email addresses without a User ID.
+pub(crate) fn cert_import_warnings(
+    oca: &Oca,
+    cert: &Cert,
+    emails: &[&str],
+) -> Result<Vec<CertWarning>> {
+    let mut warns = cert_warnings(oca, cert)?;
+
+    for ka in cert.keys() {
+        #[allow(deprecated)]
+        let weak_algo = matches!(
+            ka.key().pk_algo(),
+            PublicKeyAlgorithm::RSAEncryptSign
+                | PublicKeyAlgorithm::RSAEncrypt
+                | PublicKeyAlgorithm::RSASign
+                | PublicKeyAlgorithm::ElGamalEncryptSign
+                | PublicKeyAlgorithm::ElGamalEncrypt
+                | PublicKeyAlgorithm::DSA
+        );
+
+        // Some implementations generate 2047 bits when 2048 are requested
+        if let Some(bits) = ka.key().mpis().bits() {
+            if weak_algo && bits <= 2046 {
+                warns.push(CertWarning {
+                    kind: CertWarningKind::WeakKey,
+                    message: format!(
+                        "Key {} uses {} with only {} bits",
+                        ka.key().fingerprint(),
+                        ka.key().pk_algo(),
+                        bits
+                    ),
+                });
+            }
+        }
+    }
+
+    let policy = oca.policy()?;
+    for uid in cert.userids() {
+        let name = String::from_utf8_lossy(uid.userid().value()).to_string();
+        if uid.with_policy(&policy, None).is_err() {
+            warns.push(CertWarning {
+                kind: CertWarningKind::UnboundUserId,
+                message: format!("User ID '{name}' has no valid self-signature"),
+            });
+        }
+    }
+
+    for email in emails {
+        let found = cert.userids().any(|uid| {
+            uid.userid()
+                .email_normalized()
+                .ok()
+                .flatten()
+                .is_some_and(|e| e == email.to_lowercase())
+        });
+        if !found {
+            warns.push(CertWarning {
+                kind: CertWarningKind::MissingUserId,
+                message: format!("No User ID contains the email address {email}"),
+            });
+        }
+    }
+
+    Ok(warns)
+}
@@ -0,0 +1,137 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Reconcile the signatures that the CA key has issued, as found in an
+//! external keyring (e.g. a keyserver dump), with the CA database.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::Result;
+use sequoia_openpgp::packet::Signature;
+use sequoia_openpgp::types::SignatureType;
+use sequoia_openpgp::{Cert, KeyHandle};
+
+use crate::pgp;
+use crate::types::{IssuedCertification, IssuedCertifications};
+use crate::Oca;
+
+/// Signatures on User IDs of `cert` that claim to be issued by one of `ca_keys`
+fn signatures_by(cert: &Cert, ca_keys: &[KeyHandle]) -> Vec<IssuedCertification> {
+    let by_ca = |s: &Signature| {
+        s.get_issuers()
+            .iter()
+            .any(|issuer| ca_keys.iter().any(|k| k.aliases(issuer)))
+    };
+
+    let mut res = vec![];
+
+    for uid in cert.userids() {
+        for s in uid.certifications().chain(uid.other_revocations()) {
+            if by_ca(s) {
+                res.push(IssuedCertification {
+                    fingerprint: cert.fingerprint().to_hex(),
+                    userid: uid.userid().clone(),
+                    signature: s.clone(),
+                });
+            }
+        }
+    }
+
+    res
+}
+
+/// Does `issued` (which is part of `cert`) verify with a key of `ca_cert`?
+fn verify(issued: &IssuedCertification, cert: &Cert, ca_cert: &Cert) -> bool {
+    let pk = cert.primary_key().key();
+    let sig = &issued.signature;
+
+    ca_cert.keys().any(|ka| {
+        let key = ka.key();
+        if sig.typ() == SignatureType::CertificationRevocation {
+            sig.verify_userid_revocation(key, pk, &issued.userid)
+                .is_ok()
+        } else {
+            sig.verify_userid_binding(key, pk, &issued.userid).is_ok()
+        }
+    })
+}
+
+/// All signatures by the CA key on User IDs that are recorded in the CA
+/// database (in the current and the previous versions of all certs),
+/// indexed by their digest
+fn recorded(oca: &Oca, ca_keys: &[KeyHandle]) -> Result<BTreeMap<String, IssuedCertification>> {
+    let mut recorded = BTreeMap::new();
+
+    for db_cert in oca.storage.certs()? {
+        let mut versions = vec![db_cert.pub_cert.clone()];
+        for version in oca.storage.cert_versions(&db_cert)? {
+            versions.push(version.pub_cert);
+        }
+
+        for armored in versions {
+            let cert = pgp::to_cert(armored.as_bytes())?;
+
+            for issued in signatures_by(&cert, ca_keys) {
+                let digest = pgp::signature_digest(&issued.userid, &issued.signature)?;
+                recorded.entry(digest).or_insert(issued);
+            }
+        }
+    }
+
+    Ok(recorded)
+}
+
+/// Find all signatures by the CA key on User IDs of the certs in `keyring`,
+/// verify them, and reconcile them with the signatures that are recorded
+/// in the CA database.
+///
+/// Signatures are matched by a digest over the User ID and the signature
+/// packet.
+pub(crate) fn extract_issued_certifications(
+    oca: &Oca,
+    keyring: &[u8],
+) -> Result<IssuedCertifications> {
+    let ca_cert = oca.ca_get_cert_pub()?;
+    let ca_keys: Vec<KeyHandle> = ca_cert.keys().map(|ka| ka.key_handle()).collect();
+
+    let recorded = recorded(oca, &ca_keys)?;
+
+    let mut res = IssuedCertifications::default();
+    let mut published = HashSet::new();
+
+    for cert in pgp::armored_keyring_to_certs(&keyring)? {
+        // Self-signatures of the CA cert are not certifications
+        if cert.fingerprint() == ca_cert.fingerprint() {
+            continue;
+        }
+
+        for issued in signatures_by(&cert, &ca_keys) {
+            if !verify(&issued, &cert, &ca_cert) {
+                res.invalid.push(issued);
+                continue;
+            }
+
+            let digest = pgp::signature_digest(&issued.userid, &issued.signature)?;
+            if !published.insert(digest.clone()) {
+                continue; // the keyring contains this signature more than once
+            }
+
+            if recorded.contains_key(&digest) {
+                res.recorded.push(issued);
+            } else {
+                res.unrecorded.push(issued);
+            }
+        }
+    }
+
+    res.not_published = recorded
+        .into_iter()
+        .filter(|(digest, _)| !published.contains(digest))
+        .map(|(_, issued)| issued)
+        .collect();
+
+    Ok(res)
+}
@@ -0,0 +1,96 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! CA config: contact and policy information that the CA publishes in the
+//! direct key signature of its cert.
+
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use chrono::{SubsecRound, Utc};
+use sequoia_openpgp::packet::signature::SignatureBuilder;
+use sequoia_openpgp::packet::Signature;
+use sequoia_openpgp::Cert;
+
+use crate::pgp;
+use crate::types::{CaConfig, CaConfigChange, CaConfigKey};
+use crate::Oca;
+
+const PREF_CONFIG_HISTORY: &str = "ca_config_history";
+
+/// The CA cert and its current direct key signature
+fn ca_cert_dks(oca: &Oca) -> Result<(Cert, Signature)> {
+    let cert = oca.ca_get_cert_pub()?;
+    let policy = oca.policy()?;
+
+    let dks = cert
+        .with_policy(&policy, None)?
+        .direct_key_signature()
+        .context("CA cert has no valid direct key signature")?
+        .clone();
+
+    Ok((cert, dks))
+}
+
+pub(crate) fn ca_config(oca: &Oca) -> Result<CaConfig> {
+    let (_, dks) = ca_cert_dks(oca)?;
+
+    Ok(pgp::ca_config(&dks))
+}
+
+/// Set (or with `value` None: unset) `key` in the CA config.
+///
+/// The direct key signature of the CA cert is re-signed with the new
+/// config, and the change is recorded in the config history.
+pub(crate) fn ca_config_set(oca: &Oca, key: CaConfigKey, value: Option<&str>) -> Result<()> {
+    let (cert, dks) = ca_cert_dks(oca)?;
+
+    let mut config = pgp::ca_config(&dks);
+
+    let old = config.get(key).map(ToString::to_string);
+    if old.as_deref() == value {
+        // nothing to do
+        return Ok(());
+    }
+
+    config.set(key, value.map(ToString::to_string));
+
+    // The new signature must be newer than the current one, to supersede it.
+    // Sequoia creates it one second after the current one (signature
+    // creation times have a resolution of one second), which must not be in
+    // the future.
+    if let Some(created) = dks.signature_creation_time() {
+        let next = created + Duration::from_secs(1);
+        if let Ok(wait) = next.duration_since(SystemTime::now()) {
+            std::thread::sleep(wait);
+        }
+    }
+
+    let sb = pgp::set_ca_config(SignatureBuilder::from(dks.clone()), &config)?;
+
+    let sig = oca.secret().sign_direct_key(sb)?;
+    let cert = cert.insert_packets(sig)?;
+
+    oca.storage.ca_cert_merge(&cert)?;
+
+    let mut history = ca_config_history(oca)?;
+    history.push(CaConfigChange {
+        key,
+        old,
+        new: value.map(ToString::to_string),
+        changed: Utc::now().trunc_subsecs(0),
+    });
+
+    let json = serde_json::to_string(&history)?;
+    oca.storage.pref_set(PREF_CONFIG_HISTORY, &json)
+}
+
+pub(crate) fn ca_config_history(oca: &Oca) -> Result<Vec<CaConfigChange>> {
+    match oca.storage.pref(PREF_CONFIG_HISTORY)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(vec![]),
+    }
+}
@@ -0,0 +1,203 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Certification transparency log: an append-only, hash-chained record of
+//! the certifications and revocations that the CA issues.
+//!
+//! Each entry contains one signature by the CA, and the hash of the
+//! previous entry. The hash of an entry is the SHA-256 hash (as lowercase
+//! hex) of its fields, each followed by a newline: sequence number,
+//! creation time (RFC 3339, in UTC, with seconds precision), kind,
+//! fingerprint, base64 encoded signature, hash of the previous entry.
+//!
+//! Changing, reordering or removing an entry breaks the chain, so an
+//! exported copy of the log is a tamper-evident record of the activity of
+//! the CA, independent of the (mutable) CA database.
+//!
+//! On a split-mode front instance, signatures are recorded when the
+//! results from the back instance are imported.
+
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine};
+use chrono::{DateTime, SecondsFormat, Timelike, Utc};
+use sequoia_openpgp::packet::Signature;
+use sequoia_openpgp::parse::Parse;
+use sequoia_openpgp::serialize::Marshal;
+use sequoia_openpgp::Cert;
+
+use crate::db::models;
+use crate::storage::CaStorageRW;
+use crate::trust_package::sha256_hex;
+use crate::types::{CtLogEntry, CtLogKind, CtLogReport};
+use crate::Oca;
+
+/// `prev_hash` of the first entry
+pub(crate) const GENESIS_HASH: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Creation time for a new entry (truncated to seconds precision)
+pub(crate) fn now() -> DateTime<Utc> {
+    let now = Utc::now();
+    now.with_nanosecond(0).unwrap_or(now)
+}
+
+/// The hash of an entry with these fields
+pub(crate) fn entry_hash(
+    seq: u64,
+    created: &DateTime<Utc>,
+    kind: &str,
+    fingerprint: &str,
+    signature: &str,
+    prev_hash: &str,
+) -> String {
+    let data = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}\n",
+        seq,
+        created.to_rfc3339_opts(SecondsFormat::Secs, true),
+        kind,
+        fingerprint,
+        signature,
+        prev_hash
+    );
+
+    sha256_hex(data.as_bytes())
+}
+
+/// Append `sigs` (made by the CA on the cert `fingerprint`) to the log
+pub(crate) fn record(
+    storage: &dyn CaStorageRW,
+    kind: CtLogKind,
+    fingerprint: &str,
+    sigs: &[Signature],
+) -> Result<()> {
+    for sig in sigs {
+        let mut v: Vec<u8> = vec![];
+        sig.serialize(&mut v)?;
+
+        storage.ct_log_append(
+            kind.name(),
+            fingerprint,
+            &general_purpose::STANDARD.encode(v),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Append the signatures by the CA on `after` that are not on `before` to
+/// the log (e.g. a trust signature that was added to a remote CA cert)
+pub(crate) fn record_new(
+    storage: &dyn CaStorageRW,
+    kind: CtLogKind,
+    before: Option<&Cert>,
+    after: &Cert,
+) -> Result<()> {
+    let ca = storage.ca_get_cert_pub()?;
+
+    let known: Vec<&Signature> = before
+        .map(|c| c.userids().flat_map(|u| u.signatures()).collect())
+        .unwrap_or_default();
+
+    let new: Vec<Signature> = after
+        .userids()
+        .flat_map(|u| u.signatures())
+        .filter(|s| s.issuer_fingerprints().any(|fp| *fp == ca.fingerprint()) && !known.contains(s))
+        .cloned()
+        .collect();
+
+    record(storage, kind, &after.fingerprint().to_hex(), &new)
+}
+
+fn to_entry(e: models::CtLogEntry) -> Result<CtLogEntry> {
+    Ok(CtLogEntry {
+        seq: e.id as u64,
+        created: DateTime::from_naive_utc_and_offset(e.created, Utc),
+        kind: e.kind.parse()?,
+        fingerprint: e.fingerprint,
+        signature: e.signature,
+        prev_hash: e.prev_hash,
+        hash: e.hash,
+    })
+}
+
+/// All entries of the log, in order
+pub(crate) fn entries(oca: &Oca) -> Result<Vec<CtLogEntry>> {
+    oca.storage.ct_log()?.into_iter().map(to_entry).collect()
+}
+
+/// The log, as JSON lines
+pub(crate) fn export(oca: &Oca) -> Result<String> {
+    let mut res = String::new();
+    for entry in entries(oca)? {
+        res.push_str(&serde_json::to_string(&entry)?);
+        res.push('\n');
+    }
+
+    Ok(res)
+}
+
+/// Check that `entries` form an unbroken hash chain, starting at the first
+/// entry of the log, and that each entry contains a signature
+pub(crate) fn verify(entries: &[CtLogEntry]) -> Result<CtLogReport> {
+    let mut prev = GENESIS_HASH.to_string();
+
+    for (i, e) in entries.iter().enumerate() {
+        let seq = i as u64 + 1;
+        if e.seq != seq {
+            return Err(anyhow::anyhow!(
+                "Entry {}: unexpected sequence number {}",
+                seq,
+                e.seq
+            ));
+        }
+        if e.prev_hash != prev {
+            return Err(anyhow::anyhow!(
+                "Entry {}: doesn't link to the previous entry",
+                seq
+            ));
+        }
+
+        let hash = entry_hash(
+            e.seq,
+            &e.created,
+            e.kind.name(),
+            &e.fingerprint,
+            &e.signature,
+            &e.prev_hash,
+        );
+        if e.hash != hash {
+            return Err(anyhow::anyhow!("Entry {}: hash mismatch", seq));
+        }
+
+        let sig = general_purpose::STANDARD
+            .decode(&e.signature)
+            .map_err(|err| anyhow::anyhow!("Entry {}: bad signature encoding: {}", seq, err))?;
+        Signature::from_bytes(&sig)
+            .map_err(|err| anyhow::anyhow!("Entry {}: bad signature: {}", seq, err))?;
+
+        prev = hash;
+    }
+
+    Ok(CtLogReport {
+        entries: entries.len() as u64,
+        head: prev,
+    })
+}
+
+/// Verify an exported log (see [export])
+pub(crate) fn verify_export(jsonl: &[u8]) -> Result<CtLogReport> {
+    let entries = std::str::from_utf8(jsonl)?
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| anyhow::anyhow!("Line {}: bad log entry: {}", i + 1, e))
+        })
+        .collect::<Result<Vec<CtLogEntry>>>()?;
+
+    verify(&entries)
+}
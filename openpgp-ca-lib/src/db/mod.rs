@@ -7,6 +7,7 @@
 //! OpenPGP CA database access and model.
 
 use anyhow::{Context, Result};
+use diesel::connection::TransactionManager;
 use diesel::prelude::*;
 use diesel::result::Error;
 
@@ -18,6 +19,42 @@ use schema::*;
 
 use crate::pgp;
 
+/// Name of the pref that configures how many previous versions are retained per cert
+pub(crate) const PREF_CERT_VERSIONS_KEEP: &str = "cert_versions_keep";
+
+/// Name of the pref that selects the (sub)key that a softkey CA certifies
+/// with (empty or unset: all certification capable keys)
+pub(crate) const PREF_CERTIFICATION_KEY: &str = "certification_key";
+
+/// Kind of queue entries with requests of a split mode front instance
+pub(crate) const QUEUE_SPLIT: &str = "split";
+
+/// Kind of queue entries with changes that wait for approval by an admin
+pub(crate) const QUEUE_PROPOSAL: &str = "proposal";
+
+/// Default number of previous versions that are retained per cert
+pub(crate) const CERT_VERSIONS_KEEP_DEFAULT: u32 = 10;
+
+/// How long (in milliseconds) to wait for a lock on the database that is
+/// held by another connection (e.g. the CLI while restd is running), before
+/// failing with "database is locked"
+const BUSY_TIMEOUT_MS: u32 = 10_000;
+
+/// Escape the wildcard characters of LIKE patterns in `text` (for use with
+/// the escape character '\')
+fn like_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// The database url for an in-memory database.
+///
+/// Each connection to this url opens a separate, empty database. So an
+/// in-memory database only lives as long as its (single) [OcaDb], which
+/// must be shared between all users (as `Rc<OcaDb>`).
+pub(crate) const IN_MEMORY: &str = ":memory:";
+
 /// Database access layer
 pub(crate) struct OcaDb {
     url: String,
@@ -34,6 +71,11 @@ impl OcaDb {
             .execute(&conn)
             .context("Couldn't set 'PRAGMA foreign_keys=1;'")?;
 
+        // Wait for concurrent writers, instead of failing immediately
+        diesel::sql_query(format!("PRAGMA busy_timeout={BUSY_TIMEOUT_MS};"))
+            .execute(&conn)
+            .context("Couldn't set 'PRAGMA busy_timeout'")?;
+
         Ok(OcaDb {
             conn,
             url: db_url.to_string(),
@@ -44,12 +86,31 @@ impl OcaDb {
         &self.url
     }
 
+    /// Is this an in-memory database (that can't be reopened by its url)?
+    pub(crate) fn is_in_memory(&self) -> bool {
+        self.url == IN_MEMORY
+    }
+
+    /// Run `f` in a transaction.
+    ///
+    /// The outermost transaction is started with "BEGIN IMMEDIATE", so it
+    /// acquires the write lock on the database up front. This serializes
+    /// read-modify-write cycles between multiple processes that use the same
+    /// database (a deferred transaction that has read data could otherwise
+    /// not upgrade to a write lock, once another process has written).
+    ///
+    /// Nested transactions are run as savepoints of the outer transaction.
     pub(crate) fn transaction<T, E, F>(&self, f: F) -> Result<T, E>
     where
         F: FnOnce() -> Result<T, E>,
         E: From<Error>,
     {
-        self.conn.transaction(f)
+        let tm = self.conn.transaction_manager();
+        if TransactionManager::<SqliteConnection>::get_transaction_depth(tm) == 0 {
+            self.conn.immediate_transaction(f)
+        } else {
+            self.conn.transaction(f)
+        }
     }
 
     /// Runs the "VACUUM" command on the database, which:
@@ -67,6 +128,61 @@ impl OcaDb {
         Ok(())
     }
 
+    /// Write a vacuumed copy of the database into the new file `path`
+    ///
+    /// <https://www.sqlite.org/lang_vacuum.html#vacuuminto>
+    pub(crate) fn vacuum_into(&self, path: &str) -> Result<()> {
+        diesel::sql_query("VACUUM INTO ?;")
+            .bind::<diesel::sql_types::Text, _>(path)
+            .execute(&self.conn)
+            .context("Error while running 'VACUUM INTO'")?;
+
+        Ok(())
+    }
+
+    /// Checkpoint the write-ahead log (if the database is in WAL mode): write
+    /// its contents back to the database file, and truncate the log.
+    ///
+    /// <https://www.sqlite.org/pragma.html#pragma_wal_checkpoint>
+    pub(crate) fn checkpoint(&self) -> Result<()> {
+        diesel::sql_query("PRAGMA wal_checkpoint(TRUNCATE);")
+            .execute(&self.conn)
+            .context("Error while running 'PRAGMA wal_checkpoint(TRUNCATE);'")?;
+
+        Ok(())
+    }
+
+    /// Set the size of the write-ahead log (in pages) after which SQLite
+    /// runs a checkpoint automatically (0 disables automatic checkpoints).
+    ///
+    /// <https://www.sqlite.org/pragma.html#pragma_wal_autocheckpoint>
+    pub(crate) fn set_wal_autocheckpoint(&self, pages: u32) -> Result<()> {
+        diesel::sql_query(format!("PRAGMA wal_autocheckpoint={pages};"))
+            .execute(&self.conn)
+            .context("Error while setting 'PRAGMA wal_autocheckpoint'")?;
+
+        Ok(())
+    }
+
+    /// Does this database use the legacy schema of early OpenPGP CA versions
+    /// (with user certs in a "usercerts" table)?
+    pub(crate) fn has_legacy_schema(&self) -> Result<bool> {
+        #[derive(QueryableByName)]
+        struct Table {
+            #[sql_type = "diesel::sql_types::Text"]
+            #[allow(dead_code)]
+            name: String,
+        }
+
+        let tables: Vec<Table> = diesel::sql_query(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'usercerts'",
+        )
+        .load(&self.conn)
+        .context("Error while checking for legacy database schema")?;
+
+        Ok(!tables.is_empty())
+    }
+
     // --- building block functions ---
 
     fn user_insert(&self, user: NewUser) -> Result<User> {
@@ -181,6 +297,69 @@ impl OcaDb {
         Ok(e[0].clone())
     }
 
+    fn cert_version_insert(&self, version: NewCertVersion) -> Result<()> {
+        let inserted_count = diesel::insert_into(cert_versions::table)
+            .values(&version)
+            .execute(&self.conn)
+            .context("Error saving cert version")?;
+
+        if inserted_count != 1 {
+            return Err(anyhow::anyhow!(
+                "insert_cert_version: insert should return count '1'"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Delete all but the `keep` most recent versions of the cert with id `cert_id`.
+    ///
+    /// Returns the time at which the most recent of the deleted versions was
+    /// superseded (None, if no versions were deleted).
+    fn cert_versions_prune(
+        &self,
+        cert_id: i32,
+        keep: u32,
+    ) -> Result<Option<chrono::NaiveDateTime>> {
+        let keep_ids = cert_versions::table
+            .filter(cert_versions::cert_id.eq(cert_id))
+            .order(cert_versions::id.desc())
+            .limit(keep as i64)
+            .select(cert_versions::id)
+            .load::<i32>(&self.conn)?;
+
+        let pruned: Vec<(i32, chrono::NaiveDateTime)> = cert_versions::table
+            .filter(cert_versions::cert_id.eq(cert_id))
+            .filter(cert_versions::id.ne_all(keep_ids))
+            .select((cert_versions::id, cert_versions::created))
+            .load(&self.conn)
+            .context("Error loading cert versions")?;
+
+        let ids: Vec<i32> = pruned.iter().map(|(id, _)| *id).collect();
+        diesel::delete(cert_versions::table.filter(cert_versions::id.eq_any(ids)))
+            .execute(&self.conn)
+            .context("Error pruning cert versions")?;
+
+        Ok(pruned.into_iter().map(|(_, created)| created).max())
+    }
+
+    /// Record that the version history of the cert `cert_id` is incomplete
+    /// before `time`
+    fn cert_history_truncate(&self, cert_id: i32, time: chrono::NaiveDateTime) -> Result<()> {
+        if let Some(mut cert) = self.cert_by_id(cert_id)? {
+            if !cert.history_since.is_some_and(|since| since >= time) {
+                cert.history_since = Some(time);
+
+                diesel::update(&cert)
+                    .set(&cert)
+                    .execute(&self.conn)
+                    .context("Error updating Cert")?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn queue_by_id(&self, id: i32) -> Result<Option<Queue>> {
         let mut db: Vec<Queue> = queue::table
             .filter(queue::id.eq(id))
@@ -194,7 +373,7 @@ impl OcaDb {
         }
     }
 
-    pub(crate) fn queue_insert(&self, q: NewQueue) -> Result<()> {
+    pub(crate) fn queue_insert(&self, q: NewQueue) -> Result<Queue> {
         let inserted_count = diesel::insert_into(queue::table)
             .values(&q)
             .execute(&self.conn)
@@ -206,18 +385,78 @@ impl OcaDb {
             ));
         }
 
-        Ok(())
+        // retrieve our new row, including the generated id
+        queue::table
+            .order(queue::id.desc())
+            .first::<Queue>(&self.conn)
+            .context("queue_insert: unexpected insert failure")
     }
 
-    // get all queue entries that aren't marked as "done"
+    // get all split mode queue entries that aren't marked as "done"
     pub(crate) fn queue_not_done(&self) -> Result<Vec<Queue>> {
         queue::table
             .filter(queue::done.eq(false))
+            .filter(queue::kind.eq(QUEUE_SPLIT))
+            .order(queue::id)
+            .load::<Queue>(&self.conn)
+            .context("Error loading queue entries")
+    }
+
+    /// All queue entries of `kind`
+    pub(crate) fn queue_by_kind(&self, kind: &str) -> Result<Vec<Queue>> {
+        queue::table
+            .filter(queue::kind.eq(kind))
             .order(queue::id)
             .load::<Queue>(&self.conn)
             .context("Error loading queue entries")
     }
 
+    /// Ids and creation times of all queue entries that are marked as "done"
+    pub(crate) fn queue_done_created(&self) -> Result<Vec<(i32, chrono::NaiveDateTime)>> {
+        queue::table
+            .filter(queue::done.eq(true))
+            .select((queue::id, queue::created))
+            .order(queue::id)
+            .load(&self.conn)
+            .context("Error loading queue entries")
+    }
+
+    pub(crate) fn queue_delete(&self, ids: &[i32]) -> Result<usize> {
+        diesel::delete(queue::table.filter(queue::id.eq_any(ids)))
+            .execute(&self.conn)
+            .context("Error deleting queue entries")
+    }
+
+    /// Ids, cert ids and creation times of all cert versions
+    pub(crate) fn cert_versions_created(&self) -> Result<Vec<(i32, i32, chrono::NaiveDateTime)>> {
+        cert_versions::table
+            .select((
+                cert_versions::id,
+                cert_versions::cert_id,
+                cert_versions::created,
+            ))
+            .order(cert_versions::id)
+            .load(&self.conn)
+            .context("Error loading cert versions")
+    }
+
+    pub(crate) fn cert_versions_delete(&self, ids: &[i32]) -> Result<usize> {
+        let deleted: Vec<(i32, chrono::NaiveDateTime)> = cert_versions::table
+            .filter(cert_versions::id.eq_any(ids))
+            .select((cert_versions::cert_id, cert_versions::created))
+            .load(&self.conn)
+            .context("Error loading cert versions")?;
+
+        // The histories of the affected certs are incomplete from now on
+        for (cert_id, created) in deleted {
+            self.cert_history_truncate(cert_id, created)?;
+        }
+
+        diesel::delete(cert_versions::table.filter(cert_versions::id.eq_any(ids)))
+            .execute(&self.conn)
+            .context("Error deleting cert versions")
+    }
+
     pub(crate) fn queue_update(&self, queue: &Queue) -> Result<()> {
         diesel::update(queue)
             .set(queue)
@@ -336,6 +575,26 @@ impl OcaDb {
         Ok(())
     }
 
+    /// Replace the active Cacert entry with a new CA cert (after the CA key has been replaced).
+    ///
+    /// The previous entry is kept as an inactive Cacert, the new entry uses the same
+    /// backend configuration.
+    pub(crate) fn cacert_replace(&self, priv_cert: String, fingerprint: &str) -> Result<()> {
+        let (ca, mut cacert) = self.get_ca()?;
+
+        cacert.active = false;
+        self.cacert_update(&cacert)?;
+
+        let new_cacert = NewCacert {
+            active: true,
+            fingerprint,
+            priv_cert,
+            backend: cacert.backend.as_deref(),
+            ca_id: ca.id,
+        };
+        self.cacert_insert(&new_cacert)
+    }
+
     /// Add trust-signature(s) from a user Cert to the CA's Cert.
     ///
     /// This receives the CA's public key (optionally armored), finds any trust-signatures on
@@ -358,6 +617,23 @@ impl OcaDb {
             .context("Update of CA Cert in DB failed")
     }
 
+    /// Merge public packets from `cert` into the stored CA cert
+    /// (the private key material of the stored cert is kept).
+    pub(crate) fn ca_cert_merge(&self, cert: &sequoia_openpgp::Cert) -> Result<()> {
+        let (_, mut ca_cert) = self
+            .get_ca()
+            .context("Failed to load CA cert from database")?;
+        let ca = pgp::to_cert(ca_cert.priv_cert.as_bytes())?;
+
+        let merged = ca.merge_public(cert.clone())?;
+
+        ca_cert.priv_cert =
+            pgp::cert_to_armored_private_key(&merged).context("Failed to re-armor CA Cert")?;
+
+        self.cacert_update(&ca_cert)
+            .context("Update of CA Cert in DB failed")
+    }
+
     pub(crate) fn users_sorted_by_name(&self) -> Result<Vec<User>> {
         users::table
             .order((users::name, users::id))
@@ -365,6 +641,25 @@ impl OcaDb {
             .context("Error loading users")
     }
 
+    pub(crate) fn user_by_id(&self, id: i32) -> Result<Option<User>> {
+        let db: Vec<User> = users::table
+            .filter(users::id.eq(id))
+            .load::<User>(&self.conn)
+            .context("Error loading User by id")?;
+
+        Ok(db.first().cloned())
+    }
+
+    /// Add a user without certs
+    pub(crate) fn user_new(&self, name: &str) -> Result<User> {
+        let (ca, _) = self.get_ca().context("Couldn't find CA")?;
+
+        self.user_insert(NewUser {
+            name: Some(name),
+            ca_id: ca.id,
+        })
+    }
+
     pub(crate) fn user_by_cert(&self, cert: &Cert) -> Result<Option<User>> {
         match cert.user_id {
             None => Ok(None),
@@ -422,7 +717,6 @@ impl OcaDb {
         Ok(user)
     }
 
-    #[allow(dead_code)]
     pub(crate) fn user_update(&self, user: &User) -> Result<()> {
         diesel::update(user)
             .set(user)
@@ -444,19 +738,136 @@ impl OcaDb {
             delisted: false,
             inactive: false,
             user_id,
+            created: Some(chrono::Utc::now().naive_utc()),
         };
         self.cert_insert(cert)
     }
 
-    pub fn cert_update(&self, cert: &Cert) -> Result<()> {
-        diesel::update(cert)
-            .set(cert)
+    /// Store a modified version of `cert`.
+    ///
+    /// If the armored cert changes, the previous version is retained in the
+    /// `cert_versions` table, labeled with `origin` (subject to the configured
+    /// retention limit).
+    pub fn cert_update(&self, cert: &Cert, origin: &str) -> Result<()> {
+        let mut cert = cert.clone();
+
+        if let Some(old) = self.cert_by_id(cert.id)? {
+            // The history fields are maintained here, not by the callers
+            cert.created = old.created;
+            cert.history_since = old.history_since;
+
+            if old.pub_cert != cert.pub_cert {
+                let now = chrono::Utc::now().naive_utc();
+
+                let keep = self.cert_versions_keep()?;
+                if keep > 0 {
+                    self.cert_version_insert(NewCertVersion {
+                        pub_cert: &old.pub_cert,
+                        created: now,
+                        origin,
+                        cert_id: cert.id,
+                    })?;
+                }
+
+                let pruned = self.cert_versions_prune(cert.id, keep)?;
+
+                // Without retention, the superseded version is lost right away
+                let lost = if keep > 0 { pruned } else { Some(now) };
+                if lost > cert.history_since {
+                    cert.history_since = lost;
+                }
+
+                // Cached verification results only apply to the old version
+                self.verified_signatures_delete(cert.id)?;
+            }
+        }
+
+        diesel::update(&cert)
+            .set(&cert)
             .execute(&self.conn)
             .context("Error updating Cert")?;
 
         Ok(())
     }
 
+    /// Delete `cert`, along with all rows that reference it.
+    ///
+    /// If its user is left without certs (and isn't referenced by the
+    /// recorded moves of other certs), the user is deleted as well.
+    ///
+    /// Certs that are used by a bridge can't be purged.
+    pub(crate) fn cert_purge(&self, cert: &Cert) -> Result<()> {
+        let bridged: i64 = bridges::table
+            .filter(bridges::cert_id.eq(cert.id))
+            .count()
+            .get_result(&self.conn)
+            .context("Error loading bridges")?;
+        if bridged > 0 {
+            return Err(anyhow::anyhow!(
+                "Cert {} is used by a bridge, can't purge it",
+                cert.fingerprint
+            ));
+        }
+
+        self.verified_signatures_delete(cert.id)?;
+
+        diesel::delete(cert_versions::table.filter(cert_versions::cert_id.eq(cert.id)))
+            .execute(&self.conn)
+            .context("Error deleting cert versions")?;
+        self.cert_quarantine_delete(cert.id)?;
+        diesel::delete(cert_reassignments::table.filter(cert_reassignments::cert_id.eq(cert.id)))
+            .execute(&self.conn)
+            .context("Error deleting cert reassignments")?;
+        diesel::delete(key_replacements::table.filter(key_replacements::cert_id.eq(cert.id)))
+            .execute(&self.conn)
+            .context("Error deleting key replacements")?;
+        diesel::delete(revocations::table.filter(revocations::cert_id.eq(cert.id)))
+            .execute(&self.conn)
+            .context("Error deleting revocations")?;
+        diesel::delete(certs_emails::table.filter(certs_emails::cert_id.eq(cert.id)))
+            .execute(&self.conn)
+            .context("Error deleting emails")?;
+        diesel::delete(tags::table.filter(tags::cert_id.eq(cert.id)))
+            .execute(&self.conn)
+            .context("Error deleting tags")?;
+        diesel::delete(sync_state::table.filter(sync_state::cert_id.eq(cert.id)))
+            .execute(&self.conn)
+            .context("Error deleting sync state")?;
+        diesel::delete(keyserver_packets::table.filter(keyserver_packets::cert_id.eq(cert.id)))
+            .execute(&self.conn)
+            .context("Error deleting keyserver packets")?;
+
+        diesel::delete(certs::table.filter(certs::id.eq(cert.id)))
+            .execute(&self.conn)
+            .context("Error deleting Cert")?;
+
+        if let Some(user) = self.user_by_cert(cert)? {
+            let referenced: i64 = cert_reassignments::table
+                .filter(
+                    cert_reassignments::from_user_id
+                        .eq(user.id)
+                        .or(cert_reassignments::to_user_id.eq(user.id)),
+                )
+                .count()
+                .get_result(&self.conn)
+                .context("Error loading cert reassignments")?;
+
+            if referenced == 0 && self.certs_by_user(&user)?.is_empty() {
+                diesel::delete(tags::table.filter(tags::user_id.eq(user.id)))
+                    .execute(&self.conn)
+                    .context("Error deleting tags")?;
+                diesel::delete(group_members::table.filter(group_members::user_id.eq(user.id)))
+                    .execute(&self.conn)
+                    .context("Error deleting group members")?;
+                diesel::delete(users::table.filter(users::id.eq(user.id)))
+                    .execute(&self.conn)
+                    .context("Error deleting User")?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn cert_by_id(&self, id: i32) -> Result<Option<Cert>> {
         let db: Vec<Cert> = certs::table
             .filter(certs::id.eq(id))
@@ -504,6 +915,296 @@ impl OcaDb {
             .context("Error loading certs")
     }
 
+    /// Certs where the fingerprint, an email address, the user name, or
+    /// the notes of the cert or its user contain `text` (matching is
+    /// case-insensitive for ASCII characters), ordered by certs::id
+    pub(crate) fn certs_search(&self, text: &str) -> Result<Vec<Cert>> {
+        let pattern = format!("%{}%", like_escape(text));
+
+        // Fingerprints are stored without spaces
+        let fp: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        let fp_pattern = format!("%{}%", like_escape(&fp));
+
+        let mut ids: Vec<i32> = certs::table
+            .filter(certs::fingerprint.like(&fp_pattern).escape('\\'))
+            .select(certs::id)
+            .load(&self.conn)?;
+
+        ids.extend(
+            certs::table
+                .filter(certs::notes.like(&pattern).escape('\\'))
+                .select(certs::id)
+                .load::<i32>(&self.conn)?,
+        );
+
+        ids.extend(
+            certs_emails::table
+                .filter(certs_emails::addr.like(&pattern).escape('\\'))
+                .select(certs_emails::cert_id)
+                .load::<i32>(&self.conn)?,
+        );
+
+        ids.extend(
+            tags::table
+                .filter(
+                    tags::name
+                        .like(&pattern)
+                        .escape('\\')
+                        .or(tags::value.like(&pattern).escape('\\')),
+                )
+                .select(tags::cert_id)
+                .load::<Option<i32>>(&self.conn)?
+                .into_iter()
+                .flatten(),
+        );
+
+        let by_tag = tags::table
+            .filter(
+                tags::name
+                    .like(&pattern)
+                    .escape('\\')
+                    .or(tags::value.like(&pattern).escape('\\')),
+            )
+            .select(tags::user_id);
+        let by_name = users::table
+            .filter(users::name.like(&pattern).escape('\\'))
+            .select(users::id.nullable());
+        let by_notes = users::table
+            .filter(users::notes.like(&pattern).escape('\\'))
+            .select(users::id.nullable());
+
+        ids.extend(
+            certs::table
+                .filter(certs::user_id.eq_any(by_name))
+                .or_filter(certs::user_id.eq_any(by_notes))
+                .or_filter(certs::user_id.eq_any(by_tag))
+                .select(certs::id)
+                .load::<i32>(&self.conn)?,
+        );
+
+        certs::table
+            .filter(certs::id.eq_any(ids))
+            .order(certs::id)
+            .load::<Cert>(&self.conn)
+            .context("Error searching certs")
+    }
+
+    /// All previous versions of `cert`, ordered from oldest to newest
+    pub(crate) fn cert_versions_by_cert(&self, cert: &Cert) -> Result<Vec<CertVersion>> {
+        Ok(CertVersion::belonging_to(cert)
+            .order(cert_versions::id)
+            .load::<CertVersion>(&self.conn)?)
+    }
+
+    pub(crate) fn cert_version_by_id(&self, id: i32) -> Result<Option<CertVersion>> {
+        let db: Vec<CertVersion> = cert_versions::table
+            .filter(cert_versions::id.eq(id))
+            .load::<CertVersion>(&self.conn)
+            .context("Error loading cert version by id")?;
+
+        Ok(db.first().cloned())
+    }
+
+    /// Number of previous versions that are retained per cert
+    pub(crate) fn cert_versions_keep(&self) -> Result<u32> {
+        match self.pref(PREF_CERT_VERSIONS_KEEP)? {
+            Some(keep) => Ok(keep.parse()?),
+            None => Ok(CERT_VERSIONS_KEEP_DEFAULT),
+        }
+    }
+
+    pub(crate) fn verified_signatures_by_cert(
+        &self,
+        cert: &Cert,
+    ) -> Result<Vec<VerifiedSignature>> {
+        Ok(VerifiedSignature::belonging_to(cert).load::<VerifiedSignature>(&self.conn)?)
+    }
+
+    /// Add cached verification results (results that are already cached,
+    /// e.g. by a concurrent certification check, are skipped)
+    pub(crate) fn verified_signatures_insert(
+        &self,
+        verified: &[NewVerifiedSignature],
+    ) -> Result<()> {
+        diesel::insert_or_ignore_into(verified_signatures::table)
+            .values(verified)
+            .execute(&self.conn)
+            .context("Error saving verified signatures")?;
+
+        Ok(())
+    }
+
+    fn verified_signatures_delete(&self, cert_id: i32) -> Result<()> {
+        diesel::delete(verified_signatures::table.filter(verified_signatures::cert_id.eq(cert_id)))
+            .execute(&self.conn)
+            .context("Error deleting verified signatures")?;
+
+        Ok(())
+    }
+
+    pub(crate) fn cert_reassignments_by_cert(&self, cert: &Cert) -> Result<Vec<CertReassignment>> {
+        Ok(CertReassignment::belonging_to(cert)
+            .order(cert_reassignments::id)
+            .load::<CertReassignment>(&self.conn)?)
+    }
+
+    pub(crate) fn cert_reassignment_insert(&self, reassignment: NewCertReassignment) -> Result<()> {
+        let inserted_count = diesel::insert_into(cert_reassignments::table)
+            .values(&reassignment)
+            .execute(&self.conn)
+            .context("Error saving cert reassignment")?;
+
+        if inserted_count != 1 {
+            return Err(anyhow::anyhow!(
+                "insert_cert_reassignment: insert should return count '1'"
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn cert_quarantine_all(&self) -> Result<Vec<CertQuarantine>> {
+        cert_quarantine::table
+            .order(cert_quarantine::id)
+            .load::<CertQuarantine>(&self.conn)
+            .context("Error loading quarantined certs")
+    }
+
+    pub(crate) fn cert_quarantine_insert(&self, quarantine: NewCertQuarantine) -> Result<()> {
+        let inserted_count = diesel::insert_into(cert_quarantine::table)
+            .values(&quarantine)
+            .execute(&self.conn)
+            .context("Error saving cert quarantine")?;
+
+        if inserted_count != 1 {
+            return Err(anyhow::anyhow!(
+                "cert_quarantine_insert: insert should return count '1'"
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn cert_quarantine_delete(&self, cert_id: i32) -> Result<usize> {
+        diesel::delete(cert_quarantine::table.filter(cert_quarantine::cert_id.eq(cert_id)))
+            .execute(&self.conn)
+            .context("Error deleting cert quarantine")
+    }
+
+    pub(crate) fn key_replacements_all(&self) -> Result<Vec<KeyReplacement>> {
+        key_replacements::table
+            .order(key_replacements::id)
+            .load::<KeyReplacement>(&self.conn)
+            .context("Error loading key replacements")
+    }
+
+    pub(crate) fn key_replacement_by_id(&self, id: i32) -> Result<Option<KeyReplacement>> {
+        let db: Vec<KeyReplacement> = key_replacements::table
+            .filter(key_replacements::id.eq(id))
+            .load::<KeyReplacement>(&self.conn)
+            .context("Error loading key replacement by id")?;
+
+        Ok(db.first().cloned())
+    }
+
+    pub(crate) fn key_replacement_insert(
+        &self,
+        replacement: NewKeyReplacement,
+    ) -> Result<KeyReplacement> {
+        let inserted_count = diesel::insert_into(key_replacements::table)
+            .values(&replacement)
+            .execute(&self.conn)
+            .context("Error saving key replacement")?;
+
+        if inserted_count != 1 {
+            return Err(anyhow::anyhow!(
+                "key_replacement_insert: insert should return count '1'"
+            ));
+        }
+
+        // retrieve our new row, including the generated id
+        key_replacements::table
+            .order(key_replacements::id.desc())
+            .first::<KeyReplacement>(&self.conn)
+            .context("key_replacement_insert: unexpected insert failure")
+    }
+
+    pub(crate) fn key_replacement_update(&self, replacement: &KeyReplacement) -> Result<()> {
+        diesel::update(replacement)
+            .set(replacement)
+            .execute(&self.conn)
+            .context("Error updating key replacement")?;
+
+        Ok(())
+    }
+
+    pub(crate) fn blocklist_all(&self) -> Result<Vec<BlocklistEntry>> {
+        blocklist::table
+            .order(blocklist::id)
+            .load::<BlocklistEntry>(&self.conn)
+            .context("Error loading blocklist")
+    }
+
+    pub(crate) fn blocklist_insert(&self, entry: NewBlocklistEntry) -> Result<BlocklistEntry> {
+        let inserted_count = diesel::insert_into(blocklist::table)
+            .values(&entry)
+            .execute(&self.conn)
+            .context("Error saving blocklist entry")?;
+
+        if inserted_count != 1 {
+            return Err(anyhow::anyhow!(
+                "blocklist_insert: insert should return count '1'"
+            ));
+        }
+
+        // retrieve our new row, including the generated id
+        blocklist::table
+            .order(blocklist::id.desc())
+            .first::<BlocklistEntry>(&self.conn)
+            .context("blocklist_insert: unexpected insert failure")
+    }
+
+    pub(crate) fn blocklist_update(&self, entry: &BlocklistEntry) -> Result<()> {
+        diesel::update(entry)
+            .set(entry)
+            .execute(&self.conn)
+            .context("Error updating blocklist entry")?;
+
+        Ok(())
+    }
+
+    pub(crate) fn ct_log_all(&self) -> Result<Vec<CtLogEntry>> {
+        ct_log::table
+            .order(ct_log::id)
+            .load::<CtLogEntry>(&self.conn)
+            .context("Error loading ct log")
+    }
+
+    pub(crate) fn ct_log_last(&self) -> Result<Option<CtLogEntry>> {
+        Ok(ct_log::table
+            .order(ct_log::id.desc())
+            .first::<CtLogEntry>(&self.conn)
+            .optional()?)
+    }
+
+    pub(crate) fn ct_log_insert(&self, entry: NewCtLogEntry) -> Result<CtLogEntry> {
+        let inserted_count = diesel::insert_into(ct_log::table)
+            .values(&entry)
+            .execute(&self.conn)
+            .context("Error saving ct log entry")?;
+
+        if inserted_count != 1 {
+            return Err(anyhow::anyhow!(
+                "ct_log_insert: insert should return count '1'"
+            ));
+        }
+
+        ct_log::table
+            .find(entry.id)
+            .first::<CtLogEntry>(&self.conn)
+            .context("ct_log_insert: unexpected insert failure")
+    }
+
     pub(crate) fn revocations_by_cert(&self, cert: &Cert) -> Result<Vec<Revocation>> {
         Ok(Revocation::belonging_to(cert).load::<Revocation>(&self.conn)?)
     }
@@ -519,12 +1220,6 @@ impl OcaDb {
         })
     }
 
-    /// Check if this exact revocation (bitwise) already exists in the DB
-    pub(crate) fn revocation_exists(&self, revocation: &[u8]) -> Result<bool> {
-        let hash = &pgp::revocation_to_hash(revocation)?;
-        Ok(self.revocation_by_hash(hash)?.is_some())
-    }
-
     pub(crate) fn revocation_by_hash(&self, hash: &str) -> Result<Option<Revocation>> {
         let db: Vec<Revocation> = revocations::table
             .filter(revocations::hash.eq(hash))
@@ -539,6 +1234,21 @@ impl OcaDb {
         Ok(db.first().cloned())
     }
 
+    pub(crate) fn revocations(&self) -> Result<Vec<Revocation>> {
+        revocations::table
+            .order(revocations::id)
+            .load::<Revocation>(&self.conn)
+            .context("Error loading revocations")
+    }
+
+    pub(crate) fn revocation_delete(&self, id: i32) -> Result<()> {
+        diesel::delete(revocations::table.filter(revocations::id.eq(id)))
+            .execute(&self.conn)
+            .context("Error deleting Revocation")?;
+
+        Ok(())
+    }
+
     pub(crate) fn revocation_update(&self, revocation: &Revocation) -> Result<()> {
         diesel::update(revocation)
             .set(revocation)
@@ -548,6 +1258,13 @@ impl OcaDb {
         Ok(())
     }
 
+    pub(crate) fn email_add(&self, cert: &Cert, addr: &str) -> Result<CertEmail> {
+        self.email_insert(NewCertEmail {
+            addr: addr.to_owned(),
+            cert_id: cert.id,
+        })
+    }
+
     pub(crate) fn emails_by_cert(&self, cert: &Cert) -> Result<Vec<CertEmail>> {
         certs_emails::table
             .filter(certs_emails::cert_id.eq(cert.id))
@@ -612,6 +1329,290 @@ impl OcaDb {
             .context("Error loading bridges")
     }
 
+    pub(crate) fn tags(&self) -> Result<Vec<Tag>> {
+        tags::table
+            .order(tags::id)
+            .load::<Tag>(&self.conn)
+            .context("Error loading tags")
+    }
+
+    pub(crate) fn tags_by_user(&self, user_id: i32) -> Result<Vec<Tag>> {
+        tags::table
+            .filter(tags::user_id.eq(user_id))
+            .order(tags::name)
+            .load::<Tag>(&self.conn)
+            .context("Error loading tags")
+    }
+
+    pub(crate) fn tags_by_cert(&self, cert_id: i32) -> Result<Vec<Tag>> {
+        tags::table
+            .filter(tags::cert_id.eq(cert_id))
+            .order(tags::name)
+            .load::<Tag>(&self.conn)
+            .context("Error loading tags")
+    }
+
+    /// Set the tag `name` of a user or a cert to `value` (with None: remove
+    /// the tag)
+    pub(crate) fn tag_set(
+        &self,
+        user_id: Option<i32>,
+        cert_id: Option<i32>,
+        name: &str,
+        value: Option<&str>,
+    ) -> Result<()> {
+        let existing = match (user_id, cert_id) {
+            (Some(id), None) => self.tags_by_user(id)?,
+            (None, Some(id)) => self.tags_by_cert(id)?,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "tag_set: a tag belongs to either a user or a cert"
+                ))
+            }
+        };
+
+        match (existing.into_iter().find(|t| t.name == name), value) {
+            (Some(mut tag), Some(value)) => {
+                tag.value = value.to_string();
+                diesel::update(&tag)
+                    .set(&tag)
+                    .execute(&self.conn)
+                    .context("Error updating tag")?;
+            }
+            (Some(tag), None) => {
+                diesel::delete(tags::table.filter(tags::id.eq(tag.id)))
+                    .execute(&self.conn)
+                    .context("Error deleting tag")?;
+            }
+            (None, Some(value)) => {
+                diesel::insert_into(tags::table)
+                    .values(&NewTag {
+                        user_id,
+                        cert_id,
+                        name,
+                        value,
+                    })
+                    .execute(&self.conn)
+                    .context("Error saving tag")?;
+            }
+            (None, None) => {}
+        }
+
+        Ok(())
+    }
+
+    /// The sync state of all certs for the public source `source`
+    pub(crate) fn sync_states(&self, source: &str) -> Result<Vec<SyncState>> {
+        sync_state::table
+            .filter(sync_state::source.eq(source))
+            .order(sync_state::id)
+            .load::<SyncState>(&self.conn)
+            .context("Error loading sync state")
+    }
+
+    /// Record an attempt to update the cert `cert_id` from the public
+    /// source `source` at `time`, which failed with `error` (or, with
+    /// None, succeeded)
+    pub(crate) fn sync_state_record(
+        &self,
+        cert_id: i32,
+        source: &str,
+        time: chrono::NaiveDateTime,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let existing: Option<SyncState> = sync_state::table
+            .filter(sync_state::cert_id.eq(cert_id))
+            .filter(sync_state::source.eq(source))
+            .first::<SyncState>(&self.conn)
+            .optional()
+            .context("Error loading sync state")?;
+
+        match existing {
+            Some(mut state) => {
+                state.last_attempt = time;
+                match error {
+                    None => {
+                        state.last_success = Some(time);
+                        state.last_error = None;
+                        state.failing_since = None;
+                    }
+                    Some(error) => {
+                        state.last_error = Some(error.to_string());
+                        state.failing_since.get_or_insert(time);
+                    }
+                }
+
+                diesel::update(&state)
+                    .set(&state)
+                    .execute(&self.conn)
+                    .context("Error updating sync state")?;
+            }
+            None => {
+                diesel::insert_into(sync_state::table)
+                    .values(&NewSyncState {
+                        source,
+                        last_attempt: time,
+                        last_success: error.is_none().then_some(time),
+                        last_error: error,
+                        failing_since: error.is_some().then_some(time),
+                        cert_id,
+                    })
+                    .execute(&self.conn)
+                    .context("Error saving sync state")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The packets of `cert` that were merged in from keyservers
+    pub(crate) fn keyserver_packets_by_cert(&self, cert: &Cert) -> Result<Vec<KeyserverPacket>> {
+        KeyserverPacket::belonging_to(cert)
+            .order(keyserver_packets::id)
+            .load::<KeyserverPacket>(&self.conn)
+            .context("Error loading keyserver packets")
+    }
+
+    pub(crate) fn keyserver_packets_insert(&self, packets: &[NewKeyserverPacket]) -> Result<()> {
+        diesel::insert_into(keyserver_packets::table)
+            .values(packets)
+            .execute(&self.conn)
+            .context("Error saving keyserver packets")?;
+
+        Ok(())
+    }
+
+    pub(crate) fn groups(&self) -> Result<Vec<Group>> {
+        groups::table
+            .order(groups::name)
+            .load::<Group>(&self.conn)
+            .context("Error loading groups")
+    }
+
+    pub(crate) fn group_by_name(&self, name: &str) -> Result<Option<Group>> {
+        let db: Vec<Group> = groups::table
+            .filter(groups::name.eq(name))
+            .load::<Group>(&self.conn)
+            .context("Error loading group")?;
+
+        Ok(db.first().cloned())
+    }
+
+    pub(crate) fn group_insert(&self, name: &str, description: Option<&str>) -> Result<Group> {
+        diesel::insert_into(groups::table)
+            .values(&NewGroup { name, description })
+            .execute(&self.conn)
+            .context("Error saving group")?;
+
+        self.group_by_name(name)?.context("Error loading new group")
+    }
+
+    /// Delete the group `group_id` (and its memberships)
+    pub(crate) fn group_delete(&self, group_id: i32) -> Result<()> {
+        diesel::delete(group_members::table.filter(group_members::group_id.eq(group_id)))
+            .execute(&self.conn)
+            .context("Error deleting group members")?;
+        diesel::delete(groups::table.filter(groups::id.eq(group_id)))
+            .execute(&self.conn)
+            .context("Error deleting group")?;
+
+        Ok(())
+    }
+
+    /// The users in the group `group_id`, ordered by name
+    pub(crate) fn group_users(&self, group_id: i32) -> Result<Vec<User>> {
+        let user_ids = group_members::table
+            .filter(group_members::group_id.eq(group_id))
+            .select(group_members::user_id);
+
+        users::table
+            .filter(users::id.eq_any(user_ids))
+            .order((users::name, users::id))
+            .load::<User>(&self.conn)
+            .context("Error loading group members")
+    }
+
+    /// The groups that the user `user_id` is a member of, ordered by name
+    pub(crate) fn groups_by_user(&self, user_id: i32) -> Result<Vec<Group>> {
+        let group_ids = group_members::table
+            .filter(group_members::user_id.eq(user_id))
+            .select(group_members::group_id);
+
+        groups::table
+            .filter(groups::id.eq_any(group_ids))
+            .order(groups::name)
+            .load::<Group>(&self.conn)
+            .context("Error loading groups")
+    }
+
+    /// Add the user `user_id` to the group `group_id` (or with `member`
+    /// false: remove it). Returns false if nothing changed.
+    pub(crate) fn group_member_set(
+        &self,
+        group_id: i32,
+        user_id: i32,
+        member: bool,
+    ) -> Result<bool> {
+        let existing = group_members::table
+            .filter(group_members::group_id.eq(group_id))
+            .filter(group_members::user_id.eq(user_id));
+
+        let is_member = existing
+            .clone()
+            .count()
+            .get_result::<i64>(&self.conn)
+            .context("Error loading group members")?
+            > 0;
+
+        match (is_member, member) {
+            (false, true) => {
+                diesel::insert_into(group_members::table)
+                    .values(&NewGroupMember { group_id, user_id })
+                    .execute(&self.conn)
+                    .context("Error saving group member")?;
+            }
+            (true, false) => {
+                diesel::delete(existing)
+                    .execute(&self.conn)
+                    .context("Error deleting group member")?;
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    pub(crate) fn pref(&self, name: &str) -> Result<Option<String>> {
+        let db: Vec<Pref> = prefs::table
+            .filter(prefs::name.eq(name))
+            .load::<Pref>(&self.conn)
+            .context("Error loading pref")?;
+
+        Ok(db.first().map(|p| p.value.clone()))
+    }
+
+    pub(crate) fn pref_set(&self, name: &str, value: &str) -> Result<()> {
+        let db: Vec<Pref> = prefs::table
+            .filter(prefs::name.eq(name))
+            .load::<Pref>(&self.conn)
+            .context("Error loading pref")?;
+
+        if let Some(mut pref) = db.first().cloned() {
+            pref.value = value.to_string();
+            diesel::update(&pref)
+                .set(&pref)
+                .execute(&self.conn)
+                .context("Error updating pref")?;
+        } else {
+            diesel::insert_into(prefs::table)
+                .values(&NewPref { name, value })
+                .execute(&self.conn)
+                .context("Error saving pref")?;
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn diesel_migrations_run(&self) {
         embed_migrations!();
 
@@ -12,6 +12,7 @@
 use chrono::NaiveDateTime;
 
 use crate::db::schema::*;
+use crate::types::ExportChannel;
 
 #[derive(Queryable, Debug, Clone, AsChangeset, Identifiable)]
 pub(crate) struct Ca {
@@ -57,6 +58,10 @@ pub struct User {
     pub name: Option<String>,
     // https://docs.diesel.rs/diesel/associations/index.html
     pub ca_id: i32,
+    pub notes: Option<String>, // free-form notes by the CA admins
+
+    /// The user's certs are published via WKD
+    pub wkd_publish: bool,
 }
 
 #[derive(Insertable, Debug)]
@@ -77,6 +82,45 @@ pub struct Cert {
     pub user_id: Option<i32>,
     pub delisted: bool,
     pub inactive: bool,
+    pub notes: Option<String>, // free-form notes by the CA admins
+
+    /// Time at which the cert was added to the CA (unknown for certs that
+    /// were added with older versions of OpenPGP CA)
+    pub created: Option<NaiveDateTime>,
+    /// The version history of the cert is complete from this time on
+    /// (None if it is complete)
+    pub history_since: Option<NaiveDateTime>,
+
+    // Delisting per export channel ("delisted" is set if the cert is
+    // delisted on all channels)
+    pub delisted_wkd: bool,
+    pub delisted_keylist: bool,
+    pub delisted_keyserver: bool,
+    pub delisted_certring: bool,
+}
+
+impl Cert {
+    /// The cert is not exported through `channel`
+    pub fn is_delisted(&self, channel: ExportChannel) -> bool {
+        match channel {
+            ExportChannel::Wkd => self.delisted_wkd,
+            ExportChannel::Keylist => self.delisted_keylist,
+            ExportChannel::Keyserver => self.delisted_keyserver,
+            ExportChannel::Certring => self.delisted_certring,
+        }
+    }
+
+    /// Set the delisting of the cert for `channel` (and update "delisted")
+    pub(crate) fn set_delisted(&mut self, channel: ExportChannel, delisted: bool) {
+        match channel {
+            ExportChannel::Wkd => self.delisted_wkd = delisted,
+            ExportChannel::Keylist => self.delisted_keylist = delisted,
+            ExportChannel::Keyserver => self.delisted_keyserver = delisted,
+            ExportChannel::Certring => self.delisted_certring = delisted,
+        }
+
+        self.delisted = ExportChannel::ALL.iter().all(|c| self.is_delisted(*c));
+    }
 }
 
 #[derive(Insertable, Debug)]
@@ -87,6 +131,167 @@ pub(crate) struct NewCert<'a> {
     pub user_id: Option<i32>,
     pub delisted: bool,
     pub inactive: bool,
+    pub created: Option<NaiveDateTime>,
+}
+
+/// A previous version of a user certificate (linked to user certificates)
+#[derive(Identifiable, Queryable, Debug, Associations, Clone)]
+#[table_name = "cert_versions"]
+#[belongs_to(Cert)]
+pub struct CertVersion {
+    pub id: i32,
+    pub pub_cert: String,
+    pub created: NaiveDateTime,
+    pub origin: String,
+    pub cert_id: i32,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "cert_versions"]
+pub(crate) struct NewCertVersion<'a> {
+    pub pub_cert: &'a str,
+    pub created: NaiveDateTime,
+    pub origin: &'a str,
+    pub cert_id: i32,
+}
+
+/// A record of a cert that was moved from one user to another (linked to
+/// user certificates)
+#[derive(Identifiable, Queryable, Debug, Associations, Clone)]
+#[table_name = "cert_reassignments"]
+#[belongs_to(Cert)]
+pub struct CertReassignment {
+    pub id: i32,
+    pub created: NaiveDateTime,
+    pub from_user_id: Option<i32>,
+    pub to_user_id: i32,
+    pub reason: Option<String>,
+    pub cert_id: i32,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "cert_reassignments"]
+pub(crate) struct NewCertReassignment<'a> {
+    pub created: NaiveDateTime,
+    pub from_user_id: Option<i32>,
+    pub to_user_id: i32,
+    pub reason: Option<&'a str>,
+    pub cert_id: i32,
+}
+
+/// A cert whose armored cert couldn't be parsed or recovered, and which is
+/// left out of listings until it is released
+#[derive(Identifiable, Queryable, Debug, Associations, Clone)]
+#[table_name = "cert_quarantine"]
+#[belongs_to(Cert)]
+pub struct CertQuarantine {
+    pub id: i32,
+    pub created: NaiveDateTime,
+    pub error: String,
+    pub cert_id: i32,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "cert_quarantine"]
+pub(crate) struct NewCertQuarantine<'a> {
+    pub created: NaiveDateTime,
+    pub error: &'a str,
+    pub cert_id: i32,
+}
+
+/// A replacement cert that a user submitted for the cert `cert_id`, with
+/// a handover statement signed by the old key
+#[derive(Identifiable, Queryable, Debug, Associations, Clone, AsChangeset)]
+#[table_name = "key_replacements"]
+#[belongs_to(Cert)]
+pub struct KeyReplacement {
+    pub id: i32,
+    pub created: NaiveDateTime,
+    pub new_fingerprint: String,
+    pub new_cert: String,
+    pub handover: String,
+    pub status: String,
+    pub decided: Option<NaiveDateTime>,
+    pub reason: Option<String>,
+    pub cert_id: i32,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "key_replacements"]
+pub(crate) struct NewKeyReplacement<'a> {
+    pub created: NaiveDateTime,
+    pub new_fingerprint: &'a str,
+    pub new_cert: &'a str,
+    pub handover: &'a str,
+    pub status: &'a str,
+    pub cert_id: i32,
+}
+
+/// An entry of the blocklist (a fingerprint or domain that must not be
+/// imported or certified)
+#[derive(Identifiable, Queryable, Debug, Clone, AsChangeset)]
+#[table_name = "blocklist"]
+pub struct BlocklistEntry {
+    pub id: i32,
+    pub created: NaiveDateTime,
+    pub kind: String,
+    pub pattern: String,
+    pub reason: Option<String>,
+    pub removed: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "blocklist"]
+pub(crate) struct NewBlocklistEntry<'a> {
+    pub created: NaiveDateTime,
+    pub kind: &'a str,
+    pub pattern: &'a str,
+    pub reason: Option<&'a str>,
+}
+
+/// An entry of the log of certifications and revocations that the CA issued
+/// (see [crate::types::CtLogEntry])
+#[derive(Identifiable, Queryable, Debug, Clone)]
+#[table_name = "ct_log"]
+pub struct CtLogEntry {
+    pub id: i32,
+    pub created: NaiveDateTime,
+    pub kind: String,
+    pub fingerprint: String,
+    pub signature: String,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "ct_log"]
+pub(crate) struct NewCtLogEntry<'a> {
+    pub id: i32,
+    pub created: NaiveDateTime,
+    pub kind: &'a str,
+    pub fingerprint: &'a str,
+    pub signature: &'a str,
+    pub prev_hash: &'a str,
+    pub hash: &'a str,
+}
+
+/// A signature on a user cert that has been cryptographically verified
+#[derive(Identifiable, Queryable, Debug, Associations, Clone)]
+#[table_name = "verified_signatures"]
+#[belongs_to(Cert)]
+pub struct VerifiedSignature {
+    pub id: i32,
+    pub digest: String,
+    pub verified_by: String,
+    pub cert_id: i32,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "verified_signatures"]
+pub(crate) struct NewVerifiedSignature<'a> {
+    pub digest: &'a str,
+    pub verified_by: &'a str,
+    pub cert_id: i32,
 }
 
 /// Email addresses that are associated with user certificates
@@ -108,6 +313,7 @@ pub(crate) struct NewCertEmail {
 
 /// Revocation certificates (linked to user certificates)
 #[derive(Identifiable, Queryable, Debug, Associations, Clone, AsChangeset)]
+#[changeset_options(treat_none_as_null = "true")]
 #[belongs_to(Cert)]
 pub struct Revocation {
     pub id: i32,
@@ -116,6 +322,17 @@ pub struct Revocation {
     pub published: bool,
     // FIXME - https://docs.diesel.rs/diesel/associations/index.html
     pub cert_id: i32,
+
+    /// Time of the last successful push of the revoked cert to the
+    /// keyservers
+    pub keyserver_published: Option<NaiveDateTime>,
+    /// Error of the last failed push to the keyservers
+    pub keyserver_error: Option<String>,
+    /// Time of the last successful re-export of the WKD entries of the
+    /// revoked cert
+    pub wkd_published: Option<NaiveDateTime>,
+    /// Error of the last failed re-export of the WKD entries
+    pub wkd_error: Option<String>,
 }
 
 #[derive(Insertable, Debug)]
@@ -135,6 +352,14 @@ pub struct Bridge {
     pub scope: String,
     pub cert_id: i32,
     pub cas_id: i32,
+
+    /// Name of the [crate::types::CertificationProfile] of the bridge's
+    /// trust signature
+    pub profile: String,
+
+    /// Regular expressions that scope the bridge's trust signature, as a
+    /// JSON array (`None` for bridges that predate storing the scopes)
+    pub scope_regexes: Option<String>,
 }
 
 #[derive(Insertable, Debug)]
@@ -144,9 +369,13 @@ pub(crate) struct NewBridge<'a> {
     pub scope: &'a str,
     pub cert_id: i32,
     pub cas_id: i32,
+    pub profile: &'a str,
+    pub scope_regexes: Option<&'a str>,
 }
 
-/// Queue entries
+/// Queue entries: requests of a split mode front instance for the back
+/// instance, or proposed changes that wait for approval by an admin (see
+/// [crate::types::Proposal])
 #[derive(Identifiable, Queryable, Clone, AsChangeset, Debug)]
 #[table_name = "queue"]
 pub struct Queue {
@@ -154,6 +383,10 @@ pub struct Queue {
     pub created: NaiveDateTime,
     pub task: String,
     pub done: bool,
+    pub kind: String,
+    pub status: Option<String>,
+    pub decided: Option<NaiveDateTime>,
+    pub reason: Option<String>,
 }
 
 #[derive(Insertable, Debug)]
@@ -162,6 +395,123 @@ pub(crate) struct NewQueue<'a> {
     pub created: NaiveDateTime,
     pub task: &'a str,
     pub done: bool,
+    pub kind: &'a str,
+    pub status: Option<&'a str>,
 }
 
 // FIXME: prefs table
+
+/// CA-wide settings
+#[derive(Identifiable, Queryable, Clone, AsChangeset, Debug)]
+#[table_name = "prefs"]
+pub(crate) struct Pref {
+    pub id: i32,
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "prefs"]
+pub(crate) struct NewPref<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+/// State of the updates of the cert `cert_id` from the public source
+/// `source` ("wkd" or "keyserver")
+#[derive(Identifiable, Queryable, Debug, Associations, Clone, AsChangeset)]
+#[changeset_options(treat_none_as_null = "true")]
+#[table_name = "sync_state"]
+#[belongs_to(Cert)]
+pub struct SyncState {
+    pub id: i32,
+    pub source: String,
+    pub last_attempt: NaiveDateTime,
+    pub last_success: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    pub failing_since: Option<NaiveDateTime>,
+    pub cert_id: i32,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "sync_state"]
+pub(crate) struct NewSyncState<'a> {
+    pub source: &'a str,
+    pub last_attempt: NaiveDateTime,
+    pub last_success: Option<NaiveDateTime>,
+    pub last_error: Option<&'a str>,
+    pub failing_since: Option<NaiveDateTime>,
+    pub cert_id: i32,
+}
+
+/// A packet of the cert `cert_id` that was merged in from `keyserver`
+#[derive(Identifiable, Queryable, Debug, Associations, Clone)]
+#[table_name = "keyserver_packets"]
+#[belongs_to(Cert)]
+pub struct KeyserverPacket {
+    pub id: i32,
+    pub keyserver: String,
+
+    /// SHA256 digest (as hex) of the normalized packet
+    pub digest: String,
+
+    /// Kind of the packet
+    pub packet: String,
+
+    pub created: NaiveDateTime,
+    pub cert_id: i32,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "keyserver_packets"]
+pub(crate) struct NewKeyserverPacket<'a> {
+    pub keyserver: &'a str,
+    pub digest: &'a str,
+    pub packet: &'a str,
+    pub created: NaiveDateTime,
+    pub cert_id: i32,
+}
+
+/// A key-value tag on a user or a cert (e.g. "department", "employee-id")
+#[derive(Identifiable, Queryable, Debug, Clone, AsChangeset)]
+#[table_name = "tags"]
+pub struct Tag {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub cert_id: Option<i32>,
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "tags"]
+pub(crate) struct NewTag<'a> {
+    pub user_id: Option<i32>,
+    pub cert_id: Option<i32>,
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+/// A named group of users (e.g. a department)
+#[derive(Identifiable, Queryable, Debug, Clone, PartialEq, Eq)]
+#[table_name = "groups"]
+pub struct Group {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "groups"]
+pub(crate) struct NewGroup<'a> {
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+}
+
+/// Membership of the user `user_id` in the group `group_id`
+#[derive(Insertable, Debug)]
+#[table_name = "group_members"]
+pub(crate) struct NewGroupMember {
+    pub group_id: i32,
+    pub user_id: i32,
+}
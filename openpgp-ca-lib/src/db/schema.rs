@@ -1,3 +1,14 @@
+table! {
+    blocklist (id) {
+        id -> Integer,
+        created -> Timestamp,
+        kind -> Text,
+        pattern -> Text,
+        reason -> Nullable<Text>,
+        removed -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     bridges (id) {
         id -> Integer,
@@ -5,6 +16,8 @@ table! {
         scope -> Text,
         cert_id -> Integer,
         cas_id -> Integer,
+        profile -> Text,
+        scope_regexes -> Nullable<Text>,
     }
 }
 
@@ -34,6 +47,43 @@ table! {
         user_id -> Nullable<Integer>,
         delisted -> Bool,
         inactive -> Bool,
+        notes -> Nullable<Text>,
+        created -> Nullable<Timestamp>,
+        history_since -> Nullable<Timestamp>,
+        delisted_wkd -> Bool,
+        delisted_keylist -> Bool,
+        delisted_keyserver -> Bool,
+        delisted_certring -> Bool,
+    }
+}
+
+table! {
+    cert_versions (id) {
+        id -> Integer,
+        pub_cert -> Text,
+        created -> Timestamp,
+        origin -> Text,
+        cert_id -> Integer,
+    }
+}
+
+table! {
+    cert_quarantine (id) {
+        id -> Integer,
+        created -> Timestamp,
+        error -> Text,
+        cert_id -> Integer,
+    }
+}
+
+table! {
+    cert_reassignments (id) {
+        id -> Integer,
+        created -> Timestamp,
+        from_user_id -> Nullable<Integer>,
+        to_user_id -> Integer,
+        reason -> Nullable<Text>,
+        cert_id -> Integer,
     }
 }
 
@@ -45,6 +95,59 @@ table! {
     }
 }
 
+table! {
+    ct_log (id) {
+        id -> Integer,
+        created -> Timestamp,
+        kind -> Text,
+        fingerprint -> Text,
+        signature -> Text,
+        prev_hash -> Text,
+        hash -> Text,
+    }
+}
+
+table! {
+    group_members (id) {
+        id -> Integer,
+        group_id -> Integer,
+        user_id -> Integer,
+    }
+}
+
+table! {
+    groups (id) {
+        id -> Integer,
+        name -> Text,
+        description -> Nullable<Text>,
+    }
+}
+
+table! {
+    key_replacements (id) {
+        id -> Integer,
+        created -> Timestamp,
+        new_fingerprint -> Text,
+        new_cert -> Text,
+        handover -> Text,
+        status -> Text,
+        decided -> Nullable<Timestamp>,
+        reason -> Nullable<Text>,
+        cert_id -> Integer,
+    }
+}
+
+table! {
+    keyserver_packets (id) {
+        id -> Integer,
+        keyserver -> Text,
+        digest -> Text,
+        packet -> Text,
+        created -> Timestamp,
+        cert_id -> Integer,
+    }
+}
+
 table! {
     revocations (id) {
         id -> Integer,
@@ -52,6 +155,10 @@ table! {
         revocation -> Text,
         published -> Bool,
         cert_id -> Integer,
+        keyserver_published -> Nullable<Timestamp>,
+        keyserver_error -> Nullable<Text>,
+        wkd_published -> Nullable<Timestamp>,
+        wkd_error -> Nullable<Text>,
     }
 }
 
@@ -60,6 +167,16 @@ table! {
         id -> Integer,
         name -> Nullable<Text>,
         ca_id -> Integer,
+        notes -> Nullable<Text>,
+        wkd_publish -> Bool,
+    }
+}
+
+table! {
+    prefs (id) {
+        id -> Integer,
+        name -> Text,
+        value -> Text,
     }
 }
 
@@ -69,23 +186,81 @@ table! {
         created -> Timestamp,
         task -> Text,
         done -> Bool,
+        kind -> Text,
+        status -> Nullable<Text>,
+        decided -> Nullable<Timestamp>,
+        reason -> Nullable<Text>,
+    }
+}
+
+table! {
+    sync_state (id) {
+        id -> Integer,
+        source -> Text,
+        last_attempt -> Timestamp,
+        last_success -> Nullable<Timestamp>,
+        last_error -> Nullable<Text>,
+        failing_since -> Nullable<Timestamp>,
+        cert_id -> Integer,
+    }
+}
+
+table! {
+    tags (id) {
+        id -> Integer,
+        user_id -> Nullable<Integer>,
+        cert_id -> Nullable<Integer>,
+        name -> Text,
+        value -> Text,
+    }
+}
+
+table! {
+    verified_signatures (id) {
+        id -> Integer,
+        digest -> Text,
+        verified_by -> Text,
+        cert_id -> Integer,
     }
 }
 
 joinable!(bridges -> cas (cas_id));
 joinable!(bridges -> certs (cert_id));
 joinable!(cacerts -> cas (ca_id));
+joinable!(cert_quarantine -> certs (cert_id));
+joinable!(cert_reassignments -> certs (cert_id));
+joinable!(cert_versions -> certs (cert_id));
 joinable!(certs -> users (user_id));
 joinable!(certs_emails -> certs (cert_id));
+joinable!(group_members -> groups (group_id));
+joinable!(group_members -> users (user_id));
+joinable!(key_replacements -> certs (cert_id));
+joinable!(keyserver_packets -> certs (cert_id));
 joinable!(revocations -> certs (cert_id));
+joinable!(sync_state -> certs (cert_id));
+joinable!(tags -> certs (cert_id));
+joinable!(tags -> users (user_id));
 joinable!(users -> cas (ca_id));
+joinable!(verified_signatures -> certs (cert_id));
 
 allow_tables_to_appear_in_same_query!(
+    blocklist,
     bridges,
     cacerts,
     cas,
     certs,
+    cert_quarantine,
+    cert_reassignments,
+    cert_versions,
     certs_emails,
+    ct_log,
+    group_members,
+    groups,
+    key_replacements,
+    keyserver_packets,
     revocations,
+    sync_state,
+    tags,
     users,
+    verified_signatures,
 );
@@ -0,0 +1,332 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Checks for damaged rows in the database: recovery of armored certs that
+//! can't be parsed, and quarantine of those that can't be recovered.
+//! Also, a read-only check of the invariants between the tables of the
+//! database.
+
+use anyhow::Result;
+
+use crate::backend::split::QueueEntry;
+use crate::backend::Backend;
+use crate::db::models;
+use crate::pgp;
+use crate::types::{
+    CertBlobOutcome, CertBlobReport, ConsistencyIssue, ConsistencyIssueKind, ConsistencyReport,
+    ProposalStatus, ProposedChange,
+};
+use crate::Oca;
+
+/// Origin label of cert versions that were replaced by a recovered cert
+const ORIGIN_REPAIR: &str = "blob repair";
+
+/// Recover the cert in `db_cert`, if it has the fingerprint of the row
+fn recover(db_cert: &models::Cert) -> Result<String> {
+    let cert = pgp::to_cert_lenient(db_cert.pub_cert.as_bytes())?;
+
+    if cert.fingerprint().to_hex() != db_cert.fingerprint {
+        return Err(anyhow::anyhow!(
+            "Recovered cert has the fingerprint {}",
+            cert.fingerprint()
+        ));
+    }
+
+    pgp::cert_to_armored(&cert)
+}
+
+/// Check all cert rows that aren't quarantined for armored certs that can't
+/// be parsed.
+///
+/// With `repair`, recoverable certs are replaced with the recovered version
+/// (the damaged version is kept as a cert version), and irrecoverable rows
+/// are quarantined.
+pub(crate) fn check_blobs(oca: &Oca, repair: bool) -> Result<Vec<CertBlobReport>> {
+    let quarantined: Vec<i32> = oca
+        .storage
+        .certs_quarantined()?
+        .iter()
+        .map(|q| q.cert_id)
+        .collect();
+
+    let mut reports = vec![];
+
+    for db_cert in oca.storage.cert_rows()? {
+        if quarantined.contains(&db_cert.id) {
+            continue;
+        }
+
+        let error = match pgp::to_cert(db_cert.pub_cert.as_bytes()) {
+            Ok(_) => continue,
+            Err(e) => format!("{e:#}"),
+        };
+
+        let outcome = match (recover(&db_cert), repair) {
+            (Ok(_), false) => CertBlobOutcome::Recoverable,
+            (Ok(armored), true) => {
+                oca.storage
+                    .cert_blob_replace(&db_cert, &armored, ORIGIN_REPAIR)?;
+                CertBlobOutcome::Repaired
+            }
+            (Err(_), false) => CertBlobOutcome::Irrecoverable,
+            (Err(_), true) => {
+                oca.storage.cert_quarantine(&db_cert, &error)?;
+                CertBlobOutcome::Quarantined
+            }
+        };
+
+        reports.push(CertBlobReport {
+            cert_id: db_cert.id,
+            fingerprint: db_cert.fingerprint,
+            error,
+            outcome,
+        });
+    }
+
+    Ok(reports)
+}
+
+/// Does the CA cert of a CA with `backend` contain private key material?
+fn has_secret_ca_key(backend: &Backend) -> bool {
+    match backend {
+        Backend::Softkey => true,
+        Backend::SplitBack(inner) => has_secret_ca_key(inner),
+        Backend::Card(_) | Backend::SplitFront => false,
+    }
+}
+
+fn check_cacert(oca: &Oca, issues: &mut Vec<ConsistencyIssue>) -> Result<()> {
+    let cacert = oca.storage.cacert()?;
+
+    let mut issue = |message: String| {
+        issues.push(ConsistencyIssue {
+            kind: ConsistencyIssueKind::CaCert,
+            row: format!("cacerts row {}", cacert.id),
+            message,
+        })
+    };
+
+    let cert = match pgp::to_cert(cacert.priv_cert.as_bytes()) {
+        Ok(cert) => cert,
+        Err(e) => {
+            issue(format!("CA cert can't be parsed: {e:#}"));
+            return Ok(());
+        }
+    };
+
+    if cert.fingerprint().to_hex() != cacert.fingerprint {
+        issue(format!(
+            "CA cert has the fingerprint {}, the row has {}",
+            cert.fingerprint().to_hex(),
+            cacert.fingerprint
+        ));
+    }
+
+    let expected = has_secret_ca_key(oca.backend());
+    if cert.primary_key().key().has_secret() != expected {
+        issue(match expected {
+            true => "CA cert has no private key material, but the backend needs it".to_string(),
+            false => "CA cert contains private key material, but the backend keeps the \
+                      key elsewhere"
+                .to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Check the cert rows (except for quarantined ones) and their emails
+fn check_certs(oca: &Oca, report: &mut ConsistencyReport) -> Result<()> {
+    let quarantined: Vec<i32> = oca
+        .storage
+        .certs_quarantined()?
+        .iter()
+        .map(|q| q.cert_id)
+        .collect();
+
+    for db_cert in oca.storage.cert_rows()? {
+        if quarantined.contains(&db_cert.id) {
+            continue;
+        }
+        report.certs += 1;
+
+        let row = format!("certs row {}", db_cert.id);
+
+        let cert = match pgp::to_cert(db_cert.pub_cert.as_bytes()) {
+            Ok(cert) => cert,
+            Err(e) => {
+                report.issues.push(ConsistencyIssue {
+                    kind: ConsistencyIssueKind::CertUnparsable,
+                    row,
+                    message: format!("{e:#}"),
+                });
+                continue;
+            }
+        };
+
+        if cert.fingerprint().to_hex() != db_cert.fingerprint {
+            report.issues.push(ConsistencyIssue {
+                kind: ConsistencyIssueKind::FingerprintMismatch,
+                row: row.clone(),
+                message: format!(
+                    "Cert has the fingerprint {}, the row has {}",
+                    cert.fingerprint().to_hex(),
+                    db_cert.fingerprint
+                ),
+            });
+        }
+
+        let uid_emails: Vec<String> = cert
+            .userids()
+            .filter_map(|uid| uid.userid().email2().ok().flatten().map(str::to_string))
+            .collect();
+
+        for email in oca.storage.emails_by_cert(&db_cert)? {
+            if !uid_emails
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(&email.addr))
+            {
+                report.issues.push(ConsistencyIssue {
+                    kind: ConsistencyIssueKind::EmailMismatch,
+                    row: row.clone(),
+                    message: format!("No User ID for the email {}", email.addr),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_revocations(oca: &Oca, report: &mut ConsistencyReport) -> Result<()> {
+    for revocation in oca.storage.revocations()? {
+        report.revocations += 1;
+
+        let mut issue = |message: String| {
+            report.issues.push(ConsistencyIssue {
+                kind: ConsistencyIssueKind::RevocationInvalid,
+                row: format!("revocations row {}", revocation.id),
+                message,
+            })
+        };
+
+        let db_cert = match oca.storage.cert_by_id(revocation.cert_id)? {
+            Some(db_cert) => db_cert,
+            None => {
+                issue(format!("No cert row {}", revocation.cert_id));
+                continue;
+            }
+        };
+
+        // Certs that can't be parsed are reported as such
+        let cert = match pgp::to_cert(db_cert.pub_cert.as_bytes()) {
+            Ok(cert) => cert,
+            Err(_) => continue,
+        };
+
+        let mut sig = match pgp::to_signature(revocation.revocation.as_bytes()) {
+            Ok(sig) => sig,
+            Err(e) => {
+                issue(format!("Revocation can't be parsed: {e:#}"));
+                continue;
+            }
+        };
+
+        let pk = cert.primary_key().key();
+        if sig.verify_primary_key_revocation(pk, pk).is_err() {
+            issue(format!(
+                "Revocation doesn't validate against the cert {}",
+                db_cert.fingerprint
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the pending split mode requests and proposals
+fn check_queue(oca: &Oca, report: &mut ConsistencyReport) -> Result<()> {
+    let known = |fp: &str| -> Result<bool> { Ok(oca.storage.cert_by_fp(fp)?.is_some()) };
+
+    let pending_proposals = oca.storage.proposals()?.into_iter().filter(|q| {
+        q.status
+            .as_deref()
+            .unwrap_or(ProposalStatus::Pending.name())
+            == ProposalStatus::Pending.name()
+    });
+
+    for q in oca.storage.queue_not_done()? {
+        report.queue_entries += 1;
+
+        let problem = match serde_json::from_str::<QueueEntry>(&q.task)
+            .map_err(anyhow::Error::from)
+            .and_then(|entry| entry.cert())
+        {
+            Ok(cert) => match known(&cert.fingerprint().to_hex())? {
+                true => None,
+                false => Some(format!("No cert with fingerprint {}", cert.fingerprint())),
+            },
+            Err(e) => Some(format!("Request can't be parsed: {e:#}")),
+        };
+
+        if let Some(message) = problem {
+            report.issues.push(ConsistencyIssue {
+                kind: ConsistencyIssueKind::QueueDangling,
+                row: format!("queue row {}", q.id),
+                message,
+            });
+        }
+    }
+
+    for q in pending_proposals {
+        report.queue_entries += 1;
+
+        let problem = match serde_json::from_str::<ProposedChange>(&q.task) {
+            Ok(ProposedChange::CertImport { cert, .. }) => pgp::to_cert(cert.as_bytes())
+                .err()
+                .map(|e| format!("Proposed cert can't be parsed: {e:#}")),
+            Ok(ProposedChange::CertDeactivate { fingerprint })
+            | Ok(ProposedChange::CertDelist { fingerprint }) => match known(&fingerprint)? {
+                true => None,
+                false => Some(format!("No cert with fingerprint {fingerprint}")),
+            },
+            Ok(ProposedChange::RevocationDelete { hash }) => {
+                match oca.storage.revocation_by_hash(&hash)? {
+                    Some(_) => None,
+                    None => Some(format!("No revocation with hash {hash}")),
+                }
+            }
+            Ok(ProposedChange::RevocationAdd { revocation }) => {
+                pgp::to_signature(revocation.as_bytes())
+                    .err()
+                    .map(|e| format!("Proposed revocation can't be parsed: {e:#}"))
+            }
+            Err(e) => Some(format!("Proposal can't be parsed: {e:#}")),
+        };
+
+        if let Some(message) = problem {
+            report.issues.push(ConsistencyIssue {
+                kind: ConsistencyIssueKind::QueueDangling,
+                row: format!("queue row {}", q.id),
+                message,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the invariants of the database, without changing it
+pub(crate) fn check_consistency(oca: &Oca) -> Result<ConsistencyReport> {
+    let mut report = ConsistencyReport::default();
+
+    check_cacert(oca, &mut report.issues)?;
+    check_certs(oca, &mut report)?;
+    check_revocations(oca, &mut report)?;
+    check_queue(oca, &mut report)?;
+
+    Ok(report)
+}
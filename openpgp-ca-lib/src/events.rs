@@ -0,0 +1,468 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Structured events about changes in an OpenPGP CA instance.
+//!
+//! Events are serialized as JSON and signed with the CA key (see
+//! [SignedEvent]). They are handed to a set of [EventPublisher]s, which
+//! forward them to external systems.
+//!
+//! Publishers for NATS and AMQP are available with the `nats` and `amqp`
+//! features. They are configured via [EventsConfig], which is persisted in
+//! the CA database.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sequoia_openpgp::Cert;
+use serde::{Deserialize, Serialize};
+
+use crate::pgp;
+use crate::Oca;
+
+const PREF_EVENTS_CONFIG: &str = "events_config";
+
+/// Version of the JSON schema of [Event]
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Default prefix for topic names (see [EventsConfig::topic_prefix])
+pub const DEFAULT_TOPIC_PREFIX: &str = "openpgp-ca";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A new user cert was added to the CA (imported or generated)
+    CertImported,
+
+    /// An existing user cert was updated
+    CertUpdated,
+
+    /// The CA made new certifications on a user cert
+    CertCertified,
+
+    /// A revocation was applied to a user cert
+    CertRevoked,
+
+    /// A user cert was moved to a different user
+    CertReassigned,
+
+    /// A user cert was permanently deleted from the CA
+    CertPurged,
+
+    /// A user cert was offboarded: revoked (or its certifications
+    /// retracted), deactivated and delisted
+    CertOffboarded,
+
+    /// Certifications from a split mode back instance were ingested
+    QueueProcessed,
+
+    /// A user submitted a replacement for their cert, which needs to be
+    /// approved by an admin
+    KeyReplacementRequested,
+
+    /// A change was proposed via restd in approval mode, and needs to be
+    /// approved by an admin
+    ChangeProposed,
+}
+
+impl EventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::CertImported => "cert_imported",
+            EventKind::CertUpdated => "cert_updated",
+            EventKind::CertCertified => "cert_certified",
+            EventKind::CertRevoked => "cert_revoked",
+            EventKind::CertReassigned => "cert_reassigned",
+            EventKind::CertPurged => "cert_purged",
+            EventKind::CertOffboarded => "cert_offboarded",
+            EventKind::QueueProcessed => "queue_processed",
+            EventKind::KeyReplacementRequested => "key_replacement_requested",
+            EventKind::ChangeProposed => "change_proposed",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    /// Schema version, see [EVENT_SCHEMA_VERSION]
+    pub version: u32,
+
+    pub kind: EventKind,
+
+    /// Domain name of the CA that emitted this event
+    pub ca: String,
+
+    pub time: DateTime<Utc>,
+
+    /// Fingerprint of the affected user cert (if any)
+    pub fingerprint: Option<String>,
+
+    /// Email addresses associated with the affected user cert
+    pub emails: Vec<String>,
+}
+
+/// An [Event] along with a detached signature by the CA key.
+///
+/// The signature is made over the exact bytes of `event`, so consumers
+/// should verify it before parsing the event.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedEvent {
+    /// JSON serialization of an [Event]
+    pub event: String,
+
+    /// Armored detached signature over `event`
+    pub signature: String,
+}
+
+impl SignedEvent {
+    /// Verify the signature with `ca_cert`, and return the parsed [Event]
+    pub fn verify(&self, ca_cert: &Cert) -> Result<Event> {
+        pgp::verify_detached(ca_cert, self.event.as_bytes(), &self.signature)
+            .map_err(|_| anyhow::anyhow!("Event signature verification failed"))?;
+
+        Ok(serde_json::from_str(&self.event)?)
+    }
+}
+
+/// Configuration of the event publishers of a CA
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventsConfig {
+    /// Topics are named "<topic_prefix>.<event kind>", e.g.
+    /// "openpgp-ca.cert_imported" (defaults to [DEFAULT_TOPIC_PREFIX])
+    pub topic_prefix: Option<String>,
+
+    /// Server URL, e.g. "nats://localhost:4222" (requires the `nats` feature)
+    pub nats_url: Option<String>,
+
+    /// Server URL, e.g. "amqp://localhost:5672/%2f" (requires the `amqp` feature)
+    pub amqp_url: Option<String>,
+
+    /// The AMQP exchange to publish to (the default exchange, if unset).
+    /// Topic names are used as routing keys.
+    pub amqp_exchange: Option<String>,
+}
+
+impl EventsConfig {
+    pub fn topic(&self, kind: EventKind) -> String {
+        let prefix = self.topic_prefix.as_deref().unwrap_or(DEFAULT_TOPIC_PREFIX);
+
+        format!("{}.{}", prefix, kind.name())
+    }
+}
+
+/// A sink for CA events
+pub trait EventPublisher {
+    fn publish(&self, topic: &str, event: &SignedEvent) -> Result<()>;
+}
+
+pub(crate) fn events_config(oca: &Oca) -> Result<EventsConfig> {
+    match oca.storage.pref(PREF_EVENTS_CONFIG)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(EventsConfig::default()),
+    }
+}
+
+pub(crate) fn set_events_config(oca: &Oca, config: &EventsConfig) -> Result<()> {
+    let json = serde_json::to_string(config)?;
+    oca.storage.pref_set(PREF_EVENTS_CONFIG, &json)?;
+
+    // Publishers are set up from the new configuration on the next event
+    oca.event_publishers.replace(None);
+
+    Ok(())
+}
+
+/// Set up the publishers that are configured in `config`
+fn publishers_from_config(config: &EventsConfig) -> Result<Vec<Box<dyn EventPublisher>>> {
+    #[allow(unused_mut)]
+    let mut publishers: Vec<Box<dyn EventPublisher>> = vec![];
+
+    if let Some(_url) = &config.nats_url {
+        #[cfg(feature = "nats")]
+        publishers.push(Box::new(nats::NatsPublisher::new(_url)?));
+
+        #[cfg(not(feature = "nats"))]
+        return Err(anyhow::anyhow!(
+            "A NATS server is configured, but OpenPGP CA was built without the 'nats' feature"
+        ));
+    }
+
+    if let Some(_url) = &config.amqp_url {
+        #[cfg(feature = "amqp")]
+        publishers.push(Box::new(amqp::AmqpPublisher::new(
+            _url,
+            config.amqp_exchange.as_deref().unwrap_or_default(),
+        )?));
+
+        #[cfg(not(feature = "amqp"))]
+        return Err(anyhow::anyhow!(
+            "An AMQP server is configured, but OpenPGP CA was built without the 'amqp' feature"
+        ));
+    }
+
+    Ok(publishers)
+}
+
+pub(crate) fn add_publisher(oca: &Oca, publisher: Box<dyn EventPublisher>) -> Result<()> {
+    load_publishers(oca)?;
+
+    if let Some(publishers) = oca.event_publishers.borrow_mut().as_mut() {
+        publishers.push(publisher);
+    }
+
+    Ok(())
+}
+
+fn load_publishers(oca: &Oca) -> Result<()> {
+    if oca.event_publishers.borrow().is_none() {
+        let publishers = publishers_from_config(&events_config(oca)?)?;
+        oca.event_publishers.replace(Some(publishers));
+    }
+
+    Ok(())
+}
+
+/// Emit an event about the user cert `fingerprint` (if set).
+///
+/// Failure to publish an event doesn't fail the operation that caused it,
+/// problems are only logged.
+pub(crate) fn emit(oca: &Oca, kind: EventKind, fingerprint: Option<&str>) {
+    if let Err(e) = try_emit(oca, kind, fingerprint) {
+        tracing::warn!(event = kind.name(), "Failed to publish event: {:?}", e);
+    }
+}
+
+fn try_emit(oca: &Oca, kind: EventKind, fingerprint: Option<&str>) -> Result<()> {
+    load_publishers(oca)?;
+
+    let publishers = oca.event_publishers.borrow();
+    let publishers = match publishers.as_ref() {
+        Some(p) if !p.is_empty() => p,
+        _ => return Ok(()), // nobody is listening
+    };
+
+    let emails = match fingerprint {
+        Some(fp) => match oca.storage.cert_by_fp(fp)? {
+            Some(cert) => oca
+                .storage
+                .emails_by_cert(&cert)?
+                .into_iter()
+                .map(|e| e.addr)
+                .collect(),
+            None => vec![],
+        },
+        None => vec![],
+    };
+
+    let event = Event {
+        version: EVENT_SCHEMA_VERSION,
+        kind,
+        ca: oca.domainname().to_string(),
+        time: Utc::now(),
+        fingerprint: fingerprint.map(ToString::to_string),
+        emails,
+    };
+
+    let event = serde_json::to_string(&event)?;
+    let signature = oca.secret().sign_detached(event.as_bytes())?;
+    let signed = SignedEvent { event, signature };
+
+    let topic = events_config(oca)?.topic(kind);
+    for publisher in publishers.iter() {
+        publisher.publish(&topic, &signed)?;
+    }
+
+    Ok(())
+}
+
+/// Runs an async client for a message bus on a separate thread.
+///
+/// Events are handed to the thread via a channel. When the worker is
+/// dropped, pending events are delivered before the thread terminates.
+#[cfg(any(feature = "nats", feature = "amqp"))]
+struct Worker {
+    tx: Option<std::sync::mpsc::Sender<(String, Vec<u8>)>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(any(feature = "nats", feature = "amqp"))]
+impl Worker {
+    /// Start `run` on a new thread. `run` must signal on its second
+    /// parameter once it is connected (or has failed to connect).
+    fn spawn<F>(run: F) -> Result<Self>
+    where
+        F: FnOnce(
+                std::sync::mpsc::Receiver<(String, Vec<u8>)>,
+                std::sync::mpsc::SyncSender<Result<()>>,
+            ) -> Result<()>
+            + Send
+            + 'static,
+    {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (ready_tx, ready_rx) = std::sync::mpsc::sync_channel(1);
+
+        let handle = std::thread::spawn(move || {
+            if let Err(e) = run(rx, ready_tx) {
+                tracing::error!("Event publisher failed: {e:?}");
+            }
+        });
+
+        ready_rx
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Event publisher terminated during setup"))??;
+
+        Ok(Self {
+            tx: Some(tx),
+            handle: Some(handle),
+        })
+    }
+
+    fn send(&self, topic: &str, event: &SignedEvent) -> Result<()> {
+        let payload = serde_json::to_vec(event)?;
+
+        self.tx
+            .as_ref()
+            .expect("Worker is running")
+            .send((topic.to_string(), payload))
+            .map_err(|_| anyhow::anyhow!("Event publisher is not running"))
+    }
+}
+
+#[cfg(any(feature = "nats", feature = "amqp"))]
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // closing the channel makes the worker thread finish up
+        drop(self.tx.take());
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(feature = "nats")]
+pub mod nats {
+    use anyhow::Result;
+    use tokio::runtime::Runtime;
+
+    use super::{EventPublisher, SignedEvent, Worker};
+
+    /// Publishes events to a NATS server
+    pub struct NatsPublisher {
+        worker: Worker,
+    }
+
+    impl NatsPublisher {
+        pub fn new(url: &str) -> Result<Self> {
+            let url = url.to_string();
+
+            let worker = Worker::spawn(move |rx, ready| {
+                let rt = Runtime::new()?;
+
+                rt.block_on(async {
+                    let client = match async_nats::connect(url).await {
+                        Ok(client) => {
+                            let _ = ready.send(Ok(()));
+                            client
+                        }
+                        Err(e) => {
+                            let _ = ready.send(Err(e.into()));
+                            return Ok(());
+                        }
+                    };
+
+                    while let Ok((topic, payload)) = rx.recv() {
+                        client.publish(topic, payload.into()).await?;
+                    }
+
+                    client.flush().await?;
+
+                    Ok(())
+                })
+            })?;
+
+            Ok(Self { worker })
+        }
+    }
+
+    impl EventPublisher for NatsPublisher {
+        fn publish(&self, topic: &str, event: &SignedEvent) -> Result<()> {
+            self.worker.send(topic, event)
+        }
+    }
+}
+
+#[cfg(feature = "amqp")]
+pub mod amqp {
+    use anyhow::Result;
+    use lapin::options::BasicPublishOptions;
+    use lapin::{BasicProperties, Connection, ConnectionProperties};
+    use tokio::runtime::Runtime;
+
+    use super::{EventPublisher, SignedEvent, Worker};
+
+    /// Publishes events to an AMQP broker
+    pub struct AmqpPublisher {
+        worker: Worker,
+    }
+
+    impl AmqpPublisher {
+        pub fn new(url: &str, exchange: &str) -> Result<Self> {
+            let url = url.to_string();
+            let exchange = exchange.to_string();
+
+            let worker = Worker::spawn(move |rx, ready| {
+                let rt = Runtime::new()?;
+
+                rt.block_on(async {
+                    let connect = async {
+                        let conn =
+                            Connection::connect(&url, ConnectionProperties::default()).await?;
+                        let channel = conn.create_channel().await?;
+
+                        Ok::<_, lapin::Error>((conn, channel))
+                    };
+
+                    let (conn, channel) = match connect.await {
+                        Ok(c) => {
+                            let _ = ready.send(Ok(()));
+                            c
+                        }
+                        Err(e) => {
+                            let _ = ready.send(Err(e.into()));
+                            return Ok(());
+                        }
+                    };
+
+                    while let Ok((topic, payload)) = rx.recv() {
+                        channel
+                            .basic_publish(
+                                &exchange,
+                                &topic,
+                                BasicPublishOptions::default(),
+                                &payload,
+                                BasicProperties::default()
+                                    .with_content_type("application/json".into()),
+                            )
+                            .await?
+                            .await?;
+                    }
+
+                    conn.close(200, "OK").await?;
+
+                    Ok(())
+                })
+            })?;
+
+            Ok(Self { worker })
+        }
+    }
+
+    impl EventPublisher for AmqpPublisher {
+        fn publish(&self, topic: &str, event: &SignedEvent) -> Result<()> {
+            self.worker.send(topic, event)
+        }
+    }
+}
@@ -6,61 +6,450 @@
 // SPDX-FileCopyrightText: 2019-2022 Heiko Schaefer <heiko@schaefer.name>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+//! Exports of the CA's certs (keyrings, files per email, certificate chains,
+//! GnuPG homedirs, WKD, Autocrypt, SSH keys, keylist, revocation list, web
+//! of trust graph).
+//!
+//! Exports are reproducible: entries are ordered independently of the order
+//! of rows in the database, so that consecutive exports of an unchanged CA
+//! are identical (except for signatures and "created" timestamps), and
+//! exports can be tracked in version control with meaningful diffs.
+//!
+//! - The CA cert comes first (where it is included).
+//! - User certs are ordered by fingerprint.
+//! - Per-email exports are ordered by email address, then by fingerprint.
+//! - Informational timestamps are truncated to whole seconds.
+//!
+//! Keyrings can be compressed, and split into chunks of certs (see
+//! [export_certring_chunked]).
+
+use std::collections::BTreeSet;
 use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::str::FromStr;
 
 use anyhow::{Context, Result};
+use base64::{engine::general_purpose, Engine};
+use chrono::{DateTime, SubsecRound, Utc};
 use openpgp_keylist::{Key, Keylist, Metadata};
+use sequoia_openpgp::armor;
+use sequoia_openpgp::cert::amalgamation::ValidAmalgamation;
+use sequoia_openpgp::cert::CertParser;
+use sequoia_openpgp::crypto::mpi;
+use sequoia_openpgp::packet::UserID;
+use sequoia_openpgp::parse::Parse;
+use sequoia_openpgp::policy::Policy;
+use sequoia_openpgp::serialize::{Serialize as _, SerializeInto};
+use sequoia_openpgp::types::{Curve, RevocationStatus};
+use sequoia_openpgp::{Cert, Packet};
+use serde::Serialize;
 
+use crate::db::models;
+use crate::groups;
 use crate::pgp;
+use crate::progress;
+use crate::revocation;
+use crate::trust_package;
+use crate::types::{
+    CertFormat, ChunkedExportManifest, ExportChannel, ExportChunk, ExportCompat, ExportCompression,
+    ExportRejection, KeylistConfig, KeylistFilter, ProgressOperation, WotEdge, WotEdgeKind,
+    WotGraph, WotGraphFormat, WotNode, WotNodeKind,
+};
 use crate::Oca;
 
 // export filename of keylist
-const KEYLIST_FILE: &str = "keylist.json";
+pub(crate) const KEYLIST_FILE: &str = "keylist.json";
+
+// Version identifier of the revocation list format, to be incremented when the JSON format
+// changes in an incompatible way.
+const REVOCATION_LIST_VERSION: u32 = 1;
+
+// Version identifier of the chunked export index format
+const CHUNKED_EXPORT_VERSION: u32 = 1;
+
+// File name of the index of a chunked export
+const CHUNKED_EXPORT_INDEX: &str = "index.json";
 
-/// Write all Certs to stdout as one armored certring (or a subset of certs,
-/// filtered by User ID via email)
-pub fn print_certring(oca: &Oca, email_filter: Option<String>) -> Result<()> {
-    // Load all user-certs (optionally filtered by email)
-    let certs = match &email_filter {
+/// User certs (optionally filtered by User ID via email), ordered by fingerprint
+fn user_certs_sorted(oca: &Oca, email_filter: Option<&str>) -> Result<Vec<models::Cert>> {
+    let mut certs = match email_filter {
         Some(email) => oca.certs_by_email(email)?,
         None => oca.user_certs_get_all()?,
     };
 
+    certs.sort_by(|a, b| a.fingerprint.cmp(&b.fingerprint));
+
+    Ok(certs)
+}
+
+/// Minimal version of the user cert `cert` for publication: only the User IDs
+/// for which `keep_uid` returns true, with only the CA's certifications, and
+/// only currently valid subkeys.
+fn minimal_cert(oca: &Oca, cert: &Cert, keep_uid: impl Fn(&UserID) -> bool) -> Result<Cert> {
+    let ca = oca.ca_get_cert_pub()?;
+    let policy = oca.policy()?;
+
+    let certifications: Vec<_> = cert
+        .userids()
+        .filter(|uid| keep_uid(uid.userid()))
+        .flat_map(|uid| pgp::valid_certifications_by(&uid, cert, ca.clone(), &policy))
+        .collect();
+
+    pgp::minimize_cert(cert, keep_uid, &certifications, true)
+}
+
+/// User IDs with the address `email`
+fn is_email(uid: &UserID, email: &str) -> bool {
+    matches!(uid.email2(), Ok(Some(e)) if e == email)
+}
+
+/// The user certs (optionally filtered by User ID via email) for a
+/// certring export, ordered by fingerprint.
+///
+/// If `minimize` is set, user certs only contain the User IDs that are
+/// certified by the CA (or the User ID for the email filter).
+fn user_certs_for_export(
+    oca: &Oca,
+    email_filter: Option<&str>,
+    minimize: bool,
+    compat: ExportCompat,
+) -> Result<Vec<Cert>> {
+    certs_for_export(
+        oca,
+        user_certs_sorted(oca, email_filter)?,
+        email_filter,
+        minimize,
+        compat,
+    )
+}
+
+/// The certs `db_certs` for a certring export (see [user_certs_for_export]),
+/// without certs that are delisted on certrings
+fn certs_for_export(
+    oca: &Oca,
+    db_certs: Vec<models::Cert>,
+    email_filter: Option<&str>,
+    minimize: bool,
+    compat: ExportCompat,
+) -> Result<Vec<Cert>> {
+    let mut c = Vec::new();
+
+    for db_cert in db_certs {
+        if db_cert.is_delisted(ExportChannel::Certring) {
+            continue;
+        }
+
+        let cert = pgp::cert_for_export(oca.storage.cert_parsed(&db_cert)?, compat)?;
+
+        if minimize || compat.minimize() {
+            let minimal = match email_filter {
+                Some(email) => minimal_cert(oca, &cert, |uid| is_email(uid, email))?,
+                None => {
+                    let certified = oca.cert_check_ca_sig(&db_cert)?.certified;
+                    minimal_cert(oca, &cert, |uid| certified.contains(uid))?
+                }
+            };
+            c.push(minimal);
+        } else {
+            c.push(cert);
+        }
+    }
+
+    Ok(c)
+}
+
+/// The CA cert (if no email filter is set) and the user certs, as one
+/// certring
+fn certring(
+    oca: &Oca,
+    email_filter: Option<&str>,
+    minimize: bool,
+    compat: ExportCompat,
+) -> Result<Vec<Cert>> {
     let mut c = Vec::new();
 
     // add CA cert if no filter has been set
     if email_filter.is_none() {
-        c.push(oca.ca_get_cert_pub()?);
+        c.push(pgp::cert_for_export(oca.ca_get_cert_pub()?, compat)?);
+    }
+
+    c.extend(user_certs_for_export(oca, email_filter, minimize, compat)?);
+
+    Ok(c)
+}
+
+/// All Certs as one certring, serialized in `format` (or a subset of
+/// certs, filtered by User ID via email)
+///
+/// If `minimize` is set, user certs only contain the User IDs that are
+/// certified by the CA (or the User ID for the email filter).
+///
+/// The certs are adjusted to the quirks of the client `compat`.
+pub fn serialize_certring(
+    oca: &Oca,
+    email_filter: Option<&str>,
+    minimize: bool,
+    format: CertFormat,
+    compat: ExportCompat,
+) -> Result<Vec<u8>> {
+    let c = certring(oca, email_filter, minimize, compat)?;
+
+    match format {
+        CertFormat::Armored => Ok(pgp::certs_to_armored(&c)?.into_bytes()),
+        CertFormat::Binary => pgp::certs_to_binary(&c),
     }
+}
+
+/// Write all Certs (or a subset of certs, filtered by User ID via email) as
+/// one certring in `format` to the file `path`, with `compression`.
+pub fn export_certring(
+    oca: &Oca,
+    path: &Path,
+    email_filter: Option<&str>,
+    minimize: bool,
+    format: CertFormat,
+    compat: ExportCompat,
+    compression: ExportCompression,
+) -> Result<()> {
+    let c = certring(oca, email_filter, minimize, compat)?;
+    let data = compression.compress(&pgp::certs_serialize(&c, format)?)?;
+
+    std::fs::write(path, data).context(format!("Failed to write {}", path.display()))
+}
+
+/// Write the CA cert and the user certs `db_certs` (e.g. the certs of the
+/// members of a group) as one certring in `format` to the file `path`, with
+/// `compression`.
+pub(crate) fn export_certring_of(
+    oca: &Oca,
+    path: &Path,
+    mut db_certs: Vec<models::Cert>,
+    minimize: bool,
+    format: CertFormat,
+    compat: ExportCompat,
+    compression: ExportCompression,
+) -> Result<()> {
+    db_certs.sort_by(|a, b| a.fingerprint.cmp(&b.fingerprint));
+
+    let mut c = vec![pgp::cert_for_export(oca.ca_get_cert_pub()?, compat)?];
+    c.extend(certs_for_export(oca, db_certs, None, minimize, compat)?);
+
+    let data = compression.compress(&pgp::certs_serialize(&c, format)?)?;
+
+    std::fs::write(path, data).context(format!("Failed to write {}", path.display()))
+}
+
+/// Number of leading fingerprint bits that determine the chunk of a cert,
+/// for `count` certs with (on average) at most `chunk_size` certs per chunk
+fn chunk_bits(count: usize, chunk_size: usize) -> u32 {
+    let chunks = count.div_ceil(chunk_size).max(1);
+
+    chunks.next_power_of_two().trailing_zeros()
+}
+
+/// The chunk of the cert `fp` (a hex fingerprint), by its leading `bits`
+fn chunk_of(fp: &str, bits: u32) -> Result<usize> {
+    if bits == 0 {
+        return Ok(0);
+    }
+
+    let head = fp
+        .get(..8)
+        .ok_or_else(|| anyhow::anyhow!("Unexpected fingerprint {fp}"))?;
+    let head = u32::from_str_radix(head, 16)?;
+
+    Ok((head >> (32 - bits)) as usize)
+}
+
+/// Write `data` to `path`, unless the file already has this content (so
+/// that unchanged files keep their modification time)
+fn write_if_changed(path: &Path, data: &[u8]) -> Result<()> {
+    if std::fs::read(path).ok().as_deref() != Some(data) {
+        std::fs::write(path, data).context(format!("Failed to write {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Export the CA cert and all user certs into the directory `dir`: the
+/// user certs are split into chunks of (on average) at most `chunk_size`
+/// certs, in `format`, with `compression`.
+///
+/// Certs are assigned to chunks by the leading bits of their fingerprint.
+/// Chunks with unchanged certs are byte-for-byte identical between runs,
+/// until the number of certs crosses a power of two multiple of
+/// `chunk_size` (then the number of chunks changes, and all chunks are
+/// reassigned).
+///
+/// An index of the chunks is written to "index.json". Chunk files from
+/// previous exports that are not part of this export are removed.
+pub fn export_certring_chunked(
+    oca: &Oca,
+    dir: &Path,
+    chunk_size: usize,
+    minimize: bool,
+    format: CertFormat,
+    compat: ExportCompat,
+    compression: ExportCompression,
+) -> Result<ChunkedExportManifest> {
+    if chunk_size == 0 {
+        return Err(anyhow::anyhow!("The chunk size must be at least 1"));
+    }
+
+    std::fs::create_dir_all(dir)?;
+
+    let ext = format!("{}{}", format.extension(), compression.suffix());
+
+    let ca = pgp::cert_for_export(oca.ca_get_cert_pub()?, compat)?;
+    let ca_file = format!("ca.{ext}");
+    write_if_changed(
+        &dir.join(&ca_file),
+        &compression.compress(&pgp::certs_serialize(std::slice::from_ref(&ca), format)?)?,
+    )?;
+
+    let certs = user_certs_for_export(oca, None, minimize, compat)?;
+    let bits = chunk_bits(certs.len(), chunk_size);
+
+    let mut chunked: Vec<Vec<Cert>> = vec![vec![]; 1 << bits];
+    for cert in certs {
+        let i = chunk_of(&cert.fingerprint().to_hex(), bits)?;
+        chunked[i].push(cert);
+    }
+
+    let mut chunks = vec![];
+    for (i, certs) in chunked.iter().enumerate() {
+        let path = format!("chunk-{}-{i:05}.{ext}", chunked.len());
+        let data = compression.compress(&pgp::certs_serialize(certs, format)?)?;
+        write_if_changed(&dir.join(&path), &data)?;
+
+        chunks.push(ExportChunk {
+            path,
+            sha256: trust_package::sha256_hex(&data),
+            fingerprints: certs.iter().map(|c| c.fingerprint().to_hex()).collect(),
+        });
+    }
+
+    // Remove stale chunks of previous exports
+    let current: BTreeSet<_> = chunks.iter().map(|c| c.path.as_str()).collect();
+    for entry in std::fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        if let Some(name) = name.to_str() {
+            if name.starts_with("chunk-") && !current.contains(name) {
+                std::fs::remove_file(dir.join(name))?;
+            }
+        }
+    }
+
+    let manifest = ChunkedExportManifest {
+        format: CHUNKED_EXPORT_VERSION,
+        created: Utc::now().trunc_subsecs(0),
+        ca_fingerprint: ca.fingerprint().to_hex(),
+        ca_file,
+        chunks,
+    };
+    std::fs::write(
+        dir.join(CHUNKED_EXPORT_INDEX),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+
+    Ok(manifest)
+}
+
+/// Get all user certs (optionally filtered by User ID via email) that can be used for
+/// encryption right now.
+///
+/// Each cert is evaluated under the CA's policy at the current time. Certs that are
+/// not valid, not alive, revoked or lack a live encryption-capable subkey are returned
+/// separately, with the reason for their exclusion.
+pub fn certs_for_encryption(
+    oca: &Oca,
+    email_filter: Option<String>,
+) -> Result<(Vec<Cert>, Vec<ExportRejection>)> {
+    let certs = user_certs_sorted(oca, email_filter.as_deref())?;
+    let policy = oca.policy()?;
+
+    let mut usable = vec![];
+    let mut rejected = vec![];
 
     for cert in certs {
-        c.push(pgp::to_cert(cert.pub_cert.as_bytes())?);
+        let c = oca.storage.cert_parsed(&cert)?;
+
+        match encryption_check(&c, &policy) {
+            Ok(()) => usable.push(c),
+            Err(reason) => rejected.push(ExportRejection {
+                fingerprint: cert.fingerprint,
+                reason,
+            }),
+        }
+    }
+
+    Ok((usable, rejected))
+}
+
+/// Check if `cert` can be used for encryption now (under `policy`). If not,
+/// return the reason.
+fn encryption_check(cert: &Cert, policy: &dyn Policy) -> std::result::Result<(), String> {
+    let valid = cert
+        .with_policy(policy, None)
+        .map_err(|e| format!("Not valid under the policy: {e}"))?;
+
+    if let RevocationStatus::Revoked(_) = valid.revocation_status() {
+        return Err("Revoked".to_string());
     }
 
-    println!("{}", pgp::certs_to_armored(&c)?);
+    valid.alive().map_err(|e| format!("Not alive: {e}"))?;
+
+    if valid
+        .keys()
+        .alive()
+        .revoked(false)
+        .for_transport_encryption()
+        .for_storage_encryption()
+        .next()
+        .is_none()
+    {
+        return Err("No valid encryption-capable subkey".to_string());
+    }
 
     Ok(())
 }
 
 /// Export Certs to filesystem, as individual files split and named by email.
 /// (Optionally: filter by User ID via list of emails)
-pub fn export_certs_as_files(oca: &Oca, email_filter: Option<String>, path: &str) -> Result<()> {
+///
+/// If `minimize` is set, the certs in each file only contain the User ID for
+/// that file's email.
+///
+/// The files are written in `format` (and named "<email>.asc" or
+/// "<email>.pgp", accordingly). The certs are adjusted to the quirks of the
+/// client `compat`.
+pub fn export_certs_as_files(
+    oca: &Oca,
+    email_filter: Option<String>,
+    path: &str,
+    minimize: bool,
+    format: CertFormat,
+    compat: ExportCompat,
+) -> Result<()> {
+    let ext = format.extension();
+
     // export CA cert
     if email_filter.is_none() {
         // add CA cert to output
-        let ca_cert = oca.ca_get_cert_pub()?;
+        let ca_cert = pgp::cert_for_export(oca.ca_get_cert_pub()?, compat)?;
 
         std::fs::write(
-            path_append(path, &format!("{}.asc", &oca.get_ca_email()?))?,
-            pgp::certs_to_armored(&[ca_cert])?,
+            path_append(path, &format!("{}.{ext}", &oca.get_ca_email()?))?,
+            pgp::certs_serialize(&[ca_cert], format)?,
         )?;
     }
 
-    let emails = if let Some(email) = email_filter {
-        vec![email]
+    let export_all = email_filter.is_none();
+
+    let emails: BTreeSet<String> = if let Some(email) = email_filter {
+        [email].into()
     } else {
         oca.get_emails_all()?
             .iter()
@@ -69,19 +458,46 @@ pub fn export_certs_as_files(oca: &Oca, email_filter: Option<String>, path: &str
     };
 
     for email in &emails {
-        let certs = oca
-            .certs_by_email(email)
+        let certs = user_certs_sorted(oca, Some(email))
             .context(format!("Failed to load certs for email '{email}'"))?;
 
         if !certs.is_empty() {
             let mut c: Vec<_> = vec![];
             for cert in certs {
-                c.push(pgp::to_cert(cert.pub_cert.as_bytes())?);
+                let cert = pgp::cert_for_export(oca.storage.cert_parsed(&cert)?, compat)?;
+
+                if minimize || compat.minimize() {
+                    c.push(minimal_cert(oca, &cert, |uid| is_email(uid, email))?);
+                } else {
+                    c.push(cert);
+                }
             }
 
             std::fs::write(
-                path_append(path, &format!("{email}.asc"))?,
-                pgp::certs_to_armored(&c)?,
+                path_append(path, &format!("{email}.{ext}"))?,
+                pgp::certs_serialize(&c, format)?,
+            )?;
+        }
+    }
+
+    // Certs without email addresses (e.g. service account or role keys)
+    // are exported under their fingerprint
+    if export_all {
+        for db_cert in user_certs_sorted(oca, None)? {
+            if !oca.storage.emails_by_cert(&db_cert)?.is_empty() {
+                continue;
+            }
+
+            let mut cert = pgp::cert_for_export(oca.storage.cert_parsed(&db_cert)?, compat)?;
+            if minimize || compat.minimize() {
+                cert = minimal_cert(oca, &cert, |uid| {
+                    pgp::uid_role(uid).is_some() || pgp::uid_uri(uid).is_some()
+                })?;
+            }
+
+            std::fs::write(
+                path_append(path, &format!("{}.{ext}", db_cert.fingerprint))?,
+                pgp::certs_serialize(&[cert], format)?,
             )?;
         }
     }
@@ -119,53 +535,589 @@ fn path_append(path: &str, filename: &str) -> Result<PathBuf> {
     }
 }
 
+// --------- certificate chain
+
+/// A keyring for external verification of signatures by the user with
+/// `email`: the user's currently valid cert with only the User ID for `email`
+/// and its CA certification, followed by the CA cert (without third-party
+/// signatures).
+///
+/// The keyring can be checked with [pgp::verify_chain].
+pub fn export_chain(oca: &Oca, email: &str) -> Result<String> {
+    let cert = oca
+        .lookup_valid_cert(email)?
+        .ok_or_else(|| anyhow::anyhow!("No valid, certified cert for {} found", email))?;
+
+    let ca = oca.ca_get_cert_pub()?;
+    let policy = oca.policy()?;
+
+    let certifications: Vec<_> = cert
+        .userids()
+        .filter(|uid| is_email(uid.userid(), email))
+        .flat_map(|uid| pgp::valid_certifications_by(&uid, &cert, ca.clone(), &policy))
+        .collect();
+
+    let user = pgp::minimize_cert(&cert, |uid| is_email(uid, email), &certifications, false)?;
+    let ca = pgp::minimize_cert(&ca, |_| true, &[], false)?;
+
+    let headers = vec![
+        ("Comment", format!("Certificate chain for <{email}>")),
+        ("Comment", format!("User key: {}", user.fingerprint())),
+        (
+            "Comment",
+            format!("Certified by CA key: {}", ca.fingerprint()),
+        ),
+    ];
+
+    let mut writer = armor::Writer::with_headers(Vec::new(), armor::Kind::PublicKey, headers)?;
+    user.export(&mut writer)?;
+    ca.export(&mut writer)?;
+
+    Ok(String::from_utf8(writer.finalize()?)?)
+}
+
+// --------- GnuPG
+
+/// Run `gpg` with the homedir `home`, feeding `input` on stdin
+fn gpg_run(home: &Path, args: &[&str], input: &[u8]) -> Result<()> {
+    let mut child = Command::new("gpg")
+        .arg("--homedir")
+        .arg(home)
+        .args(["--batch", "--quiet", "--no-tty"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run gpg. Is GnuPG installed?")?;
+
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(input)?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "gpg {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Import the CA cert and all user certs into the GnuPG homedir `path`
+/// (which is created, if it doesn't exist), and set "ultimate" ownertrust
+/// for the CA cert.
+///
+/// GnuPG then considers the User IDs that are certified by the CA as valid.
+pub fn export_to_gnupg_home(oca: &Oca, path: &Path) -> Result<()> {
+    if !path.exists() {
+        std::fs::create_dir_all(path)?;
+
+        // GnuPG warns about homedirs that are accessible by other users
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o700))?;
+        }
+    }
+
+    let ca = oca.ca_get_cert_pub()?;
+
+    let mut certs = vec![pgp::cert_for_export(ca.clone(), ExportCompat::GnuPG22)?];
+    for db_cert in user_certs_sorted(oca, None)? {
+        let cert = oca.storage.cert_parsed(&db_cert)?;
+        certs.push(pgp::cert_for_export(cert, ExportCompat::GnuPG22)?);
+    }
+
+    gpg_run(path, &["--import"], &pgp::certs_to_binary(&certs)?)?;
+
+    // Trust level 6 is "ultimate"
+    let ownertrust = format!("{}:6:\n", ca.fingerprint().to_hex());
+    gpg_run(path, &["--import-ownertrust"], ownertrust.as_bytes())?;
+
+    Ok(())
+}
+
 // --------- wkd
 
-pub fn wkd_export(oca: &Oca, domain: &str, path: &Path) -> Result<()> {
+/// Export the CA cert and all user certs with User IDs in `domain` into a WKD
+/// directory structure in `path`.
+///
+/// If `minimize` is set, user certs only contain their User IDs in `domain`.
+///
+/// The certs are adjusted to the quirks of the client `compat`.
+///
+/// Certs that are delisted on WKD, and the certs of users who opted out of
+/// WKD publication, are skipped. If `emails` is set, only User IDs with one of these
+/// addresses are exported.
+pub fn wkd_export(
+    oca: &Oca,
+    domain: &str,
+    path: &Path,
+    minimize: bool,
+    compat: ExportCompat,
+    emails: Option<&[&str]>,
+) -> Result<()> {
     use sequoia_net::wkd;
 
-    let ca_cert = oca.ca_get_cert_pub()?;
+    let ca_cert = pgp::cert_for_export(oca.ca_get_cert_pub()?, compat)?;
     wkd::insert(path, domain, None, &ca_cert)?;
 
-    for cert in oca.user_certs_get_all()? {
-        // Don't export to WKD if the cert is marked "delisted"
-        if !cert.delisted {
-            let c = pgp::to_cert(cert.pub_cert.as_bytes())?;
-
-            if pgp::cert_has_uid_in_domain(&c, domain)? {
-                if let Err(err) = wkd::insert(path, domain, None, &c) {
-                    // FIXME 1: wkd::import should accept a policy
-                    // FIXME 2: if there are still errors, don't print them here.
-                    // Any warning information should be returned to the caller.
-                    println!("WARN: skipped cert {} ({})", c.fingerprint(), err);
-                }
+    let emails: Option<Vec<String>> =
+        emails.map(|emails| emails.iter().map(|e| e.to_lowercase()).collect());
+    let in_filter = |uid: &UserID| match &emails {
+        Some(emails) => uid
+            .email_normalized()
+            .ok()
+            .flatten()
+            .is_some_and(|e| emails.contains(&e)),
+        None => true,
+    };
+
+    // Certs for the same email address are added to the same file, in order
+    let certs = user_certs_sorted(oca, None)?;
+    for (i, cert) in certs.iter().enumerate() {
+        progress::report(
+            oca,
+            ProgressOperation::WkdExport,
+            i,
+            certs.len(),
+            Some(&cert.fingerprint),
+        );
+
+        // Don't export to WKD if the cert is delisted on WKD
+        if cert.is_delisted(ExportChannel::Wkd) {
+            continue;
+        }
+
+        // Don't export the certs of users who opted out of WKD publication
+        if let Some(user) = oca.storage.user_by_cert(cert)? {
+            if !user.wkd_publish {
+                continue;
+            }
+        }
+
+        let mut c = pgp::cert_for_export(oca.storage.cert_parsed(cert)?, compat)?;
+
+        // With a filter, only the User IDs for the listed addresses are published
+        if emails.is_some() {
+            if !c.userids().any(|uid| in_filter(uid.userid())) {
+                continue;
+            }
+            c = c.retain_userids(|uid| in_filter(uid.userid()));
+        }
+
+        if pgp::cert_has_uid_in_domain(&c, domain)? {
+            if minimize || compat.minimize() {
+                c = minimal_cert(oca, &c, |uid| {
+                    pgp::uid_in_domain(uid, domain).unwrap_or(false)
+                })?;
+            }
+
+            // Machine identities (User IDs with URIs) are not published via WKD
+            c = c.retain_userids(|uid| pgp::uid_uri(uid.userid()).is_none());
+
+            if let Err(err) = wkd::insert(path, domain, None, &c) {
+                // FIXME: wkd::import should accept a policy
+                tracing::warn!(fingerprint = %c.fingerprint(), "Skipped cert in WKD export: {}", err);
             }
         }
     }
 
-    Ok(())
+    progress::report(
+        oca,
+        ProgressOperation::WkdExport,
+        certs.len(),
+        certs.len(),
+        None,
+    );
+
+    wkd_sort_files(&path.join(".well-known/openpgpkey").join(domain).join("hu"))
 }
 
-// --------- keylist
+/// Export into a WKD directory structure in `path`, like [wkd_export], but
+/// only write files whose content has changed. Unchanged files (and their
+/// modification times) are left alone, so that publication pipelines (e.g.
+/// based on rsync) only transfer what changed.
+///
+/// The WKD is generated from scratch: changed files are replaced, and not
+/// merged with their previous content.
+///
+/// Returns the written files, relative to `path`, in lexical order.
+pub fn wkd_export_changed(
+    oca: &Oca,
+    domain: &str,
+    path: &Path,
+    minimize: bool,
+    compat: ExportCompat,
+    emails: Option<&[&str]>,
+) -> Result<Vec<PathBuf>> {
+    let staging = tempfile::tempdir()?;
+    wkd_export(oca, domain, staging.path(), minimize, compat, emails)?;
 
-pub fn export_keylist(
+    let mut written = vec![];
+    copy_changed(staging.path(), path, Path::new(""), &mut written)?;
+
+    Ok(written)
+}
+
+/// Copy the files in `from`/`rel` to `to`/`rel` (recursively), if their
+/// content differs. Adds the paths of copied files to `written`.
+fn copy_changed(from: &Path, to: &Path, rel: &Path, written: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = std::fs::read_dir(from.join(rel))?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    std::fs::create_dir_all(to.join(rel))?;
+
+    for entry in entries {
+        let rel = rel.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            copy_changed(from, to, &rel, written)?;
+        } else {
+            let data = std::fs::read(entry.path())?;
+            let target = to.join(&rel);
+
+            if std::fs::read(&target).ok().as_deref() != Some(&data[..]) {
+                std::fs::write(&target, &data)?;
+                written.push(rel);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Export into a WKD directory structure, like [wkd_export], and return it
+/// as a tar archive (e.g. for publication pipelines that fetch the WKD over
+/// the network).
+///
+/// The files are stored in lexical order, with paths relative to the root of
+/// the WKD directory structure (".well-known/openpgpkey/...").
+pub fn wkd_export_tar(
     oca: &Oca,
-    path: PathBuf,
-    signature_uri: String,
-    overwrite: bool,
-) -> Result<()> {
-    // Use last part of signature_uri as filename for sigfile
-    let sigfile_name = match signature_uri.split('/').last() {
-        Some(file) => file,
-        None => {
-            return Err(anyhow::anyhow!("Unexpected signature_uri format"));
+    domain: &str,
+    minimize: bool,
+    compat: ExportCompat,
+    emails: Option<&[&str]>,
+) -> Result<Vec<u8>> {
+    let staging = tempfile::tempdir()?;
+    wkd_export(oca, domain, staging.path(), minimize, compat, emails)?;
+
+    let mut files = vec![];
+    collect_files(staging.path(), Path::new(""), &mut files)?;
+
+    let mtime = Utc::now().timestamp() as u64;
+
+    let mut tar = vec![];
+    for (rel, data) in files {
+        let path = rel
+            .to_str()
+            .context("Unexpected non-UTF-8 path in WKD export")?;
+        trust_package::tar_append(&mut tar, path, &data, mtime)?;
+    }
+    trust_package::tar_finish(&mut tar);
+
+    Ok(tar)
+}
+
+/// Add the files in `dir`/`rel` (recursively, in lexical order) to `files`,
+/// with their paths relative to `dir`
+fn collect_files(dir: &Path, rel: &Path, files: &mut Vec<(PathBuf, Vec<u8>)>) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir.join(rel))?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let rel = rel.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            collect_files(dir, &rel, files)?;
+        } else {
+            files.push((rel, std::fs::read(entry.path())?));
+        }
+    }
+
+    Ok(())
+}
+
+/// sequoia_net::wkd::insert writes the certs in a WKD file in arbitrary
+/// order. Rewrite each file in `hu_dir` with its certs ordered by fingerprint.
+fn wkd_sort_files(hu_dir: &Path) -> Result<()> {
+    for entry in std::fs::read_dir(hu_dir)? {
+        let file = entry?.path();
+
+        let mut certs =
+            CertParser::from_file(&file)?.collect::<sequoia_openpgp::Result<Vec<_>>>()?;
+        certs.sort_by_key(|c| c.fingerprint().to_hex());
+
+        let mut out = File::create(&file)?;
+        for cert in certs {
+            cert.export(&mut out)?;
+        }
+    }
+
+    Ok(())
+}
+
+// --------- autocrypt
+
+/// The Autocrypt attributes ("addr=...; keydata=...") for the currently valid,
+/// CA-certified cert of `email`, for use in "Autocrypt" and "Autocrypt-Gossip"
+/// headers.
+///
+/// <https://autocrypt.org/level1.html#the-autocrypt-header>
+pub fn export_autocrypt(oca: &Oca, email: &str) -> Result<String> {
+    let cert = oca
+        .lookup_valid_cert(email)?
+        .ok_or_else(|| anyhow::anyhow!("No valid, certified cert for {} found", email))?;
+
+    let minimal = autocrypt_minimal_cert(&cert, email)?;
+    let keydata = general_purpose::STANDARD.encode(minimal.to_vec()?);
+
+    Ok(format!("addr={email}; keydata={keydata}"))
+}
+
+/// Reduce `cert` to the packets that Autocrypt recommends: the primary key,
+/// the User ID for `email`, and one encryption subkey (each with its current
+/// self-signature).
+fn autocrypt_minimal_cert(cert: &Cert, email: &str) -> Result<Cert> {
+    let valid = cert.with_policy(pgp::SP, None)?;
+
+    let uid = valid
+        .userids()
+        .find(|uid| matches!(uid.email2(), Ok(Some(e)) if e == email))
+        .ok_or_else(|| anyhow::anyhow!("No valid User ID for {} found", email))?;
+
+    let subkey = valid
+        .keys()
+        .subkeys()
+        .alive()
+        .revoked(false)
+        .for_transport_encryption()
+        .max_by_key(|ka| ka.creation_time())
+        .ok_or_else(|| anyhow::anyhow!("No valid encryption-capable subkey found"))?;
+
+    let packets: Vec<Packet> = vec![
+        valid.primary_key().key().clone().into(),
+        uid.userid().clone().into(),
+        uid.binding_signature().clone().into(),
+        subkey.key().clone().into(),
+        subkey.binding_signature().clone().into(),
+    ];
+
+    Cert::from_packets(packets.into_iter())
+}
+
+// --------- SSH
+
+/// The live authentication subkeys of the currently valid, CA-certified cert
+/// of `email`, as lines in OpenSSH "authorized_keys" format (one per subkey,
+/// ordered by creation time).
+///
+/// Subkeys with algorithms that have no SSH equivalent are skipped.
+pub fn export_ssh_keys(oca: &Oca, email: &str) -> Result<String> {
+    let cert = oca
+        .lookup_valid_cert(email)?
+        .ok_or_else(|| anyhow::anyhow!("No valid, certified cert for {} found", email))?;
+
+    let policy = oca.policy()?;
+    let valid = cert.with_policy(&policy, None)?;
+
+    let mut subkeys: Vec<_> = valid
+        .keys()
+        .subkeys()
+        .alive()
+        .revoked(false)
+        .for_authentication()
+        .collect();
+    subkeys.sort_by_key(|ka| ka.creation_time());
+
+    let mut lines = String::new();
+    for ka in subkeys {
+        if let Some((alg, blob)) = ssh_public_key(ka.key().mpis())? {
+            // Same comment as "gpg --export-ssh-key"
+            lines.push_str(&format!(
+                "{} {} openpgp:0x{}\n",
+                alg,
+                general_purpose::STANDARD.encode(blob),
+                ka.key().keyid().to_hex()
+            ));
+        }
+    }
+
+    Ok(lines)
+}
+
+/// The SSH algorithm name and public key blob (RFC 4253, 5656, 8709) for the
+/// OpenPGP public key material `mpis` (or None, for unsupported algorithms).
+fn ssh_public_key(mpis: &mpi::PublicKey) -> Result<Option<(&'static str, Vec<u8>)>> {
+    fn string(out: &mut Vec<u8>, data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(data);
+    }
+
+    fn mpint(out: &mut Vec<u8>, value: &[u8]) {
+        // positive values with the high bit set get a leading zero byte
+        if value.first().map_or(false, |b| b & 0x80 != 0) {
+            string(out, &[&[0], value].concat());
+        } else {
+            string(out, value);
         }
+    }
+
+    let mut blob = Vec::new();
+
+    let alg = match mpis {
+        mpi::PublicKey::RSA { e, n } => {
+            string(&mut blob, b"ssh-rsa");
+            mpint(&mut blob, e.value());
+            mpint(&mut blob, n.value());
+            "ssh-rsa"
+        }
+        mpi::PublicKey::EdDSA {
+            curve: Curve::Ed25519,
+            q,
+        } => {
+            let (x, _) = q.decode_point(&Curve::Ed25519)?;
+            string(&mut blob, b"ssh-ed25519");
+            string(&mut blob, x);
+            "ssh-ed25519"
+        }
+        mpi::PublicKey::ECDSA { curve, q } => {
+            let (alg, name) = match curve {
+                Curve::NistP256 => ("ecdsa-sha2-nistp256", "nistp256"),
+                Curve::NistP384 => ("ecdsa-sha2-nistp384", "nistp384"),
+                Curve::NistP521 => ("ecdsa-sha2-nistp521", "nistp521"),
+                _ => return Ok(None),
+            };
+            string(&mut blob, alg.as_bytes());
+            string(&mut blob, name.as_bytes());
+            // uncompressed point (0x04 || x || y), as in OpenPGP
+            string(&mut blob, q.value());
+            alg
+        }
+        _ => return Ok(None),
     };
 
+    Ok(Some((alg, blob)))
+}
+
+// --------- keylist
+
+const PREF_KEYLISTS: &str = "keylists";
+
+pub(crate) fn keylists(oca: &Oca) -> Result<Vec<KeylistConfig>> {
+    match oca.storage.pref(PREF_KEYLISTS)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(vec![]),
+    }
+}
+
+fn keylists_store(oca: &Oca, lists: &[KeylistConfig]) -> Result<()> {
+    let json = serde_json::to_string(lists)?;
+    oca.storage.pref_set(PREF_KEYLISTS, &json)
+}
+
+/// Add the keylist `config`, or replace the keylist of the same name
+pub(crate) fn keylist_set(oca: &Oca, config: KeylistConfig) -> Result<()> {
+    let valid_name = !config.name.is_empty()
+        && config
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        return Err(anyhow::anyhow!(
+            "Keylist names may only contain ASCII letters, digits, '-' and '_'"
+        ));
+    }
+    keylist_sigfile_name(&config.signature_uri)?;
+    if let Some(group) = &config.filter.group {
+        groups::group(oca, group)?;
+    }
+
+    let mut lists = keylists(oca)?;
+    lists.retain(|l| l.name != config.name);
+    lists.push(config);
+    lists.sort_by(|a, b| a.name.cmp(&b.name));
+
+    keylists_store(oca, &lists)
+}
+
+pub(crate) fn keylist_remove(oca: &Oca, name: &str) -> Result<()> {
+    let mut lists = keylists(oca)?;
+
+    let len = lists.len();
+    lists.retain(|l| l.name != name);
+    if lists.len() == len {
+        return Err(anyhow::anyhow!("Keylist '{}' not found", name));
+    }
+
+    keylists_store(oca, &lists)
+}
+
+/// Last part of `signature_uri`, used as the filename of the signature file
+fn keylist_sigfile_name(signature_uri: &str) -> Result<&str> {
+    match signature_uri.split('/').last() {
+        Some(file) if !file.is_empty() => Ok(file),
+        _ => Err(anyhow::anyhow!("Unexpected signature_uri format")),
+    }
+}
+
+/// Does `filter` allow the User ID with `email` on `cert`?
+fn keylist_filter_matches(filter: &KeylistFilter, cert: &models::Cert, email: &str) -> bool {
+    if filter.exclude_inactive && cert.inactive {
+        return false;
+    }
+
+    if !filter.emails.is_empty() && !filter.emails.iter().any(|e| e.eq_ignore_ascii_case(email)) {
+        return false;
+    }
+
+    if !filter.domains.is_empty() {
+        let domain = email.rsplit('@').next().unwrap_or_default();
+        if !filter
+            .domains
+            .iter()
+            .any(|d| d.eq_ignore_ascii_case(domain))
+        {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Does `filter` allow role User IDs (without email address) on `cert`?
+///
+/// Role User IDs are only listed if the filter opts in, and isn't
+/// restricted to emails or domains.
+fn keylist_role_matches(filter: &KeylistFilter, cert: &models::Cert) -> bool {
+    filter.include_roles
+        && filter.emails.is_empty()
+        && filter.domains.is_empty()
+        && !(filter.exclude_inactive && cert.inactive)
+}
+
+/// The keylist with `signature_uri`, containing the CA cert and the
+/// CA-certified User IDs that match `filter`.
+///
+/// Returns the keylist, the name of its signature file, and the signature.
+pub(crate) fn signed_keylist(
+    oca: &Oca,
+    signature_uri: &str,
+    filter: &KeylistFilter,
+) -> Result<(String, String, String)> {
+    let sigfile_name = keylist_sigfile_name(signature_uri)?;
+
     // Start populating new Keylist with metadata
     let mut ukl = Keylist {
         metadata: Metadata {
-            signature_uri: signature_uri.clone(),
+            signature_uri: signature_uri.to_string(),
             keyserver: None,
             comment: Some("Exported from OpenPGP CA".to_string()),
         },
@@ -183,37 +1135,341 @@ pub fn export_keylist(
         keyserver: None,
     });
 
+    // With a group filter, only the certs of the group's members are listed
+    let members: Option<BTreeSet<i32>> = match &filter.group {
+        Some(group) => Some(
+            groups::group_users(oca, group)?
+                .iter()
+                .map(|u| u.id)
+                .collect(),
+        ),
+        None => None,
+    };
+
     // .. and add all user certs that were certified by this CA.
+    let mut user_keys = vec![];
     for user in &oca.users_get_all()? {
+        if members.as_ref().is_some_and(|m| !m.contains(&user.id)) {
+            continue;
+        }
+
         for cert in oca.get_certs_by_user(user)? {
+            // Certs that are delisted on keylists are not published
+            if cert.is_delisted(ExportChannel::Keylist) {
+                continue;
+            }
+
             // Create Keylist entry for each User ID that the CA has certified
             for uid in oca.cert_check_ca_sig(&cert)?.certified {
                 if let Ok(Some(email)) = uid.email2() {
-                    ukl.keys.push(Key {
+                    if !keylist_filter_matches(filter, &cert, email) {
+                        continue;
+                    }
+
+                    user_keys.push(Key {
                         fingerprint: cert.fingerprint.clone(),
                         name: user.name.clone(),
                         email: Some(email.to_string()),
                         comment: None,
                         keyserver: None,
                     });
+                } else if let Some(role) = pgp::uid_role(&uid) {
+                    if !keylist_role_matches(filter, &cert) {
+                        continue;
+                    }
+
+                    user_keys.push(Key {
+                        fingerprint: cert.fingerprint.clone(),
+                        name: Some(role.to_string()),
+                        email: None,
+                        comment: None,
+                        keyserver: None,
+                    });
                 }
             }
         }
     }
 
+    user_keys.sort_by(|a, b| (&a.email, &a.fingerprint).cmp(&(&b.email, &b.fingerprint)));
+    ukl.keys.append(&mut user_keys);
+
     let signer = Box::new(|text: &str| oca.secret().sign_detached(text.as_bytes()));
 
     // Make a signed list object
     let skl = ukl.sign(signer)?;
 
+    Ok((skl.keylist, sigfile_name.to_string(), skl.sig))
+}
+
+/// Write the keylist with `signature_uri`, containing the CA cert and the
+/// CA-certified User IDs that match `filter`, into `path`
+fn write_keylist(
+    oca: &Oca,
+    path: &Path,
+    signature_uri: &str,
+    filter: &KeylistFilter,
+    overwrite: bool,
+) -> Result<()> {
+    let (keylist, sigfile_name, sig) = signed_keylist(oca, signature_uri, filter)?;
+
     // Write keylist and signature to the filesystem
-    let mut keylist = path.clone();
-    keylist.push(KEYLIST_FILE);
-    open_file(keylist, overwrite)?.write_all(skl.keylist.as_bytes())?;
+    open_file(path.join(KEYLIST_FILE), overwrite)?.write_all(keylist.as_bytes())?;
+    open_file(path.join(sigfile_name), overwrite)?.write_all(sig.as_bytes())?;
+
+    Ok(())
+}
+
+pub fn export_keylist(
+    oca: &Oca,
+    path: PathBuf,
+    signature_uri: String,
+    overwrite: bool,
+) -> Result<()> {
+    write_keylist(
+        oca,
+        &path,
+        &signature_uri,
+        &KeylistFilter::default(),
+        overwrite,
+    )
+}
+
+/// Export the keylist with `signature_uri` for the members of `group` into
+/// `path` (see [export_keylist])
+pub(crate) fn export_group_keylist(
+    oca: &Oca,
+    group: &str,
+    path: &Path,
+    signature_uri: &str,
+    overwrite: bool,
+) -> Result<()> {
+    let filter = KeylistFilter {
+        group: Some(group.to_string()),
+        ..Default::default()
+    };
+
+    write_keylist(oca, path, signature_uri, &filter, overwrite)
+}
+
+/// Export all configured keylists, each into a subdirectory of `path`
+/// (named after the list). Returns the names of the exported lists.
+pub(crate) fn export_keylists(oca: &Oca, path: &Path, overwrite: bool) -> Result<Vec<String>> {
+    let lists = keylists(oca)?;
+    if lists.is_empty() {
+        return Err(anyhow::anyhow!("No keylists are configured"));
+    }
+
+    let mut exported = vec![];
+    for list in lists {
+        let dir = path.join(&list.name);
+        std::fs::create_dir_all(&dir)?;
+
+        write_keylist(oca, &dir, &list.signature_uri, &list.filter, overwrite)
+            .with_context(|| format!("Failed to export keylist '{}'", list.name))?;
+
+        exported.push(list.name);
+    }
+
+    Ok(exported)
+}
+
+// --------- revocation list
+
+#[derive(Serialize, Debug)]
+struct RevocationList {
+    version: u32,
+    ca_fingerprint: String,
+    created: DateTime<Utc>,
+    revoked: Vec<RevokedCert>,
+}
+
+#[derive(Serialize, Debug)]
+struct RevokedCert {
+    fingerprint: String,
+    revocation_time: Option<DateTime<Utc>>,
+    reason: String,
+}
 
-    let mut sigfile = path;
-    sigfile.push(sigfile_name);
-    open_file(sigfile, overwrite)?.write_all(skl.sig.as_bytes())?;
+/// Write a timestamped list of all revoked user certs to `path`, and a detached
+/// CA signature over that list to `path` with an added ".sig" suffix.
+///
+/// A cert is considered revoked if our copy of it is revoked under the CA's policy
+/// (that is: revocations that have been applied).
+pub fn export_revocation_list(oca: &Oca, path: &Path) -> Result<()> {
+    let policy = oca.policy()?;
+    let mut revoked = vec![];
+
+    for cert in user_certs_sorted(oca, None)? {
+        let c = oca.storage.cert_parsed(&cert)?;
+
+        if let Some((revocation_time, reason)) = revocation::effective_revocation(&c, &policy) {
+            revoked.push(RevokedCert {
+                fingerprint: cert.fingerprint.clone(),
+                revocation_time,
+                reason,
+            });
+        }
+    }
+
+    let list = RevocationList {
+        version: REVOCATION_LIST_VERSION,
+        ca_fingerprint: oca.ca_get_cert_pub()?.fingerprint().to_hex(),
+        created: Utc::now().trunc_subsecs(0),
+        revoked,
+    };
+
+    let text = serde_json::to_string_pretty(&list)?;
+    let sig = oca.secret().sign_detached(text.as_bytes())?;
+
+    std::fs::write(path, &text)?;
+
+    let mut sigfile = path.as_os_str().to_owned();
+    sigfile.push(".sig");
+    std::fs::write(sigfile, sig)?;
 
     Ok(())
 }
+
+/// The trust topology of the CA (see [WotGraph]).
+///
+/// Nodes are ordered: the CA first, then user certs, then remote CAs (each
+/// ordered by fingerprint). Inactive user certs are omitted.
+pub fn wot_graph(oca: &Oca) -> Result<WotGraph> {
+    let ca = oca.ca_get_cert_pub()?;
+    let ca_fp = ca.fingerprint().to_hex();
+
+    let mut nodes = vec![WotNode {
+        fingerprint: ca_fp.clone(),
+        kind: WotNodeKind::Ca,
+        label: oca.get_ca_userid()?.to_string(),
+    }];
+    let mut edges = vec![];
+
+    for cert in user_certs_sorted(oca, None)? {
+        if cert.inactive {
+            continue;
+        }
+
+        let emails: Vec<_> = oca.emails_get(&cert)?.into_iter().map(|e| e.addr).collect();
+
+        nodes.push(WotNode {
+            fingerprint: cert.fingerprint.clone(),
+            kind: WotNodeKind::User,
+            label: format!("{} ({})", oca.cert_get_name(&cert)?, emails.join(", ")),
+        });
+
+        if !oca.cert_check_ca_sig(&cert)?.certified.is_empty() {
+            edges.push(WotEdge {
+                from: ca_fp.clone(),
+                to: cert.fingerprint.clone(),
+                kind: WotEdgeKind::Certification,
+                scope: vec![],
+            });
+        }
+
+        if oca.cert_check_tsig_on_ca(&cert)? {
+            edges.push(WotEdge {
+                from: cert.fingerprint.clone(),
+                to: ca_fp.clone(),
+                kind: WotEdgeKind::Tsig,
+                scope: vec![],
+            });
+        }
+    }
+
+    let mut remote = vec![];
+    for bridge in oca.bridges_get()? {
+        let cert = oca.bridge_get_cert(&bridge)?;
+        remote.push((bridge, cert));
+    }
+    remote.sort_by(|(_, a), (_, b)| a.fingerprint.cmp(&b.fingerprint));
+
+    for (bridge, cert) in remote {
+        nodes.push(WotNode {
+            fingerprint: cert.fingerprint.clone(),
+            kind: WotNodeKind::RemoteCa,
+            label: bridge.email.clone(),
+        });
+
+        // The CA's tsig on the remote CA, with its scope
+        let c = oca.storage.cert_parsed(&cert)?;
+        let tsigs: Vec<_> = pgp::get_trust_sigs(&c)?
+            .into_iter()
+            .filter(|s| s.issuer_fingerprints().any(|fp| fp == &ca.fingerprint()))
+            .collect();
+
+        if let Some(tsig) = tsigs.iter().max_by_key(|s| s.signature_creation_time()) {
+            edges.push(WotEdge {
+                from: ca_fp.clone(),
+                to: cert.fingerprint.clone(),
+                kind: WotEdgeKind::Tsig,
+                scope: tsig
+                    .regular_expressions()
+                    .map(|r| String::from_utf8_lossy(r).to_string())
+                    .collect(),
+            });
+        }
+
+        // The remote CA's tsig on the CA (if the bridge is mutual)
+        if oca.cert_check_tsig_on_ca(&cert)? {
+            edges.push(WotEdge {
+                from: cert.fingerprint.clone(),
+                to: ca_fp.clone(),
+                kind: WotEdgeKind::Tsig,
+                scope: vec![],
+            });
+        }
+    }
+
+    Ok(WotGraph { nodes, edges })
+}
+
+/// Quote `s` as a DOT ID
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The trust topology of the CA (see [wot_graph]), in `format`
+pub fn export_wot_graph(oca: &Oca, format: WotGraphFormat) -> Result<String> {
+    let graph = wot_graph(oca)?;
+
+    match format {
+        WotGraphFormat::Json => Ok(serde_json::to_string_pretty(&graph)?),
+        WotGraphFormat::Dot => {
+            let mut dot = String::from("digraph wot {\n");
+
+            for node in &graph.nodes {
+                let shape = match node.kind {
+                    WotNodeKind::Ca => "doubleoctagon",
+                    WotNodeKind::User => "ellipse",
+                    WotNodeKind::RemoteCa => "octagon",
+                };
+
+                dot.push_str(&format!(
+                    "    {} [label={}, shape={shape}];\n",
+                    dot_quote(&node.fingerprint),
+                    dot_quote(&format!("{}\n{}", node.label, node.fingerprint)),
+                ));
+            }
+
+            for edge in &graph.edges {
+                let label = match edge.kind {
+                    WotEdgeKind::Certification => "certification".to_string(),
+                    WotEdgeKind::Tsig if edge.scope.is_empty() => "tsig".to_string(),
+                    WotEdgeKind::Tsig => format!("tsig {}", edge.scope.join(" ")),
+                };
+
+                dot.push_str(&format!(
+                    "    {} -> {} [label={}];\n",
+                    dot_quote(&edge.from),
+                    dot_quote(&edge.to),
+                    dot_quote(&label),
+                ));
+            }
+
+            dot.push_str("}\n");
+
+            Ok(dot)
+        }
+    }
+}
@@ -0,0 +1,122 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Federation metadata: a signed document that describes a CA to partner
+//! CAs, and can be used to bootstrap bridges.
+
+use anyhow::{Context, Result};
+use chrono::{SubsecRound, Utc};
+use sequoia_openpgp::Cert;
+
+use crate::pgp;
+use crate::types::{FederationMetadata, SignedFederationMetadata};
+use crate::Oca;
+
+/// Version of the schema of [FederationMetadata]
+const METADATA_VERSION: u32 = 1;
+
+/// Location of the metadata document, relative to the web root of a domain
+pub(crate) const WELL_KNOWN_PATH: &str = ".well-known/openpgp-ca.json";
+
+/// Generate the signed federation metadata document for this CA.
+///
+/// The CA's own domain is always listed first in the document.
+pub(crate) fn metadata(
+    oca: &Oca,
+    domains: &[&str],
+    keylist_url: Option<&str>,
+    wkd_method: Option<&str>,
+    contact: Option<&str>,
+) -> Result<String> {
+    if let Some(method) = wkd_method {
+        if method != "advanced" && method != "direct" {
+            return Err(anyhow::anyhow!(
+                "Unknown WKD method '{method}', expected 'advanced' or 'direct'"
+            ));
+        }
+    }
+
+    let mut all_domains = vec![oca.domainname().to_string()];
+    for d in domains {
+        if !all_domains.iter().any(|a| a.eq_ignore_ascii_case(d)) {
+            all_domains.push(d.to_string());
+        }
+    }
+
+    let metadata = FederationMetadata {
+        version: METADATA_VERSION,
+        domains: all_domains,
+        keylist_url: keylist_url.map(ToString::to_string),
+        wkd_method: wkd_method.map(ToString::to_string),
+        contact: contact.map(ToString::to_string),
+        ca_cert: oca.ca_get_pubkey_armored()?,
+        created: Utc::now().trunc_subsecs(0),
+    };
+
+    let metadata = serde_json::to_string(&metadata)?;
+    let signature = oca.secret().sign_detached(metadata.as_bytes())?;
+
+    Ok(serde_json::to_string_pretty(&SignedFederationMetadata {
+        metadata,
+        signature,
+    })?)
+}
+
+/// Check a signed federation metadata document.
+///
+/// Checks that the document is signed by the CA cert that it contains, and
+/// that this CA cert belongs to one of the listed domains.
+/// If `fingerprint` is set, the CA cert must have that fingerprint.
+///
+/// Note that the document is self-signed: the operator must confirm the
+/// fingerprint of the remote CA through a trusted channel.
+pub(crate) fn verify(doc: &[u8], fingerprint: Option<&str>) -> Result<(FederationMetadata, Cert)> {
+    let signed: SignedFederationMetadata =
+        serde_json::from_slice(doc).context("Couldn't parse federation metadata document")?;
+
+    let metadata: FederationMetadata = serde_json::from_str(&signed.metadata)
+        .context("Couldn't parse federation metadata document")?;
+
+    if metadata.version != METADATA_VERSION {
+        return Err(anyhow::anyhow!(
+            "Unsupported federation metadata version {}",
+            metadata.version
+        ));
+    }
+
+    let ca_cert = pgp::to_cert(metadata.ca_cert.as_bytes())?;
+
+    pgp::verify_detached(&ca_cert, signed.metadata.as_bytes(), &signed.signature)
+        .context("Federation metadata is not signed by the CA key it contains")?;
+
+    if let Some(fp) = fingerprint {
+        if pgp::normalize_fp(fp)? != ca_cert.fingerprint().to_hex() {
+            return Err(anyhow::anyhow!(
+                "The CA key in the federation metadata has the fingerprint {}, expected {}",
+                ca_cert.fingerprint(),
+                fp
+            ));
+        }
+    }
+
+    let in_domains = ca_cert.userids().any(|uid| {
+        if let Ok(Some(email)) = uid.userid().email2() {
+            metadata
+                .domains
+                .iter()
+                .any(|d| email.eq_ignore_ascii_case(&format!("openpgp-ca@{d}")))
+        } else {
+            false
+        }
+    });
+    if !in_domains {
+        return Err(anyhow::anyhow!(
+            "The CA key in the federation metadata doesn't belong to any of its domains"
+        ));
+    }
+
+    Ok((metadata, ca_cert))
+}
@@ -0,0 +1,158 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Named groups of users (e.g. departments).
+//!
+//! Certification, re-certification, exports and notifications can be scoped
+//! to a group, so that an organization can roll out such operations one
+//! group at a time, instead of acting on all users at once.
+
+use anyhow::Result;
+
+use crate::cert;
+use crate::db::models;
+use crate::types::{CertificationProfile, CertificationValidity, GroupNotification};
+use crate::Oca;
+
+/// Check that `name` is a legal group name
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(anyhow::anyhow!(
+            "Group names may only contain ASCII letters, digits, '-' and '_'"
+        ));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn group_new(oca: &Oca, name: &str, description: Option<&str>) -> Result<models::Group> {
+    check_name(name)?;
+    oca.storage.group_new(name, description)
+}
+
+/// The group `name` (fails if it doesn't exist)
+pub(crate) fn group(oca: &Oca, name: &str) -> Result<models::Group> {
+    oca.storage
+        .group_by_name(name)?
+        .ok_or_else(|| anyhow::anyhow!("Group '{}' not found", name))
+}
+
+pub(crate) fn group_users(oca: &Oca, name: &str) -> Result<Vec<models::User>> {
+    let group = group(oca, name)?;
+    oca.storage.group_users(&group)
+}
+
+/// The certs of the users in the group `name`, ordered by fingerprint
+pub(crate) fn group_certs(oca: &Oca, name: &str) -> Result<Vec<models::Cert>> {
+    let mut certs = vec![];
+    for user in group_users(oca, name)? {
+        certs.extend(oca.storage.certs_by_user(&user)?);
+    }
+
+    certs.sort_by(|a, b| a.fingerprint.cmp(&b.fingerprint));
+
+    Ok(certs)
+}
+
+/// The email addresses of the certs of the users in the group `name`
+/// (sorted)
+pub(crate) fn group_emails(oca: &Oca, name: &str) -> Result<Vec<String>> {
+    let mut emails = vec![];
+    for cert in group_certs(oca, name)? {
+        emails.extend(
+            oca.storage
+                .emails_by_cert(&cert)?
+                .into_iter()
+                .map(|e| e.addr),
+        );
+    }
+
+    emails.sort();
+    emails.dedup();
+
+    Ok(emails)
+}
+
+/// Certify the User IDs of the active certs in the group `name` that carry
+/// one of the cert's email addresses, and are not yet certified by the CA.
+///
+/// Returns the certs that got new certifications.
+pub(crate) fn group_certify(
+    oca: &Oca,
+    name: &str,
+    validity: Option<CertificationValidity>,
+) -> Result<Vec<models::Cert>> {
+    let mut certified = vec![];
+
+    for db_cert in group_certs(oca, name)?.into_iter().filter(|c| !c.inactive) {
+        let emails: Vec<String> = oca
+            .storage
+            .emails_by_cert(&db_cert)?
+            .into_iter()
+            .map(|e| e.addr.to_lowercase())
+            .collect();
+
+        let missing: Vec<String> = oca
+            .cert_check_ca_sig(&db_cert)?
+            .uncertified
+            .iter()
+            .filter_map(|uid| uid.email_normalized().ok().flatten())
+            .filter(|e| emails.contains(e))
+            .collect();
+
+        if missing.is_empty() {
+            continue;
+        }
+
+        let missing: Vec<&str> = missing.iter().map(String::as_str).collect();
+        cert::cert_certify(
+            oca,
+            &db_cert.fingerprint,
+            &missing,
+            validity,
+            CertificationProfile::Default,
+        )?;
+
+        certified.push(db_cert);
+    }
+
+    Ok(certified)
+}
+
+pub(crate) fn group_refresh_ca_certifications(
+    oca: &Oca,
+    name: &str,
+    threshold_days: u64,
+    validity: CertificationValidity,
+) -> Result<()> {
+    cert::refresh_ca_certifications(oca, group_certs(oca, name)?, threshold_days, validity)
+}
+
+/// A notification email with `text` for the users in the group `name`,
+/// addressed to the CA-certified email addresses of their active certs
+pub(crate) fn group_notification(oca: &Oca, name: &str, text: &str) -> Result<GroupNotification> {
+    let mut recipients = vec![];
+    for db_cert in group_certs(oca, name)?.into_iter().filter(|c| !c.inactive) {
+        recipients.extend(
+            oca.cert_check_ca_sig(&db_cert)?
+                .certified
+                .iter()
+                .filter_map(|uid| uid.email_normalized().ok().flatten()),
+        );
+    }
+
+    recipients.sort();
+    recipients.dedup();
+
+    Ok(GroupNotification {
+        recipients,
+        mail: oca.mail_text(text)?,
+    })
+}
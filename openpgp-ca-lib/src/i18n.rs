@@ -0,0 +1,1252 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! A small message catalog for user-facing output: the reports of the
+//! openpgp-ca CLI, and the explanatory text in generated artifacts (such as
+//! the CA revocation file).
+//!
+//! Messages are identified by a [Msg], and may contain named placeholders
+//! (e.g. `{email}`) that are filled in by [tr].
+//!
+//! The language is configured per CA, and can be overridden for one
+//! invocation with the environment variable `OPENPGP_CA_LANG`. Without
+//! configuration, output is in English.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+
+use crate::Oca;
+
+const PREF_LANGUAGE: &str = "language";
+
+/// Environment variable that overrides the language of a CA
+pub const ENV_LANGUAGE: &str = "OPENPGP_CA_LANG";
+
+/// A language that OpenPGP CA has a message catalog for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    /// ISO 639-1 code of the language
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+        }
+    }
+
+    /// The language set in the environment variable [ENV_LANGUAGE], if any
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var(ENV_LANGUAGE) {
+            Ok(lang) if !lang.is_empty() => Ok(Some(lang.parse()?)),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for Language {
+    type Err = anyhow::Error;
+
+    /// Parse a language code ("de"), or a locale name ("de_DE.UTF-8")
+    fn from_str(s: &str) -> Result<Self> {
+        let code = s
+            .split(['_', '-', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match Language::ALL.iter().find(|l| l.code() == code) {
+            Some(lang) => Ok(*lang),
+            None => Err(anyhow::anyhow!(
+                "Unsupported language '{}' (supported: {})",
+                s,
+                Language::ALL.map(|l| l.code()).join(", ")
+            )),
+        }
+    }
+}
+
+/// Identifiers of the messages in the catalog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    // Revocation file for the CA key (see `Oca::ca_generate_revocations`)
+    RevocationsIntro,
+    RevocationsExplanation,
+    RevocationsCaCert,
+    RevocationsFollow,
+    RevocationCommentHard,
+    RevocationCommentSoft,
+
+    // CLI reports
+    RevocationsWritten,
+    Confirm,
+    Initialized,
+    Warning,
+    ImportStrict,
+    ImportedCertifications,
+    Purged,
+    PurgePreview,
+    PurgeUser,
+    PurgeEmail,
+    PurgeRevocations,
+    PurgeForce,
+
+    // CLI reports: CA administration
+    CardInit,
+    CardGeneratedKey,
+    CardInitFromCard,
+    CardUserPinPrompt,
+    CardInitImport,
+    CardGenerateOnCard,
+    CardMigrate,
+    CardMigrated,
+    BackendChanged,
+    KeyPolicyMinRsaBits,
+    KeyPolicyAllowedAlgorithms,
+    KeyPolicyRequireEncryptionSubkey,
+    KeyPolicyRequireSigningSubkey,
+    KeyPolicyMaxExpiry,
+    KeyPolicyMaxSubkeyAge,
+    BlocklistAdded,
+    BlocklistRemoved,
+    BlocklistLogAdded,
+    BlocklistLogRemoved,
+    CtLogIntact,
+    NotationShow,
+    NotationNone,
+    CryptoPolicySha1Cutoff,
+    CryptoPolicyRejectedHashes,
+    CryptoPolicyRejectedAlgorithms,
+    StatsPreview,
+    StatsSigned,
+    StatsConfirm,
+    StatsSent,
+    TorProxy,
+    TorDirect,
+    Keyserver,
+    KeyserverQuorum,
+    RevocationPublicationDisabled,
+    RevocationPublicationWkd,
+    CertificationKeySelected,
+    CertificationKeyAll,
+    DoctorReleased,
+    DoctorChecked,
+    DoctorConsistent,
+    DoctorBlobsOk,
+    DoctorBlob,
+    BlobRecoverable,
+    BlobRepaired,
+    BlobIrrecoverable,
+    BlobQuarantined,
+    DoctorQuarantined,
+    DoctorQuarantinedRow,
+    Retention,
+    RetentionQueueDone,
+    RetentionCertVersions,
+    CleanupQueuePreview,
+    CleanupVersionsPreview,
+    CleanupQueue,
+    CleanupVersions,
+    FederationWritten,
+    TrustPackageWritten,
+    TrustPackage,
+    EventsTopicPrefix,
+    EventsNatsServer,
+    EventsAmqpBroker,
+    EventsAmqpExchange,
+    RekeyReplaced,
+    RekeyTsigNeeded,
+    TimelineEmpty,
+    FingerprintMatches,
+    CardOnCardNote,
+    CaInfoDomain,
+    CaInfoFingerprint,
+    CaInfoCreated,
+    CaInfoBackend,
+
+    // CLI reports: split mode
+    SplitExportEmpty,
+    SplitExported,
+    SplitImported,
+    SplitPushed,
+    SplitRequestCertification,
+    SplitRequestBridge,
+    SplitRequestKey,
+    SplitRequestUserId,
+    SplitRequestValidDays,
+    SplitRequestValidUntil,
+    SplitRequestNoExpiration,
+    SplitRequestProfile,
+    SplitRequestNotation,
+    SplitRequestRemoteKey,
+    SplitRequestUnscoped,
+    SplitRequestScope,
+    SplitCertifyPrompt,
+    SplitCertifySkipped,
+
+    // CLI reports: users and keys
+    UserCardCreated,
+    SubkeyRotationDue,
+    SubkeyRotationNoSubkey,
+    Recertified,
+    CertificationExpires,
+    RenewFailed,
+    RenewPreview,
+    RenewQueued,
+    Renewed,
+    ExportSkipped,
+    ExportedChunks,
+    TagUser,
+    SearchMatched,
+    WkdPublishDisabled,
+    WkdPublishEnabled,
+    Listed,
+    Delisted,
+    Reassigned,
+    NoUser,
+    ReplacementStored,
+    OffboardAlreadyRevoked,
+    OffboardApplied,
+    OffboardNoRevocation,
+    OffboardRetracted,
+    Offboarded,
+    VersionReplacedBy,
+    AsOfMissing,
+    AsOfVersion,
+    AsOfCurrent,
+    AsOfRevoked,
+    AsOfNotCertified,
+    AsOfCertified,
+    AsOfIncomplete,
+    UserKeyCreatedFor,
+    UserKeyCreated,
+    UserKeyWritten,
+    UserKeyPassword,
+    UserKeyNoPassword,
+
+    // CLI reports: key details
+    PrimaryKey,
+    Subkey,
+    KeyAlgorithm,
+    KeyFlags,
+    KeyCreated,
+    KeyExpires,
+    Revoked,
+    NoReason,
+    PolicyRejected,
+    UserId,
+    NotUtf8,
+    DossierUser,
+    DossierWkdOptOut,
+    DossierNotes,
+    DossierTag,
+    DossierKey,
+    DossierDelisted,
+    DossierDelistedOn,
+    DossierDeactivated,
+    DossierTsig,
+    DossierNoTsig,
+    DossierCertificationExpired,
+    DossierCertified,
+    DossierNotCertified,
+    UnknownTime,
+    DossierRevocationApplied,
+    DossierRevocationPending,
+    PublicationFailed,
+    Published,
+
+    // CLI reports: bridges
+    BridgeAdded,
+    BridgeFingerprint,
+    BridgeDryRun,
+    BridgeVerify,
+    BridgeCommit,
+    BridgeMetadata,
+    BridgeMetadataDomains,
+    BridgeMetadataKeylist,
+    BridgeMetadataWkd,
+    BridgeMetadataContact,
+    BridgeRevocation,
+    BridgeTsigImported,
+
+    // CLI reports: WKD and keylists
+    WkdUpdated,
+    KeylistExported,
+    KeylistSignatureUri,
+    KeylistDomains,
+    KeylistEmails,
+    KeylistExcludesInactive,
+    KeylistGroup,
+    KeylistIncludesRoles,
+
+    // CLI reports: groups
+    GroupSummary,
+    NoName,
+    GroupAlreadyMember,
+    GroupNotMember,
+    GroupCertified,
+
+    // CLI reports: key profiles
+    SubkeyEncryption,
+    SubkeySigning,
+    SubkeyAuthentication,
+    ProfileCipherSuite,
+    ProfileSubkeys,
+    ProfileExpiry,
+    ProfilePassword,
+    ProfilePasswordShortWords,
+    ProfileNoPassword,
+
+    // CLI reports: approval queue
+    ProposalApplied,
+
+    // CLI reports: keyserver and WKD updates
+    SyncFailingSince,
+    SyncLastSuccess,
+    SyncNeverSucceeded,
+    SyncLastError,
+    SyncReport,
+
+    // CLI reports: utilities
+    ChainVerified,
+
+    // Guided setup (`ca init --interactive`)
+    WizardYesNo,
+    WizardChoice,
+    WizardChooseListed,
+    WizardFileExists,
+    WizardNotAFile,
+    WizardUsingBlankCard,
+    WizardUsingMatchingCard,
+    WizardBlankCards,
+    WizardMatchingCards,
+    WizardCipherSuite,
+    WizardRecommended,
+    WizardCardMode,
+    WizardCardModeHost,
+    WizardCardModeOnCard,
+    WizardCardModeImport,
+    WizardCardModeFromCard,
+    WizardBackupNote,
+    WizardBackupFile,
+    WizardPrivateKeyFile,
+    WizardPublicKeyFile,
+    WizardDomain,
+    WizardName,
+    WizardBackend,
+    WizardBackendSoftkey,
+    WizardBackendCard,
+    WizardBackendSplit,
+    WizardBackOnCard,
+    WizardSplitNote,
+    WizardFrontFile,
+    WizardBackFile,
+    WizardRevocationsNote,
+    WizardRevocationsGenerate,
+    WizardRevocationsFile,
+    WizardSummary,
+    WizardSummaryDomain,
+    WizardSummaryName,
+    WizardSummaryKey,
+    WizardSummaryCipherSuite,
+    WizardSummaryRevocations,
+    WizardSummarySplit,
+    WizardKeySoftkey,
+    WizardKeyCard,
+    WizardModeHost,
+    WizardModeOnCard,
+    WizardModeImport,
+    WizardModeFromCard,
+    WizardRevocationsNone,
+    WizardSplitValue,
+    WizardBackupFailed,
+    WizardIntro,
+    WizardConfirm,
+    WizardRevocationsWritten,
+    WizardSplitDone,
+    WizardBackupReminder,
+}
+
+/// The English message for `msg`
+fn en(msg: Msg) -> &'static str {
+    match msg {
+        Msg::RevocationsIntro => {
+            "This file contains revocation certificates for the OpenPGP CA \n\
+             instance '{email}'."
+        }
+        Msg::RevocationsExplanation => {
+            "These revocations can be used to invalidate the CA's key.
+This is useful e.g. if the (private) CA key gets compromised (i.e. available
+to a third party), or when the CA key becomes inaccessible to you.
+
+CAUTION: This file needs to be kept safe from third parties who could use
+the revocations to adversarially invalidate your CA certificate!
+Keep in mind that an attacker can use these revocations to
+perform a denial of service attack on your CA at the most inconvenient
+moment. When a revocation certificate has been published for your CA, you
+will need to start over with a fresh CA key.
+
+Please store this file appropriately, to avoid it becoming accessible to
+adversaries."
+        }
+        Msg::RevocationsCaCert => "For reference, the certificate of your CA is",
+        Msg::RevocationsFollow => "Revocation certificates (ordered by 'creation time') follow:",
+        Msg::RevocationCommentHard => "Hard revocation (certificate compromised) ({date})",
+        Msg::RevocationCommentSoft => "Soft revocation (certificate retired) ({date})",
+
+        Msg::RevocationsWritten => "Wrote a set of revocations to the output file",
+        Msg::Confirm => "Are you sure? (type 'yes' to continue)",
+        Msg::Initialized => "Initialized OpenPGP CA instance:",
+        Msg::Warning => "Warning: {message}",
+        Msg::ImportStrict => "Not importing the key: {count} warning(s), and --strict is set",
+        Msg::ImportedCertifications => "Imported {count} certifications.",
+        Msg::Purged => "Purged key {fingerprint}.",
+        Msg::PurgePreview => "This would permanently delete the key {fingerprint}",
+        Msg::PurgeUser => "  user: {name}",
+        Msg::PurgeEmail => "  email: {email}",
+        Msg::PurgeRevocations => "  revocations: {count}",
+        Msg::PurgeForce => "Re-run with '--force' to delete it.",
+
+        Msg::CardInit => "Initializing OpenPGP CA on card {ident}.",
+        Msg::CardGeneratedKey => {
+            "Generated new CA key:
+
+{key}"
+        }
+        Msg::CardInitFromCard => {
+            "Initializing OpenPGP CA from pre-configured OpenPGP card {ident}."
+        }
+        Msg::CardUserPinPrompt => "Enter User PIN for OpenPGP card {ident}: ",
+        Msg::CardInitImport => "Initializing OpenPGP CA from existing key, on card {ident}.",
+        Msg::CardGenerateOnCard => "Generate new OpenPGP CA key on card {ident}.",
+        Msg::CardMigrate => {
+            "Migrating OpenPGP CA instance to card {ident}.
+
+Caution: After migration is performed, the CA private key material will not
+be available in the CA database anymore!
+
+Make sure you have a backup of your CA key before continuing!"
+        }
+        Msg::CardMigrated => "Migrated OpenPGP CA instance:",
+        Msg::BackendChanged => "CA backend configuration is changed.",
+        Msg::KeyPolicyMinRsaBits => "Minimum RSA bits",
+        Msg::KeyPolicyAllowedAlgorithms => "Allowed algorithms",
+        Msg::KeyPolicyRequireEncryptionSubkey => "Require encryption subkey",
+        Msg::KeyPolicyRequireSigningSubkey => "Require signing subkey",
+        Msg::KeyPolicyMaxExpiry => "Maximum expiry (days)",
+        Msg::KeyPolicyMaxSubkeyAge => "Max. subkey age (days)",
+        Msg::BlocklistAdded => "Added {kind} '{pattern}' to the blocklist.",
+        Msg::BlocklistRemoved => "Removed {kind} '{pattern}' from the blocklist.",
+        Msg::BlocklistLogAdded => "added",
+        Msg::BlocklistLogRemoved => "removed",
+        Msg::CtLogIntact => {
+            "The log is intact ({entries} entries).
+Head: {head}"
+        }
+        Msg::NotationShow => {
+            "Notation: {name}={value}
+Required: {required}"
+        }
+        Msg::NotationNone => "No notation is added to certifications",
+        Msg::CryptoPolicySha1Cutoff => "SHA-1 cutoff",
+        Msg::CryptoPolicyRejectedHashes => "Rejected hashes",
+        Msg::CryptoPolicyRejectedAlgorithms => "Rejected algorithms",
+        Msg::StatsPreview => "The following data will be sent to {endpoint}:",
+        Msg::StatsSigned => {
+            "The statistics are signed with the CA key, so the recipient can
+recognize repeated submissions from this CA."
+        }
+        Msg::StatsConfirm => "Send these statistics? (type 'yes' to continue)",
+        Msg::StatsSent => "Sent usage statistics to {endpoint}.",
+        Msg::TorProxy => "Network lookups are routed through Tor at {proxy}",
+        Msg::TorDirect => "Network lookups use direct connections",
+        Msg::Keyserver => "Keyserver: {keyserver}",
+        Msg::KeyserverQuorum => "Quorum: {quorum}",
+        Msg::RevocationPublicationDisabled => {
+            "Revoked keys are not published when revocations are applied"
+        }
+        Msg::RevocationPublicationWkd => "WKD: {path}",
+        Msg::CertificationKeySelected => "{fingerprint} (selected)",
+        Msg::CertificationKeyAll => "Certifications are issued with each of these keys.",
+        Msg::DoctorReleased => "Released cert row {cert_id} from quarantine.",
+        Msg::DoctorChecked => {
+            "Checked {certs} certs, {revocations} revocations, {queue_entries} pending queue \
+             entries."
+        }
+        Msg::DoctorConsistent => "No inconsistencies found.",
+        Msg::DoctorBlobsOk => "All certs can be parsed.",
+        Msg::DoctorBlob => "cert row {cert_id} ({fingerprint}): {error} [{outcome}]",
+        Msg::BlobRecoverable => "recoverable",
+        Msg::BlobRepaired => "repaired",
+        Msg::BlobIrrecoverable => "irrecoverable",
+        Msg::BlobQuarantined => "quarantined",
+        Msg::DoctorQuarantined => "Quarantined cert rows:",
+        Msg::DoctorQuarantinedRow => " {cert_id} (since {since}): {error}",
+        Msg::Retention => "max age (days): {max_age_days}, max count: {max_count}",
+        Msg::RetentionQueueDone => "Done queue entries",
+        Msg::RetentionCertVersions => "Cert versions",
+        Msg::CleanupQueuePreview => "Would remove {count} done queue entries.",
+        Msg::CleanupVersionsPreview => "Would remove {count} cert versions.",
+        Msg::CleanupQueue => "Removed {count} done queue entries.",
+        Msg::CleanupVersions => "Removed {count} cert versions.",
+        Msg::FederationWritten => "Wrote federation metadata to {path}",
+        Msg::TrustPackageWritten => "Wrote trust package version {version} to {path}",
+        Msg::TrustPackage => {
+            "Trust package version {version} of {domain} ({fingerprint}), created {created}:"
+        }
+        Msg::EventsTopicPrefix => "Topic prefix",
+        Msg::EventsNatsServer => "NATS server",
+        Msg::EventsAmqpBroker => "AMQP broker",
+        Msg::EventsAmqpExchange => "AMQP exchange",
+        Msg::RekeyReplaced => "Replaced CA key {old} with {new}",
+        Msg::RekeyTsigNeeded => "These users need to tsign the new CA cert:",
+        Msg::TimelineEmpty => "Nothing expires in the next {days} days.",
+        Msg::FingerprintMatches => "The fingerprint matches the key of this CA.",
+        Msg::CardOnCardNote => {
+            "Note:
+1) The private CA key will only exist on the card (you can't make a backup)
+2) The randomness your OpenPGP card generates could be worse than your host computer's"
+        }
+        Msg::CaInfoDomain => "    CA Domain: {domain}",
+        Msg::CaInfoFingerprint => "  Fingerprint: {fingerprint}",
+        Msg::CaInfoCreated => "Creation time: {created}",
+        Msg::CaInfoBackend => "   CA Backend: {backend}",
+
+        Msg::SplitExportEmpty => {
+            "The queue contains no requests for the back instance, didn't export."
+        }
+        Msg::SplitExported => {
+            "Exported queue with {count} entries for processing by the back instance"
+        }
+        Msg::SplitImported => "Imported {count} certifications from the back instance.",
+        Msg::SplitPushed => "Imported {count} certification(s) into the front instance.",
+        Msg::SplitRequestCertification => "Certification request [#{id}, queued {created}]",
+        Msg::SplitRequestBridge => "Bridging request [#{id}, queued {created}]",
+        Msg::SplitRequestKey => "  Key {fingerprint}",
+        Msg::SplitRequestUserId => "  User ID '{userid}'",
+        Msg::SplitRequestValidDays => "  Valid for {days} days",
+        Msg::SplitRequestValidUntil => "  Valid until {until}",
+        Msg::SplitRequestNoExpiration => "  No expiration",
+        Msg::SplitRequestProfile => "  Certification profile '{profile}'",
+        Msg::SplitRequestNotation => "  Notation {name}={value}",
+        Msg::SplitRequestRemoteKey => "  Remote CA key {fingerprint}",
+        Msg::SplitRequestUnscoped => "  Unscoped",
+        Msg::SplitRequestScope => "  Scope '{scope}'",
+        Msg::SplitCertifyPrompt => "Certify? [y/n]",
+        Msg::SplitCertifySkipped => "Skipping this queue entry",
+
+        Msg::UserCardCreated => {
+            "Created new user key {fingerprint} on card {ident}.
+
+User PIN:  {user_pin}
+Admin PIN: {admin_pin}"
+        }
+        Msg::SubkeyRotationDue => {
+            "name {name}, fingerprint {fingerprint}: rotate subkey {subkey} by {date}"
+        }
+        Msg::SubkeyRotationNoSubkey => {
+            "name {name}, fingerprint {fingerprint}: no valid encryption subkey"
+        }
+        Msg::Recertified => "Re-certified {fingerprint}",
+        Msg::CertificationExpires => "{fingerprint}{user}: '{userid}' expires {date}",
+        Msg::RenewFailed => "Failed to renew certifications on {fingerprint}: {error}",
+        Msg::RenewPreview => "{count} certifications would be renewed.",
+        Msg::RenewQueued => "{count} certifications were queued for the back instance.",
+        Msg::Renewed => "{count} certifications were renewed.",
+        Msg::ExportSkipped => "Skipped key {fingerprint}: {reason}",
+        Msg::ExportedChunks => "Exported {certs} keys in {chunks} chunks to {path}",
+        Msg::TagUser => "{name}={value} (User)",
+        Msg::SearchMatched => "(matched: {fields})",
+        Msg::WkdPublishDisabled => "The User of key {fingerprint} is not published via WKD.",
+        Msg::WkdPublishEnabled => "The User of key {fingerprint} is published via WKD.",
+        Msg::Listed => "Key {fingerprint} is listed on: {channels}",
+        Msg::Delisted => "Key {fingerprint} is delisted on: {channels}",
+        Msg::Reassigned => "Moved key {fingerprint} to user '{user}' (id {id}).",
+        Msg::NoUser => "no user",
+        Msg::ReplacementStored => "Stored replacement key {fingerprint}.",
+        Msg::OffboardAlreadyRevoked => "{fingerprint}: was already revoked",
+        Msg::OffboardApplied => "{fingerprint}: applied revocation {hash}",
+        Msg::OffboardNoRevocation => "{fingerprint}: no revocation on file",
+        Msg::OffboardRetracted => "  retracted certification of '{userid}'",
+        Msg::Offboarded => "All keys of {email} are deactivated and delisted.",
+        Msg::VersionReplacedBy => "replaced by: {origin}",
+        Msg::AsOfMissing => "The key was not in the CA at {time}",
+        Msg::AsOfVersion => "Version: {id}",
+        Msg::AsOfCurrent => "Version: current",
+        Msg::AsOfRevoked => "The key was revoked",
+        Msg::AsOfNotCertified => "No User ID was certified by the CA",
+        Msg::AsOfCertified => "Certified: {userid}",
+        Msg::AsOfIncomplete => {
+            "Warning: the version history of the key doesn't reach back to {time}, this state \
+             may be inaccurate"
+        }
+        Msg::UserKeyCreatedFor => "Created new user key for {name}.",
+        Msg::UserKeyCreated => "Created new user key.",
+        Msg::UserKeyWritten => "Wrote the private key to {path}.",
+        Msg::UserKeyPassword => "Password for this key: '{password}'.",
+        Msg::UserKeyNoPassword => "No password set for this key.",
+
+        Msg::PrimaryKey => "Primary key: {fingerprint}",
+        Msg::Subkey => "Subkey: {fingerprint}",
+        Msg::KeyAlgorithm => "  algorithm: {algorithm} ({bits} bits)",
+        Msg::KeyFlags => "  flags: {flags}",
+        Msg::KeyCreated => "  created: {time}",
+        Msg::KeyExpires => "  expires: {time}",
+        Msg::Revoked => "  revoked: {reason}",
+        Msg::NoReason => "no reason",
+        Msg::PolicyRejected => "  rejected by policy: {error}",
+        Msg::UserId => "User ID: {userid}",
+        Msg::NotUtf8 => "(not utf8)",
+        Msg::DossierUser => "User '{name}'",
+        Msg::DossierWkdOptOut => "  opted out of WKD publication",
+        Msg::DossierNotes => "  notes: {notes}",
+        Msg::DossierTag => "  tag {name}: {value}",
+        Msg::DossierKey => "Key {fingerprint}",
+        Msg::DossierDelisted => "  delisted (not exported)",
+        Msg::DossierDelistedOn => "  delisted on: {channels}",
+        Msg::DossierDeactivated => "  deactivated",
+        Msg::DossierTsig => "  has trust-signed the CA key",
+        Msg::DossierNoTsig => "  has not trust-signed the CA key",
+        Msg::DossierCertificationExpired => "  certification by the CA expired",
+        Msg::DossierCertified => "  certified by the CA",
+        Msg::DossierNotCertified => "  not certified by the CA",
+        Msg::UnknownTime => "unknown time",
+        Msg::DossierRevocationApplied => "Revocation {hash} (applied): {reason}, {time}",
+        Msg::DossierRevocationPending => "Revocation {hash} (not applied): {reason}, {time}",
+        Msg::PublicationFailed => "{channel}: publication failed: {error}",
+        Msg::Published => "{channel}: published {time}",
+
+        Msg::BridgeAdded => "Added OpenPGP key for {email} as bridge.",
+        Msg::BridgeFingerprint => {
+            "The fingerprint of the remote CA key is
+{fingerprint}"
+        }
+        Msg::BridgeDryRun => "Bridge creation DRY RUN.",
+        Msg::BridgeVerify => {
+            "Please verify that this is the correct fingerprint for the remote CA admin \
+             before continuing:"
+        }
+        Msg::BridgeCommit => {
+            "When you've confirmed that the remote key is correct, repeat this command with \
+             the additional parameter '--commit' to commit the OpenPGP CA bridge to the \
+             database."
+        }
+        Msg::BridgeMetadata => "Federation metadata of the remote CA:",
+        Msg::BridgeMetadataDomains => "  Domains: {domains}",
+        Msg::BridgeMetadataKeylist => "  Keylist: {url}",
+        Msg::BridgeMetadataWkd => "  WKD method: {method}",
+        Msg::BridgeMetadataContact => "  Contact: {contact}",
+        Msg::BridgeRevocation => "Revocation for the bridge to {email}:",
+        Msg::BridgeTsigImported => "Imported the trust signature by {email}.",
+
+        Msg::WkdUpdated => "Updated {path}",
+        Msg::KeylistExported => "Exported keylist '{name}'",
+        Msg::KeylistSignatureUri => "  Signature URI: {uri}",
+        Msg::KeylistDomains => "  Domains: {domains}",
+        Msg::KeylistEmails => "  Emails: {emails}",
+        Msg::KeylistExcludesInactive => "  Excludes inactive keys",
+        Msg::KeylistGroup => "  Group: {group}",
+        Msg::KeylistIncludesRoles => "  Includes role User IDs",
+
+        Msg::GroupSummary => "{name} ({count} users)",
+        Msg::NoName => "<no name>",
+        Msg::GroupAlreadyMember => "The user is already a member of '{name}'",
+        Msg::GroupNotMember => "The user is not a member of '{name}'",
+        Msg::GroupCertified => "Certified {fingerprint}",
+
+        Msg::SubkeyEncryption => "encryption",
+        Msg::SubkeySigning => "signing",
+        Msg::SubkeyAuthentication => "authentication",
+        Msg::ProfileCipherSuite => "  Cipher suite: {cipher_suite}",
+        Msg::ProfileSubkeys => "  Subkeys: {subkeys}",
+        Msg::ProfileExpiry => "  Expires after {days} days",
+        Msg::ProfilePassword => "  Password: {words} words",
+        Msg::ProfilePasswordShortWords => "  Password: {words} words (short word list)",
+        Msg::ProfileNoPassword => "  No password",
+
+        Msg::ProposalApplied => "Applied: {change}",
+
+        Msg::SyncFailingSince => "  failing since: {time}",
+        Msg::SyncLastSuccess => "  last success: {time}",
+        Msg::SyncNeverSucceeded => "  never succeeded",
+        Msg::SyncLastError => "  last error: {error}",
+        Msg::SyncReport => {
+            "Updated: {updated}, unchanged: {unchanged}, failed: {failed}, skipped: {skipped}"
+        }
+
+        Msg::ChainVerified => {
+            "The key {fingerprint} for <{email}> is certified by the CA key {ca_fingerprint}."
+        }
+
+        Msg::WizardYesNo => "Please answer 'y' or 'n'.",
+        Msg::WizardChoice => "Choice",
+        Msg::WizardChooseListed => "Please choose one of the listed options.",
+        Msg::WizardFileExists => "{path} already exists, please choose another file.",
+        Msg::WizardNotAFile => "{path} is not a file.",
+        Msg::WizardUsingBlankCard => "Using blank OpenPGP card {ident}.",
+        Msg::WizardUsingMatchingCard => "Using matching OpenPGP card {ident}.",
+        Msg::WizardBlankCards => "Multiple blank OpenPGP cards found:",
+        Msg::WizardMatchingCards => "Multiple matching OpenPGP cards found:",
+        Msg::WizardCipherSuite => "Cipher suite for the CA key:",
+        Msg::WizardRecommended => "{name} (recommended)",
+        Msg::WizardCardMode => "How should the CA key get onto the OpenPGP card?",
+        Msg::WizardCardModeHost => "Generate on this computer, keep a backup file (recommended)",
+        Msg::WizardCardModeOnCard => "Generate on the card (no backup possible!)",
+        Msg::WizardCardModeImport => "Import an existing CA private key file",
+        Msg::WizardCardModeFromCard => "Use a card that already holds the CA key",
+        Msg::WizardBackupNote => {
+            "The generated private key is written to a backup file.
+Store it offline, it is needed to replace a lost or broken card."
+        }
+        Msg::WizardBackupFile => "Backup file",
+        Msg::WizardPrivateKeyFile => "CA private key file",
+        Msg::WizardPublicKeyFile => "CA public key file",
+        Msg::WizardDomain => "CA domain name (e.g. example.org)",
+        Msg::WizardName => "Descriptive name for the CA (optional)",
+        Msg::WizardBackend => "Where should the CA private key be kept?",
+        Msg::WizardBackendSoftkey => "In the CA database file",
+        Msg::WizardBackendCard => "On an OpenPGP card",
+        Msg::WizardBackendSplit => "In a separate \"back\" database, ideally on an offline machine",
+        Msg::WizardBackOnCard => "Should the back instance use an OpenPGP card?",
+        Msg::WizardSplitNote => {
+            "The front instance handles day-to-day operations, without the CA key.
+The back instance holds the CA key and only signs certification requests."
+        }
+        Msg::WizardFrontFile => "Front database file",
+        Msg::WizardBackFile => "Back database file",
+        Msg::WizardRevocationsNote => {
+            "Revocation certificates for the CA key allow revoking it, even if the key is lost.
+Store them offline, and separately from the CA key."
+        }
+        Msg::WizardRevocationsGenerate => "Generate revocation certificates now?",
+        Msg::WizardRevocationsFile => "Revocations file",
+        Msg::WizardSummary => "Summary:",
+        Msg::WizardSummaryDomain => "Domain",
+        Msg::WizardSummaryName => "Name",
+        Msg::WizardSummaryKey => "CA key",
+        Msg::WizardSummaryCipherSuite => "Cipher suite",
+        Msg::WizardSummaryRevocations => "Revocations",
+        Msg::WizardSummarySplit => "Split mode",
+        Msg::WizardKeySoftkey => "in the database (softkey)",
+        Msg::WizardKeyCard => "on OpenPGP card {ident} ({mode})",
+        Msg::WizardModeHost => "generated on host, backup in {path}",
+        Msg::WizardModeOnCard => "generated on card, no backup",
+        Msg::WizardModeImport => "imported from {path}",
+        Msg::WizardModeFromCard => "already on card, public key from {path}",
+        Msg::WizardRevocationsNone => "not generated",
+        Msg::WizardSplitValue => "front {front}, back {back}",
+        Msg::WizardBackupFailed => {
+            "Failed to write the CA key backup ({error}), this is the CA key:
+
+{key}"
+        }
+        Msg::WizardIntro => {
+            "Guided setup of a new OpenPGP CA instance.
+Nothing is changed until you confirm the summary at the end."
+        }
+        Msg::WizardConfirm => "Initialize the CA with these settings? (type 'yes' to continue)",
+        Msg::WizardRevocationsWritten => "Wrote a set of revocations to {path}",
+        Msg::WizardSplitDone => {
+            "Split the CA into front instance {front} and back instance {back}.
+Move the back instance to an offline machine.
+
+Caution: the original CA database still contains the CA key (or card
+configuration). Delete it, or store it offline, as a backup."
+        }
+        Msg::WizardBackupReminder => "The CA private key backup is in {path}, store it offline!",
+    }
+}
+
+/// The German message for `msg`
+fn de(msg: Msg) -> &'static str {
+    match msg {
+        Msg::RevocationsIntro => {
+            "Diese Datei enthält Widerrufszertifikate für die OpenPGP CA \n\
+             Instanz '{email}'."
+        }
+        Msg::RevocationsExplanation => {
+            "Mit diesen Widerrufen kann der Schlüssel der CA ungültig gemacht werden.
+Das ist z.B. nötig, wenn der (private) CA-Schlüssel kompromittiert wird (also
+Dritten zugänglich ist), oder wenn Sie keinen Zugriff mehr auf ihn haben.
+
+ACHTUNG: Diese Datei muss vor Dritten geschützt werden, die mit den Widerrufen
+Ihr CA-Zertifikat böswillig ungültig machen könnten!
+Bedenken Sie, dass ein Angreifer diese Widerrufe nutzen kann, um Ihre CA im
+ungünstigsten Moment lahmzulegen (Denial of Service). Wenn ein Widerruf für
+Ihre CA veröffentlicht wurde, müssen Sie mit einem neuen CA-Schlüssel von
+vorne beginnen.
+
+Bitte bewahren Sie diese Datei so auf, dass sie für Angreifer nicht
+zugänglich ist."
+        }
+        Msg::RevocationsCaCert => "Zur Referenz, das Zertifikat Ihrer CA ist",
+        Msg::RevocationsFollow => {
+            "Es folgen die Widerrufszertifikate (nach Erstellungszeit sortiert):"
+        }
+        Msg::RevocationCommentHard => "Harter Widerruf (Zertifikat kompromittiert) ({date})",
+        Msg::RevocationCommentSoft => "Weicher Widerruf (Zertifikat stillgelegt) ({date})",
+
+        Msg::RevocationsWritten => "Die Widerrufe wurden in die Ausgabedatei geschrieben",
+        Msg::Confirm => "Sind Sie sicher? (zum Fortfahren 'yes' eingeben)",
+        Msg::Initialized => "OpenPGP CA Instanz initialisiert:",
+        Msg::Warning => "Warnung: {message}",
+        Msg::ImportStrict => {
+            "Der Schlüssel wird nicht importiert: {count} Warnung(en), und --strict ist gesetzt"
+        }
+        Msg::ImportedCertifications => "{count} Beglaubigungen importiert.",
+        Msg::Purged => "Schlüssel {fingerprint} gelöscht.",
+        Msg::PurgePreview => "Der Schlüssel {fingerprint} würde endgültig gelöscht",
+        Msg::PurgeUser => "  Benutzer: {name}",
+        Msg::PurgeEmail => "  E-Mail: {email}",
+        Msg::PurgeRevocations => "  Widerrufe: {count}",
+        Msg::PurgeForce => "Zum Löschen mit '--force' erneut ausführen.",
+
+        Msg::CardInit => "Initialisiere OpenPGP CA auf der Karte {ident}.",
+        Msg::CardGeneratedKey => {
+            "Neuer CA-Schlüssel erzeugt:
+
+{key}"
+        }
+        Msg::CardInitFromCard => {
+            "Initialisiere OpenPGP CA von der vorkonfigurierten OpenPGP-Karte {ident}."
+        }
+        Msg::CardUserPinPrompt => "User-PIN der OpenPGP-Karte {ident} eingeben: ",
+        Msg::CardInitImport => {
+            "Initialisiere OpenPGP CA mit vorhandenem Schlüssel, auf der Karte {ident}."
+        }
+        Msg::CardGenerateOnCard => "Erzeuge neuen OpenPGP CA-Schlüssel auf der Karte {ident}.",
+        Msg::CardMigrate => {
+            "Migriere OpenPGP CA Instanz auf die Karte {ident}.
+
+Achtung: Nach der Migration ist das private Schlüsselmaterial der CA nicht mehr
+in der CA-Datenbank verfügbar!
+
+Stellen Sie sicher, dass Sie eine Sicherung Ihres CA-Schlüssels haben!"
+        }
+        Msg::CardMigrated => "OpenPGP CA Instanz migriert:",
+        Msg::BackendChanged => "Die Backend-Konfiguration der CA wurde geändert.",
+        Msg::KeyPolicyMinRsaBits => "Minimale RSA-Bits",
+        Msg::KeyPolicyAllowedAlgorithms => "Erlaubte Algorithmen",
+        Msg::KeyPolicyRequireEncryptionSubkey => "Verschlüsselungs-Unterschlüssel nötig",
+        Msg::KeyPolicyRequireSigningSubkey => "Signatur-Unterschlüssel nötig",
+        Msg::KeyPolicyMaxExpiry => "Maximale Gültigkeit (Tage)",
+        Msg::KeyPolicyMaxSubkeyAge => "Max. Alter der Unterschlüssel (Tage)",
+        Msg::BlocklistAdded => "{kind} '{pattern}' zur Sperrliste hinzugefügt.",
+        Msg::BlocklistRemoved => "{kind} '{pattern}' von der Sperrliste entfernt.",
+        Msg::BlocklistLogAdded => "hinzugefügt",
+        Msg::BlocklistLogRemoved => "entfernt",
+        Msg::CtLogIntact => {
+            "Das Log ist intakt ({entries} Einträge).
+Kopf: {head}"
+        }
+        Msg::NotationShow => {
+            "Notation: {name}={value}
+Erforderlich: {required}"
+        }
+        Msg::NotationNone => "Beglaubigungen wird keine Notation hinzugefügt",
+        Msg::CryptoPolicySha1Cutoff => "SHA-1 Stichtag",
+        Msg::CryptoPolicyRejectedHashes => "Abgelehnte Hashverfahren",
+        Msg::CryptoPolicyRejectedAlgorithms => "Abgelehnte Algorithmen",
+        Msg::StatsPreview => "Die folgenden Daten werden an {endpoint} gesendet:",
+        Msg::StatsSigned => {
+            "Die Statistik wird mit dem CA-Schlüssel signiert, damit der Empfänger
+wiederholte Übermittlungen dieser CA erkennen kann."
+        }
+        Msg::StatsConfirm => "Diese Statistik senden? (zum Fortfahren 'yes' eingeben)",
+        Msg::StatsSent => "Nutzungsstatistik an {endpoint} gesendet.",
+        Msg::TorProxy => "Netzwerkabfragen werden über Tor bei {proxy} geleitet",
+        Msg::TorDirect => "Netzwerkabfragen nutzen direkte Verbindungen",
+        Msg::Keyserver => "Keyserver: {keyserver}",
+        Msg::KeyserverQuorum => "Quorum: {quorum}",
+        Msg::RevocationPublicationDisabled => {
+            "Widerrufene Schlüssel werden beim Anwenden von Widerrufen nicht veröffentlicht"
+        }
+        Msg::RevocationPublicationWkd => "WKD: {path}",
+        Msg::CertificationKeySelected => "{fingerprint} (ausgewählt)",
+        Msg::CertificationKeyAll => "Beglaubigungen werden mit jedem dieser Schlüssel ausgestellt.",
+        Msg::DoctorReleased => "Schlüsselzeile {cert_id} aus der Quarantäne entlassen.",
+        Msg::DoctorChecked => {
+            "{certs} Schlüssel, {revocations} Widerrufe, {queue_entries} offene \
+             Warteschlangeneinträge geprüft."
+        }
+        Msg::DoctorConsistent => "Keine Inkonsistenzen gefunden.",
+        Msg::DoctorBlobsOk => "Alle Schlüssel können gelesen werden.",
+        Msg::DoctorBlob => "Schlüsselzeile {cert_id} ({fingerprint}): {error} [{outcome}]",
+        Msg::BlobRecoverable => "wiederherstellbar",
+        Msg::BlobRepaired => "repariert",
+        Msg::BlobIrrecoverable => "nicht wiederherstellbar",
+        Msg::BlobQuarantined => "in Quarantäne",
+        Msg::DoctorQuarantined => "Schlüsselzeilen in Quarantäne:",
+        Msg::DoctorQuarantinedRow => " {cert_id} (seit {since}): {error}",
+        Msg::Retention => "max. Alter (Tage): {max_age_days}, max. Anzahl: {max_count}",
+        Msg::RetentionQueueDone => "Erledigte Warteschlangeneinträge",
+        Msg::RetentionCertVersions => "Schlüsselversionen",
+        Msg::CleanupQueuePreview => "{count} erledigte Warteschlangeneinträge würden entfernt.",
+        Msg::CleanupVersionsPreview => "{count} Schlüsselversionen würden entfernt.",
+        Msg::CleanupQueue => "{count} erledigte Warteschlangeneinträge entfernt.",
+        Msg::CleanupVersions => "{count} Schlüsselversionen entfernt.",
+        Msg::FederationWritten => "Föderations-Metadaten nach {path} geschrieben",
+        Msg::TrustPackageWritten => "Vertrauenspaket Version {version} nach {path} geschrieben",
+        Msg::TrustPackage => {
+            "Vertrauenspaket Version {version} von {domain} ({fingerprint}), erstellt {created}:"
+        }
+        Msg::EventsTopicPrefix => "Themen-Präfix",
+        Msg::EventsNatsServer => "NATS-Server",
+        Msg::EventsAmqpBroker => "AMQP-Broker",
+        Msg::EventsAmqpExchange => "AMQP-Exchange",
+        Msg::RekeyReplaced => "CA-Schlüssel {old} durch {new} ersetzt",
+        Msg::RekeyTsigNeeded => {
+            "Diese Benutzer müssen das neue CA-Zertifikat mit einer Vertrauenssignatur versehen:"
+        }
+        Msg::TimelineEmpty => "In den nächsten {days} Tagen läuft nichts ab.",
+        Msg::FingerprintMatches => "Der Fingerprint passt zum Schlüssel dieser CA.",
+        Msg::CardOnCardNote => {
+            "Hinweis:
+1) Der private CA-Schlüssel existiert nur auf der Karte (es ist keine Sicherung möglich)
+2) Die Zufallszahlen Ihrer OpenPGP-Karte könnten schlechter sein als die Ihres Computers"
+        }
+        Msg::CaInfoDomain => "    CA-Domain: {domain}",
+        Msg::CaInfoFingerprint => "  Fingerprint: {fingerprint}",
+        Msg::CaInfoCreated => " Erstellt am: {created}",
+        Msg::CaInfoBackend => "  CA-Backend: {backend}",
+
+        Msg::SplitExportEmpty => {
+            "Die Warteschlange enthält keine Anfragen für die Back-Instanz, es wurde nichts \
+             exportiert."
+        }
+        Msg::SplitExported => {
+            "Warteschlange mit {count} Einträgen zur Verarbeitung durch die Back-Instanz exportiert"
+        }
+        Msg::SplitImported => "{count} Beglaubigungen von der Back-Instanz importiert.",
+        Msg::SplitPushed => "{count} Beglaubigung(en) in die Front-Instanz importiert.",
+        Msg::SplitRequestCertification => {
+            "Anfrage für eine Beglaubigung [#{id}, eingereiht {created}]"
+        }
+        Msg::SplitRequestBridge => "Anfrage für eine Brücke [#{id}, eingereiht {created}]",
+        Msg::SplitRequestKey => "  Schlüssel {fingerprint}",
+        Msg::SplitRequestUserId => "  User ID '{userid}'",
+        Msg::SplitRequestValidDays => "  Gültig für {days} Tage",
+        Msg::SplitRequestValidUntil => "  Gültig bis {until}",
+        Msg::SplitRequestNoExpiration => "  Kein Ablaufdatum",
+        Msg::SplitRequestProfile => "  Beglaubigungsprofil '{profile}'",
+        Msg::SplitRequestNotation => "  Notation {name}={value}",
+        Msg::SplitRequestRemoteKey => "  Schlüssel der entfernten CA {fingerprint}",
+        Msg::SplitRequestUnscoped => "  Ohne Einschränkung",
+        Msg::SplitRequestScope => "  Eingeschränkt auf '{scope}'",
+        Msg::SplitCertifyPrompt => "Beglaubigen? [y/n]",
+        Msg::SplitCertifySkipped => "Dieser Eintrag wird übersprungen",
+
+        Msg::UserCardCreated => {
+            "Neuer Benutzerschlüssel {fingerprint} auf der Karte {ident} erzeugt.
+
+User-PIN:  {user_pin}
+Admin-PIN: {admin_pin}"
+        }
+        Msg::SubkeyRotationDue => {
+            "Name {name}, Fingerprint {fingerprint}: Unterschlüssel {subkey} bis {date} erneuern"
+        }
+        Msg::SubkeyRotationNoSubkey => {
+            "Name {name}, Fingerprint {fingerprint}: kein gültiger Verschlüsselungs-Unterschlüssel"
+        }
+        Msg::Recertified => "{fingerprint} neu beglaubigt",
+        Msg::CertificationExpires => "{fingerprint}{user}: '{userid}' läuft am {date} ab",
+        Msg::RenewFailed => {
+            "Beglaubigungen von {fingerprint} konnten nicht erneuert werden: {error}"
+        }
+        Msg::RenewPreview => "{count} Beglaubigungen würden erneuert.",
+        Msg::RenewQueued => "{count} Beglaubigungen wurden für die Back-Instanz eingereiht.",
+        Msg::Renewed => "{count} Beglaubigungen wurden erneuert.",
+        Msg::ExportSkipped => "Schlüssel {fingerprint} übersprungen: {reason}",
+        Msg::ExportedChunks => "{certs} Schlüssel in {chunks} Teilen nach {path} exportiert",
+        Msg::TagUser => "{name}={value} (Benutzer)",
+        Msg::SearchMatched => "(Treffer: {fields})",
+        Msg::WkdPublishDisabled => {
+            "Der Benutzer des Schlüssels {fingerprint} wird nicht per WKD veröffentlicht."
+        }
+        Msg::WkdPublishEnabled => {
+            "Der Benutzer des Schlüssels {fingerprint} wird per WKD veröffentlicht."
+        }
+        Msg::Listed => "Schlüssel {fingerprint} ist gelistet auf: {channels}",
+        Msg::Delisted => "Schlüssel {fingerprint} ist nicht gelistet auf: {channels}",
+        Msg::Reassigned => "Schlüssel {fingerprint} zum Benutzer '{user}' (ID {id}) verschoben.",
+        Msg::NoUser => "kein Benutzer",
+        Msg::ReplacementStored => "Ersatzschlüssel {fingerprint} gespeichert.",
+        Msg::OffboardAlreadyRevoked => "{fingerprint}: war bereits widerrufen",
+        Msg::OffboardApplied => "{fingerprint}: Widerruf {hash} angewendet",
+        Msg::OffboardNoRevocation => "{fingerprint}: kein Widerruf hinterlegt",
+        Msg::OffboardRetracted => "  Beglaubigung von '{userid}' zurückgezogen",
+        Msg::Offboarded => "Alle Schlüssel von {email} sind deaktiviert und nicht mehr gelistet.",
+        Msg::VersionReplacedBy => "ersetzt durch: {origin}",
+        Msg::AsOfMissing => "Der Schlüssel war am {time} nicht in der CA",
+        Msg::AsOfVersion => "Version: {id}",
+        Msg::AsOfCurrent => "Version: aktuell",
+        Msg::AsOfRevoked => "Der Schlüssel war widerrufen",
+        Msg::AsOfNotCertified => "Keine User ID war von der CA beglaubigt",
+        Msg::AsOfCertified => "Beglaubigt: {userid}",
+        Msg::AsOfIncomplete => {
+            "Warnung: die Versionsgeschichte des Schlüssels reicht nicht bis {time} zurück, \
+             dieser Stand kann ungenau sein"
+        }
+        Msg::UserKeyCreatedFor => "Neuer Benutzerschlüssel für {name} erzeugt.",
+        Msg::UserKeyCreated => "Neuer Benutzerschlüssel erzeugt.",
+        Msg::UserKeyWritten => "Der private Schlüssel wurde nach {path} geschrieben.",
+        Msg::UserKeyPassword => "Passwort für diesen Schlüssel: '{password}'.",
+        Msg::UserKeyNoPassword => "Für diesen Schlüssel ist kein Passwort gesetzt.",
+
+        Msg::PrimaryKey => "Primärschlüssel: {fingerprint}",
+        Msg::Subkey => "Unterschlüssel: {fingerprint}",
+        Msg::KeyAlgorithm => "  Algorithmus: {algorithm} ({bits} Bit)",
+        Msg::KeyFlags => "  Flags: {flags}",
+        Msg::KeyCreated => "  erstellt: {time}",
+        Msg::KeyExpires => "  läuft ab: {time}",
+        Msg::Revoked => "  widerrufen: {reason}",
+        Msg::NoReason => "kein Grund",
+        Msg::PolicyRejected => "  von der Richtlinie abgelehnt: {error}",
+        Msg::UserId => "User ID: {userid}",
+        Msg::NotUtf8 => "(kein UTF-8)",
+        Msg::DossierUser => "Benutzer '{name}'",
+        Msg::DossierWkdOptOut => "  hat der Veröffentlichung per WKD widersprochen",
+        Msg::DossierNotes => "  Notizen: {notes}",
+        Msg::DossierTag => "  Tag {name}: {value}",
+        Msg::DossierKey => "Schlüssel {fingerprint}",
+        Msg::DossierDelisted => "  nicht gelistet (wird nicht exportiert)",
+        Msg::DossierDelistedOn => "  nicht gelistet auf: {channels}",
+        Msg::DossierDeactivated => "  deaktiviert",
+        Msg::DossierTsig => "  hat den CA-Schlüssel mit einer Vertrauenssignatur versehen",
+        Msg::DossierNoTsig => "  hat den CA-Schlüssel nicht mit einer Vertrauenssignatur versehen",
+        Msg::DossierCertificationExpired => "  Beglaubigung durch die CA abgelaufen",
+        Msg::DossierCertified => "  von der CA beglaubigt",
+        Msg::DossierNotCertified => "  nicht von der CA beglaubigt",
+        Msg::UnknownTime => "unbekannte Zeit",
+        Msg::DossierRevocationApplied => "Widerruf {hash} (angewendet): {reason}, {time}",
+        Msg::DossierRevocationPending => "Widerruf {hash} (nicht angewendet): {reason}, {time}",
+        Msg::PublicationFailed => "{channel}: Veröffentlichung fehlgeschlagen: {error}",
+        Msg::Published => "{channel}: veröffentlicht {time}",
+
+        Msg::BridgeAdded => "OpenPGP-Schlüssel für {email} als Brücke hinzugefügt.",
+        Msg::BridgeFingerprint => {
+            "Der Fingerprint des entfernten CA-Schlüssels ist
+{fingerprint}"
+        }
+        Msg::BridgeDryRun => "Brückenerstellung TESTLAUF.",
+        Msg::BridgeVerify => {
+            "Bitte prüfen Sie vor dem Fortfahren, dass dies der richtige Fingerprint des \
+             Admins der entfernten CA ist:"
+        }
+        Msg::BridgeCommit => {
+            "Wenn Sie bestätigt haben, dass der entfernte Schlüssel korrekt ist, wiederholen \
+             Sie diesen Befehl mit dem zusätzlichen Parameter '--commit', um die OpenPGP CA \
+             Brücke in der Datenbank zu speichern."
+        }
+        Msg::BridgeMetadata => "Föderations-Metadaten der entfernten CA:",
+        Msg::BridgeMetadataDomains => "  Domains: {domains}",
+        Msg::BridgeMetadataKeylist => "  Keylist: {url}",
+        Msg::BridgeMetadataWkd => "  WKD-Methode: {method}",
+        Msg::BridgeMetadataContact => "  Kontakt: {contact}",
+        Msg::BridgeRevocation => "Widerruf für die Brücke zu {email}:",
+        Msg::BridgeTsigImported => "Die Vertrauenssignatur von {email} wurde importiert.",
+
+        Msg::WkdUpdated => "{path} aktualisiert",
+        Msg::KeylistExported => "Keylist '{name}' exportiert",
+        Msg::KeylistSignatureUri => "  Signatur-URI: {uri}",
+        Msg::KeylistDomains => "  Domains: {domains}",
+        Msg::KeylistEmails => "  E-Mails: {emails}",
+        Msg::KeylistExcludesInactive => "  Schließt inaktive Schlüssel aus",
+        Msg::KeylistGroup => "  Gruppe: {group}",
+        Msg::KeylistIncludesRoles => "  Enthält Rollen-User-IDs",
+
+        Msg::GroupSummary => "{name} ({count} Benutzer)",
+        Msg::NoName => "<kein Name>",
+        Msg::GroupAlreadyMember => "Der Benutzer ist bereits Mitglied von '{name}'",
+        Msg::GroupNotMember => "Der Benutzer ist kein Mitglied von '{name}'",
+        Msg::GroupCertified => "{fingerprint} beglaubigt",
+
+        Msg::SubkeyEncryption => "Verschlüsselung",
+        Msg::SubkeySigning => "Signatur",
+        Msg::SubkeyAuthentication => "Authentisierung",
+        Msg::ProfileCipherSuite => "  Cipher-Suite: {cipher_suite}",
+        Msg::ProfileSubkeys => "  Unterschlüssel: {subkeys}",
+        Msg::ProfileExpiry => "  Läuft nach {days} Tagen ab",
+        Msg::ProfilePassword => "  Passwort: {words} Wörter",
+        Msg::ProfilePasswordShortWords => "  Passwort: {words} Wörter (kurze Wortliste)",
+        Msg::ProfileNoPassword => "  Kein Passwort",
+
+        Msg::ProposalApplied => "Angewendet: {change}",
+
+        Msg::SyncFailingSince => "  fehlerhaft seit: {time}",
+        Msg::SyncLastSuccess => "  letzter Erfolg: {time}",
+        Msg::SyncNeverSucceeded => "  nie erfolgreich",
+        Msg::SyncLastError => "  letzter Fehler: {error}",
+        Msg::SyncReport => {
+            "Aktualisiert: {updated}, unverändert: {unchanged}, fehlgeschlagen: {failed}, \
+             übersprungen: {skipped}"
+        }
+
+        Msg::ChainVerified => {
+            "Der Schlüssel {fingerprint} für <{email}> ist vom CA-Schlüssel {ca_fingerprint} \
+             beglaubigt."
+        }
+
+        Msg::WizardYesNo => "Bitte mit 'y' (ja) oder 'n' (nein) antworten.",
+        Msg::WizardChoice => "Auswahl",
+        Msg::WizardChooseListed => "Bitte wählen Sie eine der aufgeführten Möglichkeiten.",
+        Msg::WizardFileExists => "{path} existiert bereits, bitte wählen Sie eine andere Datei.",
+        Msg::WizardNotAFile => "{path} ist keine Datei.",
+        Msg::WizardUsingBlankCard => "Verwende die leere OpenPGP-Karte {ident}.",
+        Msg::WizardUsingMatchingCard => "Verwende die passende OpenPGP-Karte {ident}.",
+        Msg::WizardBlankCards => "Mehrere leere OpenPGP-Karten gefunden:",
+        Msg::WizardMatchingCards => "Mehrere passende OpenPGP-Karten gefunden:",
+        Msg::WizardCipherSuite => "Cipher-Suite für den CA-Schlüssel:",
+        Msg::WizardRecommended => "{name} (empfohlen)",
+        Msg::WizardCardMode => "Wie soll der CA-Schlüssel auf die OpenPGP-Karte kommen?",
+        Msg::WizardCardModeHost => "Auf diesem Computer erzeugen, mit Sicherungsdatei (empfohlen)",
+        Msg::WizardCardModeOnCard => "Auf der Karte erzeugen (keine Sicherung möglich!)",
+        Msg::WizardCardModeImport => "Eine vorhandene Datei mit privatem CA-Schlüssel importieren",
+        Msg::WizardCardModeFromCard => "Eine Karte verwenden, die den CA-Schlüssel bereits enthält",
+        Msg::WizardBackupNote => {
+            "Der erzeugte private Schlüssel wird in eine Sicherungsdatei geschrieben.
+Bewahren Sie sie offline auf, sie wird zum Ersatz einer verlorenen oder defekten Karte benötigt."
+        }
+        Msg::WizardBackupFile => "Sicherungsdatei",
+        Msg::WizardPrivateKeyFile => "Datei mit privatem CA-Schlüssel",
+        Msg::WizardPublicKeyFile => "Datei mit öffentlichem CA-Schlüssel",
+        Msg::WizardDomain => "Domainname der CA (z.B. example.org)",
+        Msg::WizardName => "Beschreibender Name der CA (optional)",
+        Msg::WizardBackend => "Wo soll der private CA-Schlüssel aufbewahrt werden?",
+        Msg::WizardBackendSoftkey => "In der Datenbankdatei der CA",
+        Msg::WizardBackendCard => "Auf einer OpenPGP-Karte",
+        Msg::WizardBackendSplit => {
+            "In einer separaten \"Back\"-Datenbank, idealerweise auf einem Offline-Rechner"
+        }
+        Msg::WizardBackOnCard => "Soll die Back-Instanz eine OpenPGP-Karte verwenden?",
+        Msg::WizardSplitNote => {
+            "Die Front-Instanz erledigt den Alltagsbetrieb, ohne den CA-Schlüssel.
+Die Back-Instanz hält den CA-Schlüssel und signiert nur Beglaubigungsanfragen."
+        }
+        Msg::WizardFrontFile => "Datenbankdatei der Front-Instanz",
+        Msg::WizardBackFile => "Datenbankdatei der Back-Instanz",
+        Msg::WizardRevocationsNote => {
+            "Mit Widerrufszertifikaten kann der CA-Schlüssel widerrufen werden, auch wenn
+er verloren ist. Bewahren Sie sie offline und getrennt vom CA-Schlüssel auf."
+        }
+        Msg::WizardRevocationsGenerate => "Jetzt Widerrufszertifikate erzeugen?",
+        Msg::WizardRevocationsFile => "Datei für die Widerrufe",
+        Msg::WizardSummary => "Zusammenfassung:",
+        Msg::WizardSummaryDomain => "Domain",
+        Msg::WizardSummaryName => "Name",
+        Msg::WizardSummaryKey => "CA-Schlüssel",
+        Msg::WizardSummaryCipherSuite => "Cipher-Suite",
+        Msg::WizardSummaryRevocations => "Widerrufe",
+        Msg::WizardSummarySplit => "Split-Modus",
+        Msg::WizardKeySoftkey => "in der Datenbank (Softkey)",
+        Msg::WizardKeyCard => "auf der OpenPGP-Karte {ident} ({mode})",
+        Msg::WizardModeHost => "auf dem Computer erzeugt, Sicherung in {path}",
+        Msg::WizardModeOnCard => "auf der Karte erzeugt, keine Sicherung",
+        Msg::WizardModeImport => "importiert aus {path}",
+        Msg::WizardModeFromCard => "bereits auf der Karte, öffentlicher Schlüssel aus {path}",
+        Msg::WizardRevocationsNone => "nicht erzeugt",
+        Msg::WizardSplitValue => "Front {front}, Back {back}",
+        Msg::WizardBackupFailed => {
+            "Die Sicherung des CA-Schlüssels konnte nicht geschrieben werden ({error}),
+dies ist der CA-Schlüssel:
+
+{key}"
+        }
+        Msg::WizardIntro => {
+            "Geführte Einrichtung einer neuen OpenPGP CA Instanz.
+Es wird nichts verändert, bis Sie die Zusammenfassung am Ende bestätigen."
+        }
+        Msg::WizardConfirm => {
+            "Die CA mit diesen Einstellungen initialisieren? (zum Fortfahren 'yes' eingeben)"
+        }
+        Msg::WizardRevocationsWritten => "Widerrufe nach {path} geschrieben",
+        Msg::WizardSplitDone => {
+            "Die CA wurde in die Front-Instanz {front} und die Back-Instanz {back} aufgeteilt.
+Bringen Sie die Back-Instanz auf einen Offline-Rechner.
+
+Achtung: die ursprüngliche CA-Datenbank enthält weiterhin den CA-Schlüssel (oder die
+Kartenkonfiguration). Löschen Sie sie, oder bewahren Sie sie offline als Sicherung auf."
+        }
+        Msg::WizardBackupReminder => {
+            "Die Sicherung des privaten CA-Schlüssels ist in {path}, bewahren Sie sie offline auf!"
+        }
+    }
+}
+
+/// The message `msg` in `lang`, with the placeholders in `args` filled in
+///
+/// The template is scanned once: placeholders in the substituted values are
+/// kept as they are, as are placeholders that are not in `args`.
+pub fn tr(lang: Language, msg: Msg, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let template = match lang {
+        Language::English => en(msg),
+        Language::German => de(msg),
+    };
+
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let arg = after.find('}').and_then(|end| {
+            let name = &after[..end];
+            args.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, value)| (end, value))
+        });
+
+        match arg {
+            Some((end, value)) => {
+                text.push_str(&value.to_string());
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+
+    text
+}
+
+/// The language for output of `oca`: the environment override, if set,
+/// otherwise the configured language of the CA
+pub(crate) fn language(oca: &Oca) -> Result<Language> {
+    if let Some(lang) = Language::from_env()? {
+        return Ok(lang);
+    }
+
+    configured_language(oca)
+}
+
+pub(crate) fn configured_language(oca: &Oca) -> Result<Language> {
+    match oca.storage.pref(PREF_LANGUAGE)? {
+        Some(lang) => lang.parse(),
+        None => Ok(Language::default()),
+    }
+}
+
+pub(crate) fn set_language(oca: &Oca, lang: Language) -> Result<()> {
+    oca.storage.pref_set(PREF_LANGUAGE, lang.code())
+}
@@ -0,0 +1,100 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Named key profiles: parameters for generating user keys.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+
+use crate::pgp::{CipherSuite, Dictionary, PasswordPolicy};
+use crate::types::KeyProfile;
+use crate::Oca;
+
+const PREF_KEY_PROFILES: &str = "key_profiles";
+
+pub(crate) fn key_profiles(oca: &Oca) -> Result<Vec<KeyProfile>> {
+    match oca.storage.pref(PREF_KEY_PROFILES)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(vec![]),
+    }
+}
+
+fn key_profiles_store(oca: &Oca, profiles: &[KeyProfile]) -> Result<()> {
+    let json = serde_json::to_string(profiles)?;
+    oca.storage.pref_set(PREF_KEY_PROFILES, &json)
+}
+
+pub(crate) fn key_profile(oca: &Oca, name: &str) -> Result<KeyProfile> {
+    key_profiles(oca)?
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| anyhow::anyhow!("Key profile '{}' not found", name))
+}
+
+/// Add the key profile `profile`, or replace the profile of the same name
+pub(crate) fn key_profile_set(oca: &Oca, mut profile: KeyProfile) -> Result<()> {
+    let valid_name = !profile.name.is_empty()
+        && profile
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        return Err(anyhow::anyhow!(
+            "Key profile names may only contain ASCII letters, digits, '-' and '_'"
+        ));
+    }
+
+    cipher_suite(&profile)?;
+    profile.cipher_suite = profile.cipher_suite.to_lowercase();
+
+    if profile.password && profile.password_words == 0 {
+        return Err(anyhow::anyhow!(
+            "Generated passwords need at least one word"
+        ));
+    }
+    if profile.expiration_days == Some(0) {
+        return Err(anyhow::anyhow!("Key expiration must be at least one day"));
+    }
+
+    let mut profiles = key_profiles(oca)?;
+    profiles.retain(|p| p.name != profile.name);
+    profiles.push(profile);
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+
+    key_profiles_store(oca, &profiles)
+}
+
+pub(crate) fn key_profile_remove(oca: &Oca, name: &str) -> Result<()> {
+    let mut profiles = key_profiles(oca)?;
+
+    let len = profiles.len();
+    profiles.retain(|p| p.name != name);
+    if profiles.len() == len {
+        return Err(anyhow::anyhow!("Key profile '{}' not found", name));
+    }
+
+    key_profiles_store(oca, &profiles)
+}
+
+/// The cipher suite of keys that are generated with `profile`
+pub(crate) fn cipher_suite(profile: &KeyProfile) -> Result<CipherSuite> {
+    CipherSuite::from_str(&profile.cipher_suite)
+        .map_err(|e| anyhow::anyhow!("{} '{}' in key profile", e, profile.cipher_suite))
+}
+
+/// How passwords for keys that are generated with `profile` are obtained
+pub(crate) fn password_policy(profile: &KeyProfile) -> PasswordPolicy {
+    PasswordPolicy::Diceware {
+        words: profile.password_words,
+        dictionary: if profile.password_short_words {
+            Dictionary::EffShort
+        } else {
+            Dictionary::EffLarge
+        },
+        capitalize: false,
+    }
+}
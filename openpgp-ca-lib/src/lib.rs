@@ -28,21 +28,31 @@
 //! // Create a new user, certified by the CA, and a trust signature by the user
 //! // key on the CA key.
 //! //
-//! // The new private key for the user is printed to stdout and needs to be manually
-//! // processed from there.
-//! ca.user_new(
-//!     Some(&"Alice"),
-//!     &["alice@example.org"],
-//!     None,
-//!     false,
-//!     None,
-//!     false,
-//!     None,
-//!     true,
-//!     true,
-//!     false,
-//! )
-//! .unwrap();
+//! // The new private key for the user is returned, and needs to be handed
+//! // over to the user.
+//! let key = ca
+//!     .user_new(
+//!         Some(&"Alice"),
+//!         &["alice@example.org"],
+//!         None,
+//!         false,
+//!         None,
+//!         None,
+//!         true,
+//!         true,
+//!         false,
+//!     )
+//!     .unwrap();
+//! assert!(key.private_key.contains("PRIVATE KEY"));
+//! ```
+//!
+//! For tests and ephemeral use, a CA can also be kept in an in-memory
+//! database, which is discarded when the CA instance is dropped:
+//!
+//! ```
+//! # use openpgp_ca_lib::Oca;
+//! let ca = Oca::open_in_memory("example.org").unwrap();
+//! assert_eq!(ca.domainname(), "example.org");
 //! ```
 
 #[macro_use]
@@ -54,24 +64,58 @@ extern crate diesel_migrations;
 /// The version of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+pub mod api;
 mod backend;
+mod blocklist;
 mod bridge;
 mod cert;
+pub mod cert_info;
+mod certifications;
+mod config;
+mod ct_log;
 pub mod db;
+mod doctor;
+pub mod events;
 mod export;
+mod federation;
+mod groups;
+pub mod i18n;
+mod key_profile;
+mod mail;
+mod offboard;
 pub mod pgp;
+mod policy;
+mod progress;
+mod proposal;
+mod rekey;
+mod replacement;
+mod retention;
 mod revocation;
+mod rotation;
+mod search;
 mod secret;
+mod smoketest;
+pub mod split_format;
+mod stats;
 mod storage;
+mod tags;
+mod timeline;
+mod tor;
+mod trust_package;
+mod tsig;
 pub mod types;
 mod update;
+pub mod util;
+mod x509;
 
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str::FromStr;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use anyhow::{Context, Result};
 use chrono::offset::Utc;
@@ -81,19 +125,37 @@ use openpgp_card_pcsc::PcscBackend;
 use openpgp_card_sequoia::{state::Open, Card};
 use sequoia_openpgp::packet::{Signature, UserID};
 use sequoia_openpgp::parse::Parse;
+use sequoia_openpgp::policy::StandardPolicy;
 use sequoia_openpgp::Cert;
 
 use crate::backend::card::{check_card_empty, CardBackend};
 use crate::backend::softkey::SoftkeyBackend;
 use crate::backend::split::SplitCa;
 use crate::backend::{card, split, Backend};
+use crate::cert_info::{CertInfo, CertWarning};
 use crate::db::models;
 use crate::db::models::NewCacert;
 use crate::db::OcaDb;
-use crate::pgp::CipherSuite;
+use crate::events::{EventKind, EventPublisher, EventsConfig};
+use crate::pgp::{CipherSuite, KeyVersion, PasswordPolicy};
 use crate::secret::{CaSec, CaSecCB};
+use crate::split_format::QueueEntry;
 use crate::storage::{CaStorageRW, DbCa, UninitDb};
-use crate::types::CertificationStatus;
+use crate::types::{
+    BlocklistKind, BlocklistMatch, BridgeBundleImport, BridgeScope, CaConfig, CaConfigChange,
+    CaConfigKey, CaInfo, CaRekeyParams, CaRekeyReport, CaTsig, CardUserKey, CertAsOf,
+    CertBlobReport, CertDiff, CertDossier, CertFormat, CertificationExtensionReport,
+    CertificationProfile, CertificationStatus, CertificationValidity, CheckpointPolicy,
+    ChunkedExportManifest, CleanupReport, ConsistencyReport, CryptoPolicy, CtLogEntry, CtLogReport,
+    ExportChannel, ExportCompat, ExportCompression, ExportRejection, FederationMetadata,
+    FingerprintFormat, GroupNotification, IssuedCertifications, KeyPolicy, KeyPolicyViolation,
+    KeyProfile, KeyReplacementStatus, KeylistConfig, KeyserverConfig, MimeEntity, NewUserKey,
+    Notation, NotationPolicy, OffboardReport, Progress, Proposal, ProposedChange,
+    ProvisioningBundle, RetentionPolicy, RevocationPublication, SearchMatch, SignedCertStatus,
+    SmoketestStep, SubkeyRotation, SyncOptions, SyncReport, SyncSource, SyncStatus, TimelineEvent,
+    TrustPackageManifest, UriPolicy, UsageStats, UsageStatsSubmission, UserDossier, WotGraph,
+    WotGraphFormat, X509Mapping,
+};
 
 /// List of cards that are blank (no fingerprint in any slot)
 pub fn blank_cards() -> Result<Vec<String>> {
@@ -143,6 +205,18 @@ pub struct Oca {
 
     backend: Backend,
     domainname: String,
+
+    lookup_cache: RefCell<HashMap<String, cert::CachedLookup>>,
+
+    // Use (and fill) the cache of signature verification results in the
+    // database (see [Oca::set_verification_cache])
+    verification_cache: Cell<bool>,
+
+    // Set up lazily, when the first event is emitted
+    event_publishers: RefCell<Option<Vec<Box<dyn EventPublisher>>>>,
+
+    // See [Oca::set_progress_handler]
+    progress_handler: RefCell<Option<Box<dyn Fn(&Progress)>>>,
 }
 
 impl Uninit {
@@ -162,7 +236,30 @@ impl Uninit {
             return Err(anyhow::anyhow!("ERROR: no database configuration found"));
         };
 
-        let db = Rc::new(OcaDb::new(&db_url)?);
+        Self::with_db(OcaDb::new(&db_url)?)
+    }
+
+    /// Instantiate a new Uninit object with an empty in-memory database.
+    ///
+    /// Nothing is persisted: the database is discarded when the Uninit
+    /// object (or the [Oca] that it is initialized into) is dropped.
+    /// This is intended for tests and ephemeral use.
+    pub fn new_in_memory() -> Result<Self> {
+        Self::with_db(OcaDb::new(db::IN_MEMORY)?)
+    }
+
+    fn with_db(db: OcaDb) -> Result<Self> {
+        let db = Rc::new(db);
+
+        // The schema migrations can't convert databases from early versions
+        if db.has_legacy_schema()? {
+            return Err(anyhow::anyhow!(
+                "The database {} uses the legacy 'usercerts' schema, which is not supported \
+                 by this version of OpenPGP CA",
+                db.url()
+            ));
+        }
+
         db.diesel_migrations_run();
 
         let storage = UninitDb::new(db);
@@ -170,8 +267,13 @@ impl Uninit {
         Ok(Self { storage })
     }
 
+    /// Is the CA database already initialized?
+    pub fn is_initialized(&self) -> Result<bool> {
+        self.storage.is_ca_initialized()
+    }
+
     /// Check if domainname is legal according to Mozilla's Public Suffix List
-    fn check_domainname(domainname: &str) -> Result<()> {
+    pub fn check_domainname(domainname: &str) -> Result<()> {
         // domainname syntax check
         use addr::parser::DomainName;
         use addr::psl::List;
@@ -194,9 +296,32 @@ impl Uninit {
         domainname: &str,
         name: Option<&str>,
         cipher_suite: Option<CipherSuite>,
+    ) -> Result<Oca> {
+        self.init_softkey_with_validity(domainname, name, cipher_suite, KeyVersion::V4, None)
+    }
+
+    /// Init CA with softkey backend, like [Self::init_softkey].
+    ///
+    /// The CA key is an OpenPGP key of version `key_version`.
+    ///
+    /// If `validity_days` is set, the generated CA key expires after that
+    /// many days.
+    pub fn init_softkey_with_validity(
+        self,
+        domainname: &str,
+        name: Option<&str>,
+        cipher_suite: Option<CipherSuite>,
+        key_version: KeyVersion,
+        validity_days: Option<u64>,
     ) -> Result<Oca> {
         Self::check_domainname(domainname)?;
-        let (cert, _) = pgp::make_ca_cert(domainname, name, cipher_suite)?;
+        let (cert, _) = pgp::make_ca_cert(
+            domainname,
+            name,
+            cipher_suite,
+            key_version,
+            validity_days.map(|days| Duration::from_secs(days * pgp::SECONDS_IN_DAY)),
+        )?;
 
         self.storage
             .transaction(|| self.storage.ca_init_softkey(domainname, &cert))?;
@@ -242,6 +367,32 @@ impl Uninit {
         domain: &str,
         name: Option<&str>,
         cipher_suite: Option<CipherSuite>,
+    ) -> Result<(Oca, String)> {
+        self.init_card_generate_on_host_with_validity(
+            ident,
+            domain,
+            name,
+            cipher_suite,
+            KeyVersion::V4,
+            None,
+        )
+    }
+
+    /// Init CA with OpenPGP card backend, like
+    /// [Self::init_card_generate_on_host].
+    ///
+    /// The CA key is an OpenPGP key of version `key_version`.
+    ///
+    /// If `validity_days` is set, the generated CA key expires after that
+    /// many days.
+    pub fn init_card_generate_on_host_with_validity(
+        self,
+        ident: &str,
+        domain: &str,
+        name: Option<&str>,
+        cipher_suite: Option<CipherSuite>,
+        key_version: KeyVersion,
+        validity_days: Option<u64>,
     ) -> Result<(Oca, String)> {
         // The CA database must be uninitialized!
         if self.storage.is_ca_initialized()? {
@@ -249,7 +400,13 @@ impl Uninit {
         }
 
         // Generate a new CA private key
-        let (ca_key, _) = pgp::make_ca_cert(domain, name, cipher_suite)?;
+        let (ca_key, _) = pgp::make_ca_cert(
+            domain,
+            name,
+            cipher_suite,
+            key_version,
+            validity_days.map(|days| Duration::from_secs(days * pgp::SECONDS_IN_DAY)),
+        )?;
 
         // Import key material to card.
         let user_pin = card::import_to_card(ident, &ca_key)?;
@@ -386,18 +543,24 @@ impl Uninit {
 
         match &backend {
             Backend::Softkey => {
-                let softkey = SoftkeyBackend::new(self.storage.ca_get_cert_private()?);
-
+                let ca_cert_priv = self.storage.ca_get_cert_private()?;
                 let ca_cert_pub = self.storage.ca_get_cert_pub()?;
+                let db = self.storage.db();
+
+                let softkey = SoftkeyBackend::new(ca_cert_priv, db.clone());
                 let ca_sec = CaSecCB::new(Rc::new(softkey), ca_cert_pub);
 
-                let storage = Box::new(DbCa::new(self.storage.db()));
+                let storage = Box::new(DbCa::new(db));
 
                 Ok(Oca {
                     storage,
                     secret: Box::new(ca_sec),
                     backend,
                     domainname,
+                    lookup_cache: Default::default(),
+                    verification_cache: Cell::new(true),
+                    event_publishers: Default::default(),
+                    progress_handler: Default::default(),
                 })
             }
             Backend::Card(card) => {
@@ -413,6 +576,10 @@ impl Uninit {
                     secret: Box::new(ca_sec),
                     backend,
                     domainname,
+                    lookup_cache: Default::default(),
+                    verification_cache: Cell::new(true),
+                    event_publishers: Default::default(),
+                    progress_handler: Default::default(),
                 })
             }
             Backend::SplitFront => {
@@ -426,19 +593,26 @@ impl Uninit {
                     secret,
                     backend,
                     domainname,
+                    lookup_cache: Default::default(),
+                    verification_cache: Cell::new(true),
+                    event_publishers: Default::default(),
+                    progress_handler: Default::default(),
                 })
             }
             Backend::SplitBack(inner) => {
+                let back = self.storage.db();
+
                 let secret: Box<dyn CaSec> = match &**inner {
                     Backend::Softkey => {
-                        let softkey = SoftkeyBackend::new(self.storage.ca_get_cert_private()?);
-                        let ca_cert_pub = self.storage.ca_get_cert_pub()?;
+                        let softkey =
+                            SoftkeyBackend::new(storage::ca_get_cert_private(&back)?, back.clone());
+                        let ca_cert_pub = storage::ca_get_cert_pub(&back)?;
                         Box::new(CaSecCB::new(Rc::new(softkey), ca_cert_pub))
                     }
                     Backend::Card(card) => {
                         let card_ca = CardBackend::new(&card.ident, &card.user_pin)?;
 
-                        let ca_cert = self.storage.ca_get_cert_pub()?;
+                        let ca_cert = storage::ca_get_cert_pub(&back)?;
                         Box::new(CaSecCB::new(Rc::new(card_ca), ca_cert))
                     }
 
@@ -447,12 +621,12 @@ impl Uninit {
 
                 let db = match env::var("OPENPGP_CA_FRONT_DB") {
                     Ok(readonly) => {
-                        println!("Using {readonly} as r/o online datasource");
+                        tracing::info!(path = %readonly, "Using r/o online datasource");
 
                         let ocadb = OcaDb::new(&readonly)?;
-                        split::SplitBackDb::new(Some(Rc::new(ocadb)))
+                        split::SplitBackDb::new(back, Some(Rc::new(ocadb)))
                     }
-                    Err(_e) => split::SplitBackDb::new(None),
+                    Err(_e) => split::SplitBackDb::new(back, None),
                 };
 
                 let storage = Box::new(db);
@@ -462,6 +636,10 @@ impl Uninit {
                     secret,
                     backend,
                     domainname,
+                    lookup_cache: Default::default(),
+                    verification_cache: Cell::new(true),
+                    event_publishers: Default::default(),
+                    progress_handler: Default::default(),
                 })
             }
         }
@@ -479,6 +657,44 @@ impl Oca {
         cau.init_from_db_state()
     }
 
+    /// Create a new CA for `domainname` in an in-memory database, with a
+    /// softkey CA key.
+    ///
+    /// Nothing is persisted: the database is discarded when the Oca is
+    /// dropped. This is intended for tests (no temporary database file is
+    /// needed) and ephemeral use. Other backends can be set up in memory
+    /// with [Uninit::new_in_memory].
+    pub fn open_in_memory(domainname: &str) -> Result<Self> {
+        Uninit::new_in_memory()?.init_softkey(domainname, None, None)
+    }
+
+    /// Close this Oca instance.
+    ///
+    /// Checkpoints the write-ahead log of the database (if the database is
+    /// in WAL mode), and releases the database connection(s).
+    ///
+    /// Dropping an Oca also releases its connections, but skips the
+    /// checkpoint, and can't report errors.
+    pub fn close(self) -> Result<()> {
+        self.checkpoint()
+    }
+
+    /// Checkpoint the write-ahead log of the database (if the database is in
+    /// WAL mode), and truncate the log file.
+    ///
+    /// Long-running processes should call this periodically (see
+    /// [CheckpointPolicy]).
+    pub fn checkpoint(&self) -> Result<()> {
+        self.storage.checkpoint()
+    }
+
+    /// Configure when SQLite checkpoints the write-ahead log of this
+    /// instance's database connection automatically.
+    pub fn set_checkpoint_policy(&self, policy: CheckpointPolicy) -> Result<()> {
+        self.storage
+            .set_wal_autocheckpoint(policy.autocheckpoint_pages())
+    }
+
     pub fn domainname(&self) -> &str {
         &self.domainname
     }
@@ -530,8 +746,13 @@ impl Oca {
     // -------- CA
 
     /// Generate revocations for the CA key, write to output file.
-    pub fn ca_generate_revocations(&self, output: PathBuf) -> Result<()> {
-        self.secret.ca_generate_revocations(output)
+    ///
+    /// The revocations are armored as the client `compat` expects. The
+    /// explanatory text in the file is written in the language of the CA
+    /// (see [Oca::language]).
+    pub fn ca_generate_revocations(&self, output: PathBuf, compat: ExportCompat) -> Result<()> {
+        self.secret
+            .ca_generate_revocations(output, compat, self.language()?)
     }
 
     /// Ingest/merge in any new tsigs for our CA certificate from 'cert'
@@ -539,6 +760,25 @@ impl Oca {
         self.storage.ca_import_tsig(cert)
     }
 
+    /// List the trust signatures on the CA cert, and whether they were
+    /// made by current, superseded or revoked user keys
+    pub fn ca_tsigs(&self) -> Result<Vec<CaTsig>> {
+        tsig::ca_tsigs(self)
+    }
+
+    /// Find user certs that should tsig the CA cert: current certs that
+    /// have not tsigned the CA cert, for email addresses whose superseded or
+    /// revoked certs had tsigned it
+    pub fn ca_tsigs_missing(&self) -> Result<Vec<models::Cert>> {
+        tsig::ca_tsigs_missing(self)
+    }
+
+    /// Get the CA certificate (see [Self::ca_get_cert_pub]), without trust
+    /// signatures by superseded or revoked user keys
+    pub fn ca_get_cert_pub_without_stale_tsigs(&self) -> Result<Cert> {
+        tsig::ca_cert_without_stale_tsigs(self)
+    }
+
     /// Get current CA certificate from storage.
     /// This representation of the CA cert includes user certifications.
     ///
@@ -573,6 +813,45 @@ impl Oca {
         Ok(ca_pub)
     }
 
+    /// Returns the fingerprint of the CA, in `format` (see [Self::ca_get_cert_pub]).
+    pub fn ca_fingerprint(&self, format: FingerprintFormat) -> Result<String> {
+        let cert = self.ca_get_cert_pub()?;
+
+        Ok(pgp::fingerprint_to_string(&cert.fingerprint(), format))
+    }
+
+    /// Check if `fingerprint` (e.g. as read out during a bridge setup
+    /// ceremony) is the fingerprint of this CA.
+    ///
+    /// `fingerprint` may be given in any of the [FingerprintFormat]s.
+    /// Errors if it is not a well-formed fingerprint.
+    pub fn ca_verify_fingerprint(&self, fingerprint: &str) -> Result<bool> {
+        let fp = pgp::fingerprint_from_str(fingerprint)?;
+
+        Ok(fp == self.ca_get_cert_pub()?.fingerprint())
+    }
+
+    /// Get the contact and policy information that this CA publishes in
+    /// the direct key signature of its cert.
+    pub fn ca_config(&self) -> Result<CaConfig> {
+        config::ca_config(self)
+    }
+
+    /// Set `key` in the CA config to `value` (or unset it, if `value` is
+    /// None).
+    ///
+    /// This re-signs the direct key signature of the CA cert with the
+    /// active backend. Previous values are kept in the config history
+    /// (see [Self::ca_config_history]).
+    pub fn ca_config_set(&self, key: CaConfigKey, value: Option<&str>) -> Result<()> {
+        config::ca_config_set(self, key, value)
+    }
+
+    /// List all changes of the CA config, oldest first.
+    pub fn ca_config_history(&self) -> Result<Vec<CaConfigChange>> {
+        config::ca_config_history(self)
+    }
+
     /// Get the User ID of this CA
     pub(crate) fn get_ca_userid(&self) -> Result<UserID> {
         let cert = self.ca_get_cert_pub()?;
@@ -597,29 +876,40 @@ impl Oca {
         }
     }
 
-    /// Print information about the Ca to stdout.
-    ///
-    /// This shows the domainname, fingerprint and creation time of this OpenPGP CA instance.
-    pub fn ca_show(&self) -> Result<()> {
+    /// Information about the CA: the domainname, fingerprint and creation
+    /// time of this OpenPGP CA instance, and its backend.
+    pub fn ca_info(&self) -> Result<CaInfo> {
         let cert = self.secret().cert()?;
 
         let created = cert.primary_key().key().creation_time();
-        let created: DateTime<Utc> = created.into();
 
-        println!("    CA Domain: {}", self.domainname());
-        println!("  Fingerprint: {}", cert.fingerprint());
-        println!("Creation time: {}", created.format("%F %T %Z"));
+        Ok(CaInfo {
+            domainname: self.domainname().to_string(),
+            fingerprint: cert.fingerprint().to_hex(),
+            created: created.into(),
+            backend: self.backend().to_string(),
+        })
+    }
 
-        let backend = self.backend();
-        println!("   CA Backend: {backend}");
+    /// Anonymous usage statistics of this CA instance (counts of users,
+    /// certs and bridges, backend type, version)
+    pub fn usage_stats(&self) -> Result<UsageStats> {
+        stats::usage_stats(self)
+    }
 
-        Ok(())
+    /// The usage statistics of this CA instance, as they would be shared with
+    /// the OpenPGP CA project: serialized, with a signature by the CA key.
+    ///
+    /// The signature allows the recipient to recognize repeated submissions
+    /// of the same CA.
+    pub fn usage_stats_submission(&self) -> Result<UsageStatsSubmission> {
+        stats::usage_stats_submission(self)
     }
 
-    /// Print private key of the Ca to stdout.
+    /// The armored private key of the CA.
     ///
     /// This operation is only supported for Softkey and SplitBack+Softkey instances.
-    pub fn ca_print_private(&self) -> Result<()> {
+    pub fn ca_private_key(&self) -> Result<String> {
         match &self.backend {
             Backend::Softkey => {
                 // OK
@@ -646,9 +936,27 @@ impl Oca {
             .storage
             .cacert()
             .context("failed to load CA from database")?;
-        println!("{}", ca_cert.priv_cert);
 
-        Ok(())
+        Ok(ca_cert.priv_cert)
+    }
+
+    /// Fingerprints of the valid, certification capable (sub)keys of the CA cert
+    pub fn certification_keys(&self) -> Result<Vec<String>> {
+        backend::certification_keys(self)
+    }
+
+    /// The (sub)key that the CA certifies with (None, if the CA certifies
+    /// with each of its certification capable keys)
+    pub fn certification_key(&self) -> Result<Option<String>> {
+        backend::certification_key(self)
+    }
+
+    /// Certify only with the certification capable (sub)key `fingerprint`
+    /// of the CA cert (or, with None, with each certification capable key).
+    ///
+    /// Only supported for softkey CAs.
+    pub fn set_certification_key(&self, fingerprint: Option<&str>) -> Result<()> {
+        backend::set_certification_key(self, fingerprint)
     }
 
     /// Find all User IDs that have been certified by `ca_cert_old` and re-certify them
@@ -656,10 +964,44 @@ impl Oca {
     ///
     /// This can be useful after CA key rotation: when the CA has a new key, `ca_re_certify` issues
     /// fresh certifications for all previously CA-certified user certs.
+    ///
+    /// Redundant certifications by the current CA key (made with several of its certification
+    /// capable keys at once) are removed from the re-certified certs, keeping the certifications
+    /// of the selected certification key (see [Self::set_certification_key]).
     pub fn ca_re_certify(&self, ca_cert_old: &[u8], validity_days: u64) -> Result<()> {
         let ca_cert_old = pgp::to_cert(ca_cert_old)?;
 
-        cert::certs_re_certify(self, ca_cert_old, validity_days)
+        cert::certs_re_certify(self, ca_cert_old, validity_days)?;
+
+        Ok(())
+    }
+
+    /// Replace the CA key with a newly generated key (e.g. after a compromise
+    /// of the old key).
+    ///
+    /// User certs that were certified by the old CA key are re-certified with
+    /// the new key. The report lists the users who need to tsign the new CA cert.
+    ///
+    /// On a split-mode back instance, only the CA key is replaced. The new public
+    /// CA cert must then be imported into the front instance
+    /// (see [Self::ca_rekey_import]).
+    ///
+    /// This operation is supported for softkey-based CAs.
+    pub fn ca_rekey(self, params: &CaRekeyParams) -> Result<(Oca, CaRekeyReport)> {
+        rekey::ca_rekey(self, params)
+    }
+
+    /// Import the new CA cert of the back instance into a split-mode front instance,
+    /// after the CA key has been replaced on the back instance (see [Self::ca_rekey]).
+    ///
+    /// Certification requests for all user certs that were certified by the old CA key
+    /// are queued for the back instance.
+    pub fn ca_rekey_import(
+        self,
+        new_ca_cert: &[u8],
+        certification_validity_days: u64,
+    ) -> Result<(Oca, CaRekeyReport)> {
+        rekey::ca_rekey_import(self, new_ca_cert, certification_validity_days)
     }
 
     /// Split a CA instance into a pair of "front" and "back" CA instances.
@@ -690,10 +1032,15 @@ impl Oca {
                     }
                 }
 
-                // - Copy the database file to "front" CA file
-                std::fs::copy(db_url, front)?;
-
                 if let Some(url) = front.to_str() {
+                    // - Copy the database file to "front" CA file
+                    // (an in-memory database has no file, it is written out)
+                    if db.is_in_memory() {
+                        db.vacuum_into(url)?;
+                    } else {
+                        std::fs::copy(db_url, front)?;
+                    }
+
                     let front = OcaDb::new(url)?;
 
                     // - Remove cacerts and add a new one ('ca' entry stays unchanged)
@@ -744,13 +1091,17 @@ impl Oca {
     }
 
     /// Merge a back CA into a front CA instance, resulting in a regular ("non-split") CA.
+    ///
+    /// The front instance database is converted in place. Merging requires that the front
+    /// instance has no pending certification requests (they must first be processed by the back
+    /// instance, see [Oca::ca_split_export]).
     pub fn ca_merge_split(self, back: &Path) -> Result<()> {
         match self.backend {
             Backend::SplitFront => {
                 // get inner backend and cacert data from the back instance
                 if let Some(url) = back.to_str() {
                     let back = OcaDb::new(url)?;
-                    let (_back_ca, back_cacert) = back.get_ca()?;
+                    let (back_ca, back_cacert) = back.get_ca()?;
 
                     let orig_back = Backend::from_config(back_cacert.backend.as_deref())?;
                     if let Backend::SplitBack(inner) = orig_back {
@@ -766,6 +1117,24 @@ impl Oca {
                             ));
                         }
 
+                        if self.domainname != back_ca.domainname {
+                            return Err(anyhow::anyhow!(
+                                "Front {} and back {} instance use different domain names",
+                                self.domainname,
+                                back_ca.domainname
+                            ));
+                        }
+
+                        // Requests in the queue would be lost after the merge
+                        let pending = self.storage.queue_not_done()?;
+                        if !pending.is_empty() {
+                            return Err(anyhow::anyhow!(
+                                "The front instance has {} pending certification request(s). \
+                                 Process them with the back instance before merging.",
+                                pending.len()
+                            ));
+                        }
+
                         // The back CA contains private key material (in softkey mode).
                         // Start from the back CA Cert, merge in the public material from the front CA.
                         // Use the resulting merged cert for the newly merged CA.
@@ -781,9 +1150,14 @@ impl Oca {
 
                         let db = self.storage;
                         db.cacert_update(&front_cacert)?;
-                    }
 
-                    Ok(())
+                        Ok(())
+                    } else {
+                        Err(anyhow::anyhow!(
+                            "{:?} is not a split mode back instance",
+                            url
+                        ))
+                    }
                 } else {
                     Err(anyhow::anyhow!(
                         "Failed to use back instance path ({:?})",
@@ -810,15 +1184,60 @@ impl Oca {
     /// One design goal of this format is to make it easy to implement small (and thus more easily
     /// auditable) certification services, which may use arbitrary underlying mechanisms
     /// (and/or PGP implementations) for signing.
-    pub fn ca_split_export(&self, file: PathBuf) -> Result<()> {
+    ///
+    /// Returns the number of exported requests (if the queue is empty, no
+    /// file is written).
+    pub fn ca_split_export(&self, file: PathBuf) -> Result<usize> {
         match self.backend {
             Backend::SplitFront => {
                 let cacert = self.storage.cacert()?;
 
                 let queue = self.storage.queue_not_done()?;
-                SplitCa::export_csr_queue(file, queue, &cacert.fingerprint)?;
+                SplitCa::export_csr_queue(file, queue, &cacert.fingerprint)
+            }
+            _ => Err(anyhow::anyhow!(
+                "Operation is only supported on split mode front instances."
+            )),
+        }
+    }
 
-                Ok(())
+    /// The pending certification requests for the back instance, in the
+    /// JSON format of [Self::ca_split_export] (e.g. for transfer over HTTP).
+    ///
+    /// Unlike [Self::ca_split_export], this returns a request document also if
+    /// the queue is empty.
+    pub fn ca_split_requests(&self) -> Result<String> {
+        match self.backend {
+            Backend::SplitFront => {
+                let cacert = self.storage.cacert()?;
+                let queue = self.storage.queue_not_done()?;
+
+                SplitCa::csr_queue_json(&queue, &cacert.fingerprint)
+            }
+            _ => Err(anyhow::anyhow!(
+                "Operation is only supported on split mode front instances."
+            )),
+        }
+    }
+
+    /// Write the pending certification requests for the back instance to
+    /// `writer`, in the JSON format of [Self::ca_split_export] (see
+    /// [split_format::SplitOcaRequests]).
+    ///
+    /// This allows moving the requests over custom transports, without
+    /// temporary files. Unlike [Self::ca_split_export], a request document is
+    /// written also if the queue is empty.
+    ///
+    /// Returns the number of requests.
+    pub fn ca_split_export_to_writer(&self, writer: &mut dyn std::io::Write) -> Result<usize> {
+        match self.backend {
+            Backend::SplitFront => {
+                let cacert = self.storage.cacert()?;
+                let queue = self.storage.queue_not_done()?;
+
+                SplitCa::csr_queue(&queue, &cacert.fingerprint)?.to_writer(writer)?;
+
+                Ok(queue.len())
             }
             _ => Err(anyhow::anyhow!(
                 "Operation is only supported on split mode front instances."
@@ -828,137 +1247,718 @@ impl Oca {
 
     /// Process certification requests in a SplitBack instance
     ///
-    /// When "batch" is false, this fn is interactive.
+    /// `approve` is called with the queue id, creation time and content of
+    /// each request; requests for which it returns `false` are skipped
+    /// (e.g. after asking the CA operator).
+    pub fn ca_split_certify(
+        &self,
+        import: PathBuf,
+        export: PathBuf,
+        approve: &mut dyn FnMut(i32, DateTime<Utc>, &QueueEntry) -> Result<bool>,
+    ) -> Result<()> {
+        match self.backend {
+            Backend::SplitBack(_) => split::certify(&*self.secret, import, export, approve),
+            _ => Err(anyhow::anyhow!(
+                "Operation is only supported on split mode back instances."
+            )),
+        }
+    }
+
+    /// Process the certification requests in `input` (see
+    /// [split_format::SplitOcaRequests]) in a SplitBack instance, and write
+    /// the response to `output` (see [split_format::SplitOcaResponse]).
     ///
-    /// In interactive mode, it reads KeyEvents for user feedback
-    /// about certification operations.
-    pub fn ca_split_certify(&self, import: PathBuf, export: PathBuf, batch: bool) -> Result<()> {
+    /// Like [Self::ca_split_certify], but for custom transports.
+    pub fn ca_split_certify_from_reader(
+        &self,
+        input: &mut dyn std::io::Read,
+        output: &mut dyn std::io::Write,
+        approve: &mut dyn FnMut(i32, DateTime<Utc>, &QueueEntry) -> Result<bool>,
+    ) -> Result<()> {
         match self.backend {
-            Backend::SplitBack(_) => split::certify(&*self.secret, import, export, batch),
+            Backend::SplitBack(_) => split::certify_stream(&*self.secret, input, output, approve),
             _ => Err(anyhow::anyhow!(
                 "Operation is only supported on split mode back instances."
             )),
         }
     }
 
-    /// Ingest the certifications that were generated by the split backend
-    pub fn ca_split_import(&self, file: PathBuf) -> Result<()> {
+    /// Ingest a response of the split backend (in the JSON format of
+    /// [Self::ca_split_certify], e.g. received over HTTP).
+    ///
+    /// Returns the number of newly imported entries (entries that have
+    /// already been imported are ignored).
+    pub fn ca_split_import_response(&self, response: &[u8]) -> Result<usize> {
         match self.backend {
-            Backend::SplitFront => split::ca_split_import(&*self.storage, file),
+            Backend::SplitFront => {
+                let (imported, _) = split::import_response(&*self.storage, response)?;
+                events::emit(self, EventKind::QueueProcessed, None);
+
+                Ok(imported)
+            }
             _ => Err(anyhow::anyhow!(
                 "Operation is only supported on split mode front instances."
             )),
         }
     }
 
-    /// Show the currently not done entries in the queue of a split mode front instance
-    pub fn ca_split_show_queue(&self) -> Result<()> {
+    /// Ingest a response of the split backend from `reader` (see
+    /// [split_format::SplitOcaResponse]), e.g. received over a custom
+    /// transport.
+    ///
+    /// Returns the number of newly imported entries (entries that have
+    /// already been imported are ignored).
+    pub fn ca_split_import_from_reader(&self, reader: &mut dyn std::io::Read) -> Result<usize> {
+        let mut response = vec![];
+        reader.read_to_end(&mut response)?;
+
+        self.ca_split_import_response(&response)
+    }
+
+    /// Ingest the certifications that were generated by the split backend
+    ///
+    /// Returns the number of newly imported entries (entries that have
+    /// already been imported are ignored).
+    pub fn ca_split_import(&self, file: PathBuf) -> Result<usize> {
         match self.backend {
-            Backend::SplitFront => split::ca_split_show_queue(&*self.storage),
+            Backend::SplitFront => {
+                let imported = split::ca_split_import(&*self.storage, file)?;
+                events::emit(self, EventKind::QueueProcessed, None);
+
+                Ok(imported)
+            }
             _ => Err(anyhow::anyhow!(
                 "Operation is only supported on split mode front instances."
             )),
         }
     }
 
-    // -------- users / certs
+    /// Check that this CA deployment works end-to-end (e.g. after an
+    /// upgrade): database access, operations with the CA key, and the
+    /// handling of a throwaway user (generation, certification, WKD export,
+    /// revocation, and the split mode queue, if applicable).
+    ///
+    /// The data of this CA is not changed: the user operations run on a
+    /// temporary clone of the CA, which is deleted afterwards.
+    ///
+    /// Returns the outcome of each step.
+    pub fn smoketest(&self) -> Result<Vec<SmoketestStep>> {
+        smoketest::smoketest(self)
+    }
 
-    /// Get a list of all User Certs
-    pub fn user_certs_get_all(&self) -> Result<Vec<models::Cert>> {
-        let users = self.storage.users_sorted_by_name()?;
-        let mut user_certs = Vec::new();
-        for user in users {
-            user_certs.append(&mut self.get_certs_by_user(&user)?);
+    /// The currently not done entries in the queue of a split mode front
+    /// instance: queue id, creation time and request
+    pub fn ca_split_queue(&self) -> Result<Vec<(i32, DateTime<Utc>, QueueEntry)>> {
+        match self.backend {
+            Backend::SplitFront => split::ca_split_queue(&*self.storage),
+            _ => Err(anyhow::anyhow!(
+                "Operation is only supported on split mode front instances."
+            )),
         }
-        Ok(user_certs)
     }
 
-    /// Which certs will be expired in 'days' days?
+    /// Get the key policy of this CA, which defines minimum requirements
+    /// for imported user keys.
+    pub fn key_policy(&self) -> Result<KeyPolicy> {
+        policy::key_policy(self)
+    }
+
+    /// Set the key policy of this CA.
     ///
-    /// If a cert is not "alive" now, it will not get returned as expiring
-    /// (otherwise old/abandoned certs would clutter the results)
-    pub fn certs_expired(&self, days: u64) -> Result<HashMap<models::Cert, Option<SystemTime>>> {
-        cert::certs_expired(self, days)
+    /// The policy is enforced when new user keys are imported (existing
+    /// keys and updates to them are not affected).
+    pub fn set_key_policy(&self, policy: &KeyPolicy) -> Result<()> {
+        policy::set_key_policy(self, policy)
     }
 
-    /// Check if this Cert has been certified by the CA Key, returns all
-    /// certified User IDs
-    pub fn cert_check_ca_sig(&self, cert: &models::Cert) -> Result<CertificationStatus> {
-        cert::cert_check_ca_sig(self, cert).context("Failed while checking CA sig")
+    /// Get the URI policy of this CA, which defines which URI identifiers
+    /// in User IDs the CA certifies.
+    pub fn uri_policy(&self) -> Result<UriPolicy> {
+        policy::uri_policy(self)
     }
 
-    /// Check if this Cert has tsigned the CA Key
-    pub fn cert_check_tsig_on_ca(&self, cert: &models::Cert) -> Result<bool> {
-        cert::cert_check_tsig_on_ca(self, cert).context("Failed while checking tsig on CA")
+    /// Set the URI policy of this CA.
+    pub fn set_uri_policy(&self, policy: &UriPolicy) -> Result<()> {
+        policy::set_uri_policy(self, policy)
     }
 
-    /// Check if this CA has tsigned the bridge cert
-    pub fn check_tsig_on_bridge(&self, bridge: &models::Bridge) -> Result<bool> {
-        let ca = self.ca_get_cert_pub()?;
+    /// Get the notation policy of this CA, which defines the notation that
+    /// marks certifications as issued by this CA.
+    pub fn notation_policy(&self) -> Result<NotationPolicy> {
+        policy::notation_policy(self)
+    }
 
-        if let Some(br) = self.storage.cert_by_id(bridge.cert_id)? {
-            let bridge_cert = pgp::to_cert(br.pub_cert.as_bytes())?;
+    /// Set the notation policy of this CA (this only affects certifications
+    /// that are issued after the change).
+    pub fn set_notation_policy(&self, policy: &NotationPolicy) -> Result<()> {
+        policy::set_notation_policy(self, policy)
+    }
 
-            Ok(cert::check_tsig_on_cert(&ca, &bridge_cert)?)
-        } else {
-            Err(anyhow::anyhow!(
-                "No public key found for bridge to '{}'",
-                bridge.email
-            ))
-        }
+    /// The notation that this CA currently adds to the certifications it
+    /// issues (None, if the notation policy is not enabled).
+    ///
+    /// Use [Notation::is_on] to check if a signature carries it.
+    pub fn certification_notation(&self) -> Result<Option<Notation>> {
+        policy::certification_notation(self)
     }
 
-    /// Check all Certs for certifications from the CA. If a certification
-    /// expires in less than `threshold_days` and it is not marked as
-    /// 'inactive', make a new certification that is good for
-    /// `validity_days` and update the Cert.
-    pub fn certs_refresh_ca_certifications(
-        &self,
-        threshold_days: u64,
-        validity_days: u64,
-    ) -> Result<()> {
-        cert::certs_refresh_ca_certifications(self, threshold_days, validity_days)
+    /// Check a cert against the key policy of this CA.
+    ///
+    /// Returns the list of violations, which is empty if the cert is acceptable.
+    pub fn check_key_policy(&self, cert: &Cert) -> Result<Vec<KeyPolicyViolation>> {
+        Ok(policy::check(&self.key_policy()?, &self.policy()?, cert))
     }
 
-    /// Create a new OpenPGP CA User.
-    /// ("Centralized key creation workflow")
+    /// Get the crypto policy of this CA, which adjusts Sequoia's standard
+    /// policy (e.g. to accept SHA-1 in signatures on legacy certs).
+    pub fn crypto_policy(&self) -> Result<CryptoPolicy> {
+        policy::crypto_policy(self)
+    }
+
+    /// Set the crypto policy of this CA.
     ///
-    /// This generates a fresh OpenPGP key for the new User.
-    /// The private key is printed to stdout and NOT stored in OpenPGP CA.
-    /// The public key material (Cert) is stored in the OpenPGP CA database.
+    /// The policy is applied when certs and certifications are evaluated
+    /// (e.g. in expiry checks and when checking CA certifications).
+    pub fn set_crypto_policy(&self, policy: &CryptoPolicy) -> Result<()> {
+        policy::set_crypto_policy(self, policy)
+    }
+
+    /// The policy that this CA evaluates certs with: Sequoia's standard
+    /// policy, adjusted by the crypto policy of this CA.
+    pub fn policy(&self) -> Result<StandardPolicy<'static>> {
+        policy::standard_policy(&self.crypto_policy()?, None)
+    }
+
+    /// Like [Self::policy], but for evaluation at `time` (this affects
+    /// which algorithms the standard policy considers broken).
+    pub fn policy_at(&self, time: SystemTime) -> Result<StandardPolicy<'static>> {
+        policy::standard_policy(&self.crypto_policy()?, Some(time))
+    }
+
+    /// Get the retention policy of this CA, which limits how long done
+    /// queue entries and cert versions are kept.
+    pub fn retention_policy(&self) -> Result<RetentionPolicy> {
+        retention::retention_policy(self)
+    }
+
+    /// Set the retention policy of this CA.
     ///
-    /// The CA Cert is trust-signed by this new user key and the user
-    /// Cert is certified by the CA.
-    #[allow(clippy::too_many_arguments)]
-    pub fn user_new(
-        &self,
-        name: Option<&str>,
-        emails: &[&str],
-        duration_days: Option<u64>,
-        password: bool,
-        password_file: Option<String>,
-        output_format_minimal: bool,
-        cipher_suite: Option<CipherSuite>,
+    /// The policy is enforced by [Oca::db_cleanup].
+    pub fn set_retention_policy(&self, policy: &RetentionPolicy) -> Result<()> {
+        retention::set_retention_policy(self, policy)
+    }
+
+    /// Are notification emails of this CA signed (see [Oca::mail_text])?
+    pub fn mail_signing(&self) -> Result<bool> {
+        mail::mail_signing(self)
+    }
+
+    /// Enable or disable PGP/MIME signing of notification emails.
+    pub fn set_mail_signing(&self, enabled: bool) -> Result<()> {
+        mail::set_mail_signing(self, enabled)
+    }
+
+    /// The language of user-facing output and of generated artifacts (such
+    /// as the CA revocation file).
+    ///
+    /// This is the configured language of the CA, unless it is overridden
+    /// with the environment variable [i18n::ENV_LANGUAGE].
+    pub fn language(&self) -> Result<i18n::Language> {
+        i18n::language(self)
+    }
+
+    /// The language that is configured for this CA (ignoring the
+    /// environment override)
+    pub fn configured_language(&self) -> Result<i18n::Language> {
+        i18n::configured_language(self)
+    }
+
+    /// Configure the language of user-facing output for this CA
+    pub fn set_language(&self, lang: i18n::Language) -> Result<()> {
+        i18n::set_language(self, lang)
+    }
+
+    /// The body for a notification email with `text`.
+    ///
+    /// If mail signing is enabled, this is a PGP/MIME signed (RFC 3156)
+    /// multipart/signed entity, with a signature by the CA key. Otherwise,
+    /// it is a plain text/plain entity.
+    ///
+    /// Split-mode front instances have no access to the CA key, and don't
+    /// queue signatures for the back instance: with mail signing enabled,
+    /// this fails on a front instance.
+    pub fn mail_text(&self, text: &str) -> Result<MimeEntity> {
+        mail::mail_text(self, text)
+    }
+
+    /// Sign the MIME entity `part` with the CA key, as a PGP/MIME
+    /// multipart/signed entity (regardless of the mail signing setting).
+    ///
+    /// Fails on split-mode front instances.
+    pub fn mail_sign(&self, part: &MimeEntity) -> Result<MimeEntity> {
+        mail::sign(self, part)
+    }
+
+    /// Remove the rows that the retention policy doesn't retain.
+    ///
+    /// With `dry_run`, nothing is removed, but the report shows how many rows
+    /// would be.
+    pub fn db_cleanup(&self, dry_run: bool) -> Result<CleanupReport> {
+        retention::cleanup(self, dry_run)
+    }
+
+    /// Check all cert rows (except quarantined ones) for armored certs that
+    /// can't be parsed. Listings of certs skip such rows.
+    ///
+    /// With `repair`, certs that can be recovered by lenient parsing are
+    /// replaced with the recovered version, and the other rows are
+    /// quarantined for manual handling.
+    pub fn db_check_blobs(&self, repair: bool) -> Result<Vec<CertBlobReport>> {
+        doctor::check_blobs(self, repair)
+    }
+
+    /// Check the invariants of the database, without changing it: that all
+    /// certs parse and match the fingerprints of their rows, that emails
+    /// match User IDs, that revocations validate against their certs, that
+    /// pending queue entries refer to existing certs, and that the CA cert
+    /// has private key material exactly if the backend needs it.
+    pub fn check_consistency(&self) -> Result<ConsistencyReport> {
+        doctor::check_consistency(self)
+    }
+
+    /// Certs that have been quarantined by [Self::db_check_blobs]
+    pub fn certs_quarantined(&self) -> Result<Vec<models::CertQuarantine>> {
+        self.storage.certs_quarantined()
+    }
+
+    /// Release the cert row `cert_id` from quarantine (e.g. after its
+    /// armored cert has been fixed manually)
+    pub fn cert_quarantine_release(&self, cert_id: i32) -> Result<()> {
+        self.storage.cert_quarantine_release(cert_id)
+    }
+
+    /// Get the configuration of the event publishers of this CA
+    pub fn events_config(&self) -> Result<EventsConfig> {
+        events::events_config(self)
+    }
+
+    /// Configure the event publishers of this CA.
+    ///
+    /// Events are signed with the CA key, so publishing events is not
+    /// possible on split mode front instances.
+    pub fn set_events_config(&self, config: &EventsConfig) -> Result<()> {
+        events::set_events_config(self, config)
+    }
+
+    /// Add an additional event publisher to this Oca instance
+    /// (in addition to the publishers that are set up from [EventsConfig]).
+    pub fn add_event_publisher(&self, publisher: Box<dyn EventPublisher>) -> Result<()> {
+        events::add_publisher(self, publisher)
+    }
+
+    // -------- users / certs
+
+    /// Get a list of all User Certs
+    pub fn user_certs_get_all(&self) -> Result<Vec<models::Cert>> {
+        let users = self.storage.users_sorted_by_name()?;
+        let mut user_certs = Vec::new();
+        for user in users {
+            user_certs.append(&mut self.get_certs_by_user(&user)?);
+        }
+        Ok(user_certs)
+    }
+
+    /// Which certs will be expired in 'days' days?
+    ///
+    /// If a cert is not "alive" now, it will not get returned as expiring
+    /// (otherwise old/abandoned certs would clutter the results)
+    pub fn certs_expired(&self, days: u64) -> Result<HashMap<models::Cert, Option<SystemTime>>> {
+        cert::certs_expired(self, days)
+    }
+
+    /// Upcoming expirations within the next `horizon_days` days, sorted
+    /// chronologically: of the CA cert, of user certs, of the CA's
+    /// certifications on user certs, and of the CA's trust signatures on
+    /// bridged CAs.
+    ///
+    /// Certs that are already expired or revoked are not considered.
+    pub fn expiry_timeline(&self, horizon_days: u64) -> Result<Vec<TimelineEvent>> {
+        timeline::expiry_timeline(self, horizon_days)
+    }
+
+    /// Check if this Cert has been certified by the CA Key, returns all
+    /// certified User IDs.
+    ///
+    /// If the notation policy requires it, only certifications that carry the
+    /// CA's notation are considered.
+    pub fn cert_check_ca_sig(&self, cert: &models::Cert) -> Result<CertificationStatus> {
+        cert::cert_check_ca_sig(self, cert).context("Failed while checking CA sig")
+    }
+
+    /// Structured information about `cert`: its User IDs and (sub)keys,
+    /// with algorithms, expiration, revocations, and the verdict of the
+    /// policy of this CA on each component.
+    pub fn cert_info(&self, cert: &Cert) -> Result<CertInfo> {
+        cert_info::cert_info(self, cert)
+    }
+
+    /// Problems that `cert` will run into in the future (weak hashes that
+    /// the policy of this CA is going to reject, imminent expiry).
+    pub fn cert_warnings(&self, cert: &Cert) -> Result<Vec<CertWarning>> {
+        cert_info::cert_warnings(self, cert)
+    }
+
+    /// Problems with importing `cert` for a user with the email addresses
+    /// `emails`: the warnings of [Self::cert_warnings], keys with
+    /// insufficient length, User IDs without a valid self-signature, and
+    /// email addresses that no User ID contains.
+    pub fn cert_import_warnings(&self, cert: &Cert, emails: &[&str]) -> Result<Vec<CertWarning>> {
+        cert_info::cert_import_warnings(self, cert, emails)
+    }
+
+    /// Find all certifications (and certification revocations) that the CA
+    /// key has issued in `keyring` (e.g. a keyserver dump), verify them, and
+    /// reconcile them with the certifications recorded in the CA database.
+    ///
+    /// Certifications that verify, but are not recorded in the CA database,
+    /// may indicate misuse of the CA key.
+    pub fn extract_issued_certifications(&self, keyring: &[u8]) -> Result<IssuedCertifications> {
+        certifications::extract_issued_certifications(self, keyring)
+    }
+
+    /// Set a handler that is called with the progress of long operations
+    /// (updates of certs from WKD or keyservers, WKD exports, and renewals
+    /// of CA certifications), e.g. to show a progress bar, or to report the
+    /// status of a task. `None` removes the handler.
+    ///
+    /// The handler is called before each item is processed, and when the
+    /// operation is finished (with `done == total`).
+    pub fn set_progress_handler(&self, handler: Option<Box<dyn Fn(&Progress)>>) {
+        self.progress_handler.replace(handler);
+    }
+
+    /// Enable or disable the database cache for signature verification
+    /// results (enabled by default).
+    ///
+    /// With the cache enabled, checks of CA certifications only verify
+    /// signatures cryptographically once per version of a Cert.
+    pub fn set_verification_cache(&self, enabled: bool) {
+        self.verification_cache.set(enabled);
+    }
+
+    /// Retract the CA certification of one User ID of a Cert (e.g. if the
+    /// User ID was certified by mistake).
+    ///
+    /// The CA issues a certification revocation for `userid`, which is
+    /// stored with the Cert and included in exports. The rest of the Cert
+    /// remains untouched.
+    pub fn cert_retract_certification(
+        &self,
+        fingerprint: &str,
+        userid: &str,
+        reason: &str,
+    ) -> Result<()> {
+        cert::cert_retract_certification(self, fingerprint, userid, reason)
+    }
+
+    /// Check if this Cert has tsigned the CA Key
+    pub fn cert_check_tsig_on_ca(&self, cert: &models::Cert) -> Result<bool> {
+        cert::cert_check_tsig_on_ca(self, cert).context("Failed while checking tsig on CA")
+    }
+
+    /// Check if this CA has tsigned the bridge cert
+    pub fn check_tsig_on_bridge(&self, bridge: &models::Bridge) -> Result<bool> {
+        let ca = self.ca_get_cert_pub()?;
+
+        if let Some(br) = self.storage.cert_by_id(bridge.cert_id)? {
+            let bridge_cert = self.storage.cert_parsed(&br)?;
+
+            Ok(cert::check_tsig_on_cert(&ca, &bridge_cert)?)
+        } else {
+            Err(anyhow::anyhow!(
+                "No public key found for bridge to '{}'",
+                bridge.email
+            ))
+        }
+    }
+
+    /// Check all Certs for certifications from the CA. If a certification
+    /// expires in less than `threshold_days` and it is not marked as
+    /// 'inactive', make a new certification that is good for
+    /// `validity_days` and update the Cert.
+    pub fn certs_refresh_ca_certifications(
+        &self,
+        threshold_days: u64,
+        validity_days: u64,
+    ) -> Result<()> {
+        cert::certs_refresh_ca_certifications(
+            self,
+            threshold_days,
+            CertificationValidity::Days(validity_days),
+        )
+    }
+
+    /// Refresh CA certifications that expire in less than `threshold_days`,
+    /// like [Self::certs_refresh_ca_certifications]. The new certifications
+    /// are valid until `until` (e.g. an annual re-validation date), which
+    /// must be more than `threshold_days` in the future.
+    pub fn certs_refresh_ca_certifications_until(
+        &self,
+        threshold_days: u64,
+        until: DateTime<Utc>,
+    ) -> Result<()> {
+        cert::certs_refresh_ca_certifications(
+            self,
+            threshold_days,
+            CertificationValidity::Until(until),
+        )
+    }
+
+    /// Renew the certifications of User IDs whose certifications by the CA
+    /// all expire between `window_start` and `window_end` (e.g. before an
+    /// org-wide change freeze). The new certifications are good for
+    /// `validity_days`, which must reach past `window_end`.
+    ///
+    /// Only active certs that are neither expired nor revoked are renewed.
+    /// In split mode, the new certifications are queued for the back
+    /// instance.
+    ///
+    /// With `dry_run`, only the affected certifications are listed.
+    pub fn certifications_extend_expiring(
+        &self,
+        window_start: DateTime<Utc>,
+        window_end: DateTime<Utc>,
+        validity_days: u64,
+        dry_run: bool,
+    ) -> Result<CertificationExtensionReport> {
+        cert::certifications_extend_expiring(self, window_start, window_end, validity_days, dry_run)
+    }
+
+    /// Re-certify User IDs whose certifications by the CA have all expired
+    /// within the last `grace_days` days, on certs that are otherwise fine
+    /// (active, and neither expired nor revoked).
+    ///
+    /// The new certifications get the validity period of the expired ones.
+    /// In split mode, they are queued for the back instance.
+    ///
+    /// Returns the certs that got new certifications.
+    pub fn certs_recertify_expired(&self, grace_days: u64) -> Result<Vec<models::Cert>> {
+        cert::certs_recertify_expired(self, grace_days)
+    }
+
+    /// Create a new OpenPGP CA User.
+    /// ("Centralized key creation workflow")
+    ///
+    /// This generates a fresh OpenPGP key for the new User.
+    /// The private key is returned (see [NewUserKey]) and NOT stored in
+    /// OpenPGP CA. The public key material (Cert) is stored in the OpenPGP
+    /// CA database.
+    ///
+    /// The CA Cert is trust-signed by this new user key and the user
+    /// Cert is certified by the CA.
+    ///
+    /// If `password` is set, the private key is protected with a password
+    /// that is obtained according to `password_policy` (by default, a
+    /// diceware password is generated).
+    #[allow(clippy::too_many_arguments)]
+    pub fn user_new(
+        &self,
+        name: Option<&str>,
+        emails: &[&str],
+        duration_days: Option<u64>,
+        password: bool,
+        password_policy: Option<PasswordPolicy>,
+        cipher_suite: Option<CipherSuite>,
         enable_encryption_subkey: bool,
         enable_signing_subkey: bool,
         enable_authentication_subkey: bool,
-    ) -> Result<()> {
+    ) -> Result<NewUserKey> {
+        self.user_new_with_validity(
+            name,
+            emails,
+            duration_days,
+            None,
+            password,
+            password_policy,
+            cipher_suite,
+            None,
+            enable_encryption_subkey,
+            enable_signing_subkey,
+            enable_authentication_subkey,
+        )
+    }
+
+    /// Create a new user, like [Self::user_new].
+    ///
+    /// If `key_validity_days` is set, the generated user key expires after
+    /// that many days.
+    ///
+    /// If `key_version` is set, the user key is an OpenPGP key of that
+    /// version (by default, it has the version of the CA key, see
+    /// [KeyVersion]).
+    #[allow(clippy::too_many_arguments)]
+    pub fn user_new_with_validity(
+        &self,
+        name: Option<&str>,
+        emails: &[&str],
+        duration_days: Option<u64>,
+        key_validity_days: Option<u64>,
+        password: bool,
+        password_policy: Option<PasswordPolicy>,
+        cipher_suite: Option<CipherSuite>,
+        key_version: Option<KeyVersion>,
+        enable_encryption_subkey: bool,
+        enable_signing_subkey: bool,
+        enable_authentication_subkey: bool,
+    ) -> Result<NewUserKey> {
         // storage: ca_import_tsig + user_add
         cert::user_new(
             self,
             name,
             emails,
-            duration_days,
+            &[],
+            CertificationValidity::from_days(duration_days),
             password,
-            password_file,
-            output_format_minimal,
+            password_policy,
             cipher_suite,
+            key_version,
+            key_validity_days,
             enable_encryption_subkey,
             enable_signing_subkey,
             enable_authentication_subkey,
+            None,
+        )
+    }
+
+    /// Create a new user, with a key that is generated according to the
+    /// key profile `profile` (see [Oca::key_profile_set]).
+    ///
+    /// If `password_policy` is set, it overrides the password settings of
+    /// the profile (the key is password protected in that case).
+    pub fn user_new_with_profile(
+        &self,
+        name: Option<&str>,
+        emails: &[&str],
+        duration_days: Option<u64>,
+        profile: &str,
+        password_policy: Option<PasswordPolicy>,
+    ) -> Result<NewUserKey> {
+        let profile = key_profile::key_profile(self, profile)?;
+
+        cert::user_new_with_profile(
+            self,
+            name,
+            emails,
+            &[],
+            CertificationValidity::from_days(duration_days),
+            &profile,
+            password_policy,
+        )
+    }
+
+    /// Create a new user, and move the generated key to the OpenPGP card
+    /// `ident` ("Centralized key creation", for users with cards).
+    ///
+    /// The encryption, signing and authentication subkeys are uploaded to
+    /// the card, which must be empty and have the default Admin PIN. The
+    /// User and Admin PIN of the card are then set to new random values.
+    ///
+    /// Only the public key material is stored in OpenPGP CA. The private
+    /// key is never written to disk, and not returned: after onboarding,
+    /// the card holds the only copy.
+    ///
+    /// If `key_validity_days` is set, the generated user key expires after
+    /// that many days.
+    pub fn user_new_on_card(
+        &self,
+        ident: &str,
+        name: Option<&str>,
+        emails: &[&str],
+        duration_days: Option<u64>,
+        cipher_suite: Option<CipherSuite>,
+        key_validity_days: Option<u64>,
+    ) -> Result<CardUserKey> {
+        cert::user_new_on_card(
+            self,
+            ident,
+            name,
+            emails,
+            CertificationValidity::from_days(duration_days),
+            cipher_suite,
+            key_validity_days,
+        )
+    }
+
+    /// Create a new service account or role user: the generated key has
+    /// one User ID for each of `roles` (e.g. "Backup signing key 2024"),
+    /// and no email addresses. The CA certifies the role User IDs.
+    ///
+    /// The key is generated according to the key profile `profile`, if set
+    /// (otherwise with the default parameters of [Oca::user_new]).
+    ///
+    /// Role User IDs are never published via email-centric channels (WKD,
+    /// keylists that are restricted to emails or domains).
+    pub fn role_new(
+        &self,
+        name: Option<&str>,
+        roles: &[&str],
+        duration_days: Option<u64>,
+        profile: Option<&str>,
+        password_policy: Option<PasswordPolicy>,
+    ) -> Result<NewUserKey> {
+        if roles.is_empty() {
+            return Err(anyhow::anyhow!("A role key needs at least one role"));
+        }
+
+        let profile = match profile {
+            Some(profile) => key_profile::key_profile(self, profile)?,
+            None => KeyProfile::default(),
+        };
+
+        cert::user_new_with_profile(
+            self,
+            name,
+            &[],
+            roles,
+            CertificationValidity::from_days(duration_days),
+            &profile,
+            password_policy,
         )
     }
 
+    /// The key profiles that are configured in this CA (ordered by name)
+    pub fn key_profiles(&self) -> Result<Vec<KeyProfile>> {
+        key_profile::key_profiles(self)
+    }
+
+    /// Add a key profile, or replace the profile with the same name.
+    ///
+    /// Names may only contain ASCII letters, digits, '-' and '_'.
+    pub fn key_profile_set(&self, profile: KeyProfile) -> Result<()> {
+        key_profile::key_profile_set(self, profile)
+    }
+
+    /// Remove the key profile `name`
+    pub fn key_profile_remove(&self, name: &str) -> Result<()> {
+        key_profile::key_profile_remove(self, name)
+    }
+
+    /// Collect the artifacts for setting up the OpenPGP software of the
+    /// user with the cert `fingerprint` (e.g. after creating the user with
+    /// [Oca::user_new]): the user's key, the CA cert, and the CA cert with
+    /// the user's tsig.
+    ///
+    /// OpenPGP CA doesn't store private user keys. If `private_key` is
+    /// supplied (armored), the bundle contains it, otherwise it contains
+    /// the user's public cert.
+    pub fn user_provisioning_bundle(
+        &self,
+        fingerprint: &str,
+        private_key: Option<&str>,
+    ) -> Result<ProvisioningBundle> {
+        cert::user_provisioning_bundle(self, fingerprint, private_key)
+    }
+
     /// Import an existing OpenPGP Cert (public key) as a new OpenPGP CA user.
     ///
     /// The `cert` parameter accepts the user's armored public key.
@@ -980,17 +1980,267 @@ impl Oca {
         emails: &[&str],
         duration_days: Option<u64>,
     ) -> Result<()> {
-        cert::cert_import_new(self, cert, revoc_certs, name, emails, duration_days)
+        cert::cert_import_new(
+            self,
+            cert,
+            revoc_certs,
+            name,
+            emails,
+            &[],
+            &[],
+            CertificationValidity::from_days(duration_days),
+        )
+    }
+
+    /// Import an existing OpenPGP Cert as a new OpenPGP CA user, like
+    /// [Self::cert_import_new]. Additionally, the User IDs that carry one of
+    /// the URI identifiers in `uris` (e.g. machine identities such as
+    /// "spiffe://cluster/ns/job") are signed by the CA.
+    ///
+    /// Each of `uris` must be allowed by the URI policy of the CA (see
+    /// [Self::set_uri_policy]).
+    ///
+    /// Certs are included in keyring exports, but User IDs with URIs are
+    /// never published via email-centric channels (WKD, keylist).
+    pub fn cert_import_new_with_uris(
+        &self,
+        cert: &[u8],
+        revoc_certs: &[&[u8]],
+        name: Option<&str>,
+        emails: &[&str],
+        uris: &[&str],
+        duration_days: Option<u64>,
+    ) -> Result<()> {
+        cert::cert_import_new(
+            self,
+            cert,
+            revoc_certs,
+            name,
+            emails,
+            uris,
+            &[],
+            CertificationValidity::from_days(duration_days),
+        )
+    }
+
+    /// Import an existing OpenPGP Cert as a new OpenPGP CA user, like
+    /// [Self::cert_import_new_with_uris]. Additionally, the role User IDs
+    /// in `roles` (User IDs without an email address or URI, such as
+    /// "Backup signing key 2024" for a service account) are signed by the
+    /// CA.
+    #[allow(clippy::too_many_arguments)]
+    pub fn cert_import_new_with_roles(
+        &self,
+        cert: &[u8],
+        revoc_certs: &[&[u8]],
+        name: Option<&str>,
+        emails: &[&str],
+        uris: &[&str],
+        roles: &[&str],
+        duration_days: Option<u64>,
+    ) -> Result<()> {
+        cert::cert_import_new(
+            self,
+            cert,
+            revoc_certs,
+            name,
+            emails,
+            uris,
+            roles,
+            CertificationValidity::from_days(duration_days),
+        )
+    }
+
+    /// Import an existing OpenPGP Cert as a new OpenPGP CA user, like
+    /// [Self::cert_import_new_with_roles]. The certifications by the CA are
+    /// valid until `until` (e.g. the end of an employment contract), instead
+    /// of a number of days.
+    #[allow(clippy::too_many_arguments)]
+    pub fn cert_import_new_until(
+        &self,
+        cert: &[u8],
+        revoc_certs: &[&[u8]],
+        name: Option<&str>,
+        emails: &[&str],
+        uris: &[&str],
+        roles: &[&str],
+        until: DateTime<Utc>,
+    ) -> Result<()> {
+        cert::cert_import_new(
+            self,
+            cert,
+            revoc_certs,
+            name,
+            emails,
+            uris,
+            roles,
+            Some(CertificationValidity::Until(until)),
+        )
     }
 
     /// Update existing Cert in database (e.g. if the user has extended
     /// the expiry date)
+    ///
+    /// `cert` is merged into the stored cert. Updates that would roll back
+    /// the stored cert (lose packets, move the expiry to an earlier time, or
+    /// remove a revocation) are rejected with a [types::CertDowngradeError].
     pub fn cert_import_update(&self, cert: &[u8]) -> Result<()> {
-        cert::cert_import_update(self, cert)
+        cert::cert_import_update(self, cert, false)
+    }
+
+    /// Like [Self::cert_import_update], but without the downgrade checks
+    /// (for deliberate corrections, e.g. when a user has shortened the
+    /// expiry of their cert).
+    pub fn cert_import_update_allow_downgrade(&self, cert: &[u8]) -> Result<()> {
+        cert::cert_import_update(self, cert, true)
     }
 
-    /// Mark a cert as "delisted" in the OpenPGP CA database.
-    /// As a result, the cert will not be exported to WKD anymore.
+    /// Like [Self::cert_import_update], for updates that rotate the
+    /// encryption subkey of a cert.
+    ///
+    /// The update is rejected unless it adds an encryption subkey that is
+    /// newer than the current newest one, and that is younger than the
+    /// maximum subkey age of the key policy (if set).
+    pub fn cert_import_rotation(&self, cert: &[u8]) -> Result<()> {
+        rotation::cert_import_rotation(self, cert)
+    }
+
+    /// The newest valid encryption subkey of `cert`, and when it is due for
+    /// rotation according to the key policy.
+    pub fn subkey_rotation(&self, cert: &Cert) -> Result<SubkeyRotation> {
+        rotation::subkey_rotation(self, cert)
+    }
+
+    /// Active, valid certs whose newest encryption subkey exceeds the
+    /// maximum subkey age of the key policy within the next `days` (or that
+    /// have no valid encryption subkey).
+    ///
+    /// Fails if the key policy doesn't limit the age of encryption subkeys.
+    pub fn certs_subkey_rotation_due(
+        &self,
+        days: u64,
+    ) -> Result<Vec<(models::Cert, SubkeyRotation)>> {
+        rotation::subkey_rotation_due(self, days)
+    }
+
+    /// A notification email that reminds the owner of `cert` to rotate the
+    /// encryption subkey (see [Self::mail_text]).
+    pub fn subkey_rotation_reminder(
+        &self,
+        cert: &models::Cert,
+        rotation: &SubkeyRotation,
+    ) -> Result<MimeEntity> {
+        rotation::subkey_rotation_reminder(self, cert, rotation)
+    }
+
+    /// Import certifications that were made with the CA key outside of
+    /// OpenPGP CA (e.g. with `sq` on an airgapped machine).
+    ///
+    /// `cert` is a copy of a user cert that is stored in this CA. Only the
+    /// certifications (and certification revocations) by the CA key on
+    /// User IDs of the stored cert are merged, after checking that they
+    /// are valid and acceptable by the CA policy. All other packets in
+    /// `cert` are ignored.
+    ///
+    /// Returns the number of newly merged signatures.
+    pub fn import_certifications(&self, cert: &[u8]) -> Result<usize> {
+        cert::import_certifications(self, cert)
+    }
+
+    /// Certify the User IDs of the cert `fingerprint` that contain one of
+    /// `emails` (User IDs that the CA has already certified are skipped).
+    ///
+    /// The certifications are made according to `profile`, which is noted
+    /// in the cert's version history. In split mode, they are queued for
+    /// the back instance.
+    pub fn cert_certify(
+        &self,
+        fingerprint: &str,
+        emails: &[&str],
+        duration_days: Option<u64>,
+        profile: CertificationProfile,
+    ) -> Result<()> {
+        cert::cert_certify(
+            self,
+            fingerprint,
+            emails,
+            CertificationValidity::from_days(duration_days),
+            profile,
+        )
+    }
+
+    /// Certify the User IDs of the cert `fingerprint` that contain one of
+    /// `emails`, like [Self::cert_certify]. The certifications are valid
+    /// until `until` (e.g. the end of an employment contract).
+    pub fn cert_certify_until(
+        &self,
+        fingerprint: &str,
+        emails: &[&str],
+        until: DateTime<Utc>,
+        profile: CertificationProfile,
+    ) -> Result<()> {
+        cert::cert_certify(
+            self,
+            fingerprint,
+            emails,
+            Some(CertificationValidity::Until(until)),
+            profile,
+        )
+    }
+
+    /// Get the previous versions of a cert, oldest first.
+    ///
+    /// A previous version is recorded each time a cert changes in the
+    /// OpenPGP CA database (the number of retained versions is bounded,
+    /// see [Self::cert_versions_set_retention]).
+    pub fn cert_versions(&self, fingerprint: &str) -> Result<Vec<models::CertVersion>> {
+        let cert = self
+            .cert_get_by_fingerprint(fingerprint)?
+            .ok_or_else(|| anyhow::anyhow!("No cert with fingerprint {} found", fingerprint))?;
+
+        self.storage.cert_versions(&cert)
+    }
+
+    /// Show the packet-level changes between the previous cert version
+    /// `from` and the version `to` (or the current cert, if `to` is None).
+    pub fn cert_versions_diff(&self, from: i32, to: Option<i32>) -> Result<CertDiff> {
+        cert::cert_versions_diff(self, from, to)
+    }
+
+    /// The cert `fingerprint` as the CA stored it at `time`, reconstructed
+    /// from its previous versions, with the User IDs that were certified by
+    /// the CA at that time.
+    ///
+    /// This answers audit questions like "was this key certified by the CA
+    /// on 2023-06-01?" without restoring old database backups. The answer
+    /// is only as good as the version history of the cert (see
+    /// [Self::cert_versions_set_retention] and [CertAsOf::incomplete]).
+    pub fn cert_as_of(&self, fingerprint: &str, time: DateTime<Utc>) -> Result<CertAsOf> {
+        cert::cert_as_of(self, fingerprint, time)
+    }
+
+    /// Replace a cert with one of its previous versions.
+    pub fn cert_version_restore(&self, id: i32) -> Result<()> {
+        cert::cert_version_restore(self, id)
+    }
+
+    /// The number of previous versions that are retained for each cert
+    pub fn cert_versions_retention(&self) -> Result<u32> {
+        self.storage.cert_versions_keep()
+    }
+
+    /// Set the number of previous versions that are retained for each cert.
+    ///
+    /// With a value of 0, no previous versions are recorded.
+    /// Excess versions are pruned on the next update of a cert.
+    pub fn cert_versions_set_retention(&self, keep: u32) -> Result<()> {
+        self.storage.cert_versions_set_keep(keep)
+    }
+
+    /// Mark a cert as "delisted" in the OpenPGP CA database, on all export
+    /// channels (see [ExportChannel]).
+    /// As a result, the cert will not be exported to WKD, keylists,
+    /// keyservers or certrings anymore.
     ///
     /// Note: existing CA certifications will still get renewed for delisted
     /// certs, but as the cert is not published via WKD, third parties might not
@@ -1005,14 +2255,498 @@ impl Oca {
         self.storage.cert_delist(fp)
     }
 
-    /// Mark a certificate as "deactivated".
-    /// It will continue to be listed and exported to WKD.
-    /// However, the certification by our CA will expire and not get renewed.
+    /// Set whether a cert is delisted on one export `channel` (e.g. to
+    /// exclude it from keylists, while still publishing it via WKD).
+    ///
+    /// The cert counts as "delisted" if it is delisted on all channels.
+    pub fn cert_set_delisted(
+        &self,
+        fp: &str,
+        channel: ExportChannel,
+        delisted: bool,
+    ) -> Result<()> {
+        self.storage.cert_set_delisted(fp, channel, delisted)
+    }
+
+    /// Mark a certificate as "deactivated".
+    /// It will continue to be listed and exported to WKD.
+    /// However, the certification by our CA will expire and not get renewed.
+    ///
+    /// This approach is probably appropriate in most cases to phase out a
+    /// certificate.
+    pub fn cert_deactivate(&self, fp: &str) -> Result<()> {
+        self.storage.cert_deactivate(fp)
+    }
+
+    /// Offboard the user with `email` (e.g. when an employee leaves): revoke,
+    /// deactivate and delist all of their certs.
+    ///
+    /// For each cert, a stored revocation is applied (and published, if
+    /// revocation publication is configured). If no revocation is on file,
+    /// the CA retracts its certifications of the cert's User IDs instead,
+    /// with `reason`.
+    ///
+    /// Returns a summary of how each cert was handled.
+    pub fn user_offboard(&self, email: &str, reason: &str) -> Result<OffboardReport> {
+        offboard::user_offboard(self, email, reason)
+    }
+
+    /// Permanently delete the cert `fp` from the OpenPGP CA database, along
+    /// with its emails, revocations, previous versions, tags and other
+    /// associated data. If the user of the cert has no other certs, the
+    /// user is deleted as well.
+    ///
+    /// This is intended for certs that were imported by mistake (e.g.
+    /// because they contain personal data that must be removed). To phase
+    /// out a cert, use [Self::cert_deactivate].
+    ///
+    /// Certs that are used by a bridge can't be purged.
+    /// Entries of the certification transparency log are not affected.
+    pub fn cert_purge(&self, fp: &str) -> Result<()> {
+        self.storage.cert_purge(fp)?;
+
+        let fp = pgp::normalize_fp(fp)?;
+        events::emit(self, EventKind::CertPurged, Some(&fp));
+
+        Ok(())
+    }
+
+    /// Set (or with None: clear) the free-form notes on the cert `fp`
+    pub fn cert_notes_set(&self, fp: &str, notes: Option<&str>) -> Result<()> {
+        self.storage.cert_notes_set(fp, notes, false)
+    }
+
+    /// Add `note` as a new line to the notes on the cert `fp`
+    pub fn cert_notes_append(&self, fp: &str, note: &str) -> Result<()> {
+        self.storage.cert_notes_set(fp, Some(note), true)
+    }
+
+    /// Set (or with None: clear) the free-form notes on the user that the
+    /// cert `fp` belongs to
+    pub fn user_notes_set(&self, fp: &str, notes: Option<&str>) -> Result<()> {
+        self.storage.user_notes_set(fp, notes, false)
+    }
+
+    /// Add `note` as a new line to the notes on the user that the cert `fp`
+    /// belongs to
+    pub fn user_notes_append(&self, fp: &str, note: &str) -> Result<()> {
+        self.storage.user_notes_set(fp, Some(note), true)
+    }
+
+    /// Set if the certs of the user of the cert `fp` are published via WKD
+    /// (the default).
+    ///
+    /// Users who opt out of WKD publication remain certified by the CA, and
+    /// their certs are still available via other exports.
+    pub fn user_set_wkd_publish(&self, fp: &str, publish: bool) -> Result<()> {
+        self.storage.user_wkd_publish_set(fp, publish)
+    }
+
+    /// The tags on the cert `cert` (by name)
+    pub fn cert_tags(&self, cert: &models::Cert) -> Result<BTreeMap<String, String>> {
+        Ok(tags::to_map(self.storage.tags_by_cert(cert)?))
+    }
+
+    /// The tags on the user `user` (by name)
+    pub fn user_tags(&self, user: &models::User) -> Result<BTreeMap<String, String>> {
+        Ok(tags::to_map(self.storage.tags_by_user(user)?))
+    }
+
+    /// Set the tag `name` on the cert `fp` to `value` (replacing an existing
+    /// value).
+    ///
+    /// Tags are key-value metadata for use by the CA admins and by
+    /// integrations (e.g. the employee ID of a user in an HR system).
+    /// Names may only contain ASCII letters, digits, '-' and '_'.
+    pub fn cert_tag_set(&self, fp: &str, name: &str, value: &str) -> Result<()> {
+        tags::cert_tag_set(self, fp, name, Some(value))
+    }
+
+    /// Remove the tag `name` from the cert `fp`
+    pub fn cert_tag_remove(&self, fp: &str, name: &str) -> Result<()> {
+        tags::cert_tag_set(self, fp, name, None)
+    }
+
+    /// Set the tag `name` on the user that the cert `fp` belongs to (see
+    /// [Self::cert_tag_set])
+    pub fn user_tag_set(&self, fp: &str, name: &str, value: &str) -> Result<()> {
+        tags::user_tag_set(self, fp, name, Some(value))
+    }
+
+    /// Remove the tag `name` from the user that the cert `fp` belongs to
+    pub fn user_tag_remove(&self, fp: &str, name: &str) -> Result<()> {
+        tags::user_tag_set(self, fp, name, None)
+    }
+
+    /// Certs that have the tag `name`, or whose user has it (optionally
+    /// only if the tag has the value `value`)
+    pub fn certs_by_tag(&self, name: &str, value: Option<&str>) -> Result<Vec<models::Cert>> {
+        tags::certs_by_tag(self, name, value)
+    }
+
+    /// All groups of users, ordered by name
+    pub fn groups(&self) -> Result<Vec<models::Group>> {
+        self.storage.groups()
+    }
+
+    /// Add a new, empty group of users (e.g. a department).
+    ///
+    /// Operations such as certification, re-certification, exports and
+    /// notifications can be performed per group, to roll them out one
+    /// group at a time. Names may only contain ASCII letters, digits, '-'
+    /// and '_'.
+    pub fn group_new(&self, name: &str, description: Option<&str>) -> Result<models::Group> {
+        groups::group_new(self, name, description)
+    }
+
+    /// Delete the group `name` (the users in the group are not affected)
+    pub fn group_delete(&self, name: &str) -> Result<()> {
+        self.storage.group_delete(name)
+    }
+
+    /// Add the user that the cert `fp` belongs to to the group `name`.
+    ///
+    /// Returns false if the user already was a member.
+    pub fn group_add_member(&self, name: &str, fp: &str) -> Result<bool> {
+        self.storage.group_member_set(name, fp, true)
+    }
+
+    /// Remove the user that the cert `fp` belongs to from the group `name`.
+    ///
+    /// Returns false if the user wasn't a member.
+    pub fn group_remove_member(&self, name: &str, fp: &str) -> Result<bool> {
+        self.storage.group_member_set(name, fp, false)
+    }
+
+    /// The users in the group `name`, ordered by name
+    pub fn group_users(&self, name: &str) -> Result<Vec<models::User>> {
+        groups::group_users(self, name)
+    }
+
+    /// The groups that `user` is a member of, ordered by name
+    pub fn user_groups(&self, user: &models::User) -> Result<Vec<models::Group>> {
+        self.storage.groups_by_user(user)
+    }
+
+    /// The certs of the users in the group `name`, ordered by fingerprint
+    pub fn group_certs(&self, name: &str) -> Result<Vec<models::Cert>> {
+        groups::group_certs(self, name)
+    }
+
+    /// The email addresses of the certs of the users in the group `name`
+    /// (e.g. for [Self::export_wkd_for_emails])
+    pub fn group_emails(&self, name: &str) -> Result<Vec<String>> {
+        groups::group_emails(self, name)
+    }
+
+    /// Certify the User IDs of the active certs in the group `name` that
+    /// carry one of the email addresses of their cert, and that the CA
+    /// hasn't certified yet.
+    ///
+    /// In split mode, the certifications are queued for the back instance.
+    /// Returns the certs that got new certifications.
+    pub fn group_certify(
+        &self,
+        name: &str,
+        duration_days: Option<u64>,
+    ) -> Result<Vec<models::Cert>> {
+        groups::group_certify(self, name, CertificationValidity::from_days(duration_days))
+    }
+
+    /// Refresh CA certifications on the certs in the group `name`, like
+    /// [Self::certs_refresh_ca_certifications]
+    pub fn group_refresh_ca_certifications(
+        &self,
+        name: &str,
+        threshold_days: u64,
+        validity_days: u64,
+    ) -> Result<()> {
+        groups::group_refresh_ca_certifications(
+            self,
+            name,
+            threshold_days,
+            CertificationValidity::Days(validity_days),
+        )
+    }
+
+    /// Write the CA cert and the certs in the group `name` as one certring
+    /// to the file `path` (see [Self::export_certring])
+    pub fn export_group_certring(
+        &self,
+        name: &str,
+        path: &Path,
+        minimize: bool,
+        format: CertFormat,
+        compat: ExportCompat,
+        compression: ExportCompression,
+    ) -> Result<()> {
+        export::export_certring_of(
+            self,
+            path,
+            groups::group_certs(self, name)?,
+            minimize,
+            format,
+            compat,
+            compression,
+        )
+    }
+
+    /// Export the User IDs of the certs in the group `name` into a wkd
+    /// directory structure, like [Self::export_wkd_for_emails]
+    pub fn export_group_wkd(
+        &self,
+        name: &str,
+        domain: &str,
+        path: &Path,
+        minimize: bool,
+        compat: ExportCompat,
+    ) -> Result<()> {
+        let emails = groups::group_emails(self, name)?;
+        let emails: Vec<&str> = emails.iter().map(String::as_str).collect();
+
+        export::wkd_export(self, domain, path, minimize, compat, Some(&emails))
+    }
+
+    /// Export a keylist with the CA cert and the CA-certified User IDs of
+    /// the certs in the group `name` (see [Self::export_keylist])
+    pub fn export_group_keylist(
+        &self,
+        name: &str,
+        path: &Path,
+        signature_uri: &str,
+        force: bool,
+    ) -> Result<()> {
+        export::export_group_keylist(self, name, path, signature_uri, force)
+    }
+
+    /// A notification email with `text` for the users in the group `name`
+    /// (see [Self::mail_text]), with the CA-certified email addresses of
+    /// their active certs as recipients
+    pub fn group_notification(&self, name: &str, text: &str) -> Result<GroupNotification> {
+        groups::group_notification(self, name, text)
+    }
+
+    /// Export a mapping of the identities and keys of the currently valid
+    /// user certs, for an X.509 CA that issues matching S/MIME certificates.
+    ///
+    /// Entries show the X.509 serial number that was recorded for a cert
+    /// (see [Self::x509_serial_set]), and if the certified email addresses
+    /// changed since then.
+    ///
+    /// Experimental: the format of the mapping may change.
+    pub fn export_x509_mapping(&self) -> Result<X509Mapping> {
+        x509::mapping(self)
+    }
+
+    /// Record the serial number (in hex) of the X.509 certificate that was
+    /// issued for the cert `fp`, along with the cert's currently certified
+    /// email addresses.
+    ///
+    /// With None, the recorded serial number is removed.
+    pub fn x509_serial_set(&self, fp: &str, serial: Option<&str>) -> Result<()> {
+        x509::serial_set(self, fp, serial)
+    }
+
+    /// The serial number of the X.509 certificate that was recorded for `cert`
+    pub fn x509_serial(&self, cert: &models::Cert) -> Result<Option<String>> {
+        x509::serial(self, cert)
+    }
+
+    /// Move the cert `fp` to the existing user named `to` (or, with
+    /// `new_user`, to a new user with that name).
+    ///
+    /// This is the explicit way to transfer a cert between users: each move
+    /// is recorded, with an optional `reason` (see
+    /// [Self::cert_reassignments]). Returns the new user of the cert.
+    pub fn cert_reassign(
+        &self,
+        fp: &str,
+        to: &str,
+        new_user: bool,
+        reason: Option<&str>,
+    ) -> Result<models::User> {
+        let user = self.storage.cert_reassign(fp, to, new_user, reason)?;
+
+        let fp = pgp::normalize_fp(fp)?;
+        events::emit(self, EventKind::CertReassigned, Some(&fp));
+
+        Ok(user)
+    }
+
+    /// The recorded moves of the cert `fp` between users, oldest first
+    pub fn cert_reassignments(&self, fp: &str) -> Result<Vec<models::CertReassignment>> {
+        let cert = self
+            .cert_get_by_fingerprint(fp)?
+            .ok_or_else(|| anyhow::anyhow!("No cert with fingerprint {} found", fp))?;
+
+        self.storage.cert_reassignments(&cert)
+    }
+
+    /// The statement that a user signs with their current key `old_fp`, to
+    /// ask for its replacement with the key `new_fp` (see
+    /// [Self::key_replacement_submit])
+    pub fn key_replacement_statement(&self, old_fp: &str, new_fp: &str) -> Result<String> {
+        replacement::handover_statement(self, old_fp, new_fp)
+    }
+
+    /// Submit `new_cert` as the replacement for the user cert `old_fp`.
+    ///
+    /// `handover` is an armored detached signature over the
+    /// [Self::key_replacement_statement], made with the key `old_fp`. The
+    /// replacement is staged until an admin approves it (see
+    /// [Self::key_replacement_approve]).
+    pub fn key_replacement_submit(
+        &self,
+        old_fp: &str,
+        new_cert: &[u8],
+        handover: &str,
+    ) -> Result<models::KeyReplacement> {
+        replacement::submit(self, old_fp, new_cert, handover)
+    }
+
+    /// All key replacement requests (including decided ones)
+    pub fn key_replacements(&self) -> Result<Vec<models::KeyReplacement>> {
+        self.storage.key_replacements()
+    }
+
+    /// Key replacement requests that wait for approval
+    pub fn key_replacements_pending(&self) -> Result<Vec<models::KeyReplacement>> {
+        Ok(self
+            .key_replacements()?
+            .into_iter()
+            .filter(|r| r.status == KeyReplacementStatus::Pending.name())
+            .collect())
+    }
+
+    /// Approve the pending key replacement `id`: the replacement cert is
+    /// stored for the user of the old cert, and certified for its email
+    /// addresses. With `deactivate_old`, the old cert is marked as inactive.
+    pub fn key_replacement_approve(
+        &self,
+        id: i32,
+        validity_days: Option<u64>,
+        deactivate_old: bool,
+    ) -> Result<models::Cert> {
+        replacement::approve(self, id, validity_days, deactivate_old)
+    }
+
+    /// Reject the pending key replacement `id`
+    pub fn key_replacement_reject(&self, id: i32, reason: Option<&str>) -> Result<()> {
+        replacement::reject(self, id, reason)
+    }
+
+    /// Queue `change`, instead of applying it directly: it is applied only
+    /// when an admin approves it (see [Self::proposal_approve]).
+    ///
+    /// This is used by restd in approval mode, so that semi-trusted
+    /// frontends can submit changes without changing the CA database.
+    pub fn proposal_submit(&self, change: ProposedChange) -> Result<Proposal> {
+        proposal::submit(self, change)
+    }
+
+    /// All proposed changes (including decided ones)
+    pub fn proposals(&self) -> Result<Vec<Proposal>> {
+        proposal::list(self, true)
+    }
+
+    /// Proposed changes that wait for a decision by an admin
+    pub fn proposals_pending(&self) -> Result<Vec<Proposal>> {
+        proposal::list(self, false)
+    }
+
+    /// Apply the change of the pending proposal `id`
+    pub fn proposal_approve(&self, id: i32) -> Result<Proposal> {
+        proposal::approve(self, id)
+    }
+
+    /// Reject the pending proposal `id`
+    pub fn proposal_reject(&self, id: i32, reason: Option<&str>) -> Result<()> {
+        proposal::reject(self, id, reason)
+    }
+
+    /// Add `pattern` to the blocklist: certs with a matching fingerprint, or
+    /// email addresses in a matching domain, are not imported or certified
+    /// by this CA anymore.
+    ///
+    /// Updates of certs that are already stored (e.g. revocations) are still
+    /// accepted.
+    pub fn blocklist_add(
+        &self,
+        kind: BlocklistKind,
+        pattern: &str,
+        reason: Option<&str>,
+    ) -> Result<models::BlocklistEntry> {
+        blocklist::add(self, kind, pattern, reason)
+    }
+
+    /// Remove the entry `id` from the blocklist (the entry is retained in
+    /// the blocklist log, see [Self::blocklist_log])
+    pub fn blocklist_remove(&self, id: i32) -> Result<models::BlocklistEntry> {
+        blocklist::remove(self, id)
+    }
+
+    /// The active entries of the blocklist
+    pub fn blocklist(&self) -> Result<Vec<models::BlocklistEntry>> {
+        blocklist::entries(self)
+    }
+
+    /// All entries that have ever been added to the blocklist, with the
+    /// time of their removal (if any)
+    pub fn blocklist_log(&self) -> Result<Vec<models::BlocklistEntry>> {
+        self.storage.blocklist()
+    }
+
+    /// The blocklist entries that match a key of `cert` (if any), or the
+    /// domain of one of `emails`
+    pub fn blocklist_check(
+        &self,
+        cert: Option<&Cert>,
+        emails: &[&str],
+    ) -> Result<Vec<BlocklistMatch>> {
+        blocklist::matches(self, cert, emails)
+    }
+
+    /// The certification transparency log: all certifications and
+    /// revocations that the CA has issued, in order.
+    ///
+    /// Each entry contains the hash of the previous entry. The hash of an
+    /// entry is the SHA-256 hash (as lowercase hex) of its fields, each
+    /// followed by a newline: `seq`, `created` (RFC 3339, in UTC, with
+    /// seconds precision), `kind`, `fingerprint`, `signature`, `prev_hash`.
+    pub fn ct_log(&self) -> Result<Vec<CtLogEntry>> {
+        ct_log::entries(self)
+    }
+
+    /// Export the certification transparency log as JSON lines (one
+    /// [CtLogEntry] per line)
+    pub fn ct_log_export(&self) -> Result<String> {
+        ct_log::export(self)
+    }
+
+    /// Check that the certification transparency log in the CA database is
+    /// an unbroken hash chain.
     ///
-    /// This approach is probably appropriate in most cases to phase out a
-    /// certificate.
-    pub fn cert_deactivate(&self, fp: &str) -> Result<()> {
-        self.storage.cert_deactivate(fp)
+    /// Returns the number of entries, and the hash of the last entry.
+    pub fn ct_log_verify(&self) -> Result<CtLogReport> {
+        ct_log::verify(&self.ct_log()?)
+    }
+
+    /// Check an exported certification transparency log (see
+    /// [Self::ct_log_export]), like [Self::ct_log_verify]
+    pub fn ct_log_verify_export(jsonl: &[u8]) -> Result<CtLogReport> {
+        ct_log::verify_export(jsonl)
+    }
+
+    /// Get the user with the database id `id`
+    pub fn user_by_id(&self, id: i32) -> Result<Option<models::User>> {
+        self.storage.user_by_id(id)
+    }
+
+    /// Search certs that contain `text` (case-insensitive) in their
+    /// fingerprint, the name of their user, their email addresses, User IDs,
+    /// or the notes or tags on the cert or its user.
+    pub fn search(&self, text: &str) -> Result<Vec<SearchMatch>> {
+        search::search(self, text)
     }
 
     /// Get Cert by fingerprint.
@@ -1025,6 +2759,19 @@ impl Oca {
         self.storage.cert_by_fp(&fp)
     }
 
+    /// Look up the cert that relying parties should use for `email`.
+    ///
+    /// Returns the currently valid cert (not expired, not revoked) that has
+    /// a User ID for `email` with a valid certification by this CA, or None.
+    /// Certs that are delisted on all export channels are not considered.
+    /// If multiple certs qualify, the most recently created one is returned.
+    ///
+    /// Results are cached for a few minutes, and re-evaluated early if a
+    /// cert for `email` changes.
+    pub fn lookup_valid_cert(&self, email: &str) -> Result<Option<Cert>> {
+        cert::lookup_valid_cert(self, email)
+    }
+
     /// Get a list of all Certs for one User
     pub fn get_certs_by_user(&self, user: &models::User) -> Result<Vec<models::Cert>> {
         self.storage.certs_by_user(user)
@@ -1092,6 +2839,14 @@ impl Oca {
 
                     println!();
                 }
+
+                if !sigs_by_ca.expired.is_empty() {
+                    println!("Expired CA certification for {}:", db_cert.fingerprint);
+                    for uid in &sigs_by_ca.expired {
+                        println!("  {}", String::from_utf8_lossy(uid.value()));
+                    }
+                    println!();
+                }
             }
         }
 
@@ -1136,6 +2891,49 @@ impl Oca {
         Ok(())
     }
 
+    /// The users that have a cert with the email address `email`, with
+    /// details about all of their certs: User IDs and (sub)keys, CA
+    /// certification status, trust signature on the CA cert, and
+    /// revocations on file
+    pub fn user_dossiers(&self, email: &str) -> Result<Vec<UserDossier>> {
+        let mut users: Vec<models::User> = vec![];
+        for cert in self.certs_by_email(email)? {
+            if let Some(user) = self.cert_get_users(&cert)? {
+                if !users.iter().any(|u| u.id == user.id) {
+                    users.push(user);
+                }
+            }
+        }
+
+        users
+            .into_iter()
+            .map(|user| {
+                let certs = self
+                    .get_certs_by_user(&user)?
+                    .into_iter()
+                    .map(|cert| {
+                        let c = self.storage.cert_parsed(&cert)?;
+
+                        Ok(CertDossier {
+                            info: self.cert_info(&c)?,
+                            certification: self.cert_check_ca_sig(&cert)?,
+                            tsig_on_ca: self.cert_check_tsig_on_ca(&cert)?,
+                            revocations: self.revocations_get(&cert)?,
+                            tags: self.cert_tags(&cert)?,
+                            cert,
+                        })
+                    })
+                    .collect::<Result<_>>()?;
+
+                Ok(UserDossier {
+                    tags: self.user_tags(&user)?,
+                    user,
+                    certs,
+                })
+            })
+            .collect()
+    }
+
     pub fn print_users(&self) -> Result<()> {
         for db_user in self.users_get_all()? {
             for db_cert in self.get_certs_by_user(&db_user)? {
@@ -1146,6 +2944,27 @@ impl Oca {
                 if let Some(name) = &db_user.name {
                     println!(" User '{name}'");
                 }
+                if !db_user.wkd_publish {
+                    println!(" User opted out of WKD publication");
+                }
+                if let Some(notes) = &db_user.notes {
+                    println!(" User notes:");
+                    for line in notes.lines() {
+                        println!("   {line}");
+                    }
+                }
+                if let Some(notes) = &db_cert.notes {
+                    println!(" Notes:");
+                    for line in notes.lines() {
+                        println!("   {line}");
+                    }
+                }
+                for (name, value) in self.user_tags(&db_user)? {
+                    println!(" User tag {name}: {value}");
+                }
+                for (name, value) in self.cert_tags(&db_cert)? {
+                    println!(" Tag {name}: {value}");
+                }
 
                 if !sig_by_ca.certified.is_empty() {
                     println!(" Identities certified by this CA:");
@@ -1158,9 +2977,9 @@ impl Oca {
                     println!(" Has trust-signed this CA");
                 }
 
-                let c = pgp::to_cert(db_cert.pub_cert.as_bytes())?;
+                let c = self.storage.cert_parsed(&db_cert)?;
 
-                match pgp::get_expiry(&c) {
+                match pgp::get_expiry(&c, &self.policy()?) {
                     Ok(Some(exp)) => {
                         let datetime: DateTime<Utc> = exp.into();
                         println!(" Expiration {}", datetime.format("%d/%m/%Y"));
@@ -1198,7 +3017,11 @@ impl Oca {
     ///
     /// Verifies that applying the revocation cert can be validated by the
     /// cert. Only if this is successful is the revocation stored.
-    pub fn revocation_add(&self, revoc_cert: &[u8]) -> Result<()> {
+    ///
+    /// The revocation is stored unpublished (it is not applied to the cert).
+    /// Returns the stored revocation (if an equivalent revocation was
+    /// already stored, that one is returned).
+    pub fn revocation_add(&self, revoc_cert: &[u8]) -> Result<models::Revocation> {
         self.storage.revocation_add(revoc_cert)
     }
 
@@ -1206,7 +3029,21 @@ impl Oca {
     pub fn revocation_add_from_file(&self, filename: &Path) -> Result<()> {
         let rev = std::fs::read(filename)?;
 
-        self.revocation_add(&rev)
+        self.revocation_add(&rev)?;
+
+        Ok(())
+    }
+
+    /// Get all Revocations in the OpenPGP CA database
+    pub fn revocations_get_all(&self) -> Result<Vec<models::Revocation>> {
+        self.storage.revocations()
+    }
+
+    /// Remove a revocation from the OpenPGP CA database.
+    ///
+    /// Only revocations that have not been applied can be removed.
+    pub fn revocation_delete(&self, hash: &str) -> Result<()> {
+        self.storage.revocation_delete(hash)
     }
 
     /// Get a Revocation by hash
@@ -1221,8 +3058,49 @@ impl Oca {
     /// Apply a revocation.
     ///
     /// The revocation is merged into out copy of the OpenPGP Cert.
+    ///
+    /// If revocation publication is configured for this CA (see
+    /// [Self::set_revocation_publication]), the revoked cert is then
+    /// published immediately. Failures to publish don't fail this call, they
+    /// are recorded in the revocation's publication status.
     pub fn revocation_apply(&self, revoc: models::Revocation) -> Result<()> {
-        self.storage.revocation_apply(revoc)
+        let cert = self.storage.cert_by_id(revoc.cert_id)?;
+        let hash = revoc.hash.clone();
+
+        self.storage.revocation_apply(revoc)?;
+
+        if let Some(cert) = cert {
+            events::emit(self, EventKind::CertRevoked, Some(&cert.fingerprint));
+        }
+
+        if revocation::publication(self)?.enabled() {
+            self.revocation_publish(&hash)?;
+        }
+
+        Ok(())
+    }
+
+    /// Publish the cert that the applied revocation `hash` revoked through
+    /// the configured channels (e.g. to retry after a failed publication).
+    ///
+    /// Returns the revocation with its updated publication status.
+    pub fn revocation_publish(&self, hash: &str) -> Result<models::Revocation> {
+        let revocation = self.revocation_get_by_hash(hash)?;
+        revocation::publish(self, &revocation)
+    }
+
+    /// Get the channels through which this CA publishes revocations when
+    /// they are applied
+    pub fn revocation_publication(&self) -> Result<RevocationPublication> {
+        revocation::publication(self)
+    }
+
+    /// Set the channels through which this CA publishes revocations when
+    /// they are applied (the default configuration publishes nothing).
+    ///
+    /// Keyservers must use hkps.
+    pub fn set_revocation_publication(&self, config: &RevocationPublication) -> Result<()> {
+        revocation::set_publication(self, config)
     }
 
     /// Get reason and creation time for a Revocation
@@ -1316,6 +3194,14 @@ impl Oca {
         }
     }
 
+    /// The regular expressions that scope the trust signature of `bridge`
+    /// (empty, if the bridge is unscoped).
+    ///
+    /// `None` for bridges that were created before the scope was stored.
+    pub fn bridge_scope_regexes(&self, bridge: &models::Bridge) -> Result<Option<Vec<String>>> {
+        bridge::scope_regexes(bridge)
+    }
+
     pub fn add_bridge(
         &self,
         email: Option<&str>,
@@ -1323,32 +3209,172 @@ impl Oca {
         scope: Option<&str>,
         unscoped: bool,
     ) -> Result<(String, String)> {
-        let (bridge, fingerprint) = bridge::bridge_new(self, key_file, email, scope, unscoped)?;
+        self.add_bridge_with_profile(
+            email,
+            key_file,
+            scope,
+            unscoped,
+            CertificationProfile::Default,
+        )
+    }
+
+    /// Add a bridge, like [Self::add_bridge], with a trust signature that is
+    /// made according to `profile`.
+    ///
+    /// If `profile` doesn't support scoped trust signatures, the bridge is
+    /// unscoped (and a warning is printed).
+    pub fn add_bridge_with_profile(
+        &self,
+        email: Option<&str>,
+        key_file: &Path,
+        scope: Option<&str>,
+        unscoped: bool,
+        profile: CertificationProfile,
+    ) -> Result<(String, String)> {
+        let scope = match (scope, unscoped) {
+            (_, true) => BridgeScope::Unscoped,
+            (Some(domain), false) => BridgeScope::Domain {
+                domain: domain.to_string(),
+                force: false,
+            },
+            (None, false) => BridgeScope::RemoteDomain,
+        };
+
+        self.add_bridge_with_scope(email, key_file, &scope, profile)
+    }
+
+    /// Add a bridge, like [Self::add_bridge_with_profile], with the trust
+    /// signature scoped according to `scope`.
+    ///
+    /// All regular expressions of the scope are stored with the bridge
+    /// (see [Self::bridge_scope_regexes]).
+    pub fn add_bridge_with_scope(
+        &self,
+        email: Option<&str>,
+        key_file: &Path,
+        scope: &BridgeScope,
+        profile: CertificationProfile,
+    ) -> Result<(String, String)> {
+        let remote_ca_cert = Cert::from_file(key_file).context("Failed to read key")?;
+
+        let (bridge, fingerprint) =
+            bridge::bridge_new(self, remote_ca_cert, email, scope, profile)?;
+
+        Ok((bridge.email, fingerprint.to_string()))
+    }
+
+    /// Check a federation metadata document of a remote CA (see
+    /// [Self::federation_metadata]).
+    ///
+    /// Returns the metadata, if the document is signed by the CA key it
+    /// contains, and that key belongs to one of the listed domains.
+    /// If `fingerprint` is set, the remote CA key must have that fingerprint.
+    ///
+    /// The metadata is self-signed, so the fingerprint of the remote CA
+    /// key must be confirmed through a trusted channel.
+    pub fn federation_metadata_verify(
+        doc: &[u8],
+        fingerprint: Option<&str>,
+    ) -> Result<FederationMetadata> {
+        federation::verify(doc, fingerprint).map(|(metadata, _)| metadata)
+    }
+
+    /// Add a bridge to a remote CA, based on its federation metadata
+    /// document (see [Self::federation_metadata_verify]).
+    ///
+    /// The trust signature is made according to `profile`.
+    ///
+    /// Returns the bridge email and the fingerprint of the remote CA key.
+    pub fn add_bridge_from_metadata(
+        &self,
+        email: Option<&str>,
+        doc: &[u8],
+        fingerprint: Option<&str>,
+        profile: CertificationProfile,
+    ) -> Result<(String, String)> {
+        let (_, remote_ca_cert) = federation::verify(doc, fingerprint)?;
+
+        let (bridge, fingerprint) = bridge::bridge_new(
+            self,
+            remote_ca_cert,
+            email,
+            &BridgeScope::RemoteDomain,
+            profile,
+        )?;
 
         Ok((bridge.email, fingerprint.to_string()))
     }
 
+    /// Export the bridge to `email` as a bundle for the operator of the
+    /// remote CA: one armored keyring with the remote CA cert (carrying our
+    /// trust signature) and our CA cert.
+    ///
+    /// The remote CA distributes the bundle to its users, and ingests it
+    /// with [Self::import_bridge_bundle].
+    pub fn export_bridge_bundle(&self, email: &str) -> Result<String> {
+        bridge::export_bundle(self, email)
+    }
+
+    /// Import a bridge bundle that a remote CA exported for this CA (see
+    /// [Self::export_bridge_bundle]).
+    ///
+    /// The remote CA's trust signature is merged into our CA cert, and a
+    /// bridge to the remote CA is created (scoped to its domain, with a
+    /// trust signature according to `profile`), unless it already exists.
+    /// So setting up a bidirectional bridge takes one bundle in each
+    /// direction.
+    ///
+    /// If `fingerprint` is set, the remote CA key must have that
+    /// fingerprint. Otherwise, it must be confirmed through a trusted
+    /// channel.
+    pub fn import_bridge_bundle(
+        &self,
+        bundle: &[u8],
+        fingerprint: Option<&str>,
+        profile: CertificationProfile,
+    ) -> Result<BridgeBundleImport> {
+        bridge::import_bundle(self, bundle, fingerprint, profile)
+    }
+
     /// Create a revocation Certificate for a Bridge and apply it the our
     /// copy of the remote CA's public key.
     ///
-    /// Both the revoked remote public key and the revocation cert are
-    /// printed to stdout.
-    pub fn bridge_revoke(&self, email: &str) -> Result<()> {
+    /// Returns the revocation, e.g. for publication through external
+    /// mechanisms.
+    pub fn bridge_revoke(&self, email: &str) -> Result<Signature> {
         bridge::bridge_revoke(self, email)
     }
 
-    pub fn print_bridges(&self, email: Option<String>) -> Result<()> {
+    /// Get the remote CA certs of all bridges (or of the bridge to `email`),
+    /// as pairs of bridge email and armored cert.
+    ///
+    /// The certs are adjusted to the quirks of the client `compat`.
+    pub fn bridges_export(
+        &self,
+        email: Option<String>,
+        compat: ExportCompat,
+    ) -> Result<Vec<(String, String)>> {
         let bridges = if let Some(email) = email {
             vec![self.bridges_search(&email)?]
         } else {
             self.bridges_get()?
         };
 
+        let mut res = vec![];
         for bridge in bridges {
-            println!("Bridge to '{}'", bridge.email);
             if let Some(db_cert) = self.storage.cert_by_id(bridge.cert_id)? {
-                println!("{}", db_cert.pub_cert);
+                let cert = pgp::cert_for_export(self.storage.cert_parsed(&db_cert)?, compat)?;
+                res.push((bridge.email, pgp::cert_to_armored(&cert)?));
             }
+        }
+
+        Ok(res)
+    }
+
+    pub fn print_bridges(&self, email: Option<String>, compat: ExportCompat) -> Result<()> {
+        for (email, armored) in self.bridges_export(email, compat)? {
+            println!("Bridge to '{email}'");
+            println!("{armored}");
             println!();
         }
 
@@ -1359,15 +3385,30 @@ impl Oca {
         for bridge in self.bridges_get()? {
             let tsigned = self.check_tsig_on_bridge(&bridge)?;
 
+            let scope = match self.bridge_scope_regexes(&bridge)? {
+                Some(regexes) if regexes.is_empty() => "unscoped".to_string(),
+                Some(regexes) => regexes
+                    .iter()
+                    .map(|r| format!("'{r}'"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                None => format!("'{}'", bridge.scope),
+            };
+
             println!(
-                "Bridge to '{}'{}, (scope: '{}')",
+                "Bridge to '{}'{}, (scope: {}){}",
                 bridge.email,
                 if !tsigned {
                     " [no trust signature]"
                 } else {
                     ""
                 },
-                bridge.scope,
+                scope,
+                if bridge.profile != CertificationProfile::Default.name() {
+                    format!(" [profile: {}]", bridge.profile)
+                } else {
+                    "".to_string()
+                },
             )
         }
 
@@ -1380,8 +3421,113 @@ impl Oca {
     /// into a wkd directory structure
     ///
     /// <https://tools.ietf.org/html/draft-koch-openpgp-webkey-service-08>
-    pub fn export_wkd(&self, domain: &str, path: &Path) -> Result<()> {
-        export::wkd_export(self, domain, path)
+    ///
+    /// If `minimize` is set, user certs are reduced to their User IDs in
+    /// `domain` (with only the CA's certifications) and their currently valid
+    /// subkeys.
+    ///
+    /// The certs are adjusted to the quirks of the client `compat`.
+    ///
+    /// The certs of users who opted out of WKD publication (see
+    /// [Self::user_set_wkd_publish]) are not exported.
+    pub fn export_wkd(
+        &self,
+        domain: &str,
+        path: &Path,
+        minimize: bool,
+        compat: ExportCompat,
+    ) -> Result<()> {
+        export::wkd_export(self, domain, path, minimize, compat, None)
+    }
+
+    /// Export into a wkd directory structure, like [Self::export_wkd], but
+    /// only the User IDs with one of the addresses in `emails`
+    pub fn export_wkd_for_emails(
+        &self,
+        domain: &str,
+        path: &Path,
+        emails: &[&str],
+        minimize: bool,
+        compat: ExportCompat,
+    ) -> Result<()> {
+        export::wkd_export(self, domain, path, minimize, compat, Some(emails))
+    }
+
+    /// Export into a wkd directory structure, like [Self::export_wkd], but
+    /// only write files whose content has changed (so that unchanged files
+    /// keep their modification time).
+    ///
+    /// Returns the written files, relative to `path`.
+    pub fn export_wkd_changed(
+        &self,
+        domain: &str,
+        path: &Path,
+        minimize: bool,
+        compat: ExportCompat,
+    ) -> Result<Vec<PathBuf>> {
+        export::wkd_export_changed(self, domain, path, minimize, compat, None)
+    }
+
+    /// Export into a wkd directory structure, like
+    /// [Self::export_wkd_for_emails], but only write files whose content has
+    /// changed (like [Self::export_wkd_changed])
+    pub fn export_wkd_changed_for_emails(
+        &self,
+        domain: &str,
+        path: &Path,
+        emails: &[&str],
+        minimize: bool,
+        compat: ExportCompat,
+    ) -> Result<Vec<PathBuf>> {
+        export::wkd_export_changed(self, domain, path, minimize, compat, Some(emails))
+    }
+
+    /// Export a WKD directory structure, like [Self::export_wkd], as a tar
+    /// archive
+    pub fn export_wkd_tar(
+        &self,
+        domain: &str,
+        minimize: bool,
+        compat: ExportCompat,
+    ) -> Result<Vec<u8>> {
+        export::wkd_export_tar(self, domain, minimize, compat, None)
+    }
+
+    /// Generate a signed federation metadata document for this CA, which
+    /// describes its domains and publication methods to partner CAs.
+    ///
+    /// `domains` are listed in addition to the domain of this CA.
+    pub fn federation_metadata(
+        &self,
+        domains: &[&str],
+        keylist_url: Option<&str>,
+        wkd_method: Option<&str>,
+        contact: Option<&str>,
+    ) -> Result<String> {
+        federation::metadata(self, domains, keylist_url, wkd_method, contact)
+    }
+
+    /// Write a federation metadata document (see [Self::federation_metadata])
+    /// into its `.well-known` location below `path`.
+    ///
+    /// Returns the name of the written file.
+    pub fn export_federation_metadata(
+        &self,
+        path: &Path,
+        domains: &[&str],
+        keylist_url: Option<&str>,
+        wkd_method: Option<&str>,
+        contact: Option<&str>,
+    ) -> Result<PathBuf> {
+        let doc = self.federation_metadata(domains, keylist_url, wkd_method, contact)?;
+
+        let file = path.join(federation::WELL_KNOWN_PATH);
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&file, doc)?;
+
+        Ok(file)
     }
 
     /// Export the contents of a CA in Keylist format.
@@ -1400,53 +3546,351 @@ impl Oca {
         export::export_keylist(self, path, signature_uri, force)
     }
 
+    /// The named keylists that are configured in this CA (ordered by name)
+    pub fn keylists(&self) -> Result<Vec<KeylistConfig>> {
+        export::keylists(self)
+    }
+
+    /// Add a named keylist, or replace the configuration of the keylist
+    /// with the same name.
+    ///
+    /// Names may only contain ASCII letters, digits, '-' and '_'.
+    pub fn keylist_set(&self, config: KeylistConfig) -> Result<()> {
+        export::keylist_set(self, config)
+    }
+
+    /// Remove the named keylist `name`
+    pub fn keylist_remove(&self, name: &str) -> Result<()> {
+        export::keylist_remove(self, name)
+    }
+
+    /// Export all named keylists in one call (see [Oca::export_keylist]).
+    ///
+    /// Each list is written into a subdirectory of `path` that is named
+    /// after the list, with its own signature file.
+    ///
+    /// Returns the names of the exported lists.
+    pub fn export_keylists(&self, path: &Path, force: bool) -> Result<Vec<String>> {
+        export::export_keylists(self, path, force)
+    }
+
+    /// Generate an org trust package: a tar archive with the CA cert, the
+    /// bridges, all named keylists and the policies of this CA, described by
+    /// a manifest that is signed by the CA key.
+    ///
+    /// Each call produces a package with a new, higher version number.
+    pub fn trust_package(&self) -> Result<(TrustPackageManifest, Vec<u8>)> {
+        trust_package::generate(self)
+    }
+
+    /// The version of the most recently generated trust package (0, if no
+    /// trust package has been generated yet)
+    pub fn trust_package_version(&self) -> Result<u64> {
+        trust_package::version(self)
+    }
+
+    /// Generate a trust package (see [Self::trust_package]), and write it
+    /// into `dir`, as "trust-package-<version>.tar".
+    ///
+    /// "latest.json" in `dir` is updated to point to the new package, so
+    /// that clients can check for newer versions.
+    pub fn trust_package_export(&self, dir: &Path) -> Result<TrustPackageManifest> {
+        trust_package::export(self, dir)
+    }
+
+    /// Check a trust package: its manifest must be signed by the CA key with
+    /// `fingerprint`, and all files must match the manifest.
+    /// If `newer_than` is set, the package version must be higher.
+    pub fn trust_package_verify(
+        package: &[u8],
+        fingerprint: &str,
+        newer_than: Option<u64>,
+    ) -> Result<TrustPackageManifest> {
+        trust_package::verify(package, fingerprint, newer_than).map(|(manifest, _)| manifest)
+    }
+
+    /// Check a trust package (see [Self::trust_package_verify]), and write
+    /// its contents into `dir`
+    pub fn trust_package_unpack(
+        package: &[u8],
+        fingerprint: &str,
+        newer_than: Option<u64>,
+        dir: &Path,
+    ) -> Result<TrustPackageManifest> {
+        trust_package::unpack(package, fingerprint, newer_than, dir)
+    }
+
     /// Export Certs from this CA into files, with filenames based on email
     /// addresses of user ids.
-    pub fn export_certs_as_files(&self, email_filter: Option<String>, path: &str) -> Result<()> {
-        export::export_certs_as_files(self, email_filter, path)
+    ///
+    /// If `minimize` is set, the certs in each file are reduced to the User
+    /// ID for that email (with only the CA's certifications) and their
+    /// currently valid subkeys.
+    ///
+    /// The files are written in `format` (armored ".asc" or binary ".pgp"),
+    /// adjusted to the quirks of the client `compat`.
+    pub fn export_certs_as_files(
+        &self,
+        email_filter: Option<String>,
+        path: &str,
+        minimize: bool,
+        format: CertFormat,
+        compat: ExportCompat,
+    ) -> Result<()> {
+        export::export_certs_as_files(self, email_filter, path, minimize, format, compat)
+    }
+
+    /// The CA cert and all user certs (or the user certs for
+    /// `email_filter`) as one certring, serialized in `format`.
+    ///
+    /// If `minimize` is set, user certs are reduced to the User IDs that the
+    /// CA has certified (or the User ID for `email_filter`), with only the
+    /// CA's certifications, and their currently valid subkeys.
+    ///
+    /// The certs are adjusted to the quirks of the client `compat`.
+    pub fn certring(
+        &self,
+        email_filter: Option<&str>,
+        minimize: bool,
+        format: CertFormat,
+        compat: ExportCompat,
+    ) -> Result<Vec<u8>> {
+        export::serialize_certring(self, email_filter, minimize, format, compat)
+    }
+
+    /// Write the CA cert and all user certs (or the user certs for
+    /// `email_filter`) as one certring in `format` to the file `path`,
+    /// compressed with `compression`.
+    ///
+    /// `minimize` and `compat` work as for [Self::certring].
+    pub fn export_certring(
+        &self,
+        path: &Path,
+        email_filter: Option<&str>,
+        minimize: bool,
+        format: CertFormat,
+        compat: ExportCompat,
+        compression: ExportCompression,
+    ) -> Result<()> {
+        export::export_certring(
+            self,
+            path,
+            email_filter,
+            minimize,
+            format,
+            compat,
+            compression,
+        )
+    }
+
+    /// Export the CA cert and all user certs into the directory `dir`, with
+    /// the user certs split into chunks of (on average) at most
+    /// `chunk_size` certs, for delivery of very large keyrings.
+    ///
+    /// Certs are assigned to chunks by their fingerprint, so chunks without
+    /// changed certs keep their content (and hash) between runs. The
+    /// returned index is also written to "index.json" in `dir`.
+    ///
+    /// `minimize` and `compat` work as for [Self::certring].
+    pub fn export_certring_chunked(
+        &self,
+        dir: &Path,
+        chunk_size: usize,
+        minimize: bool,
+        format: CertFormat,
+        compat: ExportCompat,
+        compression: ExportCompression,
+    ) -> Result<ChunkedExportManifest> {
+        export::export_certring_chunked(
+            self,
+            dir,
+            chunk_size,
+            minimize,
+            format,
+            compat,
+            compression,
+        )
+    }
+
+    /// Export the certificate chain for `email`, for external verification of
+    /// the user's signatures: an armored keyring with the user's currently
+    /// valid cert (reduced to the User ID for `email` and its CA
+    /// certification) and the CA cert.
+    ///
+    /// Recipients can check the chain with [pgp::verify_chain].
+    pub fn export_chain(&self, email: &str) -> Result<String> {
+        export::export_chain(self, email)
+    }
+
+    /// Import the CA cert and all user certs into the GnuPG homedir `path`,
+    /// with "ultimate" ownertrust for the CA cert (e.g. to provision a mail
+    /// gateway that uses GnuPG).
+    ///
+    /// This invokes the `gpg` binary. The homedir is created, if it doesn't exist.
+    pub fn export_to_gnupg_home(&self, path: &Path) -> Result<()> {
+        export::export_to_gnupg_home(self, path)
+    }
+
+    /// Export the currently valid, CA-certified cert for `email` as
+    /// Autocrypt attributes ("addr=...; keydata=..."), for use as the value
+    /// of an "Autocrypt" or "Autocrypt-Gossip" mail header.
+    ///
+    /// The cert is reduced to its primary key, the User ID for `email` and
+    /// one encryption subkey.
+    pub fn export_autocrypt(&self, email: &str) -> Result<String> {
+        export::export_autocrypt(self, email)
+    }
+
+    /// Export the authentication subkeys of the currently valid, CA-certified
+    /// cert for `email` in OpenSSH "authorized_keys" format, one line per
+    /// subkey (e.g. to use the CA as a directory of SSH keys).
+    ///
+    /// Subkeys with algorithms that SSH doesn't support are skipped.
+    pub fn export_ssh_keys(&self, email: &str) -> Result<String> {
+        export::export_ssh_keys(self, email)
+    }
+
+    /// Get the user certs (optionally filtered by User ID via email) that are usable for
+    /// encryption today: valid under the CA's policy, alive, not revoked and with
+    /// at least one live encryption-capable subkey.
+    ///
+    /// Returns the usable certs, and a report of the certs that were excluded.
+    pub fn certs_for_encryption(
+        &self,
+        email_filter: Option<String>,
+    ) -> Result<(Vec<Cert>, Vec<ExportRejection>)> {
+        export::certs_for_encryption(self, email_filter)
+    }
+
+    /// Export a list of all revoked user certs (fingerprint, revocation time and reason)
+    /// as a JSON document to `path`.
+    ///
+    /// The list is timestamped and signed by the CA: a detached signature is written to
+    /// `path` with an added ".sig" suffix.
+    pub fn export_revocation_list(&self, path: &Path) -> Result<()> {
+        export::export_revocation_list(self, path)
+    }
+
+    /// The revocation status of the cert `fingerprint` (good, revoked or
+    /// unknown), as a statement that is signed by the CA.
+    ///
+    /// Relying parties may rely on the statement for `validity` (see
+    /// [types::CertStatus::next_update]). They can check it with
+    /// [types::SignedCertStatus::verify].
+    pub fn cert_status(&self, fingerprint: &str, validity: Duration) -> Result<SignedCertStatus> {
+        revocation::cert_status(self, fingerprint, validity)
     }
 
-    pub fn print_certring(&self, email_filter: Option<String>) -> Result<()> {
-        export::print_certring(self, email_filter)
+    /// Get the trust topology of this CA: which users the CA has certified,
+    /// which users have tsigned the CA cert, and the bridges to remote CAs
+    /// (see [WotGraph]).
+    pub fn wot_graph(&self) -> Result<WotGraph> {
+        export::wot_graph(self)
+    }
+
+    /// Export the trust topology of this CA (see [Self::wot_graph]) as a
+    /// Graphviz DOT graph, or as JSON.
+    pub fn export_wot_graph(&self, format: WotGraphFormat) -> Result<String> {
+        export::export_wot_graph(self, format)
     }
 
     // -------- Update certs from public sources
 
+    /// The SOCKS5 proxy of the Tor client (e.g. a system Tor daemon, or
+    /// `arti proxy`) that this CA uses for WKD and keyserver lookups.
+    ///
+    /// None, if lookups use direct connections.
+    pub fn tor_proxy(&self) -> Result<Option<SocketAddr>> {
+        tor::proxy(self)
+    }
+
+    /// Route WKD and keyserver lookups of this CA through the Tor SOCKS5
+    /// proxy at `proxy` (or, with None, use direct connections).
+    ///
+    /// Each lookup uses a separate Tor circuit. While a proxy is configured,
+    /// lookups never fall back to direct connections.
+    ///
+    /// Routing through Tor requires the "tor" feature.
+    pub fn set_tor_proxy(&self, proxy: Option<SocketAddr>) -> Result<()> {
+        tor::set_proxy(self, proxy)
+    }
+
     /// Pull updates for all certs from WKD and merge them into our local
     /// storage.
+    ///
+    /// Certs that were looked up within the last hour are skipped (see
+    /// [Self::sync_certs]).
+    ///
+    /// Fails if this CA is configured to use Tor, and the Tor proxy is not
+    /// available (see [Self::set_tor_proxy]).
     pub fn update_from_wkd(&self) -> Result<()> {
-        for c in self.user_certs_get_all()? {
-            match update::update_from_wkd(self, &c) {
-                Ok(true) => {
-                    println!("Got update for cert {}", c.fingerprint);
-                }
-                Ok(false) => {
-                    println!("No changes for cert {}", c.fingerprint);
-                }
-                Err(e) => {
-                    eprintln!("Failed to update cert {}: {}", c.fingerprint, e);
-                }
-            }
-        }
+        self.sync_certs(SyncSource::Wkd, &SyncOptions::default())?;
         Ok(())
     }
 
-    /// Update all certs from the hagrid keyserver (<https://keys.openpgp.org/>)
-    /// and merge any updates into our local storage for this cert.
+    /// Update all certs from the keyservers that are configured for this CA
+    /// (by default, <https://keys.openpgp.org/>), and merge any updates into
+    /// our local storage for this cert (see [Self::set_keyserver_config]).
+    ///
+    /// Certs that were looked up within the last hour are skipped (see
+    /// [Self::sync_certs]).
+    ///
+    /// Fails if this CA is configured to use Tor, and the Tor proxy is not
+    /// available (see [Self::set_tor_proxy]).
     pub fn update_from_keyserver(&self) -> Result<()> {
-        for c in self.user_certs_get_all()? {
-            match update::update_from_hagrid(self, &c) {
-                Ok(true) => {
-                    println!("Got update for cert {}", c.fingerprint);
-                }
-                Ok(false) => {
-                    println!("No changes for cert {}", c.fingerprint);
-                }
-                Err(e) => {
-                    eprintln!("Failed to update cert {}: {}", c.fingerprint, e);
-                }
-            }
-        }
+        self.sync_certs(SyncSource::Keyserver, &SyncOptions::default())?;
         Ok(())
     }
+
+    /// Get the keyservers that this CA updates certs from
+    pub fn keyserver_config(&self) -> Result<KeyserverConfig> {
+        update::keyserver_config(self)
+    }
+
+    /// Set the keyservers that this CA updates certs from, and the number
+    /// of them that must agree on a packet before it is merged in.
+    ///
+    /// Keyservers must use hkps.
+    pub fn set_keyserver_config(&self, config: &KeyserverConfig) -> Result<()> {
+        update::set_keyserver_config(self, config)
+    }
+
+    /// The packets of `cert` that were merged in from keyservers, with the
+    /// keyservers that supplied them
+    pub fn cert_keyserver_packets(
+        &self,
+        cert: &models::Cert,
+    ) -> Result<Vec<models::KeyserverPacket>> {
+        self.storage.keyserver_packets(cert)
+    }
+
+    /// Pull updates for the certs from `source`, and merge them into our
+    /// local storage.
+    ///
+    /// The outcome of each lookup is recorded per cert (see
+    /// [Self::sync_status]). Certs that were looked up more recently than
+    /// `opts.min_interval` are skipped. The others are looked up in the order
+    /// of their last lookup, so an interrupted run (or one that was limited
+    /// with `opts.limit`) is resumed by the next run.
+    ///
+    /// Fails if this CA is configured to use Tor, and the Tor proxy is not
+    /// available (see [Self::set_tor_proxy]).
+    pub fn sync_certs(&self, source: SyncSource, opts: &SyncOptions) -> Result<SyncReport> {
+        update::sync(self, source, opts)
+    }
+
+    /// The state of the updates of all certs from `source`
+    pub fn sync_status(&self, source: SyncSource) -> Result<Vec<SyncStatus>> {
+        update::status(self, source)
+    }
+
+    /// Certs that have been failing to update from `source` (or, with None,
+    /// from any source) for at least `min_duration`
+    pub fn sync_failing(
+        &self,
+        source: Option<SyncSource>,
+        min_duration: Duration,
+    ) -> Result<Vec<SyncStatus>> {
+        update::failing(self, source, min_duration)
+    }
 }
@@ -0,0 +1,163 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Bodies for notification emails, optionally signed with the CA key as
+//! PGP/MIME (RFC 3156) messages.
+//!
+//! Signing is configured per CA. The signature is made via the active
+//! backend. A split-mode front instance has no access to the CA key, and
+//! signatures are not queued for the back instance (a notification can't
+//! wait for the next round trip), so signing fails on front instances.
+
+use anyhow::Result;
+use sequoia_openpgp::fmt::hex;
+use sequoia_openpgp::types::HashAlgorithm;
+
+use crate::backend::Backend;
+use crate::pgp;
+use crate::types::MimeEntity;
+use crate::Oca;
+
+const PREF_MAIL_SIGNING: &str = "mail_signing";
+
+/// Maximum length of encoded lines in quoted-printable bodies (RFC 2045)
+const QP_LINE_MAX: usize = 76;
+
+pub(crate) fn mail_signing(oca: &Oca) -> Result<bool> {
+    match oca.storage.pref(PREF_MAIL_SIGNING)? {
+        Some(value) => Ok(value.parse()?),
+        None => Ok(false),
+    }
+}
+
+pub(crate) fn set_mail_signing(oca: &Oca, enabled: bool) -> Result<()> {
+    oca.storage
+        .pref_set(PREF_MAIL_SIGNING, &enabled.to_string())
+}
+
+/// Encode `text` as quoted-printable, with CRLF line endings
+fn quoted_printable(text: &str) -> String {
+    let mut out = vec![];
+
+    for line in text.lines() {
+        let bytes = line.as_bytes();
+        let mut encoded = String::new();
+        let mut len = 0;
+
+        for (i, b) in bytes.iter().enumerate() {
+            let last = i == bytes.len() - 1;
+
+            let token = match b {
+                // Trailing whitespace would get lost in transport
+                b' ' | b'\t' if !last => (*b as char).to_string(),
+                33..=60 | 62..=126 => (*b as char).to_string(),
+                _ => format!("={b:02X}"),
+            };
+
+            // Soft line break (the '=' counts towards the line length)
+            if len + token.len() > QP_LINE_MAX - 1 {
+                encoded.push_str("=\r\n");
+                len = 0;
+            }
+
+            len += token.len();
+            encoded.push_str(&token);
+        }
+
+        out.push(encoded);
+    }
+
+    out.join("\r\n")
+}
+
+/// A text/plain entity for `text`
+pub(crate) fn text_part(text: &str) -> MimeEntity {
+    MimeEntity {
+        headers: vec![
+            (
+                "Content-Type".to_string(),
+                "text/plain; charset=utf-8".to_string(),
+            ),
+            (
+                "Content-Transfer-Encoding".to_string(),
+                "quoted-printable".to_string(),
+            ),
+        ],
+        body: quoted_printable(text),
+    }
+}
+
+/// The "micalg" parameter for signatures with `hash` (RFC 3156, section 5)
+fn micalg(hash: HashAlgorithm) -> Result<&'static str> {
+    Ok(match hash {
+        HashAlgorithm::SHA1 => "pgp-sha1",
+        HashAlgorithm::SHA224 => "pgp-sha224",
+        HashAlgorithm::SHA256 => "pgp-sha256",
+        HashAlgorithm::SHA384 => "pgp-sha384",
+        HashAlgorithm::SHA512 => "pgp-sha512",
+        _ => return Err(anyhow::anyhow!("Unsupported hash algorithm {hash}")),
+    })
+}
+
+/// Wrap `part` in a multipart/signed entity, with a detached signature by
+/// the CA key.
+///
+/// Fails on split-mode front instances.
+pub(crate) fn sign(oca: &Oca, part: &MimeEntity) -> Result<MimeEntity> {
+    if matches!(oca.backend(), Backend::SplitFront) {
+        return Err(anyhow::anyhow!(
+            "Signing emails is not supported on a split-mode front instance \
+             (disable mail signing, or send signed notifications from the back instance)"
+        ));
+    }
+
+    // The signature covers the entity exactly as it is transmitted
+    // (with CRLF line endings, which `part` already uses)
+    let signed = part.to_string();
+
+    let armored = oca.secret().sign_detached(signed.as_bytes())?;
+    let micalg = micalg(pgp::to_signature(armored.as_bytes())?.hash_algo())?;
+
+    let mut boundary = [0; 16];
+    sequoia_openpgp::crypto::random(&mut boundary);
+    let boundary = format!("oca-{}", hex::encode(boundary));
+
+    let signature = armored.lines().collect::<Vec<_>>().join("\r\n");
+
+    let body = format!(
+        "--{boundary}\r\n\
+         {signed}\r\n\
+         --{boundary}\r\n\
+         Content-Type: application/pgp-signature; name=\"signature.asc\"\r\n\
+         Content-Description: OpenPGP digital signature\r\n\
+         Content-Disposition: attachment; filename=\"signature.asc\"\r\n\
+         \r\n\
+         {signature}\r\n\
+         --{boundary}--\r\n"
+    );
+
+    Ok(MimeEntity {
+        headers: vec![(
+            "Content-Type".to_string(),
+            format!(
+                "multipart/signed; micalg={micalg}; protocol=\"application/pgp-signature\"; boundary=\"{boundary}\""
+            ),
+        )],
+        body,
+    })
+}
+
+/// The body for a notification email with `text` (signed, if mail
+/// signing is enabled for this CA)
+pub(crate) fn mail_text(oca: &Oca, text: &str) -> Result<MimeEntity> {
+    let part = text_part(text);
+
+    if mail_signing(oca)? {
+        sign(oca, &part)
+    } else {
+        Ok(part)
+    }
+}
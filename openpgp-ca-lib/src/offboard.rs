@@ -0,0 +1,81 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Offboarding of users (e.g. when an employee leaves the organization):
+//! all of their certs are revoked, deactivated and delisted in one step.
+
+use anyhow::Result;
+use sequoia_openpgp::types::RevocationStatus;
+
+use crate::cert;
+use crate::events::{self, EventKind};
+use crate::types::{OffboardReport, OffboardRevocation, OffboardedCert};
+use crate::Oca;
+
+/// Revoke the cert `fp`: apply a stored revocation, if one is on file.
+/// Otherwise, retract the CA certifications of its User IDs.
+fn revoke(oca: &Oca, fp: &str, reason: &str) -> Result<OffboardRevocation> {
+    let db_cert = oca
+        .storage
+        .cert_by_fp(fp)?
+        .ok_or_else(|| anyhow::anyhow!("No cert with fingerprint {} found", fp))?;
+
+    let c = oca.storage.cert_parsed(&db_cert)?;
+    if let RevocationStatus::Revoked(_) = c.revocation_status(&oca.policy()?, None) {
+        return Ok(OffboardRevocation::AlreadyRevoked);
+    }
+
+    if let Some(rev) = oca
+        .storage
+        .revocations_by_cert(&db_cert)?
+        .into_iter()
+        .find(|r| !r.published)
+    {
+        let hash = rev.hash.clone();
+        oca.revocation_apply(rev)?;
+
+        return Ok(OffboardRevocation::Applied(hash));
+    }
+
+    let mut retracted = vec![];
+    for uid in oca.cert_check_ca_sig(&db_cert)?.certified {
+        let uid = String::from_utf8_lossy(uid.value()).to_string();
+        cert::cert_retract_certification(oca, fp, &uid, reason)?;
+
+        retracted.push(uid);
+    }
+
+    Ok(OffboardRevocation::Retracted(retracted))
+}
+
+pub(crate) fn user_offboard(oca: &Oca, email: &str, reason: &str) -> Result<OffboardReport> {
+    let certs = oca.storage.certs_by_email(email)?;
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!("No cert found for {}", email));
+    }
+
+    let mut offboarded = vec![];
+    for db_cert in certs {
+        let fp = db_cert.fingerprint;
+
+        let revocation = revoke(oca, &fp, reason)?;
+
+        oca.storage.cert_deactivate(&fp)?;
+        oca.storage.cert_delist(&fp)?;
+
+        events::emit(oca, EventKind::CertOffboarded, Some(&fp));
+
+        offboarded.push(OffboardedCert {
+            fingerprint: fp,
+            revocation,
+        });
+    }
+
+    Ok(OffboardReport {
+        email: email.to_string(),
+        certs: offboarded,
+    })
+}
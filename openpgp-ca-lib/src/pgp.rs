@@ -5,12 +5,17 @@
 // https://gitlab.com/openpgp-ca/openpgp-ca
 
 //! PGP helper functions.
+//!
+//! These helpers are meant for the internal use of OpenPGP CA, and may
+//! change at any time. Downstream tooling should use the stable subset in
+//! [crate::util].
 
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::io;
 use std::io::BufRead;
 use std::str::FromStr;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use anyhow::{Context, Result};
 use chbs::probability::Probability;
@@ -20,32 +25,134 @@ use sequoia_openpgp::cert::amalgamation::key::ValidKeyAmalgamation;
 use sequoia_openpgp::cert::amalgamation::{ValidAmalgamation, ValidateAmalgamation};
 use sequoia_openpgp::cert::prelude::ComponentAmalgamation;
 use sequoia_openpgp::cert::{CertParser, CipherSuite as SeqCipherSuite};
-use sequoia_openpgp::crypto::KeyPair;
-use sequoia_openpgp::packet::signature::SignatureBuilder;
-use sequoia_openpgp::packet::{signature, Signature, UserID};
-use sequoia_openpgp::parse::{PacketParser, Parse};
-use sequoia_openpgp::policy::StandardPolicy;
+use sequoia_openpgp::crypto::hash::Digest as _;
+use sequoia_openpgp::crypto::{mpi, KeyPair, Signer};
+use sequoia_openpgp::packet::key::{PublicParts, UnspecifiedRole};
+use sequoia_openpgp::packet::signature::subpacket::{Subpacket, SubpacketTag, SubpacketValue};
+use sequoia_openpgp::packet::signature::{Signature4, SignatureBuilder};
+use sequoia_openpgp::packet::{signature, Key, Signature, UserID};
+use sequoia_openpgp::parse::{PacketParser, PacketParserResult, Parse};
+use sequoia_openpgp::policy::{HashAlgoSecurity, Policy, StandardPolicy};
 use sequoia_openpgp::serialize::{Serialize, SerializeInto};
-use sequoia_openpgp::types::{KeyFlags, RevocationStatus, SignatureType};
-use sequoia_openpgp::{Cert, Fingerprint, KeyHandle, Packet};
+use sequoia_openpgp::types::{HashAlgorithm, KeyFlags, RevocationStatus, SignatureType};
+use sequoia_openpgp::{Cert, Fingerprint, KeyHandle, Packet, PacketPile};
 use sha2::Digest;
 
+use crate::types::{
+    CaConfig, CertDowngrade, CertFormat, CertificationProfile, ExportCompat, FingerprintFormat,
+};
+
 pub(crate) const CA_KEY_NOTATION: &str = "openpgp-ca@notations.sequoia-pgp.org";
+const CA_SECURITY_CONTACT_NOTATION: &str = "openpgp-ca-security-contact@notations.sequoia-pgp.org";
+const CA_CPS_NOTATION: &str = "openpgp-ca-cps@notations.sequoia-pgp.org";
 
 pub(crate) const SECONDS_IN_DAY: u64 = 60 * 60 * 24;
 
 pub(crate) const SP: &StandardPolicy<'static> = &StandardPolicy::new();
 
-// FIXME: configurable dictionaries, ... ?
-fn diceware() -> String {
-    use chbs::{config::BasicConfig, prelude::*};
+fn diceware(words: usize, dictionary: &Dictionary, capitalize: bool) -> Result<String> {
+    use chbs::{config::BasicConfig, prelude::*, word::WordList};
+
+    if words == 0 {
+        return Err(anyhow::anyhow!("Diceware password needs at least one word"));
+    }
+
+    let list = match dictionary {
+        Dictionary::EffLarge => WordList::builtin_eff_large(),
+        Dictionary::EffShort => WordList::builtin_eff_short(),
+        Dictionary::Custom(list) => {
+            if list.is_empty() {
+                return Err(anyhow::anyhow!("Diceware dictionary is empty"));
+            }
+            WordList::new(list.clone())
+        }
+    };
+
+    let capitalize_first = if capitalize {
+        Probability::Always
+    } else {
+        Probability::Never
+    };
 
     let config = BasicConfig {
-        capitalize_first: Probability::Never,
+        words,
+        word_provider: list.sampler(),
+        capitalize_first,
         capitalize_words: Probability::Never,
         ..Default::default()
     };
-    config.to_scheme().generate()
+    Ok(config.to_scheme().generate())
+}
+
+/// Word list for generated diceware passwords
+#[derive(Clone)]
+pub enum Dictionary {
+    /// The EFF large word list (7776 words)
+    EffLarge,
+
+    /// The EFF short word list (1296 words)
+    EffShort,
+
+    /// A caller-supplied word list
+    Custom(Vec<String>),
+}
+
+/// Determines how the password for a newly generated user key is obtained
+pub enum PasswordPolicy {
+    /// Generate a random diceware password with `words` words
+    Diceware {
+        words: usize,
+        dictionary: Dictionary,
+
+        /// Capitalize the first letter of each word
+        capitalize: bool,
+    },
+
+    /// Use this password
+    Fixed(String),
+
+    /// Read the password from a file (or from stdin, if the filename is "-")
+    File(String),
+
+    /// Obtain the password from a caller-supplied function
+    Callback(Box<dyn Fn() -> Result<String>>),
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy::Diceware {
+            words: 5,
+            dictionary: Dictionary::EffLarge,
+            capitalize: false,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    fn password(&self) -> Result<String> {
+        match self {
+            PasswordPolicy::Diceware {
+                words,
+                dictionary,
+                capitalize,
+            } => diceware(*words, dictionary, *capitalize),
+            PasswordPolicy::Fixed(pw) => Ok(pw.clone()),
+            PasswordPolicy::File(file) => {
+                if file == "-" {
+                    // Get password from stdin
+                    let mut buffer = String::default();
+                    io::stdin().lock().read_line(&mut buffer)?;
+
+                    Ok(buffer)
+                } else {
+                    // Get password from `file`
+                    let mut f = std::fs::File::open(file)?;
+                    Ok(io::read_to_string(&mut f)?)
+                }
+            }
+            PasswordPolicy::Callback(f) => f(),
+        }
+    }
 }
 
 pub(crate) fn ca_user_id(email: &str, name: Option<&str>) -> UserID {
@@ -78,6 +185,73 @@ pub(crate) fn add_ca_domain_notation(
     )
 }
 
+/// The contact and policy information in the direct key signature `dks`
+/// of a CA cert
+pub(crate) fn ca_config(dks: &Signature) -> CaConfig {
+    let notation = |name| {
+        dks.notation(name)
+            .next()
+            .map(|v| String::from_utf8_lossy(v).to_string())
+    };
+
+    CaConfig {
+        policy_uri: dks
+            .policy_uri()
+            .map(|v| String::from_utf8_lossy(v).to_string()),
+        security_contact: notation(CA_SECURITY_CONTACT_NOTATION),
+        cps_uri: notation(CA_CPS_NOTATION),
+    }
+}
+
+/// Replace the contact and policy information in `sb` (a builder for the
+/// direct key signature of a CA cert) with `config`.
+///
+/// All other subpackets (e.g. the CA domain notation) are kept.
+pub(crate) fn set_ca_config(
+    mut sb: SignatureBuilder,
+    config: &CaConfig,
+) -> Result<SignatureBuilder> {
+    let area = sb.hashed_area_mut();
+
+    // Keep all notations other than the ones we replace
+    let keep: Vec<_> = area
+        .iter()
+        .filter(|sp| match sp.value() {
+            SubpacketValue::NotationData(n) => {
+                n.name() != CA_SECURITY_CONTACT_NOTATION && n.name() != CA_CPS_NOTATION
+            }
+            _ => false,
+        })
+        .cloned()
+        .collect();
+
+    area.remove_all(SubpacketTag::NotationData);
+    area.remove_all(SubpacketTag::PolicyURI);
+    for sp in keep {
+        area.add(sp)?;
+    }
+
+    if let Some(uri) = &config.policy_uri {
+        sb = sb.set_policy_uri(uri.as_bytes())?;
+    }
+
+    for (name, value) in [
+        (CA_SECURITY_CONTACT_NOTATION, &config.security_contact),
+        (CA_CPS_NOTATION, &config.cps_uri),
+    ] {
+        if let Some(value) = value {
+            sb = sb.add_notation(
+                name,
+                value.as_bytes(),
+                signature::subpacket::NotationDataFlags::empty().set_human_readable(),
+                false,
+            )?;
+        }
+    }
+
+    Ok(sb)
+}
+
 /// Generate a new CA key (and a revocation).
 ///
 /// `domain` is the domainname for the CA (such as `example.org`).
@@ -86,17 +260,24 @@ pub(crate) fn add_ca_domain_notation(
 ///
 /// `name` is an optional additional identifier that is added to the
 /// UserID, if it is supplied.
+///
+/// If `validity` is set, the generated key expires after that period.
+///
+/// Fails for key versions that can't be generated (see [KeyVersion]).
 pub(crate) fn make_ca_cert(
     domain: &str,
     name: Option<&str>,
     cipher_suite: Option<CipherSuite>,
+    key_version: KeyVersion,
+    validity: Option<Duration>,
 ) -> Result<(Cert, Signature)> {
+    key_version.check_supported()?;
+
     // Generate key for a new CA
     let (mut ca_key, revocation) = cert::CertBuilder::new()
         .set_cipher_suite(cipher_suite.unwrap_or(CipherSuite::Cv25519).into())
         .add_signing_subkey()
-        // FIXME: set expiration from CLI
-        // .set_validity_period()
+        .set_validity_period(validity)
         .generate()?;
 
     // Get keypair for the CA primary key, as a Signer
@@ -169,45 +350,35 @@ pub(crate) fn make_ca_cert(
 /// if supplied.
 ///
 /// If `password` is true, the generated private key will be password
-/// protected (with a generated diceware password).
+/// protected. The password is obtained according to `password_policy`
+/// (by default, a diceware password is generated).
+///
+/// If `validity` is set, the generated key expires after that period.
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn make_user_cert(
     emails: &[&str],
+    roles: &[&str],
     name: Option<&str>,
     password: bool,
-    password_file: Option<String>,
+    password_policy: Option<PasswordPolicy>,
     cipher_suite: Option<CipherSuite>,
+    key_version: KeyVersion,
+    validity: Option<Duration>,
     enable_encryption_subkey: bool,
     enable_signing_subkey: bool,
     enable_authentication_subkey: bool,
 ) -> Result<(Cert, Signature, Option<String>)> {
-    let pass = if password {
-        // The user wants to set a password, figure out how we acquire it
-        let pw = match password_file {
-            None => diceware(), // We generate a new, random password
-            Some(file) => {
-                // A password is provided by the user
-                if &file == "-" {
-                    // Get password from stdin
-                    let mut buffer = String::default();
-                    io::stdin().lock().read_line(&mut buffer)?;
-
-                    buffer
-                } else {
-                    // Get password from `file`
-                    let mut f = std::fs::File::open(&file)?;
-                    io::read_to_string(&mut f)?
-                }
-            }
-        };
+    key_version.check_supported()?;
 
-        Some(pw)
+    let pass = if password {
+        Some(password_policy.unwrap_or_default().password()?)
     } else {
         None
     };
 
     let mut builder = cert::CertBuilder::new()
-        .set_cipher_suite(cipher_suite.unwrap_or(CipherSuite::Cv25519).into());
+        .set_cipher_suite(cipher_suite.unwrap_or(CipherSuite::Cv25519).into())
+        .set_validity_period(validity);
 
     if enable_encryption_subkey {
         builder = builder.add_subkey(
@@ -234,6 +405,9 @@ pub(crate) fn make_user_cert(
     for email in emails {
         builder = builder.add_userid(user_id(email, name));
     }
+    for role in roles {
+        builder = builder.add_userid(UserID::from(*role));
+    }
 
     let (cert, revocation) = builder.generate()?;
     Ok((cert, revocation, pass))
@@ -261,6 +435,27 @@ pub fn certs_to_armored(certs: &[Cert]) -> Result<String> {
     Ok(String::from_utf8_lossy(&buffer).to_string())
 }
 
+/// Get the binary "public keyring" representation of a set of Certs.
+///
+/// Like [certs_to_armored], this strips non-exportable signatures.
+pub fn certs_to_binary(certs: &[Cert]) -> Result<Vec<u8>> {
+    let mut buffer = vec![];
+
+    for cert in certs {
+        cert.export(&mut buffer)?;
+    }
+
+    Ok(buffer)
+}
+
+/// Get the "public keyring" representation of a set of Certs, in `format`
+pub fn certs_serialize(certs: &[Cert], format: CertFormat) -> Result<Vec<u8>> {
+    match format {
+        CertFormat::Armored => Ok(certs_to_armored(certs)?.into_bytes()),
+        CertFormat::Binary => certs_to_binary(certs),
+    }
+}
+
 /// Get "private key" armored representation of a Cert
 pub fn cert_to_armored_private_key(cert: &Cert) -> Result<String> {
     let mut buffer = vec![];
@@ -298,6 +493,42 @@ pub fn to_cert(data: &[u8]) -> Result<Cert> {
     Ok(cert)
 }
 
+/// Recover the first Cert from damaged (armored or binary) `data`.
+///
+/// The armor is read tolerantly (a damaged or missing end of the armored
+/// data is ignored), and packets are collected up to the first packet that
+/// can't be read.
+pub(crate) fn to_cert_lenient(data: &[u8]) -> Result<Cert> {
+    // NUL bytes and carriage returns sometimes creep into text columns
+    let cleaned: Vec<u8> = data
+        .iter()
+        .copied()
+        .filter(|&b| b != 0 && b != b'\r')
+        .collect();
+
+    let mut raw = vec![];
+    let mut reader = armor::Reader::from_bytes(&cleaned, armor::ReaderMode::Tolerant(None));
+    // Reading stops at damaged armor, the data up to that point is kept
+    let _ = io::Read::read_to_end(&mut reader, &mut raw);
+
+    let mut packets = vec![];
+    let mut ppr = PacketParser::from_bytes(&raw).context("No OpenPGP data found")?;
+    while let PacketParserResult::Some(pp) = ppr {
+        match pp.next() {
+            Ok((packet, next)) => {
+                packets.push(packet);
+                ppr = next;
+            }
+            Err(_) => break,
+        }
+    }
+    packets.retain(|p| !matches!(p, Packet::Unknown(_)));
+
+    CertParser::from_iter(packets.into_iter().map(Ok))
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No cert found"))?
+}
+
 /// Get a Signature object from signature data (optionally armored)
 pub fn to_signature(data: &[u8]) -> Result<Signature> {
     let p = Packet::from_bytes(data).context("Input could not be parsed")?;
@@ -316,15 +547,29 @@ pub fn to_signature(data: &[u8]) -> Result<Signature> {
 /// Note:this uses `armor::Kind::PublicKey`, because GnuPG doesn't
 /// seem to accept revocations with the `armor::Kind::Signature` kind.
 pub fn revoc_to_armored(sig: &Signature, headers: Option<Vec<(String, String)>>) -> Result<String> {
+    revoc_to_armored_compat(sig, headers, ExportCompat::GnuPG22)
+}
+
+/// Make an armored representation of a revocation signature, with the
+/// armor kind that `compat` expects.
+///
+/// Errors for non-exportable signatures.
+pub fn revoc_to_armored_compat(
+    sig: &Signature,
+    headers: Option<Vec<(String, String)>>,
+    compat: ExportCompat,
+) -> Result<String> {
+    let kind = if compat.revocation_as_public_key() {
+        armor::Kind::PublicKey
+    } else {
+        armor::Kind::Signature
+    };
+
     let mut buf = vec![];
     {
         let rev = Packet::Signature(sig.clone());
 
-        let mut writer = armor::Writer::with_headers(
-            &mut buf,
-            armor::Kind::PublicKey,
-            headers.unwrap_or_default(),
-        )?;
+        let mut writer = armor::Writer::with_headers(&mut buf, kind, headers.unwrap_or_default())?;
         rev.export(&mut writer)?;
         writer.finalize()?;
     }
@@ -332,9 +577,113 @@ pub fn revoc_to_armored(sig: &Signature, headers: Option<Vec<(String, String)>>)
     Ok(String::from_utf8(buf)?)
 }
 
-/// Get expiration time of cert as a SystemTime
-pub fn get_expiry(cert: &Cert) -> Result<Option<SystemTime>> {
-    let primary = cert.primary_key().with_policy(SP, None)?;
+/// Make an armored representation of a set of signatures.
+///
+/// Like [revoc_to_armored], this uses `armor::Kind::PublicKey`.
+pub fn signatures_to_armored(sigs: &[Signature]) -> Result<String> {
+    let mut writer = armor::Writer::new(Vec::new(), armor::Kind::PublicKey)?;
+
+    for sig in sigs {
+        Packet::Signature(sig.clone()).export(&mut writer)?;
+    }
+    let buffer = writer.finalize()?;
+
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// Parse OpenPGP data (binary or armored) into a PacketPile.
+///
+/// Fails for input that isn't valid OpenPGP data.
+fn to_packet_pile(data: &[u8]) -> Result<PacketPile> {
+    let pile = PacketPile::from_bytes(data).context("Input could not be parsed")?;
+
+    if pile.children().next().is_none() {
+        return Err(anyhow::anyhow!("Input contains no OpenPGP packets"));
+    }
+
+    Ok(pile)
+}
+
+/// Get an ascii-armored representation of OpenPGP data (binary or armored).
+///
+/// The armor kind is chosen based on the first packet. Secret key material
+/// is retained.
+pub fn armor(data: &[u8]) -> Result<String> {
+    let pile = to_packet_pile(data)?;
+
+    let kind = match pile.children().next() {
+        Some(Packet::PublicKey(_)) => armor::Kind::PublicKey,
+        Some(Packet::SecretKey(_)) => armor::Kind::SecretKey,
+        Some(Packet::Signature(_)) => armor::Kind::Signature,
+        _ => armor::Kind::Message,
+    };
+
+    let mut writer = armor::Writer::new(Vec::new(), kind)?;
+    pile.serialize(&mut writer)?;
+    let buffer = writer.finalize()?;
+
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// Get the binary representation of OpenPGP data (binary or armored).
+pub fn dearmor(data: &[u8]) -> Result<Vec<u8>> {
+    let pile = to_packet_pile(data)?;
+
+    pile.to_vec()
+}
+
+/// Get all signatures in OpenPGP data (e.g. a cert or a set of
+/// revocations) that were issued by the key `issuer` (a fingerprint or
+/// Key ID).
+pub fn signatures_by_issuer(data: &[u8], issuer: &str) -> Result<Vec<Signature>> {
+    let issuer = KeyHandle::from_str(issuer)
+        .with_context(|| format!("Invalid fingerprint or Key ID '{issuer}'"))?;
+
+    let pile = to_packet_pile(data)?;
+
+    let sigs = pile
+        .descendants()
+        .filter_map(|p| match p {
+            Packet::Signature(sig) => Some(sig),
+            _ => None,
+        })
+        .filter(|sig| sig.get_issuers().iter().any(|kh| kh.aliases(&issuer)))
+        .cloned()
+        .collect();
+
+    Ok(sigs)
+}
+
+/// Verify an armored detached signature over `data`, made by a signing
+/// capable key of `signer` (e.g. by [crate::secret::CaSec::sign_detached]).
+///
+/// Only keys that are valid, alive and not revoked at the creation time of
+/// the signature (under the standard policy) are considered.
+pub(crate) fn verify_detached(signer: &Cert, data: &[u8], signature: &str) -> Result<()> {
+    let sig = to_signature(signature.as_bytes())?;
+
+    let time = sig
+        .signature_creation_time()
+        .ok_or_else(|| anyhow::anyhow!("Signature has no creation time"))?;
+
+    let valid = signer.with_policy(SP, time)?;
+
+    if valid
+        .keys()
+        .for_signing()
+        .alive()
+        .revoked(false)
+        .any(|ka| sig.verify_message(ka.key(), data).is_ok())
+    {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Signature verification failed"))
+    }
+}
+
+/// Get expiration time of cert as a SystemTime (evaluated with `policy`)
+pub fn get_expiry(cert: &Cert, policy: &dyn Policy) -> Result<Option<SystemTime>> {
+    let primary = cert.primary_key().with_policy(policy, None)?;
     Ok(primary.key_expiration_time())
 }
 
@@ -349,6 +698,33 @@ pub(crate) fn normalize_fp(fp: &str) -> Result<String> {
     Ok(Fingerprint::from_hex(fp)?.to_hex())
 }
 
+/// Format `fp` for display, in `format`
+pub fn fingerprint_to_string(fp: &Fingerprint, format: FingerprintFormat) -> String {
+    match format {
+        FingerprintFormat::Plain => fp.to_hex(),
+        FingerprintFormat::Spaced => fp.to_spaced_hex(),
+        FingerprintFormat::Qr => format!("OPENPGP4FPR:{}", fp.to_hex()),
+    }
+}
+
+/// Parse a fingerprint that was provided by a human, in any of the
+/// [FingerprintFormat]s (and optionally with a "0x" prefix)
+pub fn fingerprint_from_str(fp: &str) -> Result<Fingerprint> {
+    let fp = fp.trim();
+
+    let fp = if fp.len() >= 12 && fp[..12].eq_ignore_ascii_case("OPENPGP4FPR:") {
+        &fp[12..]
+    } else {
+        fp
+    };
+    let fp = fp
+        .strip_prefix("0x")
+        .or_else(|| fp.strip_prefix("0X"))
+        .unwrap_or(fp);
+
+    Fingerprint::from_hex(fp).context(format!("Invalid fingerprint '{fp}'"))
+}
+
 pub fn get_revoc_issuer_fp(revoc_cert: &Signature) -> Result<Option<Fingerprint>> {
     let issuers = revoc_cert.get_issuers();
     let sig_fingerprints: Vec<&Fingerprint> = issuers
@@ -425,6 +801,73 @@ pub fn tsign(signee: Cert, signer: &Cert, pass: Option<&str>) -> Result<Cert> {
     Ok(signed)
 }
 
+/// `signer` binds `userid` of `cert`, with a signature based on the
+/// template `sb`, shaped according to `profile`.
+pub(crate) fn bind_userid(
+    signer: &mut dyn Signer,
+    cert: &Cert,
+    userid: &UserID,
+    sb: SignatureBuilder,
+    profile: CertificationProfile,
+) -> Result<Signature> {
+    match profile {
+        CertificationProfile::Default => userid.bind(signer, cert, sb),
+        CertificationProfile::Rfc4880Compat => bind_userid_rfc4880(signer, cert, userid, sb),
+    }
+}
+
+/// Like [SignatureBuilder::sign_userid_binding], but with SHA-256, and
+/// without the salt notation that Sequoia adds to all signatures it makes.
+///
+/// The hashed area only contains the subpackets from `sb`, the signature
+/// creation time and the issuer key ID. The issuer fingerprint (which is
+/// not defined in RFC 4880) goes into the unhashed area.
+fn bind_userid_rfc4880(
+    signer: &mut dyn Signer,
+    cert: &Cert,
+    userid: &UserID,
+    sb: SignatureBuilder,
+) -> Result<Signature> {
+    let hash_algo = HashAlgorithm::SHA256;
+    let pk_algo = signer.public().pk_algo();
+
+    let mut sb = sb
+        .set_signature_creation_time(SystemTime::now())?
+        .set_issuer(signer.public().keyid())?;
+    sb.unhashed_area_mut().add(Subpacket::new(
+        SubpacketValue::IssuerFingerprint(signer.public().fingerprint()),
+        false,
+    )?)?;
+
+    let sig = |digest_prefix, mpis| {
+        Signature4::new(
+            sb.typ(),
+            pk_algo,
+            hash_algo,
+            sb.hashed_area().clone(),
+            sb.unhashed_area().clone(),
+            digest_prefix,
+            mpis,
+        )
+    };
+
+    // The hash covers the signature fields, but not the signature itself
+    let template = sig(
+        [0, 0],
+        mpi::Signature::Unknown {
+            mpis: Box::new([]),
+            rest: Box::new([]),
+        },
+    );
+    let mut hash = hash_algo.context()?;
+    template.hash_userid_binding(&mut hash, cert.primary_key().key(), userid);
+    let digest = hash.into_digest()?;
+
+    let mpis = signer.sign(hash_algo, &digest)?;
+
+    Ok(sig([digest[0], digest[1]], mpis).into())
+}
+
 /// Merge new CA tsigs from `import` into `ca_cert`.
 /// Return merged Cert as TSK (if available).
 pub(crate) fn merge_in_tsigs(ca_cert: Cert, import: Cert) -> Result<Cert> {
@@ -471,36 +914,93 @@ pub(crate) fn get_cert_keys(cert: &Cert, password: Option<&str>) -> Vec<KeyPair>
 
 // -------- helper functions
 
-pub fn print_cert_info(data: &[u8]) -> Result<()> {
-    let c = to_cert(data)?;
-    for uid in c.userids() {
-        println!("User ID: {}", uid.userid());
-    }
-    println!("Fingerprint '{c}'");
-    Ok(())
-}
-
 /// Does any User ID of this cert use an email address in "domain"?
 pub(crate) fn cert_has_uid_in_domain(c: &Cert, domain: &str) -> Result<bool> {
     for uid in c.userids() {
         // is any uid in domain
-        let email = uid.email2()?;
-        if let Some(email) = email {
-            let split: Vec<_> = email.split('@').collect();
+        if uid_in_domain(uid.userid(), domain)? {
+            return Ok(true);
+        }
+    }
 
-            if split.len() != 2 {
-                return Err(anyhow::anyhow!("unexpected email format"));
-            }
+    Ok(false)
+}
 
-            if split[1] == domain {
-                return Ok(true);
-            }
+/// Does `uid` have an email address in `domain`?
+pub(crate) fn uid_in_domain(uid: &UserID, domain: &str) -> Result<bool> {
+    if let Some(email) = uid.email2()? {
+        let split: Vec<_> = email.split('@').collect();
+
+        if split.len() != 2 {
+            return Err(anyhow::anyhow!("unexpected email format"));
         }
+
+        return Ok(split[1] == domain);
     }
 
     Ok(false)
 }
 
+/// The URI identifier of `uid` (e.g. "spiffe://cluster/ns/job"), if any
+pub(crate) fn uid_uri(uid: &UserID) -> Option<&str> {
+    uid.uri2().ok().flatten()
+}
+
+/// The role of `uid`, for User IDs that have neither an email address nor
+/// a URI identifier (e.g. "Backup signing key 2024", for a service account)
+pub(crate) fn uid_role(uid: &UserID) -> Option<&str> {
+    if matches!(uid.email2(), Ok(Some(_))) || uid_uri(uid).is_some() {
+        return None;
+    }
+
+    std::str::from_utf8(uid.value())
+        .ok()
+        .filter(|role| !role.trim().is_empty())
+}
+
+/// Ways in which `new` (an updated version of `old`) rolls back the state
+/// of the cert: lost packets, an earlier expiry or a removed revocation.
+pub fn cert_downgrades(old: &Cert, new: &Cert) -> Vec<CertDowngrade> {
+    let mut downgrades = vec![];
+
+    let same = |a: &Packet, b: &Packet| match (a, b) {
+        // The unhashed areas of signatures may get merged
+        (Packet::Signature(a), Packet::Signature(b)) => a.normalized_eq(b),
+        _ => a == b,
+    };
+
+    let new_packets: Vec<_> = new.clone().into_packets2().collect();
+    let lost = old
+        .clone()
+        .into_packets2()
+        .filter(|p| !new_packets.iter().any(|n| same(p, n)))
+        .count();
+    if lost > 0 {
+        downgrades.push(CertDowngrade::PacketsLost { count: lost });
+    }
+
+    if let (Ok(old_exp), Ok(new_exp)) = (get_expiry(old, SP), get_expiry(new, SP)) {
+        let rolled_back = match (old_exp, new_exp) {
+            (None, Some(_)) => true,
+            (Some(o), Some(n)) => n < o,
+            _ => false,
+        };
+        if rolled_back {
+            downgrades.push(CertDowngrade::ExpiryRolledBack {
+                old: old_exp,
+                new: new_exp,
+            });
+        }
+    }
+
+    let revoked = |c: &Cert| matches!(c.revocation_status(SP, None), RevocationStatus::Revoked(_));
+    if revoked(old) && !revoked(new) {
+        downgrades.push(CertDowngrade::RevocationRemoved);
+    }
+
+    downgrades
+}
+
 /// Get all trust sigs on User IDs in this Cert
 pub(crate) fn get_trust_sigs(c: &Cert) -> Result<Vec<Signature>> {
     Ok(get_third_party_sigs(c)?
@@ -522,16 +1022,151 @@ fn get_third_party_sigs(c: &Cert) -> Result<Vec<Signature>> {
     Ok(res)
 }
 
+/// Cached results of cryptographic signature verification.
+///
+/// Maps the digest of a signature (see [signature_digest]) to the fingerprint
+/// of the key that the signature has been verified with.
+pub(crate) type VerifiedSignatures = HashMap<String, String>;
+
+/// SHA256 digest (as hex) over the User ID `uid` and a signature on it
+pub(crate) fn signature_digest(uid: &UserID, sig: &Signature) -> Result<String> {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(uid.value());
+    hasher.update(Packet::from(sig.clone()).to_vec()?);
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|d| format!("{d:02X}"))
+        .collect())
+}
+
+/// SHA256 digest (as hex) over the packet `p`.
+///
+/// Signatures are normalized first, so that copies of a signature that
+/// only differ in their unhashed subpackets get the same digest.
+pub(crate) fn packet_digest(p: &Packet) -> Result<String> {
+    let bits = match p {
+        Packet::Signature(sig) => Packet::from(sig.normalize()).to_vec()?,
+        p => p.to_vec()?,
+    };
+
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(bits);
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|d| format!("{d:02X}"))
+        .collect())
+}
+
 /// For User ID `uid` (which is a part of `cert`):
 /// find all valid certifications that have been made by `certifier`.
+///
+/// Certifications that `certifier` has retracted (by issuing a
+/// certification revocation for `uid` at a later time) are not returned.
+///
+/// Certifications (and certifier keys) that `policy` rejects are ignored.
 pub fn valid_certifications_by(
     uid: &ComponentAmalgamation<UserID>,
     cert: &Cert,
     certifier: Cert,
+    policy: &dyn Policy,
+) -> Vec<Signature> {
+    valid_certifications_by_cached(uid, cert, certifier, policy, &mut HashMap::new())
+}
+
+/// Find redundant certifications by `ca` on the User IDs of `cert`:
+/// certifications that were made in one go, with several certification
+/// capable keys of the CA.
+///
+/// Of each set of redundant certifications, the one by the key `preferred`
+/// is kept (or the first one, if none of them was made by `preferred`).
+///
+/// Returns the cert without the redundant certifications, and the number
+/// of removed certifications.
+pub(crate) fn dedup_certifications(
+    cert: Cert,
+    ca: &Cert,
+    preferred: Option<&str>,
+    policy: &dyn Policy,
+) -> Result<(Cert, usize)> {
+    let mut redundant: Vec<Signature> = vec![];
+
+    for uid in cert.userids() {
+        // Certifications that were made in one go have the same creation
+        // time (and the same trust signature parameters)
+        let mut groups: HashMap<_, Vec<Signature>> = HashMap::new();
+        for sig in valid_certifications_by(&uid, &cert, ca.clone(), policy) {
+            groups
+                .entry((sig.signature_creation_time(), sig.trust_signature()))
+                .or_default()
+                .push(sig);
+        }
+
+        for mut group in groups.into_values().filter(|g| g.len() > 1) {
+            let keep = group
+                .iter()
+                .position(|sig| {
+                    preferred.is_some_and(|fp| sig.issuer_fingerprints().any(|i| i.to_hex() == fp))
+                })
+                .unwrap_or(0);
+
+            group.remove(keep);
+            redundant.append(&mut group);
+        }
+    }
+
+    if redundant.is_empty() {
+        return Ok((cert, 0));
+    }
+
+    let packets = cert.into_packets2().filter(|p| match p {
+        Packet::Signature(sig) => !redundant.contains(sig),
+        _ => true,
+    });
+
+    Ok((Cert::from_packets(packets)?, redundant.len()))
+}
+
+/// If all of `certifications` have expired at `time`: the expiration time
+/// of the certification that expired last.
+///
+/// Returns None if any certification is not expired (or doesn't expire),
+/// and if `certifications` is empty.
+pub(crate) fn certifications_expired(
+    certifications: &[Signature],
+    time: SystemTime,
+) -> Option<SystemTime> {
+    let mut last = None;
+
+    for s in certifications {
+        match s.signature_expiration_time() {
+            Some(expiration) if expiration <= time => last = last.max(Some(expiration)),
+            _ => return None,
+        }
+    }
+
+    last
+}
+
+/// Like [valid_certifications_by], but signatures that are listed in
+/// `verified` are not cryptographically verified again (as long as the key
+/// they were verified with is still a valid certification key of
+/// `certifier`).
+///
+/// Signatures that get verified in this call are added to `verified`.
+pub(crate) fn valid_certifications_by_cached(
+    uid: &ComponentAmalgamation<UserID>,
+    cert: &Cert,
+    certifier: Cert,
+    policy: &dyn Policy,
+    verified: &mut VerifiedSignatures,
 ) -> Vec<Signature> {
     let certifier_keys: Vec<_> = certifier
         .keys()
-        .with_policy(SP, None)
+        .with_policy(policy, None)
         .alive()
         .revoked(false)
         .for_certification()
@@ -541,22 +1176,210 @@ pub fn valid_certifications_by(
 
     let pk = cert.primary_key();
 
+    // Does `policy` accept the algorithms of the third-party signature `s`?
+    let acceptable = |s: &Signature| {
+        policy
+            .signature(s, HashAlgoSecurity::CollisionResistance)
+            .is_ok()
+    };
+
+    // Is `s` made by one of `certifier_keys`? The cryptographic check
+    // `valid` is skipped if a cached result exists.
+    let mut verify = |s: &Signature, valid: &dyn Fn(&Key<PublicParts, UnspecifiedRole>) -> bool| {
+        let digest = signature_digest(uid.userid(), s).ok();
+
+        if let Some(fp) = digest.as_ref().and_then(|d| verified.get(d)) {
+            if certifier_keys
+                .iter()
+                .any(|k| &k.fingerprint().to_hex() == fp)
+            {
+                return true;
+            }
+        }
+
+        match certifier_keys.iter().find(|signer| valid(signer.key())) {
+            Some(signer) => {
+                if let Some(digest) = digest {
+                    verified.insert(digest, signer.fingerprint().to_hex());
+                }
+                true
+            }
+            None => false,
+        }
+    };
+
+    // the most recent valid certification revocation by `certifier`
+    let retracted = uid
+        .other_revocations()
+        .filter(|&s| {
+            s.issuer_fingerprints()
+                .any(|issuer| issuer == &certifier_fp)
+        })
+        .filter(|&s| acceptable(s))
+        .filter(|&s| {
+            verify(s, &|signer| {
+                s.verify_userid_revocation(signer, &pk, uid).is_ok()
+            })
+        })
+        .filter_map(|s| s.signature_creation_time())
+        .max();
+
     uid.certifications()
         .filter(|&s| {
             // does the signature appear to be issued by `certifier`?
             s.issuer_fingerprints()
                 .any(|issuer| issuer == &certifier_fp)
         })
+        .filter(|&s| acceptable(s))
         .filter(|&s| {
             // check if the apparent certification by `certifier` is valid
-            certifier_keys
-                .iter()
-                .any(|signer| s.clone().verify_userid_binding(signer, &pk, uid).is_ok())
+            verify(s, &|signer| {
+                s.clone().verify_userid_binding(signer, &pk, uid).is_ok()
+            })
+        })
+        .filter(|&s| {
+            // check that the certification has not been retracted
+            match (retracted, s.signature_creation_time()) {
+                (Some(revoked), Some(created)) => created > revoked,
+                (Some(_), None) => false,
+                (None, _) => true,
+            }
         })
         .cloned()
         .collect()
 }
 
+/// A copy of `cert` without signatures that contain regular expression
+/// subpackets (i.e. scoped trust signatures).
+pub(crate) fn strip_regex_signatures(cert: Cert) -> Result<Cert> {
+    let packets = cert.into_packets2().filter(|p| match p {
+        Packet::Signature(s) => s.regular_expressions().next().is_none(),
+        _ => true,
+    });
+
+    Cert::from_packets(packets)
+}
+
+/// Adjust the user cert `cert` for export to `compat`
+pub(crate) fn cert_for_export(cert: Cert, compat: ExportCompat) -> Result<Cert> {
+    if compat.regex_trust_signatures() {
+        Ok(cert)
+    } else {
+        strip_regex_signatures(cert)
+    }
+}
+
+/// A copy of `cert` that only contains self-signatures, and only the User
+/// IDs for which `keep_uid` returns true. Of the third-party signatures on
+/// those User IDs, only `certifications` are kept.
+///
+/// If `current_subkeys_only` is set, subkeys that are currently not valid
+/// (e.g. expired or revoked) are dropped.
+pub(crate) fn minimize_cert(
+    cert: &Cert,
+    keep_uid: impl Fn(&UserID) -> bool,
+    certifications: &[Signature],
+    current_subkeys_only: bool,
+) -> Result<Cert> {
+    let pk = cert.primary_key();
+
+    let mut packets: Vec<Packet> = vec![pk.key().clone().into()];
+    packets.extend(
+        pk.self_signatures()
+            .chain(pk.self_revocations())
+            .map(|s| s.clone().into()),
+    );
+
+    for uid in cert.userids().filter(|uid| keep_uid(uid.userid())) {
+        packets.push(uid.userid().clone().into());
+        packets.extend(
+            uid.self_signatures()
+                .chain(uid.self_revocations())
+                .chain(uid.certifications().filter(|s| certifications.contains(s)))
+                .map(|s| s.clone().into()),
+        );
+    }
+
+    let current: Vec<Fingerprint> = match cert.with_policy(SP, None) {
+        Ok(valid) => valid
+            .keys()
+            .subkeys()
+            .alive()
+            .revoked(false)
+            .map(|ka| ka.fingerprint())
+            .collect(),
+        Err(_) => vec![],
+    };
+
+    for sk in cert.keys().subkeys() {
+        if current_subkeys_only && !current.contains(&sk.fingerprint()) {
+            continue;
+        }
+
+        packets.push(sk.key().clone().into());
+        packets.extend(
+            sk.self_signatures()
+                .chain(sk.self_revocations())
+                .map(|s| s.clone().into()),
+        );
+    }
+
+    Cert::from_packets(packets.into_iter())
+}
+
+/// Verify a certificate chain (as exported with [crate::Oca::export_chain]),
+/// without access to a CA database.
+///
+/// `keyring` must contain a cert that is currently valid, with a User ID for
+/// `email` that has a valid certification by the CA cert with the
+/// fingerprint `ca_fingerprint` (which must also be in `keyring`).
+///
+/// The CA cert is only authenticated by `ca_fingerprint`: the keyring itself
+/// can't vouch for which of its certs is the CA.
+///
+/// Returns the user cert and the CA cert.
+pub fn verify_chain(keyring: &[u8], email: &str, ca_fingerprint: &str) -> Result<(Cert, Cert)> {
+    let certs = armored_keyring_to_certs(&keyring)?;
+
+    let ca_fingerprint = normalize_fp(ca_fingerprint)?;
+
+    let ca = certs
+        .iter()
+        .find(|c| c.fingerprint().to_hex() == ca_fingerprint)
+        .ok_or_else(|| anyhow::anyhow!("The CA cert {} is not in the keyring", ca_fingerprint))?;
+
+    for user in &certs {
+        if user.fingerprint() == ca.fingerprint() {
+            continue;
+        }
+
+        let valid = match user.with_policy(SP, None) {
+            Ok(valid) => valid,
+            Err(_) => continue,
+        };
+        if valid.alive().is_err() || is_possibly_revoked(user) {
+            continue;
+        }
+
+        for uid in valid.userids() {
+            if !matches!(uid.email2(), Ok(Some(e)) if e == email) {
+                continue;
+            }
+
+            if !valid_certifications_by(&uid, user, ca.clone(), SP).is_empty() {
+                return Ok((user.clone(), ca.clone()));
+            }
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "No valid cert for {} with a certification by {} found",
+        email,
+        ca_fingerprint
+    ))
+}
+
+/// Algorithms for keys that OpenPGP CA generates (see also [KeyVersion]).
 #[derive(Clone)]
 pub enum CipherSuite {
     Cv25519,
@@ -598,3 +1421,55 @@ impl FromStr for CipherSuite {
         })
     }
 }
+
+/// OpenPGP version of the keys that OpenPGP CA generates.
+///
+/// Versions can be mixed: a CA with a v6 key can certify v4 user keys, and
+/// vice versa (RFC 9580 certifications don't depend on the version of the
+/// certified key). New user keys have the version of the CA key, unless a
+/// version is set explicitly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyVersion {
+    /// OpenPGP v4 keys (RFC 4880)
+    #[default]
+    V4,
+
+    /// OpenPGP v6 keys (RFC 9580).
+    ///
+    /// Generation of v6 keys fails: sequoia-openpgp 1.x, which OpenPGP CA
+    /// is built on, has no support for them.
+    V6,
+}
+
+impl KeyVersion {
+    /// The version of the primary key of `cert`
+    pub(crate) fn of(cert: &Cert) -> Self {
+        match cert.primary_key().key().version() {
+            6 => KeyVersion::V6,
+            _ => KeyVersion::V4,
+        }
+    }
+
+    /// Fail if keys of this version can't be generated
+    fn check_supported(self) -> Result<()> {
+        match self {
+            KeyVersion::V4 => Ok(()),
+            KeyVersion::V6 => Err(anyhow::anyhow!(
+                "OpenPGP v6 keys (RFC 9580) are unsupported by sequoia-openpgp 1.x, \
+                 which OpenPGP CA is built on"
+            )),
+        }
+    }
+}
+
+impl FromStr for KeyVersion {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "4" | "v4" => KeyVersion::V4,
+            "6" | "v6" => KeyVersion::V6,
+            _ => return Err("Unknown key version (supported: 4, 6)"),
+        })
+    }
+}
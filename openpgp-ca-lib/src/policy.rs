@@ -0,0 +1,283 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Key policy: minimum requirements for user keys.
+//!
+//! URI policy: which URI identifiers in User IDs the CA certifies.
+//!
+//! Crypto policy: adjustments of Sequoia's standard policy.
+//!
+//! Notation policy: marking of the certifications that the CA issues.
+
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use sequoia_openpgp::cert::amalgamation::key::ValidSubordinateKeyAmalgamation;
+use sequoia_openpgp::cert::ValidCert;
+use sequoia_openpgp::packet::key::PublicParts;
+use sequoia_openpgp::policy::{AsymmetricAlgorithm, Policy, StandardPolicy};
+use sequoia_openpgp::types::{HashAlgorithm, PublicKeyAlgorithm};
+use sequoia_openpgp::Cert;
+
+use crate::pgp;
+use crate::types::{
+    CryptoPolicy, KeyPolicy, KeyPolicyViolation, Notation, NotationPolicy, UriPolicy,
+};
+use crate::Oca;
+
+const PREF_KEY_POLICY: &str = "key_policy";
+const PREF_URI_POLICY: &str = "uri_policy";
+const PREF_CRYPTO_POLICY: &str = "crypto_policy";
+const PREF_NOTATION_POLICY: &str = "notation_policy";
+
+pub(crate) fn key_policy(oca: &Oca) -> Result<KeyPolicy> {
+    match oca.storage.pref(PREF_KEY_POLICY)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(KeyPolicy::default()),
+    }
+}
+
+pub(crate) fn set_key_policy(oca: &Oca, policy: &KeyPolicy) -> Result<()> {
+    let json = serde_json::to_string(policy)?;
+    oca.storage.pref_set(PREF_KEY_POLICY, &json)
+}
+
+pub(crate) fn uri_policy(oca: &Oca) -> Result<UriPolicy> {
+    match oca.storage.pref(PREF_URI_POLICY)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(UriPolicy::default()),
+    }
+}
+
+pub(crate) fn set_uri_policy(oca: &Oca, policy: &UriPolicy) -> Result<()> {
+    let json = serde_json::to_string(policy)?;
+    oca.storage.pref_set(PREF_URI_POLICY, &json)
+}
+
+pub(crate) fn crypto_policy(oca: &Oca) -> Result<CryptoPolicy> {
+    match oca.storage.pref(PREF_CRYPTO_POLICY)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(CryptoPolicy::default()),
+    }
+}
+
+pub(crate) fn set_crypto_policy(oca: &Oca, policy: &CryptoPolicy) -> Result<()> {
+    // reject policies with unknown algorithm names
+    standard_policy(policy, None)?;
+
+    let json = serde_json::to_string(policy)?;
+    oca.storage.pref_set(PREF_CRYPTO_POLICY, &json)
+}
+
+pub(crate) fn notation_policy(oca: &Oca) -> Result<NotationPolicy> {
+    match oca.storage.pref(PREF_NOTATION_POLICY)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(NotationPolicy::default()),
+    }
+}
+
+pub(crate) fn set_notation_policy(oca: &Oca, policy: &NotationPolicy) -> Result<()> {
+    if policy.required && !policy.enabled {
+        return Err(anyhow::anyhow!(
+            "The notation can only be required if it is added to certifications"
+        ));
+    }
+
+    // Notation names outside of the IETF namespace are "name@domain"
+    // (RFC 4880, 5.2.3.16)
+    if let Some(name) = &policy.name {
+        match name.split_once('@') {
+            Some((n, d)) if !n.is_empty() && !d.is_empty() => {}
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Illegal notation name '{name}', expected the form 'name@domain'"
+                ))
+            }
+        }
+    }
+
+    if policy.value.as_deref() == Some("") {
+        return Err(anyhow::anyhow!("The notation value must not be empty"));
+    }
+
+    let json = serde_json::to_string(policy)?;
+    oca.storage.pref_set(PREF_NOTATION_POLICY, &json)
+}
+
+/// The notation that is added to new certifications by the CA (if any)
+pub(crate) fn certification_notation(oca: &Oca) -> Result<Option<Notation>> {
+    Ok(notation_policy(oca)?.notation(oca.domainname()))
+}
+
+/// Sequoia's standard policy (for evaluation at `time`, or now), adjusted
+/// by `policy`
+pub(crate) fn standard_policy(
+    policy: &CryptoPolicy,
+    time: Option<SystemTime>,
+) -> Result<StandardPolicy<'static>> {
+    let mut sp = match time {
+        Some(time) => StandardPolicy::at(time),
+        None => StandardPolicy::new(),
+    };
+
+    if let Some(cutoff) = policy.sha1_cutoff {
+        sp.reject_hash_at(HashAlgorithm::SHA1, SystemTime::from(cutoff));
+    }
+
+    for name in &policy.rejected_hashes {
+        let hash = HashAlgorithm::from_str(name)
+            .map_err(|_| anyhow::anyhow!("Unknown hash algorithm '{}'", name))?;
+        sp.reject_hash(hash);
+    }
+
+    for name in &policy.rejected_algorithms {
+        let algo = AsymmetricAlgorithm::variants()
+            .find(|a| a.to_string().eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow::anyhow!("Unknown public key algorithm '{}'", name))?;
+        sp.reject_asymmetric_algo(algo);
+    }
+
+    Ok(sp)
+}
+
+/// The most recently created encryption subkey of `valid` that is alive
+/// and not revoked
+pub(crate) fn newest_encryption_subkey<'a>(
+    valid: &ValidCert<'a>,
+) -> Option<ValidSubordinateKeyAmalgamation<'a, PublicParts>> {
+    let subkeys = || valid.keys().subkeys().alive().revoked(false);
+
+    subkeys()
+        .for_transport_encryption()
+        .chain(subkeys().for_storage_encryption())
+        .max_by_key(|ka| ka.creation_time())
+}
+
+/// Does `policy` allow certifying `uri`?
+pub(crate) fn uri_allowed(policy: &UriPolicy, uri: &str) -> bool {
+    policy.allowed_prefixes.iter().any(|prefix| {
+        uri.strip_prefix(prefix.as_str()).is_some_and(|rest| {
+            rest.is_empty() || prefix.ends_with(['/', ':']) || rest.starts_with(['/', ':'])
+        })
+    })
+}
+
+/// Short, lowercase name for a public key algorithm, as used in
+/// [KeyPolicy::allowed_algorithms]
+fn algorithm_name(algo: PublicKeyAlgorithm) -> String {
+    #[allow(deprecated)]
+    match algo {
+        PublicKeyAlgorithm::RSAEncryptSign
+        | PublicKeyAlgorithm::RSAEncrypt
+        | PublicKeyAlgorithm::RSASign => "rsa".to_string(),
+        PublicKeyAlgorithm::DSA => "dsa".to_string(),
+        PublicKeyAlgorithm::ElGamalEncrypt | PublicKeyAlgorithm::ElGamalEncryptSign => {
+            "elgamal".to_string()
+        }
+        PublicKeyAlgorithm::ECDSA => "ecdsa".to_string(),
+        PublicKeyAlgorithm::EdDSA => "eddsa".to_string(),
+        PublicKeyAlgorithm::ECDH => "ecdh".to_string(),
+        a => a.to_string().to_lowercase(),
+    }
+}
+
+/// Check `cert` against `policy`, returns all violations (an empty list
+/// means that the cert is acceptable).
+///
+/// `sp` is the crypto policy that the cert is evaluated with.
+pub(crate) fn check(policy: &KeyPolicy, sp: &dyn Policy, cert: &Cert) -> Vec<KeyPolicyViolation> {
+    let now = SystemTime::now();
+
+    let valid = match cert.with_policy(sp, now) {
+        Ok(valid) => valid,
+        Err(e) => {
+            return vec![KeyPolicyViolation::InvalidCert {
+                reason: e.to_string(),
+            }]
+        }
+    };
+
+    let mut violations = vec![];
+
+    for ka in valid.keys() {
+        let algo = ka.pk_algo();
+        let fingerprint = ka.fingerprint().to_hex();
+
+        if let Some(allowed) = &policy.allowed_algorithms {
+            let name = algorithm_name(algo);
+            if !allowed.iter().any(|a| a.eq_ignore_ascii_case(&name)) {
+                violations.push(KeyPolicyViolation::AlgorithmNotAllowed {
+                    fingerprint: fingerprint.clone(),
+                    algorithm: name,
+                });
+            }
+        }
+
+        if let Some(min) = policy.min_rsa_bits {
+            if algorithm_name(algo) == "rsa" {
+                let bits = ka.mpis().bits().unwrap_or(0);
+                if bits < min {
+                    violations.push(KeyPolicyViolation::RsaKeyTooSmall {
+                        fingerprint,
+                        bits,
+                        min,
+                    });
+                }
+            }
+        }
+    }
+
+    let subkeys = || valid.keys().subkeys().alive().revoked(false);
+
+    if policy.require_encryption_subkey
+        && subkeys()
+            .for_transport_encryption()
+            .chain(subkeys().for_storage_encryption())
+            .next()
+            .is_none()
+    {
+        violations.push(KeyPolicyViolation::MissingEncryptionSubkey);
+    }
+
+    if policy.require_signing_subkey && subkeys().for_signing().next().is_none() {
+        violations.push(KeyPolicyViolation::MissingSigningSubkey);
+    }
+
+    if let Some(max_days) = policy.max_encryption_subkey_age_days {
+        if let Some(ka) = newest_encryption_subkey(&valid) {
+            let days = now
+                .duration_since(ka.creation_time())
+                .unwrap_or(Duration::ZERO)
+                .as_secs()
+                / pgp::SECONDS_IN_DAY;
+
+            if days > max_days {
+                violations.push(KeyPolicyViolation::EncryptionSubkeyTooOld {
+                    fingerprint: ka.fingerprint().to_hex(),
+                    days,
+                    max_days,
+                });
+            }
+        }
+    }
+
+    if let Some(max_days) = policy.max_expiry_days {
+        match valid.primary_key().key_expiration_time() {
+            None => violations.push(KeyPolicyViolation::NoExpiry { max_days }),
+            Some(exp) => {
+                let remaining = exp.duration_since(now).unwrap_or(Duration::ZERO);
+                let days = remaining.as_secs() / pgp::SECONDS_IN_DAY;
+
+                if days > max_days {
+                    violations.push(KeyPolicyViolation::ExpiryTooLong { days, max_days });
+                }
+            }
+        }
+    }
+
+    violations
+}
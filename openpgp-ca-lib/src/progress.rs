@@ -0,0 +1,31 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Progress reporting for long operations that process many certs (see
+//! [crate::Oca::set_progress_handler]).
+
+use crate::types::{Progress, ProgressOperation};
+use crate::Oca;
+
+/// Report to the progress handler of `oca` (if any) that `done` of `total`
+/// items of `operation` have been processed, and that `item` is processed
+/// next
+pub(crate) fn report(
+    oca: &Oca,
+    operation: ProgressOperation,
+    done: usize,
+    total: usize,
+    item: Option<&str>,
+) {
+    if let Some(handler) = oca.progress_handler.borrow().as_ref() {
+        handler(&Progress {
+            operation,
+            done,
+            total,
+            item: item.map(str::to_string),
+        });
+    }
+}
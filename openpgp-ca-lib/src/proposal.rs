@@ -0,0 +1,220 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Changes that a semi-trusted frontend proposed (restd in approval mode),
+//! and that are applied only after a CA admin approves them.
+//!
+//! Proposals are stored in the queue table, which also holds the requests
+//! of a split mode front instance for its back instance. They are
+//! distinguished by the `kind` of the queue entries.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::db::models;
+use crate::events::{self, EventKind};
+use crate::pgp;
+use crate::types::{Proposal, ProposalStatus, ProposedChange};
+use crate::Oca;
+
+fn to_proposal(q: models::Queue) -> Result<Proposal> {
+    let status = q
+        .status
+        .as_deref()
+        .unwrap_or(ProposalStatus::Pending.name())
+        .parse()?;
+
+    Ok(Proposal {
+        id: q.id,
+        created: DateTime::from_naive_utc_and_offset(q.created, Utc),
+        change: serde_json::from_str(&q.task)?,
+        status,
+        decided: q
+            .decided
+            .map(|d| DateTime::from_naive_utc_and_offset(d, Utc)),
+        reason: q.reason,
+    })
+}
+
+/// Check that `change` refers to a cert (or revocation) that the CA knows,
+/// and normalize the fingerprints in it
+fn check(oca: &Oca, change: ProposedChange) -> Result<ProposedChange> {
+    let known = |fp: &str| -> Result<String> {
+        let fp = pgp::normalize_fp(fp)?;
+        if oca.storage.cert_by_fp(&fp)?.is_none() {
+            return Err(anyhow::anyhow!("No cert with fingerprint {} found", fp));
+        }
+        Ok(fp)
+    };
+
+    Ok(match change {
+        ProposedChange::CertImport {
+            fingerprint,
+            cert,
+            name,
+            emails,
+            revocations,
+            days,
+        } => {
+            let c = pgp::to_cert(cert.as_bytes())?;
+            if c.is_tsk() {
+                return Err(anyhow::anyhow!(
+                    "The cert must not contain private key material"
+                ));
+            }
+            if c.fingerprint().to_hex() != pgp::normalize_fp(&fingerprint)? {
+                return Err(anyhow::anyhow!(
+                    "The cert doesn't have the fingerprint {}",
+                    fingerprint
+                ));
+            }
+
+            ProposedChange::CertImport {
+                fingerprint: c.fingerprint().to_hex(),
+                cert,
+                name,
+                emails,
+                revocations,
+                days,
+            }
+        }
+        ProposedChange::CertDeactivate { fingerprint } => ProposedChange::CertDeactivate {
+            fingerprint: known(&fingerprint)?,
+        },
+        ProposedChange::CertDelist { fingerprint } => ProposedChange::CertDelist {
+            fingerprint: known(&fingerprint)?,
+        },
+        ProposedChange::RevocationDelete { hash } => {
+            oca.storage
+                .revocation_by_hash(&hash)?
+                .ok_or_else(|| anyhow::anyhow!("No revocation with hash {} found", hash))?;
+
+            ProposedChange::RevocationDelete { hash }
+        }
+        ProposedChange::RevocationAdd { revocation } => {
+            let sig = pgp::to_signature(revocation.as_bytes())?;
+            if let Some(issuer) = pgp::get_revoc_issuer_fp(&sig)? {
+                known(&issuer.to_hex())?;
+            }
+
+            ProposedChange::RevocationAdd { revocation }
+        }
+    })
+}
+
+/// Queue `change`, pending approval by an admin
+pub(crate) fn submit(oca: &Oca, change: ProposedChange) -> Result<Proposal> {
+    let change = check(oca, change)?;
+
+    let q = oca.storage.proposal_add(&serde_json::to_string(&change)?)?;
+
+    let fp = match &change {
+        ProposedChange::CertImport { fingerprint, .. }
+        | ProposedChange::CertDeactivate { fingerprint }
+        | ProposedChange::CertDelist { fingerprint } => Some(fingerprint.as_str()),
+        ProposedChange::RevocationDelete { .. } | ProposedChange::RevocationAdd { .. } => None,
+    };
+    events::emit(oca, EventKind::ChangeProposed, fp);
+
+    to_proposal(q)
+}
+
+/// All proposals (with `all`), or only the pending ones
+pub(crate) fn list(oca: &Oca, all: bool) -> Result<Vec<Proposal>> {
+    oca.storage
+        .proposals()?
+        .into_iter()
+        .map(to_proposal)
+        .filter(|p| {
+            all || p
+                .as_ref()
+                .map_or(true, |p| p.status == ProposalStatus::Pending)
+        })
+        .collect()
+}
+
+fn by_id(oca: &Oca, id: i32) -> Result<Proposal> {
+    list(oca, true)?
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| anyhow::anyhow!("No proposal with id {} found", id))
+}
+
+fn pending(oca: &Oca, id: i32) -> Result<Proposal> {
+    let proposal = by_id(oca, id)?;
+
+    if proposal.status != ProposalStatus::Pending {
+        return Err(anyhow::anyhow!(
+            "Proposal {} is not pending (it was {})",
+            id,
+            proposal.status
+        ));
+    }
+
+    Ok(proposal)
+}
+
+fn apply(oca: &Oca, change: &ProposedChange) -> Result<()> {
+    match change {
+        ProposedChange::CertImport {
+            fingerprint,
+            cert,
+            name,
+            emails,
+            revocations,
+            days,
+        } => {
+            if oca.storage.cert_by_fp(fingerprint)?.is_some() {
+                oca.cert_import_update(cert.as_bytes())?;
+                for rev in revocations {
+                    oca.revocation_add(rev.as_bytes())?;
+                }
+            } else {
+                let revocations: Vec<&[u8]> = revocations.iter().map(|r| r.as_bytes()).collect();
+                let emails: Vec<&str> = emails.iter().map(String::as_str).collect();
+
+                oca.cert_import_new(
+                    cert.as_bytes(),
+                    &revocations,
+                    name.as_deref(),
+                    &emails,
+                    *days,
+                )?;
+            }
+        }
+        ProposedChange::CertDeactivate { fingerprint } => oca.cert_deactivate(fingerprint)?,
+        ProposedChange::CertDelist { fingerprint } => oca.cert_delist(fingerprint)?,
+        ProposedChange::RevocationDelete { hash } => oca.revocation_delete(hash)?,
+        ProposedChange::RevocationAdd { revocation } => {
+            oca.revocation_add(revocation.as_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply the change of the pending proposal `id`
+pub(crate) fn approve(oca: &Oca, id: i32) -> Result<Proposal> {
+    // The change and the decision are stored in one transaction, so a
+    // proposal can't stay pending after its change has been applied
+    oca.storage.transaction(&mut || {
+        let proposal = pending(oca, id)?;
+
+        apply(oca, &proposal.change)?;
+        oca.storage
+            .proposal_decide(id, ProposalStatus::Approved.name(), None)
+    })?;
+
+    by_id(oca, id)
+}
+
+/// Reject the pending proposal `id`, without applying its change
+pub(crate) fn reject(oca: &Oca, id: i32, reason: Option<&str>) -> Result<()> {
+    pending(oca, id)?;
+
+    oca.storage
+        .proposal_decide(id, ProposalStatus::Rejected.name(), reason)
+}
@@ -0,0 +1,157 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Replacing the CA key (e.g. after a compromise of the old key).
+//!
+//! The old CA cert is kept in the database as an inactive cacert entry.
+//! User certs that were certified by the old CA key are re-certified with
+//! the new key. The tsigs that users made on the old CA cert can't be
+//! carried over, so the users who need to tsign the new CA cert are
+//! reported.
+//!
+//! In split mode, the back instance generates the new key, and the front
+//! instance imports the new public CA cert. The front instance then queues
+//! certification requests for the back instance.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::backend::Backend;
+use crate::db::models;
+use crate::pgp::KeyVersion;
+use crate::types::{CaRekeyParams, CaRekeyReport, TsigStatus};
+use crate::{cert, pgp, Oca, Uninit};
+
+/// Current user certs that have tsigned the CA cert (with the current or
+/// an older key of the same user)
+fn tsig_signers(oca: &Oca) -> Result<Vec<models::Cert>> {
+    let mut seen = HashSet::new();
+
+    Ok(oca
+        .ca_tsigs()?
+        .into_iter()
+        .filter(|t| t.status == TsigStatus::Current)
+        .filter_map(|t| t.cert)
+        .chain(oca.ca_tsigs_missing()?)
+        .filter(|c| seen.insert(c.id))
+        .collect())
+}
+
+/// Persist `priv_cert` as the active CA cert, and re-open the CA instance.
+fn replace_ca_cert(oca: Oca, priv_cert: String, fingerprint: &str) -> Result<Oca> {
+    let storage = oca.storage.into_uninit();
+    storage.transaction(|| storage.cacert_replace(priv_cert, fingerprint))?;
+
+    Uninit { storage }.init_from_db_state()
+}
+
+pub(crate) fn ca_rekey(oca: Oca, params: &CaRekeyParams) -> Result<(Oca, CaRekeyReport)> {
+    let split_back = match oca.backend() {
+        Backend::Softkey => false,
+        Backend::SplitBack(inner) if matches!(**inner, Backend::Softkey) => true,
+        Backend::SplitFront => {
+            return Err(anyhow::anyhow!(
+                "On a split-mode front instance, replace the key on the back instance, \
+                 then import the new CA cert on the front instance."
+            ))
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Replacing the CA key is only supported for softkey-based CAs"
+            ))
+        }
+    };
+
+    // The back instance has no user data: user certs are re-certified
+    // after the new CA cert is imported into the front instance.
+    let (old, tsig_needed) = if split_back {
+        (oca.secret().cert()?, vec![])
+    } else {
+        (oca.ca_get_cert_pub()?, tsig_signers(&oca)?)
+    };
+
+    let (new, _) = pgp::make_ca_cert(
+        oca.domainname(),
+        params.name.as_deref(),
+        params.cipher_suite.clone(),
+        KeyVersion::of(&old),
+        params
+            .validity_days
+            .map(|days| Duration::from_secs(days * pgp::SECONDS_IN_DAY)),
+    )?;
+    let new_fp = new.fingerprint().to_hex();
+
+    let oca = replace_ca_cert(oca, pgp::cert_to_armored_private_key(&new)?, &new_fp)?;
+
+    let recertified = if split_back {
+        vec![]
+    } else {
+        cert::certs_re_certify(&oca, old.clone(), params.certification_validity_days)?
+    };
+
+    Ok((
+        oca,
+        CaRekeyReport {
+            old_fingerprint: old.fingerprint().to_hex(),
+            new_fingerprint: new_fp,
+            recertified,
+            tsig_needed,
+        },
+    ))
+}
+
+pub(crate) fn ca_rekey_import(
+    oca: Oca,
+    new_ca: &[u8],
+    certification_validity_days: u64,
+) -> Result<(Oca, CaRekeyReport)> {
+    if !matches!(oca.backend(), Backend::SplitFront) {
+        return Err(anyhow::anyhow!(
+            "Importing a new CA cert is only supported on split-mode front instances"
+        ));
+    }
+
+    let new = pgp::to_cert(new_ca)?;
+    if new.is_tsk() {
+        return Err(anyhow::anyhow!(
+            "The new CA cert must not contain private key material"
+        ));
+    }
+
+    let old = oca.ca_get_cert_pub()?;
+    let new_fp = new.fingerprint().to_hex();
+
+    if new.fingerprint() == old.fingerprint() {
+        return Err(anyhow::anyhow!("The CA cert {} is already in use", new_fp));
+    }
+
+    let email = format!("openpgp-ca@{}", oca.domainname());
+    if !new
+        .userids()
+        .any(|u| matches!(u.email2(), Ok(Some(e)) if e == email))
+    {
+        return Err(anyhow::anyhow!("The new CA cert has no User ID {}", email));
+    }
+
+    let tsig_needed = tsig_signers(&oca)?;
+
+    let oca = replace_ca_cert(oca, pgp::cert_to_armored(&new)?, &new_fp)?;
+
+    // The certifications are queued for the back instance
+    let recertified = cert::certs_re_certify(&oca, old.clone(), certification_validity_days)?;
+
+    Ok((
+        oca,
+        CaRekeyReport {
+            old_fingerprint: old.fingerprint().to_hex(),
+            new_fingerprint: new_fp,
+            recertified,
+            tsig_needed,
+        },
+    ))
+}
@@ -0,0 +1,214 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Self-service replacement of user keys.
+//!
+//! A user who moves to a new key submits the new cert, along with a
+//! handover statement (see [handover_statement]) that is signed with their
+//! current key. The signature shows that the holder of the old key asks for
+//! the replacement.
+//!
+//! Submitted replacements are staged until an admin approves them. Only
+//! then is the new cert stored for the user, and certified for the email
+//! addresses of the old cert (on a split-mode front instance: the
+//! certifications are queued for the back instance).
+
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use sequoia_openpgp::Cert;
+
+use crate::db::models;
+use crate::events::{self, EventKind};
+use crate::types::{
+    CertificationProfile, CertificationValidity, KeyPolicyError, KeyReplacementStatus,
+};
+use crate::{blocklist, cert, pgp, Oca};
+
+/// The statement that a user signs with their old key, to ask for the
+/// replacement of the cert `old_fp` with the cert `new_fp`
+pub(crate) fn handover_statement(oca: &Oca, old_fp: &str, new_fp: &str) -> Result<String> {
+    Ok(format!(
+        "OpenPGP CA key replacement\nCA: {}\nOld: {}\nNew: {}\n",
+        oca.domainname(),
+        pgp::normalize_fp(old_fp)?,
+        pgp::normalize_fp(new_fp)?
+    ))
+}
+
+/// Check that `handover` is a valid signature over `statement`, by a
+/// signing capable key of `old`
+fn check_handover(oca: &Oca, old: &Cert, statement: &str, handover: &str) -> Result<()> {
+    let sig =
+        pgp::to_signature(handover.as_bytes()).context("The handover signature can't be parsed")?;
+
+    let policy = oca.policy()?;
+    let valid = old
+        .with_policy(&policy, sig.signature_creation_time())
+        .context("The old cert is not valid")?;
+
+    if valid
+        .keys()
+        .for_signing()
+        .alive()
+        .revoked(false)
+        .any(|ka| sig.verify_message(ka.key(), statement.as_bytes()).is_ok())
+    {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "The handover statement is not signed by the old cert {}",
+            old.fingerprint()
+        ))
+    }
+}
+
+/// Stage `new_cert` as the replacement of the cert `old_fp`, pending
+/// approval by an admin.
+///
+/// `handover` is a detached signature over the [handover_statement], made
+/// by the old cert. The new cert must have User IDs for all email
+/// addresses of the old cert, and conform to the key policy.
+pub(crate) fn submit(
+    oca: &Oca,
+    old_fp: &str,
+    new_cert: &[u8],
+    handover: &str,
+) -> Result<models::KeyReplacement> {
+    let old_fp = pgp::normalize_fp(old_fp)?;
+
+    let db_cert = oca
+        .storage
+        .cert_by_fp(&old_fp)?
+        .ok_or_else(|| anyhow::anyhow!("No cert with fingerprint {} found", old_fp))?;
+    if db_cert.inactive {
+        return Err(anyhow::anyhow!("The cert {} is inactive", old_fp));
+    }
+
+    let new = pgp::to_cert(new_cert).context("The replacement cert can't be parsed")?;
+    if new.is_tsk() {
+        return Err(anyhow::anyhow!(
+            "The replacement cert must not contain private key material"
+        ));
+    }
+
+    let new_fp = new.fingerprint().to_hex();
+    if oca.storage.cert_by_fp(&new_fp)?.is_some() {
+        return Err(anyhow::anyhow!(
+            "The replacement cert {} is already known to this CA",
+            new_fp
+        ));
+    }
+
+    let old = oca.storage.cert_parsed(&db_cert)?;
+    let statement = handover_statement(oca, &old_fp, &new_fp)?;
+    check_handover(oca, &old, &statement, handover)?;
+
+    let new_emails: HashSet<String> = new
+        .userids()
+        .filter_map(|u| u.userid().email_normalized().ok().flatten())
+        .collect();
+    let missing: Vec<_> = oca
+        .storage
+        .emails_by_cert(&db_cert)?
+        .into_iter()
+        .map(|e| e.addr)
+        .filter(|addr| !new_emails.contains(&addr.to_lowercase()))
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow::anyhow!(
+            "The replacement cert has no User ID for {}",
+            missing.join(", ")
+        ));
+    }
+
+    let violations = oca.check_key_policy(&new)?;
+    if !violations.is_empty() {
+        return Err(KeyPolicyError { violations }.into());
+    }
+
+    blocklist::check(oca, Some(&new), &[])?;
+
+    let replacement = oca.storage.key_replacement_add(
+        &db_cert,
+        &pgp::cert_to_armored(&new)?,
+        &new_fp,
+        handover,
+    )?;
+
+    events::emit(oca, EventKind::KeyReplacementRequested, Some(&old_fp));
+
+    Ok(replacement)
+}
+
+fn pending(oca: &Oca, id: i32) -> Result<models::KeyReplacement> {
+    let replacement = oca
+        .storage
+        .key_replacement_by_id(id)?
+        .ok_or_else(|| anyhow::anyhow!("No key replacement with id {} found", id))?;
+
+    if replacement.status != KeyReplacementStatus::Pending.name() {
+        return Err(anyhow::anyhow!(
+            "Key replacement {} is not pending (it was {})",
+            id,
+            replacement.status
+        ));
+    }
+
+    Ok(replacement)
+}
+
+/// Store the replacement cert of the pending key replacement `id` for the
+/// user of the old cert, and certify it for the email addresses of the old
+/// cert. With `deactivate_old`, the old cert is marked as inactive.
+///
+/// Returns the stored replacement cert.
+pub(crate) fn approve(
+    oca: &Oca,
+    id: i32,
+    validity_days: Option<u64>,
+    deactivate_old: bool,
+) -> Result<models::Cert> {
+    let replacement = pending(oca, id)?;
+
+    let old = oca
+        .storage
+        .cert_by_id(replacement.cert_id)?
+        .ok_or_else(|| anyhow::anyhow!("The replaced cert doesn't exist anymore"))?;
+    let emails: Vec<_> = oca
+        .storage
+        .emails_by_cert(&old)?
+        .into_iter()
+        .map(|e| e.addr)
+        .collect();
+    let emails: Vec<&str> = emails.iter().map(String::as_str).collect();
+
+    let new = pgp::to_cert(replacement.new_cert.as_bytes())?;
+    let certified = cert::certify_emails(
+        oca,
+        &new,
+        Some(&emails),
+        CertificationValidity::from_days(validity_days),
+        CertificationProfile::Default,
+    )?;
+
+    let cert = oca.storage.key_replacement_approve(
+        &replacement,
+        &pgp::cert_to_armored(&certified)?,
+        &emails,
+        deactivate_old,
+    )?;
+
+    events::emit(oca, EventKind::CertImported, Some(&cert.fingerprint));
+
+    Ok(cert)
+}
+
+pub(crate) fn reject(oca: &Oca, id: i32, reason: Option<&str>) -> Result<()> {
+    let replacement = pending(oca, id)?;
+
+    oca.storage.key_replacement_reject(&replacement, reason)
+}
@@ -0,0 +1,94 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Retention of rows in tables that grow over time (done queue entries,
+//! cert versions).
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+
+use crate::types::{CleanupReport, Retention, RetentionPolicy};
+use crate::Oca;
+
+const PREF_RETENTION_POLICY: &str = "retention_policy";
+
+pub(crate) fn retention_policy(oca: &Oca) -> Result<RetentionPolicy> {
+    match oca.storage.pref(PREF_RETENTION_POLICY)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(RetentionPolicy::default()),
+    }
+}
+
+pub(crate) fn set_retention_policy(oca: &Oca, policy: &RetentionPolicy) -> Result<()> {
+    let json = serde_json::to_string(policy)?;
+    oca.storage.pref_set(PREF_RETENTION_POLICY, &json)
+}
+
+/// Ids of the `rows` (id, created) that `retention` doesn't retain at `now`
+fn expired(rows: &[(i32, NaiveDateTime)], retention: &Retention, now: NaiveDateTime) -> Vec<i32> {
+    let cutoff = retention
+        .max_age_days
+        .map(|days| now - chrono::Duration::days(days as i64));
+
+    let mut rows = rows.to_vec();
+    rows.sort_by_key(|(id, _)| std::cmp::Reverse(*id)); // newest first
+
+    rows.iter()
+        .enumerate()
+        .filter(|(idx, (_, created))| {
+            retention.max_count.is_some_and(|max| *idx as u64 >= max)
+                || cutoff.is_some_and(|cutoff| *created < cutoff)
+        })
+        .map(|(_, (id, _))| *id)
+        .collect()
+}
+
+/// Ids of the cert versions in `rows` (id, cert_id, created) that
+/// `retention` doesn't retain at `now` (`max_count` applies to the versions
+/// of each cert separately)
+fn expired_per_cert(
+    rows: &[(i32, i32, NaiveDateTime)],
+    retention: &Retention,
+    now: NaiveDateTime,
+) -> Vec<i32> {
+    let mut by_cert: BTreeMap<i32, Vec<(i32, NaiveDateTime)>> = BTreeMap::new();
+    for (id, cert_id, created) in rows {
+        by_cert.entry(*cert_id).or_default().push((*id, *created));
+    }
+
+    by_cert
+        .values()
+        .flat_map(|rows| expired(rows, retention, now))
+        .collect()
+}
+
+/// Remove rows that the retention policy doesn't retain (in `dry_run` mode,
+/// only count them)
+pub(crate) fn cleanup(oca: &Oca, dry_run: bool) -> Result<CleanupReport> {
+    let policy = retention_policy(oca)?;
+    let now = Utc::now().naive_utc();
+
+    let queue = expired(&oca.storage.queue_done_created()?, &policy.queue_done, now);
+    let versions = expired_per_cert(
+        &oca.storage.cert_versions_created()?,
+        &policy.cert_versions,
+        now,
+    );
+
+    if dry_run {
+        return Ok(CleanupReport {
+            queue_done: queue.len(),
+            cert_versions: versions.len(),
+        });
+    }
+
+    Ok(CleanupReport {
+        queue_done: oca.storage.queue_delete(&queue)?,
+        cert_versions: oca.storage.cert_versions_delete(&versions)?,
+    })
+}
@@ -6,30 +6,45 @@
 // SPDX-FileCopyrightText: 2019-2022 Heiko Schaefer <heiko@schaefer.name>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::time::Duration;
+
 use anyhow::{Context, Result};
+use chrono::{DateTime, SubsecRound, Utc};
 use sequoia_openpgp::packet::Signature;
+use sequoia_openpgp::policy::Policy;
+use sequoia_openpgp::types::RevocationStatus;
 use sequoia_openpgp::Cert;
 use sequoia_openpgp::KeyHandle;
+use tokio::runtime::Runtime;
 
 use crate::db::models;
-use crate::pgp;
+use crate::types::{
+    CertStatus, CertStatusKind, ExportChannel, ExportCompat, PublicationChannel,
+    RevocationPublication, SignedCertStatus,
+};
+use crate::{export, pgp, tor, Oca};
+
+/// Version of the schema of [CertStatus]
+const CERT_STATUS_VERSION: u32 = 1;
+
+const PREF_REVOCATION_PUBLICATION: &str = "revocation_publication";
 
-/// Check if the CA database has a variant of the revocation
-/// certificate 'revocation' (according to Signature::normalized_eq()).
-pub(crate) fn check_for_equivalent_revocation(
+/// Find a variant of the revocation certificate 'revocation' in
+/// `revocations` (according to Signature::normalized_eq()).
+pub(crate) fn equivalent_revocation(
     revocations: Vec<models::Revocation>,
     revocation: &Signature,
-) -> Result<bool> {
+) -> Result<Option<models::Revocation>> {
     for db_rev in revocations {
         let r = pgp::to_signature(db_rev.revocation.as_bytes())
             .context("Couldn't re-armor revocation cert from CA db")?;
 
         if revocation.normalized_eq(&r) {
-            return Ok(true);
+            return Ok(Some(db_rev));
         }
     }
 
-    Ok(false)
+    Ok(None)
 }
 
 /// Verify that `revoc_cert` can be used to revoke the primary key of `cert`.
@@ -80,3 +95,169 @@ pub(crate) fn search_revocable_cert_by_keyid(
     }
     Ok(None)
 }
+
+/// The revocation of `cert` that is in effect under `policy` (if any), as
+/// its creation time and a description of the reason.
+///
+/// If there are multiple revocations, the earliest one is relevant.
+pub(crate) fn effective_revocation(
+    cert: &Cert,
+    policy: &dyn Policy,
+) -> Option<(Option<DateTime<Utc>>, String)> {
+    if let RevocationStatus::Revoked(sigs) = cert.revocation_status(policy, None) {
+        let sig = sigs.iter().min_by_key(|s| s.signature_creation_time())?;
+
+        let reason = match sig.reason_for_revocation() {
+            Some((code, reason)) => {
+                format!("{} ({})", code, String::from_utf8_lossy(reason))
+            }
+            None => "Revocation reason unknown".to_string(),
+        };
+
+        Some((sig.signature_creation_time().map(|t| t.into()), reason))
+    } else {
+        None
+    }
+}
+
+/// The revocation status of the cert `fingerprint`, signed by the CA.
+///
+/// A cert is considered revoked if our copy of it is revoked under the CA's
+/// policy (revocations that have been deposited, but not applied, don't
+/// count). Relying parties may rely on the statement for `validity`.
+pub(crate) fn cert_status(
+    oca: &Oca,
+    fingerprint: &str,
+    validity: Duration,
+) -> Result<SignedCertStatus> {
+    let fingerprint = pgp::normalize_fp(fingerprint)?;
+
+    let (status, revocation_time, reason) = match oca.storage.cert_by_fp(&fingerprint)? {
+        Some(db_cert) => {
+            let cert = oca.storage.cert_parsed(&db_cert)?;
+
+            match effective_revocation(&cert, &oca.policy()?) {
+                Some((time, reason)) => (CertStatusKind::Revoked, time, Some(reason)),
+                None => (CertStatusKind::Good, None, None),
+            }
+        }
+        None => (CertStatusKind::Unknown, None, None),
+    };
+
+    let produced_at = Utc::now().trunc_subsecs(0);
+
+    let status = CertStatus {
+        version: CERT_STATUS_VERSION,
+        ca_fingerprint: oca.ca_get_cert_pub()?.fingerprint().to_hex(),
+        fingerprint,
+        status,
+        revocation_time,
+        reason,
+        produced_at,
+        next_update: produced_at + chrono::Duration::from_std(validity)?,
+    };
+
+    let status = serde_json::to_string(&status)?;
+    let signature = oca.secret().sign_detached(status.as_bytes())?;
+
+    Ok(SignedCertStatus { status, signature })
+}
+
+pub(crate) fn publication(oca: &Oca) -> Result<RevocationPublication> {
+    match oca.storage.pref(PREF_REVOCATION_PUBLICATION)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(RevocationPublication::default()),
+    }
+}
+
+pub(crate) fn set_publication(oca: &Oca, config: &RevocationPublication) -> Result<()> {
+    for keyserver in &config.keyservers {
+        sequoia_net::KeyServer::new(sequoia_net::Policy::Encrypted, keyserver).with_context(
+            || format!("Illegal keyserver '{keyserver}' (expected e.g. hkps://keys.openpgp.org)"),
+        )?;
+    }
+
+    let json = serde_json::to_string(config)?;
+    oca.storage.pref_set(PREF_REVOCATION_PUBLICATION, &json)
+}
+
+/// Publish the cert that the (applied) `revocation` revoked through the
+/// channels that are configured for this CA, and record the outcome per
+/// channel.
+///
+/// Failures to publish are recorded, not returned. Certs are not published
+/// to keyservers or WKD if they are delisted on that channel.
+pub(crate) fn publish(oca: &Oca, revocation: &models::Revocation) -> Result<models::Revocation> {
+    if !revocation.published {
+        return Err(anyhow::anyhow!(
+            "Revocation {} has not been applied",
+            revocation.hash
+        ));
+    }
+
+    let db_cert = oca
+        .storage
+        .cert_by_id(revocation.cert_id)?
+        .ok_or_else(|| anyhow::anyhow!("No cert found for revocation {}", revocation.hash))?;
+
+    let config = publication(oca)?;
+
+    if !config.keyservers.is_empty() && !db_cert.is_delisted(ExportChannel::Keyserver) {
+        let cert = oca.storage.cert_parsed(&db_cert)?;
+        let rt = Runtime::new()?;
+
+        let errors: Vec<String> = config
+            .keyservers
+            .iter()
+            .filter_map(|ks| {
+                tor::keyserver_send(oca, &rt, ks, &cert)
+                    .err()
+                    .map(|e| format!("{ks}: {e}"))
+            })
+            .collect();
+
+        let error = (!errors.is_empty()).then(|| errors.join("; "));
+        oca.storage.revocation_publication_record(
+            revocation,
+            PublicationChannel::Keyserver,
+            error.as_deref(),
+        )?;
+    }
+
+    let wkd_path = config
+        .wkd_path
+        .as_ref()
+        .filter(|_| !db_cert.is_delisted(ExportChannel::Wkd));
+
+    if let Some(path) = wkd_path {
+        let emails: Vec<String> = oca
+            .storage
+            .emails_by_cert(&db_cert)?
+            .into_iter()
+            .map(|e| e.addr)
+            .collect();
+        let emails: Vec<&str> = emails.iter().map(String::as_str).collect();
+
+        // Updates the files of the cert's addresses, other entries of
+        // the WKD are left in place
+        let res = export::wkd_export(
+            oca,
+            oca.domainname(),
+            path,
+            false,
+            ExportCompat::default(),
+            Some(&emails),
+        );
+
+        let error = res.err().map(|e| e.to_string());
+        oca.storage.revocation_publication_record(
+            revocation,
+            PublicationChannel::Wkd,
+            error.as_deref(),
+        )?;
+    }
+
+    oca.storage
+        .revocation_by_hash(&revocation.hash)?
+        .ok_or_else(|| anyhow::anyhow!("No revocation found for {}", revocation.hash))
+}
@@ -0,0 +1,177 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Rotation of encryption subkeys, according to the maximum subkey age of
+//! the key policy.
+
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sequoia_openpgp::types::RevocationStatus;
+use sequoia_openpgp::Cert;
+
+use crate::db::models;
+use crate::types::{MimeEntity, SubkeyRotation};
+use crate::{cert, pgp, policy, Oca};
+
+/// The newest encryption subkey of `cert`, and when it is due for rotation
+pub(crate) fn subkey_rotation(oca: &Oca, cert: &Cert) -> Result<SubkeyRotation> {
+    let max_days = oca.key_policy()?.max_encryption_subkey_age_days;
+    let sp = oca.policy()?;
+
+    let valid = cert.with_policy(&sp, None)?;
+    let newest = policy::newest_encryption_subkey(&valid);
+
+    let created = newest.as_ref().map(|ka| ka.creation_time());
+
+    Ok(SubkeyRotation {
+        fingerprint: cert.fingerprint().to_hex(),
+        subkey: newest.as_ref().map(|ka| ka.fingerprint().to_hex()),
+        created: created.map(DateTime::<Utc>::from),
+        expiration: newest
+            .as_ref()
+            .and_then(|ka| ka.key_expiration_time())
+            .map(DateTime::<Utc>::from),
+        rotation_due: created
+            .zip(max_days)
+            .map(|(c, d)| (c + Duration::from_secs(d * pgp::SECONDS_IN_DAY)).into()),
+    })
+}
+
+/// Active, valid certs whose newest encryption subkey is (or will be,
+/// within `days`) older than the maximum subkey age of the key policy.
+///
+/// Certs without any valid encryption subkey are included.
+pub(crate) fn subkey_rotation_due(
+    oca: &Oca,
+    days: u64,
+) -> Result<Vec<(models::Cert, SubkeyRotation)>> {
+    if oca.key_policy()?.max_encryption_subkey_age_days.is_none() {
+        return Err(anyhow::anyhow!(
+            "The key policy doesn't limit the age of encryption subkeys"
+        ));
+    }
+
+    let sp = oca.policy()?;
+    let limit: DateTime<Utc> =
+        (SystemTime::now() + Duration::from_secs(days * pgp::SECONDS_IN_DAY)).into();
+
+    let mut res = vec![];
+
+    for db_cert in oca
+        .storage
+        .certs()?
+        .into_iter()
+        // ignore "inactive" Certs
+        .filter(|c| !c.inactive)
+    {
+        let c = oca.storage.cert_parsed(&db_cert)?;
+
+        // Only remind owners of certs that are in use
+        let valid = match c.with_policy(&sp, None) {
+            Ok(valid) => valid,
+            Err(_) => continue,
+        };
+        if valid.alive().is_err() {
+            continue;
+        }
+        if let RevocationStatus::Revoked(_) = valid.revocation_status() {
+            continue;
+        }
+
+        let rotation = subkey_rotation(oca, &c)?;
+        let due = match rotation.rotation_due {
+            Some(due) => due <= limit,
+            None => true, // no encryption subkey
+        };
+
+        if due {
+            res.push((db_cert, rotation));
+        }
+    }
+
+    Ok(res)
+}
+
+/// A notification email for the owner of `cert`, about the rotation of its
+/// encryption subkey
+pub(crate) fn subkey_rotation_reminder(
+    oca: &Oca,
+    cert: &models::Cert,
+    rotation: &SubkeyRotation,
+) -> Result<MimeEntity> {
+    let name = oca.cert_get_name(cert)?;
+    let date = |t: DateTime<Utc>| t.format("%F").to_string();
+
+    let status = match (&rotation.subkey, rotation.created, rotation.rotation_due) {
+        (Some(subkey), Some(created), Some(due)) => format!(
+            "The encryption subkey {} of your OpenPGP key {} was created on {}.\n\
+             According to the key policy of {}, it must be rotated by {}.",
+            subkey,
+            rotation.fingerprint,
+            date(created),
+            oca.domainname(),
+            date(due)
+        ),
+        _ => format!(
+            "Your OpenPGP key {} has no valid encryption subkey.",
+            rotation.fingerprint
+        ),
+    };
+
+    let text = format!(
+        "Hello {name},\n\n\
+         {status}\n\n\
+         Please add a new encryption subkey to your key, and send the updated\n\
+         key to the OpenPGP CA administrators.\n"
+    );
+
+    oca.mail_text(&text)
+}
+
+/// Merge `cert` into the stored cert, as an encryption subkey rotation:
+/// the update must add a new encryption subkey, which is younger than the
+/// maximum subkey age of the key policy.
+pub(crate) fn cert_import_rotation(oca: &Oca, cert: &[u8]) -> Result<()> {
+    let update = pgp::to_cert(cert)?;
+    let fp = update.fingerprint().to_hex();
+
+    let db_cert = oca
+        .storage
+        .cert_by_fp(&fp)?
+        .ok_or_else(|| anyhow::anyhow!("No cert with fingerprint {} found", fp))?;
+    let stored = oca.storage.cert_parsed(&db_cert)?;
+
+    let merged = stored.clone().merge_public(update)?;
+
+    let before = subkey_rotation(oca, &stored)?;
+    let after = subkey_rotation(oca, &merged)?;
+
+    let fresh = match (&after.subkey, after.created, before.created) {
+        (None, ..) => false,
+        (Some(_), _, None) => true,
+        (Some(_), Some(new), Some(old)) => after.subkey != before.subkey && new > old,
+        (Some(_), None, Some(_)) => false,
+    };
+    if !fresh {
+        return Err(anyhow::anyhow!(
+            "The update of cert {} doesn't add a new encryption subkey",
+            fp
+        ));
+    }
+
+    if let Some(due) = after.rotation_due {
+        if due <= Utc::now() {
+            return Err(anyhow::anyhow!(
+                "The new encryption subkey {} is older than the key policy allows",
+                after.subkey.unwrap_or_default()
+            ));
+        }
+    }
+
+    cert::cert_import_update(oca, cert, false)
+}
@@ -0,0 +1,125 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Search across the certs and users of the CA.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use crate::db::models;
+use crate::types::{SearchField, SearchMatch};
+use crate::Oca;
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(needle)
+}
+
+/// The fields of `cert` (and its `user`) that contain `text`
+fn matching_fields(
+    oca: &Oca,
+    cert: &models::Cert,
+    user: Option<&models::User>,
+    text: &str,
+) -> Result<Vec<SearchField>> {
+    let needle = text.to_lowercase();
+    let fp: String = needle.chars().filter(|c| !c.is_whitespace()).collect();
+
+    let mut fields = vec![];
+
+    if contains(&cert.fingerprint, &fp) {
+        fields.push(SearchField::Fingerprint);
+    }
+    if let Some(name) = user.and_then(|u| u.name.as_deref()) {
+        if contains(name, &needle) {
+            fields.push(SearchField::Name);
+        }
+    }
+    if oca
+        .storage
+        .emails_by_cert(cert)?
+        .iter()
+        .any(|e| contains(&e.addr, &needle))
+    {
+        fields.push(SearchField::Email);
+    }
+    if oca
+        .storage
+        .cert_parsed(cert)?
+        .userids()
+        .any(|uid| contains(&String::from_utf8_lossy(uid.value()), &needle))
+    {
+        fields.push(SearchField::UserId);
+    }
+    if let Some(notes) = &cert.notes {
+        if contains(notes, &needle) {
+            fields.push(SearchField::CertNotes);
+        }
+    }
+    if let Some(notes) = user.and_then(|u| u.notes.as_deref()) {
+        if contains(notes, &needle) {
+            fields.push(SearchField::UserNotes);
+        }
+    }
+
+    let mut tags = oca.storage.tags_by_cert(cert)?;
+    if let Some(user) = user {
+        tags.extend(oca.storage.tags_by_user(user)?);
+    }
+    if tags
+        .iter()
+        .any(|t| contains(&t.name, &needle) || contains(&t.value, &needle))
+    {
+        fields.push(SearchField::Tags);
+    }
+
+    Ok(fields)
+}
+
+/// Search certs by `text` in their fingerprint, user name, email
+/// addresses, User IDs, notes and tags
+pub(crate) fn search(oca: &Oca, text: &str) -> Result<Vec<SearchMatch>> {
+    if text.trim().is_empty() {
+        return Err(anyhow::anyhow!("Empty search term"));
+    }
+
+    // Candidates: the database columns are matched in SQL, User IDs are
+    // only available in the stored certs
+    let mut candidates: BTreeMap<i32, models::Cert> = oca
+        .storage
+        .certs_search(text)?
+        .into_iter()
+        .map(|c| (c.id, c))
+        .collect();
+
+    let needle = text.to_lowercase();
+    for cert in oca.storage.certs()? {
+        if candidates.contains_key(&cert.id) {
+            continue;
+        }
+
+        if oca
+            .storage
+            .cert_parsed(&cert)?
+            .userids()
+            .any(|uid| contains(&String::from_utf8_lossy(uid.value()), &needle))
+        {
+            candidates.insert(cert.id, cert);
+        }
+    }
+
+    let mut matches = vec![];
+    for cert in candidates.into_values() {
+        let user = oca.storage.user_by_cert(&cert)?;
+        let fields = matching_fields(oca, &cert, user.as_ref(), text)?;
+
+        if !fields.is_empty() {
+            matches.push(SearchMatch { cert, user, fields });
+        }
+    }
+
+    Ok(matches)
+}
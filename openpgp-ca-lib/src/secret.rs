@@ -12,13 +12,16 @@ use std::time::{Duration, SystemTime};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use sequoia_openpgp::cert::CertRevocationBuilder;
+use sequoia_openpgp::packet::signature::subpacket::NotationDataFlags;
 use sequoia_openpgp::packet::{signature::SignatureBuilder, Signature, UserID};
 use sequoia_openpgp::serialize::Serialize;
 use sequoia_openpgp::types::{ReasonForRevocation, SignatureType};
 use sequoia_openpgp::{armor, cert, Cert, Packet};
 
 use crate::backend::CertificationBackend;
+use crate::i18n::{tr, Language, Msg};
 use crate::pgp;
+use crate::types::{CertificationProfile, CertificationValidity, ExportCompat, Notation};
 
 /// Abstraction of operations that need private key material
 pub(crate) trait CaSec {
@@ -27,12 +30,27 @@ pub(crate) trait CaSec {
         &self,
         cert: &Cert,
         uids_certify: &[&UserID],
-        duration_days: Option<u64>,
+        validity: Option<CertificationValidity>,
+        profile: CertificationProfile,
+        notation: Option<&Notation>,
     ) -> Result<Vec<Signature>>;
-    fn ca_generate_revocations(&self, output: PathBuf) -> Result<()>;
+    fn ca_generate_revocations(
+        &self,
+        output: PathBuf,
+        compat: ExportCompat,
+        lang: Language,
+    ) -> Result<()>;
     fn sign_detached(&self, data: &[u8]) -> Result<String>;
-    fn bridge_to_remote_ca(&self, remote_ca: Cert, scope_regexes: Vec<String>) -> Result<Cert>;
+    fn bridge_to_remote_ca(
+        &self,
+        remote_ca: Cert,
+        scope_regexes: Vec<String>,
+        profile: CertificationProfile,
+    ) -> Result<Cert>;
     fn bridge_revoke(&self, remote_ca: &Cert) -> Result<(Signature, Cert)>;
+    fn revoke_certification(&self, cert: &Cert, userid: &UserID, reason: &str)
+        -> Result<Signature>;
+    fn sign_direct_key(&self, sb: SignatureBuilder) -> Result<Signature>;
 }
 
 /// A CaSec that uses a CertificationBackend internally
@@ -92,7 +110,9 @@ impl<T: CertificationBackend> CaSec for CaSecCB<T> {
         &self,
         cert: &Cert,
         uids_certify: &[&UserID],
-        duration_days: Option<u64>,
+        validity: Option<CertificationValidity>,
+        profile: CertificationProfile,
+        notation: Option<&Notation>,
     ) -> Result<Vec<Signature>> {
         let ca_cert = self.get_ca_cert()?; // CA cert (must include CA User ID)
 
@@ -111,12 +131,19 @@ impl<T: CertificationBackend> CaSec for CaSecCB<T> {
 
             // If an expiration setting for the certifications has been
             // provided, apply it to the signatures
-            if let Some(days) = duration_days {
-                // The signature should be valid for the specified
-                // number of `days`
-                sb = sb.set_signature_validity_period(Duration::from_secs(
-                    pgp::SECONDS_IN_DAY * days,
-                ))?;
+            if let Some(validity) = validity {
+                // The signature should be valid for the specified number of
+                // days, or until a fixed point in time
+                // (creation times have a precision of seconds)
+                let now = SystemTime::UNIX_EPOCH
+                    + Duration::from_secs(
+                        SystemTime::now()
+                            .duration_since(SystemTime::UNIX_EPOCH)?
+                            .as_secs(),
+                    );
+                sb = sb
+                    .set_signature_creation_time(now)?
+                    .set_signature_validity_period(validity.period(now)?)?;
             }
 
             // Include 'Signer's UserID' packet
@@ -130,9 +157,19 @@ impl<T: CertificationBackend> CaSec for CaSecCB<T> {
                 ));
             }
 
+            // Mark the certification as issued by the CA (see NotationPolicy)
+            if let Some(n) = notation {
+                sb = sb.add_notation(
+                    &n.name,
+                    n.value.as_bytes(),
+                    NotationDataFlags::empty().set_human_readable(),
+                    false,
+                )?;
+            }
+
             self.cb
                 .certify(&mut |signer: &mut dyn sequoia_openpgp::crypto::Signer| {
-                    let sig = userid.bind(signer, cert, sb.clone())?;
+                    let sig = pgp::bind_userid(signer, cert, userid, sb.clone(), profile)?;
 
                     // collect in packets
                     packets.push(sig);
@@ -155,7 +192,12 @@ impl<T: CertificationBackend> CaSec for CaSecCB<T> {
     /// The output file is human readable, contains some informational
     /// explanation, followed by the CA certificate and the list of
     /// revocation certificates
-    fn ca_generate_revocations(&self, output: PathBuf) -> Result<()> {
+    fn ca_generate_revocations(
+        &self,
+        output: PathBuf,
+        compat: ExportCompat,
+        lang: Language,
+    ) -> Result<()> {
         let ca_pub = self.get_ca_cert()?;
 
         let mut file = std::fs::File::create(output)?;
@@ -163,39 +205,26 @@ impl<T: CertificationBackend> CaSec for CaSecCB<T> {
         // write informational header
         writeln!(
             &mut file,
-            "This file contains revocation certificates for the OpenPGP CA \n\
-            instance '{}'.",
-            self.ca_email()?
+            "{}",
+            tr(lang, Msg::RevocationsIntro, &[("email", &self.ca_email()?)])
         )?;
         writeln!(&mut file)?;
 
-        let msg = r#"These revocations can be used to invalidate the CA's key.
-This is useful e.g. if the (private) CA key gets compromised (i.e. available
-to a third party), or when the CA key becomes inaccessible to you.
-
-CAUTION: This file needs to be kept safe from third parties who could use
-the revocations to adversarially invalidate your CA certificate!
-Keep in mind that an attacker can use these revocations to
-perform a denial of service attack on your CA at the most inconvenient
-moment. When a revocation certificate has been published for your CA, you
-will need to start over with a fresh CA key.
-
-Please store this file appropriately, to avoid it becoming accessible to
-adversaries."#;
-
-        writeln!(&mut file, "{msg}\n\n")?;
-
         writeln!(
             &mut file,
-            "For reference, the certificate of your CA is\n\n{}\n",
-            pgp::cert_to_armored(ca_pub)?
+            "{}\n\n",
+            tr(lang, Msg::RevocationsExplanation, &[])
         )?;
 
         writeln!(
             &mut file,
-            "Revocation certificates (ordered by 'creation time') follow:\n"
+            "{}\n\n{}\n",
+            tr(lang, Msg::RevocationsCaCert, &[]),
+            pgp::cert_to_armored(ca_pub)?
         )?;
 
+        writeln!(&mut file, "{}\n", tr(lang, Msg::RevocationsFollow, &[]))?;
+
         let now = SystemTime::now();
         let thirty_days = Duration::new(30 * 24 * 60 * 60, 0);
 
@@ -217,12 +246,12 @@ adversaries."#;
 
                     let header = vec![(
                         "Comment".to_string(),
-                        format!("Hard revocation (certificate compromised) ({date})"),
+                        tr(lang, Msg::RevocationCommentHard, &[("date", &date)]),
                     )];
                     writeln!(
                         &mut file,
                         "{}\n",
-                        &pgp::revoc_to_armored(&hard, Some(header))?
+                        &pgp::revoc_to_armored_compat(&hard, Some(header), compat)?
                     )?;
 
                     Ok(())
@@ -240,12 +269,12 @@ adversaries."#;
 
                     let header = vec![(
                         "Comment".to_string(),
-                        format!("Soft revocation (certificate retired) ({date})"),
+                        tr(lang, Msg::RevocationCommentSoft, &[("date", &date)]),
                     )];
                     writeln!(
                         &mut file,
                         "{}\n",
-                        &pgp::revoc_to_armored(&soft, Some(header))?
+                        &pgp::revoc_to_armored_compat(&soft, Some(header), compat)?
                     )?;
 
                     Ok(())
@@ -279,7 +308,19 @@ adversaries."#;
     ///
     /// If `scope_regexes` is empty, no regex scoping is added to the trust
     /// signature.
-    fn bridge_to_remote_ca(&self, remote_ca: Cert, scope_regexes: Vec<String>) -> Result<Cert> {
+    fn bridge_to_remote_ca(
+        &self,
+        remote_ca: Cert,
+        scope_regexes: Vec<String>,
+        profile: CertificationProfile,
+    ) -> Result<Cert> {
+        if !scope_regexes.is_empty() && !profile.regex_trust_signatures() {
+            return Err(anyhow::anyhow!(
+                "Scoped trust signatures are not supported with the '{}' profile",
+                profile
+            ));
+        }
+
         // There should be exactly one User ID in the remote CA Cert
         let uids: Vec<_> = remote_ca.userids().collect();
 
@@ -299,7 +340,8 @@ adversaries."#;
             self.cb
                 .certify(&mut |signer: &mut dyn sequoia_openpgp::crypto::Signer| {
                     // Create one tsig for each signer
-                    let tsig = userid.bind(signer, &remote_ca, builder.clone())?;
+                    let tsig =
+                        pgp::bind_userid(signer, &remote_ca, userid, builder.clone(), profile)?;
                     packets.push(tsig.into());
 
                     Ok(())
@@ -355,4 +397,48 @@ adversaries."#;
             ))
         }
     }
+
+    /// CA retracts its certification(s) on a User ID of `cert`.
+    ///
+    /// The resulting certification revocation invalidates all certifications
+    /// of `userid` by this CA that were made before it.
+    fn revoke_certification(
+        &self,
+        cert: &Cert,
+        userid: &UserID,
+        reason: &str,
+    ) -> Result<Signature> {
+        let mut revocation = None;
+
+        self.cb
+            .certify(&mut |signer: &mut dyn sequoia_openpgp::crypto::Signer| {
+                let rev = cert::UserIDRevocationBuilder::new()
+                    .set_reason_for_revocation(ReasonForRevocation::Unspecified, reason.as_bytes())?
+                    .build(signer, cert, userid, None)?;
+
+                revocation = Some(rev);
+
+                Ok(())
+            })?;
+
+        revocation.ok_or_else(|| anyhow::anyhow!("Failed to generate certification revocation"))
+    }
+
+    /// Make a new direct key signature on the CA key, from `sb`
+    fn sign_direct_key(&self, sb: SignatureBuilder) -> Result<Signature> {
+        let mut dks = None;
+
+        self.cb
+            .certify(&mut |signer: &mut dyn sequoia_openpgp::crypto::Signer| {
+                let sig = sb
+                    .clone()
+                    .sign_direct_key(signer, Some(self.ca_cert.primary_key().key()))?;
+
+                dks = Some(sig);
+
+                Ok(())
+            })?;
+
+        dks.ok_or_else(|| anyhow::anyhow!("Failed to generate direct key signature"))
+    }
 }
@@ -0,0 +1,281 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Smoke test: an end-to-end check of a CA deployment (e.g. after an upgrade).
+//!
+//! The test doesn't touch the data of the CA. It works on a temporary
+//! clone of the CA, which has the CA's key (or card configuration) and
+//! policies, but no users or certs.
+
+use anyhow::{Context, Result};
+use sequoia_openpgp::types::RevocationStatus;
+
+use crate::backend::Backend;
+use crate::pgp::{self, KeyVersion};
+use crate::types::{
+    CertificationProfile, CertificationValidity, ExportCompat, SmoketestStatus, SmoketestStep,
+};
+use crate::{Oca, Uninit};
+
+/// Collects the outcomes of the steps of a smoke test
+#[derive(Default)]
+struct Steps(Vec<SmoketestStep>);
+
+impl Steps {
+    /// Run the step `name`, return if it passed
+    fn run(&mut self, name: &'static str, f: impl FnOnce() -> Result<()>) -> bool {
+        let status = match f() {
+            Ok(()) => SmoketestStatus::Passed,
+            Err(e) => SmoketestStatus::Failed(format!("{e:#}")),
+        };
+        let passed = status == SmoketestStatus::Passed;
+
+        self.0.push(SmoketestStep { name, status });
+
+        passed
+    }
+
+    fn skip(&mut self, name: &'static str, reason: &str) {
+        self.0.push(SmoketestStep {
+            name,
+            status: SmoketestStatus::Skipped(reason.to_string()),
+        });
+    }
+}
+
+const DATABASE: &str = "database";
+const BACKEND: &str = "backend";
+const TEMP_CA: &str = "temporary CA";
+const GENERATION: &str = "user key generation";
+const CERTIFICATION: &str = "certification";
+const WKD_EXPORT: &str = "WKD export";
+const REVOCATION: &str = "revocation";
+const SPLIT_QUEUE: &str = "split queue";
+
+/// Email address of the throwaway user
+fn smoketest_email(oca: &Oca) -> String {
+    format!("openpgp-ca-smoketest@{}", oca.domainname())
+}
+
+/// Clone the CA config of `oca` into a new in-memory database
+fn clone_ca(oca: &Oca) -> Result<Oca> {
+    let ca = oca.storage.ca()?;
+    let cacert = oca.storage.cacert()?;
+
+    let uninit = Uninit::new_in_memory()?;
+    uninit.storage.ca_insert(
+        &ca.domainname,
+        &cacert.priv_cert,
+        &cacert.fingerprint,
+        cacert.backend.as_deref(),
+    )?;
+
+    let clone = uninit.init_from_db_state()?;
+
+    // Only the policies are copied (other settings, like the events config,
+    // would have side effects)
+    clone.set_key_policy(&oca.key_policy()?)?;
+    clone.set_uri_policy(&oca.uri_policy()?)?;
+    clone.set_crypto_policy(&oca.crypto_policy()?)?;
+
+    Ok(clone)
+}
+
+/// Run the smoke test on `oca`
+pub(crate) fn smoketest(oca: &Oca) -> Result<Vec<SmoketestStep>> {
+    let mut steps = Steps::default();
+
+    let email = smoketest_email(oca);
+
+    // -- The CA instance itself (read-only)
+
+    if let Backend::SplitBack(_) = oca.backend() {
+        steps.skip(DATABASE, "split mode back instance");
+    } else {
+        steps.run(DATABASE, || {
+            oca.storage.ca()?;
+            oca.storage.certs()?;
+            Ok(())
+        });
+    }
+
+    if let Backend::SplitFront = oca.backend() {
+        steps.skip(BACKEND, "split mode front instance has no CA key");
+    } else {
+        // Certify a throwaway cert with the CA key (the certification is not stored)
+        steps.run(BACKEND, || {
+            let (cert, _, _) = pgp::make_user_cert(
+                &[&email],
+                &[],
+                None,
+                false,
+                None,
+                None,
+                KeyVersion::default(),
+                None,
+                true,
+                true,
+                false,
+            )?;
+            let ca_cert = oca.secret().cert()?;
+
+            let uid = cert
+                .userids()
+                .next()
+                .context("No User ID")?
+                .userid()
+                .clone();
+            let sigs = oca.secret().sign_user_ids(
+                &cert,
+                &[&uid],
+                Some(CertificationValidity::Days(1)),
+                CertificationProfile::Default,
+                None,
+            )?;
+
+            for sig in sigs {
+                sig.verify_userid_binding(
+                    ca_cert.primary_key().key(),
+                    cert.primary_key().key(),
+                    &uid,
+                )
+                .context("Certification by the CA key doesn't verify")?;
+            }
+            Ok(())
+        });
+    }
+
+    // -- A temporary clone of the CA
+
+    let names = [
+        GENERATION,
+        CERTIFICATION,
+        WKD_EXPORT,
+        REVOCATION,
+        SPLIT_QUEUE,
+    ];
+
+    if let Backend::SplitBack(_) = oca.backend() {
+        steps.skip(TEMP_CA, "split mode back instance");
+        for name in names {
+            steps.skip(name, "split mode back instance");
+        }
+        return Ok(steps.0);
+    }
+
+    let dir = tempfile::tempdir().context("Couldn't create temporary directory")?;
+
+    let mut clone = None;
+    if !steps.run(TEMP_CA, || {
+        clone = Some(clone_ca(oca)?);
+        Ok(())
+    }) {
+        for name in names {
+            steps.skip(name, "no temporary CA");
+        }
+        return Ok(steps.0);
+    }
+    let clone = clone.context("No temporary CA")?;
+
+    let split_front = matches!(clone.backend(), Backend::SplitFront);
+
+    let mut user = None;
+    if !steps.run(GENERATION, || {
+        let (cert, revoc, _) = pgp::make_user_cert(
+            &[&email],
+            &[],
+            None,
+            false,
+            None,
+            None,
+            KeyVersion::default(),
+            None,
+            true,
+            true,
+            false,
+        )?;
+        let revoc = pgp::revoc_to_armored(&revoc, None)?;
+
+        clone.cert_import_new(
+            pgp::cert_to_armored(&cert)?.as_bytes(),
+            &[revoc.as_bytes()],
+            None,
+            &[&email],
+            None,
+        )?;
+
+        user = clone.cert_get_by_fingerprint(&cert.fingerprint().to_hex())?;
+        Ok(())
+    }) {
+        for name in &names[1..] {
+            steps.skip(name, "no user");
+        }
+        return Ok(steps.0);
+    }
+    let user = user.context("User cert was not stored")?;
+
+    if split_front {
+        steps.skip(
+            CERTIFICATION,
+            "certifications are made by the back instance",
+        );
+    } else {
+        steps.run(CERTIFICATION, || {
+            if clone.cert_check_ca_sig(&user)?.certified.is_empty() {
+                return Err(anyhow::anyhow!("User ID was not certified"));
+            }
+            Ok(())
+        });
+    }
+
+    steps.run(WKD_EXPORT, || {
+        clone.export_wkd(
+            clone.domainname(),
+            &dir.path().join("wkd"),
+            false,
+            ExportCompat::default(),
+        )
+    });
+
+    steps.run(REVOCATION, || {
+        let revoc = clone
+            .revocations_get(&user)?
+            .pop()
+            .context("No revocation stored")?;
+        clone.revocation_apply(revoc)?;
+
+        let user = clone
+            .cert_get_by_fingerprint(&user.fingerprint)?
+            .context("User cert not found")?;
+        let cert = pgp::to_cert(user.pub_cert.as_bytes())?;
+
+        match cert.revocation_status(&clone.policy()?, None) {
+            RevocationStatus::Revoked(_) => Ok(()),
+            _ => Err(anyhow::anyhow!("Revocation was not applied")),
+        }
+    });
+
+    if split_front {
+        // Export the queue for the back instance
+        steps.run(SPLIT_QUEUE, || {
+            if clone.storage.queue_not_done()?.is_empty() {
+                return Err(anyhow::anyhow!("No certification request was queued"));
+            }
+
+            let file = dir.path().join("queue.tar");
+            clone.ca_split_export(file.clone())?;
+
+            if std::fs::metadata(file)?.len() == 0 {
+                return Err(anyhow::anyhow!("Queue export is empty"));
+            }
+            Ok(())
+        });
+    } else {
+        steps.skip(SPLIT_QUEUE, "not a split mode instance");
+    }
+
+    Ok(steps.0)
+}
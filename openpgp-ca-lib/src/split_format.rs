@@ -0,0 +1,26 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! The documents that split-mode front and back instances exchange: the
+//! certification requests of the front instance ([SplitOcaRequests]), and
+//! the response of the back instance ([SplitOcaResponse]).
+//!
+//! Integrators can use these types to move queue data over their own
+//! channels (e.g. ticket systems, object storage, or QR codes), see
+//! [crate::Oca::ca_split_export_to_writer],
+//! [crate::Oca::ca_split_certify_from_reader] and
+//! [crate::Oca::ca_split_import_from_reader].
+//!
+//! The JSON serialization of the documents is versioned
+//! ([SPLIT_OCA_REQUEST_VERSION], [SPLIT_OCA_RESPONSE_VERSION]). Within a
+//! version, the format only changes in backward compatible ways: new fields
+//! are optional, and omitted when they have their default value. The Rust
+//! API of this module only changes in semver-incompatible releases.
+
+pub use crate::backend::split::{
+    BridgeReq, BridgeResp, CertificationReq, CertificationResp, QueueEntry, QueueResponse,
+    SplitOcaRequests, SplitOcaResponse, SPLIT_OCA_REQUEST_VERSION, SPLIT_OCA_RESPONSE_VERSION,
+};
@@ -0,0 +1,35 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Anonymous usage statistics, which CA operators can choose to share with
+//! the OpenPGP CA project. Nothing is ever sent by the library itself.
+
+use anyhow::Result;
+
+use crate::types::{UsageStats, UsageStatsSubmission};
+use crate::Oca;
+
+/// Version of the schema of [UsageStats]
+const STATS_FORMAT: u32 = 1;
+
+pub(crate) fn usage_stats(oca: &Oca) -> Result<UsageStats> {
+    Ok(UsageStats {
+        format: STATS_FORMAT,
+        version: crate::VERSION.to_string(),
+        backend: oca.backend().kind(),
+        users: oca.storage.users_sorted_by_name()?.len(),
+        certs: oca.storage.certs()?.len(),
+        bridges: oca.storage.list_bridges()?.len(),
+    })
+}
+
+/// The usage statistics of `oca`, serialized and signed with the CA key
+pub(crate) fn usage_stats_submission(oca: &Oca) -> Result<UsageStatsSubmission> {
+    let payload = serde_json::to_string_pretty(&usage_stats(oca)?)?;
+    let signature = oca.secret().sign_detached(payload.as_bytes())?;
+
+    Ok(UsageStatsSubmission { payload, signature })
+}
@@ -1,15 +1,33 @@
 // SPDX-FileCopyrightText: 2019-2023 Heiko Schaefer <heiko@schaefer.name>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
 use diesel::result::Error;
 use sequoia_openpgp::{Cert, Packet};
 
-use crate::db::models::{NewQueue, Queue};
-use crate::db::{models, OcaDb};
-use crate::pgp;
+use crate::db::models::{
+    NewBlocklistEntry, NewCertQuarantine, NewCtLogEntry, NewKeyReplacement, NewQueue, Queue,
+};
+use crate::db::{models, OcaDb, QUEUE_PROPOSAL};
+use crate::types::{
+    CertDowngradeError, CertificationProfile, ExportChannel, KeyReplacementStatus, ProposalStatus,
+    PublicationChannel,
+};
+use crate::{ct_log, pgp};
+
+/// Set `notes`, or with `append`: add `notes` as a new line to `old`
+fn notes_edit(old: Option<String>, notes: Option<&str>, append: bool) -> Option<String> {
+    match (old, notes) {
+        (Some(old), Some(notes)) if append && !old.is_empty() => Some(format!("{old}\n{notes}")),
+        (old, None) if append => old,
+        (_, notes) => notes.map(ToString::to_string),
+    }
+}
 
 pub(crate) fn ca_get_cert_pub(db: &Rc<OcaDb>) -> Result<Cert> {
     Ok(ca_get_cert_private(db)?.strip_secret_key_material())
@@ -71,6 +89,11 @@ impl UninitDb {
         self.db.cacert_update(cacert)
     }
 
+    /// Make `priv_cert` the active CA cert, the previous CA cert is kept as inactive.
+    pub(crate) fn cacert_replace(&self, priv_cert: String, fingerprint: &str) -> Result<()> {
+        self.db.cacert_replace(priv_cert, fingerprint)
+    }
+
     /// Get the Cert of the CA (without private key material).
     pub(crate) fn ca_get_cert_pub(&self) -> Result<Cert> {
         ca_get_cert_pub(&self.db)
@@ -113,7 +136,7 @@ impl QueueDb {
     }
 
     pub(crate) fn queue_insert(&self, q: NewQueue) -> Result<()> {
-        self.db.queue_insert(q)
+        self.db.queue_insert(q).map(|_| ())
     }
 
     pub(crate) fn cert(&self) -> Result<Cert> {
@@ -127,18 +150,75 @@ pub(crate) trait CaStorage {
 
     fn ca_get_cert_pub(&self) -> Result<Cert>;
 
+    /// All certs, except for quarantined certs and certs whose armored
+    /// cert can't be parsed (this applies to all listings of certs)
     fn certs(&self) -> Result<Vec<models::Cert>>;
+
+    /// All cert rows, including quarantined and unparseable ones
+    fn cert_rows(&self) -> Result<Vec<models::Cert>>;
+    fn certs_quarantined(&self) -> Result<Vec<models::CertQuarantine>>;
+
     fn cert_by_id(&self, id: i32) -> Result<Option<models::Cert>>;
     fn cert_by_fp(&self, fingerprint: &str) -> Result<Option<models::Cert>>;
     fn certs_by_email(&self, email: &str) -> Result<Vec<models::Cert>>;
     fn certs_by_user(&self, user: &models::User) -> Result<Vec<models::Cert>>;
 
+    /// Certs where the fingerprint, an email address, the user name, or the
+    /// notes or tags (names or values) of the cert or its user contain `text`
+    fn certs_search(&self, text: &str) -> Result<Vec<models::Cert>>;
+
+    /// The parsed form of the armored `pub_cert` of `cert`
+    fn cert_parsed(&self, cert: &models::Cert) -> Result<Cert> {
+        pgp::to_cert(cert.pub_cert.as_bytes())
+    }
+
+    fn cert_versions(&self, cert: &models::Cert) -> Result<Vec<models::CertVersion>>;
+    fn cert_version_by_id(&self, id: i32) -> Result<Option<models::CertVersion>>;
+    fn cert_versions_keep(&self) -> Result<u32>;
+
+    fn pref(&self, name: &str) -> Result<Option<String>>;
+
+    fn verified_signatures(&self, cert: &models::Cert) -> Result<Vec<models::VerifiedSignature>>;
+
     fn emails(&self) -> Result<Vec<models::CertEmail>>;
     fn emails_by_cert(&self, cert: &models::Cert) -> Result<Vec<models::CertEmail>>;
     fn user_by_cert(&self, cert: &models::Cert) -> Result<Option<models::User>>;
+    fn user_by_id(&self, id: i32) -> Result<Option<models::User>>;
     fn users_sorted_by_name(&self) -> Result<Vec<models::User>>;
 
-    fn revocation_exists(&self, revocation: &[u8]) -> Result<bool>;
+    fn cert_reassignments(&self, cert: &models::Cert) -> Result<Vec<models::CertReassignment>>;
+
+    fn key_replacements(&self) -> Result<Vec<models::KeyReplacement>>;
+    fn key_replacement_by_id(&self, id: i32) -> Result<Option<models::KeyReplacement>>;
+
+    /// All blocklist entries, including removed ones
+    fn blocklist(&self) -> Result<Vec<models::BlocklistEntry>>;
+
+    /// All entries of the certification transparency log, in order
+    fn ct_log(&self) -> Result<Vec<models::CtLogEntry>>;
+
+    /// All tags, on users and on certs
+    fn tags(&self) -> Result<Vec<models::Tag>>;
+    fn tags_by_user(&self, user: &models::User) -> Result<Vec<models::Tag>>;
+    fn tags_by_cert(&self, cert: &models::Cert) -> Result<Vec<models::Tag>>;
+
+    /// All groups of users, ordered by name
+    fn groups(&self) -> Result<Vec<models::Group>>;
+    fn group_by_name(&self, name: &str) -> Result<Option<models::Group>>;
+
+    /// The users in `group`, ordered by name
+    fn group_users(&self, group: &models::Group) -> Result<Vec<models::User>>;
+
+    /// The groups that `user` is a member of, ordered by name
+    fn groups_by_user(&self, user: &models::User) -> Result<Vec<models::Group>>;
+
+    /// The state of the updates of all certs from the public source `source`
+    fn sync_states(&self, source: &str) -> Result<Vec<models::SyncState>>;
+
+    /// The packets of `cert` that were merged in from keyservers
+    fn keyserver_packets(&self, cert: &models::Cert) -> Result<Vec<models::KeyserverPacket>>;
+
+    fn revocations(&self) -> Result<Vec<models::Revocation>>;
     fn revocations_by_cert(&self, cert: &models::Cert) -> Result<Vec<models::Revocation>>;
     fn revocation_by_hash(&self, hash: &str) -> Result<Option<models::Revocation>>;
 
@@ -147,6 +227,12 @@ pub(crate) trait CaStorage {
 
     fn queue(&self, id: i32) -> Result<Option<models::Queue>>;
     fn queue_not_done(&self) -> Result<Vec<models::Queue>>;
+
+    /// All queue entries with proposed changes (see [crate::types::Proposal])
+    fn proposals(&self) -> Result<Vec<models::Queue>>;
+
+    fn queue_done_created(&self) -> Result<Vec<(i32, NaiveDateTime)>>;
+    fn cert_versions_created(&self) -> Result<Vec<(i32, i32, NaiveDateTime)>>;
 }
 
 pub(crate) trait CaStorageWrite {
@@ -156,6 +242,10 @@ pub(crate) trait CaStorageWrite {
 
     fn ca_import_tsig(&self, cert: &[u8]) -> Result<()>;
 
+    /// Merge public packets of `cert` (e.g. new self-signatures) into the
+    /// stored CA cert
+    fn ca_cert_merge(&self, cert: &Cert) -> Result<()>;
+
     fn cert_add(
         &self,
         pub_cert: &str,
@@ -163,11 +253,152 @@ pub(crate) trait CaStorageWrite {
         user_id: Option<i32>,
     ) -> Result<models::Cert>;
 
-    fn cert_update(&self, cert: &[u8]) -> Result<()>;
+    /// Merge `cert` into the stored version of the cert.
+    ///
+    /// Unless `allow_downgrade` is set, the update is rejected with a
+    /// [crate::types::CertDowngradeError] if it would roll back the state
+    /// of the stored cert (see [pgp::cert_downgrades]).
+    fn cert_update(&self, cert: &[u8], origin: &str, allow_downgrade: bool) -> Result<()>;
+
+    fn cert_version_restore(&self, version: &models::CertVersion) -> Result<()>;
+
+    /// Replace the armored cert of `cert` with `pub_cert`, without merging
+    /// (the previous armored cert is kept as a cert version)
+    fn cert_blob_replace(&self, cert: &models::Cert, pub_cert: &str, origin: &str) -> Result<()>;
+
+    fn cert_quarantine(&self, cert: &models::Cert, error: &str) -> Result<()>;
+    fn cert_quarantine_release(&self, cert_id: i32) -> Result<()>;
+
+    /// Record an attempt to update `cert` from the public source `source`,
+    /// which failed with `error` (or, with None, succeeded)
+    fn sync_state_record(
+        &self,
+        cert: &models::Cert,
+        source: &str,
+        error: Option<&str>,
+    ) -> Result<()>;
+
+    /// Record that the packets of `cert` were merged in from keyservers,
+    /// as triples of (keyserver, packet digest, packet kind)
+    fn keyserver_packets_add(
+        &self,
+        cert: &models::Cert,
+        packets: &[(String, String, String)],
+    ) -> Result<()>;
+
+    fn cert_versions_set_keep(&self, keep: u32) -> Result<()>;
+
+    fn pref_set(&self, name: &str, value: &str) -> Result<()>;
+
+    /// Cache verification results for signatures on `cert`, as pairs of
+    /// (signature digest, fingerprint of the verifying key)
+    fn verified_signatures_add(
+        &self,
+        cert: &models::Cert,
+        verified: &[(String, String)],
+    ) -> Result<()>;
 
     fn cert_delist(&self, fp: &str) -> Result<()>;
+
+    /// Set whether the cert `fp` is delisted on `channel`
+    fn cert_set_delisted(&self, fp: &str, channel: ExportChannel, delisted: bool) -> Result<()>;
+
     fn cert_deactivate(&self, fp: &str) -> Result<()>;
 
+    /// Permanently delete the cert `fp`, along with its emails, revocations
+    /// and all other rows that reference it
+    fn cert_purge(&self, fp: &str) -> Result<()>;
+
+    /// Set the notes of the cert `fp` (with `append`: add a line to them)
+    fn cert_notes_set(&self, fp: &str, notes: Option<&str>, append: bool) -> Result<()>;
+
+    /// Set the notes of the user of the cert `fp` (with `append`: add a
+    /// line to them)
+    fn user_notes_set(&self, fp: &str, notes: Option<&str>, append: bool) -> Result<()>;
+
+    /// Set if the certs of the user of the cert `fp` are published via WKD
+    fn user_wkd_publish_set(&self, fp: &str, publish: bool) -> Result<()>;
+
+    /// Set the tag `name` of the cert `fp` to `value` (with None: remove it)
+    fn cert_tag_set(&self, fp: &str, name: &str, value: Option<&str>) -> Result<()>;
+
+    /// Set several `tags` (name, value) of the cert `fp` at once, in one
+    /// transaction (see [Self::cert_tag_set])
+    fn cert_tags_set(&self, fp: &str, tags: &[(&str, Option<&str>)]) -> Result<()>;
+
+    /// Set the tag `name` of the user of the cert `fp` to `value` (with
+    /// None: remove it)
+    fn user_tag_set(&self, fp: &str, name: &str, value: Option<&str>) -> Result<()>;
+
+    /// Add a new (empty) group of users
+    fn group_new(&self, name: &str, description: Option<&str>) -> Result<models::Group>;
+
+    /// Delete the group `name` (its members are not affected)
+    fn group_delete(&self, name: &str) -> Result<()>;
+
+    /// Add the user of the cert `fp` to the group `name` (with `member`
+    /// false: remove it from the group). Returns false if the user already
+    /// was (or wasn't) a member.
+    fn group_member_set(&self, name: &str, fp: &str, member: bool) -> Result<bool>;
+
+    /// Move the cert `fp` to the user named `to` (or to a new user with
+    /// that name, with `new_user`), and record the move in the
+    /// `cert_reassignments` table. Returns the new user of the cert.
+    fn cert_reassign(
+        &self,
+        fp: &str,
+        to: &str,
+        new_user: bool,
+        reason: Option<&str>,
+    ) -> Result<models::User>;
+
+    /// Stage `new_cert` as a replacement for `cert`, pending approval
+    fn key_replacement_add(
+        &self,
+        cert: &models::Cert,
+        new_cert: &str,
+        new_fingerprint: &str,
+        handover: &str,
+    ) -> Result<models::KeyReplacement>;
+
+    /// Store `pub_cert` (the certified replacement cert of a pending key
+    /// replacement) for the user of the replaced cert, with `emails`.
+    /// With `deactivate`, the replaced cert is marked as inactive.
+    fn key_replacement_approve(
+        &self,
+        replacement: &models::KeyReplacement,
+        pub_cert: &str,
+        emails: &[&str],
+        deactivate: bool,
+    ) -> Result<models::Cert>;
+
+    fn key_replacement_reject(
+        &self,
+        replacement: &models::KeyReplacement,
+        reason: Option<&str>,
+    ) -> Result<()>;
+
+    /// Add an entry to the blocklist (fails if an entry for `pattern` of
+    /// `kind` is already active)
+    fn blocklist_add(
+        &self,
+        kind: &str,
+        pattern: &str,
+        reason: Option<&str>,
+    ) -> Result<models::BlocklistEntry>;
+
+    /// Mark the blocklist entry `id` as removed
+    fn blocklist_remove(&self, id: i32) -> Result<models::BlocklistEntry>;
+
+    /// Append an entry for `signature` (base64 encoded) to the
+    /// certification transparency log, chained to the current last entry
+    fn ct_log_append(
+        &self,
+        kind: &str,
+        fingerprint: &str,
+        signature: &str,
+    ) -> Result<models::CtLogEntry>;
+
     fn user_add(
         &self,
         name: Option<&str>,
@@ -177,18 +408,52 @@ pub(crate) trait CaStorageWrite {
         ca_cert_tsigned: Option<&[u8]>,
     ) -> Result<models::User>;
 
-    fn revocation_add(&self, revocation: &[u8]) -> Result<()>;
+    /// Store `revocation` (unpublished), returns the stored entry (or the
+    /// entry of an equivalent revocation that was already stored)
+    fn revocation_add(&self, revocation: &[u8]) -> Result<models::Revocation>;
     fn revocation_apply(&self, db_revoc: models::Revocation) -> Result<()>;
 
+    /// Remove an unpublished revocation
+    fn revocation_delete(&self, hash: &str) -> Result<()>;
+
+    /// Record an attempt to publish the cert that `revocation` was applied
+    /// to via `channel`, which failed with `error` (or, with None,
+    /// succeeded)
+    fn revocation_publication_record(
+        &self,
+        revocation: &models::Revocation,
+        channel: PublicationChannel,
+        error: Option<&str>,
+    ) -> Result<()>;
+
     fn bridge_add(
         &self,
         remote_armored: &str,
         remote_fp: &str,
         remote_email: &str,
         scope: &str,
+        scope_regexes: &[String],
+        profile: CertificationProfile,
     ) -> Result<models::Bridge>;
 
     fn queue_mark_done(&self, id: i32) -> Result<()>;
+
+    /// Queue the proposed change `task` (serialized), pending approval
+    fn proposal_add(&self, task: &str) -> Result<models::Queue>;
+
+    /// Record the decision on the pending proposal `id` (fails if it is not
+    /// pending)
+    fn proposal_decide(&self, id: i32, status: &str, reason: Option<&str>) -> Result<()>;
+
+    /// Run `f` in one transaction (nested transactions are run as
+    /// savepoints of the outer transaction)
+    fn transaction(&self, f: &mut dyn FnMut() -> Result<()>) -> Result<()>;
+
+    fn checkpoint(&self) -> Result<()>;
+    fn set_wal_autocheckpoint(&self, pages: u32) -> Result<()>;
+
+    fn queue_delete(&self, ids: &[i32]) -> Result<usize>;
+    fn cert_versions_delete(&self, ids: &[i32]) -> Result<usize>;
 }
 
 pub(crate) trait CaStorageRW: CaStorage + CaStorageWrite {}
@@ -196,13 +461,54 @@ pub(crate) trait CaStorageRW: CaStorage + CaStorageWrite {}
 /// DB storage for a regular CA instance
 pub(crate) struct DbCa {
     db: Rc<OcaDb>,
+
+    /// Parsed certs, by fingerprint (with the armored text they were parsed from)
+    cert_cache: RefCell<HashMap<String, (String, Cert)>>,
+
+    /// Ids of cert rows that have been skipped in listings (and reported)
+    /// because their armored cert couldn't be parsed
+    skipped: RefCell<HashSet<i32>>,
 }
 
 impl CaStorageRW for DbCa {}
 
 impl DbCa {
     pub(crate) fn new(db: Rc<OcaDb>) -> Self {
-        Self { db }
+        Self {
+            db,
+            cert_cache: RefCell::new(HashMap::new()),
+            skipped: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// `certs` without quarantined certs, and without certs whose armored
+    /// cert can't be parsed (those are reported on stderr, once per row)
+    fn certs_usable(&self, certs: Vec<models::Cert>) -> Result<Vec<models::Cert>> {
+        let quarantined: HashSet<i32> = self
+            .db
+            .cert_quarantine_all()?
+            .iter()
+            .map(|q| q.cert_id)
+            .collect();
+
+        Ok(certs
+            .into_iter()
+            .filter(|c| !quarantined.contains(&c.id))
+            .filter(|c| match self.cert_parsed(c) {
+                Ok(_) => true,
+                Err(e) => {
+                    if self.skipped.borrow_mut().insert(c.id) {
+                        tracing::warn!(
+                            cert_id = c.id,
+                            fingerprint = %c.fingerprint,
+                            "Skipped cert row, which can't be parsed: {:#}",
+                            e
+                        );
+                    }
+                    false
+                }
+            })
+            .collect())
     }
 
     pub(crate) fn transaction<T, E, F>(&self, f: F) -> Result<T, E>
@@ -231,9 +537,17 @@ impl CaStorage for DbCa {
     }
 
     fn certs(&self) -> Result<Vec<models::Cert>> {
+        self.certs_usable(self.db.certs()?)
+    }
+
+    fn cert_rows(&self) -> Result<Vec<models::Cert>> {
         self.db.certs()
     }
 
+    fn certs_quarantined(&self) -> Result<Vec<models::CertQuarantine>> {
+        self.db.cert_quarantine_all()
+    }
+
     fn cert_by_id(&self, id: i32) -> Result<Option<models::Cert>> {
         self.db.cert_by_id(id)
     }
@@ -243,11 +557,55 @@ impl CaStorage for DbCa {
     }
 
     fn certs_by_email(&self, email: &str) -> Result<Vec<models::Cert>> {
-        self.db.certs_by_email(email)
+        self.certs_usable(self.db.certs_by_email(email)?)
     }
 
     fn certs_by_user(&self, user: &models::User) -> Result<Vec<models::Cert>> {
-        self.db.certs_by_user(user)
+        self.certs_usable(self.db.certs_by_user(user)?)
+    }
+
+    fn certs_search(&self, text: &str) -> Result<Vec<models::Cert>> {
+        self.certs_usable(self.db.certs_search(text)?)
+    }
+
+    /// Parsed certs are cached for the lifetime of this object. A cached
+    /// entry is only used if it was parsed from the same armored text
+    /// (so updated rows are parsed again).
+    fn cert_parsed(&self, cert: &models::Cert) -> Result<Cert> {
+        if let Some((armored, parsed)) = self.cert_cache.borrow().get(&cert.fingerprint) {
+            if armored == &cert.pub_cert {
+                return Ok(parsed.clone());
+            }
+        }
+
+        let parsed = pgp::to_cert(cert.pub_cert.as_bytes())?;
+
+        self.cert_cache.borrow_mut().insert(
+            cert.fingerprint.clone(),
+            (cert.pub_cert.clone(), parsed.clone()),
+        );
+
+        Ok(parsed)
+    }
+
+    fn cert_versions(&self, cert: &models::Cert) -> Result<Vec<models::CertVersion>> {
+        self.db.cert_versions_by_cert(cert)
+    }
+
+    fn cert_version_by_id(&self, id: i32) -> Result<Option<models::CertVersion>> {
+        self.db.cert_version_by_id(id)
+    }
+
+    fn cert_versions_keep(&self) -> Result<u32> {
+        self.db.cert_versions_keep()
+    }
+
+    fn pref(&self, name: &str) -> Result<Option<String>> {
+        self.db.pref(name)
+    }
+
+    fn verified_signatures(&self, cert: &models::Cert) -> Result<Vec<models::VerifiedSignature>> {
+        self.db.verified_signatures_by_cert(cert)
     }
 
     fn emails(&self) -> Result<Vec<models::CertEmail>> {
@@ -262,12 +620,72 @@ impl CaStorage for DbCa {
         self.db.user_by_cert(cert)
     }
 
+    fn user_by_id(&self, id: i32) -> Result<Option<models::User>> {
+        self.db.user_by_id(id)
+    }
+
     fn users_sorted_by_name(&self) -> Result<Vec<models::User>> {
         self.db.users_sorted_by_name()
     }
 
-    fn revocation_exists(&self, revocation: &[u8]) -> Result<bool> {
-        self.db.revocation_exists(revocation)
+    fn cert_reassignments(&self, cert: &models::Cert) -> Result<Vec<models::CertReassignment>> {
+        self.db.cert_reassignments_by_cert(cert)
+    }
+
+    fn key_replacements(&self) -> Result<Vec<models::KeyReplacement>> {
+        self.db.key_replacements_all()
+    }
+
+    fn key_replacement_by_id(&self, id: i32) -> Result<Option<models::KeyReplacement>> {
+        self.db.key_replacement_by_id(id)
+    }
+
+    fn blocklist(&self) -> Result<Vec<models::BlocklistEntry>> {
+        self.db.blocklist_all()
+    }
+
+    fn ct_log(&self) -> Result<Vec<models::CtLogEntry>> {
+        self.db.ct_log_all()
+    }
+
+    fn tags(&self) -> Result<Vec<models::Tag>> {
+        self.db.tags()
+    }
+
+    fn tags_by_user(&self, user: &models::User) -> Result<Vec<models::Tag>> {
+        self.db.tags_by_user(user.id)
+    }
+
+    fn tags_by_cert(&self, cert: &models::Cert) -> Result<Vec<models::Tag>> {
+        self.db.tags_by_cert(cert.id)
+    }
+
+    fn groups(&self) -> Result<Vec<models::Group>> {
+        self.db.groups()
+    }
+
+    fn group_by_name(&self, name: &str) -> Result<Option<models::Group>> {
+        self.db.group_by_name(name)
+    }
+
+    fn group_users(&self, group: &models::Group) -> Result<Vec<models::User>> {
+        self.db.group_users(group.id)
+    }
+
+    fn groups_by_user(&self, user: &models::User) -> Result<Vec<models::Group>> {
+        self.db.groups_by_user(user.id)
+    }
+
+    fn sync_states(&self, source: &str) -> Result<Vec<models::SyncState>> {
+        self.db.sync_states(source)
+    }
+
+    fn keyserver_packets(&self, cert: &models::Cert) -> Result<Vec<models::KeyserverPacket>> {
+        self.db.keyserver_packets_by_cert(cert)
+    }
+
+    fn revocations(&self) -> Result<Vec<models::Revocation>> {
+        self.db.revocations()
     }
 
     fn revocations_by_cert(&self, cert: &models::Cert) -> Result<Vec<models::Revocation>> {
@@ -295,6 +713,18 @@ impl CaStorage for DbCa {
     fn queue_not_done(&self) -> Result<Vec<models::Queue>> {
         self.db.queue_not_done()
     }
+
+    fn queue_done_created(&self) -> Result<Vec<(i32, NaiveDateTime)>> {
+        self.db.queue_done_created()
+    }
+
+    fn proposals(&self) -> Result<Vec<models::Queue>> {
+        self.db.queue_by_kind(QUEUE_PROPOSAL)
+    }
+
+    fn cert_versions_created(&self) -> Result<Vec<(i32, i32, NaiveDateTime)>> {
+        self.db.cert_versions_created()
+    }
 }
 
 impl CaStorageWrite for DbCa {
@@ -310,6 +740,10 @@ impl CaStorageWrite for DbCa {
         self.transaction(|| self.db.ca_import_tsig(ca_cert_tsigned))
     }
 
+    fn ca_cert_merge(&self, cert: &Cert) -> Result<()> {
+        self.transaction(|| self.db.ca_cert_merge(cert))
+    }
+
     fn cert_add(
         &self,
         pub_cert: &str,
@@ -319,7 +753,7 @@ impl CaStorageWrite for DbCa {
         self.db.cert_add(pub_cert, fingerprint, user_id)
     }
 
-    fn cert_update(&self, cert: &[u8]) -> Result<()> {
+    fn cert_update(&self, cert: &[u8], origin: &str, allow_downgrade: bool) -> Result<()> {
         let cert_new = pgp::to_cert(cert).context("cert_update: couldn't process cert")?;
         let fp = cert_new.fingerprint().to_hex();
 
@@ -330,12 +764,24 @@ impl CaStorageWrite for DbCa {
                 .context("cert_import_update(): get_cert() check by fingerprint failed")?
             {
                 // merge existing and new public key
-                let cert_old = pgp::to_cert(db_cert.pub_cert.as_bytes())?;
+                let cert_old = self.cert_parsed(&db_cert)?;
+
+                let updated = cert_old.clone().merge_public(cert_new)?;
+
+                if !allow_downgrade {
+                    let downgrades = pgp::cert_downgrades(&cert_old, &updated);
+                    if !downgrades.is_empty() {
+                        return Err(CertDowngradeError {
+                            fingerprint: fp.clone(),
+                            downgrades,
+                        }
+                        .into());
+                    }
+                }
 
-                let updated = cert_old.merge_public(cert_new)?;
                 db_cert.pub_cert = pgp::cert_to_armored(&updated)?;
 
-                self.db.cert_update(&db_cert)
+                self.db.cert_update(&db_cert, origin)
             } else {
                 Err(anyhow::anyhow!(
                     "No cert with this fingerprint found in DB, cannot update"
@@ -344,6 +790,106 @@ impl CaStorageWrite for DbCa {
         })
     }
 
+    /// Replace the current version of a cert with the previous `version`.
+    ///
+    /// The current version is retained as a previous version in turn.
+    fn cert_version_restore(&self, version: &models::CertVersion) -> Result<()> {
+        self.transaction(|| {
+            if let Some(mut db_cert) = self.db.cert_by_id(version.cert_id)? {
+                db_cert.pub_cert = version.pub_cert.clone();
+                self.db.cert_update(&db_cert, "restore")
+            } else {
+                Err(anyhow::anyhow!(
+                    "Couldn't find cert for version {}",
+                    version.id
+                ))
+            }
+        })
+    }
+
+    fn cert_blob_replace(&self, cert: &models::Cert, pub_cert: &str, origin: &str) -> Result<()> {
+        let mut db_cert = cert.clone();
+        db_cert.pub_cert = pub_cert.to_string();
+
+        self.transaction(|| self.db.cert_update(&db_cert, origin))
+    }
+
+    fn cert_quarantine(&self, cert: &models::Cert, error: &str) -> Result<()> {
+        self.db.cert_quarantine_insert(NewCertQuarantine {
+            created: chrono::Utc::now().naive_utc(),
+            error,
+            cert_id: cert.id,
+        })
+    }
+
+    fn cert_quarantine_release(&self, cert_id: i32) -> Result<()> {
+        if self.db.cert_quarantine_delete(cert_id)? == 0 {
+            return Err(anyhow::anyhow!("Cert row {} is not quarantined", cert_id));
+        }
+
+        // Report the row again, if it still can't be parsed
+        self.skipped.borrow_mut().remove(&cert_id);
+
+        Ok(())
+    }
+
+    fn sync_state_record(
+        &self,
+        cert: &models::Cert,
+        source: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().naive_utc();
+        self.transaction(|| self.db.sync_state_record(cert.id, source, now, error))
+    }
+
+    fn keyserver_packets_add(
+        &self,
+        cert: &models::Cert,
+        packets: &[(String, String, String)],
+    ) -> Result<()> {
+        let created = chrono::Utc::now().naive_utc();
+
+        let new: Vec<_> = packets
+            .iter()
+            .map(|(keyserver, digest, packet)| models::NewKeyserverPacket {
+                keyserver,
+                digest,
+                packet,
+                created,
+                cert_id: cert.id,
+            })
+            .collect();
+
+        self.db.keyserver_packets_insert(&new)
+    }
+
+    fn cert_versions_set_keep(&self, keep: u32) -> Result<()> {
+        self.db
+            .pref_set(crate::db::PREF_CERT_VERSIONS_KEEP, &keep.to_string())
+    }
+
+    fn pref_set(&self, name: &str, value: &str) -> Result<()> {
+        self.db.pref_set(name, value)
+    }
+
+    fn verified_signatures_add(
+        &self,
+        cert: &models::Cert,
+        verified: &[(String, String)],
+    ) -> Result<()> {
+        let new: Vec<_> = verified
+            .iter()
+            .map(|(digest, verified_by)| models::NewVerifiedSignature {
+                digest,
+                verified_by,
+                cert_id: cert.id,
+            })
+            .collect();
+
+        self.db.verified_signatures_insert(&new)
+    }
+
     fn cert_delist(&self, fp: &str) -> Result<()> {
         let fp = pgp::normalize_fp(fp)?;
 
@@ -351,8 +897,25 @@ impl CaStorageWrite for DbCa {
             let cert = self.cert_by_fp(&fp)?;
 
             if let Some(mut cert) = cert {
-                cert.delisted = true;
-                self.db.cert_update(&cert)
+                for channel in ExportChannel::ALL {
+                    cert.set_delisted(channel, true);
+                }
+                self.db.cert_update(&cert, "delist")
+            } else {
+                Err(anyhow::anyhow!("Cert not found"))
+            }
+        })
+    }
+
+    fn cert_set_delisted(&self, fp: &str, channel: ExportChannel, delisted: bool) -> Result<()> {
+        let fp = pgp::normalize_fp(fp)?;
+
+        self.transaction(|| {
+            let cert = self.cert_by_fp(&fp)?;
+
+            if let Some(mut cert) = cert {
+                cert.set_delisted(channel, delisted);
+                self.db.cert_update(&cert, "delist")
             } else {
                 Err(anyhow::anyhow!("Cert not found"))
             }
@@ -367,13 +930,393 @@ impl CaStorageWrite for DbCa {
 
             if let Some(mut cert) = cert {
                 cert.inactive = true;
-                self.db.cert_update(&cert)
+                self.db.cert_update(&cert, "deactivate")
             } else {
                 Err(anyhow::anyhow!("Cert not found"))
             }
         })
     }
 
+    fn cert_purge(&self, fp: &str) -> Result<()> {
+        let fp = pgp::normalize_fp(fp)?;
+
+        self.transaction(|| {
+            let cert = self.cert_by_fp(&fp)?.context("Cert not found")?;
+            self.db.cert_purge(&cert)
+        })?;
+
+        // Remove traces of the deleted rows from the database file
+        self.db.vacuum()
+    }
+
+    fn cert_notes_set(&self, fp: &str, notes: Option<&str>, append: bool) -> Result<()> {
+        let fp = pgp::normalize_fp(fp)?;
+
+        self.transaction(|| {
+            let cert = self.cert_by_fp(&fp)?;
+
+            if let Some(mut cert) = cert {
+                cert.notes = notes_edit(cert.notes.take(), notes, append);
+                self.db.cert_update(&cert, "notes")
+            } else {
+                Err(anyhow::anyhow!("Cert not found"))
+            }
+        })
+    }
+
+    fn user_notes_set(&self, fp: &str, notes: Option<&str>, append: bool) -> Result<()> {
+        let fp = pgp::normalize_fp(fp)?;
+
+        self.transaction(|| {
+            let cert = self.cert_by_fp(&fp)?.context("Cert not found")?;
+
+            if let Some(mut user) = self.db.user_by_cert(&cert)? {
+                user.notes = notes_edit(user.notes.take(), notes, append);
+                self.db.user_update(&user)
+            } else {
+                Err(anyhow::anyhow!("Cert doesn't belong to a user"))
+            }
+        })
+    }
+
+    fn user_wkd_publish_set(&self, fp: &str, publish: bool) -> Result<()> {
+        let fp = pgp::normalize_fp(fp)?;
+
+        self.transaction(|| {
+            let cert = self.cert_by_fp(&fp)?.context("Cert not found")?;
+
+            if let Some(mut user) = self.db.user_by_cert(&cert)? {
+                user.wkd_publish = publish;
+                self.db.user_update(&user)
+            } else {
+                Err(anyhow::anyhow!("Cert doesn't belong to a user"))
+            }
+        })
+    }
+
+    fn cert_tag_set(&self, fp: &str, name: &str, value: Option<&str>) -> Result<()> {
+        let fp = pgp::normalize_fp(fp)?;
+
+        self.transaction(|| {
+            let cert = self.cert_by_fp(&fp)?.context("Cert not found")?;
+            self.db.tag_set(None, Some(cert.id), name, value)
+        })
+    }
+
+    fn cert_tags_set(&self, fp: &str, tags: &[(&str, Option<&str>)]) -> Result<()> {
+        let fp = pgp::normalize_fp(fp)?;
+
+        self.transaction(|| {
+            let cert = self.cert_by_fp(&fp)?.context("Cert not found")?;
+            for (name, value) in tags {
+                self.db.tag_set(None, Some(cert.id), name, *value)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn user_tag_set(&self, fp: &str, name: &str, value: Option<&str>) -> Result<()> {
+        let fp = pgp::normalize_fp(fp)?;
+
+        self.transaction(|| {
+            let cert = self.cert_by_fp(&fp)?.context("Cert not found")?;
+
+            if let Some(user) = self.db.user_by_cert(&cert)? {
+                self.db.tag_set(Some(user.id), None, name, value)
+            } else {
+                Err(anyhow::anyhow!("Cert doesn't belong to a user"))
+            }
+        })
+    }
+
+    fn group_new(&self, name: &str, description: Option<&str>) -> Result<models::Group> {
+        self.transaction(|| {
+            if self.db.group_by_name(name)?.is_some() {
+                return Err(anyhow::anyhow!("Group '{}' already exists", name));
+            }
+
+            self.db.group_insert(name, description)
+        })
+    }
+
+    fn group_delete(&self, name: &str) -> Result<()> {
+        self.transaction(|| {
+            let group = self
+                .db
+                .group_by_name(name)?
+                .ok_or_else(|| anyhow::anyhow!("Group '{}' not found", name))?;
+
+            self.db.group_delete(group.id)
+        })
+    }
+
+    fn group_member_set(&self, name: &str, fp: &str, member: bool) -> Result<bool> {
+        let fp = pgp::normalize_fp(fp)?;
+
+        self.transaction(|| {
+            let group = self
+                .db
+                .group_by_name(name)?
+                .ok_or_else(|| anyhow::anyhow!("Group '{}' not found", name))?;
+            let cert = self.cert_by_fp(&fp)?.context("Cert not found")?;
+
+            if let Some(user) = self.db.user_by_cert(&cert)? {
+                self.db.group_member_set(group.id, user.id, member)
+            } else {
+                Err(anyhow::anyhow!("Cert doesn't belong to a user"))
+            }
+        })
+    }
+
+    fn cert_reassign(
+        &self,
+        fp: &str,
+        to: &str,
+        new_user: bool,
+        reason: Option<&str>,
+    ) -> Result<models::User> {
+        let fp = pgp::normalize_fp(fp)?;
+
+        self.transaction(|| {
+            let mut cert = self.cert_by_fp(&fp)?.context("Cert not found")?;
+
+            let named: Vec<_> = self
+                .db
+                .users_sorted_by_name()?
+                .into_iter()
+                .filter(|u| u.name.as_deref() == Some(to))
+                .collect();
+
+            let user = match (named.len(), new_user) {
+                (0, true) => self.db.user_new(to)?,
+                (0, false) => {
+                    return Err(anyhow::anyhow!(
+                        "No user named '{to}' found (a new user can be created explicitly)"
+                    ))
+                }
+                (_, true) => return Err(anyhow::anyhow!("A user named '{to}' already exists")),
+                (1, false) => named[0].clone(),
+                (n, false) => {
+                    return Err(anyhow::anyhow!(
+                        "{n} users are named '{to}', the target user is ambiguous"
+                    ))
+                }
+            };
+
+            if cert.user_id == Some(user.id) {
+                return Err(anyhow::anyhow!("Cert already belongs to user '{to}'"));
+            }
+
+            self.db
+                .cert_reassignment_insert(models::NewCertReassignment {
+                    created: chrono::Utc::now().naive_utc(),
+                    from_user_id: cert.user_id,
+                    to_user_id: user.id,
+                    reason,
+                    cert_id: cert.id,
+                })?;
+
+            cert.user_id = Some(user.id);
+            self.db.cert_update(&cert, "reassign")?;
+
+            Ok(user)
+        })
+    }
+
+    fn key_replacement_add(
+        &self,
+        cert: &models::Cert,
+        new_cert: &str,
+        new_fingerprint: &str,
+        handover: &str,
+    ) -> Result<models::KeyReplacement> {
+        let pending = KeyReplacementStatus::Pending.name();
+
+        self.transaction(|| {
+            if self
+                .db
+                .key_replacements_all()?
+                .iter()
+                .any(|r| r.cert_id == cert.id && r.status == pending)
+            {
+                return Err(anyhow::anyhow!(
+                    "A replacement for cert {} is already pending",
+                    cert.fingerprint
+                ));
+            }
+
+            self.db.key_replacement_insert(NewKeyReplacement {
+                created: chrono::Utc::now().naive_utc(),
+                new_fingerprint,
+                new_cert,
+                handover,
+                status: pending,
+                cert_id: cert.id,
+            })
+        })
+    }
+
+    fn key_replacement_approve(
+        &self,
+        replacement: &models::KeyReplacement,
+        pub_cert: &str,
+        emails: &[&str],
+        deactivate: bool,
+    ) -> Result<models::Cert> {
+        self.transaction(|| {
+            let mut replacement = self
+                .db
+                .key_replacement_by_id(replacement.id)?
+                .context("Key replacement not found")?;
+            if replacement.status != KeyReplacementStatus::Pending.name() {
+                return Err(anyhow::anyhow!(
+                    "Key replacement {} is not pending",
+                    replacement.id
+                ));
+            }
+
+            if self.db.cert_by_fp(&replacement.new_fingerprint)?.is_some() {
+                return Err(anyhow::anyhow!(
+                    "A cert with this fingerprint already exists"
+                ));
+            }
+
+            let mut old = self
+                .db
+                .cert_by_id(replacement.cert_id)?
+                .context("Replaced cert not found")?;
+
+            let cert = self
+                .db
+                .cert_add(pub_cert, &replacement.new_fingerprint, old.user_id)?;
+            for addr in emails {
+                self.db.email_add(&cert, addr)?;
+            }
+
+            if deactivate {
+                old.inactive = true;
+                self.db.cert_update(&old, "replace")?;
+            }
+
+            replacement.status = KeyReplacementStatus::Approved.name().to_string();
+            replacement.decided = Some(chrono::Utc::now().naive_utc());
+            self.db.key_replacement_update(&replacement)?;
+
+            Ok(cert)
+        })
+    }
+
+    fn key_replacement_reject(
+        &self,
+        replacement: &models::KeyReplacement,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        self.transaction(|| {
+            let mut replacement = self
+                .db
+                .key_replacement_by_id(replacement.id)?
+                .context("Key replacement not found")?;
+            if replacement.status != KeyReplacementStatus::Pending.name() {
+                return Err(anyhow::anyhow!(
+                    "Key replacement {} is not pending",
+                    replacement.id
+                ));
+            }
+
+            replacement.status = KeyReplacementStatus::Rejected.name().to_string();
+            replacement.decided = Some(chrono::Utc::now().naive_utc());
+            replacement.reason = reason.map(ToString::to_string);
+
+            self.db.key_replacement_update(&replacement)
+        })
+    }
+
+    fn blocklist_add(
+        &self,
+        kind: &str,
+        pattern: &str,
+        reason: Option<&str>,
+    ) -> Result<models::BlocklistEntry> {
+        self.transaction(|| {
+            if self
+                .db
+                .blocklist_all()?
+                .iter()
+                .any(|e| e.removed.is_none() && e.kind == kind && e.pattern == pattern)
+            {
+                return Err(anyhow::anyhow!(
+                    "The {} '{}' is already on the blocklist",
+                    kind,
+                    pattern
+                ));
+            }
+
+            self.db.blocklist_insert(NewBlocklistEntry {
+                created: chrono::Utc::now().naive_utc(),
+                kind,
+                pattern,
+                reason,
+            })
+        })
+    }
+
+    fn blocklist_remove(&self, id: i32) -> Result<models::BlocklistEntry> {
+        self.transaction(|| {
+            let mut entry = self
+                .db
+                .blocklist_all()?
+                .into_iter()
+                .find(|e| e.id == id)
+                .ok_or_else(|| anyhow::anyhow!("No blocklist entry with id {} found", id))?;
+            if entry.removed.is_some() {
+                return Err(anyhow::anyhow!(
+                    "Blocklist entry {} has already been removed",
+                    id
+                ));
+            }
+
+            entry.removed = Some(chrono::Utc::now().naive_utc());
+            self.db.blocklist_update(&entry)?;
+
+            Ok(entry)
+        })
+    }
+
+    fn ct_log_append(
+        &self,
+        kind: &str,
+        fingerprint: &str,
+        signature: &str,
+    ) -> Result<models::CtLogEntry> {
+        self.transaction(|| {
+            let (seq, prev_hash) = match self.db.ct_log_last()? {
+                Some(last) => (last.id + 1, last.hash),
+                None => (1, ct_log::GENESIS_HASH.to_string()),
+            };
+
+            let created = ct_log::now();
+            let hash = ct_log::entry_hash(
+                seq as u64,
+                &created,
+                kind,
+                fingerprint,
+                signature,
+                &prev_hash,
+            );
+
+            self.db.ct_log_insert(NewCtLogEntry {
+                id: seq,
+                created: created.naive_utc(),
+                kind,
+                fingerprint,
+                signature,
+                prev_hash: &prev_hash,
+                hash: &hash,
+            })
+        })
+    }
+
     fn user_add(
         &self,
         name: Option<&str>,
@@ -403,11 +1346,12 @@ impl CaStorageWrite for DbCa {
     ///
     /// This implicitly searches for a cert that the revocation can be applied to.
     /// If no suitable cert is found, an error is returned.
-    fn revocation_add(&self, revocation: &[u8]) -> Result<()> {
+    fn revocation_add(&self, revocation: &[u8]) -> Result<models::Revocation> {
         self.transaction(|| {
-            // Check if this revocation already exists in db
-            if self.revocation_exists(revocation)? {
-                return Ok(()); // this revocation is already stored -> do nothing
+            // Check if this exact revocation (bitwise) already exists in db
+            let hash = pgp::revocation_to_hash(revocation)?;
+            if let Some(existing) = self.revocation_by_hash(&hash)? {
+                return Ok(existing); // this revocation is already stored -> do nothing
             }
 
             let mut revocation = pgp::to_signature(revocation)
@@ -428,23 +1372,21 @@ impl CaStorageWrite for DbCa {
             }
 
             if let Some(cert) = cert {
-                let c = pgp::to_cert(cert.pub_cert.as_bytes())?;
+                let c = self.cert_parsed(&cert)?;
 
                 // verify that revocation certificate validates with cert
                 if crate::revocation::validate_revocation(&c, &mut revocation)? {
                     let revocations = self.revocations_by_cert(&cert)?;
-                    if !crate::revocation::check_for_equivalent_revocation(
-                        revocations,
-                        &revocation,
-                    )? {
-                        // update sig in DB
-                        let armored = pgp::revoc_to_armored(&revocation, None)
-                            .context("couldn't armor revocation cert")?;
-
-                        let _ = self.db.revocation_add(&armored, &cert)?;
+                    match crate::revocation::equivalent_revocation(revocations, &revocation)? {
+                        Some(existing) => Ok(existing),
+                        None => {
+                            // update sig in DB
+                            let armored = pgp::revoc_to_armored(&revocation, None)
+                                .context("couldn't armor revocation cert")?;
+
+                            self.db.revocation_add(&armored, &cert)
+                        }
                     }
-
-                    Ok(())
                 } else {
                     Err(anyhow::anyhow!(format!(
                         "Revocation couldn't be matched to a cert:\n{revocation:?}"
@@ -462,7 +1404,7 @@ impl CaStorageWrite for DbCa {
         self.transaction(|| {
             if let Some(mut db_cert) = self.db.cert_by_id(db_revoc.cert_id)? {
                 let sig = pgp::to_signature(db_revoc.revocation.as_bytes())?;
-                let c = pgp::to_cert(db_cert.pub_cert.as_bytes())?;
+                let c = self.cert_parsed(&db_cert)?;
 
                 let revocation: Packet = sig.into();
                 let revoked = c.insert_packets(vec![revocation])?;
@@ -472,7 +1414,7 @@ impl CaStorageWrite for DbCa {
                 db_revoc.published = true;
 
                 self.db
-                    .cert_update(&db_cert)
+                    .cert_update(&db_cert, "revocation")
                     .context("Couldn't update Cert")?;
 
                 self.db
@@ -486,13 +1428,71 @@ impl CaStorageWrite for DbCa {
         })
     }
 
+    fn revocation_delete(&self, hash: &str) -> Result<()> {
+        self.transaction(|| {
+            let revocation = self
+                .db
+                .revocation_by_hash(hash)?
+                .ok_or_else(|| anyhow::anyhow!("No revocation found for {}", hash))?;
+
+            if revocation.published {
+                return Err(anyhow::anyhow!(
+                    "Revocation {} has already been applied, it can't be removed",
+                    hash
+                ));
+            }
+
+            self.db.revocation_delete(revocation.id)
+        })
+    }
+
+    fn revocation_publication_record(
+        &self,
+        revocation: &models::Revocation,
+        channel: PublicationChannel,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().naive_utc();
+
+        self.transaction(|| {
+            let mut revocation = self
+                .db
+                .revocation_by_hash(&revocation.hash)?
+                .ok_or_else(|| anyhow::anyhow!("No revocation found for {}", revocation.hash))?;
+
+            let (published, last_error) = match channel {
+                PublicationChannel::Keyserver => (
+                    &mut revocation.keyserver_published,
+                    &mut revocation.keyserver_error,
+                ),
+                PublicationChannel::Wkd => {
+                    (&mut revocation.wkd_published, &mut revocation.wkd_error)
+                }
+            };
+
+            match error {
+                None => {
+                    *published = Some(now);
+                    *last_error = None;
+                }
+                Some(error) => *last_error = Some(error.to_string()),
+            }
+
+            self.db.revocation_update(&revocation)
+        })
+    }
+
     fn bridge_add(
         &self,
         remote_armored: &str,
         remote_fp: &str,
         remote_email: &str,
         scope: &str,
+        scope_regexes: &[String],
+        profile: CertificationProfile,
     ) -> Result<models::Bridge> {
+        let scope_regexes = serde_json::to_string(scope_regexes)?;
+
         self.transaction(|| {
             // Cert of remote CA
             let db_cert = self.cert_add(remote_armored, remote_fp, None)?;
@@ -503,6 +1503,8 @@ impl CaStorageWrite for DbCa {
                 scope,
                 cert_id: db_cert.id,
                 cas_id: self.ca()?.id,
+                profile: profile.name(),
+                scope_regexes: Some(&scope_regexes),
             };
             self.db.bridge_insert(new_bridge)
         })
@@ -520,4 +1522,53 @@ impl CaStorageWrite for DbCa {
             }
         })
     }
+
+    fn proposal_add(&self, task: &str) -> Result<models::Queue> {
+        self.db.queue_insert(NewQueue {
+            created: chrono::Utc::now().naive_utc(),
+            task,
+            done: false,
+            kind: QUEUE_PROPOSAL,
+            status: Some(ProposalStatus::Pending.name()),
+        })
+    }
+
+    fn proposal_decide(&self, id: i32, status: &str, reason: Option<&str>) -> Result<()> {
+        self.transaction(|| {
+            let mut q = self
+                .db
+                .queue_by_id(id)?
+                .filter(|q| q.kind == QUEUE_PROPOSAL)
+                .ok_or_else(|| anyhow::anyhow!("No proposal with id {} found", id))?;
+            if q.done {
+                return Err(anyhow::anyhow!("Proposal {} is not pending", id));
+            }
+
+            q.done = true;
+            q.status = Some(status.to_string());
+            q.decided = Some(chrono::Utc::now().naive_utc());
+            q.reason = reason.map(ToString::to_string);
+            self.db.queue_update(&q)
+        })
+    }
+
+    fn transaction(&self, f: &mut dyn FnMut() -> Result<()>) -> Result<()> {
+        self.db.transaction(f)
+    }
+
+    fn checkpoint(&self) -> Result<()> {
+        self.db.checkpoint()
+    }
+
+    fn set_wal_autocheckpoint(&self, pages: u32) -> Result<()> {
+        self.db.set_wal_autocheckpoint(pages)
+    }
+
+    fn queue_delete(&self, ids: &[i32]) -> Result<usize> {
+        self.db.queue_delete(ids)
+    }
+
+    fn cert_versions_delete(&self, ids: &[i32]) -> Result<usize> {
+        self.db.cert_versions_delete(ids)
+    }
 }
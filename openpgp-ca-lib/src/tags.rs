@@ -0,0 +1,82 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Key-value tags on users and certs, e.g. to store the department or the
+//! employee ID that an external system (such as an HR system) uses for a
+//! user.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+
+use crate::db::models;
+use crate::Oca;
+
+/// Check that `name` is a legal tag name
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(anyhow::anyhow!(
+            "Tag names may only contain ASCII letters, digits, '-' and '_'"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check the arguments for setting (or with None: removing) a tag
+fn check(name: &str, value: Option<&str>) -> Result<()> {
+    check_name(name)?;
+    if value.is_some_and(|v| v.trim().is_empty()) {
+        return Err(anyhow::anyhow!("Tag values may not be empty"));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn cert_tag_set(oca: &Oca, fp: &str, name: &str, value: Option<&str>) -> Result<()> {
+    check(name, value)?;
+    oca.storage.cert_tag_set(fp, name, value)
+}
+
+pub(crate) fn user_tag_set(oca: &Oca, fp: &str, name: &str, value: Option<&str>) -> Result<()> {
+    check(name, value)?;
+    oca.storage.user_tag_set(fp, name, value)
+}
+
+/// `tags` as a map from name to value
+pub(crate) fn to_map(tags: Vec<models::Tag>) -> BTreeMap<String, String> {
+    tags.into_iter().map(|t| (t.name, t.value)).collect()
+}
+
+/// Certs that have the tag `name` (with the value `value`, if set), or whose
+/// user has it
+pub(crate) fn certs_by_tag(
+    oca: &Oca,
+    name: &str,
+    value: Option<&str>,
+) -> Result<Vec<models::Cert>> {
+    let mut cert_ids = BTreeSet::new();
+    let mut user_ids = BTreeSet::new();
+
+    for tag in oca.storage.tags()? {
+        if tag.name != name || value.is_some_and(|v| v != tag.value) {
+            continue;
+        }
+        cert_ids.extend(tag.cert_id);
+        user_ids.extend(tag.user_id);
+    }
+
+    Ok(oca
+        .storage
+        .certs()?
+        .into_iter()
+        .filter(|c| cert_ids.contains(&c.id) || c.user_id.is_some_and(|id| user_ids.contains(&id)))
+        .collect())
+}
@@ -0,0 +1,122 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! A forward-looking view of the upcoming expirations in a CA: of the CA
+//! cert, of user certs, of the CA's certifications on user certs, and of the
+//! CA's trust signatures on bridged CAs.
+
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use sequoia_openpgp::packet::Signature;
+use sequoia_openpgp::types::RevocationStatus;
+
+use crate::types::{TimelineEvent, TimelineEventKind};
+use crate::{pgp, Oca};
+
+/// The expiration time of the signature in `sigs` that is valid the longest
+/// (None, if there is no signature, or if one of them doesn't expire)
+fn latest_expiration(sigs: &[Signature]) -> Option<SystemTime> {
+    sigs.iter()
+        .map(|s| s.signature_expiration_time())
+        .try_fold(None, |latest: Option<SystemTime>, exp| {
+            exp.map(|exp| latest.max(Some(exp)))
+        })
+        .flatten()
+}
+
+pub(crate) fn expiry_timeline(oca: &Oca, horizon_days: u64) -> Result<Vec<TimelineEvent>> {
+    let now = SystemTime::now();
+    let horizon = now + Duration::from_secs(horizon_days * pgp::SECONDS_IN_DAY);
+
+    let upcoming = |time: Option<SystemTime>| time.filter(|t| *t > now && *t <= horizon);
+
+    let ca = oca.ca_get_cert_pub()?;
+    let policy = oca.policy()?;
+
+    let mut events = vec![];
+
+    if let Some(time) = upcoming(pgp::get_expiry(&ca, &policy)?) {
+        events.push(TimelineEvent {
+            time: time.into(),
+            kind: TimelineEventKind::CaCert,
+            fingerprint: ca.fingerprint().to_hex(),
+            label: None,
+            user_id: None,
+        });
+    }
+
+    for db_cert in oca.user_certs_get_all()? {
+        let c = oca.storage.cert_parsed(&db_cert)?;
+
+        // Certs that are already expired or revoked have nothing to expire
+        let valid = match c.with_policy(&policy, now) {
+            Ok(valid) => valid,
+            Err(_) => continue,
+        };
+        if valid.alive().is_err() {
+            continue;
+        }
+        if let RevocationStatus::Revoked(_) = valid.revocation_status() {
+            continue;
+        }
+
+        let user_name = oca.storage.user_by_cert(&db_cert)?.and_then(|u| u.name);
+
+        if let Some(time) = upcoming(valid.primary_key().key_expiration_time()) {
+            events.push(TimelineEvent {
+                time: time.into(),
+                kind: TimelineEventKind::UserCert,
+                fingerprint: db_cert.fingerprint.clone(),
+                label: user_name.clone(),
+                user_id: None,
+            });
+        }
+
+        for uid in valid.userids().revoked(false) {
+            let ca_certifications = pgp::valid_certifications_by(&uid, &c, ca.clone(), &policy);
+
+            if let Some(time) = upcoming(latest_expiration(&ca_certifications)) {
+                events.push(TimelineEvent {
+                    time: time.into(),
+                    kind: TimelineEventKind::Certification,
+                    fingerprint: db_cert.fingerprint.clone(),
+                    label: user_name.clone(),
+                    user_id: Some(String::from_utf8_lossy(uid.userid().value()).to_string()),
+                });
+            }
+        }
+    }
+
+    for bridge in oca.bridges_get()? {
+        let db_cert = match oca.storage.cert_by_id(bridge.cert_id)? {
+            Some(db_cert) => db_cert,
+            None => continue,
+        };
+        let c = oca.storage.cert_parsed(&db_cert)?;
+
+        for uid in c.userids() {
+            let tsigs: Vec<_> = pgp::valid_certifications_by(&uid, &c, ca.clone(), &policy)
+                .into_iter()
+                .filter(|s| s.trust_signature().is_some())
+                .collect();
+
+            if let Some(time) = upcoming(latest_expiration(&tsigs)) {
+                events.push(TimelineEvent {
+                    time: time.into(),
+                    kind: TimelineEventKind::BridgeTsig,
+                    fingerprint: db_cert.fingerprint.clone(),
+                    label: Some(bridge.email.clone()),
+                    user_id: Some(String::from_utf8_lossy(uid.userid().value()).to_string()),
+                });
+            }
+        }
+    }
+
+    events.sort_by_key(|e| e.time);
+
+    Ok(events)
+}
@@ -0,0 +1,418 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Routing of network operations (WKD and keyserver lookups, keyserver
+//! uploads) through Tor.
+//!
+//! A CA can be configured to use the SOCKS5 proxy of a Tor client (a system
+//! Tor daemon, or `arti proxy`). Host names are resolved by the proxy, so no
+//! DNS requests leave the host.
+//!
+//! Each request uses fresh, random SOCKS credentials. Tor isolates streams
+//! with different credentials onto separate circuits, so lookups for
+//! different certs can't be linked to each other by exit relays.
+//!
+//! If a proxy is configured, network operations never fall back to direct
+//! connections: they fail if the proxy is unreachable, or if OpenPGP CA was
+//! built without the "tor" feature.
+
+use std::net::SocketAddr;
+
+use anyhow::{Context, Result};
+use sequoia_openpgp::Cert;
+use tokio::runtime::Runtime;
+
+use crate::Oca;
+
+const PREF_TOR_PROXY: &str = "tor_socks_proxy";
+
+/// The SOCKS5 proxy of the Tor client that this CA uses for network
+/// operations (None, if network operations use direct connections)
+pub(crate) fn proxy(oca: &Oca) -> Result<Option<SocketAddr>> {
+    match oca.storage.pref(PREF_TOR_PROXY)? {
+        Some(addr) if !addr.is_empty() => {
+            let addr = addr
+                .parse()
+                .with_context(|| format!("Illegal Tor proxy address '{addr}' in CA settings"))?;
+            Ok(Some(addr))
+        }
+        _ => Ok(None),
+    }
+}
+
+pub(crate) fn set_proxy(oca: &Oca, proxy: Option<SocketAddr>) -> Result<()> {
+    if proxy.is_some() && !cfg!(feature = "tor") {
+        return Err(not_supported());
+    }
+
+    let value = proxy.map(|p| p.to_string()).unwrap_or_default();
+    oca.storage.pref_set(PREF_TOR_PROXY, &value)
+}
+
+fn not_supported() -> anyhow::Error {
+    anyhow::anyhow!(
+        "This CA is configured to route network operations through Tor, \
+         but OpenPGP CA was built without the 'tor' feature"
+    )
+}
+
+/// Check that network operations can be performed: if a Tor proxy is
+/// configured, it must be reachable.
+pub(crate) fn check_available(oca: &Oca) -> Result<()> {
+    match proxy(oca)? {
+        None => Ok(()),
+
+        #[cfg(feature = "tor")]
+        Some(proxy) => Runtime::new()?.block_on(socks::check(proxy)),
+
+        #[cfg(not(feature = "tor"))]
+        Some(_) => Err(not_supported()),
+    }
+}
+
+/// Look up the certs for `email` via WKD (through Tor, if configured)
+pub(crate) fn wkd_get(oca: &Oca, rt: &Runtime, email: &str) -> Result<Vec<Cert>> {
+    match proxy(oca)? {
+        None => Ok(rt.block_on(sequoia_net::wkd::get(email))?),
+
+        #[cfg(feature = "tor")]
+        Some(proxy) => rt.block_on(socks::wkd_get(proxy, email)),
+
+        #[cfg(not(feature = "tor"))]
+        Some(_) => Err(not_supported()),
+    }
+}
+
+/// Look up the cert `fp` on the keyserver `keyserver` (e.g.
+/// "hkps://keys.openpgp.org"), through Tor, if configured
+pub(crate) fn keyserver_get(
+    oca: &Oca,
+    rt: &Runtime,
+    keyserver: &str,
+    fp: &sequoia_openpgp::Fingerprint,
+) -> Result<Cert> {
+    match proxy(oca)? {
+        None => {
+            let mut ks = sequoia_net::KeyServer::new(sequoia_net::Policy::Encrypted, keyserver)?;
+            let kid = sequoia_openpgp::KeyID::from(fp);
+            Ok(rt.block_on(async move { ks.get(&kid).await })?)
+        }
+
+        #[cfg(feature = "tor")]
+        Some(proxy) => rt.block_on(socks::keyserver_get(proxy, keyserver, fp)),
+
+        #[cfg(not(feature = "tor"))]
+        Some(_) => Err(not_supported()),
+    }
+}
+
+/// Upload `cert` to the keyserver `keyserver` (e.g. "hkps://keys.openpgp.org"),
+/// through Tor, if configured
+pub(crate) fn keyserver_send(oca: &Oca, rt: &Runtime, keyserver: &str, cert: &Cert) -> Result<()> {
+    match proxy(oca)? {
+        None => {
+            let mut ks = sequoia_net::KeyServer::new(sequoia_net::Policy::Encrypted, keyserver)?;
+            Ok(rt.block_on(async move { ks.send(cert).await })?)
+        }
+
+        #[cfg(feature = "tor")]
+        Some(proxy) => rt.block_on(socks::keyserver_send(proxy, keyserver, cert)),
+
+        #[cfg(not(feature = "tor"))]
+        Some(_) => Err(not_supported()),
+    }
+}
+
+#[cfg(feature = "tor")]
+mod socks {
+    use std::convert::TryFrom;
+    use std::future::Future;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use anyhow::Result;
+    use hyper::header::CONTENT_TYPE;
+    use hyper::service::Service;
+    use hyper::{Body, Client, Request, StatusCode, Uri};
+    use hyper_tls::HttpsConnector;
+    use rand::distributions::Alphanumeric;
+    use rand::Rng;
+    use sequoia_net::wkd::{Url, Variant};
+    use sequoia_openpgp::cert::CertParser;
+    use sequoia_openpgp::parse::Parse;
+    use sequoia_openpgp::serialize::SerializeInto;
+    use sequoia_openpgp::{Cert, Fingerprint};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    fn unavailable(proxy: SocketAddr, e: impl std::fmt::Display) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("Tor SOCKS proxy at {proxy} is not available (is Tor running?): {e}"),
+        )
+    }
+
+    fn socks_error(msg: String) -> io::Error {
+        io::Error::other(msg)
+    }
+
+    /// Connect to `proxy`, and authenticate with username/password
+    /// (RFC 1929). Tor uses the credentials for circuit isolation only.
+    async fn authenticate(proxy: SocketAddr, user: &str, password: &str) -> io::Result<TcpStream> {
+        let mut s = TcpStream::connect(proxy)
+            .await
+            .map_err(|e| unavailable(proxy, e))?;
+
+        // Greeting: SOCKS5, offering "username/password" authentication
+        s.write_all(&[5, 1, 2]).await?;
+        let mut reply = [0u8; 2];
+        s.read_exact(&mut reply)
+            .await
+            .map_err(|e| unavailable(proxy, e))?;
+        if reply != [5, 2] {
+            return Err(unavailable(
+                proxy,
+                "the proxy doesn't accept SOCKS5 username/password authentication",
+            ));
+        }
+
+        let mut auth = vec![1, user.len() as u8];
+        auth.extend_from_slice(user.as_bytes());
+        auth.push(password.len() as u8);
+        auth.extend_from_slice(password.as_bytes());
+        s.write_all(&auth).await?;
+
+        s.read_exact(&mut reply).await?;
+        if reply[1] != 0 {
+            return Err(unavailable(proxy, "SOCKS5 authentication failed"));
+        }
+
+        Ok(s)
+    }
+
+    /// Check that a SOCKS5 proxy is listening at `proxy`
+    pub(super) async fn check(proxy: SocketAddr) -> Result<()> {
+        let (user, password) = isolation();
+        authenticate(proxy, &user, &password).await?;
+
+        Ok(())
+    }
+
+    /// Random SOCKS credentials, for a separate Tor circuit
+    fn isolation() -> (String, String) {
+        let random = || -> String {
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(16)
+                .map(char::from)
+                .collect()
+        };
+
+        (random(), random())
+    }
+
+    /// Connects to hosts through a SOCKS5 proxy, the host names are resolved
+    /// by the proxy
+    #[derive(Clone)]
+    struct SocksConnector {
+        proxy: SocketAddr,
+        user: String,
+        password: String,
+    }
+
+    impl SocksConnector {
+        fn new(proxy: SocketAddr) -> Self {
+            let (user, password) = isolation();
+            Self {
+                proxy,
+                user,
+                password,
+            }
+        }
+
+        async fn connect(self, uri: Uri) -> io::Result<TcpStream> {
+            let host = uri
+                .host()
+                .ok_or_else(|| socks_error(format!("No host in {uri}")))?;
+            let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+                Some("http") => 80,
+                _ => 443,
+            });
+
+            // SOCKS5 encodes the length of the domain name in one byte
+            let host_len = u8::try_from(host.len())
+                .map_err(|_| socks_error(format!("Host name too long for SOCKS5: {host}")))?;
+
+            let mut s = authenticate(self.proxy, &self.user, &self.password).await?;
+
+            // CONNECT to a domain name
+            let mut req = vec![5, 1, 0, 3, host_len];
+            req.extend_from_slice(host.as_bytes());
+            req.extend_from_slice(&port.to_be_bytes());
+            s.write_all(&req).await?;
+
+            let mut reply = [0u8; 4];
+            s.read_exact(&mut reply).await?;
+            if reply[1] != 0 {
+                return Err(socks_error(format!(
+                    "Tor failed to connect to {host}:{port} ({})",
+                    reply_text(reply[1])
+                )));
+            }
+
+            // Skip the bound address
+            let len = match reply[3] {
+                1 => 4,
+                4 => 16,
+                3 => s.read_u8().await? as usize,
+                _ => return Err(socks_error("Illegal SOCKS5 reply".to_string())),
+            };
+            let mut bound = vec![0u8; len + 2];
+            s.read_exact(&mut bound).await?;
+
+            Ok(s)
+        }
+    }
+
+    fn reply_text(code: u8) -> &'static str {
+        match code {
+            1 => "general failure",
+            2 => "connection not allowed",
+            3 => "network unreachable",
+            4 => "host unreachable",
+            5 => "connection refused",
+            6 => "TTL expired",
+            7 => "command not supported",
+            8 => "address type not supported",
+            _ => "unknown error",
+        }
+    }
+
+    impl Service<Uri> for SocksConnector {
+        type Response = TcpStream;
+        type Error = io::Error;
+        type Future = Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, uri: Uri) -> Self::Future {
+            Box::pin(self.clone().connect(uri))
+        }
+    }
+
+    /// GET `uri` on a separate Tor circuit.
+    ///
+    /// Returns None if the resource doesn't exist.
+    async fn get(proxy: SocketAddr, uri: Uri) -> Result<Option<Vec<u8>>> {
+        let https = HttpsConnector::new_with_connector(SocksConnector::new(proxy));
+        let client = Client::builder().build::<_, Body>(https);
+
+        let res = client.get(uri.clone()).await?;
+        match res.status() {
+            StatusCode::OK => Ok(Some(hyper::body::to_bytes(res.into_body()).await?.to_vec())),
+            StatusCode::NOT_FOUND => Ok(None),
+            status => Err(anyhow::anyhow!("GET {} failed: {}", uri, status)),
+        }
+    }
+
+    pub(super) async fn wkd_get(proxy: SocketAddr, email: &str) -> Result<Vec<Cert>> {
+        let url = Url::from(email)?;
+
+        // Try the advanced method first, then fall back to the direct method
+        let body = match get(proxy, url.to_uri(Variant::Advanced)?).await {
+            Ok(Some(body)) => Some(body),
+            _ => get(proxy, url.to_uri(Variant::Direct)?).await?,
+        };
+
+        let body = body.ok_or_else(|| anyhow::anyhow!("No WKD entry for {}", email))?;
+
+        // Only return certs with a User ID for `email`
+        let certs = CertParser::from_bytes(&body)?
+            .flatten()
+            .filter(|c| {
+                c.userids()
+                    .any(|u| matches!(u.email2(), Ok(Some(e)) if e == email))
+            })
+            .collect();
+
+        Ok(certs)
+    }
+
+    /// The host (and optional port) of the hkps:// keyserver `keyserver`
+    fn keyserver_host(keyserver: &str) -> Result<&str> {
+        let host = keyserver.strip_prefix("hkps://").unwrap_or(keyserver);
+        if host.contains("://") {
+            return Err(anyhow::anyhow!(
+                "Unsupported keyserver '{}' (only hkps:// keyservers are supported)",
+                keyserver
+            ));
+        }
+
+        Ok(host.trim_end_matches('/'))
+    }
+
+    /// Look up the cert `fp` via the HKP interface of `keyserver`, on a
+    /// separate Tor circuit
+    pub(super) async fn keyserver_get(
+        proxy: SocketAddr,
+        keyserver: &str,
+        fp: &Fingerprint,
+    ) -> Result<Cert> {
+        let uri: Uri = format!(
+            "https://{}/pks/lookup?op=get&options=mr&search=0x{}",
+            keyserver_host(keyserver)?,
+            fp.to_hex()
+        )
+        .parse()?;
+
+        let body = get(proxy, uri)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Cert {} not found on {}", fp, keyserver))?;
+
+        Cert::from_bytes(&body)
+    }
+
+    /// Encode `data` for a form field ("application/x-www-form-urlencoded")
+    fn form_encode(data: &[u8]) -> String {
+        data.iter()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'*' => {
+                    char::from(*b).to_string()
+                }
+                b' ' => "+".to_string(),
+                _ => format!("%{b:02X}"),
+            })
+            .collect()
+    }
+
+    /// Upload `cert` to the HKP interface of `keyserver`, on a separate Tor
+    /// circuit
+    pub(super) async fn keyserver_send(
+        proxy: SocketAddr,
+        keyserver: &str,
+        cert: &Cert,
+    ) -> Result<()> {
+        let uri: Uri = format!("https://{}/pks/add", keyserver_host(keyserver)?).parse()?;
+
+        let body = format!("keytext={}", form_encode(&cert.armored().to_vec()?));
+        let req = Request::post(uri.clone())
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body))?;
+
+        let https = HttpsConnector::new_with_connector(SocksConnector::new(proxy));
+        let client = Client::builder().build::<_, Body>(https);
+
+        let res = client.request(req).await?;
+        match res.status() {
+            StatusCode::OK => Ok(()),
+            status => Err(anyhow::anyhow!("POST {} failed: {}", uri, status)),
+        }
+    }
+}
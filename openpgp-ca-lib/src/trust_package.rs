@@ -0,0 +1,416 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Org trust packages: the trust configuration of a CA (CA cert, bridges,
+//! keylists and policies) in one versioned, CA-signed tar archive.
+
+use std::path::{Component, Path};
+
+use anyhow::{Context, Result};
+use chrono::{SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::export::{self, KEYLIST_FILE};
+use crate::pgp;
+use crate::types::{CryptoPolicy, KeyPolicy, TrustPackageFile, TrustPackageManifest, UriPolicy};
+use crate::Oca;
+
+/// Version of the schema of [TrustPackageManifest]
+const MANIFEST_FORMAT: u32 = 1;
+
+const PREF_TRUST_PACKAGE_VERSION: &str = "trust_package_version";
+
+const MANIFEST_FILE: &str = "manifest.json";
+const MANIFEST_SIG_FILE: &str = "manifest.json.asc";
+
+/// Name of the file that points to the newest package in an export directory
+pub(crate) const LATEST_FILE: &str = "latest.json";
+
+/// Paths and contents of the files in a trust package
+type Files = Vec<(String, Vec<u8>)>;
+
+/// Bridge to a remote CA, as listed in a trust package
+#[derive(Serialize, Deserialize)]
+struct PackageBridge {
+    email: String,
+    scope: String,
+
+    /// Regular expressions of the trust signature (empty: unscoped)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope_regexes: Option<Vec<String>>,
+    fingerprint: String,
+    profile: String,
+}
+
+/// Policies of the CA, as listed in a trust package
+#[derive(Serialize, Deserialize)]
+struct PackagePolicies {
+    key_policy: KeyPolicy,
+    crypto_policy: CryptoPolicy,
+    uri_policy: UriPolicy,
+}
+
+/// Pointer to the newest package in an export directory
+#[derive(Serialize, Deserialize)]
+struct Latest {
+    version: u64,
+    file: String,
+    sha256: String,
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|d| format!("{d:02x}"))
+        .collect()
+}
+
+/// The version of the most recently generated trust package (0, if none
+/// has been generated)
+pub(crate) fn version(oca: &Oca) -> Result<u64> {
+    match oca.storage.pref(PREF_TRUST_PACKAGE_VERSION)? {
+        Some(v) => Ok(v.parse()?),
+        None => Ok(0),
+    }
+}
+
+/// Generate a trust package with the next version number.
+///
+/// Returns the manifest and the tar archive.
+pub(crate) fn generate(oca: &Oca) -> Result<(TrustPackageManifest, Vec<u8>)> {
+    let mut files: Files = vec![];
+
+    files.push((
+        "ca.asc".to_string(),
+        oca.ca_get_pubkey_armored()?.into_bytes(),
+    ));
+
+    let mut bridges = vec![];
+    for bridge in oca.bridges_get()? {
+        let cert = oca.bridge_get_cert(&bridge)?;
+
+        files.push((
+            format!("bridges/{}.asc", cert.fingerprint),
+            cert.pub_cert.into_bytes(),
+        ));
+        bridges.push(PackageBridge {
+            scope_regexes: oca.bridge_scope_regexes(&bridge)?,
+            email: bridge.email,
+            scope: bridge.scope,
+            fingerprint: cert.fingerprint,
+            profile: bridge.profile,
+        });
+    }
+    files.push((
+        "bridges.json".to_string(),
+        serde_json::to_vec_pretty(&bridges)?,
+    ));
+
+    for list in oca.keylists()? {
+        let (keylist, sigfile, sig) =
+            export::signed_keylist(oca, &list.signature_uri, &list.filter)?;
+
+        files.push((
+            format!("keylists/{}/{}", list.name, KEYLIST_FILE),
+            keylist.into_bytes(),
+        ));
+        files.push((
+            format!("keylists/{}/{}", list.name, sigfile),
+            sig.into_bytes(),
+        ));
+    }
+
+    let policies = PackagePolicies {
+        key_policy: oca.key_policy()?,
+        crypto_policy: oca.crypto_policy()?,
+        uri_policy: oca.uri_policy()?,
+    };
+    files.push((
+        "policy.json".to_string(),
+        serde_json::to_vec_pretty(&policies)?,
+    ));
+
+    for (path, _) in &files {
+        check_path(path)?;
+    }
+
+    let version = version(oca)? + 1;
+
+    let manifest = TrustPackageManifest {
+        format: MANIFEST_FORMAT,
+        version,
+        created: Utc::now().trunc_subsecs(0),
+        domain: oca.domainname().to_string(),
+        ca_fingerprint: oca.ca_get_cert_pub()?.fingerprint().to_hex(),
+        files: files
+            .iter()
+            .map(|(path, data)| TrustPackageFile {
+                path: path.clone(),
+                sha256: sha256_hex(data),
+            })
+            .collect(),
+    };
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    let signature = oca.secret().sign_detached(&manifest_json)?;
+
+    let mtime = manifest.created.timestamp() as u64;
+
+    let mut tar = vec![];
+    tar_append(&mut tar, MANIFEST_FILE, &manifest_json, mtime)?;
+    tar_append(&mut tar, MANIFEST_SIG_FILE, signature.as_bytes(), mtime)?;
+    for (path, data) in &files {
+        tar_append(&mut tar, path, data, mtime)?;
+    }
+    tar_finish(&mut tar);
+
+    oca.storage
+        .pref_set(PREF_TRUST_PACKAGE_VERSION, &version.to_string())?;
+
+    Ok((manifest, tar))
+}
+
+/// Generate a trust package, and write it to `dir`, along with a pointer
+/// file ("latest.json") to the new package
+pub(crate) fn export(oca: &Oca, dir: &Path) -> Result<TrustPackageManifest> {
+    let (manifest, tar) = generate(oca)?;
+
+    let file = format!("trust-package-{}.tar", manifest.version);
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join(&file), &tar)?;
+
+    let latest = Latest {
+        version: manifest.version,
+        file,
+        sha256: sha256_hex(&tar),
+    };
+    std::fs::write(dir.join(LATEST_FILE), serde_json::to_vec_pretty(&latest)?)?;
+
+    Ok(manifest)
+}
+
+/// Check the trust package `data`: it must be signed by the CA key with
+/// `fingerprint`, contain exactly the files that its manifest lists (with
+/// matching digests), and have a version newer than `newer_than`.
+///
+/// Returns the manifest and the files of the package.
+pub(crate) fn verify(
+    data: &[u8],
+    fingerprint: &str,
+    newer_than: Option<u64>,
+) -> Result<(TrustPackageManifest, Files)> {
+    let mut entries = tar_entries(data).context("Couldn't read trust package")?;
+
+    let mut take = |name: &str| -> Result<Vec<u8>> {
+        let pos = entries
+            .iter()
+            .position(|(path, _)| path == name)
+            .ok_or_else(|| anyhow::anyhow!("Trust package doesn't contain '{}'", name))?;
+
+        Ok(entries.remove(pos).1)
+    };
+
+    let manifest_json = take(MANIFEST_FILE)?;
+    let signature = String::from_utf8(take(MANIFEST_SIG_FILE)?)?;
+    let ca_cert = entries
+        .iter()
+        .find(|(path, _)| path == "ca.asc")
+        .map(|(_, data)| pgp::to_cert(data))
+        .ok_or_else(|| anyhow::anyhow!("Trust package doesn't contain 'ca.asc'"))??;
+
+    if pgp::normalize_fp(fingerprint)? != ca_cert.fingerprint().to_hex() {
+        return Err(anyhow::anyhow!(
+            "The CA key in the trust package has the fingerprint {}, expected {}",
+            ca_cert.fingerprint(),
+            fingerprint
+        ));
+    }
+
+    pgp::verify_detached(&ca_cert, &manifest_json, &signature)
+        .context("The trust package manifest is not signed by the CA key")?;
+
+    let manifest: TrustPackageManifest =
+        serde_json::from_slice(&manifest_json).context("Couldn't parse trust package manifest")?;
+
+    if manifest.format != MANIFEST_FORMAT {
+        return Err(anyhow::anyhow!(
+            "Unsupported trust package format {}",
+            manifest.format
+        ));
+    }
+    if manifest.ca_fingerprint != ca_cert.fingerprint().to_hex() {
+        return Err(anyhow::anyhow!(
+            "The trust package manifest belongs to the CA key {}",
+            manifest.ca_fingerprint
+        ));
+    }
+    if let Some(v) = newer_than {
+        if manifest.version <= v {
+            return Err(anyhow::anyhow!(
+                "The trust package has version {}, expected a version newer than {}",
+                manifest.version,
+                v
+            ));
+        }
+    }
+
+    let mut files = vec![];
+    for f in &manifest.files {
+        let pos = entries
+            .iter()
+            .position(|(path, _)| path == &f.path)
+            .ok_or_else(|| anyhow::anyhow!("Trust package doesn't contain '{}'", f.path))?;
+        let (path, data) = entries.remove(pos);
+
+        if sha256_hex(&data) != f.sha256 {
+            return Err(anyhow::anyhow!("Digest mismatch for '{}'", path));
+        }
+
+        files.push((path, data));
+    }
+
+    if let Some((path, _)) = entries.first() {
+        return Err(anyhow::anyhow!(
+            "Trust package contains '{}', which is not listed in its manifest",
+            path
+        ));
+    }
+
+    Ok((manifest, files))
+}
+
+/// Check the trust package `data` (see [verify]), and write its files into
+/// `dir`
+pub(crate) fn unpack(
+    data: &[u8],
+    fingerprint: &str,
+    newer_than: Option<u64>,
+    dir: &Path,
+) -> Result<TrustPackageManifest> {
+    let (manifest, files) = verify(data, fingerprint, newer_than)?;
+
+    for (path, data) in files {
+        let target = dir.join(&path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(target, data)?;
+    }
+
+    Ok(manifest)
+}
+
+/// Only plain relative paths are allowed in trust packages (and other
+/// archives)
+fn check_path(path: &str) -> Result<()> {
+    let ok = !path.is_empty()
+        && path.len() < 100
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+
+    if ok {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Unsupported path '{}' in archive", path))
+    }
+}
+
+// --------- minimal ustar archives (regular files only)
+
+const BLOCK: usize = 512;
+
+/// Write `value` as a NUL-terminated octal number into `field`
+fn octal(field: &mut [u8], value: u64) {
+    let s = format!("{:0width$o}\0", value, width = field.len() - 1);
+    field.copy_from_slice(s.as_bytes());
+}
+
+pub(crate) fn tar_append(tar: &mut Vec<u8>, path: &str, data: &[u8], mtime: u64) -> Result<()> {
+    check_path(path)?;
+
+    let mut header = [0u8; BLOCK];
+    header[..path.len()].copy_from_slice(path.as_bytes());
+    octal(&mut header[100..108], 0o644); // mode
+    octal(&mut header[108..116], 0); // uid
+    octal(&mut header[116..124], 0); // gid
+    octal(&mut header[124..136], data.len() as u64);
+    octal(&mut header[136..148], mtime);
+    header[156] = b'0'; // regular file
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // the checksum is calculated with the checksum field set to spaces
+    header[148..156].copy_from_slice(b"        ");
+    let sum: u64 = header.iter().map(|&b| b as u64).sum();
+    octal(&mut header[148..155], sum);
+    header[155] = b' ';
+
+    tar.extend_from_slice(&header);
+    tar.extend_from_slice(data);
+    tar.resize(tar.len() + (BLOCK - data.len() % BLOCK) % BLOCK, 0);
+
+    Ok(())
+}
+
+pub(crate) fn tar_finish(tar: &mut Vec<u8>) {
+    tar.resize(tar.len() + 2 * BLOCK, 0);
+}
+
+fn parse_octal(field: &[u8]) -> Result<u64> {
+    let s = std::str::from_utf8(field)?.trim_matches(|c: char| c == '\0' || c == ' ');
+
+    if s.is_empty() {
+        Ok(0)
+    } else {
+        Ok(u64::from_str_radix(s, 8)?)
+    }
+}
+
+fn tar_entries(tar: &[u8]) -> Result<Files> {
+    let mut entries = vec![];
+    let mut pos = 0;
+
+    while pos + BLOCK <= tar.len() {
+        let header = &tar[pos..pos + BLOCK];
+        if header.iter().all(|&b| b == 0) {
+            // end of archive
+            return Ok(entries);
+        }
+
+        let stored = parse_octal(&header[148..156])?;
+        let sum: u64 = header[..148]
+            .iter()
+            .chain([b' '; 8].iter())
+            .chain(header[156..].iter())
+            .map(|&b| b as u64)
+            .sum();
+        if sum != stored {
+            return Err(anyhow::anyhow!("Bad checksum in tar header"));
+        }
+
+        if header[156] != b'0' && header[156] != 0 {
+            return Err(anyhow::anyhow!("Unsupported entry type in tar archive"));
+        }
+
+        let name_len = header[..100].iter().position(|&b| b == 0).unwrap_or(100);
+        let name = std::str::from_utf8(&header[..name_len])?.to_string();
+        check_path(&name)?;
+
+        let size = parse_octal(&header[124..136])? as usize;
+
+        pos += BLOCK;
+        if pos + size > tar.len() {
+            return Err(anyhow::anyhow!("Truncated tar archive"));
+        }
+        entries.push((name, tar[pos..pos + size].to_vec()));
+
+        pos += size + (BLOCK - size % BLOCK) % BLOCK;
+    }
+
+    Err(anyhow::anyhow!("Truncated tar archive"))
+}
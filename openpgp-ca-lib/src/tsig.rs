@@ -0,0 +1,208 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Trust signatures by user keys on the CA cert.
+//!
+//! When a user rotates to a new key, the tsig they made with their old key
+//! remains on the CA cert. This module identifies such stale tsigs, and the
+//! users who still need to tsig the CA cert with their new key.
+//!
+//! Certs are associated with each other via their email addresses: a cert
+//! is superseded by a newer cert for the same email address.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use sequoia_openpgp::packet::Signature;
+use sequoia_openpgp::{Cert, Packet};
+
+use crate::db::models;
+use crate::pgp;
+use crate::types::{CaTsig, TsigStatus};
+use crate::Oca;
+
+/// A user cert of the CA, with its email addresses
+struct UserCert {
+    db: models::Cert,
+    cert: Cert,
+    emails: HashSet<String>,
+}
+
+fn user_certs(oca: &Oca) -> Result<Vec<UserCert>> {
+    let mut certs = vec![];
+
+    for db in oca.storage.certs()? {
+        if db.user_id.is_some() {
+            let cert = oca.storage.cert_parsed(&db)?;
+            let emails = oca
+                .storage
+                .emails_by_cert(&db)?
+                .into_iter()
+                .map(|e| e.addr)
+                .collect();
+
+            certs.push(UserCert { db, cert, emails });
+        }
+    }
+
+    Ok(certs)
+}
+
+/// Status of each user cert, keyed by cert id.
+///
+/// A cert is superseded if there is a newer, unrevoked cert for one of its
+/// email addresses.
+fn cert_status(certs: &[UserCert]) -> HashMap<i32, TsigStatus> {
+    certs
+        .iter()
+        .map(|uc| {
+            let status = if pgp::is_possibly_revoked(&uc.cert) {
+                TsigStatus::Revoked
+            } else if certs.iter().any(|other| {
+                other.db.id != uc.db.id
+                    && !other.emails.is_disjoint(&uc.emails)
+                    && !pgp::is_possibly_revoked(&other.cert)
+                    && other.cert.primary_key().creation_time()
+                        > uc.cert.primary_key().creation_time()
+            }) {
+                TsigStatus::Superseded
+            } else {
+                TsigStatus::Current
+            };
+
+            (uc.db.id, status)
+        })
+        .collect()
+}
+
+/// All tsigs on the CA cert, along with their status
+fn tsigs(oca: &Oca, certs: &[UserCert]) -> Result<Vec<(Signature, CaTsig)>> {
+    let ca = oca.ca_get_cert_pub()?;
+
+    let status = cert_status(certs);
+
+    let mut res = vec![];
+
+    for uid in ca.userids() {
+        for sig in uid.certifications() {
+            if sig.trust_signature().is_none() {
+                continue;
+            }
+
+            let issuers = sig.get_issuers();
+
+            // the user cert with a key that made `sig`
+            let signer = certs.iter().find(|uc| {
+                uc.cert.keys().any(|ka| {
+                    issuers.iter().any(|h| h.aliases(ka.key_handle()))
+                        && sig
+                            .clone()
+                            .verify_userid_binding(ka.key(), ca.primary_key().key(), uid.userid())
+                            .is_ok()
+                })
+            });
+
+            let tsig = match signer {
+                Some(uc) => CaTsig {
+                    issuer: uc.db.fingerprint.clone(),
+                    created: sig.signature_creation_time(),
+                    cert: Some(uc.db.clone()),
+                    status: status[&uc.db.id],
+                },
+                None => CaTsig {
+                    issuer: issuers.first().map(|h| h.to_hex()).unwrap_or_default(),
+                    created: sig.signature_creation_time(),
+                    cert: None,
+                    status: TsigStatus::Unknown,
+                },
+            };
+
+            res.push((sig.clone(), tsig));
+        }
+    }
+
+    Ok(res)
+}
+
+pub(crate) fn ca_tsigs(oca: &Oca) -> Result<Vec<CaTsig>> {
+    let certs = user_certs(oca)?;
+
+    Ok(tsigs(oca, &certs)?
+        .into_iter()
+        .map(|(_, tsig)| tsig)
+        .collect())
+}
+
+/// Current user certs without a tsig on the CA cert, which share an email
+/// address with a superseded or revoked cert that has tsigned the CA cert.
+pub(crate) fn ca_tsigs_missing(oca: &Oca) -> Result<Vec<models::Cert>> {
+    let certs = user_certs(oca)?;
+    let status = cert_status(&certs);
+
+    let tsigs = tsigs(oca, &certs)?;
+
+    let signers: HashSet<i32> = tsigs
+        .iter()
+        .filter_map(|(_, t)| t.cert.as_ref().map(|c| c.id))
+        .collect();
+
+    // email addresses of users who have tsigned the CA cert with an old key
+    let stale_emails: HashSet<&String> = certs
+        .iter()
+        .filter(|uc| signers.contains(&uc.db.id) && status[&uc.db.id].is_stale())
+        .flat_map(|uc| uc.emails.iter())
+        .collect();
+
+    Ok(certs
+        .iter()
+        .filter(|uc| status[&uc.db.id] == TsigStatus::Current && !signers.contains(&uc.db.id))
+        .filter(|uc| uc.emails.iter().any(|e| stale_emails.contains(e)))
+        .map(|uc| uc.db.clone())
+        .collect())
+}
+
+/// The CA cert with only the tsigs made by the user cert `fp` (None, if that
+/// cert has not tsigned the CA cert)
+pub(crate) fn ca_cert_tsigned_by(oca: &Oca, fp: &str) -> Result<Option<Cert>> {
+    let certs = user_certs(oca)?;
+
+    let (own, others): (Vec<_>, Vec<_>) = tsigs(oca, &certs)?
+        .into_iter()
+        .partition(|(_, tsig)| tsig.issuer == fp);
+
+    if own.is_empty() {
+        return Ok(None);
+    }
+
+    let others: Vec<Signature> = others.into_iter().map(|(sig, _)| sig).collect();
+
+    let ca = oca.ca_get_cert_pub()?;
+
+    let packets = ca
+        .into_packets2()
+        .filter(|p| !matches!(p, Packet::Signature(s) if others.contains(s)));
+
+    Ok(Some(Cert::from_packets(packets)?))
+}
+
+/// The CA cert, without tsigs by superseded or revoked user keys
+pub(crate) fn ca_cert_without_stale_tsigs(oca: &Oca) -> Result<Cert> {
+    let certs = user_certs(oca)?;
+
+    let stale: Vec<Signature> = tsigs(oca, &certs)?
+        .into_iter()
+        .filter(|(_, tsig)| tsig.status.is_stale())
+        .map(|(sig, _)| sig)
+        .collect();
+
+    let ca = oca.ca_get_cert_pub()?;
+
+    let packets = ca
+        .into_packets2()
+        .filter(|p| !matches!(p, Packet::Signature(s) if stale.contains(s)));
+
+    Cert::from_packets(packets)
+}
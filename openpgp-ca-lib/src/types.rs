@@ -8,10 +8,2404 @@
 
 //! OpenPGP CA data types.
 
-use sequoia_openpgp::packet::UserID;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use flate2::write::GzEncoder;
+use sequoia_openpgp::packet::{Signature, UserID};
+use sequoia_openpgp::Cert;
+use serde::{Deserialize, Serialize};
+
+use crate::cert_info::CertInfo;
+use crate::db::models;
+use crate::pgp;
 
 /// Models which User IDs of a Cert have (or have not) been certified by a CA
 pub struct CertificationStatus {
     pub certified: Vec<UserID>,
     pub uncertified: Vec<UserID>,
+
+    /// The User IDs in `certified` whose certifications by the CA have all
+    /// expired (see [crate::Oca::certs_recertify_expired])
+    pub expired: Vec<UserID>,
+}
+
+/// Everything the CA knows about a user (see [crate::Oca::user_dossiers])
+pub struct UserDossier {
+    pub user: models::User,
+    pub tags: BTreeMap<String, String>,
+    pub certs: Vec<CertDossier>,
+}
+
+/// One cert of a [UserDossier]
+pub struct CertDossier {
+    pub cert: models::Cert,
+
+    /// User IDs and (sub)keys, as evaluated by the policy of the CA
+    pub info: CertInfo,
+
+    /// CA certification status of the User IDs
+    pub certification: CertificationStatus,
+
+    /// The cert has trust-signed the CA cert
+    pub tsig_on_ca: bool,
+
+    /// Revocations on file (published or not)
+    pub revocations: Vec<models::Revocation>,
+
+    pub tags: BTreeMap<String, String>,
+}
+
+/// A signature by the CA key on a User ID of a cert (a certification, or a
+/// certification revocation)
+#[derive(Clone, Debug)]
+pub struct IssuedCertification {
+    /// Fingerprint of the cert that the User ID belongs to
+    pub fingerprint: String,
+    pub userid: UserID,
+    pub signature: Signature,
+}
+
+/// Signatures by the CA key in a keyring, reconciled with the signatures
+/// that are recorded in the CA database
+#[derive(Clone, Debug, Default)]
+pub struct IssuedCertifications {
+    /// Valid signatures in the keyring that are recorded in the CA database
+    pub recorded: Vec<IssuedCertification>,
+
+    /// Valid signatures in the keyring that are not recorded in the CA
+    /// database (this may indicate misuse of the CA key!)
+    pub unrecorded: Vec<IssuedCertification>,
+
+    /// Signatures in the keyring that claim to be issued by the CA key,
+    /// but don't verify
+    pub invalid: Vec<IssuedCertification>,
+
+    /// Signatures recorded in the CA database that are not in the keyring
+    pub not_published: Vec<IssuedCertification>,
+}
+
+/// A cert that was excluded from a filtered export, and the reason why
+pub struct ExportRejection {
+    pub fingerprint: String,
+    pub reason: String,
+}
+
+/// Status of a trust signature by a user key on the CA cert
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TsigStatus {
+    /// Made by a current user key
+    Current,
+
+    /// Made by a user key that has been replaced by a newer key (for the
+    /// same email address)
+    Superseded,
+
+    /// Made by a revoked user key
+    Revoked,
+
+    /// Not made by any user key of this CA
+    Unknown,
+}
+
+impl TsigStatus {
+    /// The tsig was made by a user key that is not in use anymore
+    pub fn is_stale(&self) -> bool {
+        matches!(self, TsigStatus::Superseded | TsigStatus::Revoked)
+    }
+}
+
+impl fmt::Display for TsigStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TsigStatus::Current => "current",
+            TsigStatus::Superseded => "superseded",
+            TsigStatus::Revoked => "revoked",
+            TsigStatus::Unknown => "unknown",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// A trust signature on the CA cert
+pub struct CaTsig {
+    /// Fingerprint of the user cert that made the tsig (or the issuer, if
+    /// the tsig was not made by a user cert of this CA)
+    pub issuer: String,
+
+    pub created: Option<SystemTime>,
+
+    /// The user cert that made the tsig
+    pub cert: Option<models::Cert>,
+
+    pub status: TsigStatus,
+}
+
+/// The key material of a newly created user (see [crate::Oca::user_new]).
+///
+/// OpenPGP CA doesn't store the private key: it must be handed over to the
+/// user.
+pub struct NewUserKey {
+    /// Fingerprint of the new user key
+    pub fingerprint: String,
+
+    /// The armored public cert of the user, as certified by the CA
+    pub cert: String,
+
+    /// The armored private key of the user
+    pub private_key: String,
+
+    /// The password that protects the private key (None, if the private
+    /// key is not password protected)
+    pub password: Option<String>,
+}
+
+impl NewUserKey {
+    /// Write the armored private key to the new file `path`, which only the
+    /// current user can read.
+    ///
+    /// Fails if `path` already exists.
+    pub fn write_private_key(&self, path: &Path) -> anyhow::Result<()> {
+        let mut file = crate::util::create_private_file(path)?;
+        file.write_all(self.private_key.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// A newly created user whose key was moved to an OpenPGP card (see
+/// [crate::Oca::user_new_on_card]).
+///
+/// The card holds the only copy of the user's private key material.
+pub struct CardUserKey {
+    /// Fingerprint of the new user key
+    pub fingerprint: String,
+
+    /// The armored public cert of the user, as certified by the CA
+    pub cert: String,
+
+    /// Identifier of the card that holds the user's subkeys
+    pub ident: String,
+
+    /// The new User PIN of the card
+    pub user_pin: String,
+
+    /// The new Admin PIN of the card
+    pub admin_pin: String,
+}
+
+/// Artifacts for setting up a user's OpenPGP software (see
+/// [crate::Oca::user_provisioning_bundle])
+pub struct ProvisioningBundle {
+    /// The user's armored private key (if it was supplied), otherwise the
+    /// user's armored public cert, as stored in the CA
+    pub user_key: String,
+
+    /// The armored public cert of the CA
+    pub ca_cert: String,
+
+    /// The armored CA cert with only the user's tsig (None, if the user's
+    /// cert has not tsigned the CA cert)
+    pub ca_cert_tsigned: Option<String>,
+}
+
+impl ProvisioningBundle {
+    /// All artifacts, concatenated into one armored file
+    pub fn armored(&self) -> String {
+        let mut s = self.user_key.clone();
+        s.push_str(&self.ca_cert);
+        if let Some(tsigned) = &self.ca_cert_tsigned {
+            s.push_str(tsigned);
+        }
+        s
+    }
+}
+
+/// Packet-level differences between two versions of a cert
+pub struct CertDiff {
+    /// Descriptions of packets that only exist in the newer version
+    pub added: Vec<String>,
+
+    /// Descriptions of packets that only exist in the older version
+    pub removed: Vec<String>,
+}
+
+/// A user cert, as the CA stored it at a point in time (see
+/// [crate::Oca::cert_as_of])
+#[derive(Clone, Debug)]
+pub struct CertAsOf {
+    pub time: DateTime<Utc>,
+
+    /// The cert at `time` (None, if the cert was not in the CA yet)
+    pub cert: Option<Cert>,
+
+    /// Id of the previous version that was current at `time` (None, if it
+    /// is the current version)
+    pub version: Option<i32>,
+
+    /// The User IDs that carried a valid certification by the CA at `time`
+    pub certified: Vec<UserID>,
+
+    /// The cert was revoked at `time`
+    pub revoked: bool,
+
+    /// The version history of the cert doesn't reach back to `time` (older
+    /// versions were pruned, or the cert was added by an older version of
+    /// OpenPGP CA), so the reconstructed state may be inaccurate
+    pub incomplete: bool,
+}
+
+/// Minimum requirements for user keys that are imported into a CA.
+///
+/// The default policy doesn't impose any requirements.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyPolicy {
+    /// Minimum size of RSA keys, in bits
+    pub min_rsa_bits: Option<usize>,
+
+    /// Allowed public key algorithms ("rsa", "dsa", "elgamal", "ecdsa",
+    /// "eddsa", "ecdh"), all algorithms are allowed if unset
+    pub allowed_algorithms: Option<Vec<String>>,
+
+    /// Require a valid subkey that is capable of encryption
+    pub require_encryption_subkey: bool,
+
+    /// Require a valid subkey that is capable of signing
+    pub require_signing_subkey: bool,
+
+    /// Maximum remaining validity period of the primary key, in days
+    pub max_expiry_days: Option<u64>,
+
+    /// Maximum age of the newest encryption subkey, in days (encryption
+    /// subkeys must be rotated at least this often)
+    #[serde(default)]
+    pub max_encryption_subkey_age_days: Option<u64>,
+}
+
+/// Adjustments of Sequoia's standard policy, which decides which
+/// cryptographic algorithms are acceptable in certs and signatures.
+///
+/// The default policy is Sequoia's standard policy, unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CryptoPolicy {
+    /// Accept SHA-1 in signatures that were created before this time
+    /// (e.g. for legacy certs)
+    pub sha1_cutoff: Option<DateTime<Utc>>,
+
+    /// Hash algorithms that are rejected, in addition to the ones that the
+    /// standard policy rejects (e.g. "sha224")
+    pub rejected_hashes: Vec<String>,
+
+    /// Public key algorithms that are rejected, in addition to the ones
+    /// that the standard policy rejects (e.g. "rsa2048", "brainpoolp256")
+    pub rejected_algorithms: Vec<String>,
+}
+
+/// Which URI identifiers in User IDs (e.g. machine identities such as
+/// "spiffe://cluster/ns/job") a CA certifies.
+///
+/// The default policy doesn't allow certifying any URI.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UriPolicy {
+    /// URI prefixes that may be certified. A prefix matches a URI that is
+    /// equal to it, or that continues after the prefix with "/" or ":"
+    /// (so "spiffe://cluster" matches "spiffe://cluster/ns/job", but not
+    /// "spiffe://cluster2/job").
+    pub allowed_prefixes: Vec<String>,
+}
+
+/// A notation on a signature (name and human-readable value)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notation {
+    pub name: String,
+    pub value: String,
+}
+
+impl Notation {
+    /// Does the hashed area of `sig` contain this notation?
+    pub fn is_on(&self, sig: &Signature) -> bool {
+        sig.notation(&self.name).any(|v| v == self.value.as_bytes())
+    }
+}
+
+/// A notation that the CA adds to all certifications it issues, so that
+/// CA-issued signatures can be told apart from other signatures by the
+/// same key (e.g. in audits).
+///
+/// The default policy doesn't add a notation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotationPolicy {
+    /// Add the notation to new certifications
+    pub enabled: bool,
+
+    /// Name of the notation (default: [NotationPolicy::DEFAULT_NAME])
+    pub name: Option<String>,
+
+    /// Value of the notation (default: "certified=<domain of the CA>")
+    pub value: Option<String>,
+
+    /// Only certifications that carry the notation count as CA
+    /// certifications when certs are checked (see
+    /// [crate::Oca::cert_check_ca_sig])
+    pub required: bool,
+}
+
+impl NotationPolicy {
+    pub const DEFAULT_NAME: &'static str = "openpgp-ca@notations.sequoia-pgp.org";
+
+    /// The notation for certifications by the CA for `domain` (None, if
+    /// the policy is not enabled)
+    pub fn notation(&self, domain: &str) -> Option<Notation> {
+        if !self.enabled {
+            return None;
+        }
+
+        Some(Notation {
+            name: self
+                .name
+                .clone()
+                .unwrap_or_else(|| Self::DEFAULT_NAME.to_string()),
+            value: self
+                .value
+                .clone()
+                .unwrap_or_else(|| format!("certified={domain}")),
+        })
+    }
+}
+
+/// Retention of the rows of one database table.
+///
+/// Rows are removed if they are older than `max_age_days`, or if they are
+/// not among the `max_count` newest rows. Unset limits are not enforced.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Retention {
+    pub max_age_days: Option<u64>,
+    pub max_count: Option<u64>,
+}
+
+/// Retention policies for tables of the CA database that grow over time.
+///
+/// The default policy retains all rows.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Queue entries of split mode CAs that have been processed
+    pub queue_done: Retention,
+
+    /// Previous versions of certs (in addition to the per-cert limit, see
+    /// [crate::Oca::cert_versions_keep]). `max_count` applies to the
+    /// versions of each cert separately.
+    pub cert_versions: Retention,
+}
+
+/// Keyservers that certs are updated from (see
+/// [crate::Oca::update_from_keyserver]).
+///
+/// A cert is looked up on all `keyservers`. A packet that isn't part of
+/// our copy of the cert yet is only merged in if at least `quorum` of the
+/// keyservers returned it, so that one stale or misbehaving keyserver
+/// can't add packets on its own.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyserverConfig {
+    /// Keyservers that certs are looked up on (e.g.
+    /// "hkps://keys.openpgp.org")
+    pub keyservers: Vec<String>,
+
+    /// The number of keyservers that must return a packet, before it is
+    /// merged in (at least 1, at most the number of keyservers)
+    pub quorum: usize,
+}
+
+impl Default for KeyserverConfig {
+    fn default() -> Self {
+        Self {
+            keyservers: vec!["hkps://keys.openpgp.org".to_string()],
+            quorum: 1,
+        }
+    }
+}
+
+/// Channels through which revocations are published as soon as they are
+/// applied (see [crate::Oca::revocation_apply]).
+///
+/// The default configuration publishes nothing: applied revocations are
+/// only merged into the CA's copy of the cert.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationPublication {
+    /// Keyservers that revoked certs are pushed to (e.g.
+    /// "hkps://keys.openpgp.org")
+    #[serde(default)]
+    pub keyservers: Vec<String>,
+
+    /// WKD directory structure (as written by [crate::Oca::export_wkd])
+    /// in which the entries of revoked certs are updated
+    #[serde(default)]
+    pub wkd_path: Option<PathBuf>,
+}
+
+impl RevocationPublication {
+    /// Revocations are published through at least one channel
+    pub fn enabled(&self) -> bool {
+        !self.keyservers.is_empty() || self.wkd_path.is_some()
+    }
+}
+
+/// A channel through which revoked certs are published
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublicationChannel {
+    Keyserver,
+    Wkd,
+}
+
+/// A channel through which certs are exported. Certs can be delisted per
+/// channel (see [crate::Oca::cert_set_delisted]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportChannel {
+    /// WKD exports
+    Wkd,
+
+    /// Signed keylists
+    Keylist,
+
+    /// Publication of revoked certs to keyservers
+    Keyserver,
+
+    /// Certring exports (as one file, or in chunks)
+    Certring,
+}
+
+impl ExportChannel {
+    pub const ALL: [ExportChannel; 4] = [
+        ExportChannel::Wkd,
+        ExportChannel::Keylist,
+        ExportChannel::Keyserver,
+        ExportChannel::Certring,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportChannel::Wkd => "wkd",
+            ExportChannel::Keylist => "keylist",
+            ExportChannel::Keyserver => "keyserver",
+            ExportChannel::Certring => "certring",
+        }
+    }
+}
+
+impl std::str::FromStr for ExportChannel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ExportChannel::ALL
+            .iter()
+            .copied()
+            .find(|c| c.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown export channel '{s}'"))
+    }
+}
+
+impl fmt::Display for ExportChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Summary of offboarding a user (see [crate::Oca::user_offboard])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OffboardReport {
+    pub email: String,
+    pub certs: Vec<OffboardedCert>,
+}
+
+/// How one cert of an offboarded user was handled. All offboarded certs
+/// are deactivated and delisted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OffboardedCert {
+    pub fingerprint: String,
+    pub revocation: OffboardRevocation,
+}
+
+/// How an offboarded cert was revoked
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OffboardRevocation {
+    /// The cert was already revoked
+    AlreadyRevoked,
+
+    /// The stored revocation with this hash was applied
+    Applied(String),
+
+    /// No revocation was on file: the CA retracted its certifications of
+    /// these User IDs instead
+    Retracted(Vec<String>),
+}
+
+/// Number of rows that a cleanup removed (or would remove, in a dry run)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CleanupReport {
+    pub queue_done: usize,
+    pub cert_versions: usize,
+}
+
+/// Information about a CA instance (see [crate::Oca::ca_info])
+#[derive(Clone, Debug)]
+pub struct CaInfo {
+    /// The domain name of the CA
+    pub domainname: String,
+
+    /// Fingerprint of the CA cert
+    pub fingerprint: String,
+
+    /// Creation time of the CA key
+    pub created: DateTime<Utc>,
+
+    /// Description of the backend of the CA (e.g. "Split-mode front instance")
+    pub backend: String,
+}
+
+/// Anonymous usage statistics of a CA instance, for sharing with the
+/// OpenPGP CA project (see [crate::Oca::usage_stats]).
+///
+/// Contains no names, email addresses, domains or fingerprints.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UsageStats {
+    /// Version of the schema of this document
+    pub format: u32,
+
+    /// Version of OpenPGP CA
+    pub version: String,
+
+    /// Type of the CA backend ("softkey", "card", "split-front",
+    /// "split-back(...)")
+    pub backend: String,
+
+    pub users: usize,
+    pub certs: usize,
+    pub bridges: usize,
+}
+
+/// Usage statistics as they are sent: the serialized statistics, and a
+/// detached signature over them by the CA key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UsageStatsSubmission {
+    pub payload: String,
+
+    /// Armored detached signature over `payload`
+    pub signature: String,
+}
+
+impl UsageStatsSubmission {
+    /// Verify the signature with `ca_cert`, and return the parsed
+    /// [UsageStats]
+    pub fn verify(&self, ca_cert: &Cert) -> anyhow::Result<UsageStats> {
+        pgp::verify_detached(ca_cert, self.payload.as_bytes(), &self.signature)
+            .map_err(|_| anyhow::anyhow!("Usage statistics signature verification failed"))?;
+
+        Ok(serde_json::from_str(&self.payload)?)
+    }
+}
+
+/// What happened (or would happen, without repair) to a cert row whose
+/// armored cert can't be parsed (see [crate::Oca::db_check_blobs])
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CertBlobOutcome {
+    /// The cert can be recovered by lenient parsing
+    Recoverable,
+
+    /// The recovered cert has been stored
+    Repaired,
+
+    /// The cert can't be recovered
+    Irrecoverable,
+
+    /// The row has been quarantined for manual handling
+    Quarantined,
+}
+
+/// A cert row whose armored cert can't be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertBlobReport {
+    /// Id of the row in the certs table
+    pub cert_id: i32,
+
+    /// Fingerprint of the cert, as stored in the row
+    pub fingerprint: String,
+
+    /// Why the armored cert can't be parsed
+    pub error: String,
+
+    pub outcome: CertBlobOutcome,
+}
+
+/// The kind of a violated database invariant (see
+/// [crate::Oca::check_consistency])
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsistencyIssueKind {
+    /// The CA cert can't be parsed, or its private key material doesn't
+    /// match the backend
+    CaCert,
+
+    /// An armored cert can't be parsed
+    CertUnparsable,
+
+    /// The fingerprint of a cert row doesn't match its armored cert
+    FingerprintMismatch,
+
+    /// An email of a cert has no matching User ID
+    EmailMismatch,
+
+    /// A revocation can't be parsed, doesn't belong to an existing cert, or
+    /// doesn't validate against its cert
+    RevocationInvalid,
+
+    /// A pending queue entry can't be parsed, or refers to a cert (or
+    /// revocation) that doesn't exist
+    QueueDangling,
+}
+
+impl fmt::Display for ConsistencyIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ConsistencyIssueKind::CaCert => "CA cert",
+            ConsistencyIssueKind::CertUnparsable => "unparsable cert",
+            ConsistencyIssueKind::FingerprintMismatch => "fingerprint mismatch",
+            ConsistencyIssueKind::EmailMismatch => "email mismatch",
+            ConsistencyIssueKind::RevocationInvalid => "invalid revocation",
+            ConsistencyIssueKind::QueueDangling => "dangling queue entry",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// A violated database invariant
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsistencyIssue {
+    pub kind: ConsistencyIssueKind,
+
+    /// The affected row (e.g. "certs row 3")
+    pub row: String,
+
+    pub message: String,
+}
+
+/// The result of a database consistency check (see
+/// [crate::Oca::check_consistency])
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConsistencyReport {
+    /// Number of cert rows that were checked (quarantined rows are skipped)
+    pub certs: usize,
+
+    /// Number of revocations that were checked
+    pub revocations: usize,
+
+    /// Number of pending queue entries that were checked
+    pub queue_entries: usize,
+
+    pub issues: Vec<ConsistencyIssue>,
+}
+
+impl ConsistencyReport {
+    /// No issues were found
+    pub fn is_consistent(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// A User ID whose certifications by the CA all expire within a window
+/// (see [crate::Oca::certifications_extend_expiring])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpiringCertification {
+    /// Fingerprint of the cert
+    pub fingerprint: String,
+
+    /// Name of the user that the cert belongs to
+    pub user_name: Option<String>,
+
+    /// The certified User ID
+    pub user_id: String,
+
+    /// Expiration time of the CA certification that is valid the longest
+    pub expiration: DateTime<Utc>,
+}
+
+/// Result of [crate::Oca::certifications_extend_expiring]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CertificationExtensionReport {
+    /// The certifications that expire within the window (in a dry run, or
+    /// if the renewal of a cert failed: they have not been renewed)
+    pub expiring: Vec<ExpiringCertification>,
+
+    /// Number of User IDs that got new certifications (0 in a dry run)
+    pub renewed: usize,
+
+    /// The new certifications were queued for the back instance of a
+    /// split-mode CA (they take effect after the queue is processed)
+    pub queued: bool,
+
+    /// Certs whose renewal failed (fingerprint and error message)
+    pub failed: Vec<(String, String)>,
+}
+
+/// A reason why a cert doesn't meet the [KeyPolicy] of a CA
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyPolicyViolation {
+    /// The cert is not valid according to the standard policy
+    InvalidCert { reason: String },
+
+    /// A (sub)key uses RSA with fewer than `min` bits
+    RsaKeyTooSmall {
+        fingerprint: String,
+        bits: usize,
+        min: usize,
+    },
+
+    /// A (sub)key uses an algorithm that is not allowed
+    AlgorithmNotAllowed {
+        fingerprint: String,
+        algorithm: String,
+    },
+
+    /// There is no valid encryption-capable subkey
+    MissingEncryptionSubkey,
+
+    /// There is no valid signing-capable subkey
+    MissingSigningSubkey,
+
+    /// The primary key never expires, but at most `max_days` are allowed
+    NoExpiry { max_days: u64 },
+
+    /// The primary key expires in `days`, but at most `max_days` are allowed
+    ExpiryTooLong { days: u64, max_days: u64 },
+
+    /// The newest encryption subkey is `days` old, but at most `max_days`
+    /// are allowed
+    EncryptionSubkeyTooOld {
+        fingerprint: String,
+        days: u64,
+        max_days: u64,
+    },
+}
+
+impl fmt::Display for KeyPolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCert { reason } => write!(f, "Cert is invalid: {reason}"),
+            Self::RsaKeyTooSmall {
+                fingerprint,
+                bits,
+                min,
+            } => write!(
+                f,
+                "Key {fingerprint} is RSA with {bits} bits, at least {min} are required"
+            ),
+            Self::AlgorithmNotAllowed {
+                fingerprint,
+                algorithm,
+            } => write!(
+                f,
+                "Key {fingerprint} uses algorithm {algorithm}, which is not allowed"
+            ),
+            Self::MissingEncryptionSubkey => write!(f, "No valid encryption subkey"),
+            Self::MissingSigningSubkey => write!(f, "No valid signing subkey"),
+            Self::NoExpiry { max_days } => write!(
+                f,
+                "Cert doesn't expire, an expiry within {max_days} days is required"
+            ),
+            Self::ExpiryTooLong { days, max_days } => write!(
+                f,
+                "Cert expires in {days} days, at most {max_days} days are allowed"
+            ),
+            Self::EncryptionSubkeyTooOld {
+                fingerprint,
+                days,
+                max_days,
+            } => write!(
+                f,
+                "Encryption subkey {fingerprint} is {days} days old, \
+                 it must be rotated every {max_days} days"
+            ),
+        }
+    }
+}
+
+/// The newest encryption subkey of a cert, and when it is due for rotation
+/// according to the key policy (see [crate::Oca::subkey_rotation])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubkeyRotation {
+    /// Fingerprint of the cert
+    pub fingerprint: String,
+
+    /// Fingerprint of the newest valid encryption subkey (None, if the cert
+    /// has no valid encryption subkey)
+    pub subkey: Option<String>,
+
+    /// Creation time of `subkey`
+    pub created: Option<DateTime<Utc>>,
+
+    /// Expiration time of `subkey`
+    pub expiration: Option<DateTime<Utc>>,
+
+    /// When `subkey` exceeds the maximum age of the key policy (None, if
+    /// the policy doesn't limit the age of encryption subkeys)
+    pub rotation_due: Option<DateTime<Utc>>,
+}
+
+/// Error for certs that are rejected because of [KeyPolicyViolation]s
+/// (can be recovered from an `anyhow::Error` via `downcast_ref`)
+#[derive(Debug)]
+pub struct KeyPolicyError {
+    pub violations: Vec<KeyPolicyViolation>,
+}
+
+impl fmt::Display for KeyPolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cert doesn't meet the key policy of this CA:")?;
+        for v in &self.violations {
+            write!(f, "\n- {v}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for KeyPolicyError {}
+
+/// A way in which an update would roll back the state of a cert in the CA
+/// database
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CertDowngrade {
+    /// Packets of the stored cert would be lost
+    PacketsLost { count: usize },
+
+    /// The expiration time of the cert would move to an earlier time
+    /// (`None` means that the cert doesn't expire)
+    ExpiryRolledBack {
+        old: Option<SystemTime>,
+        new: Option<SystemTime>,
+    },
+
+    /// The stored cert is revoked, the updated cert would not be
+    RevocationRemoved,
+}
+
+impl fmt::Display for CertDowngrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = |t: &Option<SystemTime>| match t {
+            Some(t) => DateTime::<Utc>::from(*t).to_string(),
+            None => "never".to_string(),
+        };
+
+        match self {
+            Self::PacketsLost { count } => write!(f, "{count} packet(s) would be lost"),
+            Self::ExpiryRolledBack { old, new } => {
+                write!(f, "Expiry would move from {} to {}", time(old), time(new))
+            }
+            Self::RevocationRemoved => write!(f, "The revocation of the cert would be removed"),
+        }
+    }
+}
+
+/// Error for cert updates that are rejected because of [CertDowngrade]s
+/// (can be recovered from an `anyhow::Error` via `downcast_ref`)
+#[derive(Debug)]
+pub struct CertDowngradeError {
+    pub fingerprint: String,
+    pub downgrades: Vec<CertDowngrade>,
+}
+
+impl fmt::Display for CertDowngradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Update would downgrade cert {}:", self.fingerprint)?;
+        for d in &self.downgrades {
+            write!(f, "\n- {d}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CertDowngradeError {}
+
+/// Error for imports of a cert that is already known as a cert of a
+/// different user (can be recovered from an `anyhow::Error` via
+/// `downcast_ref`)
+#[derive(Debug)]
+pub struct CertOwnershipError {
+    pub fingerprint: String,
+
+    /// The user that the cert belongs to
+    pub user_id: i32,
+    pub user_name: Option<String>,
+}
+
+impl fmt::Display for CertOwnershipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cert {} already belongs to the user '{}' (id {}).\nTo move it to another user, use the 'user reassign-cert' command.",
+            self.fingerprint,
+            self.user_name.as_deref().unwrap_or("<no name>"),
+            self.user_id
+        )
+    }
+}
+
+impl std::error::Error for CertOwnershipError {}
+
+/// Encoding of exported certs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CertFormat {
+    /// ASCII armored (".asc")
+    Armored,
+
+    /// Binary OpenPGP packets (".pgp")
+    Binary,
+}
+
+impl CertFormat {
+    /// File name extension for certs in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CertFormat::Armored => "asc",
+            CertFormat::Binary => "pgp",
+        }
+    }
+}
+
+/// Compression of an exported file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportCompression {
+    /// Uncompressed (the default)
+    #[default]
+    None,
+
+    /// gzip (".gz")
+    Gzip,
+
+    /// Zstandard (".zst")
+    Zstd,
+}
+
+impl ExportCompression {
+    /// Suffix that is appended to the names of files with this compression
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            ExportCompression::None => "",
+            ExportCompression::Gzip => ".gz",
+            ExportCompression::Zstd => ".zst",
+        }
+    }
+
+    /// Compress `data`.
+    ///
+    /// The output only depends on `data`, so unchanged exports compress to
+    /// identical files (the gzip header doesn't contain a timestamp).
+    pub fn compress(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        match self {
+            ExportCompression::None => Ok(data.to_vec()),
+            ExportCompression::Gzip => {
+                let mut enc = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                enc.write_all(data)?;
+                Ok(enc.finish()?)
+            }
+            ExportCompression::Zstd => Ok(zstd::encode_all(data, 0)?),
+        }
+    }
+}
+
+/// Representation of a fingerprint, for comparison by humans
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FingerprintFormat {
+    /// Uppercase hex digits
+    Plain,
+
+    /// Uppercase hex digits in groups of four (as shown by GnuPG)
+    Spaced,
+
+    /// An "OPENPGP4FPR:" URI, the payload of fingerprint QR codes (as used
+    /// e.g. by OpenKeychain)
+    Qr,
+}
+
+/// Target client of an export.
+///
+/// OpenPGP clients differ in which artifacts they accept. Exports for a
+/// target are adjusted to its quirks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportCompat {
+    /// GnuPG 2.2 (the default)
+    #[default]
+    GnuPG22,
+
+    /// Thunderbird (which uses the RNP OpenPGP implementation)
+    Thunderbird,
+
+    /// Sequoia PGP
+    Sequoia,
+}
+
+impl ExportCompat {
+    /// Standalone revocations are armored as "PGP PUBLIC KEY BLOCK".
+    ///
+    /// GnuPG doesn't accept revocations with the "PGP SIGNATURE" armor kind.
+    pub fn revocation_as_public_key(&self) -> bool {
+        !matches!(self, ExportCompat::Sequoia)
+    }
+
+    /// Trust signatures that are scoped with regular expressions (as
+    /// issued for bridges) are exported.
+    ///
+    /// RNP doesn't support regular expression subpackets.
+    pub fn regex_trust_signatures(&self) -> bool {
+        !matches!(self, ExportCompat::Thunderbird)
+    }
+
+    /// User certs are always minimized (see `minimize` on the export
+    /// functions).
+    ///
+    /// Thunderbird handles certs with many third-party signatures poorly.
+    pub fn minimize(&self) -> bool {
+        matches!(self, ExportCompat::Thunderbird)
+    }
+}
+
+/// Scope of the trust signature of a bridge: which User IDs the remote CA
+/// is trusted to certify.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum BridgeScope {
+    /// User IDs in the domain of the remote CA (the default)
+    #[default]
+    RemoteDomain,
+
+    /// User IDs in `domain`. Unless `force` is set, `domain` must be the
+    /// domain of the remote CA.
+    Domain { domain: String, force: bool },
+
+    /// User IDs that match one of these regular expressions
+    Regexes(Vec<String>),
+
+    /// All User IDs
+    Unscoped,
+}
+
+/// Profile for the signatures that the CA issues on user certs
+/// (certifications) and on remote CA certs (bridge trust signatures).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CertificationProfile {
+    /// Signatures as Sequoia PGP generates them (the default)
+    #[default]
+    Default,
+
+    /// For interoperability with legacy OpenPGP implementations: v4
+    /// signatures with SHA-256 and only subpackets that are defined in
+    /// RFC 4880. In particular, there is no salt notation (which Sequoia
+    /// otherwise adds; the CA's own notation is still added, if configured),
+    /// and trust signatures are not scoped with regular expressions.
+    Rfc4880Compat,
+}
+
+impl CertificationProfile {
+    pub const ALL: [CertificationProfile; 2] = [
+        CertificationProfile::Default,
+        CertificationProfile::Rfc4880Compat,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CertificationProfile::Default => "default",
+            CertificationProfile::Rfc4880Compat => "rfc4880-compat",
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == CertificationProfile::Default
+    }
+
+    /// Label for the cert history entry of a change with `origin`, made
+    /// with this profile
+    pub(crate) fn history_label(&self, origin: &str) -> String {
+        match self {
+            CertificationProfile::Default => origin.to_string(),
+            _ => format!("{origin} ({self})"),
+        }
+    }
+
+    /// Trust signatures can be scoped with regular expressions.
+    ///
+    /// Some legacy implementations mishandle regular expression subpackets.
+    pub fn regex_trust_signatures(&self) -> bool {
+        !matches!(self, CertificationProfile::Rfc4880Compat)
+    }
+}
+
+impl std::str::FromStr for CertificationProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CertificationProfile::ALL
+            .iter()
+            .copied()
+            .find(|p| p.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown certification profile '{s}'"))
+    }
+}
+
+impl fmt::Display for CertificationProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// How long a certification by the CA is valid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CertificationValidity {
+    /// Valid for a number of days, from the time of certification
+    Days(u64),
+
+    /// Valid until a fixed point in time (e.g. the end of an employment
+    /// contract, or an annual re-validation date)
+    Until(DateTime<Utc>),
+}
+
+impl CertificationValidity {
+    /// A validity of `days` (`None` means: no expiration)
+    pub fn from_days(days: Option<u64>) -> Option<Self> {
+        days.map(CertificationValidity::Days)
+    }
+
+    /// The validity period of a certification that is created at `created`
+    pub(crate) fn period(&self, created: SystemTime) -> anyhow::Result<std::time::Duration> {
+        match self {
+            CertificationValidity::Days(days) => {
+                Ok(std::time::Duration::from_secs(pgp::SECONDS_IN_DAY * days))
+            }
+            CertificationValidity::Until(until) => SystemTime::from(*until)
+                .duration_since(created)
+                .map_err(|_| {
+                    anyhow::anyhow!(
+                        "The certification would expire in the past ({})",
+                        until.format("%F %T %Z")
+                    )
+                }),
+        }
+    }
+}
+
+impl fmt::Display for CertificationValidity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CertificationValidity::Days(days) => write!(f, "{days} days"),
+            CertificationValidity::Until(until) => write!(f, "until {}", until.format("%F %T %Z")),
+        }
+    }
+}
+
+/// Policy for checkpointing the SQLite write-ahead log of the CA database.
+///
+/// This only has an effect if the database is in WAL mode (OpenPGP CA
+/// itself doesn't enable WAL mode, but embedders may).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckpointPolicy {
+    /// SQLite's default: checkpoint automatically when the log grows beyond
+    /// 1000 pages. Suitable for short-lived processes (like the CLI), which
+    /// additionally checkpoint in [crate::Oca::close].
+    Default,
+
+    /// Checkpoint automatically when the log grows beyond the given number
+    /// of pages.
+    Pages(u32),
+
+    /// Don't checkpoint automatically. The embedder is responsible for
+    /// calling [crate::Oca::checkpoint] (e.g. periodically, from a daemon).
+    Manual,
+}
+
+impl CheckpointPolicy {
+    /// The value for SQLite's `wal_autocheckpoint` pragma
+    pub(crate) fn autocheckpoint_pages(&self) -> u32 {
+        match self {
+            CheckpointPolicy::Default => 1000,
+            CheckpointPolicy::Pages(pages) => *pages,
+            CheckpointPolicy::Manual => 0,
+        }
+    }
+}
+
+/// Federation metadata of an OpenPGP CA instance.
+///
+/// Describes how a CA publishes keys, so that partner CAs can set up
+/// bridges to it. The document is signed with the CA key (see
+/// [SignedFederationMetadata]).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FederationMetadata {
+    /// Schema version of this document
+    pub version: u32,
+
+    /// Domains that this CA is responsible for
+    pub domains: Vec<String>,
+
+    /// URL of the CA's keylist, if it publishes one
+    pub keylist_url: Option<String>,
+
+    /// WKD method ("advanced" or "direct") that the CA's keys are published with
+    pub wkd_method: Option<String>,
+
+    /// Contact address for bridge requests
+    pub contact: Option<String>,
+
+    /// The armored public key of the CA
+    pub ca_cert: String,
+
+    pub created: DateTime<Utc>,
+}
+
+/// Result of importing a bridge bundle of a remote CA (see
+/// [crate::Oca::import_bridge_bundle])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BridgeBundleImport {
+    /// Email of the bridge to the remote CA
+    pub email: String,
+
+    /// Fingerprint of the remote CA key
+    pub fingerprint: String,
+
+    /// Whether the import created the bridge (otherwise, it already existed)
+    pub bridge_created: bool,
+}
+
+/// A [FederationMetadata] document along with a detached signature by the
+/// CA key.
+///
+/// The signature is made over the exact bytes of `metadata`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedFederationMetadata {
+    /// JSON serialization of a [FederationMetadata]
+    pub metadata: String,
+
+    /// Armored detached signature over `metadata`
+    pub signature: String,
+}
+
+/// The manifest of an org trust package (see [crate::Oca::trust_package]).
+///
+/// The package is a tar archive that contains the manifest, a detached
+/// signature over the manifest by the CA key, and the files that the
+/// manifest lists.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustPackageManifest {
+    /// Schema version of this document
+    pub format: u32,
+
+    /// Version of the package: each exported package of a CA has a higher
+    /// version than the previous one
+    pub version: u64,
+
+    pub created: DateTime<Utc>,
+
+    /// Domain of the CA
+    pub domain: String,
+
+    /// Fingerprint of the CA key
+    pub ca_fingerprint: String,
+
+    /// The files in the package (except for the manifest and its signature)
+    pub files: Vec<TrustPackageFile>,
+}
+
+/// A file in an org trust package
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustPackageFile {
+    /// Path of the file in the package
+    pub path: String,
+
+    /// SHA256 digest of the content of the file (as hex)
+    pub sha256: String,
+}
+
+/// Index of a chunked keyring export (see
+/// [crate::Oca::export_certring_chunked]), stored as "index.json"
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkedExportManifest {
+    /// Schema version of this document
+    pub format: u32,
+
+    pub created: DateTime<Utc>,
+
+    /// Fingerprint of the CA key
+    pub ca_fingerprint: String,
+
+    /// The file that contains the CA cert
+    pub ca_file: String,
+
+    /// The user certs, split into chunks
+    pub chunks: Vec<ExportChunk>,
+}
+
+/// One file of a chunked keyring export.
+///
+/// User certs are assigned to chunks by the leading bits of their
+/// fingerprint, so a chunk only changes when one of its certs changes (or
+/// when the number of chunks changes).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportChunk {
+    /// Name of the file
+    pub path: String,
+
+    /// SHA256 digest of the content of the file (as hex)
+    pub sha256: String,
+
+    /// Fingerprints of the certs in the chunk
+    pub fingerprints: Vec<String>,
+}
+
+/// Revocation status of a cert, according to a CA
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CertStatusKind {
+    /// The cert is known to the CA, and not revoked
+    Good,
+
+    /// The cert is known to the CA, and revoked
+    Revoked,
+
+    /// The cert is not known to the CA
+    Unknown,
+}
+
+/// A statement by a CA about the revocation status of a cert (see
+/// [crate::Oca::cert_status]), similar to an OCSP response.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertStatus {
+    /// Schema version of this document
+    pub version: u32,
+
+    /// Fingerprint of the CA key that issued this statement
+    pub ca_fingerprint: String,
+
+    /// Fingerprint of the cert that this statement is about
+    pub fingerprint: String,
+
+    pub status: CertStatusKind,
+
+    /// Creation time of the revocation (for revoked certs)
+    pub revocation_time: Option<DateTime<Utc>>,
+
+    /// Reason for the revocation (for revoked certs)
+    pub reason: Option<String>,
+
+    /// When the CA determined this status
+    pub produced_at: DateTime<Utc>,
+
+    /// Relying parties should not rely on this statement after this time
+    pub next_update: DateTime<Utc>,
+}
+
+/// A [CertStatus] along with a detached signature by the CA key.
+///
+/// The signature is made over the exact bytes of `status`, so relying
+/// parties should verify it before parsing the status.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SignedCertStatus {
+    /// JSON serialization of a [CertStatus]
+    pub status: String,
+
+    /// Armored detached signature over `status`
+    pub signature: String,
+}
+
+impl SignedCertStatus {
+    /// Verify the signature with `ca_cert`, and return the parsed
+    /// [CertStatus] (which must be issued by `ca_cert`)
+    pub fn verify(&self, ca_cert: &Cert) -> anyhow::Result<CertStatus> {
+        pgp::verify_detached(ca_cert, self.status.as_bytes(), &self.signature)
+            .map_err(|_| anyhow::anyhow!("Cert status signature verification failed"))?;
+
+        let status: CertStatus = serde_json::from_str(&self.status)?;
+        if status.ca_fingerprint != ca_cert.fingerprint().to_hex() {
+            return Err(anyhow::anyhow!(
+                "Cert status was issued by an unexpected CA {}",
+                status.ca_fingerprint
+            ));
+        }
+
+        Ok(status)
+    }
+}
+
+/// Contact and policy information that a CA publishes in the direct key
+/// signature of its cert.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaConfig {
+    /// URL of the CA's policy (published in a policy URI subpacket)
+    pub policy_uri: Option<String>,
+
+    /// Contact for security issues (e.g. "mailto:security@example.org")
+    pub security_contact: Option<String>,
+
+    /// URL of the CA's certification practice statement
+    pub cps_uri: Option<String>,
+}
+
+impl CaConfig {
+    pub fn get(&self, key: CaConfigKey) -> Option<&str> {
+        match key {
+            CaConfigKey::PolicyUri => self.policy_uri.as_deref(),
+            CaConfigKey::SecurityContact => self.security_contact.as_deref(),
+            CaConfigKey::CpsUri => self.cps_uri.as_deref(),
+        }
+    }
+
+    pub(crate) fn set(&mut self, key: CaConfigKey, value: Option<String>) {
+        match key {
+            CaConfigKey::PolicyUri => self.policy_uri = value,
+            CaConfigKey::SecurityContact => self.security_contact = value,
+            CaConfigKey::CpsUri => self.cps_uri = value,
+        }
+    }
+}
+
+/// A setting in [CaConfig]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CaConfigKey {
+    PolicyUri,
+    SecurityContact,
+    CpsUri,
+}
+
+impl CaConfigKey {
+    pub const ALL: [CaConfigKey; 3] = [
+        CaConfigKey::PolicyUri,
+        CaConfigKey::SecurityContact,
+        CaConfigKey::CpsUri,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CaConfigKey::PolicyUri => "policy-uri",
+            CaConfigKey::SecurityContact => "security-contact",
+            CaConfigKey::CpsUri => "cps-uri",
+        }
+    }
+}
+
+impl std::str::FromStr for CaConfigKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CaConfigKey::ALL
+            .iter()
+            .copied()
+            .find(|k| k.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown CA config key '{s}'"))
+    }
+}
+
+impl fmt::Display for CaConfigKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A change of a [CaConfig] setting
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaConfigChange {
+    pub key: CaConfigKey,
+
+    /// The value before the change (`None` if it was unset)
+    pub old: Option<String>,
+
+    /// The value after the change (`None` if it was unset)
+    pub new: Option<String>,
+
+    pub changed: DateTime<Utc>,
+}
+
+/// Outcome of one step of a smoke test (see [crate::Oca::smoketest])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SmoketestStatus {
+    Passed,
+
+    /// The step failed, with the given error
+    Failed(String),
+
+    /// The step doesn't apply to this CA (or depends on a failed step)
+    Skipped(String),
+}
+
+impl fmt::Display for SmoketestStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmoketestStatus::Passed => write!(f, "PASS"),
+            SmoketestStatus::Failed(e) => write!(f, "FAIL ({e})"),
+            SmoketestStatus::Skipped(reason) => write!(f, "SKIP ({reason})"),
+        }
+    }
+}
+
+/// One step of a smoke test (see [crate::Oca::smoketest])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SmoketestStep {
+    pub name: &'static str,
+    pub status: SmoketestStatus,
+}
+
+/// Output format of a web of trust graph (see [crate::Oca::export_wot_graph])
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WotGraphFormat {
+    /// Graphviz DOT
+    Dot,
+
+    /// JSON serialization of a [WotGraph]
+    Json,
+}
+
+/// The trust topology of a CA: its cert, the (active) user certs, the
+/// remote CAs it has bridges to, and the certifications between them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WotGraph {
+    pub nodes: Vec<WotNode>,
+    pub edges: Vec<WotEdge>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WotNode {
+    pub fingerprint: String,
+    pub kind: WotNodeKind,
+
+    /// Name and email addresses (or the User ID of a CA)
+    pub label: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WotNodeKind {
+    /// This CA
+    Ca,
+    User,
+
+    /// A CA that this CA has a bridge to
+    RemoteCa,
+}
+
+/// A certification from the cert `from` on the cert `to`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WotEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: WotEdgeKind,
+
+    /// Regular expressions that limit the scope of a trust signature
+    pub scope: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WotEdgeKind {
+    /// A certification of User IDs (by the CA, on a user cert)
+    Certification,
+
+    /// A trust signature (by a user on the CA cert, or between CAs)
+    Tsig,
+}
+
+/// A field of the CA database that matched a search (see
+/// [crate::Oca::search])
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchField {
+    Fingerprint,
+
+    /// The name of the user
+    Name,
+
+    /// An email address that is associated with the cert
+    Email,
+
+    /// A User ID of the cert
+    UserId,
+
+    CertNotes,
+    UserNotes,
+
+    /// The name or value of a tag on the cert or its user
+    Tags,
+}
+
+impl fmt::Display for SearchField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SearchField::Fingerprint => "fingerprint",
+            SearchField::Name => "name",
+            SearchField::Email => "email",
+            SearchField::UserId => "user id",
+            SearchField::CertNotes => "cert notes",
+            SearchField::UserNotes => "user notes",
+            SearchField::Tags => "tags",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// A cert that matched a search
+pub struct SearchMatch {
+    pub cert: models::Cert,
+    pub user: Option<models::User>,
+
+    /// The fields that matched
+    pub fields: Vec<SearchField>,
+}
+
+/// A named set of parameters for generating user keys (see
+/// [crate::Oca::user_new_with_profile]), so that different classes of users
+/// (e.g. ordinary users, code-signing, service accounts) get appropriate keys
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyProfile {
+    /// Name of the profile (ASCII letters, digits, '-' and '_')
+    pub name: String,
+
+    /// Cipher suite of the generated keys ("cv25519", "rsa2k", "rsa3k",
+    /// "rsa4k", "p256", "p384", "p521")
+    pub cipher_suite: String,
+
+    /// Create a subkey that is capable of encryption
+    pub encryption_subkey: bool,
+
+    /// Create a subkey that is capable of signing
+    pub signing_subkey: bool,
+
+    /// Create a subkey that is capable of authentication
+    pub authentication_subkey: bool,
+
+    /// Validity period of generated keys, in days (no expiration if unset)
+    #[serde(default)]
+    pub expiration_days: Option<u64>,
+
+    /// Protect generated private keys with a password
+    pub password: bool,
+
+    /// Number of words in generated diceware passwords
+    pub password_words: usize,
+
+    /// Use the short EFF word list for generated passwords
+    #[serde(default)]
+    pub password_short_words: bool,
+}
+
+impl Default for KeyProfile {
+    /// The parameters that [crate::Oca::user_new] uses by default
+    fn default() -> Self {
+        KeyProfile {
+            name: String::default(),
+            cipher_suite: "cv25519".to_string(),
+            encryption_subkey: true,
+            signing_subkey: true,
+            authentication_subkey: false,
+            expiration_days: None,
+            password: true,
+            password_words: 5,
+            password_short_words: false,
+        }
+    }
+}
+
+/// A named keylist, with its own filter and signature URI (see
+/// [crate::Oca::export_keylists])
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeylistConfig {
+    /// Name of the list (also the name of its export subdirectory)
+    pub name: String,
+
+    /// The https address from which the signature file of the list will be
+    /// retrievable
+    pub signature_uri: String,
+
+    #[serde(default)]
+    pub filter: KeylistFilter,
+}
+
+/// Which of the CA-certified User IDs a keylist contains.
+///
+/// All configured criteria must match, an empty filter matches all
+/// CA-certified User IDs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeylistFilter {
+    /// Only email addresses in these domains
+    #[serde(default)]
+    pub domains: Vec<String>,
+
+    /// Only these email addresses (e.g. the members of a team)
+    #[serde(default)]
+    pub emails: Vec<String>,
+
+    /// Leave out certs that are marked as inactive
+    #[serde(default)]
+    pub exclude_inactive: bool,
+
+    /// Only the certs of the users in this group (see
+    /// [crate::Oca::group_new])
+    #[serde(default)]
+    pub group: Option<String>,
+
+    /// Also list role User IDs (without an email address, e.g. of service
+    /// accounts), as entries without email. Ignored if the list is
+    /// restricted to domains or emails.
+    #[serde(default)]
+    pub include_roles: bool,
+}
+
+/// A notification email for the users in a group (see
+/// [crate::Oca::group_notification])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupNotification {
+    /// The CA-certified email addresses of the group members' active certs
+    pub recipients: Vec<String>,
+
+    pub mail: MimeEntity,
+}
+
+/// A MIME entity: header fields and a body (see [crate::Oca::mail_text]).
+///
+/// Lines are terminated with CRLF, the entity can be used as the body of
+/// an email (or as a part in a multipart message).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MimeEntity {
+    /// Header fields, as (name, value)
+    pub headers: Vec<(String, String)>,
+
+    pub body: String,
+}
+
+impl MimeEntity {
+    /// The value of the header field `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl fmt::Display for MimeEntity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.headers {
+            write!(f, "{name}: {value}\r\n")?;
+        }
+        write!(f, "\r\n{}", self.body)
+    }
+}
+
+/// Settings for replacing the CA key (see [crate::Oca::ca_rekey])
+#[derive(Clone)]
+pub struct CaRekeyParams {
+    /// Descriptive name in the User ID of the new CA key
+    pub name: Option<String>,
+
+    pub cipher_suite: Option<pgp::CipherSuite>,
+
+    /// The new CA key expires after this many days (None: no expiration)
+    pub validity_days: Option<u64>,
+
+    /// Validity of the certifications that the new CA key issues for
+    /// user certs, in days
+    pub certification_validity_days: u64,
+}
+
+/// Outcome of replacing the CA key (see [crate::Oca::ca_rekey])
+pub struct CaRekeyReport {
+    pub old_fingerprint: String,
+    pub new_fingerprint: String,
+
+    /// Fingerprints of user certs that have been certified by the new CA key
+    /// (on a split-mode front instance: for which certification requests
+    /// have been queued)
+    pub recertified: Vec<String>,
+
+    /// Current user certs that had tsigned the old CA key. Their users need
+    /// to tsign the new CA key.
+    pub tsig_needed: Vec<models::Cert>,
+}
+
+/// State of a key replacement request (see [crate::Oca::key_replacement_submit])
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyReplacementStatus {
+    /// Waiting for approval by an admin
+    Pending,
+
+    /// The replacement cert has been stored for the user
+    Approved,
+
+    Rejected,
+}
+
+impl KeyReplacementStatus {
+    pub const ALL: [KeyReplacementStatus; 3] = [
+        KeyReplacementStatus::Pending,
+        KeyReplacementStatus::Approved,
+        KeyReplacementStatus::Rejected,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyReplacementStatus::Pending => "pending",
+            KeyReplacementStatus::Approved => "approved",
+            KeyReplacementStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl std::str::FromStr for KeyReplacementStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KeyReplacementStatus::ALL
+            .iter()
+            .copied()
+            .find(|p| p.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown key replacement status '{s}'"))
+    }
+}
+
+impl fmt::Display for KeyReplacementStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A change to the CA database that a semi-trusted frontend (restd in
+/// approval mode) proposed, and that is applied only after a CA admin
+/// approves it (see [crate::Oca::proposal_submit])
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "kebab-case")]
+pub enum ProposedChange {
+    /// Store a new cert, or an update of a stored cert (a new cert is
+    /// certified for `emails`, for `days`)
+    CertImport {
+        fingerprint: String,
+        cert: String,
+        name: Option<String>,
+        emails: Vec<String>,
+        revocations: Vec<String>,
+        days: Option<u64>,
+    },
+
+    /// Mark a cert as inactive
+    CertDeactivate { fingerprint: String },
+
+    /// Mark a cert as delisted
+    CertDelist { fingerprint: String },
+
+    /// Remove a revocation certificate that has not been applied
+    RevocationDelete { hash: String },
+
+    /// Store an (armored) revocation certificate for a user cert (it is
+    /// not applied)
+    RevocationAdd { revocation: String },
+}
+
+impl fmt::Display for ProposedChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProposedChange::CertImport {
+                fingerprint,
+                name,
+                emails,
+                ..
+            } => {
+                write!(f, "store cert {fingerprint}")?;
+                if let Some(name) = name {
+                    write!(f, " for '{name}'")?;
+                }
+                if !emails.is_empty() {
+                    write!(f, " ({})", emails.join(", "))?;
+                }
+                Ok(())
+            }
+            ProposedChange::CertDeactivate { fingerprint } => {
+                write!(f, "deactivate cert {fingerprint}")
+            }
+            ProposedChange::CertDelist { fingerprint } => write!(f, "delist cert {fingerprint}"),
+            ProposedChange::RevocationDelete { hash } => write!(f, "delete revocation {hash}"),
+            ProposedChange::RevocationAdd { .. } => write!(f, "store revocation certificate"),
+        }
+    }
+}
+
+/// State of a [Proposal]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProposalStatus {
+    /// Waiting for a decision by an admin
+    Pending,
+
+    /// The change has been applied
+    Approved,
+
+    Rejected,
+}
+
+impl ProposalStatus {
+    pub const ALL: [ProposalStatus; 3] = [
+        ProposalStatus::Pending,
+        ProposalStatus::Approved,
+        ProposalStatus::Rejected,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProposalStatus::Pending => "pending",
+            ProposalStatus::Approved => "approved",
+            ProposalStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl std::str::FromStr for ProposalStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ProposalStatus::ALL
+            .iter()
+            .copied()
+            .find(|p| p.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown proposal status '{s}'"))
+    }
+}
+
+impl fmt::Display for ProposalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A proposed change, and the decision on it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proposal {
+    pub id: i32,
+    pub created: DateTime<Utc>,
+    pub change: ProposedChange,
+    pub status: ProposalStatus,
+    pub decided: Option<DateTime<Utc>>,
+
+    /// Reason for a rejection
+    pub reason: Option<String>,
+}
+
+/// Kind of a blocklist entry (see [crate::Oca::blocklist_add])
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlocklistKind {
+    /// A fingerprint of a primary key or subkey
+    Fingerprint,
+
+    /// The domain of email addresses ("example.org"), or all of its
+    /// subdomains ("*.example.org")
+    Domain,
+}
+
+impl BlocklistKind {
+    pub const ALL: [BlocklistKind; 2] = [BlocklistKind::Fingerprint, BlocklistKind::Domain];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlocklistKind::Fingerprint => "fingerprint",
+            BlocklistKind::Domain => "domain",
+        }
+    }
+}
+
+impl std::str::FromStr for BlocklistKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BlocklistKind::ALL
+            .iter()
+            .copied()
+            .find(|k| k.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown blocklist kind '{s}'"))
+    }
+}
+
+impl fmt::Display for BlocklistKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A fingerprint or email address that is blocked by an entry of the
+/// blocklist
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlocklistMatch {
+    /// The blocked fingerprint, or email address
+    pub subject: String,
+
+    pub kind: BlocklistKind,
+
+    /// The pattern of the blocklist entry
+    pub pattern: String,
+
+    pub reason: Option<String>,
+}
+
+impl fmt::Display for BlocklistMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            BlocklistKind::Fingerprint => write!(f, "The key {} is blocked", self.subject)?,
+            BlocklistKind::Domain => write!(
+                f,
+                "The email address {} is in the blocked domain '{}'",
+                self.subject, self.pattern
+            )?,
+        }
+        if let Some(reason) = &self.reason {
+            write!(f, " ({reason})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Error for certs or email addresses that are rejected because of
+/// [BlocklistMatch]es (can be recovered from an `anyhow::Error` via
+/// `downcast_ref`)
+#[derive(Debug)]
+pub struct BlocklistError {
+    pub matches: Vec<BlocklistMatch>,
+}
+
+impl fmt::Display for BlocklistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Blocked by the blocklist of this CA:")?;
+        for m in &self.matches {
+            write!(f, "\n- {m}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BlocklistError {}
+
+/// Kind of a signature in the certification transparency log (see
+/// [crate::Oca::ct_log])
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CtLogKind {
+    /// A certification of a User ID on a user cert
+    Certification,
+
+    /// A revocation of a certification on a user cert
+    CertificationRevocation,
+
+    /// A trust signature on a remote CA cert (for a bridge)
+    TrustSignature,
+
+    /// A revocation of the trust signature of a bridge
+    BridgeRevocation,
+}
+
+impl CtLogKind {
+    pub const ALL: [CtLogKind; 4] = [
+        CtLogKind::Certification,
+        CtLogKind::CertificationRevocation,
+        CtLogKind::TrustSignature,
+        CtLogKind::BridgeRevocation,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CtLogKind::Certification => "certification",
+            CtLogKind::CertificationRevocation => "certification_revocation",
+            CtLogKind::TrustSignature => "trust_signature",
+            CtLogKind::BridgeRevocation => "bridge_revocation",
+        }
+    }
+}
+
+impl std::str::FromStr for CtLogKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CtLogKind::ALL
+            .iter()
+            .copied()
+            .find(|k| k.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown ct log entry kind '{s}'"))
+    }
+}
+
+impl fmt::Display for CtLogKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// An entry of the certification transparency log.
+///
+/// The exported log consists of one entry per line, as JSON.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CtLogEntry {
+    /// Sequence number (the first entry has sequence number 1)
+    pub seq: u64,
+    pub created: DateTime<Utc>,
+    pub kind: CtLogKind,
+
+    /// Fingerprint of the cert that the signature was made on
+    pub fingerprint: String,
+
+    /// The signature packet, base64 encoded
+    pub signature: String,
+
+    /// Hash of the previous entry (all zeros for the first entry)
+    pub prev_hash: String,
+
+    /// Hash of this entry (SHA-256 over the other fields, see
+    /// [crate::Oca::ct_log])
+    pub hash: String,
+}
+
+/// Result of a successful verification of a certification transparency log
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CtLogReport {
+    /// Number of entries in the log
+    pub entries: u64,
+
+    /// Hash of the last entry (all zeros for an empty log).
+    ///
+    /// A copy of the log that was published or archived earlier is a
+    /// prefix of the current log, if its head hash matches the hash of
+    /// the entry with the same sequence number.
+    pub head: String,
+}
+
+/// A long operation that reports its progress (see
+/// [crate::Oca::set_progress_handler])
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressOperation {
+    /// Updating certs from a public source (see [crate::Oca::sync_certs])
+    Sync(SyncSource),
+
+    /// Exporting certs into a WKD directory structure
+    WkdExport,
+
+    /// Renewing CA certifications
+    Recertification,
+}
+
+impl std::fmt::Display for ProgressOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgressOperation::Sync(source) => write!(f, "{} update", source.name()),
+            ProgressOperation::WkdExport => write!(f, "WKD export"),
+            ProgressOperation::Recertification => write!(f, "re-certification"),
+        }
+    }
+}
+
+/// Progress of a long operation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub operation: ProgressOperation,
+
+    /// Number of processed items (0 when the operation starts)
+    pub done: usize,
+
+    /// Number of items that the operation processes
+    pub total: usize,
+
+    /// Fingerprint of the cert that is processed next (None when the
+    /// operation is finished)
+    pub item: Option<String>,
+}
+
+/// A public source that certs are updated from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncSource {
+    /// The WKD of the domains of the email addresses of a cert
+    Wkd,
+
+    /// The configured keyservers (see [crate::Oca::set_keyserver_config])
+    Keyserver,
+}
+
+impl SyncSource {
+    pub const ALL: [SyncSource; 2] = [SyncSource::Wkd, SyncSource::Keyserver];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncSource::Wkd => "wkd",
+            SyncSource::Keyserver => "keyserver",
+        }
+    }
+}
+
+impl std::str::FromStr for SyncSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SyncSource::ALL
+            .iter()
+            .copied()
+            .find(|k| k.name() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown sync source '{s}'"))
+    }
+}
+
+impl fmt::Display for SyncSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Options for updating certs from a public source (see
+/// [crate::Oca::sync_certs])
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyncOptions {
+    /// Skip certs that were looked up more recently than this
+    pub min_interval: std::time::Duration,
+
+    /// Look up at most this many certs (the ones that were looked up
+    /// longest ago come first, so that successive runs cover all certs)
+    pub limit: Option<usize>,
+
+    /// Pause between two lookups
+    pub delay: std::time::Duration,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        SyncOptions {
+            min_interval: std::time::Duration::from_secs(60 * 60),
+            limit: None,
+            delay: std::time::Duration::ZERO,
+        }
+    }
+}
+
+/// Outcome of a run of [crate::Oca::sync_certs]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Certs for which updates were received
+    pub updated: usize,
+
+    /// Certs that were looked up, without changes
+    pub unchanged: usize,
+
+    /// Certs for which the lookup failed
+    pub failed: usize,
+
+    /// Certs that were skipped, because they were looked up recently (or
+    /// because of the limit)
+    pub skipped: usize,
+}
+
+/// The state of the updates of a cert from a public source
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub fingerprint: String,
+    pub source: SyncSource,
+
+    pub last_attempt: DateTime<Utc>,
+    pub last_success: Option<DateTime<Utc>>,
+
+    /// Error of the last attempt, if it failed
+    pub last_error: Option<String>,
+
+    /// Time of the first failed attempt since the last successful one
+    pub failing_since: Option<DateTime<Utc>>,
+}
+
+/// The kinds of events in an expiry timeline
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    /// The CA cert expires
+    CaCert,
+
+    /// A user cert expires
+    UserCert,
+
+    /// The CA's certifications on a User ID of a user cert expire
+    Certification,
+
+    /// The CA's trust signature on a User ID of a bridged CA's cert expires
+    BridgeTsig,
+}
+
+impl fmt::Display for TimelineEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TimelineEventKind::CaCert => "CA cert expires",
+            TimelineEventKind::UserCert => "user cert expires",
+            TimelineEventKind::Certification => "CA certification expires",
+            TimelineEventKind::BridgeTsig => "bridge tsig expires",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// An upcoming expiration (see [crate::Oca::expiry_timeline])
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    pub time: DateTime<Utc>,
+    pub kind: TimelineEventKind,
+
+    /// Fingerprint of the cert that expires, or that carries the expiring
+    /// signature
+    pub fingerprint: String,
+
+    /// Name of the user, or email of the bridge
+    pub label: Option<String>,
+
+    /// The User ID of an expiring certification or trust signature
+    pub user_id: Option<String>,
+}
+
+/// Mapping of the CA's user identities and keys, for use by an X.509 CA that
+/// issues matching S/MIME certificates (see [crate::Oca::export_x509_mapping]).
+///
+/// This format is experimental.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct X509Mapping {
+    /// Schema version of this document
+    pub version: u32,
+
+    pub created: DateTime<Utc>,
+
+    /// Domain of the CA
+    pub domain: String,
+
+    /// Fingerprint of the CA key
+    pub ca_fingerprint: String,
+
+    /// One entry per currently valid user cert with certified email addresses
+    pub entries: Vec<X509MappingEntry>,
+}
+
+/// The identity and keys of one user cert, in an [X509Mapping]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct X509MappingEntry {
+    /// Fingerprint of the OpenPGP cert
+    pub fingerprint: String,
+
+    /// Name of the user
+    pub name: Option<String>,
+
+    /// Suggested subject common name (the user's name, or the first email)
+    pub common_name: String,
+
+    /// Email addresses that the CA certified (for the subjectAltName)
+    pub emails: Vec<String>,
+
+    /// Fingerprints of the live signing subkeys
+    pub signing_keys: Vec<String>,
+
+    /// Fingerprints of the live encryption subkeys
+    pub encryption_keys: Vec<String>,
+
+    /// Serial number of the X.509 certificate that was recorded for this
+    /// cert (see [crate::Oca::x509_serial_set])
+    pub serial: Option<String>,
+
+    /// The certified email addresses have changed since the X.509
+    /// certificate was recorded: it should be re-issued
+    pub identity_changed: bool,
 }
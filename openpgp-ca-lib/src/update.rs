@@ -6,16 +6,22 @@
 // SPDX-FileCopyrightText: 2019-2022 Heiko Schaefer <heiko@schaefer.name>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use anyhow::Result;
-use sequoia_net::wkd;
-use sequoia_net::Policy;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::thread;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use sequoia_openpgp::serialize::SerializeInto;
-use sequoia_openpgp::{Fingerprint, KeyID};
+use sequoia_openpgp::{Fingerprint, Packet};
 use tokio::runtime::Runtime;
 
 use crate::db::models;
-use crate::pgp;
-use crate::Oca;
+use crate::types::{
+    KeyserverConfig, ProgressOperation, SyncOptions, SyncReport, SyncSource, SyncStatus,
+};
+use crate::{pgp, progress, tor, Oca};
+
+const PREF_KEYSERVERS: &str = "keyservers";
 
 /// Update a cert in the OpenPGP CA database via wkd.
 ///
@@ -23,35 +29,50 @@ use crate::Oca;
 /// all certs retrieved in that way, if they have a  matching fingerprint,
 /// the cert data from wkd is merged into the existing cert (failed merges are
 /// ignored silently).
+///
+/// Fails if none of the lookups succeeded.
+///
+/// If the CA is configured to use Tor, the lookups are routed through Tor.
 pub fn update_from_wkd(oca: &Oca, cert: &models::Cert) -> Result<bool> {
     let rt = Runtime::new()?;
 
     let emails = oca.emails_get(cert)?;
 
     // Collect all updates for 'cert' in 'merge'
-    let orig = pgp::to_cert(cert.pub_cert.as_bytes())?;
+    let orig = oca.storage.cert_parsed(cert)?;
     let mut merged = orig.clone();
 
-    for email in emails {
-        let res = rt.block_on(async move { wkd::get(&email.addr).await });
-
-        // silently ignore errors on wkd lookup
-        if let Ok(certs) = res {
-            for c in certs {
-                if c.fingerprint() == Fingerprint::from_hex(&cert.fingerprint)? {
-                    // If 'c' can't be merged, silently ignore the error that
-                    // sequoia returns
-                    if let Ok(m) = merged.clone().merge_public(c) {
-                        merged = m;
+    let mut error = None;
+    let mut found = false;
+
+    for email in &emails {
+        match tor::wkd_get(oca, &rt, &email.addr) {
+            Ok(certs) => {
+                found = true;
+
+                for c in certs {
+                    if c.fingerprint() == Fingerprint::from_hex(&cert.fingerprint)? {
+                        // If 'c' can't be merged, silently ignore the error that
+                        // sequoia returns
+                        if let Ok(m) = merged.clone().merge_public(c) {
+                            merged = m;
+                        }
                     }
                 }
             }
+            Err(e) => error = Some(e),
         }
     }
 
+    // Errors on individual wkd lookups are ignored, as long as one lookup
+    // succeeds
+    if let (false, Some(e)) = (found, error) {
+        return Err(e);
+    }
+
     if merged != orig {
         // merge updates into DB
-        oca.storage.cert_update(&merged.to_vec()?)?;
+        oca.storage.cert_update(&merged.to_vec()?, "wkd", false)?;
 
         Ok(true)
     } else {
@@ -59,33 +80,281 @@ pub fn update_from_wkd(oca: &Oca, cert: &models::Cert) -> Result<bool> {
     }
 }
 
-/// Update a cert in the OpenPGP CA database from the "Hagrid" keyserver at
-/// `keys.openpgp.org`
+pub(crate) fn keyserver_config(oca: &Oca) -> Result<KeyserverConfig> {
+    match oca.storage.pref(PREF_KEYSERVERS)? {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(KeyserverConfig::default()),
+    }
+}
+
+pub(crate) fn set_keyserver_config(oca: &Oca, config: &KeyserverConfig) -> Result<()> {
+    if config.keyservers.is_empty() {
+        return Err(anyhow::anyhow!("At least one keyserver is required"));
+    }
+    if config.quorum == 0 || config.quorum > config.keyservers.len() {
+        return Err(anyhow::anyhow!(
+            "Illegal quorum {} (must be between 1 and the number of keyservers, {})",
+            config.quorum,
+            config.keyservers.len()
+        ));
+    }
+
+    for keyserver in &config.keyservers {
+        sequoia_net::KeyServer::new(sequoia_net::Policy::Encrypted, keyserver).with_context(
+            || format!("Illegal keyserver '{keyserver}' (expected e.g. hkps://keys.openpgp.org)"),
+        )?;
+    }
+
+    let json = serde_json::to_string(config)?;
+    oca.storage.pref_set(PREF_KEYSERVERS, &json)
+}
+
+/// A short description of the packet `p` (e.g. "UserID", or
+/// "Signature (PositiveCertification)")
+fn packet_kind(p: &Packet) -> String {
+    match p {
+        Packet::Signature(sig) => format!("{} ({})", p.tag(), sig.typ()),
+        p => p.tag().to_string(),
+    }
+}
+
+/// Update a cert in the OpenPGP CA database from the keyservers that are
+/// configured for this CA (see [KeyserverConfig]).
+///
+/// The cert is looked up on all keyservers. Errors on individual lookups
+/// are ignored, as long as at least `quorum` keyservers returned the cert.
+/// Packets that are not part of our copy of the cert yet are merged in if
+/// at least `quorum` keyservers returned them. For each merged packet, the
+/// keyservers that supplied it are recorded.
+///
+/// If the CA is configured to use Tor, the lookups are routed through Tor.
 ///
 /// Returns "true" if updated data was received, false if not.
-pub fn update_from_hagrid(oca: &Oca, cert: &models::Cert) -> Result<bool> {
+pub fn update_from_keyservers(oca: &Oca, cert: &models::Cert) -> Result<bool> {
+    let config = keyserver_config(oca)?;
+
     let fp = (cert.fingerprint).parse::<Fingerprint>()?;
+    let orig = oca.storage.cert_parsed(cert)?;
 
-    let c = pgp::to_cert(cert.pub_cert.as_bytes())?;
+    let known = orig
+        .clone()
+        .into_packets2()
+        .map(|p| pgp::packet_digest(&p))
+        .collect::<Result<HashSet<_>>>()?;
 
-    // get key from hagrid
-    let mut hagrid = sequoia_net::KeyServer::keys_openpgp_org(Policy::Encrypted)?;
+    // New packets by digest, with the keyservers that returned them
+    let mut new: BTreeMap<String, (Packet, Vec<&str>)> = BTreeMap::new();
 
     let rt = Runtime::new()?;
-    let update = rt.block_on(async move { hagrid.get(&KeyID::from(fp)).await })?;
 
-    // Merge new certificate information into existing cert.
-    // (Silently ignore potential errors from merge_public())
-    if let Ok(merged) = c.clone().merge_public(update) {
-        if merged != c {
-            // merge updates into DB
-            oca.storage.cert_update(&merged.to_vec()?)?;
+    let mut answered = 0;
+    let mut error = None;
+
+    for keyserver in &config.keyservers {
+        let update = match tor::keyserver_get(oca, &rt, keyserver, &fp) {
+            Ok(c) if c.fingerprint() == fp => c,
+            Ok(c) => {
+                error = Some(anyhow::anyhow!(
+                    "{} returned cert {} instead of {}",
+                    keyserver,
+                    c.fingerprint(),
+                    fp
+                ));
+                continue;
+            }
+            Err(e) => {
+                error = Some(e.context(format!("Lookup on {keyserver} failed")));
+                continue;
+            }
+        };
+
+        answered += 1;
+
+        for p in update.into_packets2() {
+            let digest = pgp::packet_digest(&p)?;
+            if known.contains(&digest) {
+                continue;
+            }
+
+            let (_, servers) = new.entry(digest).or_insert_with(|| (p, vec![]));
+            if !servers.contains(&keyserver.as_str()) {
+                servers.push(keyserver.as_str());
+            }
+        }
+    }
+
+    if answered < config.quorum {
+        let e = error.unwrap_or_else(|| anyhow::anyhow!("No keyserver returned the cert"));
+        return Err(e.context(format!(
+            "Only {} of {} keyservers returned the cert (quorum: {})",
+            answered,
+            config.keyservers.len(),
+            config.quorum
+        )));
+    }
+
+    let accepted: Vec<_> = new
+        .into_iter()
+        .filter(|(_, (_, servers))| servers.len() >= config.quorum)
+        .collect();
+
+    // Merge new packets into the existing cert.
+    // (Silently ignore potential errors from insert_packets())
+    let packets = accepted.iter().map(|(_, (p, _))| p.clone());
+    let merged = match orig.clone().insert_packets(packets) {
+        Ok(merged) => merged,
+        Err(_) => return Ok(false),
+    };
+
+    if merged == orig {
+        // No update was received
+        return Ok(false);
+    }
+
+    let provenance: Vec<_> = accepted
+        .iter()
+        .flat_map(|(digest, (p, servers))| {
+            servers
+                .iter()
+                .map(move |ks| (ks.to_string(), digest.clone(), packet_kind(p)))
+        })
+        .collect();
+
+    // merge updates into DB, together with the provenance of the new packets
+    let merged = merged.to_vec()?;
+    oca.storage.transaction(&mut || {
+        oca.storage.cert_update(&merged, "keyserver", false)?;
+        oca.storage.keyserver_packets_add(cert, &provenance)
+    })?;
+
+    // An update for this cert was received
+    Ok(true)
+}
+
+/// Update the certs in the OpenPGP CA database from `source`.
+///
+/// The outcome of each lookup is recorded in the sync state of the cert.
+/// Certs that were looked up within `opts.min_interval` are skipped, the
+/// others are looked up in the order of their last lookup (certs that were
+/// never looked up come first). So a run that was interrupted, or limited
+/// via `opts.limit`, is resumed by the next run.
+pub(crate) fn sync(oca: &Oca, source: SyncSource, opts: &SyncOptions) -> Result<SyncReport> {
+    tor::check_available(oca)?;
+
+    let last: HashMap<i32, _> = oca
+        .storage
+        .sync_states(source.name())?
+        .into_iter()
+        .map(|s| (s.cert_id, s.last_attempt))
+        .collect();
+
+    let mut report = SyncReport::default();
+
+    let min_interval = chrono::Duration::from_std(opts.min_interval)?;
+    let now = Utc::now().naive_utc();
+
+    let mut due = vec![];
+    for c in oca.user_certs_get_all()? {
+        match last.get(&c.id) {
+            Some(&last) if now - last < min_interval => report.skipped += 1,
+            last => due.push((last.copied(), c)),
+        }
+    }
+
+    // Least recently looked up first
+    due.sort_by_key(|(last, _)| *last);
+    if let Some(limit) = opts.limit {
+        if due.len() > limit {
+            report.skipped += due.len() - limit;
+            due.truncate(limit);
+        }
+    }
+
+    let operation = ProgressOperation::Sync(source);
+
+    for (i, (_, c)) in due.iter().enumerate() {
+        progress::report(oca, operation, i, due.len(), Some(&c.fingerprint));
+
+        if i > 0 && !opts.delay.is_zero() {
+            thread::sleep(opts.delay);
+        }
+
+        let res = match source {
+            SyncSource::Wkd => update_from_wkd(oca, c),
+            SyncSource::Keyserver => update_from_keyservers(oca, c),
+        };
+
+        match res {
+            Ok(true) => {
+                tracing::info!(fingerprint = %c.fingerprint, "Got update for cert");
+                report.updated += 1;
+                oca.storage.sync_state_record(c, source.name(), None)?;
+            }
+            Ok(false) => {
+                tracing::info!(fingerprint = %c.fingerprint, "No changes for cert");
+                report.unchanged += 1;
+                oca.storage.sync_state_record(c, source.name(), None)?;
+            }
+            Err(e) => {
+                tracing::warn!(fingerprint = %c.fingerprint, "Failed to update cert: {}", e);
+                report.failed += 1;
+                oca.storage
+                    .sync_state_record(c, source.name(), Some(&e.to_string()))?;
+            }
+        }
+    }
+
+    progress::report(oca, operation, due.len(), due.len(), None);
+
+    Ok(report)
+}
+
+/// The sync state of all certs for `source`
+pub(crate) fn status(oca: &Oca, source: SyncSource) -> Result<Vec<SyncStatus>> {
+    let fps: HashMap<i32, String> = oca
+        .user_certs_get_all()?
+        .into_iter()
+        .map(|c| (c.id, c.fingerprint))
+        .collect();
+
+    let utc = |t| DateTime::from_naive_utc_and_offset(t, Utc);
+
+    Ok(oca
+        .storage
+        .sync_states(source.name())?
+        .into_iter()
+        .filter_map(|state| {
+            fps.get(&state.cert_id).map(|fp| SyncStatus {
+                fingerprint: fp.clone(),
+                source,
+                last_attempt: utc(state.last_attempt),
+                last_success: state.last_success.map(utc),
+                last_error: state.last_error,
+                failing_since: state.failing_since.map(utc),
+            })
+        })
+        .collect())
+}
+
+/// The sync state of all certs that have been failing to update from
+/// `source` (or from any source) for at least `min_duration`
+pub(crate) fn failing(
+    oca: &Oca,
+    source: Option<SyncSource>,
+    min_duration: std::time::Duration,
+) -> Result<Vec<SyncStatus>> {
+    let cutoff = Utc::now() - chrono::Duration::from_std(min_duration)?;
 
-            // An update for this cert was received
-            return Ok(true);
+    let mut res = vec![];
+    for s in SyncSource::ALL {
+        if source.is_none_or(|source| source == s) {
+            res.extend(
+                status(oca, s)?
+                    .into_iter()
+                    .filter(|st| st.failing_since.is_some_and(|t| t <= cutoff)),
+            );
         }
     }
 
-    // No update was received
-    Ok(false)
+    Ok(res)
 }
@@ -0,0 +1,111 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Stripping and (re-)armoring of OpenPGP data, for downstream tooling
+//! (e.g. custom importers) that handles certs the way OpenPGP CA does.
+//! Also, storing private key material in files.
+//!
+//! The functions in this module are a stable part of the API of
+//! openpgp-ca-lib: their signatures and behavior only change in
+//! semver-incompatible releases. The helpers in [crate::pgp], in contrast,
+//! exist for the internal use of OpenPGP CA, and may change at any time.
+
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use sequoia_openpgp::cert::prelude::ComponentAmalgamation;
+use sequoia_openpgp::packet::{Signature, UserID};
+use sequoia_openpgp::policy::Policy;
+use sequoia_openpgp::Cert;
+
+use crate::pgp;
+
+/// The armored ("public key") representation of `cert`
+pub fn cert_to_armored(cert: &Cert) -> Result<String> {
+    pgp::cert_to_armored(cert)
+}
+
+/// All certs in `data`, which may be a binary or armored keyring (or a
+/// single cert)
+pub fn armored_keyring_to_certs(data: &[u8]) -> Result<Vec<Cert>> {
+    pgp::armored_keyring_to_certs(&data)
+}
+
+/// The armored representation of the revocation signature `sig`, with the
+/// optional armor `headers`.
+///
+/// The armor kind is "public key", which GnuPG expects for revocation
+/// certificates. Fails for non-exportable signatures.
+pub fn revoc_to_armored(sig: &Signature, headers: Option<Vec<(String, String)>>) -> Result<String> {
+    pgp::revoc_to_armored(sig, headers)
+}
+
+/// Normalize a (possibly pretty-printed) fingerprint, e.g.
+/// "1234 5678 ...", to uppercase hex without spaces.
+///
+/// Fails if `fp` is not a fingerprint.
+pub fn normalize_fp(fp: &str) -> Result<String> {
+    pgp::normalize_fp(fp)
+}
+
+/// The valid certifications by `certifier` on the User ID `uid` of `cert`.
+///
+/// Certifications that `certifier` has retracted (with a later
+/// certification revocation) are not returned. Certifications (and
+/// certifier keys) that `policy` rejects are ignored.
+pub fn valid_certifications_by(
+    uid: &ComponentAmalgamation<UserID>,
+    cert: &Cert,
+    certifier: Cert,
+    policy: &dyn Policy,
+) -> Vec<Signature> {
+    pgp::valid_certifications_by(uid, cert, certifier, policy)
+}
+
+/// Strip `cert` down to the User IDs that `certifier` has validly
+/// certified, with only the self-signatures and the certifications by
+/// `certifier`, and only the currently valid subkeys.
+///
+/// This is the form in which OpenPGP CA publishes minimized user certs
+/// (e.g. via WKD).
+pub fn strip_to_certifications_by(
+    cert: &Cert,
+    certifier: &Cert,
+    policy: &dyn Policy,
+) -> Result<Cert> {
+    let mut certified: Vec<UserID> = vec![];
+    let mut certifications = vec![];
+
+    for uid in cert.userids() {
+        let sigs = valid_certifications_by(&uid, cert, certifier.clone(), policy);
+        if !sigs.is_empty() {
+            certified.push(uid.userid().clone());
+            certifications.extend(sigs);
+        }
+    }
+
+    pgp::minimize_cert(cert, |uid| certified.contains(uid), &certifications, true)
+}
+
+/// Create the new file `path`, which only the current user can read (e.g.
+/// for storing a private key).
+///
+/// Fails if `path` already exists.
+pub fn create_private_file(path: &Path) -> Result<File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options
+        .open(path)
+        .with_context(|| format!("Can't create {path:?}"))
+}
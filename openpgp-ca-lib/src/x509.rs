@@ -0,0 +1,191 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Experimental bridging to an X.509 (S/MIME) CA.
+//!
+//! OpenPGP CA doesn't issue X.509 certificates itself. Instead, it exports a
+//! mapping of its users' identities and keys, which an X.509 CA can use to
+//! issue matching S/MIME certificates. The serial numbers of the issued
+//! certificates are recorded in tags on the OpenPGP certs, along with the
+//! email addresses that they were issued for. This way, the mapping shows
+//! when the identity of a user has changed since their S/MIME certificate
+//! was issued.
+
+use anyhow::Result;
+use chrono::{SubsecRound, Utc};
+use sequoia_openpgp::types::RevocationStatus;
+
+use crate::db::models;
+use crate::types::{X509Mapping, X509MappingEntry};
+use crate::{pgp, Oca};
+
+// Version identifier of the mapping format, to be incremented when the JSON
+// format changes in an incompatible way.
+const X509_MAPPING_VERSION: u32 = 1;
+
+/// Tag on a cert: serial number of the X.509 certificate for the cert
+const TAG_X509_SERIAL: &str = "x509-serial";
+
+/// Tag on a cert: the email addresses that the X.509 certificate was issued
+/// for (sorted, comma separated)
+const TAG_X509_EMAILS: &str = "x509-emails";
+
+/// Normalize an X.509 serial number ("01:a2:..." or "01A2...") to uppercase
+/// hex without separators
+fn normalize_serial(serial: &str) -> Result<String> {
+    let hex: String = serial
+        .chars()
+        .filter(|c| *c != ':' && !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+
+    if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow::anyhow!(
+            "'{}' is not a valid X.509 serial number (expected hex digits)",
+            serial
+        ));
+    }
+
+    Ok(hex)
+}
+
+/// The email addresses of the User IDs of `cert` that are certified by the
+/// CA (sorted)
+fn certified_emails(oca: &Oca, cert: &models::Cert) -> Result<Vec<String>> {
+    let mut emails: Vec<String> = oca
+        .cert_check_ca_sig(cert)?
+        .certified
+        .iter()
+        .filter_map(|uid| uid.email2().ok().flatten().map(str::to_string))
+        .collect();
+
+    emails.sort();
+    emails.dedup();
+
+    Ok(emails)
+}
+
+pub(crate) fn serial_set(oca: &Oca, fp: &str, serial: Option<&str>) -> Result<()> {
+    let fp = pgp::normalize_fp(fp)?;
+    let cert = oca
+        .storage
+        .cert_by_fp(&fp)?
+        .ok_or_else(|| anyhow::anyhow!("No cert with fingerprint {} found", fp))?;
+
+    match serial {
+        Some(serial) => {
+            let serial = normalize_serial(serial)?;
+
+            let emails = certified_emails(oca, &cert)?;
+            if emails.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "The cert {} has no email address that is certified by the CA",
+                    fp
+                ));
+            }
+
+            let emails = emails.join(",");
+
+            // The serial and its emails are only ever stored together
+            oca.storage.cert_tags_set(
+                &cert.fingerprint,
+                &[
+                    (TAG_X509_SERIAL, Some(serial.as_str())),
+                    (TAG_X509_EMAILS, Some(emails.as_str())),
+                ],
+            )
+        }
+        None => oca.storage.cert_tags_set(
+            &cert.fingerprint,
+            &[(TAG_X509_SERIAL, None), (TAG_X509_EMAILS, None)],
+        ),
+    }
+}
+
+pub(crate) fn serial(oca: &Oca, cert: &models::Cert) -> Result<Option<String>> {
+    Ok(oca
+        .storage
+        .tags_by_cert(cert)?
+        .into_iter()
+        .find(|t| t.name == TAG_X509_SERIAL)
+        .map(|t| t.value))
+}
+
+/// The mapping entry for `db_cert`, or None if the cert is not currently
+/// valid, or has no certified email address
+fn mapping_entry(oca: &Oca, db_cert: &models::Cert) -> Result<Option<X509MappingEntry>> {
+    let cert = oca.storage.cert_parsed(db_cert)?;
+    let policy = oca.policy()?;
+
+    let valid = match cert.with_policy(&policy, None) {
+        Ok(valid) => valid,
+        Err(_) => return Ok(None),
+    };
+    if valid.alive().is_err() {
+        return Ok(None);
+    }
+    if let RevocationStatus::Revoked(_) = valid.revocation_status() {
+        return Ok(None);
+    }
+
+    let emails = certified_emails(oca, db_cert)?;
+    if emails.is_empty() {
+        return Ok(None);
+    }
+
+    let name = oca.storage.user_by_cert(db_cert)?.and_then(|u| u.name);
+    let common_name = name.clone().unwrap_or_else(|| emails[0].clone());
+
+    let subkeys = || valid.keys().subkeys().alive().revoked(false);
+    let signing_keys = subkeys()
+        .for_signing()
+        .map(|ka| ka.key().fingerprint().to_hex())
+        .collect();
+    let encryption_keys = subkeys()
+        .for_transport_encryption()
+        .for_storage_encryption()
+        .map(|ka| ka.key().fingerprint().to_hex())
+        .collect();
+
+    let tags = oca.storage.tags_by_cert(db_cert)?;
+    let tag = |name: &str| {
+        tags.iter()
+            .find(|t| t.name == name)
+            .map(|t| t.value.clone())
+    };
+
+    let serial = tag(TAG_X509_SERIAL);
+    let identity_changed = serial.is_some() && tag(TAG_X509_EMAILS) != Some(emails.join(","));
+
+    Ok(Some(X509MappingEntry {
+        fingerprint: db_cert.fingerprint.clone(),
+        name,
+        common_name,
+        emails,
+        signing_keys,
+        encryption_keys,
+        serial,
+        identity_changed,
+    }))
+}
+
+pub(crate) fn mapping(oca: &Oca) -> Result<X509Mapping> {
+    let mut certs = oca.user_certs_get_all()?;
+    certs.sort_by(|a, b| a.fingerprint.cmp(&b.fingerprint));
+
+    let mut entries = vec![];
+    for db_cert in certs.iter().filter(|c| !c.delisted && !c.inactive) {
+        entries.extend(mapping_entry(oca, db_cert)?);
+    }
+
+    Ok(X509Mapping {
+        version: X509_MAPPING_VERSION,
+        created: Utc::now().trunc_subsecs(0),
+        domain: oca.domainname().to_string(),
+        ca_fingerprint: oca.ca_get_cert_pub()?.fingerprint().to_hex(),
+        entries,
+    })
+}
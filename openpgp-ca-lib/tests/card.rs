@@ -35,7 +35,6 @@ fn init_on_card() -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -101,7 +100,6 @@ fn init_card_import_key() -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -179,7 +177,6 @@ fn init_card_import_card() -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -237,7 +234,6 @@ fn card_import_migrate() -> Result<()> {
             None,
             false,
             None,
-            false,
             None,
             true,
             true,
@@ -271,3 +267,58 @@ fn card_import_migrate() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+#[cfg_attr(not(feature = "card"), ignore)]
+/// Create a user with a softkey CA, with the user's key on the card.
+/// Check that the CA only stores the public cert, and that the card holds the user's subkeys.
+fn user_new_on_card() -> Result<()> {
+    let ident = env::var("IDENT").expect("IDENT is unset in environment");
+    util::reset_card(&ident)?;
+
+    let (_gpg, cau) = util::setup_one_uninit()?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    let key = ca.user_new_on_card(
+        &ident,
+        Some("Alice"),
+        &["alice@example.org"],
+        None,
+        None,
+        None,
+    )?;
+    assert_eq!(key.ident, ident);
+    assert_ne!(key.user_pin, key.admin_pin);
+
+    let certs = ca.user_certs_get_all()?;
+    assert_eq!(certs.len(), 1);
+    let alice = &certs[0];
+    assert_eq!(alice.fingerprint, key.fingerprint);
+
+    let cert = pgp::to_cert(alice.pub_cert.as_bytes())?;
+    assert!(!cert.is_tsk(), "The CA must not store private key material");
+
+    assert!(
+        !ca.cert_check_ca_sig(alice)?.certified.is_empty(),
+        "Alice is not certified by CA"
+    );
+
+    assert!(
+        ca.cert_check_tsig_on_ca(alice)?,
+        "CA cert is not signed by Alice"
+    );
+
+    let auth = util::card_auth_slot_fingerprint(&ident)?;
+    assert!(
+        cert.keys().subkeys().any(|k| k.fingerprint() == auth),
+        "AUT fingerprint on card doesn't match a subkey of Alice's cert"
+    );
+
+    // The card is not empty anymore
+    assert!(ca
+        .user_new_on_card(&ident, None, &["bob@example.org"], None, None, None)
+        .is_err());
+    assert_eq!(ca.user_certs_get_all()?.len(), 1);
+
+    Ok(())
+}
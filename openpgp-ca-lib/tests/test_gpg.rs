@@ -55,7 +55,6 @@ fn test_alice_authenticates_bob_centralized(gpg: Ctx, ca: Oca) -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -67,7 +66,6 @@ fn test_alice_authenticates_bob_centralized(gpg: Ctx, ca: Oca) -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -303,7 +301,6 @@ fn test_bridge(gpg: Ctx, ca1: Oca, ca2: Oca) -> Result<()> {
             None,
             false,
             None,
-            false,
             None,
             true,
             true,
@@ -320,7 +317,6 @@ fn test_bridge(gpg: Ctx, ca1: Oca, ca2: Oca) -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -334,7 +330,6 @@ fn test_bridge(gpg: Ctx, ca1: Oca, ca2: Oca) -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -481,7 +476,6 @@ fn test_multi_bridge(gpg: Ctx, ca1: Oca, ca2: Oca, ca3: Oca) -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -494,7 +488,6 @@ fn test_multi_bridge(gpg: Ctx, ca1: Oca, ca2: Oca, ca3: Oca) -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -506,7 +499,6 @@ fn test_multi_bridge(gpg: Ctx, ca1: Oca, ca2: Oca, ca3: Oca) -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -655,7 +647,6 @@ fn test_scoping(gpg: Ctx, ca1: Oca, ca2: Oca, ca3: Oca) -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -668,7 +659,6 @@ fn test_scoping(gpg: Ctx, ca1: Oca, ca2: Oca, ca3: Oca) -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -760,3 +750,51 @@ fn test_scoping(gpg: Ctx, ca1: Oca, ca2: Oca, ca3: Oca) -> Result<()> {
 
     Ok(())
 }
+
+/// Create a CA with users Alice and Bob, export everything into a GnuPG
+/// homedir. Check that GnuPG considers the CA "ultimate"ly trusted, and
+/// Alice and Bob "full"y valid.
+#[test]
+#[cfg_attr(not(feature = "softkey"), ignore)]
+fn export_to_gnupg_home() -> Result<()> {
+    let (gpg, cau) = util::setup_one_uninit()?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    for (name, email) in [("Alice", "alice@example.org"), ("Bob", "bob@example.org")] {
+        ca.user_new(
+            Some(name),
+            &[email],
+            None,
+            false,
+            None,
+            None,
+            true,
+            true,
+            false,
+        )?;
+    }
+
+    ca.export_to_gnupg_home(gpg.get_homedir())?;
+
+    let gpg_trust = gpg.list_keys()?;
+    assert_eq!(gpg_trust.len(), 3);
+
+    assert_eq!(
+        gpg_trust.get("OpenPGP CA <openpgp-ca@example.org>"),
+        Some(&"u".to_string())
+    );
+    assert_eq!(
+        gpg_trust.get("Alice <alice@example.org>"),
+        Some(&"f".to_string())
+    );
+    assert_eq!(
+        gpg_trust.get("Bob <bob@example.org>"),
+        Some(&"f".to_string())
+    );
+
+    // Exporting again into the same homedir is fine
+    ca.export_to_gnupg_home(gpg.get_homedir())?;
+    assert_eq!(gpg.list_keys()?.len(), 3);
+
+    Ok(())
+}
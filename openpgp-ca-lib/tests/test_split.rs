@@ -8,7 +8,11 @@ use std::env;
 use std::path::PathBuf;
 
 use anyhow::Result;
-use openpgp_ca_lib::Oca;
+use chrono::{Duration, Timelike};
+use openpgp_ca_lib::split_format::{QueueEntry, QueueResponse, SplitOcaRequests, SplitOcaResponse};
+use openpgp_ca_lib::types::{CaRekeyParams, SmoketestStatus};
+use openpgp_ca_lib::{pgp, Oca};
+use sequoia_openpgp::cert::CertBuilder;
 use tempfile::TempDir;
 
 mod util;
@@ -71,7 +75,6 @@ fn split_certify(ca: Oca) -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -90,7 +93,7 @@ fn split_certify(ca: Oca) -> Result<()> {
     // Ask backing ca to certify alice
 
     front.ca_split_export(csr_file.clone())?;
-    back.ca_split_certify(csr_file, sigs_file.clone(), true)?;
+    back.ca_split_certify(csr_file, sigs_file.clone(), &mut |_, _, _| Ok(true))?;
     front.ca_split_import(sigs_file)?;
 
     let certs = front.user_certs_get_all()?;
@@ -180,7 +183,7 @@ fn split_add_bridge(ca1: Oca) -> Result<()> {
 
     // Ask backing ca to certify the bridged CA
     front.ca_split_export(csr_file.clone())?;
-    back.ca_split_certify(csr_file, sigs_file.clone(), true)?;
+    back.ca_split_certify(csr_file, sigs_file.clone(), &mut |_, _, _| Ok(true))?;
     front.ca_split_import(sigs_file)?;
 
     // load bridges from front instance
@@ -193,3 +196,527 @@ fn split_add_bridge(ca1: Oca) -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+#[cfg_attr(not(feature = "softkey"), ignore)]
+fn split_merge_soft() -> Result<()> {
+    let (_gpg, cau) = util::setup_one_uninit()?;
+
+    // Make new softkey CA
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    split_merge(ca)
+}
+
+#[test]
+#[cfg_attr(not(feature = "card"), ignore)]
+fn split_merge_card() -> Result<()> {
+    let ident = env::var("IDENT").expect("IDENT is unset in environment");
+    util::reset_card(&ident)?;
+
+    let (_gpg, cau) = util::setup_one_uninit()?;
+
+    // Make new card-based CA
+    let (ca, _privkey) = cau.init_card_generate_on_host(&ident, "example.org", None, None)?;
+
+    split_merge(ca)
+}
+
+/// Tests merging a split CA back into a regular CA.
+///
+/// Split `ca` into a front and back instance, and create a user "Alice" in
+/// the front instance. Merging fails while Alice's certification request is
+/// pending. After an export-certify-import cycle, the merge succeeds, and
+/// the merged CA can certify new users directly.
+fn split_merge(ca: Oca) -> Result<()> {
+    let tmp_dir = TempDir::new()?;
+    let tmp_path = tmp_dir.into_path();
+
+    let mut csr_file = tmp_path.clone();
+    csr_file.push("csr.txt");
+
+    let mut sigs_file = tmp_path.clone();
+    sigs_file.push("certs.txt");
+
+    let mut front_path = tmp_path.clone();
+    front_path.push("front.oca");
+    let mut back_path = tmp_path;
+    back_path.push("back.oca");
+
+    ca.ca_split_into(&front_path, &back_path)?;
+    let front = Oca::open(front_path.to_str())?;
+
+    front.user_new(
+        Some("Alice"),
+        &["alice@example.org"],
+        None,
+        false,
+        None,
+        None,
+        true,
+        true,
+        false,
+    )?;
+
+    // the certification request for Alice is still pending
+    assert!(front.ca_merge_split(&back_path).is_err());
+
+    // the back instance is not a valid merge target for itself
+    let back = Oca::open(back_path.to_str())?;
+    assert!(back.ca_merge_split(&back_path).is_err());
+
+    let front = Oca::open(front_path.to_str())?;
+    let back = Oca::open(back_path.to_str())?;
+
+    front.ca_split_export(csr_file.clone())?;
+    back.ca_split_certify(csr_file, sigs_file.clone(), &mut |_, _, _| Ok(true))?;
+    front.ca_split_import(sigs_file)?;
+
+    front.ca_merge_split(&back_path)?;
+
+    let merged = Oca::open(front_path.to_str())?;
+
+    let certs = merged.user_certs_get_all()?;
+    assert_eq!(certs.len(), 1);
+    assert_eq!(merged.cert_check_ca_sig(&certs[0])?.certified.len(), 1);
+
+    // the merged CA certifies new users right away
+    merged.user_new(
+        Some("Bob"),
+        &["bob@example.org"],
+        None,
+        false,
+        None,
+        None,
+        true,
+        true,
+        false,
+    )?;
+
+    let bob = merged.certs_by_email("bob@example.org")?;
+    assert_eq!(bob.len(), 1);
+    assert_eq!(merged.cert_check_ca_sig(&bob[0])?.certified.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "softkey"), ignore)]
+/// The smoke test on split front and back instances runs the steps that
+/// apply to each
+fn split_smoketest() -> Result<()> {
+    let (_gpg, cau) = util::setup_one_uninit()?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    let tmp_dir = TempDir::new()?;
+    let front_path = tmp_dir.path().join("front.oca");
+    let back_path = tmp_dir.path().join("back.oca");
+
+    ca.ca_split_into(&front_path, &back_path)?;
+    let front = Oca::open(front_path.to_str())?;
+    let back = Oca::open(back_path.to_str())?;
+
+    let status = |oca: &Oca| -> Result<Vec<(&'static str, SmoketestStatus)>> {
+        Ok(oca
+            .smoketest()?
+            .into_iter()
+            .map(|s| (s.name, s.status))
+            .collect())
+    };
+
+    for (name, status) in status(&front)? {
+        match name {
+            "backend" | "certification" => {
+                assert!(matches!(status, SmoketestStatus::Skipped(_)), "{}", name)
+            }
+            _ => assert_eq!(status, SmoketestStatus::Passed, "{}", name),
+        }
+    }
+    assert!(front.ca_split_queue()?.is_empty());
+    assert!(front.users_get_all()?.is_empty());
+
+    for (name, status) in status(&back)? {
+        match name {
+            "backend" => assert_eq!(status, SmoketestStatus::Passed),
+            _ => assert!(matches!(status, SmoketestStatus::Skipped(_)), "{}", name),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "softkey"), ignore)]
+fn split_extend_expiring() -> Result<()> {
+    let (_gpg, cau) = util::setup_one_uninit()?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    ca.user_new(
+        Some("Alice"),
+        &["alice@example.org"],
+        Some(20),
+        false,
+        None,
+        None,
+        true,
+        true,
+        false,
+    )?;
+
+    let tmp_path = TempDir::new()?.into_path();
+    let csr_file = tmp_path.join("csr.txt");
+    let sigs_file = tmp_path.join("certs.txt");
+    let front_path = tmp_path.join("front.oca");
+    let back_path = tmp_path.join("back.oca");
+
+    ca.ca_split_into(&front_path, &back_path)?;
+    let front = Oca::open(front_path.to_str())?;
+    let back = Oca::open(back_path.to_str())?;
+
+    let now = chrono::Utc::now();
+    let (start, end) = (now + Duration::days(10), now + Duration::days(30));
+
+    // the new certification is queued for the back instance
+    let report = front.certifications_extend_expiring(start, end, 365, false)?;
+    assert_eq!(report.renewed, 1);
+    assert!(report.queued);
+
+    let alice = front.user_certs_get_all()?.pop().unwrap();
+    let cert = pgp::to_cert(alice.pub_cert.as_bytes())?;
+    assert_eq!(cert.userids().next().unwrap().certifications().count(), 1);
+
+    front.ca_split_export(csr_file.clone())?;
+    back.ca_split_certify(csr_file, sigs_file.clone(), &mut |_, _, _| Ok(true))?;
+    front.ca_split_import(sigs_file)?;
+
+    let alice = front.user_certs_get_all()?.pop().unwrap();
+    let cert = pgp::to_cert(alice.pub_cert.as_bytes())?;
+    assert_eq!(cert.userids().next().unwrap().certifications().count(), 2);
+
+    let report = front.certifications_extend_expiring(start, end, 365, true)?;
+    assert!(report.expiring.is_empty());
+
+    Ok(())
+}
+
+/// A certification until a fixed date is requested on the front instance,
+/// the back instance's certification expires at that date.
+#[test]
+#[cfg_attr(not(feature = "softkey"), ignore)]
+fn split_certify_until() -> Result<()> {
+    let (_gpg, cau) = util::setup_one_uninit()?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    let (alice, _) = CertBuilder::general_purpose(None, Some("<alice@example.org>")).generate()?;
+
+    let tmp_path = TempDir::new()?.into_path();
+    let csr_file = tmp_path.join("csr.txt");
+    let sigs_file = tmp_path.join("certs.txt");
+    let front_path = tmp_path.join("front.oca");
+    let back_path = tmp_path.join("back.oca");
+
+    ca.ca_split_into(&front_path, &back_path)?;
+    let front = Oca::open(front_path.to_str())?;
+    let back = Oca::open(back_path.to_str())?;
+
+    let until = (chrono::Utc::now() + Duration::days(100))
+        .with_nanosecond(0)
+        .unwrap();
+
+    front.cert_import_new_until(
+        pgp::cert_to_armored(&alice)?.as_bytes(),
+        &[],
+        None,
+        &["alice@example.org"],
+        &[],
+        &[],
+        until,
+    )?;
+
+    front.ca_split_export(csr_file.clone())?;
+    back.ca_split_certify(csr_file, sigs_file.clone(), &mut |_, _, _| Ok(true))?;
+    front.ca_split_import(sigs_file)?;
+
+    let alice = front.user_certs_get_all()?.pop().unwrap();
+    let cert = pgp::to_cert(alice.pub_cert.as_bytes())?;
+    let uid = cert.userids().next().unwrap();
+    let sig = uid.certifications().next().unwrap();
+    assert_eq!(
+        sig.signature_expiration_time()
+            .map(chrono::DateTime::<chrono::Utc>::from),
+        Some(until)
+    );
+
+    Ok(())
+}
+
+/// Replace the CA key on the back instance, import the new CA cert into the
+/// front instance. Alice's re-certification is queued on the front instance,
+/// and certified by the new key on the back instance.
+#[test]
+#[cfg_attr(not(feature = "softkey"), ignore)]
+fn split_rekey() -> Result<()> {
+    let (_gpg, cau) = util::setup_one_uninit()?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    ca.user_new(
+        Some("Alice"),
+        &["alice@example.org"],
+        None,
+        false,
+        None,
+        None,
+        true,
+        true,
+        false,
+    )?;
+    let alice = ca.user_certs_get_all()?.pop().unwrap();
+
+    let tmp_path = TempDir::new()?.into_path();
+    let csr_file = tmp_path.join("csr.txt");
+    let sigs_file = tmp_path.join("certs.txt");
+    let front_path = tmp_path.join("front.oca");
+    let back_path = tmp_path.join("back.oca");
+
+    ca.ca_split_into(&front_path, &back_path)?;
+    let front = Oca::open(front_path.to_str())?;
+    let back = Oca::open(back_path.to_str())?;
+
+    // rekeying must start on the back instance
+    let params = CaRekeyParams {
+        name: None,
+        cipher_suite: None,
+        validity_days: None,
+        certification_validity_days: 365,
+    };
+    assert!(front.ca_rekey(&params).is_err());
+    let front = Oca::open(front_path.to_str())?;
+
+    let (back, report) = back.ca_rekey(&params)?;
+    assert!(report.recertified.is_empty());
+
+    let new_ca = back.ca_get_pubkey_armored()?;
+    let (front, import) = front.ca_rekey_import(new_ca.as_bytes(), 365)?;
+    assert_eq!(import.old_fingerprint, report.old_fingerprint);
+    assert_eq!(import.new_fingerprint, report.new_fingerprint);
+    assert_eq!(import.recertified, vec![alice.fingerprint.clone()]);
+
+    let status = front.cert_check_ca_sig(&alice)?;
+    assert_eq!(status.certified.len(), 0);
+
+    front.ca_split_export(csr_file.clone())?;
+    back.ca_split_certify(csr_file, sigs_file.clone(), &mut |_, _, _| Ok(true))?;
+    front.ca_split_import(sigs_file)?;
+
+    let alice = front.user_certs_get_all()?.pop().unwrap();
+    let status = front.cert_check_ca_sig(&alice)?;
+    assert_eq!(status.certified.len(), 1);
+    assert_eq!(status.uncertified.len(), 0);
+
+    Ok(())
+}
+
+/// Requests and responses are exchanged as in-memory documents (as with the
+/// HTTP transport of restd), importing a response twice has no effect.
+#[test]
+#[cfg_attr(not(feature = "softkey"), ignore)]
+fn split_exchange_in_memory() -> Result<()> {
+    let (_gpg, cau) = util::setup_one_uninit()?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    // only front instances have a queue of requests
+    assert!(ca.ca_split_requests().is_err());
+
+    let (alice, _) = CertBuilder::general_purpose(None, Some("<alice@example.org>")).generate()?;
+
+    let tmp_path = TempDir::new()?.into_path();
+    let csr_file = tmp_path.join("csr.txt");
+    let sigs_file = tmp_path.join("certs.txt");
+    let front_path = tmp_path.join("front.oca");
+    let back_path = tmp_path.join("back.oca");
+
+    ca.ca_split_into(&front_path, &back_path)?;
+    let front = Oca::open(front_path.to_str())?;
+    let back = Oca::open(back_path.to_str())?;
+
+    front.cert_import_new(
+        pgp::cert_to_armored(&alice)?.as_bytes(),
+        &[],
+        None,
+        &["alice@example.org"],
+        None,
+    )?;
+
+    std::fs::write(&csr_file, front.ca_split_requests()?)?;
+    back.ca_split_certify(csr_file, sigs_file.clone(), &mut |_, _, _| Ok(true))?;
+
+    let response = std::fs::read(sigs_file)?;
+    assert_eq!(front.ca_split_import_response(&response)?, 1);
+    assert_eq!(front.ca_split_import_response(&response)?, 0);
+
+    let alice = front.user_certs_get_all()?.pop().unwrap();
+    let cert = pgp::to_cert(alice.pub_cert.as_bytes())?;
+    assert_eq!(cert.userids().next().unwrap().certifications().count(), 1);
+
+    // the back instance doesn't accept responses
+    assert!(back.ca_split_import_response(&response).is_err());
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "softkey"), ignore)]
+/// Requests and responses can be moved over custom transports, without
+/// temporary files, and inspected with the types in `split_format`
+fn split_exchange_streams() -> Result<()> {
+    let (_gpg, cau) = util::setup_one_uninit()?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    let (alice, _) = CertBuilder::general_purpose(None, Some("<alice@example.org>")).generate()?;
+
+    let tmp_path = TempDir::new()?.into_path();
+    let front_path = tmp_path.join("front.oca");
+    let back_path = tmp_path.join("back.oca");
+
+    ca.ca_split_into(&front_path, &back_path)?;
+    let front = Oca::open(front_path.to_str())?;
+    let back = Oca::open(back_path.to_str())?;
+
+    front.cert_import_new(
+        pgp::cert_to_armored(&alice)?.as_bytes(),
+        &[],
+        None,
+        &["alice@example.org"],
+        None,
+    )?;
+
+    let mut requests: Vec<u8> = vec![];
+    assert_eq!(front.ca_split_export_to_writer(&mut requests)?, 1);
+
+    let reqs = SplitOcaRequests::from_reader(&requests[..])?;
+    assert_eq!(
+        reqs.ca_fingerprint(),
+        ca.ca_get_cert_pub()?.fingerprint().to_hex()
+    );
+    let entries: Vec<_> = reqs.entries().collect();
+    assert_eq!(entries.len(), 1);
+    match entries[0].2 {
+        QueueEntry::CertificationReq(cr) => {
+            assert_eq!(cr.cert()?.fingerprint(), alice.fingerprint());
+            assert_eq!(cr.user_ids().len(), 1);
+        }
+        QueueEntry::BridgeReq(_) => panic!("unexpected bridge request"),
+    }
+
+    let queue = front.ca_split_queue()?;
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue[0].0, entries[0].0);
+
+    // only the back instance can process requests
+    assert!(front
+        .ca_split_certify_from_reader(&mut &requests[..], &mut std::io::sink(), &mut |_, _, _| {
+            Ok(true)
+        })
+        .is_err());
+
+    // requests that are not approved are skipped
+    let mut response: Vec<u8> = vec![];
+    back.ca_split_certify_from_reader(&mut &requests[..], &mut response, &mut |_, _, _| Ok(false))?;
+    assert_eq!(
+        SplitOcaResponse::from_reader(&response[..])?
+            .entries()
+            .count(),
+        0
+    );
+
+    let mut response: Vec<u8> = vec![];
+    back.ca_split_certify_from_reader(&mut &requests[..], &mut response, &mut |_, _, _| Ok(true))?;
+
+    let resp = SplitOcaResponse::from_reader(&response[..])?;
+    let entries: Vec<_> = resp.entries().collect();
+    assert_eq!(entries.len(), 1);
+    match entries[0].1 {
+        QueueResponse::CertificationResp(cr) => {
+            assert_eq!(cr.fingerprint(), alice.fingerprint().to_hex());
+            assert_eq!(cr.signatures()?.len(), 1);
+        }
+        QueueResponse::BridgeResp(_) => panic!("unexpected bridge response"),
+    }
+
+    assert_eq!(front.ca_split_import_from_reader(&mut &response[..])?, 1);
+    assert_eq!(front.ca_split_import_from_reader(&mut &response[..])?, 0);
+
+    let alice = front.user_certs_get_all()?.pop().unwrap();
+    let cert = pgp::to_cert(alice.pub_cert.as_bytes())?;
+    assert_eq!(cert.userids().next().unwrap().certifications().count(), 1);
+
+    // the queue is empty, but a (valid) request document is written
+    let mut requests: Vec<u8> = vec![];
+    assert_eq!(front.ca_split_export_to_writer(&mut requests)?, 0);
+    assert_eq!(
+        SplitOcaRequests::from_reader(&requests[..])?
+            .entries()
+            .count(),
+        0
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(not(feature = "softkey"), ignore)]
+/// A split-mode front instance can't sign notification emails: with mail
+/// signing enabled, it fails with an error (unsigned bodies still work).
+/// The back instance signs as usual.
+fn split_mail_signing() -> Result<()> {
+    let (_gpg, cau) = util::setup_one_uninit()?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    let tmp_dir = TempDir::new()?;
+    let front_path = tmp_dir.path().join("front.oca");
+    let back_path = tmp_dir.path().join("back.oca");
+
+    ca.ca_split_into(&front_path, &back_path)?;
+    let front = Oca::open(front_path.to_str())?;
+    let back = Oca::open(back_path.to_str())?;
+
+    let text = "Hello Alice,\nyour key expires soon.\n";
+
+    let plain = front.mail_text(text)?;
+    assert_eq!(
+        plain.header("Content-Type"),
+        Some("text/plain; charset=utf-8")
+    );
+    assert_eq!(
+        plain.header("Content-Transfer-Encoding"),
+        Some("quoted-printable")
+    );
+    assert_eq!(plain.body, "Hello Alice,\r\nyour key expires soon.");
+
+    front.set_mail_signing(true)?;
+    let err = front.mail_text(text).unwrap_err();
+    assert!(err.to_string().contains("split-mode front instance"));
+    assert!(front.mail_sign(&plain).is_err());
+
+    // the back instance has the CA key
+    let signed = back.mail_sign(&plain)?;
+
+    let content_type = signed.header("Content-Type").unwrap();
+    assert!(content_type.starts_with("multipart/signed; micalg=pgp-sha"));
+    assert!(content_type.contains("protocol=\"application/pgp-signature\""));
+
+    let boundary = content_type
+        .split("boundary=\"")
+        .nth(1)
+        .and_then(|b| b.strip_suffix('"'))
+        .unwrap();
+
+    let parts: Vec<_> = signed.body.split(&format!("--{boundary}")).collect();
+    assert_eq!(parts.len(), 4);
+    assert_eq!(parts[1], format!("\r\n{plain}\r\n"));
+    assert!(parts[2].starts_with("\r\nContent-Type: application/pgp-signature;"));
+    assert!(parts[2].contains("-----BEGIN PGP "));
+    assert_eq!(parts[3], "--\r\n");
+
+    Ok(())
+}
@@ -9,7 +9,10 @@ use std::path::Path;
 
 use anyhow::Result;
 use openpgp_ca_lib::pgp;
+use openpgp_ca_lib::types::ExportCompat;
 use openpgp_ca_lib::Uninit;
+use sequoia_openpgp::cert::CertParser;
+use sequoia_openpgp::parse::Parse;
 use sequoia_openpgp::{Cert, Fingerprint, KeyID};
 
 mod util;
@@ -40,7 +43,6 @@ fn test_ca_export_wkd() -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -52,7 +54,6 @@ fn test_ca_export_wkd() -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -64,7 +65,6 @@ fn test_ca_export_wkd() -> Result<()> {
         None,
         false,
         None,
-        false,
         None,
         true,
         true,
@@ -74,7 +74,7 @@ fn test_ca_export_wkd() -> Result<()> {
     let wkd_dir = home_path + "/wkd/";
     let wkd_path = Path::new(&wkd_dir);
 
-    ca.export_wkd("example.org", wkd_path)?;
+    ca.export_wkd("example.org", wkd_path, false, ExportCompat::default())?;
 
     // expect 3 exported keys (carol should not be in the export)
     let test_path = wkd_path.join(".well-known/openpgpkey/example.org/hu/");
@@ -131,7 +131,6 @@ fn test_wkd_delist() -> Result<()> {
         None,
         true,
         None,
-        false,
         None,
         true,
         true,
@@ -143,7 +142,6 @@ fn test_wkd_delist() -> Result<()> {
         None,
         true,
         None,
-        false,
         None,
         true,
         true,
@@ -160,7 +158,7 @@ fn test_wkd_delist() -> Result<()> {
     let wkd_dir = home_path + "/wkd/";
     let wkd_path = Path::new(&wkd_dir);
 
-    ca.export_wkd("example.org", wkd_path)?;
+    ca.export_wkd("example.org", wkd_path, false, ExportCompat::default())?;
 
     // expect 3 exported keys (carol should not be in the export)
     let test_path = wkd_path.join(".well-known/openpgpkey/example.org/hu/");
@@ -193,6 +191,193 @@ fn test_wkd_delist() -> Result<()> {
     Ok(())
 }
 
+#[test]
+/// Create a CA and two users with the same email address.
+/// Export to WKD twice. Check that both exports are identical, and that the
+/// certs in the shared WKD file are ordered by fingerprint.
+#[cfg_attr(not(feature = "softkey"), ignore)]
+fn test_wkd_export_reproducible() -> Result<()> {
+    let gpg = gnupg_test_wrapper::make_context()?;
+
+    let home_path = String::from(gpg.get_homedir().to_str().unwrap());
+    let db = format!("{home_path}/ca.sqlite");
+
+    let cau = Uninit::new(Some(&db))?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    for _ in 0..2 {
+        ca.user_new(
+            Some("Alice"),
+            &["alice@example.org"],
+            None,
+            false,
+            None,
+            None,
+            true,
+            true,
+            false,
+        )?;
+    }
+
+    let alice = ".well-known/openpgpkey/example.org/hu/kei1q4tipxxu1yj79k9kfukdhfy631xe";
+
+    let first = Path::new(&home_path).join("wkd1");
+    let second = Path::new(&home_path).join("wkd2");
+
+    ca.export_wkd("example.org", &first, false, ExportCompat::default())?;
+    ca.export_wkd("example.org", &second, false, ExportCompat::default())?;
+
+    let exported = fs::read(first.join(alice))?;
+    assert_eq!(exported, fs::read(second.join(alice))?);
+
+    let certs = CertParser::from_bytes(&exported)?.collect::<sequoia_openpgp::Result<Vec<_>>>()?;
+    assert_eq!(certs.len(), 2);
+    assert!(certs[0].fingerprint().to_hex() < certs[1].fingerprint().to_hex());
+
+    Ok(())
+}
+
+#[test]
+/// Export a minimized WKD: only the User ID in the CA's domain is published
+fn test_wkd_export_minimized() -> Result<()> {
+    let gpg = gnupg_test_wrapper::make_context()?;
+
+    let home_path = String::from(gpg.get_homedir().to_str().unwrap());
+    let db = format!("{home_path}/ca.sqlite");
+
+    let cau = Uninit::new(Some(&db))?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    ca.user_new(
+        Some("Alice"),
+        &["alice@example.org", "alice@other.org"],
+        None,
+        false,
+        None,
+        None,
+        true,
+        true,
+        false,
+    )?;
+
+    let alice = ".well-known/openpgpkey/example.org/hu/kei1q4tipxxu1yj79k9kfukdhfy631xe";
+
+    let full = Path::new(&home_path).join("wkd-full");
+    let minimal = Path::new(&home_path).join("wkd-minimal");
+
+    ca.export_wkd("example.org", &full, false, ExportCompat::default())?;
+    ca.export_wkd("example.org", &minimal, true, ExportCompat::default())?;
+
+    let cert = Cert::from_bytes(&fs::read(full.join(alice))?)?;
+    assert_eq!(cert.userids().count(), 2);
+
+    let cert = Cert::from_bytes(&fs::read(minimal.join(alice))?)?;
+    let uids: Vec<_> = cert.userids().collect();
+    assert_eq!(uids.len(), 1);
+    assert_eq!(uids[0].userid().email2()?, Some("alice@example.org"));
+
+    // The CA certification for the remaining User ID is kept
+    let ca_fp = ca.ca_get_cert_pub()?.fingerprint();
+    assert_eq!(uids[0].certifications().count(), 1);
+    assert!(uids[0]
+        .certifications()
+        .all(|s| s.issuer_fingerprints().any(|fp| fp == &ca_fp)));
+
+    Ok(())
+}
+
+#[test]
+/// Users can opt out of WKD publication, and the export can be limited to
+/// a list of email addresses
+#[cfg_attr(not(feature = "softkey"), ignore)]
+fn test_wkd_export_selective() -> Result<()> {
+    let gpg = gnupg_test_wrapper::make_context()?;
+
+    let home_path = String::from(gpg.get_homedir().to_str().unwrap());
+    let db = format!("{home_path}/ca.sqlite");
+
+    let cau = Uninit::new(Some(&db))?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    for emails in [
+        &["alice@example.org", "alice.b@example.org"][..],
+        &["bob@example.org"],
+        &["carol@example.org"],
+    ] {
+        ca.user_new(None, emails, None, false, None, None, true, true, false)?;
+    }
+
+    // The email addresses of all User IDs in the exported WKD
+    let exported = |path: &Path| -> Result<Vec<String>> {
+        let mut emails = vec![];
+        for file in fs::read_dir(path.join(".well-known/openpgpkey/example.org/hu"))? {
+            for cert in CertParser::from_file(file?.path())? {
+                for uid in cert?.userids() {
+                    emails.push(uid.userid().email2()?.unwrap().to_string());
+                }
+            }
+        }
+        emails.sort();
+        emails.dedup();
+        Ok(emails)
+    };
+
+    let carol = ca.certs_by_email("carol@example.org")?[0]
+        .fingerprint
+        .clone();
+    ca.user_set_wkd_publish(&carol, false)?;
+
+    let all = Path::new(&home_path).join("wkd-all");
+    ca.export_wkd("example.org", &all, false, ExportCompat::default())?;
+    assert_eq!(
+        exported(&all)?,
+        vec![
+            "alice.b@example.org",
+            "alice@example.org",
+            "bob@example.org",
+            "openpgp-ca@example.org"
+        ]
+    );
+
+    // Carol remains certified
+    let carol_cert = ca.cert_get_by_fingerprint(&carol)?.unwrap();
+    assert!(!ca.cert_check_ca_sig(&carol_cert)?.certified.is_empty());
+
+    let some = Path::new(&home_path).join("wkd-some");
+    ca.export_wkd_for_emails(
+        "example.org",
+        &some,
+        &["Alice@example.org", "carol@example.org"],
+        false,
+        ExportCompat::default(),
+    )?;
+    assert_eq!(
+        exported(&some)?,
+        vec!["alice@example.org", "openpgp-ca@example.org"]
+    );
+
+    // Carol opts in again
+    ca.user_set_wkd_publish(&carol, true)?;
+    let written = ca.export_wkd_changed_for_emails(
+        "example.org",
+        &some,
+        &["alice@example.org", "carol@example.org"],
+        false,
+        ExportCompat::default(),
+    )?;
+    assert_eq!(written.len(), 1);
+    assert_eq!(
+        exported(&some)?,
+        vec![
+            "alice@example.org",
+            "carol@example.org",
+            "openpgp-ca@example.org"
+        ]
+    );
+
+    Ok(())
+}
+
 #[test]
 #[ignore]
 /// Get sequoia-pgp.org keys for Justus and Neal from Hagrid.
@@ -249,7 +434,144 @@ fn test_ca_export_wkd_sequoia() -> Result<()> {
     let wkd_dir = home_path + "/wkd/";
     let wkd_path = Path::new(&wkd_dir);
 
-    ca.export_wkd("sequoia-pgp.org", wkd_path)?;
+    ca.export_wkd("sequoia-pgp.org", wkd_path, false, ExportCompat::default())?;
+
+    Ok(())
+}
+
+#[test]
+/// Export a WKD repeatedly, only writing files whose content changed.
+///
+/// Expected outcome: the first export writes all files, an export of the
+/// unchanged CA writes none, and after a new user is added, only the new
+/// user's file is written.
+#[cfg_attr(not(feature = "softkey"), ignore)]
+fn test_ca_export_wkd_changed() -> Result<()> {
+    let gpg = gnupg_test_wrapper::make_context()?;
+
+    let home_path = String::from(gpg.get_homedir().to_str().unwrap());
+    let db = format!("{home_path}/ca.sqlite");
+
+    let cau = Uninit::new(Some(&db))?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    ca.user_new(
+        Some("Alice"),
+        &["alice@example.org"],
+        None,
+        false,
+        None,
+        None,
+        true,
+        true,
+        false,
+    )?;
+
+    let wkd_dir = home_path + "/wkd/";
+    let wkd_path = Path::new(&wkd_dir);
+
+    let written = ca.export_wkd_changed("example.org", wkd_path, false, ExportCompat::default())?;
+    // CA, Alice and the policy file
+    assert_eq!(written.len(), 3);
+
+    let alice_file = wkd_path.join(
+        ".well-known/openpgpkey/example.org\
+         /hu/kei1q4tipxxu1yj79k9kfukdhfy631xe",
+    );
+    assert!(alice_file.is_file());
+    let modified = fs::metadata(&alice_file)?.modified()?;
+
+    // nothing changed
+    let written = ca.export_wkd_changed("example.org", wkd_path, false, ExportCompat::default())?;
+    assert!(written.is_empty());
+
+    ca.user_new(
+        Some("Bob"),
+        &["bob@example.org"],
+        None,
+        false,
+        None,
+        None,
+        true,
+        true,
+        false,
+    )?;
+
+    // Bob's file is new, and the CA cert now carries Bob's certification.
+    // Alice's file is unchanged.
+    let written = ca.export_wkd_changed("example.org", wkd_path, false, ExportCompat::default())?;
+    let hu = Path::new(".well-known/openpgpkey/example.org/hu");
+    assert_eq!(
+        written,
+        vec![
+            hu.join("ermf4k8pujzwtqqxmskb7355sebj5e4t"),
+            hu.join("jycbiujnsxs47xrkethgtj69xuunurok")
+        ]
+    );
+
+    assert_eq!(fs::metadata(&alice_file)?.modified()?, modified);
+
+    Ok(())
+}
+
+#[test]
+/// Export a WKD as a tar archive: it contains the same files as the WKD
+/// directory structure, in lexical order.
+#[cfg_attr(not(feature = "softkey"), ignore)]
+fn test_ca_export_wkd_tar() -> Result<()> {
+    let gpg = gnupg_test_wrapper::make_context()?;
+
+    let home_path = String::from(gpg.get_homedir().to_str().unwrap());
+    let db = format!("{home_path}/ca.sqlite");
+
+    let cau = Uninit::new(Some(&db))?;
+    let ca = cau.init_softkey("example.org", None, None)?;
+
+    ca.user_new(
+        Some("Alice"),
+        &["alice@example.org"],
+        None,
+        false,
+        None,
+        None,
+        true,
+        true,
+        false,
+    )?;
+
+    let wkd_path = Path::new(&home_path).join("wkd");
+    ca.export_wkd("example.org", &wkd_path, false, ExportCompat::default())?;
+
+    let tar = ca.export_wkd_tar("example.org", false, ExportCompat::default())?;
+
+    // Read the (name, content) pairs from the ustar archive
+    let mut files = vec![];
+    let mut pos = 0;
+    while tar[pos..pos + 512].iter().any(|&b| b != 0) {
+        let header = &tar[pos..pos + 512];
+
+        let name_len = header[..100].iter().position(|&b| b == 0).unwrap();
+        let name = String::from_utf8(header[..name_len].to_vec())?;
+        let size = usize::from_str_radix(std::str::from_utf8(&header[124..135])?, 8)?;
+
+        pos += 512;
+        files.push((name, tar[pos..pos + size].to_vec()));
+        pos += size.div_ceil(512) * 512;
+    }
+
+    let names: Vec<_> = files.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            ".well-known/openpgpkey/example.org/hu/jycbiujnsxs47xrkethgtj69xuunurok",
+            ".well-known/openpgpkey/example.org/hu/kei1q4tipxxu1yj79k9kfukdhfy631xe",
+            ".well-known/openpgpkey/example.org/policy",
+        ]
+    );
+
+    for (name, data) in files {
+        assert_eq!(data, fs::read(wkd_path.join(name))?);
+    }
 
     Ok(())
 }
@@ -14,21 +14,131 @@ extern crate rocket;
 
 mod cert_info;
 mod cli;
+mod downloads;
 pub mod json;
+mod jsonrpc;
+mod openapi;
 mod process_certs;
 mod restd;
+mod scheduler;
+mod unix_socket;
 pub mod util;
 
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
 use clap::Parser;
 use cli::RestdCli;
+use scheduler::Task;
 
-#[launch]
-fn rocket() -> rocket::Rocket<rocket::Build> {
+fn main() -> Result<()> {
     let cli = RestdCli::parse();
 
     let db = cli.database;
 
     match cli.cmd {
-        cli::Command::Run => restd::run(db),
+        cli::Command::Run {
+            update_keyserver,
+            update_wkd,
+            refresh_certifications,
+            export_wkd,
+            wkd_dir,
+            checkpoint,
+            cleanup,
+            purge_downloads,
+            audit_log,
+            wkd_export_token_file,
+            split_token_file,
+            write_mode,
+            tls_cert,
+            tls_key,
+            socket,
+        } => {
+            let minutes = |m: u64| Duration::from_secs(m * 60);
+
+            let mut tasks = vec![];
+            if let Some(m) = update_keyserver {
+                tasks.push((Task::UpdateKeyserver, minutes(m)));
+            }
+            if let Some(m) = update_wkd {
+                tasks.push((Task::UpdateWkd, minutes(m)));
+            }
+            if let Some(m) = refresh_certifications {
+                tasks.push((Task::RefreshCertifications, minutes(m)));
+            }
+            if let (Some(m), Some(path)) = (export_wkd, wkd_dir) {
+                tasks.push((Task::ExportWkd(path), minutes(m)));
+            }
+            if let Some(m) = checkpoint {
+                tasks.push((Task::Checkpoint, minutes(m)));
+            }
+            if let Some(m) = cleanup {
+                tasks.push((Task::Cleanup, minutes(m)));
+            }
+            if let Some(m) = purge_downloads {
+                tasks.push((Task::PurgeDownloads, minutes(m)));
+            }
+
+            if let Some(path) = audit_log {
+                downloads::set_audit_log(path).context("Failed to set up the audit log")?;
+            }
+
+            restd::set_write_mode(write_mode);
+
+            if let Some(path) = wkd_export_token_file {
+                let token = read_token(&path, "WKD export token")?;
+                restd::set_wkd_export_token(Some(token));
+            }
+
+            if let Some(path) = split_token_file {
+                let token = read_token(&path, "split mode token")?;
+                restd::set_split_token(Some(token));
+            }
+
+            scheduler::start(db.clone(), tasks).context("Failed to start the scheduler")?;
+
+            let mut rocket = restd::run(db);
+
+            if let (Some(cert), Some(key)) = (tls_cert, tls_key) {
+                let figment = rocket
+                    .figment()
+                    .clone()
+                    .merge(("tls.certs", cert))
+                    .merge(("tls.key", key));
+                rocket = rocket.configure(figment);
+            }
+
+            match socket {
+                Some(socket) => rocket::execute(unix_socket::serve(rocket, &socket))
+                    .context("Failed to run restd")?,
+                None => {
+                    // Format the error right away: rocket::Error panics if it
+                    // is dropped without being inspected
+                    rocket::execute(rocket.launch())
+                        .map_err(|e| anyhow::anyhow!("Failed to run restd: {e}"))?;
+                }
+            }
+        }
+        cli::Command::Jsonrpc { socket, audit_log } => match socket {
+            Some(socket) => jsonrpc::serve_unix(db, &socket, audit_log)?,
+            None => jsonrpc::serve_stdio(db, audit_log)?,
+        },
     }
+
+    Ok(())
+}
+
+/// Read a bearer token for restd routes from the file `path`
+fn read_token(path: &Path, what: &str) -> Result<String> {
+    let token = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the {what} from {}", path.display()))?;
+    let token = token.trim();
+    anyhow::ensure!(
+        !token.is_empty(),
+        "The {what} in {} is empty",
+        path.display()
+    );
+
+    Ok(token.to_string())
 }
@@ -6,8 +6,12 @@
 // SPDX-FileCopyrightText: 2019-2020 Heiko Schaefer <heiko@schaefer.name>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
+use crate::restd::WriteMode;
+
 #[derive(Parser)]
 #[clap(
     name = "openpgp-ca-restd",
@@ -26,5 +30,132 @@ pub struct RestdCli {
 #[derive(Subcommand)]
 pub enum Command {
     /// Run restd
-    Run,
+    Run {
+        #[clap(
+            long = "update-keyserver-every",
+            value_name = "MINUTES",
+            help = "Periodically pull updates for all certs from the configured keyservers (see `Oca::set_keyserver_config`)"
+        )]
+        update_keyserver: Option<u64>,
+
+        #[clap(
+            long = "update-wkd-every",
+            value_name = "MINUTES",
+            help = "Periodically pull updates for all certs from WKD"
+        )]
+        update_wkd: Option<u64>,
+
+        #[clap(
+            long = "refresh-certifications-every",
+            value_name = "MINUTES",
+            help = "Periodically renew CA certifications that expire soon"
+        )]
+        refresh_certifications: Option<u64>,
+
+        #[clap(
+            long = "export-wkd-every",
+            value_name = "MINUTES",
+            requires = "wkd_dir",
+            help = "Periodically export the WKD directory structure to 'wkd-dir'"
+        )]
+        export_wkd: Option<u64>,
+
+        #[clap(
+            long = "wkd-dir",
+            value_name = "PATH",
+            help = "Target directory for periodic WKD exports"
+        )]
+        wkd_dir: Option<PathBuf>,
+
+        #[clap(
+            long = "checkpoint-every",
+            value_name = "MINUTES",
+            help = "Periodically checkpoint (and truncate) the write-ahead log of the database"
+        )]
+        checkpoint: Option<u64>,
+
+        #[clap(
+            long = "cleanup-every",
+            value_name = "MINUTES",
+            help = "Periodically remove database rows according to the CA's retention policy"
+        )]
+        cleanup: Option<u64>,
+
+        #[clap(
+            long = "purge-downloads-every",
+            value_name = "MINUTES",
+            help = "Periodically delete staged welcome kits whose download links have expired"
+        )]
+        purge_downloads: Option<u64>,
+
+        #[clap(
+            long = "audit-log",
+            value_name = "PATH",
+            help = "Append the audit log of welcome kit downloads to a file"
+        )]
+        audit_log: Option<PathBuf>,
+
+        #[clap(
+            long = "wkd-export-token-file",
+            value_name = "PATH",
+            help = "Enable downloads of WKD exports from /wkd/export, for clients that send the token in this file as a bearer token"
+        )]
+        wkd_export_token_file: Option<PathBuf>,
+
+        #[clap(
+            long = "split-token-file",
+            value_name = "PATH",
+            help = "Enable the exchange of requests and responses with a split mode back instance via /split/..., for clients that send the token in this file as a bearer token"
+        )]
+        split_token_file: Option<PathBuf>,
+
+        #[clap(
+            long = "write-mode",
+            value_enum,
+            default_value = "open",
+            help = "Apply changes right away ('open'), reject them ('read-only'), or queue them for approval by a CA admin ('approval')"
+        )]
+        write_mode: WriteMode,
+
+        #[clap(
+            long = "tls-cert",
+            value_name = "PATH",
+            requires = "tls_key",
+            conflicts_with = "socket",
+            help = "Serve HTTPS directly, with the (PEM encoded) certificate chain in this file"
+        )]
+        tls_cert: Option<PathBuf>,
+
+        #[clap(
+            long = "tls-key",
+            value_name = "PATH",
+            requires = "tls_cert",
+            help = "The (PEM encoded) private key for 'tls-cert'"
+        )]
+        tls_key: Option<PathBuf>,
+
+        #[clap(
+            long = "socket",
+            value_name = "PATH",
+            help = "Listen on a unix domain socket (only accessible to the current user), instead of TCP"
+        )]
+        socket: Option<PathBuf>,
+    },
+
+    /// Serve JSON-RPC 2.0 requests (one per line) on stdio or a unix socket
+    Jsonrpc {
+        #[clap(
+            long = "socket",
+            value_name = "PATH",
+            help = "Listen on a unix domain socket (only accessible to the current user), instead of stdio"
+        )]
+        socket: Option<PathBuf>,
+
+        #[clap(
+            long = "audit-log",
+            value_name = "PATH",
+            help = "Append the audit log of all calls to a file"
+        )]
+        audit_log: Option<PathBuf>,
+    },
 }
@@ -6,13 +6,22 @@
 // SPDX-FileCopyrightText: 2019-2020 Heiko Schaefer <heiko@schaefer.name>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-//! Very basic client wrapper for the OpenPGP CA restd,
-//! intended for use in integration tests.
+//! Typed client for the OpenPGP CA restd.
+//!
+//! Note that this client panics on transport errors and unexpected
+//! responses (it was originally written for use in integration tests).
 
+use openpgp_ca_lib::types::{CertStatus, SignedCertStatus};
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use reqwest::{Response, StatusCode};
+use sequoia_openpgp::Cert;
 
-use crate::json::{CertResultJson, Certificate, ReturnError, ReturnGoodJson};
+use crate::cert_info::CertInfo;
+use crate::json::{
+    CertResultJson, Certificate, DownloadAuditEntry, DownloadLink, KeyReplacementInfo,
+    KeyReplacementRejection, KeyReplacementUpload, ReturnError, ReturnGoodJson, RevocationInfo,
+    RevocationUpload, SignedCertStatusJson, SplitImport, StageDownload, StagedDownload, TaskStatus,
+};
 
 pub struct Client {
     client: reqwest::Client,
@@ -41,6 +50,8 @@ impl Client {
                         Ok(resp)
                     }
                 }
+                // the change was queued for approval (restd in approval mode)
+                StatusCode::ACCEPTED => Ok(None),
                 StatusCode::BAD_REQUEST => {
                     let resp = o.json::<ReturnError>().await.unwrap();
 
@@ -145,6 +156,26 @@ impl Client {
         Client::map_result(resp).await
     }
 
+    pub async fn lookup(&self, email: String) -> Result<Option<String>, ReturnError> {
+        let resp = self
+            .client
+            .get(format!("{}certs/lookup/{}", &self.uri, email))
+            .send()
+            .await;
+
+        match resp {
+            Ok(o) => match o.status() {
+                StatusCode::OK => Ok(Some(o.text().await.unwrap())),
+                StatusCode::NOT_FOUND => Ok(None),
+                StatusCode::BAD_REQUEST => Err(o.json::<ReturnError>().await.unwrap()),
+                _ => panic!("unexpected status code {}", o.status()),
+            },
+            Err(e) => {
+                panic!("error {}", e);
+            }
+        }
+    }
+
     pub async fn deactivate(&self, fp: String) -> Result<Option<ReturnGoodJson>, ReturnError> {
         let resp = self
             .client
@@ -164,4 +195,393 @@ impl Client {
 
         Client::map_result(resp).await
     }
+
+    /// Get info about all certs that expire within `days`
+    pub async fn expiring(&self, days: u64) -> Result<Vec<CertInfo>, ReturnError> {
+        let resp = self
+            .client
+            .get(format!("{}certs/expire/{}", &self.uri, days))
+            .send()
+            .await;
+
+        match resp {
+            Ok(o) => match o.status() {
+                StatusCode::OK => Ok(o.json::<Vec<CertInfo>>().await.unwrap()),
+                StatusCode::BAD_REQUEST => Err(o.json::<ReturnError>().await.unwrap()),
+                _ => panic!("unexpected status code {}", o.status()),
+            },
+            Err(e) => {
+                panic!("error {}", e);
+            }
+        }
+    }
+
+    /// Renew CA certifications that expire soon
+    pub async fn refresh_ca_certifications(&self) -> Result<(), ReturnError> {
+        let resp = self
+            .client
+            .post(format!("{}refresh_ca_certifications", &self.uri))
+            .send()
+            .await;
+
+        Client::map_result(resp).await.map(|_| ())
+    }
+
+    /// Check if the restd can access the CA database
+    pub async fn healthz(&self) -> bool {
+        let resp = self
+            .client
+            .get(format!("{}healthz", &self.uri))
+            .send()
+            .await;
+
+        matches!(resp, Ok(o) if o.status() == StatusCode::OK)
+    }
+
+    /// Get the status of the periodic tasks of the scheduler
+    pub async fn scheduler(&self) -> Vec<TaskStatus> {
+        self.client
+            .get(format!("{}scheduler", &self.uri))
+            .send()
+            .await
+            .expect("scheduler request failed")
+            .json()
+            .await
+            .expect("scheduler status is not valid JSON")
+    }
+
+    /// Stage a user's welcome kit for a one-time download
+    pub async fn stage_download(
+        &self,
+        request: &StageDownload,
+    ) -> Result<DownloadLink, ReturnError> {
+        let resp = self
+            .client
+            .post(format!("{}downloads", &self.uri))
+            .json(request)
+            .send()
+            .await;
+
+        match resp {
+            Ok(o) => match o.status() {
+                StatusCode::OK => Ok(o.json::<DownloadLink>().await.unwrap()),
+                StatusCode::BAD_REQUEST => Err(o.json::<ReturnError>().await.unwrap()),
+                _ => panic!("unexpected status code {}", o.status()),
+            },
+            Err(e) => {
+                panic!("error {}", e);
+            }
+        }
+    }
+
+    /// Get the staged welcome kits that have not been downloaded yet
+    pub async fn staged_downloads(&self) -> Vec<StagedDownload> {
+        self.client
+            .get(format!("{}downloads", &self.uri))
+            .send()
+            .await
+            .expect("downloads request failed")
+            .json()
+            .await
+            .expect("staged downloads are not valid JSON")
+    }
+
+    /// Withdraw a staged welcome kit, returns false if it was not found
+    pub async fn withdraw_download(&self, id: &str) -> bool {
+        let resp = self
+            .client
+            .delete(format!("{}downloads/{}", &self.uri, id))
+            .send()
+            .await
+            .expect("withdraw request failed");
+
+        resp.status() == StatusCode::OK
+    }
+
+    /// Get the audit log of welcome kit downloads
+    pub async fn downloads_audit(&self) -> Vec<DownloadAuditEntry> {
+        self.client
+            .get(format!("{}downloads/audit", &self.uri))
+            .send()
+            .await
+            .expect("audit log request failed")
+            .json()
+            .await
+            .expect("audit log is not valid JSON")
+    }
+
+    /// Download a staged welcome kit, via the `url` of its [DownloadLink].
+    ///
+    /// Returns None if the link is not valid (anymore).
+    pub async fn download(&self, url: &str) -> Option<String> {
+        let resp = self
+            .client
+            .get(format!("{}{}", &self.uri, url.trim_start_matches('/')))
+            .send()
+            .await
+            .expect("download request failed");
+
+        match resp.status() {
+            StatusCode::OK => Some(resp.text().await.unwrap()),
+            StatusCode::NOT_FOUND => None,
+            _ => panic!("unexpected status code {}", resp.status()),
+        }
+    }
+
+    /// Download the WKD directory structure of the CA as a tar archive,
+    /// authenticated with the bearer `token`.
+    ///
+    /// Returns the HTTP status code, if the download was refused.
+    pub async fn wkd_export(&self, token: &str) -> Result<Vec<u8>, StatusCode> {
+        let resp = self
+            .client
+            .get(format!("{}wkd/export", &self.uri))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("wkd export request failed");
+
+        match resp.status() {
+            StatusCode::OK => Ok(resp.bytes().await.unwrap().to_vec()),
+            status => Err(status),
+        }
+    }
+
+    /// Get the pending requests of a split mode front instance (as JSON),
+    /// authenticated with the bearer `token`.
+    ///
+    /// Returns the HTTP status code, if the request failed.
+    pub async fn split_requests(&self, token: &str) -> Result<String, StatusCode> {
+        let resp = self
+            .client
+            .get(format!("{}split/requests", &self.uri))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("split requests request failed");
+
+        match resp.status() {
+            StatusCode::OK => Ok(resp.text().await.unwrap()),
+            status => Err(status),
+        }
+    }
+
+    /// Post the `response` of a split mode back instance (as JSON),
+    /// authenticated with the bearer `token`.
+    ///
+    /// Returns the HTTP status code, if the request failed.
+    pub async fn split_responses(
+        &self,
+        token: &str,
+        response: &str,
+    ) -> Result<SplitImport, StatusCode> {
+        let resp = self
+            .client
+            .post(format!("{}split/responses", &self.uri))
+            .bearer_auth(token)
+            .body(response.to_string())
+            .send()
+            .await
+            .expect("split responses request failed");
+
+        match resp.status() {
+            StatusCode::OK => Ok(resp.json().await.unwrap()),
+            status => Err(status),
+        }
+    }
+
+    /// Deposit an armored revocation certificate for a user cert (None if
+    /// it was queued for approval)
+    pub async fn post_revocation(
+        &self,
+        revocation: &str,
+    ) -> Result<Option<RevocationInfo>, ReturnError> {
+        let resp = self
+            .client
+            .post(format!("{}revocations", &self.uri))
+            .json(&RevocationUpload {
+                revocation: revocation.to_string(),
+            })
+            .send()
+            .await;
+
+        match resp {
+            Ok(o) => match o.status() {
+                StatusCode::OK => Ok(Some(o.json::<RevocationInfo>().await.unwrap())),
+                // the revocation was queued for approval (restd in approval mode)
+                StatusCode::ACCEPTED => Ok(None),
+                StatusCode::BAD_REQUEST => Err(o.json::<ReturnError>().await.unwrap()),
+                _ => panic!("unexpected status code {}", o.status()),
+            },
+            Err(e) => {
+                panic!("error {}", e);
+            }
+        }
+    }
+
+    /// Get all revocation certificates in the CA database
+    pub async fn revocations(&self) -> Vec<RevocationInfo> {
+        self.client
+            .get(format!("{}revocations", &self.uri))
+            .send()
+            .await
+            .expect("revocations request failed")
+            .json()
+            .await
+            .expect("revocations are not valid JSON")
+    }
+
+    /// Remove a revocation certificate that has not been applied.
+    ///
+    /// Returns Ok(false) if there is no revocation with this hash.
+    pub async fn delete_revocation(&self, hash: &str) -> Result<bool, ReturnError> {
+        let resp = self
+            .client
+            .delete(format!("{}revocations/{}", &self.uri, hash))
+            .send()
+            .await
+            .expect("delete revocation request failed");
+
+        match resp.status() {
+            StatusCode::OK | StatusCode::ACCEPTED => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            StatusCode::BAD_REQUEST => Err(resp.json::<ReturnError>().await.unwrap()),
+            _ => panic!("unexpected status code {}", resp.status()),
+        }
+    }
+
+    /// Get the statement that the holder of the cert `fp` signs, to
+    /// replace it with the cert `new_fp`
+    pub async fn replacement_statement(
+        &self,
+        fp: &str,
+        new_fp: &str,
+    ) -> Result<String, ReturnError> {
+        let resp = self
+            .client
+            .get(format!(
+                "{}replacements/statement/{}/{}",
+                &self.uri, fp, new_fp
+            ))
+            .send()
+            .await
+            .expect("replacement statement request failed");
+
+        match resp.status() {
+            StatusCode::OK => Ok(resp.text().await.unwrap()),
+            StatusCode::BAD_REQUEST => Err(resp.json::<ReturnError>().await.unwrap()),
+            _ => panic!("unexpected status code {}", resp.status()),
+        }
+    }
+
+    /// Submit a replacement for a user cert
+    pub async fn submit_replacement(
+        &self,
+        upload: &KeyReplacementUpload,
+    ) -> Result<KeyReplacementInfo, ReturnError> {
+        let resp = self
+            .client
+            .post(format!("{}replacements", &self.uri))
+            .json(upload)
+            .send()
+            .await
+            .expect("replacement request failed");
+
+        Self::map_replacement(resp).await
+    }
+
+    /// Get the key replacements that wait for approval
+    pub async fn pending_replacements(&self) -> Vec<KeyReplacementInfo> {
+        self.client
+            .get(format!("{}replacements", &self.uri))
+            .send()
+            .await
+            .expect("replacements request failed")
+            .json()
+            .await
+            .expect("replacements are not valid JSON")
+    }
+
+    /// Approve the key replacement `id`
+    pub async fn approve_replacement(&self, id: i32) -> Result<KeyReplacementInfo, ReturnError> {
+        let resp = self
+            .client
+            .post(format!("{}replacements/{}/approve", &self.uri, id))
+            .send()
+            .await
+            .expect("approve request failed");
+
+        Self::map_replacement(resp).await
+    }
+
+    /// Reject the key replacement `id`
+    pub async fn reject_replacement(
+        &self,
+        id: i32,
+        reason: Option<&str>,
+    ) -> Result<KeyReplacementInfo, ReturnError> {
+        let resp = self
+            .client
+            .post(format!("{}replacements/{}/reject", &self.uri, id))
+            .json(&KeyReplacementRejection {
+                reason: reason.map(ToString::to_string),
+            })
+            .send()
+            .await
+            .expect("reject request failed");
+
+        Self::map_replacement(resp).await
+    }
+
+    async fn map_replacement(resp: Response) -> Result<KeyReplacementInfo, ReturnError> {
+        match resp.status() {
+            StatusCode::OK => Ok(resp.json::<KeyReplacementInfo>().await.unwrap()),
+            StatusCode::BAD_REQUEST => Err(resp.json::<ReturnError>().await.unwrap()),
+            _ => panic!("unexpected status code {}", resp.status()),
+        }
+    }
+
+    /// Get the CA-signed revocation status of the cert `fp`.
+    ///
+    /// The status can be checked against the CA cert with
+    /// [SignedCertStatus::verify].
+    pub async fn cert_status(&self, fp: &str) -> Result<SignedCertStatus, ReturnError> {
+        let resp = self
+            .client
+            .get(format!("{}certs/status/{}", &self.uri, fp))
+            .send()
+            .await
+            .expect("cert status request failed");
+
+        match resp.status() {
+            StatusCode::OK => Ok(resp.json::<SignedCertStatusJson>().await.unwrap().into()),
+            StatusCode::BAD_REQUEST => Err(resp.json::<ReturnError>().await.unwrap()),
+            _ => panic!("unexpected status code {}", resp.status()),
+        }
+    }
+
+    /// Get the revocation status of the cert `fp`, after verifying that it
+    /// was signed by `ca_cert`
+    pub async fn cert_status_verified(
+        &self,
+        fp: &str,
+        ca_cert: &Cert,
+    ) -> anyhow::Result<CertStatus> {
+        match self.cert_status(fp).await {
+            Ok(scs) => scs.verify(ca_cert),
+            Err(e) => Err(anyhow::anyhow!("cert status request failed: {}", e.msg)),
+        }
+    }
+
+    /// Get the OpenAPI description of the restd API
+    pub async fn openapi(&self) -> serde_json::Value {
+        self.client
+            .get(format!("{}openapi.json", &self.uri))
+            .send()
+            .await
+            .expect("openapi request failed")
+            .json()
+            .await
+            .expect("openapi document is not valid JSON")
+    }
 }
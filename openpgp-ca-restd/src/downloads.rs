@@ -0,0 +1,244 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! One-time download links for welcome kits.
+//!
+//! An admin stages a user's welcome kit (optionally including the user's
+//! private key), and gets a download URL that contains a random token.
+//! The kit can be downloaded once, until the link expires.
+//!
+//! Staged kits are only held in the memory of the restd (they are never
+//! written to disk). A kit is deleted when it is downloaded, withdrawn,
+//! or found to be expired (expired kits are purged on each access to the
+//! downloads API, and by the `purge_downloads` scheduler task).
+//!
+//! All events are recorded in an audit log. The most recent
+//! [AUDIT_MEMORY_ENTRIES] events are kept in memory, the full log can
+//! optionally be appended to a file (as JSON lines).
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::{Lazy, OnceCell};
+use openpgp_ca_lib::Oca;
+use sequoia_openpgp::crypto::mem::Protected;
+use sequoia_openpgp::fmt::hex;
+
+use crate::json::{DownloadAuditEntry, DownloadEvent, DownloadLink, StageDownload, StagedDownload};
+
+/// Default validity of download links
+pub const DEFAULT_EXPIRY_MINUTES: u64 = 24 * 60;
+
+/// Upper limit for the validity of download links (7 days)
+pub const MAX_EXPIRY_MINUTES: u64 = 7 * 24 * 60;
+
+/// Number of audit log entries that are kept in memory
+pub const AUDIT_MEMORY_ENTRIES: usize = 1000;
+
+/// A staged welcome kit
+struct Staged {
+    info: StagedDownload,
+
+    /// The armored kit (wiped from memory when dropped)
+    kit: Protected,
+}
+
+/// Staged kits, by download token
+static STAGED: Lazy<Mutex<HashMap<String, Staged>>> = Lazy::new(Default::default);
+
+static AUDIT: Lazy<Mutex<VecDeque<DownloadAuditEntry>>> = Lazy::new(Default::default);
+
+static AUDIT_FILE: OnceCell<PathBuf> = OnceCell::new();
+
+/// Also append the audit log to the file `path`.
+///
+/// Can only be set once per process.
+pub fn set_audit_log(path: PathBuf) -> Result<()> {
+    // Fail early, if the file can't be written
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Can't open audit log {path:?}"))?;
+
+    AUDIT_FILE
+        .set(path)
+        .map_err(|_| anyhow::anyhow!("The audit log file is already set"))
+}
+
+fn audit(event: DownloadEvent, info: Option<&StagedDownload>, actor: Option<String>) {
+    let entry = DownloadAuditEntry {
+        time: Utc::now(),
+        event,
+        id: info.map(|i| i.id.clone()),
+        fingerprint: info.map(|i| i.fingerprint.clone()),
+        actor,
+    };
+
+    if let Some(path) = AUDIT_FILE.get() {
+        // Failing to write the file doesn't interrupt the flow
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            if let Ok(line) = serde_json::to_string(&entry) {
+                let _ = writeln!(file, "{line}");
+            }
+        }
+    }
+
+    let mut log = AUDIT.lock().unwrap();
+    if log.len() == AUDIT_MEMORY_ENTRIES {
+        log.pop_front();
+    }
+    log.push_back(entry);
+}
+
+/// A hex-encoded random value of `len` bytes
+fn random_hex(len: usize) -> String {
+    let mut buf = vec![0; len];
+    sequoia_openpgp::crypto::random(&mut buf);
+
+    hex::encode(buf).to_ascii_lowercase()
+}
+
+/// Delete all expired kits, returns the number of deleted kits
+pub fn purge() -> usize {
+    let now = Utc::now();
+
+    let mut staged = STAGED.lock().unwrap();
+
+    let expired: Vec<String> = staged
+        .iter()
+        .filter(|(_, s)| s.info.expires <= now)
+        .map(|(token, _)| token.clone())
+        .collect();
+
+    for token in &expired {
+        if let Some(s) = staged.remove(token) {
+            audit(DownloadEvent::Expired, Some(&s.info), None);
+        }
+    }
+
+    expired.len()
+}
+
+/// Stage the welcome kit for `request.fingerprint` for download
+pub fn stage(ca: &Oca, request: StageDownload) -> Result<DownloadLink> {
+    purge();
+
+    let minutes = request.expires_in_minutes.unwrap_or(DEFAULT_EXPIRY_MINUTES);
+    if minutes == 0 || minutes > MAX_EXPIRY_MINUTES {
+        return Err(anyhow::anyhow!(
+            "Download links must expire within 1 to {MAX_EXPIRY_MINUTES} minutes"
+        ));
+    }
+
+    let bundle =
+        ca.user_provisioning_bundle(&request.fingerprint, request.private_key.as_deref())?;
+
+    // Normalize the fingerprint (the caller may use any notation)
+    let fingerprint = ca
+        .cert_get_by_fingerprint(&request.fingerprint)?
+        .context("Cert not found")?
+        .fingerprint;
+
+    let created = Utc::now();
+    let expires: DateTime<Utc> = created + chrono::Duration::minutes(minutes as i64);
+
+    let info = StagedDownload {
+        id: random_hex(8),
+        fingerprint,
+        staged_by: request.staged_by,
+        created,
+        expires,
+    };
+    let token = random_hex(32);
+
+    let link = DownloadLink {
+        id: info.id.clone(),
+        url: format!("/download/{token}"),
+        expires,
+    };
+
+    audit(DownloadEvent::Staged, Some(&info), info.staged_by.clone());
+
+    STAGED.lock().unwrap().insert(
+        token,
+        Staged {
+            info,
+            kit: Protected::from(bundle.armored().into_bytes()),
+        },
+    );
+
+    Ok(link)
+}
+
+/// Download the kit for `token`, and delete it.
+///
+/// The armored kit stays in protected memory, until it has been written
+/// to the response.
+///
+/// Returns None if the token is unknown, or the kit has expired or has
+/// already been downloaded.
+pub fn take(token: &str, remote: Option<String>) -> Option<Protected> {
+    purge();
+
+    let staged = STAGED.lock().unwrap().remove(token);
+
+    match staged {
+        Some(staged) => {
+            audit(DownloadEvent::Downloaded, Some(&staged.info), remote);
+
+            Some(staged.kit)
+        }
+        None => {
+            audit(DownloadEvent::Rejected, None, remote);
+            None
+        }
+    }
+}
+
+/// All kits that are staged (and not expired)
+pub fn list() -> Vec<StagedDownload> {
+    purge();
+
+    let mut list: Vec<_> = STAGED
+        .lock()
+        .unwrap()
+        .values()
+        .map(|s| s.info.clone())
+        .collect();
+    list.sort_by_key(|i| i.created);
+
+    list
+}
+
+/// Delete the staged kit `id`, returns false if there is no such kit
+pub fn withdraw(id: &str, remote: Option<String>) -> bool {
+    purge();
+
+    let mut staged = STAGED.lock().unwrap();
+
+    let token = staged
+        .iter()
+        .find(|(_, s)| s.info.id == id)
+        .map(|(token, _)| token.clone());
+
+    if let Some(s) = token.and_then(|token| staged.remove(&token)) {
+        audit(DownloadEvent::Withdrawn, Some(&s.info), remote);
+        true
+    } else {
+        false
+    }
+}
+
+/// The most recent entries of the audit log of this restd process
+pub fn audit_log() -> Vec<DownloadAuditEntry> {
+    AUDIT.lock().unwrap().iter().cloned().collect()
+}
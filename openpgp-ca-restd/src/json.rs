@@ -4,9 +4,16 @@
 // This file is part of OpenPGP CA
 // https://gitlab.com/openpgp-ca/openpgp-ca
 
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+pub use openpgp_ca_lib::api::RevocationInfo;
+use openpgp_ca_lib::cert_info::{CertWarning, CertWarningKind};
 use openpgp_ca_lib::db::models;
+use openpgp_ca_lib::types::SignedCertStatus;
 use rocket::response::status::BadRequest;
 use rocket::serde::json::Json;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::cert_info::CertInfo;
@@ -15,7 +22,7 @@ use crate::cert_info::CertInfo;
 ///
 /// This data structure binds together two different variants of result:
 /// One if the Cert can be processed, and another if Cert cannot be processed.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum CertResultJson {
     Good(ReturnGoodJson),
@@ -32,7 +39,7 @@ impl From<Result<ReturnGoodJson, ReturnBadJson>> for CertResultJson {
 }
 
 /// A container for information about a "good" Cert.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ReturnGoodJson {
     /// OpenPGP CA representation of a Cert (armored cert + metadata)
     pub certificate: Certificate,
@@ -51,9 +58,26 @@ pub struct ReturnGoodJson {
     /// hint for the UI, shows if the Cert should/can/cannot be uploaded
     #[serde(skip_serializing_if = "Option::is_none")]
     pub upload: Option<Upload>,
+
+    /// key-value metadata that the CA stores for the Cert and its User
+    /// (only for Certs that are already stored in the CA)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Tags>,
+
+    /// id of the proposed change, if the Cert was queued for approval by a
+    /// CA admin instead of being stored (restd in approval mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queued: Option<i32>,
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Tags (key-value metadata, e.g. an employee ID) on a Cert and its User
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Tags {
+    pub cert: BTreeMap<String, String>,
+    pub user: BTreeMap<String, String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum Action {
     /// This cert can be imported, it is "new" to this CA:
     /// We don't have a cert with this fingerprint yet.
@@ -66,7 +90,7 @@ pub enum Action {
     Update,
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum Upload {
     /// The UI recommends uploading this cert
     ///
@@ -88,7 +112,7 @@ pub enum Upload {
 }
 
 /// A container for information about a "bad" Cert.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ReturnBadJson {
     pub error: Vec<CertError>, // FIXME: read/write access methods?
     pub cert_info: Option<CertInfo>,
@@ -106,7 +130,7 @@ impl ReturnBadJson {
 }
 
 /// User-provided input data for OpenPGP CA RESTD
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Certificate {
     /// email addresses that the organization associates with this user
     pub email: Vec<String>,
@@ -153,7 +177,7 @@ impl Certificate {
 
 /// A ReturnError gets returned when a request fails before OpenPGP CA RESTD
 /// splits the input "Certificate" data into individual Certs.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ReturnError {
     pub status: ReturnStatus,
 
@@ -180,15 +204,32 @@ impl ReturnError {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum ReturnStatus {
     BadKeyring,
+    BadRevocation,
+    BadFingerprint,
+    BadReplacement,
+    BadSplitResponse,
     NotFound,
+
+    /// The change is not allowed in the write mode of restd (read-only, or
+    /// changes need approval by a CA admin)
+    WriteProtected,
+
     InternalError,
 }
 
+/// A change that was queued for approval by a CA admin (restd in approval
+/// mode), instead of being applied
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct QueuedChange {
+    /// id of the proposed change (see `openpgp-ca queue list`)
+    pub id: i32,
+}
+
 /// A CertError gives error information about one specific Cert.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CertError {
     /// This status code should be mapped to a message that is shown to end
     /// users.
@@ -239,7 +280,7 @@ impl CertError {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum CertStatus {
     /// The cert failed a policy check, it cannot be used.
     ///
@@ -262,9 +303,29 @@ pub enum CertStatus {
     /// not suitable for use in this service.
     CertMissingLocalUserId,
 
+    /// The cert doesn't meet the key requirements of this CA (e.g.
+    /// insufficient key size, or missing subkeys).
+    ///
+    /// There is one error with this status for each unmet requirement.
+    KeyPolicy,
+
+    /// The cert is an update of a cert in the CA database, but merging it
+    /// would roll back the stored cert (e.g. an earlier expiration time, or
+    /// a removed revocation).
+    ///
+    /// There is one error with this status for each detected downgrade.
+    Downgrade,
+
     /// A bad email address was provided in 'Certificate'
     BadEmail,
 
+    /// The cert, or the domain of an email address in 'Certificate', is on
+    /// the blocklist of this CA.
+    ///
+    /// There is one error with this status for each blocked key or email
+    /// address.
+    Blocked,
+
     /// The provided OpenPGP Cert exceeds the allowed size limit
     CertSizeLimit,
 
@@ -278,7 +339,7 @@ pub enum CertStatus {
     InternalError,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Warning {
     status: WarnStatus,
 
@@ -301,11 +362,239 @@ impl Warning {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl From<CertWarning> for Warning {
+    fn from(w: CertWarning) -> Self {
+        let status = match w.kind {
+            CertWarningKind::ExpiresSoon => WarnStatus::ExpiresSoon,
+            CertWarningKind::WeakCryptoSHA1 => WarnStatus::WeakCryptoSHA1,
+            CertWarningKind::WeakKey => WarnStatus::WeakKey,
+            CertWarningKind::UnboundUserId => WarnStatus::UnboundUserId,
+            CertWarningKind::MissingUserId => WarnStatus::MissingUserId,
+        };
+
+        Warning::new(status, w.message)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub enum WarnStatus {
     ExpiresSoon,
 
     // The capitalization of this constant is part of the external API
     #[allow(clippy::upper_case_acronyms)]
     WeakCryptoSHA1,
+
+    WeakKey,
+
+    UnboundUserId,
+
+    MissingUserId,
+}
+
+/// Status of a periodic task of the restd scheduler
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TaskStatus {
+    /// e.g. "update_keyserver"
+    pub name: String,
+
+    /// Time between the end of one run and the start of the next run
+    pub interval_secs: u64,
+
+    /// The task is currently running
+    pub running: bool,
+
+    /// Number of completed runs
+    pub runs: u64,
+
+    pub last_start: Option<DateTime<Utc>>,
+    pub last_end: Option<DateTime<Utc>>,
+
+    /// Error of the last run (None, if it succeeded)
+    pub last_error: Option<String>,
+
+    /// Summary of the outcome of the last successful run (if the task
+    /// reports one)
+    pub last_result: Option<String>,
+
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+/// Request to stage a user's welcome kit (see
+/// [openpgp_ca_lib::Oca::user_provisioning_bundle]) for a one-time download
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct StageDownload {
+    /// Fingerprint of the user's cert
+    pub fingerprint: String,
+
+    /// The user's armored private key (e.g. a generated TSK). If None, the
+    /// kit contains the user's public cert.
+    pub private_key: Option<String>,
+
+    /// Validity of the download link (default: 24 hours)
+    pub expires_in_minutes: Option<u64>,
+
+    /// Who staged the kit (for the audit log)
+    pub staged_by: Option<String>,
+}
+
+/// A staged welcome kit, as returned to the admin who staged it
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DownloadLink {
+    /// Identifier of the staged kit (for listing and withdrawing)
+    pub id: String,
+
+    /// Path of the one-time download URL, relative to the restd base URL.
+    /// The path contains the secret download token.
+    pub url: String,
+
+    pub expires: DateTime<Utc>,
+}
+
+/// A staged welcome kit that has not been downloaded yet (without its
+/// download token)
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct StagedDownload {
+    pub id: String,
+    pub fingerprint: String,
+    pub staged_by: Option<String>,
+    pub created: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadEvent {
+    /// A kit was staged
+    Staged,
+
+    /// A kit was downloaded (and deleted)
+    Downloaded,
+
+    /// A kit expired before it was downloaded (and was deleted)
+    Expired,
+
+    /// A kit was withdrawn by an admin (and deleted)
+    Withdrawn,
+
+    /// A download was attempted with an unknown, expired or already used
+    /// token
+    Rejected,
+}
+
+/// An entry in the audit log of welcome kit downloads
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DownloadAuditEntry {
+    pub time: DateTime<Utc>,
+    pub event: DownloadEvent,
+
+    /// Identifier of the staged kit (None for rejected downloads)
+    pub id: Option<String>,
+
+    /// Fingerprint of the user's cert (None for rejected downloads)
+    pub fingerprint: Option<String>,
+
+    /// The admin who staged the kit, otherwise the remote address of the
+    /// request
+    pub actor: Option<String>,
+}
+
+/// The outcome of posting a response of a split mode back instance
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SplitImport {
+    /// Number of newly imported entries (entries that had already been
+    /// imported are ignored)
+    pub imported: usize,
+}
+
+/// An armored revocation certificate, deposited by a user (e.g. for a key
+/// that was generated decentrally)
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RevocationUpload {
+    pub revocation: String,
+}
+
+/// A replacement for a user cert, submitted by its user.
+///
+/// `handover` is an armored detached signature by the old cert, over the
+/// statement for this replacement (see `/replacements/statement`).
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KeyReplacementUpload {
+    /// Fingerprint of the cert that is replaced
+    pub fingerprint: String,
+
+    /// The armored replacement cert
+    pub cert: String,
+
+    pub handover: String,
+}
+
+/// Why an admin rejected a key replacement
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KeyReplacementRejection {
+    pub reason: Option<String>,
+}
+
+/// A key replacement request
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KeyReplacementInfo {
+    /// Identifier of the request (for approving or rejecting it)
+    pub id: i32,
+
+    /// Fingerprint of the cert that is replaced
+    pub fingerprint: String,
+
+    pub new_fingerprint: String,
+
+    /// "pending", "approved" or "rejected"
+    pub status: String,
+
+    pub created: DateTime<Utc>,
+    pub decided: Option<DateTime<Utc>>,
+
+    /// Why the request was rejected
+    pub reason: Option<String>,
+}
+
+impl KeyReplacementInfo {
+    pub fn from(replacement: models::KeyReplacement, fingerprint: String) -> Self {
+        KeyReplacementInfo {
+            id: replacement.id,
+            fingerprint,
+            new_fingerprint: replacement.new_fingerprint,
+            status: replacement.status,
+            created: Utc.from_utc_datetime(&replacement.created),
+            decided: replacement.decided.map(|d| Utc.from_utc_datetime(&d)),
+            reason: replacement.reason,
+        }
+    }
+}
+
+/// Revocation status of a cert, as a statement signed by the CA
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SignedCertStatusJson {
+    /// JSON serialization of the status: fingerprints of the CA and the
+    /// cert, "good"/"revoked"/"unknown", revocation time and reason (if
+    /// revoked), and the "produced_at" and "next_update" timestamps
+    pub status: String,
+
+    /// Armored detached signature over `status` by the CA
+    pub signature: String,
+}
+
+impl From<SignedCertStatus> for SignedCertStatusJson {
+    fn from(scs: SignedCertStatus) -> Self {
+        SignedCertStatusJson {
+            status: scs.status,
+            signature: scs.signature,
+        }
+    }
+}
+
+impl From<SignedCertStatusJson> for SignedCertStatus {
+    fn from(scs: SignedCertStatusJson) -> Self {
+        SignedCertStatus {
+            status: scs.status,
+            signature: scs.signature,
+        }
+    }
 }
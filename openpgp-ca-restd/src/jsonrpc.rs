@@ -0,0 +1,412 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! JSON-RPC 2.0 interface to the core operations of an OpenPGP CA, for
+//! scripting languages and provisioning agents.
+//!
+//! The methods of [openpgp_ca_lib::api] (which the C bindings also offer)
+//! are available, along with methods that return certs in the
+//! representation of the REST interface.
+//!
+//! Requests and responses are exchanged as JSON documents, one per line,
+//! either via stdin/stdout or via a unix domain socket. Batch requests are
+//! not supported. The method "rpc.discover" returns the JSON schemas of
+//! the parameters and results of all methods.
+//!
+//! Access control relies on the transport: the socket is only accessible
+//! to the user that runs the daemon. As with restd, changes to the CA emit
+//! the CA's events (see [openpgp_ca_lib::events]), and all calls can be
+//! recorded in an audit log file (as JSON lines).
+
+use std::fs::{DirBuilder, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use openpgp_ca_lib::api::{self, CallError, FingerprintParams, MethodDoc};
+use openpgp_ca_lib::{pgp, Oca};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::json::{Certificate, ReturnError};
+use crate::restd::{self, CERT_SIZE_LIMIT};
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Error code for failed CA operations
+const CA_ERROR: i64 = -32000;
+
+/// The methods that restd offers in addition to those of
+/// [api::METHODS]
+pub const RESTD_METHODS: &[&str] = &[
+    "certs.get",
+    "certs.by_email",
+    "certs.update",
+    "rpc.discover",
+];
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+
+    /// Absent for notifications (which get no response)
+    id: Option<Value>,
+
+    method: String,
+
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        RpcError::new(CA_ERROR, format!("{e:#}"))
+    }
+}
+
+impl From<ReturnError> for RpcError {
+    fn from(e: ReturnError) -> Self {
+        RpcError::new(CA_ERROR, e.msg)
+    }
+}
+
+impl From<CallError> for RpcError {
+    fn from(e: CallError) -> Self {
+        let code = match e {
+            CallError::UnknownMethod(_) => METHOD_NOT_FOUND,
+            CallError::InvalidParams(_) => INVALID_PARAMS,
+            CallError::Failed(_) => CA_ERROR,
+        };
+
+        RpcError::new(code, e.to_string())
+    }
+}
+
+/// Parameters of "certs.by_email"
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct EmailParams {
+    pub email: String,
+}
+
+/// Parameters of "certs.update"
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateParams {
+    /// The armored public key of the user, merged into the stored cert
+    pub cert: String,
+}
+
+/// One call, as recorded in the audit log
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub time: DateTime<Utc>,
+    pub method: String,
+
+    /// Error message, if the call failed
+    pub error: Option<String>,
+}
+
+fn schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Value {
+    serde_json::to_value(gen.subschema_for::<T>()).expect("schema serialization failed")
+}
+
+fn method_doc(method: &str, gen: &mut SchemaGenerator) -> MethodDoc {
+    let (summary, params, result) = match method {
+        "certs.get" => (
+            "Get the cert with a fingerprint (null if not found)",
+            schema::<FingerprintParams>(gen),
+            schema::<Option<Certificate>>(gen),
+        ),
+        "certs.by_email" => (
+            "Get all certs for an email address",
+            schema::<EmailParams>(gen),
+            schema::<Vec<Certificate>>(gen),
+        ),
+        "certs.update" => (
+            "Merge an update into a stored user cert",
+            schema::<UpdateParams>(gen),
+            schema::<Certificate>(gen),
+        ),
+        "rpc.discover" => (
+            "Describe all methods, with JSON schemas of their parameters and results",
+            Value::Null,
+            json!({ "type": "object" }),
+        ),
+        _ => {
+            return api::method_doc(method, gen)
+                .unwrap_or_else(|| unreachable!("undocumented method {}", method))
+        }
+    };
+
+    MethodDoc {
+        name: method.to_string(),
+        summary: summary.to_string(),
+        params,
+        result,
+    }
+}
+
+/// All methods, in the order in which "rpc.discover" lists them
+pub fn methods() -> Vec<&'static str> {
+    api::METHODS.iter().chain(RESTD_METHODS).copied().collect()
+}
+
+/// The methods of the interface, and the schema definitions that they refer to
+fn discover() -> Value {
+    let mut gen = SchemaSettings::draft07().into_generator();
+
+    let methods: Vec<_> = methods().iter().map(|m| method_doc(m, &mut gen)).collect();
+
+    json!({
+        "methods": methods,
+        "definitions": gen.definitions(),
+    })
+}
+
+fn params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(CA_ERROR, e.to_string()))
+}
+
+/// The cert with `fingerprint`, with its associated data
+fn certificate(ca: &Oca, fingerprint: &str) -> Result<Option<Certificate>, RpcError> {
+    match ca.cert_get_by_fingerprint(fingerprint)? {
+        Some(cert) => Ok(Some(restd::load_certificate_data(ca, &cert)?)),
+        None => Ok(None),
+    }
+}
+
+fn check_size(armored: &str) -> Result<(), RpcError> {
+    if armored.len() > CERT_SIZE_LIMIT {
+        return Err(RpcError::new(INVALID_PARAMS, "Size exceeds limit"));
+    }
+
+    Ok(())
+}
+
+fn call(ca: &Oca, method: &str, p: Value) -> Result<Value, RpcError> {
+    match method {
+        "certs.get" => {
+            let p: FingerprintParams = params(p)?;
+            to_value(certificate(ca, &p.fingerprint)?)
+        }
+        "certs.by_email" => {
+            let p: EmailParams = params(p)?;
+
+            let mut res = vec![];
+            for cert in ca.certs_by_email(&p.email)? {
+                res.push(restd::load_certificate_data(ca, &cert)?);
+            }
+            to_value(res)
+        }
+        "certs.update" => {
+            let p: UpdateParams = params(p)?;
+            check_size(&p.cert)?;
+
+            let fingerprint = pgp::to_cert(p.cert.as_bytes())?.fingerprint().to_hex();
+
+            ca.cert_import_update(p.cert.as_bytes())?;
+
+            to_value(certificate(ca, &fingerprint)?)
+        }
+        "rpc.discover" => Ok(discover()),
+        _ => api::call(ca, method, p).map_err(RpcError::from),
+    }
+}
+
+/// Append `entry` to the audit log file at `path`.
+///
+/// Failing to write the audit log doesn't interrupt the flow.
+fn audit(path: Option<&Path>, entry: AuditEntry) {
+    if let Some(path) = path {
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            if let Ok(line) = serde_json::to_string(&entry) {
+                let _ = writeln!(file, "{line}");
+            }
+        }
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> String {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    };
+
+    response.to_string()
+}
+
+/// Handle one JSON-RPC request (`line`).
+///
+/// Returns the serialized response, or None for notifications. Calls are
+/// recorded in the audit log at `audit_log`, if set.
+pub fn handle(ca: &Oca, line: &str, audit_log: Option<&Path>) -> Option<String> {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => {
+            return Some(response(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, e.to_string())),
+            ))
+        }
+    };
+
+    let request: Request = match serde_json::from_value(value) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(response(
+                Value::Null,
+                Err(RpcError::new(INVALID_REQUEST, e.to_string())),
+            ))
+        }
+    };
+
+    let result = if request.jsonrpc != "2.0" {
+        Err(RpcError::new(
+            INVALID_REQUEST,
+            "Only JSON-RPC 2.0 is supported",
+        ))
+    } else {
+        call(ca, &request.method, request.params)
+    };
+
+    audit(
+        audit_log,
+        AuditEntry {
+            time: Utc::now(),
+            method: request.method,
+            error: result.as_ref().err().map(|e| e.message.clone()),
+        },
+    );
+
+    request.id.map(|id| response(id, result))
+}
+
+/// Serve requests from `input`, writing the responses to `output`, until
+/// the end of `input`
+fn serve_stream(
+    ca: &Oca,
+    input: impl BufRead,
+    mut output: impl Write,
+    audit_log: Option<&Path>,
+) -> Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(response) = handle(ca, &line, audit_log) {
+            writeln!(output, "{response}")?;
+            output.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Serve requests from stdin, until stdin is closed
+pub fn serve_stdio(db: Option<String>, audit_log: Option<PathBuf>) -> Result<()> {
+    let ca = Oca::open(db.as_deref())?;
+
+    serve_stream(
+        &ca,
+        std::io::stdin().lock(),
+        std::io::stdout().lock(),
+        audit_log.as_deref(),
+    )
+}
+
+/// Bind a unix domain socket at `socket` that is only accessible to the
+/// current user.
+///
+/// The socket is bound in a private (0700) directory next to `socket`, and
+/// only moved to `socket` after its permissions are restricted to 0600. So
+/// other users can't connect in the window between bind and chmod.
+pub(crate) fn bind_private(socket: &Path) -> Result<UnixListener> {
+    if socket.exists() {
+        return Err(anyhow::anyhow!("Can't bind to {socket:?}: file exists"));
+    }
+
+    let name = socket
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid socket path {socket:?}"))?;
+    let parent = match socket.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+
+    let dir = parent.join(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    DirBuilder::new()
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("Can't create directory {dir:?}"))?;
+
+    let tmp = dir.join("socket");
+    let res = UnixListener::bind(&tmp)
+        .with_context(|| format!("Can't bind to {socket:?}"))
+        .and_then(|listener| {
+            std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
+            std::fs::rename(&tmp, socket)
+                .with_context(|| format!("Can't move socket to {socket:?}"))?;
+            Ok(listener)
+        });
+
+    // The socket was moved out of `dir`, unless there was an error
+    let _ = std::fs::remove_file(&tmp);
+    let _ = std::fs::remove_dir(&dir);
+
+    res
+}
+
+/// Serve requests on a unix domain socket at `socket` (which is only
+/// accessible to the current user). Connections are handled one after
+/// the other.
+pub fn serve_unix(db: Option<String>, socket: &Path, audit_log: Option<PathBuf>) -> Result<()> {
+    let ca = Oca::open(db.as_deref())?;
+
+    let listener = bind_private(socket)?;
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let input = BufReader::new(stream.try_clone()?);
+
+        // A failing connection doesn't stop the daemon
+        let _ = serve_stream(&ca, input, stream, audit_log.as_deref());
+    }
+
+    Ok(())
+}
@@ -11,7 +11,12 @@ extern crate rocket;
 
 pub mod cert_info;
 pub mod client;
+pub mod downloads;
 pub mod json;
+pub mod jsonrpc;
+pub mod openapi;
 pub mod process_certs;
 pub mod restd;
+pub mod scheduler;
+pub mod unix_socket;
 pub mod util;
@@ -0,0 +1,426 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! OpenAPI description of the restd JSON API.
+//!
+//! The document is generated from the mounted Rocket routes. Schemas for
+//! request and response bodies are derived from the types in [crate::json].
+
+use rocket::Route;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+use crate::cert_info::CertInfo;
+use crate::json::{
+    CertResultJson, Certificate, DownloadAuditEntry, DownloadLink, KeyReplacementInfo,
+    KeyReplacementRejection, KeyReplacementUpload, QueuedChange, ReturnError, ReturnGoodJson,
+    RevocationInfo, RevocationUpload, SignedCertStatusJson, SplitImport, StageDownload,
+    StagedDownload, TaskStatus,
+};
+
+/// The response of a route, in case of success
+enum Response {
+    /// No body
+    Empty,
+
+    /// A JSON body of the given schema
+    Json(Value),
+
+    /// A plain text body (404 if not found)
+    Text(&'static str),
+
+    /// A body of the given media type and schema, only for clients that send
+    /// a bearer token (401 for a wrong token, 403 if the route is disabled)
+    Authenticated(&'static str, Value),
+
+    /// A JSON body of the given schema (or no body, for None), or a
+    /// `QueuedChange` with status 202 if the change was queued for approval
+    /// (restd in approval mode)
+    Queueable(Option<Value>),
+}
+
+/// Documentation for one route, looked up by the name of its handler
+struct RouteDoc {
+    summary: &'static str,
+    request: Option<Value>,
+    response: Response,
+
+    /// The route can fail with a `ReturnError`
+    bad_request: bool,
+}
+
+fn schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Value {
+    serde_json::to_value(gen.subschema_for::<T>()).expect("schema serialization failed")
+}
+
+fn route_doc(handler: &str, gen: &mut SchemaGenerator) -> Option<RouteDoc> {
+    let doc = |summary, request, response, bad_request| RouteDoc {
+        summary,
+        request,
+        response,
+        bad_request,
+    };
+
+    Some(match handler {
+        "certs_by_email" => doc(
+            "Get all certs for an email address",
+            None,
+            Response::Json(schema::<Vec<ReturnGoodJson>>(gen)),
+            true,
+        ),
+        "cert_by_fp" => doc(
+            "Get a cert by fingerprint",
+            None,
+            Response::Json(schema::<Option<ReturnGoodJson>>(gen)),
+            true,
+        ),
+        "lookup_cert" => doc(
+            "Get the currently valid, CA-certified cert for an email address",
+            None,
+            Response::Text("application/pgp-keys"),
+            true,
+        ),
+        "check_certs" => doc(
+            "Check a cert (without storing it)",
+            Some(schema::<Certificate>(gen)),
+            Response::Json(schema::<Vec<CertResultJson>>(gen)),
+            true,
+        ),
+        "post_certs" => doc(
+            "Store a new cert, or update an existing one",
+            Some(schema::<Certificate>(gen)),
+            Response::Json(schema::<Vec<CertResultJson>>(gen)),
+            true,
+        ),
+        "deactivate_cert" => doc(
+            "Mark a cert as inactive",
+            None,
+            Response::Queueable(None),
+            true,
+        ),
+        "delist_cert" => doc(
+            "Mark a cert as delisted (it will not be published)",
+            None,
+            Response::Queueable(None),
+            true,
+        ),
+        "refresh_certifications" => doc(
+            "Renew CA certifications that expire soon",
+            None,
+            Response::Empty,
+            true,
+        ),
+        "poll_for_updates" => doc("Not implemented", None, Response::Empty, false),
+        "check_expiring" => doc(
+            "Get all certs that expire within the given number of days",
+            None,
+            Response::Json(schema::<Vec<CertInfo>>(gen)),
+            true,
+        ),
+        "ping" => doc(
+            "Check if the service is running",
+            None,
+            Response::Empty,
+            false,
+        ),
+        "healthz" => doc(
+            "Check if the service can access the CA database",
+            None,
+            Response::Empty,
+            false,
+        ),
+        "scheduler_status" => doc(
+            "Get the status of the periodic tasks of the scheduler",
+            None,
+            Response::Json(schema::<Vec<TaskStatus>>(gen)),
+            false,
+        ),
+        "stage_download" => doc(
+            "Stage a user's welcome kit for a one-time download",
+            Some(schema::<StageDownload>(gen)),
+            Response::Json(schema::<DownloadLink>(gen)),
+            true,
+        ),
+        "staged_downloads" => doc(
+            "Get the staged welcome kits that have not been downloaded yet",
+            None,
+            Response::Json(schema::<Vec<StagedDownload>>(gen)),
+            false,
+        ),
+        "withdraw_download" => doc(
+            "Withdraw a staged welcome kit (404 if not found)",
+            None,
+            Response::Empty,
+            false,
+        ),
+        "downloads_audit" => doc(
+            "Get the recent audit log of welcome kit downloads",
+            None,
+            Response::Json(schema::<Vec<DownloadAuditEntry>>(gen)),
+            false,
+        ),
+        "download" => doc(
+            "Download a staged welcome kit (works only once)",
+            None,
+            Response::Text("application/pgp-keys"),
+            false,
+        ),
+        "wkd_export" => doc(
+            "Download the WKD directory structure for the CA domain as a tar archive",
+            None,
+            Response::Authenticated(
+                "application/x-tar",
+                json!({ "type": "string", "format": "binary" }),
+            ),
+            true,
+        ),
+        "split_requests" => doc(
+            "Get the pending requests of a split mode front instance, for the back instance",
+            None,
+            Response::Authenticated("application/json", json!({ "type": "object" })),
+            true,
+        ),
+        "split_responses" => doc(
+            "Post the response of a split mode back instance to the front instance",
+            Some(json!({ "type": "object" })),
+            Response::Authenticated("application/json", schema::<SplitImport>(gen)),
+            true,
+        ),
+        "post_revocation" => doc(
+            "Deposit a revocation certificate for a user cert (it is stored, but not applied)",
+            Some(schema::<RevocationUpload>(gen)),
+            Response::Queueable(Some(schema::<RevocationInfo>(gen))),
+            true,
+        ),
+        "revocations" => doc(
+            "Get all revocation certificates in the CA database",
+            None,
+            Response::Json(schema::<Vec<RevocationInfo>>(gen)),
+            true,
+        ),
+        "delete_revocation" => doc(
+            "Remove a revocation certificate that has not been applied (404 if not found)",
+            None,
+            Response::Queueable(None),
+            true,
+        ),
+        "replacement_statement" => doc(
+            "Get the statement that a user signs with their current key, to replace it",
+            None,
+            Response::Text("text/plain"),
+            true,
+        ),
+        "submit_replacement" => doc(
+            "Submit a replacement for a user cert, signed with the old cert (it is staged for approval)",
+            Some(schema::<KeyReplacementUpload>(gen)),
+            Response::Json(schema::<KeyReplacementInfo>(gen)),
+            true,
+        ),
+        "pending_replacements" => doc(
+            "Get the key replacements that wait for approval",
+            None,
+            Response::Json(schema::<Vec<KeyReplacementInfo>>(gen)),
+            true,
+        ),
+        "approve_replacement" => doc(
+            "Approve a key replacement (the new cert is stored and certified, the old cert is deactivated)",
+            None,
+            Response::Json(schema::<KeyReplacementInfo>(gen)),
+            true,
+        ),
+        "reject_replacement" => doc(
+            "Reject a key replacement",
+            Some(schema::<KeyReplacementRejection>(gen)),
+            Response::Json(schema::<KeyReplacementInfo>(gen)),
+            true,
+        ),
+        "cert_status" => doc(
+            "Get the revocation status of a cert (good, revoked or unknown), signed by the CA",
+            None,
+            Response::Json(schema::<SignedCertStatusJson>(gen)),
+            true,
+        ),
+        "openapi_json" => doc(
+            "This OpenAPI document",
+            None,
+            Response::Json(json!({ "type": "object" })),
+            false,
+        ),
+        _ => return None,
+    })
+}
+
+/// Turn a Rocket path ("/certs/by_fp/<fp>") into an OpenAPI path
+/// ("/certs/by_fp/{fp}") and a list of its parameter names
+fn path_and_params(path: &str) -> (String, Vec<String>) {
+    let mut params = vec![];
+
+    let segments: Vec<String> = path
+        .split('/')
+        .map(|segment| {
+            if let Some(param) = segment.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+                params.push(param.to_string());
+                format!("{{{param}}}")
+            } else {
+                segment.to_string()
+            }
+        })
+        .collect();
+
+    (segments.join("/"), params)
+}
+
+fn operation(route: &Route, gen: &mut SchemaGenerator) -> Value {
+    let handler = route.name.as_deref().unwrap_or_default();
+    let (_, params) = path_and_params(route.uri.path());
+
+    let mut op = Map::new();
+    op.insert("operationId".to_string(), json!(handler));
+
+    let parameters: Vec<Value> = params
+        .iter()
+        .map(|name| {
+            let typ = if name == "days" { "integer" } else { "string" };
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": typ },
+            })
+        })
+        .collect();
+    if !parameters.is_empty() {
+        op.insert("parameters".to_string(), json!(parameters));
+    }
+
+    let mut responses = Map::new();
+
+    if let Some(doc) = route_doc(handler, gen) {
+        op.insert("summary".to_string(), json!(doc.summary));
+
+        if let Some(request) = doc.request {
+            op.insert(
+                "requestBody".to_string(),
+                json!({
+                    "required": true,
+                    "content": { "application/json": { "schema": request } },
+                }),
+            );
+        }
+
+        match doc.response {
+            Response::Empty => {
+                responses.insert("200".to_string(), json!({ "description": "Success" }));
+            }
+            Response::Json(schema) => {
+                responses.insert(
+                    "200".to_string(),
+                    json!({
+                        "description": "Success",
+                        "content": { "application/json": { "schema": schema } },
+                    }),
+                );
+            }
+            Response::Text(media_type) => {
+                responses.insert(
+                    "200".to_string(),
+                    json!({
+                        "description": "Success",
+                        "content": { media_type: { "schema": { "type": "string" } } },
+                    }),
+                );
+                responses.insert("404".to_string(), json!({ "description": "Not found" }));
+            }
+            Response::Authenticated(media_type, schema) => {
+                responses.insert(
+                    "200".to_string(),
+                    json!({
+                        "description": "Success",
+                        "content": { media_type: { "schema": schema } },
+                    }),
+                );
+                responses.insert("401".to_string(), json!({ "description": "Unauthorized" }));
+                responses.insert("403".to_string(), json!({ "description": "Disabled" }));
+                op.insert("security".to_string(), json!([{ "bearerAuth": [] }]));
+            }
+            Response::Queueable(schema) => {
+                let success = match schema {
+                    Some(schema) => json!({
+                        "description": "Success",
+                        "content": { "application/json": { "schema": schema } },
+                    }),
+                    None => json!({ "description": "Success" }),
+                };
+                responses.insert("200".to_string(), success);
+                responses.insert(
+                    "202".to_string(),
+                    json!({
+                        "description": "Queued for approval",
+                        "content": {
+                            "application/json": { "schema": schema::<QueuedChange>(gen) }
+                        },
+                    }),
+                );
+            }
+        }
+
+        if doc.bad_request {
+            responses.insert(
+                "400".to_string(),
+                json!({
+                    "description": "Error",
+                    "content": {
+                        "application/json": { "schema": schema::<ReturnError>(gen) }
+                    },
+                }),
+            );
+        }
+    } else {
+        responses.insert("200".to_string(), json!({ "description": "Success" }));
+    }
+
+    op.insert("responses".to_string(), Value::Object(responses));
+
+    Value::Object(op)
+}
+
+/// Generate the OpenAPI document for `routes`
+pub fn openapi(routes: &[Route]) -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+
+    let mut paths = Map::new();
+    for route in routes {
+        let (path, _) = path_and_params(route.uri.path());
+        let method = route.method.as_str().to_ascii_lowercase();
+
+        let op = operation(route, &mut gen);
+
+        if let Value::Object(item) = paths
+            .entry(path)
+            .or_insert_with(|| Value::Object(Map::new()))
+        {
+            item.insert(method, op);
+        }
+    }
+
+    let schemas = serde_json::to_value(gen.definitions()).expect("schema serialization failed");
+
+    json!({
+        "openapi": "3.0.0",
+        "info": {
+            "title": "OpenPGP CA restd",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "bearerAuth": { "type": "http", "scheme": "bearer" },
+            },
+        },
+    })
+}
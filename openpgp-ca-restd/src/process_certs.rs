@@ -6,19 +6,17 @@
 // SPDX-FileCopyrightText: 2019-2022 Heiko Schaefer <heiko@schaefer.name>
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use core::time::Duration;
 use std::collections::HashSet;
 use std::convert::TryInto;
 use std::error::Error;
 use std::ops::Deref;
 use std::str::FromStr;
-use std::time::SystemTime;
 
 use openpgp_ca_lib::pgp;
 use openpgp_ca_lib::Oca;
 use sequoia_openpgp::cert::ValidCert;
 use sequoia_openpgp::policy::StandardPolicy;
-use sequoia_openpgp::types::{HashAlgorithm, PublicKeyAlgorithm, RevocationStatus};
+use sequoia_openpgp::types::{PublicKeyAlgorithm, RevocationStatus};
 use sequoia_openpgp::{Cert, Message, Packet};
 
 use crate::cert_info::CertInfo;
@@ -26,81 +24,22 @@ use crate::json::*;
 use crate::restd;
 use crate::util::{is_email_in_domain, split_emails, user_id_filter};
 
-const STANDARD_POLICY: &StandardPolicy = &StandardPolicy::new();
-
-/// Warnings for this cert.
-///
-/// Warnings are currently generated for:
-/// - Standard Policy gives an error for 'now + 2 years'
-/// - Cert is not alive() in 'now + 3 months'
+/// Warnings for this cert (see [Oca::cert_warnings]).
 ///
 /// Assumption: the cert has been checked and found good by the
-/// StandardPolicy for `now`.
-pub fn get_warnings(cert: &Cert) -> Result<Option<Vec<Warning>>, CertError> {
-    let mut warns = Vec::new();
-    let now = SystemTime::now();
-
-    // Check if StandardPolicy is bad in 'now + 2 years', but good when
-    // allowing for SHA1.
-    let now2y = now
-        .clone()
-        .checked_add(Duration::from_secs(60 * 60 * 24 * 365 * 2))
-        .ok_or_else(|| {
-            CertError::new(
-                CertStatus::InternalError,
-                "cert_to_warn: duration checked_add failed",
-            )
-        })?;
-
-    let policy_plus2y = StandardPolicy::at(now2y);
-
-    let valid2y = cert.with_policy(&policy_plus2y, Some(now2y));
-
-    let mut sp_plus_sha1 = StandardPolicy::at(now2y);
-    sp_plus_sha1.accept_hash(HashAlgorithm::SHA1);
-    let valid2y_sha1 = cert.with_policy(&sp_plus_sha1, now2y);
-
-    if valid2y.is_err() && valid2y_sha1.is_ok() {
-        warns.push(Warning::new(
-            WarnStatus::WeakCryptoSHA1,
-            "This certificate relies on SHA1 hashes, which are deprecated. It should be updated!",
-        ));
-    }
-
-    // Check if cert is alive() now, but will not be in 'now + 3 months'.
-    // If so: warn about imminent expiry.
-
-    let now3m = now
-        .clone()
-        .checked_add(Duration::from_secs(60 * 60 * 24 * 30 * 3))
-        .ok_or_else(|| {
-            CertError::new(
-                CertStatus::InternalError,
-                "cert_to_warn: duration checked_add failed",
-            )
-        })?;
-
-    let policy_plus3m = StandardPolicy::at(now3m);
-
-    let valid_cert_now = cert.with_policy(STANDARD_POLICY, None);
-
-    let valid_cert_3m = cert.with_policy(&policy_plus3m, Some(now3m));
-
-    if valid_cert_now.is_ok()
-        && valid_cert_now.unwrap().alive().is_ok()
-        && valid_cert_3m.is_ok()
-        && valid_cert_3m.unwrap().alive().is_err()
-    {
-        warns.push(Warning::new(
-            WarnStatus::ExpiresSoon,
-            "Will expire in the next 90 days",
-        ));
-    }
+/// policy of the CA for `now`.
+pub fn get_warnings(ca: &Oca, cert: &Cert) -> Result<Option<Vec<Warning>>, CertError> {
+    let warns = ca.cert_warnings(cert).map_err(|e| {
+        CertError::new(
+            CertStatus::InternalError,
+            format!("cert_to_warn: error checking the cert: {e:?}"),
+        )
+    })?;
 
     if warns.is_empty() {
         Ok(None)
     } else {
-        Ok(Some(warns))
+        Ok(Some(warns.into_iter().map(Warning::from).collect()))
     }
 }
 
@@ -113,9 +52,12 @@ pub fn get_cert_info(cert: &Cert) -> Result<CertInfo, ReturnError> {
     })
 }
 
-fn cert_policy_check(cert: &Cert) -> Result<ValidCert, CertError> {
-    // check if cert is valid according to sequoia standard policy
-    cert.with_policy(STANDARD_POLICY, None).map_err(|e| {
+fn cert_policy_check<'a>(
+    cert: &'a Cert,
+    policy: &'a StandardPolicy,
+) -> Result<ValidCert<'a>, CertError> {
+    // check if cert is valid according to the (CA-adjusted) standard policy
+    cert.with_policy(policy, None).map_err(|e| {
         CertError::new_with_url(
             CertStatus::BadCert,
             // restd::POLICY_BAD_URL.to_string(),
@@ -292,6 +234,52 @@ fn process_cert(
         }
     }
 
+    // new certs must meet the key policy of this CA, and must not be blocked
+    if !is_update {
+        let violations = ca.check_key_policy(cert).map_err(|e| {
+            let ce = CertError::new(
+                CertStatus::InternalError,
+                format!("process_cert: Error during key policy check: {e:?}"),
+            );
+            ReturnBadJson::new(ce, Some(cert_info.clone()))
+        })?;
+
+        if !violations.is_empty() {
+            let mut bad = ReturnBadJson::new(
+                CertError::new(CertStatus::KeyPolicy, violations[0].to_string()),
+                Some(cert_info.clone()),
+            );
+            for v in &violations[1..] {
+                bad.error
+                    .push(CertError::new(CertStatus::KeyPolicy, v.to_string()));
+            }
+
+            return Err(bad);
+        }
+
+        let emails: Vec<&str> = certificate.email.iter().map(|e| e.deref()).collect();
+        let blocked = ca.blocklist_check(Some(cert), &emails).map_err(|e| {
+            let ce = CertError::new(
+                CertStatus::InternalError,
+                format!("process_cert: Error during blocklist check: {e:?}"),
+            );
+            ReturnBadJson::new(ce, Some(cert_info.clone()))
+        })?;
+
+        if !blocked.is_empty() {
+            let mut bad = ReturnBadJson::new(
+                CertError::new(CertStatus::Blocked, blocked[0].to_string()),
+                Some(cert_info.clone()),
+            );
+            for b in &blocked[1..] {
+                bad.error
+                    .push(CertError::new(CertStatus::Blocked, b.to_string()));
+            }
+
+            return Err(bad);
+        }
+    }
+
     // merge new cert with existing cert, if any
     let merged = match cert_in_ca_db {
         None => cert.clone(),
@@ -305,7 +293,7 @@ fn process_cert(
                 ReturnBadJson::new(error, Some(cert_info.clone()))
             })?;
 
-            db_cert.merge_public(cert.clone()).map_err(|e| {
+            let merged = db_cert.clone().merge_public(cert.clone()).map_err(|e| {
                 let error = CertError::new(
                     CertStatus::InternalError,
                     format!(
@@ -315,7 +303,24 @@ fn process_cert(
                 );
 
                 ReturnBadJson::new(error, Some(cert_info.clone()))
-            })?
+            })?;
+
+            // updates must not roll back the cert in the CA database
+            let downgrades = pgp::cert_downgrades(&db_cert, &merged);
+            if !downgrades.is_empty() {
+                let mut bad = ReturnBadJson::new(
+                    CertError::new(CertStatus::Downgrade, downgrades[0].to_string()),
+                    Some(cert_info.clone()),
+                );
+                for d in &downgrades[1..] {
+                    bad.error
+                        .push(CertError::new(CertStatus::Downgrade, d.to_string()));
+                }
+
+                return Err(bad);
+            }
+
+            merged
         }
     };
     let _ = cert; // drop previous version of the cert
@@ -344,8 +349,15 @@ fn process_cert(
     }
 
     // perform sequoia policy check
-    let valid_cert =
-        cert_policy_check(&merged).map_err(|ce| ReturnBadJson::new(ce, Some(cert_info.clone())))?;
+    let policy = ca.policy().map_err(|e| {
+        let ce = CertError::new(
+            CertStatus::InternalError,
+            format!("process_cert: Error loading the CA policy: {e:?}"),
+        );
+        ReturnBadJson::new(ce, Some(cert_info.clone()))
+    })?;
+    let valid_cert = cert_policy_check(&merged, &policy)
+        .map_err(|ce| ReturnBadJson::new(ce, Some(cert_info.clone())))?;
     let _ = merged; // drop previous version of the cert
 
     // check if the cert is revoked
@@ -477,7 +489,20 @@ fn process_cert(
         inactive: Some(inactive),
     };
 
-    let warn = get_warnings(&norm).map_err(|ce| ReturnBadJson::new(ce, Some(cert_info.clone())))?;
+    let warn =
+        get_warnings(ca, &norm).map_err(|ce| ReturnBadJson::new(ce, Some(cert_info.clone())))?;
+
+    let tags = cert_in_ca_db
+        .as_ref()
+        .map(|c| restd::load_tags(ca, c))
+        .transpose()
+        .map_err(|e| {
+            let ce = CertError::new(
+                CertStatus::InternalError,
+                format!("process_cert: Error loading tags: {e:?}"),
+            );
+            ReturnBadJson::new(ce, Some(cert_info.clone()))
+        })?;
 
     Ok(ReturnGoodJson {
         certificate,
@@ -485,6 +510,8 @@ fn process_cert(
         warn,
         action,
         upload,
+        tags,
+        queued: None,
     })
 }
 
@@ -7,20 +7,36 @@
 //! REST Interface for OpenPGP CA.
 //! This is an experimental API for use at FSFE.
 
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::io::Cursor;
+use std::sync::RwLock;
+use std::time::Duration;
 
 use once_cell::sync::OnceCell;
+pub use openpgp_ca_lib::api::{CERT_SIZE_LIMIT, CERT_STATUS_MAX_AGE_SECS};
 use openpgp_ca_lib::db::models;
 use openpgp_ca_lib::pgp;
+use openpgp_ca_lib::types::{ExportCompat, ProposedChange};
 use openpgp_ca_lib::Oca;
-use rocket::http::Status;
-use rocket::response::status::BadRequest;
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::{ContentType, Header, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::content::RawJson;
+use rocket::response::status::{Accepted, BadRequest};
+use rocket::response::{self, Responder, Response};
 use rocket::serde::json::Json;
-use rocket::Build;
+use rocket::{Build, Route};
+use sequoia_openpgp::crypto::mem::{secure_cmp, Protected};
 
 use crate::cert_info::CertInfo;
+use crate::downloads;
 use crate::json::*;
+use crate::openapi::openapi;
 use crate::process_certs::{get_cert_info, get_warnings, process_certs};
+use crate::scheduler;
+use crate::unix_socket;
 
 static DB: OnceCell<Option<String>> = OnceCell::new();
 
@@ -32,15 +48,123 @@ thread_local! {
 // CA certifications are good for 365 days
 pub const CERTIFICATION_DAYS: u64 = 365;
 
-// armored cert size limit (1 MiB)
-pub const CERT_SIZE_LIMIT: usize = 1024 * 1024;
+// CA certifications get refreshed when they expire within 30 days
+pub const REFRESH_THRESHOLD_DAYS: u64 = 30;
+
+// split mode response size limit (64 MiB)
+pub const SPLIT_RESPONSE_SIZE_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Which changes to the CA database restd accepts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WriteMode {
+    /// All changes are applied right away
+    #[default]
+    Open,
+
+    /// No changes (e.g. for a public mirror of the CA)
+    ReadOnly,
+
+    /// Changes to certs are queued, and applied after a CA admin approves
+    /// them (with `openpgp-ca queue approve`)
+    Approval,
+}
+
+impl WriteMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WriteMode::Open => "open",
+            WriteMode::ReadOnly => "read-only",
+            WriteMode::Approval => "approval",
+        }
+    }
+}
+
+static WRITE_MODE: RwLock<WriteMode> = RwLock::new(WriteMode::Open);
+
+/// Set the write mode of restd (the default is [WriteMode::Open])
+pub fn set_write_mode(mode: WriteMode) {
+    *WRITE_MODE.write().unwrap() = mode;
+}
+
+pub fn write_mode() -> WriteMode {
+    *WRITE_MODE.read().unwrap()
+}
+
+fn write_protected(route: &str) -> ReturnError {
+    ReturnError::new(
+        ReturnStatus::WriteProtected,
+        format!(
+            "{route}: Not allowed, restd is in {} mode",
+            write_mode().name()
+        ),
+    )
+}
+
+/// Fail, unless all changes are allowed
+fn check_open(route: &str) -> Result<(), ReturnError> {
+    match write_mode() {
+        WriteMode::Open => Ok(()),
+        _ => Err(write_protected(route)),
+    }
+}
+
+/// The result of a request for a change: applied right away, or queued for
+/// approval (in [WriteMode::Approval])
+#[derive(Responder)]
+enum Change {
+    Applied(()),
+    Queued(Accepted<Json<QueuedChange>>),
+}
+
+/// The result of depositing a revocation certificate: stored right away, or
+/// queued for approval (in [WriteMode::Approval])
+#[derive(Responder)]
+enum RevocationChange {
+    Stored(Json<RevocationInfo>),
+    Queued(Accepted<Json<QueuedChange>>),
+}
+
+/// Queue `change` for approval by a CA admin, returns the id of the
+/// proposed change
+fn submit(ca: &Oca, route: &str, change: ProposedChange) -> Result<i32, ReturnError> {
+    let proposal = ca.proposal_submit(change).map_err(|e| {
+        ReturnError::new(
+            ReturnStatus::InternalError,
+            format!("{route}: Error queueing change '{e:#}'"),
+        )
+    })?;
+
+    Ok(proposal.id)
+}
+
+fn propose(ca: &Oca, route: &str, change: ProposedChange) -> Result<Change, ReturnError> {
+    let id = submit(ca, route, change)?;
+
+    Ok(Change::Queued(Accepted(Json(QueuedChange { id }))))
+}
 
 // FIXME: link for information about bad certificates
 // - and what to do about them
 // const POLICY_BAD_URL: &str = "https://very-bad-cert.example.org";
 
+/// Load the tags on a Cert and on its User from the CA database
+pub(crate) fn load_tags(ca: &Oca, cert: &models::Cert) -> anyhow::Result<Tags> {
+    let user = match ca.cert_get_users(cert)? {
+        Some(user) => ca.user_tags(&user)?,
+        None => Default::default(),
+    };
+
+    Ok(Tags {
+        cert: ca.cert_tags(cert)?,
+        user,
+    })
+}
+
 /// Load all of the associated data for a Cert from the CA database
-fn load_certificate_data(ca: &Oca, cert: &models::Cert) -> Result<Certificate, ReturnError> {
+pub(crate) fn load_certificate_data(
+    ca: &Oca,
+    cert: &models::Cert,
+) -> Result<Certificate, ReturnError> {
     let user = ca.cert_get_users(cert).map_err(|e| {
         ReturnError::new(
             ReturnStatus::InternalError,
@@ -101,7 +225,7 @@ fn certs_by_email(
             })?;
 
             let cert_info = get_cert_info(&cert)?;
-            let warn = get_warnings(&cert).map_err(|ce| {
+            let warn = get_warnings(ca, &cert).map_err(|ce| {
                 ReturnError::new(
                     ReturnStatus::InternalError,
                     format!("certs_by_email: error during cert_to_warn '{ce:?}'"),
@@ -109,6 +233,12 @@ fn certs_by_email(
             })?;
 
             let certificate = load_certificate_data(ca, &c)?;
+            let tags = load_tags(ca, &c).map_err(|e| {
+                ReturnError::new(
+                    ReturnStatus::InternalError,
+                    format!("certs_by_email: error loading tags '{e:?}'"),
+                )
+            })?;
 
             res.push(ReturnGoodJson {
                 certificate,
@@ -116,6 +246,8 @@ fn certs_by_email(
                 warn,
                 action: None,
                 upload: None,
+                tags: Some(tags),
+                queued: None,
             });
         }
 
@@ -135,6 +267,12 @@ fn cert_by_fp(fp: String) -> Result<Json<Option<ReturnGoodJson>>, BadRequest<Jso
 
         if let Some(c) = c {
             let certificate = load_certificate_data(ca, &c)?;
+            let tags = load_tags(ca, &c).map_err(|e| {
+                ReturnError::new(
+                    ReturnStatus::InternalError,
+                    format!("cert_by_fp: error loading tags '{e:?}'"),
+                )
+            })?;
 
             let cert = pgp::to_cert(c.pub_cert.as_bytes()).map_err(|e| {
                 ReturnError::new(
@@ -144,7 +282,7 @@ fn cert_by_fp(fp: String) -> Result<Json<Option<ReturnGoodJson>>, BadRequest<Jso
             })?;
 
             let cert_info = get_cert_info(&cert)?;
-            let warn = get_warnings(&cert).map_err(|ce| {
+            let warn = get_warnings(ca, &cert).map_err(|ce| {
                 ReturnError::new(
                     ReturnStatus::InternalError,
                     format!("cert_by_fp: error during cert_to_warn '{ce:?}'"),
@@ -157,6 +295,8 @@ fn cert_by_fp(fp: String) -> Result<Json<Option<ReturnGoodJson>>, BadRequest<Jso
                 warn,
                 action: None,
                 upload: None,
+                tags: Some(tags),
+                queued: None,
             })))
         } else {
             Ok(Json(None))
@@ -164,6 +304,35 @@ fn cert_by_fp(fp: String) -> Result<Json<Option<ReturnGoodJson>>, BadRequest<Jso
     })
 }
 
+/// Look up the currently valid, CA-certified cert for an email address.
+///
+/// Returns the armored cert, or 404 if there is no such cert.
+/// Intended as an organization-internal key discovery service.
+#[get("/certs/lookup/<email>")]
+fn lookup_cert(email: String) -> Result<Option<String>, BadRequest<Json<ReturnError>>> {
+    CA.with(|ca| {
+        let cert = ca.lookup_valid_cert(&email).map_err(|e| {
+            ReturnError::new(
+                ReturnStatus::InternalError,
+                format!("lookup_cert: error during lookup '{e:?}'"),
+            )
+        })?;
+
+        if let Some(cert) = cert {
+            let armored = pgp::cert_to_armored(&cert).map_err(|e| {
+                ReturnError::new(
+                    ReturnStatus::InternalError,
+                    format!("lookup_cert: error during cert_to_armored '{e:?}'"),
+                )
+            })?;
+
+            Ok(Some(armored))
+        } else {
+            Ok(None)
+        }
+    })
+}
+
 /// Similar to "post_user", but doesn't commit data to DB.
 ///
 /// Returns information about what the commit would result in.
@@ -183,11 +352,40 @@ fn check_certs(
 /// 2a) one notable specific case of this:
 ///     the user adds a revocation to their key (as an update).
 /// 3) store a "new" (i.e. different fingerprint) key for the same user
+///
+/// In approval mode, good certs are not stored, but queued for approval by
+/// a CA admin (the id of the proposed change is returned in "queued").
 #[post("/certs", data = "<certificate>", format = "json")]
 fn post_certs(
     certificate: Json<Certificate>,
 ) -> Result<Json<Vec<CertResultJson>>, BadRequest<Json<ReturnError>>> {
-    CA.with(|ca| Ok(Json(process_certs(ca, &certificate.into_inner(), true)?)))
+    let certificate = certificate.into_inner();
+
+    CA.with(|ca| match write_mode() {
+        WriteMode::Open => Ok(Json(process_certs(ca, &certificate, true)?)),
+        WriteMode::ReadOnly => Err(write_protected("post_certs").into()),
+        WriteMode::Approval => {
+            let mut res = process_certs(ca, &certificate, false)?;
+
+            for r in res.iter_mut() {
+                if let CertResultJson::Good(good) = r {
+                    let change = ProposedChange::CertImport {
+                        fingerprint: good.cert_info.primary.fingerprint.clone(),
+                        cert: good.certificate.cert.clone(),
+                        name: certificate.name.clone(),
+                        emails: certificate.email.clone(),
+                        revocations: certificate.revocations.clone(),
+                        days: Some(CERTIFICATION_DAYS),
+                    };
+
+                    good.queued = Some(submit(ca, "post_certs", change)?);
+                    good.upload = None;
+                }
+            }
+
+            Ok(Json(res))
+        }
+    })
 }
 
 /// Mark a certificate as "deactivated".
@@ -197,8 +395,17 @@ fn post_certs(
 /// This approach is probably appropriate in most cases to phase out a
 /// certificate.
 #[post("/certs/deactivate/<fp>")]
-fn deactivate_cert(fp: String) -> Result<(), BadRequest<Json<ReturnError>>> {
+fn deactivate_cert(fp: String) -> Result<Change, BadRequest<Json<ReturnError>>> {
     CA.with(|ca| {
+        match write_mode() {
+            WriteMode::Open => {}
+            WriteMode::ReadOnly => return Err(write_protected("deactivate_cert").into()),
+            WriteMode::Approval => {
+                let change = ProposedChange::CertDeactivate { fingerprint: fp };
+                return Ok(propose(ca, "deactivate_cert", change)?);
+            }
+        }
+
         ca.cert_deactivate(&fp).map_err(|e| {
             ReturnError::new(
                 ReturnStatus::InternalError,
@@ -206,7 +413,7 @@ fn deactivate_cert(fp: String) -> Result<(), BadRequest<Json<ReturnError>>> {
             )
         })?;
 
-        Ok(())
+        Ok(Change::Applied(()))
     })
 }
 
@@ -223,8 +430,17 @@ fn deactivate_cert(fp: String) -> Result<(), BadRequest<Json<ReturnError>>> {
 /// serve the latest version of a cert to third parties, so they can learn
 /// about e.g. revocations on the cert)
 #[delete("/certs/<fp>")]
-fn delist_cert(fp: String) -> Result<(), BadRequest<Json<ReturnError>>> {
+fn delist_cert(fp: String) -> Result<Change, BadRequest<Json<ReturnError>>> {
     CA.with(|ca| {
+        match write_mode() {
+            WriteMode::Open => {}
+            WriteMode::ReadOnly => return Err(write_protected("delist_cert").into()),
+            WriteMode::Approval => {
+                let change = ProposedChange::CertDelist { fingerprint: fp };
+                return Ok(propose(ca, "delist_cert", change)?);
+            }
+        }
+
         ca.cert_delist(&fp).map_err(|e| {
             ReturnError::new(
                 ReturnStatus::InternalError,
@@ -232,7 +448,7 @@ fn delist_cert(fp: String) -> Result<(), BadRequest<Json<ReturnError>>> {
             )
         })?;
 
-        Ok(())
+        Ok(Change::Applied(()))
     })
 }
 
@@ -242,9 +458,11 @@ fn delist_cert(fp: String) -> Result<(), BadRequest<Json<ReturnError>>> {
 /// Make a new certification, unless the user cert is marked as "deactivated".
 #[post("/refresh_ca_certifications")]
 fn refresh_certifications() -> Result<(), BadRequest<Json<ReturnError>>> {
+    check_open("refresh_certifications")?;
+
     CA.with(|ca| {
         Ok(ca
-            .certs_refresh_ca_certifications(30, CERTIFICATION_DAYS)
+            .certs_refresh_ca_certifications(REFRESH_THRESHOLD_DAYS, CERTIFICATION_DAYS)
             .map_err(|e| {
                 ReturnError::new(
                     ReturnStatus::InternalError,
@@ -254,7 +472,8 @@ fn refresh_certifications() -> Result<(), BadRequest<Json<ReturnError>>> {
     })
 }
 
-/// Poll for updates to user keys (e.g. on https://keys.openpgp.org/)
+/// Poll for updates to user keys (e.g. on the configured keyservers, see
+/// `Oca::set_keyserver_config`)
 #[post("/poll_updates")]
 fn poll_for_updates() -> String {
     unimplemented!()
@@ -298,6 +517,589 @@ fn check_expiring(days: u64) -> Result<Json<Vec<CertInfo>>, BadRequest<Json<Retu
     })
 }
 
+/// Stage a user's welcome kit for a one-time download.
+///
+/// Returns the (expiring) download link, which should be handed to the
+/// user via a separate channel.
+#[post("/downloads", data = "<request>", format = "json")]
+fn stage_download(
+    request: Json<StageDownload>,
+) -> Result<Json<DownloadLink>, BadRequest<Json<ReturnError>>> {
+    check_open("stage_download")?;
+
+    CA.with(|ca| {
+        let link = downloads::stage(ca, request.into_inner()).map_err(|e| {
+            ReturnError::new(
+                ReturnStatus::InternalError,
+                format!("stage_download: Error '{e:?}'"),
+            )
+        })?;
+
+        Ok(Json(link))
+    })
+}
+
+/// List the staged welcome kits that have not been downloaded yet
+#[get("/downloads")]
+fn staged_downloads() -> Json<Vec<StagedDownload>> {
+    Json(downloads::list())
+}
+
+/// Withdraw a staged welcome kit
+#[delete("/downloads/<id>")]
+fn withdraw_download(
+    id: String,
+    actor: Actor,
+) -> Result<Option<()>, BadRequest<Json<ReturnError>>> {
+    check_open("withdraw_download")?;
+
+    if downloads::withdraw(&id, actor.0) {
+        Ok(Some(()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// The most recent entries of the audit log of staged, downloaded, expired
+/// and withdrawn welcome kits
+#[get("/downloads/audit")]
+fn downloads_audit() -> Json<Vec<DownloadAuditEntry>> {
+    Json(downloads::audit_log())
+}
+
+/// An armored welcome kit.
+///
+/// The response body is read directly from protected memory, which is
+/// wiped when the response has been sent.
+struct Kit(Protected);
+
+impl<'r> Responder<'r, 'static> for Kit {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        Response::build()
+            .header(ContentType::Plain)
+            .sized_body(self.0.len(), Cursor::new(self.0))
+            .ok()
+    }
+}
+
+/// Download a staged welcome kit (armored).
+///
+/// The kit is deleted after the download, the link works only once.
+/// Returns 404 if the token is unknown, the link has expired, or the kit
+/// has already been downloaded.
+#[get("/download/<token>")]
+fn download(token: String, actor: Actor) -> Option<Kit> {
+    downloads::take(&token, actor.0).map(Kit)
+}
+
+static WKD_EXPORT_TOKEN: RwLock<Option<String>> = RwLock::new(None);
+static SPLIT_TOKEN: RwLock<Option<String>> = RwLock::new(None);
+
+/// Allow downloads of WKD exports from `/wkd/export` for clients that send
+/// `token` as a bearer token (with None, the route is disabled)
+pub fn set_wkd_export_token(token: Option<String>) {
+    *WKD_EXPORT_TOKEN.write().unwrap() = token;
+}
+
+/// Allow the exchange of requests and responses with a split mode back
+/// instance via `/split/...` for clients that send `token` as a bearer token
+/// (with None, the routes are disabled)
+pub fn set_split_token(token: Option<String>) {
+    *SPLIT_TOKEN.write().unwrap() = token;
+}
+
+/// Check that `req` carries the bearer token that is configured in `token`
+/// (403 if no token is configured, 401 for a missing or wrong token)
+fn bearer_auth(req: &Request<'_>, token: &RwLock<Option<String>>) -> Outcome<(), ()> {
+    let token = token.read().unwrap().clone();
+    let token = match token {
+        Some(token) => token,
+        None => return Outcome::Error((Status::Forbidden, ())),
+    };
+
+    let bearer = req
+        .headers()
+        .get_one("Authorization")
+        .and_then(|h| h.strip_prefix("Bearer "));
+
+    match bearer {
+        // compare in constant time
+        Some(bearer) if secure_cmp(bearer.as_bytes(), token.as_bytes()) == Ordering::Equal => {
+            Outcome::Success(())
+        }
+        _ => Outcome::Error((Status::Unauthorized, ())),
+    }
+}
+
+/// Request guard: the client sent the WKD export token
+struct WkdExportAuth;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WkdExportAuth {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, ()> {
+        bearer_auth(req, &WKD_EXPORT_TOKEN).map(|_| WkdExportAuth)
+    }
+}
+
+/// Request guard: the client sent the split mode token
+struct SplitAuth;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SplitAuth {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, ()> {
+        bearer_auth(req, &SPLIT_TOKEN).map(|_| SplitAuth)
+    }
+}
+
+/// Request guard: who sent the request, for the download audit log.
+///
+/// This is the client IP address, or the credentials of the peer process
+/// for requests via the unix domain socket.
+struct Actor(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Actor {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, ()> {
+        let actor = if req.remote() == Some(unix_socket::peer_address()) {
+            req.headers()
+                .get_one(unix_socket::PEER_CRED_HEADER)
+                .map(ToString::to_string)
+        } else {
+            req.client_ip().map(|ip| ip.to_string())
+        };
+
+        Outcome::Success(Actor(actor))
+    }
+}
+
+/// A WKD directory structure as a tar archive
+#[derive(Responder)]
+#[response(content_type = "application/x-tar")]
+struct WkdArchive {
+    inner: Vec<u8>,
+    disposition: Header<'static>,
+}
+
+/// Generate the WKD directory structure for the CA domain, and download it
+/// as a tar archive.
+///
+/// Requires the WKD export token as a bearer token (the route is disabled
+/// if no token is configured).
+#[get("/wkd/export")]
+fn wkd_export(_auth: WkdExportAuth) -> Result<WkdArchive, BadRequest<Json<ReturnError>>> {
+    CA.with(|ca| {
+        let domain = ca.domainname();
+
+        let tar = ca
+            .export_wkd_tar(domain, false, ExportCompat::default())
+            .map_err(|e| {
+                ReturnError::new(
+                    ReturnStatus::InternalError,
+                    format!("wkd_export: Error '{e:#}'"),
+                )
+            })?;
+
+        Ok(WkdArchive {
+            inner: tar,
+            disposition: Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"wkd-{domain}.tar\""),
+            ),
+        })
+    })
+}
+
+/// Get the pending certification requests of a split mode front instance,
+/// for processing by the back instance (in the JSON format of `oca ca split
+/// export`).
+///
+/// Requires the split mode token as a bearer token (the route is disabled
+/// if no token is configured).
+#[get("/split/requests")]
+fn split_requests(_auth: SplitAuth) -> Result<RawJson<String>, BadRequest<Json<ReturnError>>> {
+    CA.with(|ca| {
+        let requests = ca.ca_split_requests().map_err(|e| {
+            ReturnError::new(
+                ReturnStatus::InternalError,
+                format!("split_requests: Error '{e:#}'"),
+            )
+        })?;
+
+        Ok(RawJson(requests))
+    })
+}
+
+/// Post the response of the split mode back instance (in the JSON format of
+/// `oca ca split certify`) to the front instance.
+///
+/// Requires the split mode token as a bearer token (the route is disabled
+/// if no token is configured).
+#[post("/split/responses", data = "<response>")]
+async fn split_responses(
+    _auth: SplitAuth,
+    response: Data<'_>,
+) -> Result<Json<SplitImport>, BadRequest<Json<ReturnError>>> {
+    if write_mode() == WriteMode::ReadOnly {
+        return Err(write_protected("split_responses").into());
+    }
+
+    let response = response
+        .open(SPLIT_RESPONSE_SIZE_LIMIT.bytes())
+        .into_bytes()
+        .await
+        .map_err(|e| {
+            ReturnError::new(
+                ReturnStatus::BadSplitResponse,
+                format!("split_responses: Error '{e}'"),
+            )
+        })?;
+    if !response.is_complete() {
+        return Err(ReturnError::new(
+            ReturnStatus::BadSplitResponse,
+            "split_responses: Response size exceeds limit".to_string(),
+        )
+        .into());
+    }
+
+    CA.with(|ca| {
+        let imported = ca.ca_split_import_response(&response).map_err(|e| {
+            ReturnError::new(
+                ReturnStatus::BadSplitResponse,
+                format!("split_responses: Error '{e:#}'"),
+            )
+        })?;
+
+        Ok(Json(SplitImport { imported }))
+    })
+}
+
+pub(crate) fn revocation_info(
+    revocation: models::Revocation,
+    fingerprint: String,
+) -> Result<RevocationInfo, ReturnError> {
+    RevocationInfo::new(revocation, fingerprint).map_err(|e| {
+        ReturnError::new(
+            ReturnStatus::InternalError,
+            format!("revocation_info: Error '{e:?}'"),
+        )
+    })
+}
+
+/// Fingerprints of all user certs, by database id
+pub(crate) fn cert_fingerprints(ca: &Oca) -> Result<HashMap<i32, String>, ReturnError> {
+    let certs = ca.user_certs_get_all().map_err(|e| {
+        ReturnError::new(
+            ReturnStatus::InternalError,
+            format!("Error loading certs '{e:?}'"),
+        )
+    })?;
+
+    Ok(certs.into_iter().map(|c| (c.id, c.fingerprint)).collect())
+}
+
+/// Deposit a revocation certificate for a user cert.
+///
+/// The revocation must validate against a cert in the CA database. It is
+/// stored, but not applied to the cert (the CA operator can apply it later).
+///
+/// In approval mode, the revocation is queued for approval by a CA admin.
+#[post("/revocations", data = "<upload>", format = "json")]
+fn post_revocation(
+    upload: Json<RevocationUpload>,
+) -> Result<RevocationChange, BadRequest<Json<ReturnError>>> {
+    if upload.revocation.len() > CERT_SIZE_LIMIT {
+        return Err(ReturnError::new(
+            ReturnStatus::BadRevocation,
+            "post_revocation: Revocation size exceeds limit".to_string(),
+        )
+        .into());
+    }
+
+    CA.with(|ca| {
+        match write_mode() {
+            WriteMode::Open => {}
+            WriteMode::ReadOnly => return Err(write_protected("post_revocation").into()),
+            WriteMode::Approval => {
+                let change = ProposedChange::RevocationAdd {
+                    revocation: upload.into_inner().revocation,
+                };
+                let id = submit(ca, "post_revocation", change)?;
+
+                return Ok(RevocationChange::Queued(Accepted(Json(QueuedChange {
+                    id,
+                }))));
+            }
+        }
+
+        let revocation = ca
+            .revocation_add(upload.revocation.as_bytes())
+            .map_err(|e| {
+                ReturnError::new(
+                    ReturnStatus::BadRevocation,
+                    format!("post_revocation: Error '{e:#}'"),
+                )
+            })?;
+
+        let fingerprint = cert_fingerprints(ca)?
+            .remove(&revocation.cert_id)
+            .unwrap_or_default();
+
+        Ok(RevocationChange::Stored(Json(revocation_info(
+            revocation,
+            fingerprint,
+        )?)))
+    })
+}
+
+/// List all revocation certificates in the CA database
+#[get("/revocations")]
+fn revocations() -> Result<Json<Vec<RevocationInfo>>, BadRequest<Json<ReturnError>>> {
+    CA.with(|ca| {
+        let fps = cert_fingerprints(ca)?;
+
+        let revocations = ca.revocations_get_all().map_err(|e| {
+            ReturnError::new(
+                ReturnStatus::InternalError,
+                format!("revocations: Error '{e:?}'"),
+            )
+        })?;
+
+        let mut res = vec![];
+        for rev in revocations {
+            let fp = fps.get(&rev.cert_id).cloned().unwrap_or_default();
+            res.push(revocation_info(rev, fp)?);
+        }
+
+        Ok(Json(res))
+    })
+}
+
+/// Remove a revocation certificate that has not been applied.
+///
+/// Returns 404 if there is no revocation with this hash.
+#[delete("/revocations/<hash>")]
+fn delete_revocation(hash: String) -> Result<Option<Change>, BadRequest<Json<ReturnError>>> {
+    CA.with(|ca| {
+        if ca.revocation_get_by_hash(&hash).is_err() {
+            return Ok(None);
+        }
+
+        match write_mode() {
+            WriteMode::Open => {}
+            WriteMode::ReadOnly => return Err(write_protected("delete_revocation").into()),
+            WriteMode::Approval => {
+                let change = ProposedChange::RevocationDelete { hash };
+                return Ok(Some(propose(ca, "delete_revocation", change)?));
+            }
+        }
+
+        ca.revocation_delete(&hash).map_err(|e| {
+            ReturnError::new(
+                ReturnStatus::BadRevocation,
+                format!("delete_revocation: Error '{e:#}'"),
+            )
+        })?;
+
+        Ok(Some(Change::Applied(())))
+    })
+}
+
+/// The statement that a user signs with their current key `fp` (as a
+/// detached signature), to ask for its replacement with the key `new_fp`
+#[get("/replacements/statement/<fp>/<new_fp>")]
+fn replacement_statement(
+    fp: String,
+    new_fp: String,
+) -> Result<String, BadRequest<Json<ReturnError>>> {
+    CA.with(|ca| {
+        let statement = ca.key_replacement_statement(&fp, &new_fp).map_err(|e| {
+            ReturnError::new(
+                ReturnStatus::BadFingerprint,
+                format!("replacement_statement: Error '{e:#}'"),
+            )
+        })?;
+
+        Ok(statement)
+    })
+}
+
+/// Submit a replacement for a user cert.
+///
+/// The request must be signed with the old cert (see
+/// [KeyReplacementUpload]). It is staged until an admin approves it.
+#[post("/replacements", data = "<upload>", format = "json")]
+fn submit_replacement(
+    upload: Json<KeyReplacementUpload>,
+) -> Result<Json<KeyReplacementInfo>, BadRequest<Json<ReturnError>>> {
+    if upload.cert.len() > CERT_SIZE_LIMIT {
+        return Err(ReturnError::new(
+            ReturnStatus::BadReplacement,
+            "submit_replacement: Cert size exceeds limit".to_string(),
+        )
+        .into());
+    }
+
+    if write_mode() == WriteMode::ReadOnly {
+        return Err(write_protected("submit_replacement").into());
+    }
+
+    CA.with(|ca| {
+        let replacement = ca
+            .key_replacement_submit(
+                &upload.fingerprint,
+                upload.cert.as_bytes(),
+                &upload.handover,
+            )
+            .map_err(|e| {
+                ReturnError::new(
+                    ReturnStatus::BadReplacement,
+                    format!("submit_replacement: Error '{e:#}'"),
+                )
+            })?;
+
+        Ok(Json(replacement_info(ca, replacement)?))
+    })
+}
+
+fn replacement_info(
+    ca: &Oca,
+    replacement: models::KeyReplacement,
+) -> Result<KeyReplacementInfo, ReturnError> {
+    let fingerprint = cert_fingerprints(ca)?
+        .remove(&replacement.cert_id)
+        .unwrap_or_default();
+
+    Ok(KeyReplacementInfo::from(replacement, fingerprint))
+}
+
+/// The key replacement `id`, after it has been decided
+fn decided_replacement(ca: &Oca, id: i32) -> Result<KeyReplacementInfo, ReturnError> {
+    let replacement = ca
+        .key_replacements()
+        .map_err(|e| {
+            ReturnError::new(
+                ReturnStatus::InternalError,
+                format!("Error loading key replacements '{e:?}'"),
+            )
+        })?
+        .into_iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| {
+            ReturnError::new(
+                ReturnStatus::NotFound,
+                format!("Key replacement {id} not found"),
+            )
+        })?;
+
+    replacement_info(ca, replacement)
+}
+
+/// List the key replacements that wait for approval
+#[get("/replacements")]
+fn pending_replacements() -> Result<Json<Vec<KeyReplacementInfo>>, BadRequest<Json<ReturnError>>> {
+    CA.with(|ca| {
+        let pending = ca.key_replacements_pending().map_err(|e| {
+            ReturnError::new(
+                ReturnStatus::InternalError,
+                format!("pending_replacements: Error '{e:?}'"),
+            )
+        })?;
+
+        let mut res = vec![];
+        for replacement in pending {
+            res.push(replacement_info(ca, replacement)?);
+        }
+
+        Ok(Json(res))
+    })
+}
+
+/// Approve a key replacement: the replacement cert is stored for the user
+/// and certified, the old cert is deactivated
+#[post("/replacements/<id>/approve")]
+fn approve_replacement(id: i32) -> Result<Json<KeyReplacementInfo>, BadRequest<Json<ReturnError>>> {
+    check_open("approve_replacement")?;
+
+    CA.with(|ca| {
+        ca.key_replacement_approve(id, Some(CERTIFICATION_DAYS), true)
+            .map_err(|e| {
+                ReturnError::new(
+                    ReturnStatus::BadReplacement,
+                    format!("approve_replacement: Error '{e:#}'"),
+                )
+            })?;
+
+        Ok(Json(decided_replacement(ca, id)?))
+    })
+}
+
+/// Reject a key replacement
+#[post("/replacements/<id>/reject", data = "<rejection>", format = "json")]
+fn reject_replacement(
+    id: i32,
+    rejection: Json<KeyReplacementRejection>,
+) -> Result<Json<KeyReplacementInfo>, BadRequest<Json<ReturnError>>> {
+    check_open("reject_replacement")?;
+
+    CA.with(|ca| {
+        ca.key_replacement_reject(id, rejection.reason.as_deref())
+            .map_err(|e| {
+                ReturnError::new(
+                    ReturnStatus::BadReplacement,
+                    format!("reject_replacement: Error '{e:#}'"),
+                )
+            })?;
+
+        Ok(Json(decided_replacement(ca, id)?))
+    })
+}
+
+/// A signed revocation status, with caching headers
+#[derive(Responder)]
+struct CertStatusResponse {
+    inner: Json<SignedCertStatusJson>,
+    cache_control: Header<'static>,
+}
+
+/// Revocation status of the cert `fp` (good, revoked or unknown), signed by
+/// the CA.
+///
+/// Relying parties may cache the statement until its "next_update".
+#[get("/certs/status/<fp>")]
+fn cert_status(fp: String) -> Result<CertStatusResponse, BadRequest<Json<ReturnError>>> {
+    CA.with(|ca| {
+        let fp = pgp::fingerprint_from_str(&fp).map_err(|e| {
+            ReturnError::new(
+                ReturnStatus::BadFingerprint,
+                format!("cert_status: Error '{e:#}'"),
+            )
+        })?;
+
+        let status = ca
+            .cert_status(&fp.to_hex(), Duration::from_secs(CERT_STATUS_MAX_AGE_SECS))
+            .map_err(|e| {
+                ReturnError::new(
+                    ReturnStatus::InternalError,
+                    format!("cert_status: Error '{e:#}'"),
+                )
+            })?;
+
+        Ok(CertStatusResponse {
+            inner: Json(status.into()),
+            cache_control: Header::new(
+                "Cache-Control",
+                format!("public, max-age={CERT_STATUS_MAX_AGE_SECS}"),
+            ),
+        })
+    })
+}
+
 /// Ping, good for checking the service is alive
 #[get("/ping")]
 fn ping() -> Status {
@@ -317,23 +1119,57 @@ fn healthz() -> Status {
     }
 }
 
+/// Status of the periodic tasks of the scheduler
+#[get("/scheduler")]
+fn scheduler_status() -> Json<Vec<TaskStatus>> {
+    Json(scheduler::status())
+}
+
+/// OpenAPI description of this API
+#[get("/openapi.json")]
+fn openapi_json() -> Json<serde_json::Value> {
+    Json(openapi(&routes()))
+}
+
+/// All routes of the restd API
+pub fn routes() -> Vec<Route> {
+    routes![
+        certs_by_email,
+        cert_by_fp,
+        lookup_cert,
+        check_certs,
+        post_certs,
+        deactivate_cert,
+        delist_cert,
+        refresh_certifications,
+        poll_for_updates,
+        check_expiring,
+        ping,
+        healthz,
+        scheduler_status,
+        stage_download,
+        staged_downloads,
+        withdraw_download,
+        downloads_audit,
+        download,
+        wkd_export,
+        split_requests,
+        split_responses,
+        post_revocation,
+        revocations,
+        delete_revocation,
+        replacement_statement,
+        submit_replacement,
+        pending_replacements,
+        approve_replacement,
+        reject_replacement,
+        cert_status,
+        openapi_json,
+    ]
+}
+
 pub fn run(db: Option<String>) -> rocket::Rocket<Build> {
     DB.set(db).unwrap();
 
-    rocket::build().mount(
-        "/",
-        routes![
-            certs_by_email,
-            cert_by_fp,
-            check_certs,
-            post_certs,
-            deactivate_cert,
-            delist_cert,
-            refresh_certifications,
-            poll_for_updates,
-            check_expiring,
-            ping,
-            healthz,
-        ],
-    )
+    rocket::build().mount("/", routes())
 }
@@ -0,0 +1,220 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Periodic background tasks of the restd.
+//!
+//! Scheduled tasks run one after the other on a separate thread, which uses
+//! its own connection to the CA database. Their status is available via
+//! `GET /scheduler`.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::Utc;
+use once_cell::sync::OnceCell;
+use openpgp_ca_lib::types::{ExportCompat, SyncOptions, SyncReport, SyncSource};
+use openpgp_ca_lib::Oca;
+
+use crate::downloads;
+use crate::json::TaskStatus;
+use crate::restd::{CERTIFICATION_DAYS, REFRESH_THRESHOLD_DAYS};
+
+#[derive(Clone, Debug)]
+pub enum Task {
+    /// Pull updates for all certs from the configured keyservers (see
+    /// `Oca::set_keyserver_config`)
+    UpdateKeyserver,
+
+    /// Pull updates for all certs from WKD
+    UpdateWkd,
+
+    /// Renew CA certifications that expire soon
+    RefreshCertifications,
+
+    /// Export the CA's WKD directory structure into a directory
+    ExportWkd(PathBuf),
+
+    /// Checkpoint the write-ahead log of the CA database
+    Checkpoint,
+
+    /// Remove database rows according to the retention policy of the CA
+    Cleanup,
+
+    /// Delete staged welcome kits whose download links have expired
+    PurgeDownloads,
+}
+
+impl Task {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Task::UpdateKeyserver => "update_keyserver",
+            Task::UpdateWkd => "update_wkd",
+            Task::RefreshCertifications => "refresh_certifications",
+            Task::ExportWkd(_) => "export_wkd",
+            Task::Checkpoint => "checkpoint",
+            Task::Cleanup => "cleanup",
+            Task::PurgeDownloads => "purge_downloads",
+        }
+    }
+
+    /// Run the task, returns an optional summary of the outcome
+    fn run(&self, ca: &Oca) -> Result<Option<String>> {
+        match self {
+            Task::UpdateKeyserver => {
+                let report = ca.sync_certs(SyncSource::Keyserver, &SyncOptions::default())?;
+                return Ok(Some(sync_summary(&report)));
+            }
+            Task::UpdateWkd => {
+                let report = ca.sync_certs(SyncSource::Wkd, &SyncOptions::default())?;
+                return Ok(Some(sync_summary(&report)));
+            }
+            Task::RefreshCertifications => {
+                ca.certs_refresh_ca_certifications(REFRESH_THRESHOLD_DAYS, CERTIFICATION_DAYS)?
+            }
+            Task::ExportWkd(path) => {
+                ca.export_wkd(ca.domainname(), path, false, ExportCompat::default())?
+            }
+            Task::Checkpoint => ca.checkpoint()?,
+            Task::Cleanup => {
+                let report = ca.db_cleanup(false)?;
+                return Ok(Some(format!(
+                    "removed {} done queue entries, {} cert versions",
+                    report.queue_done, report.cert_versions
+                )));
+            }
+            Task::PurgeDownloads => {
+                let purged = downloads::purge();
+                return Ok(Some(format!("deleted {purged} expired downloads")));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+fn sync_summary(report: &SyncReport) -> String {
+    format!(
+        "updated {}, unchanged {}, failed {}, skipped {} certs",
+        report.updated, report.unchanged, report.failed, report.skipped
+    )
+}
+
+static STATUS: OnceCell<Mutex<Vec<TaskStatus>>> = OnceCell::new();
+
+/// Status of all scheduled tasks (empty, if the scheduler is not running)
+pub fn status() -> Vec<TaskStatus> {
+    STATUS
+        .get()
+        .map(|status| status.lock().unwrap().clone())
+        .unwrap_or_default()
+}
+
+fn update_status(idx: usize, f: impl FnOnce(&mut TaskStatus)) {
+    if let Some(status) = STATUS.get() {
+        f(&mut status.lock().unwrap()[idx])
+    }
+}
+
+fn next_run(interval: Duration) -> Option<chrono::DateTime<Utc>> {
+    chrono::Duration::from_std(interval)
+        .ok()
+        .and_then(|i| Utc::now().checked_add_signed(i))
+}
+
+/// Start running `tasks` in the background, each with its interval.
+///
+/// The first run of each task happens one interval after startup.
+/// The scheduler can only be started once per process.
+pub fn start(db: Option<String>, tasks: Vec<(Task, Duration)>) -> Result<()> {
+    if tasks.is_empty() {
+        return Ok(());
+    }
+
+    let status = tasks
+        .iter()
+        .map(|(task, interval)| TaskStatus {
+            name: task.name().to_string(),
+            interval_secs: interval.as_secs(),
+            running: false,
+            runs: 0,
+            last_start: None,
+            last_end: None,
+            last_error: None,
+            last_result: None,
+            next_run: next_run(*interval),
+        })
+        .collect();
+
+    STATUS
+        .set(Mutex::new(status))
+        .map_err(|_| anyhow::anyhow!("The scheduler is already running"))?;
+
+    std::thread::Builder::new()
+        .name("scheduler".to_string())
+        .spawn(move || run(db, tasks))?;
+
+    Ok(())
+}
+
+fn run(db: Option<String>, tasks: Vec<(Task, Duration)>) {
+    let ca = match Oca::open(db.as_deref()) {
+        Ok(ca) => ca,
+        Err(e) => {
+            for idx in 0..tasks.len() {
+                update_status(idx, |s| {
+                    s.last_error = Some(format!("Failed to open CA: {e:?}"));
+                    s.next_run = None;
+                });
+            }
+            return;
+        }
+    };
+
+    let start = Instant::now();
+    let mut due: Vec<Instant> = tasks
+        .iter()
+        .map(|(_, interval)| start + *interval)
+        .collect();
+
+    loop {
+        let (idx, next) = due
+            .iter()
+            .copied()
+            .enumerate()
+            .min_by_key(|(_, next)| *next)
+            .expect("tasks is not empty");
+
+        std::thread::sleep(next.saturating_duration_since(Instant::now()));
+
+        let (task, interval) = &tasks[idx];
+
+        update_status(idx, |s| {
+            s.running = true;
+            s.last_start = Some(Utc::now());
+            s.next_run = None;
+        });
+
+        let res = task.run(&ca);
+
+        due[idx] = Instant::now() + *interval;
+
+        update_status(idx, |s| {
+            s.running = false;
+            s.runs += 1;
+            s.last_end = Some(Utc::now());
+            match res {
+                Ok(result) => {
+                    s.last_error = None;
+                    s.last_result = result;
+                }
+                Err(e) => s.last_error = Some(format!("{e:?}")),
+            }
+            s.next_run = next_run(*interval);
+        });
+    }
+}
@@ -0,0 +1,156 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+//! Serving restd on a unix domain socket, for small setups without a
+//! reverse proxy.
+//!
+//! Rocket only listens on TCP sockets. Here, HTTP/1 connections are accepted
+//! on a unix domain socket, and each request is dispatched to the Rocket
+//! instance in-process. Request and response bodies are buffered in memory
+//! (request bodies up to the largest size that a route of restd accepts).
+//!
+//! Access control relies on the transport: the socket is only accessible to
+//! the user that runs the daemon. The credentials of the peer process are
+//! passed on to the routes (see [PEER_CRED_HEADER]).
+
+use std::cmp::max;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use http_body::{LengthLimitError, Limited};
+use rocket::data::Limits;
+use rocket::http::hyper::{self, server::conn::Http, service::service_fn, Body};
+use rocket::http::{Header, Method};
+use rocket::local::asynchronous::Client;
+use rocket::{Build, Config, Rocket};
+use tokio::net::UnixListener;
+
+use crate::jsonrpc;
+use crate::restd::SPLIT_RESPONSE_SIZE_LIMIT;
+
+/// Header with the credentials of the peer process, as "uid=... pid=..."
+/// (a header of this name that the client sent is dropped)
+pub const PEER_CRED_HEADER: &str = "X-Oca-Peer-Cred";
+
+/// The remote address of requests that arrive on the unix domain socket.
+///
+/// Port 0 never occurs for TCP peers, so routes can tell these requests
+/// apart from requests via TCP.
+pub fn peer_address() -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::LOCALHOST, 0))
+}
+
+/// The largest request body that a route of restd accepts: JSON documents
+/// (up to the configured "json" limit), and split mode responses
+fn body_limit(config: &Config) -> usize {
+    let json = config.limits.get("json").unwrap_or(Limits::JSON);
+
+    max(json.as_u64() as usize, SPLIT_RESPONSE_SIZE_LIMIT)
+}
+
+/// Dispatch `req` to the Rocket instance of `client`
+async fn dispatch(
+    client: &Client,
+    req: hyper::Request<Body>,
+    peer_cred: Option<&str>,
+) -> hyper::Response<Body> {
+    let error = |status: u16| {
+        hyper::Response::builder()
+            .status(status)
+            .body(Body::empty())
+            .expect("valid status code")
+    };
+
+    let (parts, body) = req.into_parts();
+
+    let method = match Method::from_str(parts.method.as_str()) {
+        Ok(method) => method,
+        Err(_) => return error(405),
+    };
+    let limit = body_limit(client.rocket().config());
+    let body = match hyper::body::to_bytes(Limited::new(body, limit)).await {
+        Ok(body) => body,
+        Err(e) if e.downcast_ref::<LengthLimitError>().is_some() => return error(413),
+        Err(_) => return error(400),
+    };
+
+    let uri = parts
+        .uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/")
+        .to_string();
+
+    let mut request = client.req(method, uri).remote(peer_address()).body(body);
+    for (name, value) in &parts.headers {
+        if name.as_str().eq_ignore_ascii_case(PEER_CRED_HEADER) {
+            continue;
+        }
+        if let Ok(value) = value.to_str() {
+            request.add_header(Header::new(name.as_str().to_string(), value.to_string()));
+        }
+    }
+    if let Some(peer_cred) = peer_cred {
+        request.add_header(Header::new(PEER_CRED_HEADER, peer_cred.to_string()));
+    }
+
+    let response = request.dispatch().await;
+
+    let mut builder = hyper::Response::builder().status(response.status().code);
+    for header in response.headers().iter() {
+        builder = builder.header(header.name().as_str(), header.value());
+    }
+
+    let body = response.into_bytes().await.unwrap_or_default();
+
+    builder
+        .body(Body::from(body))
+        .unwrap_or_else(|_| error(500))
+}
+
+/// Serve `rocket` on the unix domain socket `socket` (which is only
+/// accessible to the current user)
+pub async fn serve(rocket: Rocket<Build>, socket: &Path) -> Result<()> {
+    let client = Arc::new(Client::untracked(rocket).await?);
+
+    let listener = jsonrpc::bind_private(socket)?;
+    listener.set_nonblocking(true)?;
+    let listener = UnixListener::from_std(listener)?;
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let client = client.clone();
+
+        let peer_cred: Option<Arc<str>> = stream.peer_cred().ok().map(|cred| {
+            let mut s = format!("uid={}", cred.uid());
+            if let Some(pid) = cred.pid() {
+                s.push_str(&format!(" pid={pid}"));
+            }
+            s.into()
+        });
+
+        tokio::spawn(async move {
+            let service = service_fn(move |req| {
+                let client = client.clone();
+                let peer_cred = peer_cred.clone();
+                async move { Ok::<_, Infallible>(dispatch(&client, req, peer_cred.as_deref()).await) }
+            });
+
+            // A failing connection doesn't stop the daemon
+            if let Err(e) = Http::new()
+                .http1_only(true)
+                .serve_connection(stream, service)
+                .await
+            {
+                tracing::warn!("Error on unix socket connection: {e}");
+            }
+        });
+    }
+}
@@ -0,0 +1,233 @@
+// SPDX-FileCopyrightText: 2019-2024 Heiko Schaefer <heiko@schaefer.name>
+// SPDX-License-Identifier: GPL-3.0-or-later
+//
+// This file is part of OpenPGP CA
+// https://gitlab.com/openpgp-ca/openpgp-ca
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+
+use openpgp_ca_lib::Uninit;
+use openpgp_ca_restd::jsonrpc;
+use sequoia_openpgp::armor::{Kind, Writer};
+use sequoia_openpgp::cert::CertBuilder;
+use sequoia_openpgp::serialize::{Marshal, SerializeInto};
+use serde_json::{json, Value};
+
+fn call(ca: &openpgp_ca_lib::Oca, request: Value) -> Value {
+    let response = jsonrpc::handle(ca, &request.to_string(), None).expect("no response");
+    serde_json::from_str(&response).unwrap()
+}
+
+#[test]
+fn test_jsonrpc() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = dir.path().join("ca.sqlite").to_str().unwrap().to_string();
+    let audit_log = dir.path().join("audit.log");
+
+    let cau = Uninit::new(Some(&db)).unwrap();
+    let ca = cau.init_softkey("example.org", None, None).unwrap();
+
+    let (alice, rev) = CertBuilder::general_purpose(None, Some("Alice <alice@example.org>"))
+        .generate()
+        .unwrap();
+    let alice_fp = alice.fingerprint().to_hex();
+    let alice_armored = String::from_utf8(alice.armored().to_vec().unwrap()).unwrap();
+
+    let mut w = Writer::new(vec![], Kind::Signature).unwrap();
+    sequoia_openpgp::Packet::from(rev)
+        .serialize(&mut w)
+        .unwrap();
+    let rev_armored = String::from_utf8(w.finalize().unwrap()).unwrap();
+
+    // -- ca.info --
+    let res = call(&ca, json!({"jsonrpc": "2.0", "id": 1, "method": "ca.info"}));
+    assert_eq!(res["id"], 1);
+    assert_eq!(res["result"]["domain"], "example.org");
+    assert_eq!(res["result"]["email"], "openpgp-ca@example.org");
+
+    // -- certs.import --
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "certs.import", "params": {
+            "cert": alice_armored,
+            "name": "Alice",
+            "emails": ["alice@example.org"],
+        }}),
+    );
+    assert_eq!(res["result"], alice_fp);
+
+    // -- certs.check (shared with the C bindings) --
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 15, "method": "certs.check", "params": {"fingerprint": alice_fp}}),
+    );
+    assert_eq!(
+        res["result"]["certified"],
+        json!(["Alice <alice@example.org>"])
+    );
+
+    // importing the same cert again fails
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 3, "method": "certs.import", "params": {
+            "cert": alice_armored,
+        }}),
+    );
+    assert_eq!(res["error"]["code"], -32000);
+
+    // -- certs.get / certs.by_email --
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 4, "method": "certs.get", "params": {"fingerprint": alice_fp}}),
+    );
+    assert_eq!(res["result"]["name"], "Alice");
+    assert_eq!(res["result"]["email"], json!(["alice@example.org"]));
+
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 5, "method": "certs.get", "params": {"fingerprint": "00"}}),
+    );
+    assert_eq!(res["result"], Value::Null);
+
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 6, "method": "certs.by_email", "params": {"email": "alice@example.org"}}),
+    );
+    assert_eq!(res["result"].as_array().unwrap().len(), 1);
+
+    // -- certs.status --
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 7, "method": "certs.status", "params": {"fingerprint": alice_fp}}),
+    );
+    assert!(res["result"]["status"].as_str().unwrap().contains("good"));
+
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 8, "method": "certs.status", "params": {"fingerprint": "xyz"}}),
+    );
+    assert_eq!(res["error"]["code"], -32602);
+
+    // -- revocations.add --
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 9, "method": "revocations.add", "params": {"revocation": rev_armored}}),
+    );
+    assert_eq!(res["result"]["fingerprint"], alice_fp);
+    assert_eq!(res["result"]["published"], false);
+
+    // -- rpc.discover --
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 10, "method": "rpc.discover"}),
+    );
+    let methods = res["result"]["methods"].as_array().unwrap();
+    assert_eq!(methods.len(), jsonrpc::methods().len());
+    assert!(res["result"]["definitions"]["Certificate"].is_object());
+
+    // -- errors --
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 11, "method": "user.new"}),
+    );
+    assert_eq!(res["error"]["code"], -32601);
+
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "2.0", "id": 12, "method": "certs.get", "params": {}}),
+    );
+    assert_eq!(res["error"]["code"], -32602);
+
+    let res = call(
+        &ca,
+        json!({"jsonrpc": "1.0", "id": 13, "method": "ca.info"}),
+    );
+    assert_eq!(res["error"]["code"], -32600);
+
+    let res: Value =
+        serde_json::from_str(&jsonrpc::handle(&ca, "{not json", None).unwrap()).unwrap();
+    assert_eq!(res["error"]["code"], -32700);
+    assert_eq!(res["id"], Value::Null);
+
+    // notifications get no response
+    assert!(jsonrpc::handle(&ca, r#"{"jsonrpc": "2.0", "method": "ca.info"}"#, None).is_none());
+
+    // -- audit log --
+    jsonrpc::handle(
+        &ca,
+        r#"{"jsonrpc": "2.0", "id": 14, "method": "ca.info"}"#,
+        Some(&audit_log),
+    );
+    jsonrpc::handle(
+        &ca,
+        r#"{"jsonrpc": "2.0", "method": "nope"}"#,
+        Some(&audit_log),
+    );
+
+    let log = std::fs::read_to_string(&audit_log).unwrap();
+    let entries: Vec<jsonrpc::AuditEntry> = log
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].method, "ca.info");
+    assert!(entries[0].error.is_none());
+    assert_eq!(entries[1].method, "nope");
+    assert!(entries[1].error.is_some());
+}
+
+#[test]
+fn test_jsonrpc_unix_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = dir.path().join("ca.sqlite").to_str().unwrap().to_string();
+    let socket = dir.path().join("oca.sock");
+
+    let cau = Uninit::new(Some(&db)).unwrap();
+    cau.init_softkey("example.org", None, None).unwrap();
+
+    let s = socket.clone();
+    std::thread::spawn(move || jsonrpc::serve_unix(Some(db), &s, None));
+
+    let mut stream = None;
+    for _ in 0..50 {
+        if let Ok(s) = UnixStream::connect(&socket) {
+            stream = Some(s);
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    let mut stream = stream.expect("failed to connect to socket");
+
+    writeln!(
+        stream,
+        r#"{{"jsonrpc": "2.0", "id": "a", "method": "ca.info"}}"#
+    )
+    .unwrap();
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line).unwrap();
+
+    let res: Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(res["id"], "a");
+    assert_eq!(res["result"]["domain"], "example.org");
+
+    let mode =
+        std::os::unix::fs::PermissionsExt::mode(&std::fs::metadata(&socket).unwrap().permissions());
+    assert_eq!(mode & 0o777, 0o600);
+
+    // The private directory that the socket was bound in is removed
+    assert!(!std::fs::read_dir(dir.path()).unwrap().any(|e| e
+        .unwrap()
+        .file_name()
+        .to_string_lossy()
+        .ends_with(".tmp")));
+
+    // An existing file at the socket path is not replaced
+    let res = jsonrpc::serve_unix(
+        Some(dir.path().join("ca.sqlite").to_str().unwrap().to_string()),
+        &socket,
+        None,
+    );
+    assert!(res.is_err());
+}
@@ -4,11 +4,26 @@
 // This file is part of OpenPGP CA
 // https://gitlab.com/openpgp-ca/openpgp-ca
 
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use openpgp_ca_lib::pgp;
+use openpgp_ca_lib::types::{BlocklistKind, CertStatusKind, KeyPolicy};
 use openpgp_ca_lib::Uninit;
 use openpgp_ca_restd::client::Client;
-use openpgp_ca_restd::json::{Action, CertResultJson, CertStatus, Certificate};
+use openpgp_ca_restd::json::{
+    Action, CertResultJson, CertStatus, Certificate, DownloadEvent, KeyReplacementUpload,
+    ReturnStatus, StageDownload,
+};
 use openpgp_ca_restd::restd;
+use openpgp_ca_restd::scheduler::{self, Task};
 use rocket::futures::prelude::future::{AbortHandle, Abortable};
+use sequoia_openpgp::cert::CertBuilder;
+use sequoia_openpgp::packet::signature::SignatureBuilder;
+use sequoia_openpgp::policy::StandardPolicy;
+use sequoia_openpgp::types::SignatureType;
+use sequoia_openpgp::Cert;
 
 #[allow(dead_code)]
 mod gnupg_test_wrapper;
@@ -234,10 +249,10 @@ async fn test_restd() {
 
     // -- init OpenPGP CA --
     let cau = Uninit::new(Some(&db)).unwrap();
-    let _ca = cau.init_softkey("example.org", None, None).unwrap();
+    let ca = cau.init_softkey("example.org", None, None).unwrap();
 
     // -- start restd --
-    let abort_handle = start_restd(db);
+    let abort_handle = start_restd(db.clone());
     let c = Client::new("http://localhost:8000/");
 
     // --- Various "check" calls ---
@@ -320,6 +335,39 @@ async fn test_restd() {
         panic!("error");
     }
 
+    // 4. Alice, violates the key policy of the CA
+    ca.set_key_policy(&KeyPolicy {
+        allowed_algorithms: Some(vec!["rsa".to_string()]),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let cert = Certificate {
+        cert: ALICE_CERT.to_owned(),
+        delisted: None,
+        inactive: None,
+        email: vec!["alice@example.org".to_owned()],
+        name: Some("Alice Adams".to_owned()),
+        revocations: vec![],
+    };
+
+    let res = c.check(&cert).await;
+
+    assert!(res.is_ok());
+    let res = res.unwrap();
+    assert_eq!(res.len(), 1);
+    let res = res.first().unwrap();
+
+    if let CertResultJson::Bad(res) = res {
+        // one error for each (sub)key
+        assert_eq!(res.error.len(), 3);
+        assert!(res.error.iter().all(|e| e.status == CertStatus::KeyPolicy));
+    } else {
+        panic!("error");
+    }
+
+    ca.set_key_policy(&KeyPolicy::default()).unwrap();
+
     // --- Persist, Modify, Read ---
     let cert = Certificate {
         cert: ALICE_CERT.to_owned(),
@@ -397,6 +445,15 @@ async fn test_restd() {
     let res = res.unwrap();
     assert_eq!(res.len(), 0);
 
+    // key discovery: ALICE_CERT has expired, so there is no valid cert
+    let res = c.lookup("alice@example.org".into()).await;
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+
+    let res = c.lookup("bob@example.org".into()).await;
+    assert!(res.is_ok());
+    assert!(res.unwrap().is_none());
+
     // look up by fingerprint
     let res = c.get_by_fp(alice_fp.clone()).await;
     assert!(res.is_ok());
@@ -431,6 +488,21 @@ async fn test_restd() {
     let res = res.unwrap();
     assert!(res.certificate.delisted.unwrap());
 
+    // tags are included in the JSON output
+    ca.cert_tag_set(&alice_fp, "employee-id", "4711").unwrap();
+    ca.user_tag_set(&alice_fp, "department", "sales").unwrap();
+
+    let res = c.get_by_fp(alice_fp.clone()).await.unwrap().unwrap();
+    let tags = res.tags.unwrap();
+    assert_eq!(
+        tags.cert.get("employee-id").map(String::as_str),
+        Some("4711")
+    );
+    assert_eq!(
+        tags.user.get("department").map(String::as_str),
+        Some("sales")
+    );
+
     // 4. persist key for bob; a new key for alice, an update for alice's key
 
     let cert = Certificate {
@@ -551,6 +623,467 @@ async fn test_restd() {
         panic!("cert should be bad");
     }
 
+    // 7. OpenAPI description
+    assert!(c.healthz().await);
+
+    let api = c.openapi().await;
+    assert_eq!(api["openapi"], "3.0.0");
+    assert!(api["paths"]["/certs/by_fp/{fp}"]["get"].is_object());
+    assert_eq!(
+        api["paths"]["/certs"]["post"]["requestBody"]["content"]["application/json"]["schema"]
+            ["$ref"],
+        "#/components/schemas/Certificate"
+    );
+    assert!(api["components"]["schemas"]["CertResultJson"].is_object());
+    assert!(c.expiring(3650).await.is_ok());
+
+    // 8. scheduler
+    assert!(c.scheduler().await.is_empty());
+    assert!(api["paths"]["/scheduler"]["get"].is_object());
+
+    let wkd = PathBuf::from(format!("{home_path}/wkd"));
+    scheduler::start(
+        Some(db.clone()),
+        vec![
+            (Task::RefreshCertifications, Duration::from_millis(200)),
+            (Task::ExportWkd(wkd.clone()), Duration::from_millis(200)),
+        ],
+    )
+    .expect("failed to start scheduler");
+
+    // wait until both tasks have run
+    let mut status = vec![];
+    for _ in 0..50 {
+        status = c.scheduler().await;
+        if status.iter().all(|s| s.runs > 0) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(status.len(), 2);
+    assert_eq!(status[0].name, "refresh_certifications");
+    assert_eq!(status[1].name, "export_wkd");
+    for s in &status {
+        assert!(s.runs > 0, "task {} didn't run", s.name);
+        assert!(s.last_error.is_none(), "task {} failed", s.name);
+    }
+    assert!(wkd.join(".well-known/openpgpkey/example.org").is_dir());
+
+    // the scheduler can only be started once
+    assert!(scheduler::start(
+        Some(db.clone()),
+        vec![(Task::UpdateWkd, Duration::from_secs(60))]
+    )
+    .is_err());
+
+    // 9. welcome kit downloads
+    assert!(api["paths"]["/download/{token}"]["get"].is_object());
+
+    let stage = |expires_in_minutes| StageDownload {
+        fingerprint: alice_fp.clone(),
+        private_key: None,
+        expires_in_minutes,
+        staged_by: Some("admin".to_string()),
+    };
+
+    let link = c
+        .stage_download(&stage(Some(10)))
+        .await
+        .expect("failed to stage download");
+    assert!(link.url.starts_with("/download/"));
+
+    let staged = c.staged_downloads().await;
+    assert_eq!(staged.len(), 1);
+    assert_eq!(staged[0].id, link.id);
+    assert_eq!(
+        staged[0].fingerprint,
+        "B702503FBB24BDB1656270786CC91D1754643106"
+    );
+
+    // the link works once
+    let kit = c.download(&link.url).await.expect("download failed");
+    assert!(kit.contains("-----BEGIN PGP PUBLIC KEY BLOCK-----"));
+    assert!(c.download(&link.url).await.is_none());
+    assert!(c.staged_downloads().await.is_empty());
+
+    // a withdrawn kit can't be downloaded
+    let link = c
+        .stage_download(&stage(None))
+        .await
+        .expect("failed to stage download");
+    assert!(c.withdraw_download(&link.id).await);
+    assert!(!c.withdraw_download(&link.id).await);
+    assert!(c.download(&link.url).await.is_none());
+
+    // unknown certs and excessive expiry are rejected
+    let mut unknown = stage(None);
+    unknown.fingerprint = "0000000000000000000000000000000000000000".to_string();
+    assert!(c.stage_download(&unknown).await.is_err());
+    assert!(c.stage_download(&stage(Some(60 * 24 * 365))).await.is_err());
+
+    let events: Vec<_> = c
+        .downloads_audit()
+        .await
+        .into_iter()
+        .map(|e| e.event)
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            DownloadEvent::Staged,
+            DownloadEvent::Downloaded,
+            DownloadEvent::Rejected,
+            DownloadEvent::Staged,
+            DownloadEvent::Withdrawn,
+            DownloadEvent::Rejected,
+        ]
+    );
+
+    // 10. deposit of revocation certificates
+    assert!(api["paths"]["/revocations/{hash}"]["delete"].is_object());
+
+    // carol's two revocations from step 5 (not applied)
+    let revs = c.revocations().await;
+    assert_eq!(revs.len(), 2);
+    assert!(revs.iter().all(|r| !r.published));
+    let carol_fp = revs[0].fingerprint.clone();
+    assert_eq!(revs[1].fingerprint, carol_fp);
+
+    assert!(c
+        .delete_revocation(&revs[1].hash)
+        .await
+        .expect("failed to delete revocation"));
+    assert!(!c
+        .delete_revocation(&revs[1].hash)
+        .await
+        .expect("failed to delete revocation"));
+    assert_eq!(c.revocations().await.len(), 1);
+
+    // re-deposit the deleted revocation
+    let rev = c
+        .post_revocation(CAROL_REV2)
+        .await
+        .expect("failed to deposit revocation")
+        .expect("revocation was queued");
+    assert_eq!(rev.fingerprint, carol_fp);
+    assert!(!rev.published);
+
+    // depositing a revocation again doesn't duplicate it
+    let again = c
+        .post_revocation(CAROL_REV2)
+        .await
+        .expect("failed to deposit revocation")
+        .expect("revocation was queued");
+    assert_eq!(again.hash, rev.hash);
+    assert_eq!(c.revocations().await.len(), 2);
+
+    let err = c
+        .post_revocation("not a revocation")
+        .await
+        .expect_err("invalid revocation was accepted");
+    assert_eq!(err.status, ReturnStatus::BadRevocation);
+
+    // 11. signed revocation status
+    assert!(api["paths"]["/certs/status/{fp}"]["get"].is_object());
+
+    let ca_cert = ca.ca_get_cert_pub().unwrap();
+    let ca_fp = ca_cert.fingerprint().to_hex();
+
+    // bob is good
+    let bob_fp = Cert::from_str(BOB_CERT).unwrap().fingerprint().to_hex();
+    let status = c
+        .cert_status_verified(&bob_fp, &ca_cert)
+        .await
+        .expect("failed to get cert status");
+    assert_eq!(status.fingerprint, bob_fp);
+    assert_eq!(status.ca_fingerprint, ca_fp);
+    assert_eq!(status.status, CertStatusKind::Good);
+    assert!(status.revocation_time.is_none());
+    assert!(status.next_update > status.produced_at);
+
+    // alice's second key contains a revocation (from step 4)
+    let alice2_fp = Cert::from_str(ALICE2_CERT).unwrap().fingerprint();
+    let status = c
+        .cert_status_verified(&alice2_fp.to_spaced_hex(), &ca_cert)
+        .await
+        .expect("failed to get cert status");
+    assert_eq!(status.fingerprint, alice2_fp.to_hex());
+    assert_eq!(status.status, CertStatusKind::Revoked);
+    assert!(status.revocation_time.is_some());
+
+    // carol's deposited revocation only counts once it has been applied
+    let status = c.cert_status_verified(&carol_fp, &ca_cert).await.unwrap();
+    assert_eq!(status.status, CertStatusKind::Good);
+
+    let carol = ca.cert_get_by_fingerprint(&carol_fp).unwrap().unwrap();
+    let rev = ca.revocations_get(&carol).unwrap().pop().unwrap();
+    ca.revocation_apply(rev).unwrap();
+
+    let status = c.cert_status_verified(&carol_fp, &ca_cert).await.unwrap();
+    assert_eq!(status.status, CertStatusKind::Revoked);
+    assert!(status.reason.is_some());
+
+    // a cert that the CA doesn't know
+    let unknown_fp = "0123456789ABCDEF0123456789ABCDEF01234567";
+    let status = c.cert_status_verified(unknown_fp, &ca_cert).await.unwrap();
+    assert_eq!(status.status, CertStatusKind::Unknown);
+
+    // the statement is signed by the CA, and not by anyone else
+    let signed = c.cert_status(unknown_fp).await.unwrap();
+    let bob = Cert::from_str(BOB_CERT).unwrap();
+    assert!(signed.verify(&bob).is_err());
+
+    let err = c
+        .cert_status("not a fingerprint")
+        .await
+        .expect_err("invalid fingerprint was accepted");
+    assert_eq!(err.status, ReturnStatus::BadFingerprint);
+
+    let resp = reqwest::get(format!("http://localhost:8000/certs/status/{bob_fp}"))
+        .await
+        .unwrap();
+    assert_eq!(
+        resp.headers()["cache-control"],
+        format!("public, max-age={}", restd::CERT_STATUS_MAX_AGE_SECS)
+    );
+
+    // 12. self-service key replacement
+    assert!(api["paths"]["/replacements/{id}/approve"]["post"].is_object());
+
+    let (dave1, _) = CertBuilder::general_purpose(None, Some("Dave <dave@example.org>"))
+        .generate()
+        .unwrap();
+    let (dave2, _) = CertBuilder::general_purpose(None, Some("Dave <dave@example.org>"))
+        .generate()
+        .unwrap();
+    ca.cert_import_new(
+        pgp::cert_to_armored(&dave1).unwrap().as_bytes(),
+        &[],
+        Some("Dave"),
+        &["dave@example.org"],
+        None,
+    )
+    .unwrap();
+
+    let fp1 = dave1.fingerprint().to_hex();
+    let fp2 = dave2.fingerprint().to_hex();
+
+    let statement = c.replacement_statement(&fp1, &fp2).await.unwrap();
+    assert_eq!(statement, ca.key_replacement_statement(&fp1, &fp2).unwrap());
+
+    // detached signature over the statement, by the signing subkey of `cert`
+    let policy = StandardPolicy::new();
+    let sign = |cert: &Cert| {
+        let mut keypair = cert
+            .keys()
+            .with_policy(&policy, None)
+            .for_signing()
+            .secret()
+            .next()
+            .unwrap()
+            .key()
+            .clone()
+            .into_keypair()
+            .unwrap();
+        let sig = SignatureBuilder::new(SignatureType::Binary)
+            .sign_message(&mut keypair, &statement)
+            .unwrap();
+
+        pgp::signatures_to_armored(&[sig]).unwrap()
+    };
+
+    let upload = |handover: String| KeyReplacementUpload {
+        fingerprint: fp1.clone(),
+        cert: pgp::cert_to_armored(&dave2).unwrap(),
+        handover,
+    };
+
+    // a handover statement that is signed by the new key is rejected
+    let err = c
+        .submit_replacement(&upload(sign(&dave2)))
+        .await
+        .expect_err("forged handover was accepted");
+    assert_eq!(err.status, ReturnStatus::BadReplacement);
+
+    // Dave signs the statement with his old key
+    let submitted = c.submit_replacement(&upload(sign(&dave1))).await.unwrap();
+    assert_eq!(submitted.fingerprint, fp1);
+    assert_eq!(submitted.new_fingerprint, fp2);
+    assert_eq!(submitted.status, "pending");
+
+    let pending = c.pending_replacements().await;
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].id, submitted.id);
+
+    let approved = c.approve_replacement(submitted.id).await.unwrap();
+    assert_eq!(approved.status, "approved");
+    assert!(c.pending_replacements().await.is_empty());
+
+    // a decided replacement can't be rejected anymore
+    let err = c
+        .reject_replacement(submitted.id, Some("too late"))
+        .await
+        .expect_err("decided replacement was rejected");
+    assert_eq!(err.status, ReturnStatus::BadReplacement);
+
+    // the new key is stored for Dave, the old key is deactivated
+    let dave = c.get_by_fp(fp2.clone()).await.unwrap().unwrap();
+    assert_eq!(dave.certificate.email, vec!["dave@example.org".to_string()]);
+    assert_eq!(dave.certificate.name.as_deref(), Some("Dave"));
+
+    let old = ca.cert_get_by_fingerprint(&fp1).unwrap().unwrap();
+    assert!(old.inactive);
+
+    // 13. blocklist
+    let (erin, _) = CertBuilder::general_purpose(None, Some("Erin <erin@example.org>"))
+        .generate()
+        .unwrap();
+    let erin_fp = erin.fingerprint().to_hex();
+
+    let fp_entry = ca
+        .blocklist_add(BlocklistKind::Fingerprint, &erin_fp, Some("compromised"))
+        .unwrap();
+    let domain_entry = ca
+        .blocklist_add(BlocklistKind::Domain, "example.org", None)
+        .unwrap();
+
+    let cert = Certificate {
+        cert: pgp::cert_to_armored(&erin).unwrap(),
+        delisted: None,
+        inactive: None,
+        email: vec!["erin@example.org".to_owned()],
+        name: Some("Erin".to_owned()),
+        revocations: vec![],
+    };
+
+    let res = c.check(&cert).await.unwrap();
+    if let CertResultJson::Bad(res) = &res[0] {
+        // one error for the key, and one for the email address
+        assert_eq!(res.error.len(), 2);
+        assert!(res.error.iter().all(|e| e.status == CertStatus::Blocked));
+        assert!(res.error[0].msg.contains("compromised"));
+    } else {
+        panic!("blocked cert was accepted");
+    }
+
+    ca.blocklist_remove(fp_entry.id).unwrap();
+    ca.blocklist_remove(domain_entry.id).unwrap();
+
+    let res = c.check(&cert).await.unwrap();
+    assert!(matches!(res[0], CertResultJson::Good(_)));
+
+    // 14. write modes
+    restd::set_write_mode(restd::WriteMode::ReadOnly);
+
+    let err = c.persist(&cert).await.unwrap_err();
+    assert_eq!(err.status, ReturnStatus::WriteProtected);
+    let err = c.deactivate(alice_fp.clone()).await.unwrap_err();
+    assert_eq!(err.status, ReturnStatus::WriteProtected);
+    assert!(c.refresh_ca_certifications().await.is_err());
+    let err = c.post_revocation(CAROL_REV2).await.unwrap_err();
+    assert_eq!(err.status, ReturnStatus::WriteProtected);
+    let err = c.stage_download(&stage(None)).await.unwrap_err();
+    assert_eq!(err.status, ReturnStatus::WriteProtected);
+
+    // in approval mode, changes are queued for an admin
+    restd::set_write_mode(restd::WriteMode::Approval);
+
+    let res = c.persist(&cert).await.unwrap();
+    let queued = if let CertResultJson::Good(res) = &res[0] {
+        res.queued.expect("cert was not queued")
+    } else {
+        panic!("cert was rejected");
+    };
+    assert!(ca.cert_get_by_fingerprint(&erin_fp).unwrap().is_none());
+
+    assert!(c.delist(erin_fp.clone()).await.is_err()); // not known yet
+    let err = c.refresh_ca_certifications().await.unwrap_err();
+    assert_eq!(err.status, ReturnStatus::WriteProtected);
+
+    let pending = ca.proposals_pending().unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].id, queued);
+
+    ca.proposal_approve(queued).unwrap();
+    let erin_db = ca.cert_get_by_fingerprint(&erin_fp).unwrap().unwrap();
+    assert!(!erin_db.inactive);
+
+    assert!(matches!(c.deactivate(erin_fp.clone()).await, Ok(None)));
+    assert!(
+        !ca.cert_get_by_fingerprint(&erin_fp)
+            .unwrap()
+            .unwrap()
+            .inactive
+    );
+
+    let pending = ca.proposals_pending().unwrap();
+    assert_eq!(pending.len(), 1);
+    ca.proposal_approve(pending[0].id).unwrap();
+    assert!(
+        ca.cert_get_by_fingerprint(&erin_fp)
+            .unwrap()
+            .unwrap()
+            .inactive
+    );
+
+    // deposited revocations are queued, too
+    assert!(c.post_revocation(CAROL_REV2).await.unwrap().is_none());
+    let pending = ca.proposals_pending().unwrap();
+    assert_eq!(pending.len(), 1);
+    ca.proposal_approve(pending[0].id).unwrap();
+    assert!(ca.proposals_pending().unwrap().is_empty());
+
+    let api = c.openapi().await;
+    assert!(api["paths"]["/certs/deactivate/{fp}"]["post"]["responses"]["202"].is_object());
+    assert!(api["paths"]["/revocations"]["post"]["responses"]["202"].is_object());
+
+    restd::set_write_mode(restd::WriteMode::Open);
+
+    // 15. WKD export download
+    assert_eq!(
+        c.wkd_export("secret").await.unwrap_err(),
+        reqwest::StatusCode::FORBIDDEN
+    );
+
+    restd::set_wkd_export_token(Some("secret".to_string()));
+
+    assert_eq!(
+        c.wkd_export("wrong").await.unwrap_err(),
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+
+    let tar = c.wkd_export("secret").await.unwrap();
+    assert_eq!(tar.len() % 512, 0);
+    assert!(tar.starts_with(b".well-known/openpgpkey/example.org/"));
+
+    restd::set_wkd_export_token(None);
+
+    // 16. Split mode exchange
+    assert_eq!(
+        c.split_requests("secret").await.unwrap_err(),
+        reqwest::StatusCode::FORBIDDEN
+    );
+
+    restd::set_split_token(Some("secret".to_string()));
+
+    assert_eq!(
+        c.split_requests("wrong").await.unwrap_err(),
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+
+    // this CA is not a split mode front instance
+    assert_eq!(
+        c.split_requests("secret").await.unwrap_err(),
+        reqwest::StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        c.split_responses("secret", "{}").await.unwrap_err(),
+        reqwest::StatusCode::BAD_REQUEST
+    );
+
+    restd::set_split_token(None);
+
     // -- abort restd --
     abort_handle.abort();
 }